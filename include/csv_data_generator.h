@@ -0,0 +1,39 @@
+/* The C API of csv_data_generator, in the cdylib `cargo build --release`
+ * builds (libcsv_data_generator.so, .dylib or .dll). Functions returning
+ * int return 0 on success and -1 on failure, when they point `error`, if
+ * it isn't NULL, at a message to free with csvgen_string_free. */
+
+#ifndef CSV_DATA_GENERATOR_H
+#define CSV_DATA_GENERATOR_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct csvgen_config csvgen_config;
+
+/* Receives the next `len` bytes of the file of `table`; nonzero stops the
+ * run. Called from several threads at once with several --shards. */
+typedef int (*csvgen_callback)(void *user, const char *table, const uint8_t *data, size_t len);
+
+/* The tables of `json`, a --config document, with the settings of the
+ * `argc` command line flags in `argv`, like "--seed" "7". NULL on failure. */
+csvgen_config *csvgen_config_from_json(const char *json, const char *const *argv, size_t argc, char **error);
+
+/* Writes each table to `output` with {table} replaced by its name. */
+int csvgen_generate_to_path(const csvgen_config *config, const char *output, char **error);
+
+/* Passes each table's file, in --format, to `callback` with `user`. */
+int csvgen_generate_to_callback(const csvgen_config *config, csvgen_callback callback, void *user, char **error);
+
+void csvgen_config_free(csvgen_config *config);
+void csvgen_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
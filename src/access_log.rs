@@ -0,0 +1,207 @@
+//! The `access_log` schema of web server requests, and the Apache combined
+//! log format [`RecordFormat::Combined`](crate::record::RecordFormat::Combined) writes it in.
+
+use crate::BoxError;
+use crate::generator::Row;
+use crate::schema::{Column, ColumnKind};
+
+/// The columns a combined log line is made of, in its order.
+pub const FIELDS: [&str; 10] = ["ip", "user", "time", "method", "path", "protocol", "status", "bytes", "referrer", "user_agent"];
+
+/// Requests a second the `time` column advances by.
+pub const REQUESTS_PER_SECOND: f64 = 20.0;
+
+/// Each status code by how many of 1,000 requests get it.
+const STATUSES: &[(i64, usize)] = &[
+    (200, 830),
+    (201, 20),
+    (204, 20),
+    (301, 15),
+    (302, 15),
+    (304, 40),
+    (400, 10),
+    (401, 8),
+    (403, 7),
+    (404, 25),
+    (500, 5),
+    (502, 3),
+    (503, 2),
+];
+
+const METHODS: &[(&str, usize)] = &[("GET", 80), ("POST", 12), ("PUT", 3), ("DELETE", 2), ("HEAD", 2), ("OPTIONS", 1)];
+
+const PATHS: &[&str] = &[
+    "/",
+    "/index.html",
+    "/products/{0}",
+    "/products/{0}/reviews?page=%",
+    "/category/{1}",
+    "/search?q={2}",
+    "/cart",
+    "/checkout",
+    "/api/v1/orders/%#####",
+    "/api/v1/users/%####/profile",
+    "/static/js/app.{3}.js",
+    "/static/css/main.{3}.css",
+    "/images/{0}.jpg",
+    "/favicon.ico",
+    "/robots.txt",
+    "/login",
+    "/wp-login.php",
+];
+
+const PRODUCTS: &[&str] = &["espresso-machine", "running-shoes", "desk-lamp", "wireless-earbuds", "yoga-mat", "backpack"];
+const CATEGORIES: &[&str] = &["kitchen", "sports", "electronics", "home", "outdoor"];
+const SEARCHES: &[&str] = &["shoes", "coffee+beans", "usb-c+cable", "gift+ideas", "sale"];
+const ASSET_HASHES: &[&str] = &["3f9a1c", "b72e04", "d41d8c", "9e107d"];
+
+const REFERRERS: &[(&str, usize)] = &[
+    ("-", 40),
+    ("https://www.google.com/", 25),
+    ("https://www.bing.com/", 5),
+    ("https://duckduckgo.com/", 3),
+    ("https://t.co/", 4),
+    ("https://www.facebook.com/", 5),
+    ("https://shop.example.com/", 10),
+    ("https://shop.example.com/search?q=shoes", 5),
+    ("https://shop.example.com/cart", 3),
+];
+
+const USER_AGENTS: &[(&str, usize)] = &[
+    ("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36", 35),
+    ("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15", 15),
+    ("Mozilla/5.0 (iPhone; CPU iPhone OS 17_4 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Mobile/15E148 Safari/604.1", 18),
+    ("Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Mobile Safari/537.36", 12),
+    ("Mozilla/5.0 (X11; Ubuntu; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0", 6),
+    ("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)", 6),
+    ("Mozilla/5.0 (compatible; bingbot/2.0; +http://www.bing.com/bingbot.htm)", 3),
+    ("curl/8.5.0", 3),
+    ("python-requests/2.31.0", 2),
+];
+
+fn weighted(items: &[(&str, usize)]) -> Vec<String> {
+    items.iter().flat_map(|&(item, weight)| std::iter::repeat_n(item.to_string(), weight)).collect()
+}
+
+fn list(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+/// Requests to a web shop, timed from `start` in seconds since 1970-01-01 UTC.
+pub fn columns(start: i64) -> Vec<Column> {
+    let octets: Vec<String> = (1..=254).map(|n| n.to_string()).collect();
+    let hex: Vec<String> = (1..=0xffff).step_by(0x1111).map(|n| format!("{n:x}")).collect();
+    // One client in ten on IPv6, in the documentation prefix.
+    let mut ips = vec!["{0}.{0}.{0}.{0}".to_string(); 9];
+    ips.push("2001:db8:{1}::{1}".to_string());
+    let statuses = STATUSES.iter().flat_map(|&(status, weight)| std::iter::repeat_n(status, weight)).collect();
+    vec![
+        Column::new("ip", ColumnKind::Format { formats: ips, lists: vec![octets, hex] }),
+        Column::new("user", ColumnKind::Choice(weighted(&[("-", 92), ("alice", 3), ("bob", 3), ("carol", 2)]))),
+        // Lines are written as requests finish, so times lag a little out of order.
+        Column::new("time", ColumnKind::Clock { start, per_second: REQUESTS_PER_SECOND, skew: 2.0 }),
+        Column::new("method", ColumnKind::Choice(weighted(METHODS))),
+        Column::new(
+            "path",
+            ColumnKind::Format {
+                formats: list(PATHS),
+                lists: vec![list(PRODUCTS), list(CATEGORIES), list(SEARCHES), list(ASSET_HASHES)],
+            },
+        ),
+        Column::new("protocol", ColumnKind::Choice(weighted(&[("HTTP/1.1", 7), ("HTTP/2.0", 3)]))),
+        Column::new("status", ColumnKind::IntChoice(statuses)),
+        Column::new("bytes", ColumnKind::IntRange { min: 0, max: 50_000 }),
+        Column::new("referrer", ColumnKind::Choice(weighted(REFERRERS))),
+        Column::new("user_agent", ColumnKind::Choice(weighted(USER_AGENTS))),
+    ]
+}
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Where each of [`FIELDS`] is among `names`, which must have them all.
+pub fn field_indices(names: &[String]) -> Result<Vec<usize>, String> {
+    FIELDS
+        .iter()
+        .map(|&field| {
+            names.iter().position(|n| n == field).ok_or_else(|| {
+                format!("combined log lines need the columns of --schema access_log, and there is no {field}")
+            })
+        })
+        .collect()
+}
+
+/// Encodes `row` as a combined log line without a terminator, taking each
+/// of [`FIELDS`] from the column at the same place in `indices`.
+pub fn combined_line(row: &Row, indices: &[usize]) -> Result<Vec<u8>, BoxError> {
+    let field = |i: usize| std::str::from_utf8(row.field(indices[i]));
+    let time = field(2)?;
+    if time.len() != 19 {
+        return Err(format!("{time} isn't a timestamp").into());
+    }
+    let month = MONTHS.get(time[5..7].parse::<usize>()?.wrapping_sub(1)).ok_or_else(|| format!("{time} isn't a timestamp"))?;
+    let bytes = match field(7)? {
+        "0" => "-",
+        bytes => bytes,
+    };
+    let line = format!(
+        "{} - {} [{}/{month}/{}:{} +0000] \"{} {} {}\" {} {bytes} \"{}\" \"{}\"",
+        field(0)?,
+        field(1)?,
+        &time[8..10],
+        &time[0..4],
+        &time[11..],
+        field(3)?,
+        field(4)?,
+        field(5)?,
+        field(6)?,
+        field(8)?,
+        field(9)?,
+    );
+    Ok(line.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{Generator, RowKeys};
+
+    #[test]
+    fn lines_follow_the_combined_format() {
+        let columns = columns(1_700_000_000);
+        let names: Vec<_> = columns.iter().map(|c| c.name.clone()).collect();
+        let indices = field_indices(&names).unwrap();
+        let generator = Generator::compile(&columns).unwrap();
+        let mut row = Row::new();
+        let mut statuses = std::collections::HashMap::new();
+        for index in 0..2000 {
+            generator.fill_keyed_row(&mut rand::thread_rng(), &mut row, RowKeys { index, ..RowKeys::default() });
+            let line = String::from_utf8(combined_line(&row, &indices).unwrap()).unwrap();
+            let (client, rest) = line.split_once(" - ").unwrap();
+            assert!(client.parse::<std::net::IpAddr>().is_ok(), "{line}");
+            let time = rest.split_once('[').unwrap().1.split_once(']').unwrap().0;
+            assert!(chrono::DateTime::parse_from_str(time, "%d/%b/%Y:%H:%M:%S %z").is_ok(), "{line}");
+            let request = line.split('"').nth(1).unwrap();
+            assert_eq!(request.split(' ').count(), 3, "{line}");
+            let status = line.split('"').nth(2).unwrap().split_whitespace().next().unwrap().to_string();
+            *statuses.entry(status).or_insert(0) += 1;
+        }
+        assert!(statuses["200"] > 1500, "{statuses:?}");
+        assert!(field_indices(&names[1..]).unwrap_err().contains("no ip"));
+    }
+
+    #[test]
+    fn times_advance_with_a_little_skew() {
+        let generator = Generator::compile(&columns(0)).unwrap();
+        let mut row = Row::new();
+        let times: Vec<_> = (0..2000)
+            .map(|index| {
+                generator.fill_keyed_row(&mut rand::thread_rng(), &mut row, RowKeys { index, ..RowKeys::default() });
+                crate::schema::parse_timestamp(std::str::from_utf8(row.field(2)).unwrap()).unwrap()
+            })
+            .collect();
+        // 2,000 requests at 20 a second take 100 seconds.
+        assert!(times[1999] > 80 && times[1999] <= 100, "{}", times[1999]);
+        assert!(times.windows(2).any(|w| w[1] < w[0]));
+        assert!(times.iter().enumerate().all(|(i, &t)| t <= i as i64 / 20));
+    }
+}
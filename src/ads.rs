@@ -0,0 +1,234 @@
+//! The ads preset: `campaigns`, the `impressions` of their ads, the
+//! `clicks` on those and the `conversions` after them, sharing ids so that
+//! ad analytics joins have known answers.
+//!
+//! Each campaign has a click-through and a conversion rate spread around
+//! `--ad-ctr` and `--ad-cvr`, written in `campaigns`. Every impression is
+//! clicked at its campaign's rate, a moment after it's shown, and every click
+//! converts within `--ad-attribution-days` at the campaign's conversion rate,
+//! so clicks over impressions and attributed conversions over clicks come
+//! out at the rates asked for. A quarter as many clicks again convert after the window
+//! and have `attributed` 0, as conversions an attribution model should leave
+//! out.
+
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::clickstream::exponential;
+use crate::generator::{Row, push_decimal, push_timestamp};
+use crate::presets::{self, push_int};
+use crate::schema::{Column, ColumnKind};
+use crate::signal;
+use crate::transactions::normal;
+
+/// Ads are shown from 2024-01-01 UTC for 30 days.
+const WINDOW_START: i64 = 1_704_067_200;
+const WINDOW: i64 = 30 * 86_400;
+
+/// Impressions and users at scale factor 1.
+const IMPRESSIONS: f64 = 1_000_000.0;
+const USERS: f64 = 100_000.0;
+const CAMPAIGNS: u64 = 50;
+/// How far a campaign's rates stray from the ones asked for, as a factor.
+const RATE_SPREAD: (f64, f64) = (0.5, 1.5);
+/// The mean seconds from an impression to its click.
+const MEAN_CLICK_DELAY: f64 = 20.0;
+/// Conversions after the window, per one inside it.
+const LATE_SHARE: f64 = 0.25;
+/// The median and log-scale spread of a conversion's revenue.
+const MEDIAN_REVENUE: f64 = 60.0;
+const REVENUE_SIGMA: f64 = 0.8;
+
+const TABLES: [&str; 4] = ["campaigns", "impressions", "clicks", "conversions"];
+
+const CHANNELS: &[&str] = &["search", "social", "display", "video"];
+const PLACEMENTS: &[&str] = &["feed", "sidebar", "search_results", "pre_roll", "banner"];
+const DEVICES: &[(&str, u32)] = &[("mobile", 60), ("desktop", 32), ("tablet", 8)];
+const OBJECTIVES: &[&str] = &["Spring Sale", "Brand Awareness", "Retargeting", "New Arrivals", "Loyalty", "App Installs"];
+
+/// The columns of each table, in [`TABLES`] order. Only the names and types
+/// of these matter; the values are written below.
+fn columns() -> [Vec<Column>; 4] {
+    let integer = |name| Column::new(name, ColumnKind::Sequence { start: 1 });
+    let string = |name| Column::new(name, ColumnKind::Choice(Vec::new()));
+    let timestamp = |name| Column::new(name, ColumnKind::TimestampRange { min: 0, max: 0 });
+    let decimal = |name, scale| Column::new(name, ColumnKind::Decimal { min: 0, max: 0, scale });
+    [
+        vec![integer("campaign_id"), string("name"), string("channel"), decimal("ctr", 4), decimal("cvr", 4)],
+        vec![
+            integer("impression_id"),
+            integer("campaign_id"),
+            integer("user_id"),
+            timestamp("shown_at"),
+            string("device"),
+            string("placement"),
+        ],
+        vec![integer("click_id"), integer("impression_id"), integer("campaign_id"), integer("user_id"), timestamp("clicked_at")],
+        vec![
+            integer("conversion_id"),
+            integer("click_id"),
+            integer("impression_id"),
+            integer("campaign_id"),
+            integer("user_id"),
+            timestamp("converted_at"),
+            decimal("revenue", 2),
+            integer("attributed"),
+        ],
+    ]
+}
+
+/// A campaign's rates, rounded to the four places `campaigns` has so that
+/// they are the rates used.
+fn spread<R: Rng + ?Sized>(rng: &mut R, rate: f64) -> f64 {
+    ((rate * rng.gen_range(RATE_SPREAD.0..=RATE_SPREAD.1)).min(1.0) * 10_000.0).round() / 10_000.0
+}
+
+/// Writes the campaigns, then each impression along with any click and
+/// conversion it led to.
+pub fn generate(args: &Args) -> Result<(), BoxError> {
+    let [mut campaigns_sink, mut impressions_sink, mut clicks_sink, mut conversions_sink] = presets::open_tables(args, "ads", TABLES, columns())?;
+
+    let mut rng = rand::thread_rng();
+    let preset = &args.preset;
+    let window = preset.ad_attribution_days as i64 * 86_400;
+    let mut row = Row::new();
+    let mut campaigns = Vec::new();
+    for id in 1..=CAMPAIGNS {
+        let (ctr, cvr) = (spread(&mut rng, preset.ad_ctr), spread(&mut rng, preset.ad_cvr));
+        row.clear();
+        push_int(&mut row, id);
+        row.push(format!("{} {id}", OBJECTIVES[rng.gen_range(0..OBJECTIVES.len())]).as_bytes());
+        row.push(CHANNELS[rng.gen_range(0..CHANNELS.len())].as_bytes());
+        push_decimal(&mut row, (ctr * 10_000.0).round() as i64, 4);
+        push_decimal(&mut row, (cvr * 10_000.0).round() as i64, 4);
+        campaigns_sink.write_row(&row)?;
+        campaigns.push((ctr, cvr));
+    }
+
+    let devices = WeightedIndex::new(DEVICES.iter().map(|d| d.1))?;
+    let mut impressions = ((IMPRESSIONS * preset.scale_factor).round() as u64).max(1);
+    let users = ((USERS * preset.scale_factor).round() as u64).max(1);
+    let (mut clicks, mut conversions, mut attributed) = (0u64, 0u64, 0u64);
+    for impression in 1..=impressions {
+        if signal::interrupted() {
+            impressions = impression - 1;
+            break;
+        }
+        let campaign = rng.gen_range(1..=CAMPAIGNS);
+        let (ctr, cvr) = campaigns[campaign as usize - 1];
+        let user = rng.gen_range(1..=users);
+        // Impressions are spread evenly over the window, so they come in time order.
+        let shown = WINDOW_START + ((impression - 1) as f64 / impressions as f64 * WINDOW as f64) as i64;
+        row.clear();
+        push_int(&mut row, impression);
+        push_int(&mut row, campaign);
+        push_int(&mut row, user);
+        push_timestamp(&mut row, shown);
+        row.push(DEVICES[devices.sample(&mut rng)].0.as_bytes());
+        row.push(PLACEMENTS[rng.gen_range(0..PLACEMENTS.len())].as_bytes());
+        impressions_sink.write_row(&row)?;
+        if !rng.gen_bool(ctr) {
+            continue;
+        }
+
+        clicks += 1;
+        let clicked = shown + 1 + exponential(&mut rng, MEAN_CLICK_DELAY) as i64;
+        row.clear();
+        for id in [clicks, impression, campaign, user] {
+            push_int(&mut row, id);
+        }
+        push_timestamp(&mut row, clicked);
+        clicks_sink.write_row(&row)?;
+
+        let converted = if rng.gen_bool(cvr) {
+            attributed += 1;
+            Some(clicked + rng.gen_range(0..window))
+        } else if rng.gen_bool((cvr * LATE_SHARE / (1.0 - cvr)).min(1.0)) {
+            Some(clicked + window + rng.gen_range(0..window))
+        } else {
+            None
+        };
+        let Some(converted) = converted else { continue };
+        conversions += 1;
+        row.clear();
+        for id in [conversions, clicks, impression, campaign, user] {
+            push_int(&mut row, id);
+        }
+        push_timestamp(&mut row, converted);
+        let revenue = MEDIAN_REVENUE * (REVENUE_SIGMA * normal(&mut rng)).exp();
+        push_decimal(&mut row, (revenue * 100.0).round() as i64, 2);
+        push_int(&mut row, (converted < clicked + window).into());
+        conversions_sink.write_row(&row)?;
+    }
+
+    presets::finish_tables(args, TABLES, [campaigns_sink, impressions_sink, clicks_sink, conversions_sink], [CAMPAIGNS, impressions, clicks, conversions])?;
+    println!(
+        "  {:.2}% of impressions clicked and {:.2}% of clicks converted within {} days",
+        100.0 * clicks as f64 / impressions as f64,
+        100.0 * attributed as f64 / clicks.max(1) as f64,
+        preset.ad_attribution_days,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::testing::{read, temp_dir};
+    use crate::schema::parse_timestamp;
+    use clap::Parser;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn joins_give_the_rates_asked_for() {
+        let dir = temp_dir("ads");
+        let template = dir.join("{table}.csv");
+        let args = Args::parse_from([
+            "gen",
+            "--preset",
+            "ads",
+            "--scale-factor",
+            "0.02",
+            "--ad-ctr",
+            "0.1",
+            "--ad-cvr",
+            "0.2",
+            "--ad-attribution-days",
+            "3",
+            "--output",
+            template.to_str().unwrap(),
+        ]);
+        generate(&args).unwrap();
+
+        let time = |s: &str| parse_timestamp(s).unwrap();
+        let impressions: HashMap<String, Vec<String>> =
+            read(dir.join("impressions.csv")).into_iter().map(|i| (i[0].clone(), i)).collect();
+        assert_eq!(impressions.len(), 20_000);
+        let clicks: HashMap<String, Vec<String>> = read(dir.join("clicks.csv")).into_iter().map(|c| (c[0].clone(), c)).collect();
+        for c in clicks.values() {
+            let i = &impressions[&c[1]];
+            assert_eq!((&c[2], &c[3]), (&i[1], &i[2]));
+            assert!(time(&c[4]) > time(&i[3]));
+        }
+        let conversions = read(dir.join("conversions.csv"));
+        let mut attributed = 0;
+        for v in &conversions {
+            let c = &clicks[&v[1]];
+            assert_eq!((&v[2], &v[3], &v[4]), (&c[1], &c[2], &c[3]));
+            let after = time(&v[5]) - time(&c[4]);
+            assert!(after >= 0);
+            assert_eq!(v[7] == "1", after < 3 * 86_400);
+            attributed += usize::from(v[7] == "1");
+        }
+        let ctr = clicks.len() as f64 / impressions.len() as f64;
+        let cvr = attributed as f64 / clicks.len() as f64;
+        assert!((0.085..0.115).contains(&ctr), "{ctr}");
+        assert!((0.16..0.24).contains(&cvr), "{cvr}");
+        let late = (conversions.len() - attributed) as f64 / attributed as f64;
+        assert!((0.1..0.4).contains(&late), "{late}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
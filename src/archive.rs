@@ -0,0 +1,124 @@
+//! `--archive`: the local files a run wrote, bundled after it into one
+//! `.tar.gz` or `.zip` with a `manifest.json` listing them and the
+//! `config.json` of the settings that generated them, so that a test
+//! dataset can be shared and regenerated as one file. Bundling needs the
+//! `archive` feature.
+
+#[cfg(feature = "archive")]
+use std::fs::{self, File};
+#[cfg(feature = "archive")]
+use std::io::{self, Write};
+#[cfg(feature = "archive")]
+use std::path::Component;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[cfg(feature = "archive")]
+use flate2::Compression;
+#[cfg(feature = "archive")]
+use flate2::write::GzEncoder;
+#[cfg(feature = "archive")]
+use zip::CompressionMethod;
+#[cfg(feature = "archive")]
+use zip::write::SimpleFileOptions;
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::output::{self, Destination};
+#[cfg(feature = "archive")]
+use crate::{effective_config, run_manifest};
+
+/// The local files opened so far in the run, in the order they were.
+static FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Adds `path` to what `--archive` bundles.
+pub(crate) fn track(path: &Path) {
+    let mut files = FILES.lock().unwrap();
+    if !files.iter().any(|file| file == path) {
+        files.push(path.to_path_buf());
+    }
+}
+
+/// Checks before generating that `--archive` can bundle what the run writes.
+pub(crate) fn check(args: &Args) -> Result<(), BoxError> {
+    if cfg!(not(feature = "archive")) && args.archive.is_some() {
+        return Err(crate::without("archive", "--archive"));
+    }
+    if args.archive.is_some() && !matches!(Destination::parse(&output::shard_location(&args.output, 0))?, Destination::File(_)) {
+        return Err("--archive bundles local files, so --output must be one".into());
+    }
+    Ok(())
+}
+
+/// `path` as a name in the archive: relative, without `..`.
+#[cfg(feature = "archive")]
+fn entry_name(path: &Path) -> String {
+    let parts: Vec<_> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect();
+    parts.join("/")
+}
+
+/// Writes `--archive`, if it's given, of the files the run wrote.
+#[cfg(feature = "archive")]
+pub(crate) fn write(args: &Args) -> Result<(), BoxError> {
+    let Some(archive) = &args.archive else { return Ok(()) };
+    // Files `--retain` deleted after rotating them are gone.
+    let files: Vec<_> = FILES.lock().unwrap().iter().filter(|path| path.exists()).cloned().collect();
+    let extras = [("manifest.json", run_manifest::json(args)?), ("config.json", effective_config::json(args)?)];
+    let created = File::create(archive).map_err(|e| format!("--archive {}: {e}", archive.display()))?;
+    if archive.extension().is_some_and(|e| e == "zip") {
+        let mut zip = zip::ZipWriter::new(created);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated).large_file(true);
+        for path in &files {
+            // Compressed files don't get smaller for deflating them again.
+            let compressed = path.extension().is_some_and(|e| e == "gz" || e == "parquet");
+            let method = if compressed { CompressionMethod::Stored } else { CompressionMethod::Deflated };
+            zip.start_file(entry_name(path), options.compression_method(method))?;
+            io::copy(&mut File::open(path)?, &mut zip)?;
+        }
+        for (name, contents) in &extras {
+            zip.start_file(*name, options)?;
+            zip.write_all(contents)?;
+        }
+        zip.finish()?;
+    } else {
+        let mut tar = tar::Builder::new(GzEncoder::new(created, Compression::default()));
+        for path in &files {
+            tar.append_path_with_name(path, entry_name(path))?;
+        }
+        let modified = fs::metadata(archive)?.modified()?.duration_since(std::time::UNIX_EPOCH)?.as_secs();
+        for (name, contents) in &extras {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(modified);
+            header.set_cksum();
+            tar.append_data(&mut header, name, &contents[..])?;
+        }
+        tar.into_inner()?.finish()?;
+    }
+    println!("Archived {} files to {}", files.len() + extras.len(), archive.display());
+    Ok(())
+}
+
+/// [`check`] refuses `--archive` in this build.
+#[cfg(not(feature = "archive"))]
+pub(crate) fn write(_: &Args) -> Result<(), BoxError> {
+    Ok(())
+}
+
+#[cfg(all(test, feature = "archive"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_relative() {
+        assert_eq!(entry_name(Path::new("/tmp/out/part-0.csv")), "tmp/out/part-0.csv");
+        assert_eq!(entry_name(Path::new("./out/../part-0.csv")), "out/part-0.csv");
+    }
+}
@@ -0,0 +1,262 @@
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand::Rng;
+use serde_json::{Value, json};
+use ureq::Agent;
+
+use crate::BoxError;
+use crate::aws::sigv4::{self, Credentials};
+use crate::cli::KinesisArgs;
+use crate::generator::Row;
+use crate::http;
+use crate::record::RecordEncoder;
+use crate::retry::{Failure, dead_letter, retry};
+use crate::schema::Column;
+use crate::sink::{Sink, column_indices};
+use crate::throttle::RateLimiter;
+
+/// PutRecords accepts at most this many records...
+const MAX_BATCH_RECORDS: usize = 500;
+/// ...and this many bytes of data and partition keys per request.
+const MAX_BATCH_BYTES: usize = 5 << 20;
+/// Partition keys are limited to 256 characters.
+const MAX_KEY_LEN: usize = 256;
+
+/// What one Kinesis shard accepts per second.
+const SHARD_RECORDS_PER_SEC: f64 = 1000.0;
+const SHARD_BYTES_PER_SEC: f64 = (1 << 20) as f64;
+
+/// Signs and sends Kinesis API requests, and paces them to the stream's
+/// shard limits when `--kinesis-shard-count` is given.
+pub struct KinesisClient {
+    agent: Agent,
+    credentials: Credentials,
+    region: String,
+    /// `AWS_ENDPOINT_URL_KINESIS`/`AWS_ENDPOINT_URL`, e.g. for LocalStack.
+    endpoint: Option<String>,
+    /// Record and byte limiters shared by every sink on this stream.
+    limits: Option<(RateLimiter, RateLimiter)>,
+}
+
+impl KinesisClient {
+    pub fn from_env(args: &KinesisArgs) -> Result<Self, BoxError> {
+        let endpoint = std::env::var("AWS_ENDPOINT_URL_KINESIS")
+            .or_else(|_| std::env::var("AWS_ENDPOINT_URL"))
+            .ok()
+            .map(|e| e.trim_end_matches('/').to_string());
+        let limits = args.kinesis_shard_count.map(|shards| {
+            let shards = f64::from(shards);
+            (RateLimiter::new(shards * SHARD_RECORDS_PER_SEC), RateLimiter::new(shards * SHARD_BYTES_PER_SEC))
+        });
+        Ok(KinesisClient {
+            agent: http::agent(),
+            credentials: Credentials::from_env()?,
+            region: sigv4::region_from_env(),
+            endpoint,
+            limits,
+        })
+    }
+
+    /// Sends one PutRecords request and returns the records it did not take.
+    fn put_records(&self, stream: &str, records: &[(String, Vec<u8>)]) -> Result<Vec<usize>, Failure> {
+        let body = json!({
+            "StreamName": stream,
+            "Records": records
+                .iter()
+                .map(|(key, data)| json!({ "Data": STANDARD.encode(data), "PartitionKey": key }))
+                .collect::<Vec<_>>(),
+        })
+        .to_string();
+
+        let (scheme, host) = match &self.endpoint {
+            Some(endpoint) => endpoint.split_once("://").unwrap_or(("https", endpoint)),
+            None => ("https", ""),
+        };
+        let host = if host.is_empty() { format!("kinesis.{}.amazonaws.com", self.region) } else { host.to_string() };
+        let headers = [
+            ("content-type".to_string(), "application/x-amz-json-1.1".to_string()),
+            ("x-amz-target".to_string(), "Kinesis_20131202.PutRecords".to_string()),
+        ];
+        let signed =
+            sigv4::Request { method: "POST", host: &host, path: "/", query: "", headers: &headers, payload: body.as_bytes() };
+        let auth_headers = sigv4::sign(&self.credentials, &self.region, "kinesis", &signed, chrono::Utc::now());
+
+        let mut request = ureq::http::Request::builder().method("POST").uri(format!("{scheme}://{host}/"));
+        for (name, value) in &auth_headers {
+            request = request.header(name, value);
+        }
+        let request = request.body(body.as_bytes()).map_err(|e| Failure::Fatal(e.into()))?;
+        let mut response = http::check(self.agent.run(request), &[400])?;
+        let status = response.status().as_u16();
+        let response: Value = response.body_mut().read_json().map_err(|e| Failure::Transient(e.into()))?;
+        if status == 400 {
+            return Err(classify_error(&response));
+        }
+        Ok(failed_records(&response))
+    }
+}
+
+/// Throttling errors are worth retrying; anything else in a 400 is not.
+fn classify_error(response: &Value) -> Failure {
+    let kind = response["__type"].as_str().unwrap_or_default();
+    let error = format!("{kind}: {}", response["message"].as_str().unwrap_or_default()).into();
+    if kind.contains("ProvisionedThroughputExceeded") || kind.contains("LimitExceeded") {
+        Failure::Transient(error)
+    } else {
+        Failure::Fatal(error)
+    }
+}
+
+/// The positions of the records a PutRecords response reports as failed.
+fn failed_records(response: &Value) -> Vec<usize> {
+    if response["FailedRecordCount"].as_u64().unwrap_or(0) == 0 {
+        return Vec::new();
+    }
+    response["Records"]
+        .as_array()
+        .map(|records| {
+            records.iter().enumerate().filter(|(_, r)| r.get("ErrorCode").is_some()).map(|(i, _)| i).collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Puts one record per row into a Kinesis data stream.
+///
+/// Records are sent with PutRecords in batches of up to 500 records or 5MB.
+/// Records a response reports as throttled are resent on their own after a
+/// backoff, so a batch is never duplicated as a whole.
+pub struct KinesisSink {
+    client: Arc<KinesisClient>,
+    stream: String,
+    key: Option<usize>,
+    encoder: RecordEncoder,
+    batch: Vec<(String, Vec<u8>)>,
+    batch_bytes: usize,
+    bytes: u64,
+}
+
+impl KinesisSink {
+    pub fn open(client: Arc<KinesisClient>, args: &KinesisArgs, stream: &str, columns: &[Column]) -> Result<Self, BoxError> {
+        let key = match &args.kinesis_partition_key {
+            Some(name) => Some(column_indices(columns, std::slice::from_ref(name), "--kinesis-partition-key")?[0]),
+            None => None,
+        };
+        Ok(KinesisSink {
+            client,
+            stream: stream.to_string(),
+            key,
+            encoder: RecordEncoder::new(args.kinesis_format, columns, None),
+            batch: Vec::new(),
+            batch_bytes: 0,
+            bytes: 0,
+        })
+    }
+
+    fn send_batch(&mut self) -> Result<(), BoxError> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let mut pending = std::mem::take(&mut self.batch);
+        if let Some((records, bytes)) = &self.client.limits {
+            records.acquire(pending.len() as u64);
+            bytes.acquire(self.batch_bytes as u64);
+        }
+        self.batch_bytes = 0;
+        let what = format!("putting records into Kinesis stream {}", self.stream);
+        let put = retry(&what, || {
+            let failed = self.client.put_records(&self.stream, &pending)?;
+            if failed.is_empty() {
+                return Ok(());
+            }
+            let count = failed.len();
+            pending = failed.into_iter().map(|i| std::mem::take(&mut pending[i])).collect();
+            Err(Failure::Transient(format!("{count} records were throttled").into()))
+        });
+        // Only the records still pending were never taken.
+        let records = || pending.iter().map(|(key, data)| json!({ "Data": STANDARD.encode(data), "PartitionKey": key }));
+        put.or_else(|e| dead_letter(&format!("kinesis://{}", self.stream), e, records()))
+    }
+}
+
+/// The partition key for a row: the key column's value, or a random key to
+/// spread rows evenly when there is no key column or the value is empty.
+fn partition_key(row: &Row, key: Option<usize>) -> String {
+    match key.and_then(|i| row.fields().nth(i)).filter(|field| !field.is_empty()) {
+        Some(field) => {
+            let key = String::from_utf8_lossy(field);
+            key.chars().take(MAX_KEY_LEN).collect()
+        }
+        None => format!("{:016x}", rand::thread_rng().r#gen::<u64>()),
+    }
+}
+
+impl Sink for KinesisSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        let data = self.encoder.encode(row)?;
+        let key = partition_key(row, self.key);
+        let len = data.len() + key.len();
+        if !self.batch.is_empty() && self.batch_bytes + len > MAX_BATCH_BYTES {
+            self.send_batch()?;
+        }
+        self.bytes += data.len() as u64;
+        self.batch_bytes += len;
+        self.batch.push((key, data));
+        if self.batch.len() >= MAX_BATCH_RECORDS {
+            self.send_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.send_batch()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.send_batch()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_failed_records() {
+        let response = json!({
+            "FailedRecordCount": 2,
+            "Records": [
+                { "SequenceNumber": "1", "ShardId": "shardId-0" },
+                { "ErrorCode": "ProvisionedThroughputExceededException", "ErrorMessage": "slow down" },
+                { "SequenceNumber": "2", "ShardId": "shardId-0" },
+                { "ErrorCode": "InternalFailure", "ErrorMessage": "oops" },
+            ],
+        });
+        assert_eq!(failed_records(&response), [1, 3]);
+        assert!(failed_records(&json!({ "FailedRecordCount": 0, "Records": [] })).is_empty());
+    }
+
+    #[test]
+    fn only_throttling_is_retried() {
+        let throttled = json!({ "__type": "ProvisionedThroughputExceededException", "message": "Rate exceeded" });
+        assert!(matches!(classify_error(&throttled), Failure::Transient(_)));
+        let missing = json!({ "__type": "ResourceNotFoundException", "message": "Stream x not found" });
+        assert!(matches!(classify_error(&missing), Failure::Fatal(_)));
+    }
+
+    #[test]
+    fn partition_keys_come_from_the_key_column() {
+        let mut row = Row::new();
+        row.push(b"user-7");
+        row.push(b"");
+        assert_eq!(partition_key(&row, Some(0)), "user-7");
+        // Empty values and missing key columns fall back to random keys.
+        assert_eq!(partition_key(&row, Some(1)).len(), 16);
+        assert_ne!(partition_key(&row, None), partition_key(&row, None));
+    }
+}
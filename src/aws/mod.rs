@@ -0,0 +1,5 @@
+//! AWS clients, talking to the public REST APIs directly with SigV4 signing.
+
+pub mod kinesis;
+pub mod s3;
+pub mod sigv4;
@@ -0,0 +1,233 @@
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use ureq::Agent;
+use ureq::http::Response;
+
+use crate::BoxError;
+use crate::aws::sigv4::{self, Credentials};
+use crate::cli::S3Args;
+use crate::http;
+use crate::output::Output;
+use crate::parts::{self, PartUploads, UploadPart};
+use crate::retry::{Failure, retry};
+
+/// S3 refuses more parts than this in one multipart upload.
+const MAX_PARTS: u32 = 10_000;
+
+/// Signs and sends requests to S3 or an S3-compatible endpoint.
+pub struct S3Client {
+    agent: Agent,
+    credentials: Credentials,
+    region: String,
+    /// `AWS_ENDPOINT_URL_S3`/`AWS_ENDPOINT_URL`, for MinIO and friends. Uses
+    /// path-style addressing; AWS itself is addressed virtual-hosted style.
+    endpoint: Option<String>,
+}
+
+impl S3Client {
+    pub fn from_env() -> Result<Self, BoxError> {
+        let endpoint = std::env::var("AWS_ENDPOINT_URL_S3")
+            .or_else(|_| std::env::var("AWS_ENDPOINT_URL"))
+            .ok()
+            .map(|e| e.trim_end_matches('/').to_string());
+        Ok(S3Client {
+            agent: http::agent(),
+            credentials: Credentials::from_env()?,
+            region: sigv4::region_from_env(),
+            endpoint,
+        })
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        bucket: &str,
+        key: &str,
+        query: &[(&str, &str)],
+        headers: &[(String, String)],
+        body: &[u8],
+    ) -> Result<Response<ureq::Body>, Failure> {
+        let key = http::percent_encode(key, false);
+        let (scheme, host, path) = match &self.endpoint {
+            Some(endpoint) => {
+                let (scheme, host) = endpoint.split_once("://").unwrap_or(("https", endpoint));
+                (scheme.to_string(), host.to_string(), format!("/{bucket}/{key}"))
+            }
+            None => (
+                "https".to_string(),
+                format!("{bucket}.s3.{}.amazonaws.com", self.region),
+                format!("/{key}"),
+            ),
+        };
+        let query = sigv4::canonical_query(query);
+        let signed = sigv4::Request { method, host: &host, path: &path, query: &query, headers, payload: body };
+        let auth_headers = sigv4::sign(&self.credentials, &self.region, "s3", &signed, chrono::Utc::now());
+
+        let mut request = ureq::http::Request::builder()
+            .method(method)
+            .uri(format!("{scheme}://{host}{path}?{query}"));
+        for (name, value) in &auth_headers {
+            request = request.header(name, value);
+        }
+        let request = request.body(body).map_err(|e| Failure::Fatal(e.into()))?;
+        http::check(self.agent.run(request), &[])
+    }
+}
+
+/// Checks that shards of `shard_size` bytes fit in S3's part limit.
+pub fn check_shard_size(args: &S3Args, shard_size: u64) -> Result<(), BoxError> {
+    parts::check_part_count(shard_size, args.s3_part_size, MAX_PARTS, "parts", "--s3-part-size")
+}
+
+/// Pulls the text of the first `<tag>` element out of an S3 XML response.
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{tag}>"))?;
+    Some(&xml[start..end])
+}
+
+/// An S3 object written through a multipart upload.
+///
+/// Parts are uploaded by `--s3-concurrency` threads as soon as they fill, so
+/// the object never has to exist on local disk.
+pub struct S3Upload {
+    client: Arc<S3Client>,
+    bucket: String,
+    key: String,
+    upload_id: String,
+    parts: PartUploads,
+    completed: bool,
+}
+
+impl S3Upload {
+    pub fn create(client: Arc<S3Client>, args: &S3Args, bucket: &str, key: &str) -> Result<Self, BoxError> {
+        let mut headers = Vec::new();
+        if let Some(class) = &args.s3_storage_class {
+            headers.push(("x-amz-storage-class".to_string(), class.clone()));
+        }
+        if let Some(sse) = &args.s3_sse {
+            headers.push(("x-amz-server-side-encryption".to_string(), sse.clone()));
+        }
+        if let Some(key_id) = &args.s3_sse_kms_key_id {
+            headers.push(("x-amz-server-side-encryption-aws-kms-key-id".to_string(), key_id.clone()));
+        }
+        let what = format!("starting upload of s3://{bucket}/{key}");
+        let upload_id = retry(&what, || {
+            let body = client
+                .send("POST", bucket, key, &[("uploads", "")], &headers, &[])?
+                .body_mut()
+                .read_to_string()
+                .map_err(|e| Failure::Transient(e.into()))?;
+            xml_text(&body, "UploadId")
+                .map(str::to_owned)
+                .ok_or_else(|| Failure::Fatal(format!("no UploadId in response: {body}").into()))
+        })?;
+
+        let upload: Arc<UploadPart> = {
+            let (client, bucket, key, upload_id) =
+                (client.clone(), bucket.to_string(), key.to_string(), upload_id.clone());
+            Arc::new(move |number, data| {
+                let part_number = number.to_string();
+                let what = format!("uploading part {number} of s3://{bucket}/{key}");
+                retry(&what, || {
+                    let query = [("partNumber", part_number.as_str()), ("uploadId", upload_id.as_str())];
+                    let response = client.send("PUT", &bucket, &key, &query, &[], data)?;
+                    response
+                        .headers()
+                        .get("etag")
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_owned)
+                        .ok_or_else(|| Failure::Fatal("UploadPart response had no ETag".into()))
+                })
+            })
+        };
+        let parts = PartUploads::new(args.s3_part_size as usize, MAX_PARTS, args.s3_concurrency as usize, upload);
+
+        Ok(S3Upload {
+            client,
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            upload_id,
+            parts,
+            completed: false,
+        })
+    }
+
+    fn complete(&mut self) -> Result<(), BoxError> {
+        let etags = self.parts.finish()?;
+        let mut body = String::from("<CompleteMultipartUpload>");
+        for (number, etag) in &etags {
+            body.push_str(&format!("<Part><PartNumber>{number}</PartNumber><ETag>{etag}</ETag></Part>"));
+        }
+        body.push_str("</CompleteMultipartUpload>");
+
+        let what = format!("completing upload of s3://{}/{}", self.bucket, self.key);
+        retry(&what, || {
+            let response = self
+                .client
+                .send("POST", &self.bucket, &self.key, &[("uploadId", &self.upload_id)], &[], body.as_bytes())?
+                .body_mut()
+                .read_to_string()
+                .map_err(|e| Failure::Transient(e.into()))?;
+            // CompleteMultipartUpload can report errors inside a 200 response.
+            if response.contains("<Error>") {
+                return Err(Failure::Transient(response.into()));
+            }
+            Ok(())
+        })?;
+        self.completed = true;
+        Ok(())
+    }
+}
+
+impl Write for S3Upload {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let what = |e| io::Error::other(format!("s3://{}/{}: {e}", self.bucket, self.key));
+        self.parts.write(data).map_err(what)?;
+        Ok(data.len())
+    }
+
+    /// Parts other than the last must be at least 5MiB, so partial parts are
+    /// only sent by [`Output::finish`].
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output for S3Upload {
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.complete()
+    }
+}
+
+impl Drop for S3Upload {
+    /// Aborts unfinished uploads so their parts don't linger (and get billed).
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        let _ = self.parts.join();
+        let query = [("uploadId", self.upload_id.as_str())];
+        if let Err(Failure::Transient(e) | Failure::Fatal(e)) =
+            self.client.send("DELETE", &self.bucket, &self.key, &query, &[], &[])
+        {
+            eprintln!("aborting upload of s3://{}/{} failed: {e}", self.bucket, self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_xml_elements() {
+        let xml = "<InitiateMultipartUploadResult><Bucket>b</Bucket>\
+                   <UploadId>VXBsb2FkIElE</UploadId></InitiateMultipartUploadResult>";
+        assert_eq!(xml_text(xml, "UploadId"), Some("VXBsb2FkIElE"));
+        assert_eq!(xml_text(xml, "Bucket"), Some("b"));
+        assert_eq!(xml_text(xml, "Key"), None);
+        assert_eq!(xml_text("<Code>only open", "Code"), None);
+    }
+}
@@ -0,0 +1,169 @@
+//! `--backfill`: a table's history over a range of days, as a warehouse
+//! that has been loading it daily holds it. Each day is a partition of its
+//! own, with `{day}` in `--output` replaced by its date, holding
+//! `--per-day` of rows whose dates and timestamps all fall on that day, and
+//! growing by `--growth` a year, as tables do while their business grows.
+
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::cli::Args;
+use crate::generator::{Generator, Row};
+use crate::schema::{Column, ColumnKind, DataType, format_date, parse_date};
+use crate::{BoxError, GB, signal, trend};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Days, from `start` up to but not including `end`, in days since
+/// 1970-01-01.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Days {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Days {
+    /// Parses `START..END`, two `YYYY-MM-DD` dates.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (start, end) = s.split_once("..").ok_or_else(|| format!("{s} is not START..END"))?;
+        let date = |date: &str| parse_date(date).ok_or_else(|| format!("{date} isn't a YYYY-MM-DD date"));
+        let days = Days { start: date(start)?, end: date(end)? };
+        if days.end <= days.start {
+            return Err(format!("{s}: the range ends before it starts"));
+        }
+        Ok(days)
+    }
+}
+
+impl fmt::Display for Days {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", format_date(self.start), format_date(self.end))
+    }
+}
+
+impl Serialize for Days {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Rows of `columns` sampled to work out how many a day of some size holds.
+const SAMPLE_ROWS: usize = 1000;
+
+/// About how many rows of `columns` make `size` bytes.
+fn rows_in(columns: &[Column], size: u64) -> Result<f64, BoxError> {
+    let generator = Generator::compile(columns)?;
+    let bytes: usize =
+        generator.rows(StdRng::seed_from_u64(0)).take(SAMPLE_ROWS).map(|row| row.fields().map(|f| f.len() + 1).sum::<usize>()).sum();
+    Ok(size as f64 / (bytes as f64 / SAMPLE_ROWS as f64))
+}
+
+/// `columns`, with every date and timestamp falling on day `day`, of
+/// `size` bytes. Clocks start at the day's midnight and run at a rate that
+/// spreads the day's rows over it.
+fn on_day(columns: &[Column], day: i64, size: u64) -> Result<Vec<Column>, BoxError> {
+    let midnight = day * SECONDS_PER_DAY;
+    let rate = rows_in(columns, size)? / SECONDS_PER_DAY as f64;
+    let mut columns = columns.to_vec();
+    for column in &mut columns {
+        match &mut column.kind {
+            ColumnKind::DateRange { min, max } => (*min, *max) = (day, day),
+            ColumnKind::TimestampRange { min, max } => (*min, *max) = (midnight, midnight + SECONDS_PER_DAY - 1),
+            ColumnKind::Clock { start, per_second, .. } => (*start, *per_second) = (midnight, rate.max(f64::MIN_POSITIVE)),
+            _ => {}
+        }
+    }
+    Ok(columns)
+}
+
+/// Moves the timestamps of `columns` that a lag or a clock took past either
+/// end of day `day` back onto it.
+fn clamp_to_day(columns: &[Column], day: i64) -> impl Fn(&mut Row, u64) + Send + Sync + use<> {
+    let times: Vec<usize> = columns.iter().enumerate().filter(|(_, c)| c.kind.data_type() == DataType::Timestamp).map(|(i, _)| i).collect();
+    // Timestamps sort as text, being all the same width.
+    let date = format_date(day);
+    let (first, last) = (format!("{date} 00:00:00"), format!("{date} 23:59:59"));
+    move |row, _| {
+        for &i in &times {
+            let field = row.field(i);
+            if !field.is_empty() && field < first.as_bytes() {
+                row.set(i, first.as_bytes());
+            } else if field > last.as_bytes() {
+                row.set(i, last.as_bytes());
+            }
+        }
+    }
+}
+
+/// The bytes day `day` of `args`'s backfill holds, after its growth and
+/// trend.
+fn day_size(args: &Args, day: i64) -> u64 {
+    let backfill = &args.backfill;
+    let days = backfill.backfill.expect("only called with --backfill");
+    let years = (day - days.start) as f64 / 365.25;
+    let growth = (1.0 + backfill.growth).powf(years) * trend::factor(&backfill.trend, days.start, day);
+    (backfill.per_day.expect("--backfill requires --per-day") as f64 * growth) as u64
+}
+
+/// Generates every day of `--backfill`, oldest first.
+pub fn generate(args: &Args, columns: &[Column]) -> Result<(), BoxError> {
+    let days = args.backfill.backfill.expect("only called with --backfill");
+    if !args.output.contains("{day}") {
+        return Err("--output must contain {day}, which is replaced by each partition's date".into());
+    }
+    let total: u64 = (days.start..days.end).map(|day| day_size(args, day)).sum();
+    println!("Backfilling {} days from {days}, {:.2}GB in all", days.end - days.start, total as f64 / GB);
+    for day in days.start..days.end {
+        if signal::interrupted() {
+            break;
+        }
+        // Each day's shards draw from seeds of their own.
+        let seed = args.seed.map(|seed| seed.wrapping_add((day - days.start) as u64 * args.shards));
+        let output = args.output.replace("{day}", &format_date(day));
+        let size = day_size(args, day);
+        let day_args = Args { output, size: Some(size), seed, ..args.clone() };
+        let columns = on_day(columns, day, size)?;
+        crate::generate(&day_args, &columns, Some(&clamp_to_day(&columns, day)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_confine_dates_and_times() {
+        let days = Days::parse("2024-02-28..2024-03-01").unwrap();
+        assert_eq!(days.end - days.start, 2);
+        assert_eq!(days.to_string(), "2024-02-28..2024-03-01");
+        assert!(Days::parse("2024-03-01..2024-03-01").is_err());
+        assert!(Days::parse("2024-03-01").is_err());
+
+        let columns = [
+            Column::new("day", ColumnKind::DateRange { min: 0, max: 20_000 }),
+            Column::new("at", ColumnKind::TimestampRange { min: 0, max: 1 << 31 }),
+        ];
+        let confined = on_day(&columns, days.start, 1 << 20).unwrap();
+        assert!(matches!(confined[0].kind, ColumnKind::DateRange { min, max } if min == days.start && max == days.start));
+        let midnight = parse_date("2024-02-28").unwrap() * SECONDS_PER_DAY;
+        assert!(matches!(confined[1].kind, ColumnKind::TimestampRange { min, max } if min == midnight && max == midnight + 86_399));
+
+        let clamp = clamp_to_day(&columns, days.start);
+        let mut row = Row::new();
+        row.push(b"2024-02-28");
+        row.push(b"2024-02-27 23:59:58");
+        clamp(&mut row, 0);
+        assert_eq!(row.field(1), b"2024-02-28 00:00:00");
+        row.set(1, b"2024-02-29 00:00:01");
+        clamp(&mut row, 0);
+        assert_eq!(row.field(1), b"2024-02-28 23:59:59");
+        row.set(1, b"2024-02-28 12:00:00");
+        clamp(&mut row, 0);
+        assert_eq!(row.field(1), b"2024-02-28 12:00:00");
+    }
+}
@@ -0,0 +1,315 @@
+//! Building a dataset in code rather than on the command line: the columns,
+//! how many rows and the seed, with any other setting of a run through
+//! [`GeneratorBuilder::from_args`]. The binary generates single tables
+//! through it too.
+//!
+//! ```
+//! use csv_data_generator::{ColumnKind, GeneratorBuilder, IdKind};
+//!
+//! let dataset = GeneratorBuilder::new()
+//!     .column("id", IdKind::UuidV4)
+//!     .column("age", ColumnKind::IntRange { min: 18, max: 60 })
+//!     .rows(1_000)
+//!     .seed(42)
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(dataset.rows().count(), 1_000);
+//! ```
+
+use std::io::{self, Write};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use clap::Parser;
+
+use crate::cli::{Args, FileFormat};
+use crate::generator::{Generator, Row, RowHook};
+use crate::locale::LocaleMix;
+use crate::output::Output;
+use crate::schema::{self, Column, ColumnKind};
+use crate::sink::{self, Sink};
+use crate::{BoxError, shard_rng};
+
+/// About how many bytes [`Dataset::chunks`] yields at a time.
+const CHUNK_BYTES: u64 = 64 * 1024;
+
+/// The random identifier kinds, to give [`GeneratorBuilder::column`] in
+/// place of the [`ColumnKind`] they stand for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdKind {
+    Sha256Hex,
+    UuidV4,
+}
+
+impl From<IdKind> for ColumnKind {
+    fn from(kind: IdKind) -> Self {
+        match kind {
+            IdKind::Sha256Hex => ColumnKind::Sha256Hex,
+            IdKind::UuidV4 => ColumnKind::UuidV4,
+        }
+    }
+}
+
+/// Collects the settings of a [`Dataset`].
+pub struct GeneratorBuilder {
+    args: Args,
+    columns: Vec<Column>,
+    hook: Option<Box<RowHook>>,
+}
+
+impl Default for GeneratorBuilder {
+    fn default() -> Self {
+        GeneratorBuilder::new()
+    }
+}
+
+impl GeneratorBuilder {
+    /// A builder with no columns and the command line's defaults otherwise.
+    pub fn new() -> Self {
+        GeneratorBuilder::from_args(Args::parse_from(["csv_data_generator"]))
+    }
+
+    /// A builder with the settings of `args`, like its output, format and
+    /// dirty data, and no columns.
+    pub fn from_args(args: Args) -> Self {
+        GeneratorBuilder { args, columns: Vec::new(), hook: None }
+    }
+
+    /// Adds a column.
+    pub fn column(mut self, name: impl Into<String>, kind: impl Into<ColumnKind>) -> Self {
+        self.columns.push(Column::new(name, kind.into()));
+        self
+    }
+
+    /// Adds columns, such as those of [`schema::builtin`].
+    pub fn columns(mut self, columns: impl IntoIterator<Item = Column>) -> Self {
+        self.columns.extend(columns);
+        self
+    }
+
+    /// Adds the columns of the built-in schema `name`, as `--schema` does,
+    /// with names from a [`Locale`](crate::Locale) or a mix of them.
+    pub fn schema(self, name: &str, locale: impl Into<LocaleMix>) -> Result<Self, BoxError> {
+        let columns = schema::builtin(name, locale).ok_or_else(|| format!("no built-in schema {name}"))?;
+        Ok(self.columns(columns))
+    }
+
+    /// Where [`Dataset::write`] writes, as `--output`.
+    pub fn output(mut self, location: impl Into<String>) -> Self {
+        self.args.output = location.into();
+        self
+    }
+
+    /// How many rows there are, as `--rows`.
+    pub fn rows(mut self, rows: u64) -> Self {
+        self.args.rows = Some(rows);
+        self
+    }
+
+    /// Seeds the values, as `--seed`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.args.seed = Some(seed);
+        self
+    }
+
+    /// Has `hook` check or change every row, with its index within its
+    /// shard, before it's written or returned, for invariants and derived
+    /// fields no column kind covers. Shards generate on several threads at
+    /// once, so it's called from all of them.
+    pub fn hook(mut self, hook: impl Fn(&mut Row, u64) + Send + Sync + 'static) -> Self {
+        self.hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Checks the columns, failing on any that couldn't generate a value.
+    pub fn build(self) -> Result<Dataset, BoxError> {
+        if self.columns.is_empty() {
+            return Err("a dataset needs at least one column".into());
+        }
+        let generator = Generator::compile(&self.columns)?;
+        Ok(Dataset { args: self.args, columns: self.columns, generator, hook: self.hook })
+    }
+}
+
+/// The rows a [`GeneratorBuilder`] describes.
+pub struct Dataset {
+    args: Args,
+    columns: Vec<Column>,
+    generator: Generator,
+    hook: Option<Box<RowHook>>,
+}
+
+impl Dataset {
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn generator(&self) -> &Generator {
+        &self.generator
+    }
+
+    /// The rows in memory, all of them or endlessly without a row count,
+    /// as a single shard would be written without dirty data.
+    pub fn rows(&self) -> impl Iterator<Item = Row> {
+        let rows = self.generator.rows(shard_rng(self.args.seed, 0)).hooked(self.hook.as_deref());
+        rows.take(self.args.rows.map_or(usize::MAX, |rows| usize::try_from(rows).unwrap_or(usize::MAX)))
+    }
+
+    /// Writes the rows to the output, as a run of the binary with the same
+    /// settings does.
+    pub fn write(&self) -> Result<(), BoxError> {
+        crate::generate(&self.args, &self.columns, self.hook.as_deref())
+    }
+
+    /// The file of [`Dataset::rows`] in `format`, in pieces of about 64 KiB
+    /// as it's generated, for callers without a filesystem, like a web page
+    /// offering it as a download. A Parquet file comes in one piece at the
+    /// end.
+    pub fn chunks(&self, format: FileFormat) -> Result<Chunks<impl Iterator<Item = Row>>, BoxError> {
+        let buffer = SharedBuffer::default();
+        let sink = sink::write_to(Box::new(buffer.clone()), format, &self.columns)?;
+        Ok(Chunks { rows: self.rows(), sink: Some(sink), buffer, taken: 0 })
+    }
+}
+
+/// The pieces of a file, from [`Dataset::chunks`].
+pub struct Chunks<I> {
+    rows: I,
+    /// None once the file is finished.
+    sink: Option<Box<dyn Sink>>,
+    buffer: SharedBuffer,
+    /// How many of the sink's bytes have been yielded.
+    taken: u64,
+}
+
+impl<I: Iterator<Item = Row>> Chunks<I> {
+    fn fill(&mut self) -> Result<Vec<u8>, BoxError> {
+        let Some(sink) = &mut self.sink else { return Ok(Vec::new()) };
+        while sink.bytes_written() - self.taken < CHUNK_BYTES {
+            match self.rows.next() {
+                Some(row) => sink.write_row(&row)?,
+                None => {
+                    self.sink.take().expect("an open sink").finish()?;
+                    return Ok(self.buffer.take());
+                }
+            }
+        }
+        sink.flush()?;
+        self.taken = sink.bytes_written();
+        Ok(self.buffer.take())
+    }
+}
+
+impl<I: Iterator<Item = Row>> Iterator for Chunks<I> {
+    type Item = Result<Vec<u8>, BoxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let finished = self.sink.is_none();
+            match self.fill() {
+                Ok(chunk) if chunk.is_empty() && !finished => continue,
+                Ok(chunk) if chunk.is_empty() => return None,
+                result => return Some(result),
+            }
+        }
+    }
+}
+
+/// An [`Output`] a [`Chunks`] takes the written bytes out of.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> Vec<u8> {
+        mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output for SharedBuffer {
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+
+    #[test]
+    fn writes_what_it_iterates() {
+        let path = std::env::temp_dir().join(format!("csv_data_generator_builder_{}.csv", std::process::id()));
+        let dataset = GeneratorBuilder::new()
+            .schema("users", Locale::En)
+            .unwrap()
+            .column("joined", ColumnKind::DateRange { min: 0, max: 20_000 })
+            .rows(50)
+            .seed(42)
+            .output(path.to_str().unwrap())
+            .build()
+            .unwrap();
+        dataset.write().unwrap();
+        let lines: Vec<String> = dataset.rows().map(|row| sink_line(&row)).collect();
+        assert_eq!(lines.len(), 50);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), format!("id,name,age,joined\n{}\n", lines.join("\n")));
+        std::fs::remove_file(path).unwrap();
+
+        assert!(GeneratorBuilder::new().build().is_err());
+    }
+
+    #[test]
+    fn hooks_see_every_row() {
+        let path = std::env::temp_dir().join(format!("csv_data_generator_hook_{}.csv", std::process::id()));
+        let dataset = GeneratorBuilder::new()
+            .schema("users", Locale::En)
+            .unwrap()
+            .hook(|row, index| {
+                let name = row.field(1).to_ascii_uppercase();
+                row.set(1, &name);
+                row.set(2, itoa::Buffer::new().format(index).as_bytes());
+            })
+            .rows(20)
+            .output(path.to_str().unwrap())
+            .build()
+            .unwrap();
+        dataset.write().unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        for (index, line) in written.lines().skip(1).enumerate() {
+            let fields: Vec<&str> = line.split(',').collect();
+            assert_eq!(fields[1], fields[1].to_uppercase());
+            assert_eq!(fields[2], index.to_string());
+        }
+        assert!(dataset.rows().all(|row| row.fields().count() == 3));
+    }
+
+    #[test]
+    fn chunks_are_the_file() {
+        let dataset = GeneratorBuilder::new().schema("orders", Locale::En).unwrap().rows(5_000).seed(3).build().unwrap();
+        let chunks: Vec<Vec<u8>> = dataset.chunks(FileFormat::Csv).unwrap().collect::<Result<_, _>>().unwrap();
+        assert!(chunks.len() > 2 && chunks.iter().all(|c| !c.is_empty()));
+        let lines: Vec<String> = dataset.rows().map(|row| sink_line(&row)).collect();
+        let header: Vec<&str> = dataset.columns().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(String::from_utf8(chunks.concat()).unwrap(), format!("{}\n{}\n", header.join(","), lines.join("\n")));
+
+        if cfg!(feature = "parquet") {
+            let parquet: Vec<_> = dataset.chunks(FileFormat::Parquet).unwrap().collect::<Result<_, _>>().unwrap();
+            assert!(parquet.concat().starts_with(b"PAR1"));
+        }
+    }
+
+    fn sink_line(row: &Row) -> String {
+        String::from_utf8(crate::sink::csv_record(row)).unwrap()
+    }
+}
@@ -0,0 +1,176 @@
+//! Companies, industries, job titles and departments, for CRM-style data.
+
+use crate::schema::ColumnKind;
+
+const COMPANY_WORDS: &[&str] = &[
+    "Apex", "Blue", "North", "Silver", "Quantum", "Pioneer", "Summit", "Harbor", "Granite", "Cedar", "Vertex", "Atlas",
+    "Beacon", "Crescent", "Evergreen", "Falcon", "Horizon", "Ironwood", "Keystone", "Meridian", "Nimbus", "Orchid",
+    "Redwood", "Sterling", "Tidal", "Union", "Westfield", "Zenith",
+];
+
+const COMPANY_NOUNS: &[&str] = &[
+    "Systems", "Logistics", "Analytics", "Foods", "Labs", "Dynamics", "Partners", "Holdings", "Industries", "Networks",
+    "Solutions", "Capital", "Health", "Energy", "Software", "Manufacturing", "Retail", "Media", "Consulting", "Robotics",
+];
+
+const LEGAL_SUFFIXES: &[&str] =
+    &["Inc.", "LLC", "Ltd.", "Corp.", "PLC", "GmbH", "AG", "S.A.", "SARL", "B.V.", "K.K.", "Pty Ltd", "Ltda.", "Pvt. Ltd."];
+
+const INDUSTRIES: &[&str] = &[
+    "Aerospace & Defense", "Agriculture", "Automotive", "Banking", "Biotechnology", "Chemicals", "Construction",
+    "Consumer Goods", "Education", "Energy", "Entertainment", "Food & Beverage", "Government", "Healthcare",
+    "Hospitality", "Insurance", "Legal Services", "Logistics", "Manufacturing", "Media", "Mining", "Pharmaceuticals",
+    "Real Estate", "Retail", "Software", "Telecommunications", "Utilities",
+];
+
+const SENIORITIES: &[&str] = &["Junior", "Senior", "Lead", "Principal", "Staff", "Associate"];
+
+/// Roles that take a seniority, like `Senior Data Analyst`, by function.
+const ROLES: &[&[&str]] = &[
+    &["Software Engineer", "Site Reliability Engineer", "QA Engineer", "Engineering Manager"],
+    &["Data Analyst", "Data Engineer", "Data Scientist"],
+    &["Product Manager", "Product Designer", "UX Researcher"],
+    &["Account Executive", "Sales Development Representative", "Account Manager", "Sales Engineer"],
+    &["Marketing Manager", "Content Strategist", "Growth Marketer"],
+    &["Financial Analyst", "Accountant", "Controller"],
+    &["Recruiter", "HR Business Partner", "People Operations Specialist"],
+    &["Customer Success Manager", "Support Specialist"],
+    &["Operations Manager", "Procurement Specialist", "Legal Counsel"],
+];
+
+/// Titles that don't take a seniority.
+const EXECUTIVES: &[&str] = &[
+    "Chief Executive Officer", "Chief Financial Officer", "Chief Technology Officer", "Chief Operating Officer",
+    "VP of Engineering", "VP of Sales", "VP of Marketing", "Head of People", "Director of Product",
+];
+
+/// A department and its teams.
+type Department = (&'static str, &'static [&'static str]);
+
+/// Divisions and their departments.
+const DEPARTMENTS: &[(&str, &[Department])] = &[
+    (
+        "Engineering",
+        &[
+            ("Platform", &["Infrastructure", "Storage", "Developer Tools"]),
+            ("Product Engineering", &["Web", "Mobile", "Payments"]),
+            ("Data", &["Analytics", "Machine Learning", "Data Platform"]),
+            ("Security", &["Application Security", "Compliance"]),
+        ],
+    ),
+    (
+        "Sales",
+        &[
+            ("Enterprise", &["North America", "EMEA", "APAC"]),
+            ("Commercial", &["Inbound", "Outbound"]),
+            ("Sales Operations", &["Deal Desk", "Enablement"]),
+        ],
+    ),
+    (
+        "Marketing",
+        &[("Brand", &["Creative", "Communications"]), ("Demand Generation", &["Events", "Digital", "Partner Marketing"])],
+    ),
+    (
+        "Finance",
+        &[("Accounting", &["Payables", "Receivables", "Payroll"]), ("FP&A", &["Planning", "Reporting"]), ("Tax", &["Indirect Tax"])],
+    ),
+    (
+        "People",
+        &[("Talent Acquisition", &["Technical Recruiting", "Sourcing"]), ("People Operations", &["Benefits", "HR Systems"])],
+    ),
+    (
+        "Customer Success",
+        &[("Support", &["Tier 1", "Tier 2", "Escalations"]), ("Account Management", &["Renewals", "Onboarding"])],
+    ),
+];
+
+/// How deep [`department`] paths go: division, department, team.
+pub const DEPARTMENT_DEPTH: usize = 3;
+
+fn list(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+/// A company name with a legal suffix, like `Apex Logistics GmbH`.
+pub fn company() -> ColumnKind {
+    ColumnKind::Format {
+        formats: list(&["{0} {1} {2}", "{0} {1} {2}", "{0}{3} {2}", "{0} & {0} {2}"]),
+        lists: vec![
+            list(COMPANY_WORDS),
+            list(COMPANY_NOUNS),
+            list(LEGAL_SUFFIXES),
+            list(&["tech", "soft", "ware", "works", "field", "point"]),
+        ],
+    }
+}
+
+pub fn industry() -> ColumnKind {
+    ColumnKind::Choice(list(INDUSTRIES))
+}
+
+/// A role with or without a seniority, or now and then an executive's title.
+pub fn job_title() -> ColumnKind {
+    let roles = ROLES.iter().flat_map(|roles| roles.iter());
+    let formats = roles.flat_map(|role| [role.to_string(), format!("{{0}} {role}")]).chain(list(EXECUTIVES)).collect();
+    ColumnKind::Format { formats, lists: vec![list(SENIORITIES)] }
+}
+
+/// The path to a unit `depth` levels down the organization, from 1 for a
+/// division to [`DEPARTMENT_DEPTH`] for a team, joined by `separator`, like
+/// `Engineering / Platform / Storage`.
+pub fn department(depth: usize, separator: &str) -> ColumnKind {
+    let mut paths = Vec::new();
+    for &(division, departments) in DEPARTMENTS {
+        if depth == 1 {
+            paths.push(division.to_string());
+            continue;
+        }
+        for &(department, teams) in departments {
+            if depth == 2 {
+                paths.push([division, department].join(separator));
+                continue;
+            }
+            paths.extend(teams.iter().map(|team| [division, department, team].join(separator)));
+        }
+    }
+    ColumnKind::Choice(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{Generator, Row};
+    use crate::schema::Column;
+
+    #[test]
+    fn departments_nest() {
+        let ColumnKind::Choice(divisions) = department(1, " / ") else { unreachable!() };
+        let ColumnKind::Choice(teams) = department(DEPARTMENT_DEPTH, " / ") else { unreachable!() };
+        assert_eq!(divisions.len(), DEPARTMENTS.len());
+        assert!(teams.contains(&"Engineering / Platform / Storage".to_string()));
+        for team in &teams {
+            let parts: Vec<_> = team.split(" / ").collect();
+            assert_eq!(parts.len(), 3, "{team}");
+            assert!(divisions.iter().any(|d| d == parts[0]));
+        }
+        let ColumnKind::Choice(departments) = department(2, ">") else { unreachable!() };
+        assert!(departments.contains(&"Finance>FP&A".to_string()));
+    }
+
+    #[test]
+    fn names_and_titles_fill_their_formats() {
+        let generator = Generator::compile(&[
+            Column::new("company", company()),
+            Column::new("industry", industry()),
+            Column::new("title", job_title()),
+        ])
+        .unwrap();
+        let mut row = Row::new();
+        for _ in 0..200 {
+            generator.fill_row(&mut rand::thread_rng(), &mut row);
+            let fields: Vec<_> = row.fields().map(|f| std::str::from_utf8(f).unwrap()).collect();
+            assert!(LEGAL_SUFFIXES.iter().any(|s| fields[0].ends_with(s)), "{}", fields[0]);
+            assert!(!fields[2].starts_with(' ') && !fields[2].contains(['{', '}']), "{}", fields[2]);
+        }
+    }
+}
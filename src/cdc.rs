@@ -0,0 +1,348 @@
+//! Change data capture: a snapshot of a table, then a stream of insert,
+//! update and delete events against its rows in Debezium's envelope, or a
+//! delta file of such changes per day.
+//!
+//! The schema's first column is the key. Every live row is kept in memory,
+//! so updates and deletes have a before image and always hit a row that the
+//! snapshot or an earlier insert created.
+
+use std::io::Write;
+
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+
+use crate::BoxError;
+use crate::cli::{Args, CdcArgs};
+use crate::generator::{Generator, Row};
+use crate::output::{Destination, Outputs};
+use crate::record::{RecordEncoder, RecordFormat};
+use crate::schema::{Column, ColumnKind, format_date, parse_date};
+use crate::signal;
+use crate::sink;
+
+/// A change event's `op`, as Debezium spells them.
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Create,
+    Update,
+    Delete,
+}
+
+impl Op {
+    const ALL: [Op; 3] = [Op::Create, Op::Update, Op::Delete];
+
+    fn code(self) -> &'static str {
+        match self {
+            Op::Create => "c",
+            Op::Update => "u",
+            Op::Delete => "d",
+        }
+    }
+}
+
+/// Checks the CDC settings before the snapshot is generated.
+pub fn validate(args: &CdcArgs) -> Result<(), BoxError> {
+    if args.cdc_mix.len() != 3 || args.cdc_mix.iter().all(|&w| w == 0) {
+        return Err("--cdc-mix takes three weights, for inserts, updates and deletes, that aren't all 0".into());
+    }
+    Ok(())
+}
+
+/// Writes one event as a line of JSON. `before` and `after` are rows
+/// already encoded as JSON objects.
+fn write_event(
+    out: &mut Vec<u8>,
+    op: Op,
+    before: Option<&[u8]>,
+    after: Option<&[u8]>,
+    table: &str,
+    lsn: u64,
+    ts_ms: i64,
+) -> Result<(), BoxError> {
+    out.extend_from_slice(b"{\"before\":");
+    out.extend_from_slice(before.unwrap_or(b"null"));
+    out.extend_from_slice(b",\"after\":");
+    out.extend_from_slice(after.unwrap_or(b"null"));
+    out.extend_from_slice(b",\"source\":{\"connector\":\"csv_data_generator\",\"table\":");
+    serde_json::to_writer(&mut *out, table)?;
+    write!(out, ",\"lsn\":{lsn},\"ts_ms\":{ts_ms}}},\"op\":\"{}\",\"ts_ms\":{ts_ms}}}", op.code())?;
+    out.push(b'\n');
+    Ok(())
+}
+
+/// One row's change, with its images before and after.
+struct Change {
+    op: Op,
+    before: Option<Row>,
+    after: Option<Row>,
+}
+
+/// The rows a table holds while changes are applied to it.
+struct LiveRows<'a> {
+    generator: &'a Generator,
+    rows: Vec<Row>,
+}
+
+impl LiveRows<'_> {
+    /// Applies a random change, picked by `mix`.
+    fn change<R: Rng + ?Sized>(&mut self, rng: &mut R, mix: &WeightedIndex<u32>) -> Change {
+        // With nothing left to change, the next change creates a row.
+        let op = if self.rows.is_empty() { Op::Create } else { Op::ALL[mix.sample(rng)] };
+        let mut row = Row::new();
+        match op {
+            Op::Create => {
+                self.generator.fill_row(rng, &mut row);
+                self.rows.push(row.clone());
+                Change { op, before: None, after: Some(row) }
+            }
+            Op::Update => {
+                let i = rng.gen_range(0..self.rows.len());
+                // A new row with the old key; every other column changes.
+                self.generator.fill_row(rng, &mut row);
+                let mut updated = Row::new();
+                for (n, field) in row.fields().enumerate() {
+                    updated.push(if n == 0 { self.rows[i].fields().next().unwrap_or_default() } else { field });
+                }
+                let before = std::mem::replace(&mut self.rows[i], updated.clone());
+                Change { op, before: Some(before), after: Some(updated) }
+            }
+            Op::Delete => {
+                let before = self.rows.swap_remove(rng.gen_range(0..self.rows.len()));
+                Change { op, before: Some(before), after: None }
+            }
+        }
+    }
+}
+
+/// Writes `--rows` rows of `columns` to `--output` as the snapshot and
+/// returns them.
+fn snapshot<'a>(args: &Args, outputs: &Outputs, generator: &'a Generator, columns: &[Column]) -> Result<LiveRows<'a>, BoxError> {
+    let mut rows = args.rows.expect("change modes require --rows");
+    let mut rng = rand::thread_rng();
+    let mut sink = sink::open(outputs, &Destination::parse(&args.output)?, columns)?;
+    let mut live = LiveRows { generator, rows: Vec::with_capacity(rows as usize) };
+    for written in 0..rows {
+        if signal::interrupted() {
+            rows = written;
+            break;
+        }
+        let mut row = Row::new();
+        generator.fill_row(&mut rng, &mut row);
+        sink.write_row(&row)?;
+        live.rows.push(row);
+    }
+    sink.finish()?;
+    println!("Successfully generated the snapshot {} ({rows} rows)", args.output);
+    Ok(live)
+}
+
+/// Writes `--rows` rows of `columns` to `--output` as the snapshot, then
+/// `--cdc-events` change events against them to `--cdc-output`.
+pub fn generate(args: &Args, columns: &[Column]) -> Result<(), BoxError> {
+    let cdc = &args.cdc;
+    validate(cdc)?;
+    let mut events = cdc.cdc_events.expect("only called with --cdc-events");
+    let events_location = cdc.cdc_output.as_deref().expect("--cdc-events requires --cdc-output");
+    let outputs = Outputs::new(args);
+    let generator = Generator::compile(columns)?;
+    let encoder = RecordEncoder::new(RecordFormat::Json, columns, None);
+    let mut rng = rand::thread_rng();
+    let mut live = snapshot(args, &outputs, &generator, columns)?;
+
+    let mut output = outputs.open(&Destination::parse(events_location)?)?;
+    let mix = WeightedIndex::new(&cdc.cdc_mix)?;
+    let mut ts_ms = chrono::Utc::now().timestamp_millis();
+    // Offsets into the event stream, which increase the way a log position does.
+    let mut lsn: u64 = 0;
+    let mut counts = [0u64; 3];
+    let mut line = Vec::new();
+    for event in 0..events {
+        if signal::interrupted() {
+            events = event;
+            break;
+        }
+        let change = live.change(&mut rng, &mix);
+        ts_ms += rng.gen_range(1..=1000);
+        let before = change.before.as_ref().map(|row| encoder.encode(row)).transpose()?;
+        let after = change.after.as_ref().map(|row| encoder.encode(row)).transpose()?;
+        line.clear();
+        write_event(&mut line, change.op, before.as_deref(), after.as_deref(), &args.schema, lsn, ts_ms)?;
+        output.write_all(&line)?;
+        lsn += line.len() as u64;
+        counts[change.op as usize] += 1;
+    }
+    output.finish()?;
+    println!(
+        "Successfully generated {events} change events to {events_location}: {} inserts, {} updates, {} deletes; {} rows remain",
+        counts[0],
+        counts[1],
+        counts[2],
+        live.rows.len()
+    );
+    Ok(())
+}
+
+/// The columns delta files add after the table's own.
+const DELTA_COLUMNS: [&str; 2] = ["_op", "_changed_at"];
+
+/// Writes `--rows` rows of `columns` to `--output` as the snapshot, then a
+/// delta file per day for `--deltas` days of `--delta-rows` changes each.
+pub fn generate_deltas(args: &Args, columns: &[Column]) -> Result<(), BoxError> {
+    let cdc = &args.cdc;
+    validate(cdc)?;
+    let days = cdc.deltas.expect("only called with --deltas");
+    let template = cdc.delta_output.as_deref().expect("--deltas requires --delta-output");
+    if !template.contains("{day}") {
+        return Err("--delta-output must contain {day}, which is replaced by each delta's date".into());
+    }
+    let start = match &cdc.delta_start {
+        Some(date) => parse_date(date).ok_or_else(|| format!("--delta-start: {date} isn't a YYYY-MM-DD date"))?,
+        None => chrono::Utc::now().timestamp().div_euclid(86_400),
+    };
+    let outputs = Outputs::new(args);
+    let generator = Generator::compile(columns)?;
+    let mut rng = rand::thread_rng();
+    let mut live = snapshot(args, &outputs, &generator, columns)?;
+
+    let mix = WeightedIndex::new(&cdc.cdc_mix)?;
+    let mut delta_columns = columns.to_vec();
+    // Only the names and types of these matter; the values are written below.
+    delta_columns.extend(DELTA_COLUMNS.map(|name| Column::new(name, ColumnKind::Choice(Vec::new()))));
+    for day in 0..days as i64 {
+        if signal::interrupted() {
+            break;
+        }
+        let date = format_date(start + day);
+        let location = template.replace("{day}", &date);
+        let mut sink = sink::open(&outputs, &Destination::parse(&location)?, &delta_columns)?;
+        let mut seconds: Vec<u32> = (0..cdc.delta_rows).map(|_| rng.gen_range(0..86_400)).collect();
+        seconds.sort_unstable();
+        let mut row = Row::new();
+        for second in seconds {
+            let change = live.change(&mut rng, &mix);
+            // Tombstones carry the deleted row's last values, so every field still parses as its type.
+            let image = change.after.or(change.before).expect("a change has an image");
+            row.clear();
+            for field in image.fields() {
+                row.push(field);
+            }
+            row.push(match change.op {
+                Op::Create => b"insert",
+                Op::Update => b"update",
+                Op::Delete => b"delete",
+            });
+            let (hour, minute, second) = (second / 3600, second / 60 % 60, second % 60);
+            row.push(format!("{date}T{hour:02}:{minute:02}:{second:02}Z").as_bytes());
+            sink.write_row(&row)?;
+        }
+        sink.finish()?;
+        println!("Successfully generated {location} ({} changes)", cdc.delta_rows);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+    use crate::schema;
+    use clap::Parser;
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn events_apply_to_the_snapshot() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_cdc_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (snapshot, events) = (dir.join("users.csv"), dir.join("users.jsonl"));
+        let args = Args::parse_from([
+            "gen",
+            "--rows",
+            "50",
+            "--output",
+            snapshot.to_str().unwrap(),
+            "--cdc-events",
+            "500",
+            "--cdc-output",
+            events.to_str().unwrap(),
+        ]);
+        generate(&args, &schema::builtin("users", Locale::En).unwrap()).unwrap();
+
+        // Replaying the events onto the snapshot must never miss a key.
+        let mut table: HashMap<String, Value> = csv::Reader::from_path(&snapshot)
+            .unwrap()
+            .records()
+            .map(|r| {
+                let r = r.unwrap();
+                (r[0].to_string(), serde_json::json!({ "id": &r[0], "name": &r[1], "age": r[2].parse::<i64>().unwrap() }))
+            })
+            .collect();
+        assert_eq!(table.len(), 50);
+        let mut last_lsn = None;
+        for line in fs::read_to_string(&events).unwrap().lines() {
+            let event: Value = serde_json::from_str(line).unwrap();
+            let lsn = event["source"]["lsn"].as_u64().unwrap();
+            assert!(last_lsn.is_none_or(|last| lsn > last));
+            last_lsn = Some(lsn);
+            let key = |image: &Value| image["id"].as_str().unwrap().to_string();
+            match event["op"].as_str().unwrap() {
+                "c" => assert!(table.insert(key(&event["after"]), event["after"].clone()).is_none()),
+                "u" => {
+                    assert_eq!(key(&event["before"]), key(&event["after"]));
+                    assert_eq!(table.insert(key(&event["after"]), event["after"].clone()), Some(event["before"].clone()));
+                }
+                "d" => assert_eq!(table.remove(&key(&event["before"])), Some(event["before"].clone())),
+                op => panic!("unexpected op {op}"),
+            }
+        }
+        assert!(last_lsn.is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn deltas_apply_to_the_snapshot() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_deltas_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let snapshot = dir.join("users.csv");
+        let template = dir.join("delta-{day}.csv");
+        let args = Args::parse_from([
+            "gen",
+            "--rows",
+            "40",
+            "--output",
+            snapshot.to_str().unwrap(),
+            "--deltas",
+            "3",
+            "--delta-rows",
+            "30",
+            "--delta-output",
+            template.to_str().unwrap(),
+            "--delta-start",
+            "2024-02-28",
+        ]);
+        generate_deltas(&args, &schema::builtin("users", Locale::En).unwrap()).unwrap();
+
+        let read = |path: &std::path::Path| -> Vec<Vec<String>> {
+            let mut reader = csv::Reader::from_path(path).unwrap();
+            reader.records().map(|r| r.unwrap().iter().map(String::from).collect()).collect()
+        };
+        let mut table: HashMap<String, Vec<String>> = read(&snapshot).into_iter().map(|r| (r[0].clone(), r)).collect();
+        for date in ["2024-02-28", "2024-02-29", "2024-03-01"] {
+            let delta = read(&dir.join(format!("delta-{date}.csv")));
+            assert_eq!(delta.len(), 30);
+            assert!(delta.windows(2).all(|pair| pair[0][4] <= pair[1][4]));
+            for change in delta {
+                assert!(change[4].starts_with(&format!("{date}T")), "{}", change[4]);
+                let key = change[0].clone();
+                match change[3].as_str() {
+                    "insert" => assert!(table.insert(key, change[..3].to_vec()).is_none()),
+                    "update" => assert!(table.insert(key, change[..3].to_vec()).is_some()),
+                    "delete" => assert_eq!(table.remove(&key).unwrap(), change[..3]),
+                    op => panic!("unexpected op {op}"),
+                }
+            }
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,318 @@
+//! The CDR preset: cell `towers`, `subscribers` and the call detail records
+//! of their `calls` during the first week of 2024.
+//!
+//! Subscribers have numbers in their home city's area codes and call mostly
+//! by day, for log-normally distributed durations. On most days they stay
+//! home, but now and then they travel to another city for the day, and every
+//! call they make is carried by a tower in the city they are in, as is every
+//! call to another subscriber, by a tower in the callee's home city. Calls
+//! outside the network go to numbers in any of the area codes. A call is
+//! answered, busy, unanswered or dropped part way through, more often on
+//! older radios.
+
+use std::collections::HashSet;
+
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::generator::{Row, push_timestamp};
+use crate::geo::{self, Bounds, Shape};
+use crate::presets::{self, push_int};
+use crate::schema::{Column, ColumnKind};
+use crate::signal;
+use crate::transactions::normal;
+
+/// Calls are made from 2024-01-01 UTC for a week.
+const WINDOW_START: i64 = 1_704_067_200;
+const DAYS: i64 = 7;
+
+/// Subscribers at scale factor 1.
+const SUBSCRIBERS: f64 = 10_000.0;
+/// The mean calls a subscriber makes a day.
+const CALLS_PER_DAY: f64 = 4.0;
+/// The chance of a subscriber spending a day in another city.
+const TRAVEL_RATE: f64 = 0.05;
+/// The chance of a call going to another subscriber rather than off the network.
+const ON_NET: f64 = 0.7;
+/// The median and log-scale spread of an answered call's seconds, and the
+/// longest a call lasts.
+const MEDIAN_DURATION: f64 = 90.0;
+const DURATION_SIGMA: f64 = 1.1;
+const MAX_DURATION: f64 = 4.0 * 3600.0;
+/// The chances of a call finding the callee busy or going unanswered.
+const BUSY_RATE: f64 = 0.04;
+const NO_ANSWER_RATE: f64 = 0.12;
+
+const TABLES: [&str; 3] = ["towers", "subscribers", "calls"];
+
+/// A city, its area codes, its centre and how many towers it has.
+struct City {
+    name: &'static str,
+    area_codes: &'static [u16],
+    centre: (f64, f64),
+    towers: usize,
+}
+
+const CITIES: &[City] = &[
+    City { name: "New York", area_codes: &[212, 646, 917, 718], centre: (40.7128, -74.0060), towers: 120 },
+    City { name: "Los Angeles", area_codes: &[213, 310, 323], centre: (34.0522, -118.2437), towers: 90 },
+    City { name: "Chicago", area_codes: &[312, 773, 872], centre: (41.8781, -87.6298), towers: 60 },
+    City { name: "Houston", area_codes: &[713, 832, 281], centre: (29.7604, -95.3698), towers: 50 },
+    City { name: "Phoenix", area_codes: &[602, 480, 623], centre: (33.4484, -112.0740), towers: 40 },
+    City { name: "Miami", area_codes: &[305, 786], centre: (25.7617, -80.1918), towers: 30 },
+    City { name: "Seattle", area_codes: &[206, 425], centre: (47.6062, -122.3321), towers: 30 },
+    City { name: "Denver", area_codes: &[303, 720], centre: (39.7392, -104.9903), towers: 30 },
+];
+
+/// How far towers spread from their city's centre, in degrees.
+const TOWER_SPREAD: f64 = 0.2;
+
+/// Radio technologies, how many towers have each, and how often their
+/// answered calls drop.
+const RADIOS: &[(&str, u32, f64)] = &[("5G", 30, 0.005), ("LTE", 60, 0.012), ("3G", 10, 0.035)];
+
+/// How often calls start in each hour of the day, taken to be UTC.
+const HOURS: [u32; 24] = [1, 1, 1, 1, 1, 2, 4, 7, 9, 10, 11, 11, 12, 11, 11, 11, 12, 12, 11, 10, 8, 6, 4, 2];
+
+/// The columns of each table, in [`TABLES`] order. Only the names and types
+/// of these matter; the values are written below.
+fn columns() -> [Vec<Column>; 3] {
+    let integer = |name| Column::new(name, ColumnKind::Sequence { start: 1 });
+    let string = |name| Column::new(name, ColumnKind::Choice(Vec::new()));
+    let decimal = |name| Column::new(name, ColumnKind::Decimal { min: 0, max: 0, scale: 6 });
+    [
+        vec![integer("tower_id"), string("city"), decimal("latitude"), decimal("longitude"), string("radio")],
+        vec![integer("subscriber_id"), string("msisdn"), string("home_city"), string("plan")],
+        vec![
+            integer("call_id"),
+            string("caller"),
+            string("callee"),
+            Column::new("started_at", ColumnKind::TimestampRange { min: 0, max: 0 }),
+            integer("duration_seconds"),
+            integer("originating_tower_id"),
+            integer("terminating_tower_id"),
+            string("result"),
+            integer("dropped"),
+        ],
+    ]
+}
+
+/// A number in E.164 form in one of `area_codes`, with an exchange that
+/// doesn't start with 0 or 1 and isn't an N11 service code.
+fn number<R: Rng + ?Sized>(rng: &mut R, area_codes: &[u16]) -> String {
+    let area_code = area_codes[rng.gen_range(0..area_codes.len())];
+    let exchange = loop {
+        let exchange = rng.gen_range(200..1000);
+        if exchange % 100 != 11 {
+            break exchange;
+        }
+    };
+    format!("+1{area_code}{exchange}{:04}", rng.gen_range(0..10_000))
+}
+
+/// A Poisson-distributed count with the given mean, by Knuth's method.
+pub fn poisson<R: Rng + ?Sized>(rng: &mut R, mean: f64) -> u32 {
+    let limit = (-mean).exp();
+    let (mut count, mut product) = (0, rng.r#gen::<f64>());
+    while product > limit {
+        count += 1;
+        product *= rng.r#gen::<f64>();
+    }
+    count
+}
+
+struct Subscriber {
+    msisdn: String,
+    home: usize,
+}
+
+/// Writes the towers and subscribers, then each subscriber's calls day by
+/// day.
+pub fn generate(args: &Args) -> Result<(), BoxError> {
+    let [mut towers_sink, mut subscribers_sink, mut calls_sink] = presets::open_tables(args, "CDR", TABLES, columns())?;
+
+    let mut rng = rand::thread_rng();
+    let mut row = Row::new();
+    let radios = WeightedIndex::new(RADIOS.iter().map(|r| r.1))?;
+    // Each city's towers, as `(tower_id, drop rate)`.
+    let mut towers: Vec<Vec<(u64, f64)>> = Vec::new();
+    let mut tower_id = 0;
+    for city in CITIES {
+        let (lat, lon) = city.centre;
+        let around = [Bounds { south: lat - TOWER_SPREAD, west: lon - TOWER_SPREAD, north: lat + TOWER_SPREAD, east: lon + TOWER_SPREAD }];
+        let mut city_towers = Vec::new();
+        for _ in 0..city.towers {
+            tower_id += 1;
+            let (radio, _, drop_rate) = RADIOS[radios.sample(&mut rng)];
+            row.clear();
+            push_int(&mut row, tower_id);
+            row.push(city.name.as_bytes());
+            let point = geo::sample(&mut rng, &around);
+            for shape in [Shape::Latitude, Shape::Longitude] {
+                row.push_with(|field| geo::push(&mut rng, shape, point, field));
+            }
+            row.push(radio.as_bytes());
+            towers_sink.write_row(&row)?;
+            city_towers.push((tower_id, drop_rate));
+        }
+        towers.push(city_towers);
+    }
+
+    let cities = WeightedIndex::new(CITIES.iter().map(|c| c.towers))?;
+    let count = ((SUBSCRIBERS * args.preset.scale_factor).round() as usize).max(1);
+    let mut taken = HashSet::new();
+    let mut subscribers = Vec::with_capacity(count);
+    for id in 1..=count {
+        let home = cities.sample(&mut rng);
+        let msisdn = loop {
+            let msisdn = number(&mut rng, CITIES[home].area_codes);
+            if taken.insert(msisdn.clone()) {
+                break msisdn;
+            }
+        };
+        row.clear();
+        push_int(&mut row, id as u64);
+        row.push(msisdn.as_bytes());
+        row.push(CITIES[home].name.as_bytes());
+        row.push(if rng.gen_bool(0.35) { b"prepaid" } else { b"postpaid" });
+        subscribers_sink.write_row(&row)?;
+        subscribers.push(Subscriber { msisdn, home });
+    }
+
+    let hours = WeightedIndex::new(HOURS)?;
+    let (mut calls, mut dropped) = (0u64, 0u64);
+    let mut starts = Vec::new();
+    for caller in &subscribers {
+        if signal::interrupted() {
+            break;
+        }
+        for day in 0..DAYS {
+            let city = if rng.gen_bool(TRAVEL_RATE) { cities.sample(&mut rng) } else { caller.home };
+            starts.clear();
+            starts.extend((0..poisson(&mut rng, CALLS_PER_DAY)).map(|_| {
+                WINDOW_START + day * 86_400 + hours.sample(&mut rng) as i64 * 3600 + rng.gen_range(0..3600)
+            }));
+            starts.sort_unstable();
+            for &start in &starts {
+                let (origin, drop_rate) = towers[city][rng.gen_range(0..towers[city].len())];
+                let (callee, terminating) = if rng.gen_bool(ON_NET) {
+                    let callee = &subscribers[rng.gen_range(0..subscribers.len())];
+                    let home = &towers[callee.home];
+                    (callee.msisdn.clone(), Some(home[rng.gen_range(0..home.len())].0))
+                } else {
+                    let codes = CITIES[rng.gen_range(0..CITIES.len())].area_codes;
+                    (number(&mut rng, codes), None)
+                };
+                let u: f64 = rng.r#gen();
+                let answered = (MEDIAN_DURATION * (DURATION_SIGMA * normal(&mut rng)).exp()).clamp(1.0, MAX_DURATION);
+                let (result, duration) = if u < BUSY_RATE {
+                    ("busy", 0.0)
+                } else if u < BUSY_RATE + NO_ANSWER_RATE {
+                    ("no_answer", 0.0)
+                } else if rng.gen_bool(drop_rate) {
+                    ("dropped", (answered * rng.r#gen::<f64>()).max(1.0))
+                } else {
+                    ("answered", answered)
+                };
+                calls += 1;
+                dropped += u64::from(result == "dropped");
+                row.clear();
+                push_int(&mut row, calls);
+                row.push(caller.msisdn.as_bytes());
+                row.push(callee.as_bytes());
+                push_timestamp(&mut row, start);
+                push_int(&mut row, duration as u64);
+                push_int(&mut row, origin);
+                match terminating {
+                    Some(tower) => push_int(&mut row, tower),
+                    None => row.push(b""),
+                }
+                row.push(result.as_bytes());
+                push_int(&mut row, (result == "dropped").into());
+                calls_sink.write_row(&row)?;
+            }
+        }
+    }
+    presets::finish_tables(args, TABLES, [towers_sink, subscribers_sink, calls_sink], [tower_id, count as u64, calls])?;
+    println!("  {dropped} of {calls} calls dropped");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::testing::{read, temp_dir};
+    use clap::Parser;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn calls_are_carried_where_their_parties_are() {
+        let dir = temp_dir("cdr");
+        let template = dir.join("{table}.csv");
+        let args = Args::parse_from(["gen", "--preset", "cdr", "--scale-factor", "0.02", "--output", template.to_str().unwrap()]);
+        generate(&args).unwrap();
+
+        let towers: HashMap<String, Vec<String>> = read(dir.join("towers.csv")).into_iter().map(|t| (t[0].clone(), t)).collect();
+        assert_eq!(towers.len(), CITIES.iter().map(|c| c.towers).sum::<usize>());
+        let subscribers: HashMap<String, String> =
+            read(dir.join("subscribers.csv")).into_iter().map(|s| (s[1].clone(), s[2].clone())).collect();
+        assert_eq!(subscribers.len(), 200);
+        let calls = read(dir.join("calls.csv"));
+        let (mut away, mut results) = (0, HashMap::new());
+        for c in &calls {
+            assert!(c[1].len() == 12 && c[1].starts_with("+1") && c[2].len() == 12, "{c:?}");
+            away += usize::from(towers[&c[5]][1] != subscribers[&c[1]]);
+            match subscribers.get(&c[2]) {
+                Some(home) => assert_eq!(&towers[&c[6]][1], home),
+                None => assert_eq!(c[6], ""),
+            }
+            let duration: u64 = c[4].parse().unwrap();
+            assert_eq!(duration == 0, c[7] == "busy" || c[7] == "no_answer", "{c:?}");
+            assert_eq!(c[8] == "1", c[7] == "dropped");
+            *results.entry(c[7].clone()).or_insert(0) += 1;
+        }
+        // 200 subscribers make about 4 calls a day for a week, mostly at home.
+        assert!((4000..7200).contains(&calls.len()), "{}", calls.len());
+        assert!(away > 0 && away * 10 < calls.len(), "{away}");
+        assert_eq!(results.len(), 4, "{results:?}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn calls_off_the_network_have_null_terminating_towers() {
+        let dir = temp_dir("cdr_typed");
+        let generate_as = |format: &str| {
+            let template = dir.join(format!("{{table}}.{format}"));
+            let output = template.to_str().unwrap();
+            generate(&Args::parse_from(["gen", "--preset", "cdr", "--scale-factor", "0.01", "--format", format, "--output", output]))
+        };
+        generate_as("jsonl").unwrap();
+        let calls: Vec<serde_json::Value> =
+            fs::read_to_string(dir.join("calls.jsonl")).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let off_network = calls.iter().filter(|c| c["terminating_tower_id"].is_null()).count();
+        assert!(off_network > 0 && off_network < calls.len(), "{off_network}");
+        assert!(calls.iter().all(|c| c["terminating_tower_id"].is_null() || c["terminating_tower_id"].is_u64()));
+        #[cfg(feature = "parquet")]
+        {
+            use parquet::file::reader::{FileReader, SerializedFileReader};
+            generate_as("parquet").unwrap();
+            let reader = SerializedFileReader::new(fs::File::open(dir.join("calls.parquet")).unwrap()).unwrap();
+            let rows = reader.get_row_iter(None).unwrap().map(|row| row.unwrap().to_string());
+            assert!(rows.filter(|row| row.contains("terminating_tower_id: null")).count() > 0);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn numbers_look_like_north_american_ones() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let n = number(&mut rng, &[212]);
+            assert!(n.starts_with("+1212") && n.len() == 12);
+            assert!(n.as_bytes()[5] >= b'2' && &n[6..8] != "11", "{n}");
+        }
+    }
+}
@@ -0,0 +1,384 @@
+//! The claims preset: synthetic `patients`, `providers`, and the `claims`
+//! and `claim_lines` of their encounters during 2024, shaped like health
+//! insurance claims but with no real people in them.
+//!
+//! Patients have member ids starting `SYN`, a birth date, a sex, the first
+//! three digits of a ZIP code and a plan, Medicare for those 65 and over.
+//! Providers have NPIs of ten digits with a valid check digit, but starting
+//! with 9, which no real NPI does. Each claim is for one kind of encounter,
+//! which picks the provider's specialty, the ICD-10-CM diagnoses and the
+//! CPT procedures billed on its lines. Each line is allowed a share of what
+//! was billed and paid what the patient's coinsurance leaves, unless it's
+//! denied with a claim adjustment reason code, and the claim's totals are
+//! the sums of its lines'.
+
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+
+use crate::BoxError;
+use crate::cdr::poisson;
+use crate::cli::Args;
+use crate::generator::{Row, push_date, push_decimal};
+use crate::presets::{self, push_int};
+use crate::schema::{Column, ColumnKind};
+use crate::signal;
+use crate::transactions::normal;
+
+/// Services are during 2024, from 2024-01-01 in days since 1970-01-01.
+const YEAR_START: i64 = 19_723;
+const YEAR_DAYS: i64 = 366;
+
+/// Patients at scale factor 1, and patients per provider.
+const PATIENTS: f64 = 10_000.0;
+const PATIENTS_PER_PROVIDER: usize = 50;
+/// The mean claims a patient has in the year.
+const CLAIMS_PER_PATIENT: f64 = 3.0;
+/// The chance of a line being denied, and of a claim having a second diagnosis.
+const DENIAL_RATE: f64 = 0.05;
+const SECOND_DIAGNOSIS: f64 = 0.3;
+
+const TABLES: [&str; 4] = ["patients", "providers", "claims", "claim_lines"];
+
+const SPECIALTIES: &[&str] =
+    &["Family Medicine", "Internal Medicine", "Endocrinology", "Cardiology", "Behavioral Health", "Physical Therapy", "Urgent Care"];
+
+/// A kind of encounter: how common it is, the specialties that see it, its
+/// diagnoses, and the procedures billed with the chance of each, the first
+/// always.
+struct Encounter {
+    weight: u32,
+    specialties: &'static [&'static str],
+    diagnoses: &'static [&'static str],
+    procedures: &'static [(&'static str, f64)],
+}
+
+const ENCOUNTERS: &[Encounter] = &[
+    Encounter {
+        weight: 20,
+        specialties: &["Family Medicine", "Internal Medicine"],
+        diagnoses: &["Z00.00", "Z00.01"],
+        procedures: &[("99395", 1.0), ("36415", 0.6), ("80053", 0.5), ("85025", 0.5), ("90471", 0.3)],
+    },
+    Encounter {
+        weight: 12,
+        specialties: &["Endocrinology", "Internal Medicine"],
+        diagnoses: &["E11.9", "E11.65"],
+        procedures: &[("99214", 1.0), ("83036", 0.8), ("36415", 0.8), ("80053", 0.4)],
+    },
+    Encounter {
+        weight: 15,
+        specialties: &["Cardiology", "Internal Medicine", "Family Medicine"],
+        diagnoses: &["I10"],
+        procedures: &[("99213", 1.0), ("93000", 0.2), ("80048", 0.4), ("36415", 0.4)],
+    },
+    Encounter {
+        weight: 18,
+        specialties: &["Urgent Care", "Family Medicine"],
+        diagnoses: &["J06.9", "J02.9", "J20.9"],
+        procedures: &[("99213", 1.0), ("87880", 0.4), ("71046", 0.1)],
+    },
+    Encounter {
+        weight: 10,
+        specialties: &["Physical Therapy", "Family Medicine"],
+        diagnoses: &["M54.50"],
+        procedures: &[("99213", 1.0), ("97110", 0.5), ("72100", 0.2)],
+    },
+    Encounter {
+        weight: 8,
+        specialties: &["Behavioral Health"],
+        diagnoses: &["F41.1", "F32.A"],
+        procedures: &[("90834", 1.0)],
+    },
+    Encounter {
+        weight: 7,
+        specialties: &["Urgent Care", "Family Medicine"],
+        diagnoses: &["N39.0"],
+        procedures: &[("99213", 1.0), ("81003", 0.9), ("87086", 0.5)],
+    },
+    Encounter {
+        weight: 10,
+        specialties: &["Internal Medicine", "Cardiology"],
+        diagnoses: &["E78.5"],
+        procedures: &[("99213", 1.0), ("80061", 0.9), ("36415", 0.9)],
+    },
+];
+
+/// What providers bill for each procedure, in dollars, at the median.
+const PRICES: &[(&str, f64)] = &[
+    ("99395", 250.0),
+    ("99213", 140.0),
+    ("99214", 210.0),
+    ("36415", 20.0),
+    ("80053", 60.0),
+    ("85025", 35.0),
+    ("90471", 40.0),
+    ("83036", 45.0),
+    ("93000", 70.0),
+    ("80048", 45.0),
+    ("87880", 40.0),
+    ("71046", 120.0),
+    ("97110", 95.0),
+    ("72100", 130.0),
+    ("90834", 160.0),
+    ("81003", 15.0),
+    ("87086", 50.0),
+    ("80061", 55.0),
+];
+
+/// The claim adjustment reason codes lines are denied with.
+const DENIALS: &[&str] = &["CO-16", "CO-50", "CO-97", "CO-197", "PR-204"];
+/// The place of service codes: office, outpatient hospital and telehealth.
+const PLACES: &[(&str, u32)] = &[("11", 80), ("22", 12), ("02", 8)];
+const COINSURANCE: &[i64] = &[0, 10, 20, 30];
+
+/// The columns of each table, in [`TABLES`] order. Only the names and types
+/// of these matter; the values are written below.
+fn columns() -> [Vec<Column>; 4] {
+    let integer = |name| Column::new(name, ColumnKind::Sequence { start: 1 });
+    let string = |name| Column::new(name, ColumnKind::Choice(Vec::new()));
+    let date = |name| Column::new(name, ColumnKind::DateRange { min: 0, max: 0 });
+    let money = |name| Column::new(name, ColumnKind::Decimal { min: 0, max: 0, scale: 2 });
+    [
+        vec![integer("patient_id"), string("member_id"), date("birth_date"), string("sex"), string("zip3"), string("plan")],
+        vec![integer("provider_id"), string("npi"), string("specialty")],
+        vec![
+            integer("claim_id"),
+            integer("patient_id"),
+            integer("provider_id"),
+            date("service_date"),
+            date("received_date"),
+            string("place_of_service"),
+            string("primary_diagnosis"),
+            string("secondary_diagnosis"),
+            money("total_billed"),
+            money("total_allowed"),
+            money("total_paid"),
+            string("status"),
+        ],
+        vec![
+            integer("claim_id"),
+            integer("line_number"),
+            string("procedure_code"),
+            integer("units"),
+            money("billed"),
+            money("allowed"),
+            money("paid"),
+            money("patient_responsibility"),
+            string("denial_reason"),
+        ],
+    ]
+}
+
+/// A ten-digit NPI starting with 9, with the Luhn check digit NPIs have
+/// over their first nine digits and the `80840` prefix.
+fn npi<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let mut digits: Vec<u32> = std::iter::once(9).chain((0..8).map(|_| rng.gen_range(0..10))).collect();
+    // 80840 adds 24 to the sum.
+    let mut sum = 24;
+    for (i, &d) in digits.iter().rev().enumerate() {
+        sum += if i % 2 == 0 { (2 * d) / 10 + (2 * d) % 10 } else { d };
+    }
+    digits.push((10 - sum % 10) % 10);
+    digits.iter().map(|d| char::from_digit(*d, 10).expect("a digit")).collect()
+}
+
+fn price(code: &str) -> f64 {
+    PRICES.iter().find(|(c, _)| *c == code).expect("every procedure has a price").1
+}
+
+/// A line's amounts, in cents.
+struct Line {
+    billed: i64,
+    allowed: i64,
+    paid: i64,
+    patient: i64,
+}
+
+/// Writes the providers, then each patient followed by their claims and
+/// claim lines in service date order.
+pub fn generate(args: &Args) -> Result<(), BoxError> {
+    let [mut patients_sink, mut providers_sink, mut claims_sink, mut lines_sink] = presets::open_tables(args, "claims", TABLES, columns())?;
+
+    let mut rng = rand::thread_rng();
+    let mut row = Row::new();
+    let mut patients = ((PATIENTS * args.preset.scale_factor).round() as u64).max(1);
+    // Every specialty has a provider, and each provider's index by specialty.
+    let providers = (patients as usize / PATIENTS_PER_PROVIDER).max(SPECIALTIES.len());
+    let mut by_specialty = vec![Vec::new(); SPECIALTIES.len()];
+    for id in 1..=providers {
+        let specialty = if id <= SPECIALTIES.len() { id - 1 } else { rng.gen_range(0..SPECIALTIES.len()) };
+        by_specialty[specialty].push(id as u64);
+        row.clear();
+        push_int(&mut row, id as u64);
+        row.push(npi(&mut rng).as_bytes());
+        row.push(SPECIALTIES[specialty].as_bytes());
+        providers_sink.write_row(&row)?;
+    }
+
+    let encounters = WeightedIndex::new(ENCOUNTERS.iter().map(|e| e.weight))?;
+    let places = WeightedIndex::new(PLACES.iter().map(|p| p.1))?;
+    let (mut claims, mut lines, mut denied) = (0u64, 0u64, 0u64);
+    let mut claim_lines = Vec::new();
+    for patient in 1..=patients {
+        if signal::interrupted() {
+            patients = patient - 1;
+            break;
+        }
+        // Ages 0 to 90 at the start of the year.
+        let born = YEAR_START - rng.gen_range(0..90 * 365 + 22);
+        let age = (YEAR_START - born) / 365;
+        let plan = if age >= 65 { "medicare" } else if rng.gen_bool(0.2) { "medicaid" } else { "commercial" };
+        let coinsurance = COINSURANCE[rng.gen_range(0..COINSURANCE.len())];
+        row.clear();
+        push_int(&mut row, patient);
+        row.push(format!("SYN{patient:09}").as_bytes());
+        push_date(&mut row, born);
+        row.push(if rng.gen_bool(0.5) { b"F" } else { b"M" });
+        row.push(format!("{:03}", rng.gen_range(10..1000)).as_bytes());
+        row.push(plan.as_bytes());
+        patients_sink.write_row(&row)?;
+
+        let mut service_dates: Vec<i64> =
+            (0..poisson(&mut rng, CLAIMS_PER_PATIENT)).map(|_| YEAR_START + rng.gen_range(0..YEAR_DAYS)).collect();
+        service_dates.sort_unstable();
+        for service in service_dates {
+            claims += 1;
+            let encounter = &ENCOUNTERS[encounters.sample(&mut rng)];
+            let specialty = encounter.specialties[rng.gen_range(0..encounter.specialties.len())];
+            let specialists = &by_specialty[SPECIALTIES.iter().position(|&s| s == specialty).expect("a known specialty")];
+            let provider = specialists[rng.gen_range(0..specialists.len())];
+
+            claim_lines.clear();
+            for &(code, chance) in encounter.procedures {
+                if claim_lines.is_empty() || rng.gen_bool(chance) {
+                    let units = if code == "97110" { rng.gen_range(1..=4) } else { 1 };
+                    let billed = (price(code) * units as f64 * (0.15 * normal(&mut rng)).exp() * 100.0).round() as i64;
+                    let line = if rng.gen_bool(DENIAL_RATE) {
+                        Line { billed, allowed: 0, paid: 0, patient: 0 }
+                    } else {
+                        let allowed = billed * rng.gen_range(45..=80) / 100;
+                        let patient = allowed * coinsurance / 100;
+                        Line { billed, allowed, paid: allowed - patient, patient }
+                    };
+                    claim_lines.push((code, units, line));
+                }
+            }
+            let denials = claim_lines.iter().filter(|(.., line)| line.allowed == 0).count();
+            let status = match denials {
+                0 => "paid",
+                n if n == claim_lines.len() => "denied",
+                _ => "partially_denied",
+            };
+            let diagnosis = encounter.diagnoses[rng.gen_range(0..encounter.diagnoses.len())];
+            let second = if rng.gen_bool(SECOND_DIAGNOSIS) {
+                let other = &ENCOUNTERS[encounters.sample(&mut rng)];
+                Some(other.diagnoses[rng.gen_range(0..other.diagnoses.len())]).filter(|&d| d != diagnosis)
+            } else {
+                None
+            };
+            row.clear();
+            push_int(&mut row, claims);
+            push_int(&mut row, patient);
+            push_int(&mut row, provider);
+            push_date(&mut row, service);
+            push_date(&mut row, service + rng.gen_range(1..=30));
+            row.push(PLACES[places.sample(&mut rng)].0.as_bytes());
+            row.push(diagnosis.as_bytes());
+            row.push(second.unwrap_or_default().as_bytes());
+            for total in [|l: &Line| l.billed, |l: &Line| l.allowed, |l: &Line| l.paid] {
+                push_decimal(&mut row, claim_lines.iter().map(|(.., line)| total(line)).sum(), 2);
+            }
+            row.push(status.as_bytes());
+            claims_sink.write_row(&row)?;
+
+            for (number, (code, units, line)) in (1..).zip(&claim_lines) {
+                lines += 1;
+                denied += u64::from(line.allowed == 0);
+                row.clear();
+                push_int(&mut row, claims);
+                push_int(&mut row, number);
+                row.push(code.as_bytes());
+                push_int(&mut row, *units);
+                for cents in [line.billed, line.allowed, line.paid, line.patient] {
+                    push_decimal(&mut row, cents, 2);
+                }
+                row.push(if line.allowed == 0 { DENIALS[rng.gen_range(0..DENIALS.len())] } else { "" }.as_bytes());
+                lines_sink.write_row(&row)?;
+            }
+        }
+    }
+
+    presets::finish_tables(args, TABLES, [patients_sink, providers_sink, claims_sink, lines_sink], [patients, providers as u64, claims, lines])?;
+    println!("  {denied} of {lines} claim lines denied");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::testing::{read, temp_dir};
+    use clap::Parser;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn cents(s: &str) -> i64 {
+        let (dollars, cents) = s.split_once('.').unwrap();
+        dollars.parse::<i64>().unwrap() * 100 + cents.parse::<i64>().unwrap()
+    }
+
+    #[test]
+    fn npis_have_valid_check_digits() {
+        let luhn = |npi: &str| {
+            let digits = format!("80840{npi}");
+            let sum: u32 = digits
+                .chars()
+                .rev()
+                .enumerate()
+                .map(|(i, c)| {
+                    let d = c.to_digit(10).unwrap();
+                    if i % 2 == 1 { (2 * d) / 10 + (2 * d) % 10 } else { d }
+                })
+                .sum();
+            sum.is_multiple_of(10)
+        };
+        // The example NPI from the CMS description of the check digit.
+        assert!(luhn("1234567893"));
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let npi = npi(&mut rng);
+            assert!(npi.len() == 10 && npi.starts_with('9') && luhn(&npi), "{npi}");
+        }
+    }
+
+    #[test]
+    fn claims_total_their_lines() {
+        let dir = temp_dir("claims");
+        let template = dir.join("{table}.csv");
+        let args = Args::parse_from(["gen", "--preset", "claims", "--scale-factor", "0.05", "--output", template.to_str().unwrap()]);
+        generate(&args).unwrap();
+
+        let providers: HashMap<String, String> = read(dir.join("providers.csv")).into_iter().map(|p| (p[0].clone(), p[2].clone())).collect();
+        assert_eq!(read(dir.join("patients.csv")).len(), 500);
+        let claims = read(dir.join("claims.csv"));
+        let lines = read(dir.join("claim_lines.csv"));
+        let mut by_claim: HashMap<&str, Vec<&Vec<String>>> = HashMap::new();
+        for l in &lines {
+            let (billed, allowed, paid, patient) = (cents(&l[4]), cents(&l[5]), cents(&l[6]), cents(&l[7]));
+            assert!(allowed <= billed && paid + patient == allowed);
+            assert_eq!(allowed == 0, !l[8].is_empty());
+            by_claim.entry(&l[0]).or_default().push(l);
+        }
+        for c in &claims {
+            let encounter = ENCOUNTERS.iter().find(|e| e.diagnoses.contains(&c[6].as_str())).unwrap();
+            assert!(encounter.specialties.contains(&providers[&c[2]].as_str()));
+            let lines = &by_claim[c[0].as_str()];
+            assert_eq!(lines[0][2], encounter.procedures[0].0);
+            for (column, line_column) in [(8, 4), (9, 5), (10, 6)] {
+                assert_eq!(cents(&c[column]), lines.iter().map(|l| cents(&l[line_column])).sum::<i64>());
+            }
+            assert!(c[4] > c[3]);
+        }
+        assert_eq!(by_claim.len(), claims.len());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,1467 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+
+use crate::backfill::Days;
+use crate::corrupt::Corruption;
+use crate::ddl::DdlDialect;
+use crate::drift::Drift;
+use crate::integrity::Checksum;
+use crate::noise::Noise;
+use crate::locale::{Locale, LocaleMix};
+use crate::presets::Preset;
+use crate::gcp::bigquery::TableRef;
+use crate::record::RecordFormat;
+use crate::shard_sizes::ShardSizes;
+use crate::trend::Change;
+use crate::width::Width;
+
+/// Generates large CSV files filled with random data.
+#[derive(Parser, Debug, Clone, Serialize)]
+#[command(version, about)]
+pub struct Args {
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<Command>,
+
+    /// Output location: a local path, `gs://bucket/object`, `s3://bucket/key`,
+    /// `az://container/blob` or `bq://project:dataset.table`. Paths ending in
+    /// `.sqlite` or `.duckdb` are written as database files, and `-` writes to
+    /// stdout. When generating
+    /// several files it must contain `{n}`, which is replaced by the shard index.
+    /// It may also hold `{shard:05}`, the index zero-padded, `{date}`, the
+    /// day the run started, `{seed}`, the `--seed`, and `{random}`, a
+    /// random suffix of the run.
+    #[arg(short, long, default_value = "large_data_rust.csv")]
+    pub output: String,
+
+    /// Total size to generate across all shards, e.g. `500MB` or `10GB`.
+    /// Defaults to 10GB for files; required for `bq://` tables.
+    #[arg(short, long, value_parser = parse_size)]
+    pub size: Option<u64>,
+
+    /// Stop after this many rows in total, e.g. `10k` or `1M`, even if
+    /// `--size` hasn't been reached. Given alone, it replaces `--size`.
+    #[arg(long, value_parser = parse_count)]
+    pub rows: Option<u64>,
+
+    /// Seed the generated values, so a run with the same seed and settings
+    /// writes the same rows. Shard `n` is seeded with this plus `n`. Doesn't
+    /// apply to `--config` and `--preset`.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Generate the related tables described in this TOML or JSON file,
+    /// each to `--output` with `{table}` replaced by its name, instead of
+    /// `--schema`.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["schema", "size", "rows", "dbt_project", "ddl"])]
+    pub config: Option<PathBuf>,
+
+    /// Built-in schema to generate: `users` (id, name, age), `orders`,
+    /// `contacts` (people at companies), `unicode` for text that stresses
+    /// encodings and quoting, or `access_log` (web server requests).
+    #[arg(long, default_value = "users", value_parser = parse_schema)]
+    pub schema: String,
+
+    /// Generate only these columns of `--schema`, separated by commas, in
+    /// the schema's order.
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',', conflicts_with_all = ["config", "preset"])]
+    pub select: Vec<String>,
+
+    /// Put these columns first, in this order, and the rest after them in
+    /// the schema's order.
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',', conflicts_with_all = ["config", "preset"])]
+    pub order: Vec<String>,
+
+    /// Where names, addresses and phone numbers come from, for the built-in
+    /// schemas and for config columns that don't give a `locale` of their own:
+    /// a locale, or a mix of them with weights, like `en=60,zh=20,ar=20`.
+    #[arg(long, value_name = "LOCALE", default_value = "en", value_parser = LocaleMix::parse)]
+    pub locale: LocaleMix,
+
+    /// Limit generation to this many rows per second across all shards.
+    #[arg(long, value_name = "ROWS_PER_SEC", value_parser = parse_rate)]
+    pub rate: Option<f64>,
+
+    /// Limit uploads to GCS, S3 and Azure to this many bytes per second
+    /// across all shards, after compression, e.g. `200MB/s`, so a run
+    /// sharing a network link doesn't take all of it.
+    #[arg(long, value_name = "BYTES_PER_SEC", value_parser = parse_bandwidth)]
+    pub upload_limit: Option<u64>,
+
+    /// Number of output files to split the data into. With `--config` or
+    /// `--preset`, each table is split into this many.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub shards: u64,
+
+    /// Write every row with the same value of this column to the same one
+    /// of the `--shards` files: the one murmur2 of the value picks, as
+    /// Kafka's default partitioner picks a partition. One thread generates
+    /// the rows of every file.
+    #[arg(
+        long,
+        value_name = "COLUMN",
+        conflicts_with_all = ["config", "preset", "dbt_project", "drift", "cdc_events", "deltas", "rotate_every", "rotate_size"]
+    )]
+    pub shard_by: Option<String>,
+
+    /// How big the shards are against each other: `balanced`, the same
+    /// size; `one-large:FACTOR`, the first FACTOR (default 10) times the
+    /// size of each other one; or `zipf:EXPONENT`, shard `n` in proportion
+    /// to `1 / (n + 1)^EXPONENT` (default 1). Child tables of `--config`
+    /// follow their parents.
+    #[arg(long, value_name = "SPEC", default_value = "balanced", value_parser = ShardSizes::parse, conflicts_with = "shard_by")]
+    pub shard_sizes: ShardSizes,
+
+    /// Change the schema from a shard on, for testing schema evolution:
+    /// `SHARD:add:COLUMN:TYPE` (`int`, `decimal`, `string`, `date` or
+    /// `timestamp`), `SHARD:rename:COLUMN:NEW`, `SHARD:drop:COLUMN` or
+    /// `SHARD:type:COLUMN:TYPE`. Repeat for more changes; later shards keep
+    /// the changes of earlier ones.
+    #[arg(
+        long,
+        value_name = "SPEC",
+        value_parser = crate::drift::parse,
+        conflicts_with_all = ["config", "preset", "dbt_project", "ddl", "bq_load", "sf_copy_into", "cdc_events", "deltas"]
+    )]
+    pub drift: Vec<Drift>,
+
+    /// How many shards to generate and upload at once. Defaults to the number of CPUs.
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+
+    /// How file outputs are written: RFC 4180 CSV with LF or CRLF line
+    /// endings, CSV for Excel, or the backslash-escaped text MySQL's `LOAD
+    /// DATA` reads by default.
+    #[arg(long, value_enum, default_value_t = CsvDialect::Rfc4180)]
+    pub csv_dialect: CsvDialect,
+
+    /// End every line of CSV file outputs with a delimiter, like `a,b,`, as
+    /// some legacy systems require.
+    #[arg(long, conflicts_with_all = ["bq_load", "sf_copy_into"])]
+    pub trailing_delimiter: bool,
+
+    /// Start every line of CSV file outputs with a delimiter, like `,a,b`,
+    /// which some parsers tolerate and others read as an empty first field.
+    #[arg(long, conflicts_with_all = ["bq_load", "sf_copy_into"])]
+    pub leading_delimiter: bool,
+
+    /// Pad or cut the values of columns to a byte width, as `COLUMN=WIDTH`
+    /// or `COLUMN=MIN..MAX` pairs separated by commas, e.g. `name=20`; `*`
+    /// names every other column. With every column of a fixed width, every
+    /// row has the same length, unless a value needs quoting.
+    #[arg(long, value_name = "COLUMN=WIDTH", value_delimiter = ',', value_parser = parse_width)]
+    pub width: Vec<(String, Width)>,
+
+    /// The fraction of values that repeat one of their column's recent
+    /// values, making the data more compressible, from 0 for none to 1.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_probability)]
+    pub repeat_rate: f64,
+
+    /// Repeat as many values as it takes for the rows, as CSV, to compress
+    /// about this many times smaller with gzip, e.g. `5`.
+    #[arg(long, value_name = "RATIO", conflicts_with = "repeat_rate", value_parser = parse_compression_ratio)]
+    pub compression_ratio: Option<f64>,
+
+    /// Leave cells of a column empty but for this fraction of them, as
+    /// `COLUMN=RATE` pairs separated by commas, for sparse tables. A name
+    /// ending in `*`, like `metric_*`, gives every column it starts a rate,
+    /// and the share filled is printed at the end.
+    #[arg(long, value_name = "COLUMN=RATE", value_delimiter = ',', value_parser = parse_column_rate)]
+    pub fill_rate: Vec<(String, f64)>,
+
+    /// What file outputs hold: CSV, Parquet typed by the columns, JSON
+    /// lines, or the Apache combined log lines of `--schema access_log`. `--size` counts the bytes the rows would take as CSV for
+    /// Parquet, and the bytes written otherwise.
+    #[arg(long, value_enum, default_value_t = FileFormat::Csv, conflicts_with_all = ["csv_dialect", "compression", "sf_copy_into"])]
+    pub format: FileFormat,
+
+    /// Compress file outputs; `--size` still counts uncompressed bytes, and
+    /// the file names are used as given, so end them in `.gz` or `.zst`.
+    #[arg(long, value_enum, default_value_t = FileCompression::None)]
+    pub compression: FileCompression,
+
+    /// How `--format parquet` compresses its pages.
+    #[arg(long, value_enum, default_value_t = ParquetCompression::Snappy)]
+    pub parquet_compression: ParquetCompression,
+
+    /// After generating, write a JSON manifest of the files written here,
+    /// with each one's size, row count, MD5 and CRC32C (base64, as GCS
+    /// reports them), first and last keys, and a hash of the settings.
+    #[arg(long, value_name = "LOCATION")]
+    pub manifest: Option<String>,
+
+    /// Before generating, write every setting of the run here as JSON: the
+    /// command line's, with defaults filled in, and the tables or columns
+    /// generated.
+    #[arg(long, value_name = "LOCATION")]
+    pub effective_config: Option<String>,
+
+    /// After generating, bundle the local files written into this `.tar.gz`
+    /// or `.zip` with a `manifest.json` of them and the `config.json` of
+    /// `--effective-config`, to share a reproducible dataset as one file.
+    #[arg(long, value_name = "PATH", value_parser = parse_archive)]
+    pub archive: Option<PathBuf>,
+
+    /// Load column generators and sinks from this shared library, through
+    /// the C ABI described in the readme. Can be given more than once.
+    #[arg(long, value_name = "PATH")]
+    pub plugin: Vec<PathBuf>,
+
+    /// Serve Prometheus metrics while running at `http://ADDRESS/metrics`,
+    /// like `0.0.0.0:9090`: rows and bytes written, flush latency per kind
+    /// of output, failures, and shards waiting for a worker.
+    #[arg(long, value_name = "ADDRESS")]
+    pub metrics: Option<String>,
+
+    /// Export OpenTelemetry spans of loading `--config`, each shard and each
+    /// request to a cloud service to this OTLP/HTTP collector, like
+    /// `http://localhost:4318`.
+    #[arg(long, value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+
+    /// Listen on this Unix socket, answering each connection with a progress
+    /// report like the one SIGUSR1 prints to standard error.
+    #[arg(long, value_name = "PATH")]
+    pub control_socket: Option<PathBuf>,
+
+    /// Write each file's column statistics to a `.stats.json` next to it:
+    /// counts, nulls, distinct values, minimum and maximum, and the mean,
+    /// standard deviation and a histogram of numeric columns.
+    #[arg(long)]
+    pub column_stats: bool,
+
+    /// Write a JSON Schema of each file's rows to a `.schema.json` next to
+    /// it, with the types, ranges, lists and patterns of its columns.
+    #[arg(long)]
+    pub json_schema: bool,
+
+    #[command(flatten)]
+    pub s3: S3Args,
+
+    #[command(flatten)]
+    pub azure: AzureArgs,
+
+    #[command(flatten)]
+    pub pubsub: PubSubArgs,
+
+    #[command(flatten)]
+    pub kafka: KafkaArgs,
+
+    #[command(flatten)]
+    pub kinesis: KinesisArgs,
+
+    #[command(flatten)]
+    pub postgres: PostgresArgs,
+
+    #[command(flatten)]
+    pub clickhouse: ClickHouseArgs,
+
+    #[command(flatten)]
+    pub mysql: MySqlArgs,
+
+    #[command(flatten)]
+    pub database: DatabaseArgs,
+
+    #[command(flatten)]
+    pub tcp: TcpArgs,
+
+    #[command(flatten)]
+    pub retry: RetryArgs,
+
+    #[command(flatten)]
+    pub headers: HeaderArgs,
+
+    #[command(flatten)]
+    pub integrity: IntegrityArgs,
+
+    #[command(flatten)]
+    pub corrupt: CorruptArgs,
+
+    #[command(flatten)]
+    pub preset: PresetArgs,
+
+    #[command(flatten)]
+    pub dbt: DbtArgs,
+
+    #[command(flatten)]
+    pub cdc: CdcArgs,
+
+    #[command(flatten)]
+    pub ddl: DdlArgs,
+
+    #[command(flatten)]
+    pub rotate: RotateArgs,
+
+    #[command(flatten)]
+    pub backfill: BackfillArgs,
+
+    #[command(flatten)]
+    pub bigquery: BigQueryArgs,
+
+    #[command(flatten)]
+    pub snowflake: SnowflakeArgs,
+}
+
+/// Settings for the built-in sets of related tables.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Presets")]
+pub struct PresetArgs {
+    /// Generate a built-in set of related tables, each to `--output` with
+    /// `{table}` replaced by its name.
+    #[arg(long, value_enum, conflicts_with_all = ["config", "schema", "size", "rows", "dbt_project", "ddl"])]
+    pub preset: Option<Preset>,
+
+    /// Multiplies the preset's row counts. Star fact tables grow linearly and
+    /// dimensions more slowly; TPC-H tables grow as `dbgen -s` grows them.
+    #[arg(long, default_value_t = 1.0, value_parser = parse_scale, requires = "preset")]
+    pub scale_factor: f64,
+
+    /// Dimension tables in the star preset: customer, product, store,
+    /// promotion, supplier, employee and channel, in that order.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..=crate::presets::MAX_STAR_DIMENSIONS), requires = "preset")]
+    pub star_dimensions: u64,
+
+    /// The power-law exponent of the graph preset's in- and out-degrees;
+    /// real networks mostly fall between 2 and 3.
+    #[arg(long, default_value_t = 2.1, value_parser = parse_exponent, requires = "preset")]
+    pub graph_exponent: f64,
+
+    /// Give the graph preset's edges a `weight` between 0 and 1.
+    #[arg(long, requires = "preset")]
+    pub graph_weights: bool,
+
+    /// Give the graph preset's edges a `created_at` time.
+    #[arg(long, requires = "preset")]
+    pub graph_timestamps: bool,
+
+    /// The fractions of clickstream sessions that go on from landing to
+    /// viewing a product, from there to adding it to the cart, to beginning
+    /// checkout and to purchasing.
+    #[arg(
+        long,
+        value_name = "RATES",
+        value_delimiter = ',',
+        default_value = "0.6,0.35,0.5,0.7",
+        value_parser = parse_probability,
+        requires = "preset"
+    )]
+    pub clickstream_funnel: Vec<f64>,
+
+    /// The chance of each field of an entity resolution record varying from
+    /// the person's real details.
+    #[arg(long, default_value_t = 0.3, value_parser = parse_probability, requires = "preset")]
+    pub entity_noise: f64,
+
+    /// Seconds between the readings of each IoT preset device.
+    #[arg(long, value_name = "SECONDS", default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..=3600), requires = "preset")]
+    pub iot_interval: u64,
+
+    /// The fraction of the transactions preset's transactions that are fraud.
+    #[arg(long, default_value_t = 0.01, value_parser = parse_probability, requires = "preset")]
+    pub fraud_rate: f64,
+
+    /// The fraction of the ads preset's impressions that are clicked, on
+    /// average over its campaigns.
+    #[arg(long, default_value_t = 0.02, value_parser = parse_probability, requires = "preset")]
+    pub ad_ctr: f64,
+
+    /// The fraction of the ads preset's clicks that convert within the
+    /// attribution window, on average over its campaigns.
+    #[arg(long, default_value_t = 0.05, value_parser = parse_probability, requires = "preset")]
+    pub ad_cvr: f64,
+
+    /// Days after a click that a conversion is attributed to it.
+    #[arg(long, value_name = "DAYS", default_value_t = 7, value_parser = clap::value_parser!(u64).range(1..=90), requires = "preset")]
+    pub ad_attribution_days: u64,
+
+    /// The most levels of the HR preset's hierarchy, with the chief
+    /// executive at level 1.
+    #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u64).range(2..=20), requires = "preset")]
+    pub hr_depth: u64,
+
+    /// The average direct reports of an HR preset manager.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..=100), requires = "preset")]
+    pub hr_span: u64,
+
+    /// The numeric features of each ML preset sample.
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u64).range(1..=1000), requires = "preset")]
+    pub ml_features: u64,
+
+    /// How many of the ML preset's features predict the label.
+    #[arg(long, default_value_t = 5, requires = "preset")]
+    pub ml_informative: u64,
+
+    /// The fraction of the ML preset's samples labeled 1.
+    #[arg(long, default_value_t = 0.5, value_parser = parse_probability, requires = "preset")]
+    pub ml_positive_rate: f64,
+
+    /// The standard deviation of the noise in the ML preset's label score,
+    /// whose informative part has a standard deviation of 1.
+    #[arg(long, default_value_t = 0.5, value_parser = parse_noise, requires = "preset")]
+    pub ml_noise: f64,
+}
+
+/// Settings for columns consumers can check the rows they receive against.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Integrity")]
+pub struct IntegrityArgs {
+    /// Add a `row_checksum` column with this checksum of each row's other
+    /// fields, each followed by a 0x1F byte.
+    #[arg(long, value_enum, value_name = "ALGORITHM")]
+    pub row_checksum: Option<Checksum>,
+
+    /// Add a `chain_hash` column with the SHA-256 of the previous row's
+    /// `chain_hash` in hex and then the row's fields, starting from 64
+    /// zeros in each file, so that dropped or reordered rows break the chain.
+    #[arg(long)]
+    pub hash_chain: bool,
+}
+
+/// Settings for making some of the values or rows written hard to parse.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Dirty data")]
+pub struct CorruptArgs {
+    /// The fraction of text values to embed a comma, a double quote and a
+    /// CRLF line break in. They're quoted properly, but catch out parsers
+    /// that split lines or fields by hand.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_probability)]
+    pub torture_rate: f64,
+
+    /// The fraction of values to format the way people type them, with
+    /// stray spaces, different casing, thousands separators or other date
+    /// formats, but meaning the same. Only for `--format csv`, as JSON
+    /// lines and Parquet can't hold such values.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_probability)]
+    pub noise_rate: f64,
+
+    /// The comma-separated kinds of `--noise-rate` formatting, picked between
+    /// at random among those that fit each column's type.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "KINDS",
+        default_values_t = [Noise::Whitespace, Noise::Casing, Noise::Thousands, Noise::DateFormats],
+        requires = "noise_rate"
+    )]
+    pub noise_kinds: Vec<Noise>,
+
+    /// The fraction of CSV rows to corrupt, for testing loaders' error
+    /// handling. Each file's corrupted rows are listed in its manifest.
+    #[arg(long, default_value_t = 0.0, value_parser = parse_probability)]
+    pub corrupt_rate: f64,
+
+    /// The comma-separated ways rows are corrupted, picked between at random.
+    /// The encodings are only broken, with `invalid-utf8` and `latin1`, when
+    /// they're named.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "KINDS",
+        default_values_t = [
+            Corruption::MissingFields,
+            Corruption::ExtraFields,
+            Corruption::UnterminatedQuote,
+            Corruption::ControlCharacters,
+        ],
+        requires = "corrupt_rate"
+    )]
+    pub corrupt_kinds: Vec<Corruption>,
+
+    /// Replace this fraction of a typed column's values with ones that
+    /// don't parse as its type, such as `twenty` or nothing for an integer,
+    /// as `COLUMN=RATE`. Repeat or separate with commas for more columns;
+    /// the number put in each is printed at the end. Only for `--format
+    /// csv`, as JSON lines and Parquet can't hold such values.
+    #[arg(long, value_name = "COLUMN=RATE", value_delimiter = ',', value_parser = parse_column_rate)]
+    pub mismatch: Vec<(String, f64)>,
+
+    /// Make the same changes to the same rows on every run, chosen by this
+    /// number. Whichever options are given, every change made to a file is
+    /// listed in a CSV next to it, named as it is with `.manifest.csv` added.
+    #[arg(long, value_name = "SEED")]
+    pub dirty_seed: Option<u64>,
+}
+
+/// Settings for running as a daemon that rotates its output files.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "File rotation")]
+pub struct RotateArgs {
+    /// Keep generating, like an application writing logs, and start a new
+    /// file this often, e.g. `30s`, `5m` or `1h`. `--output` names each file
+    /// with `{time}`, when it was opened, or `{n}`, its number. The run goes
+    /// on until it's stopped, or reaches `--rows` or `--size` in total.
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        conflicts_with_all = ["config", "preset", "dbt_project", "cdc_events", "deltas"]
+    )]
+    pub rotate_every: Option<Duration>,
+
+    /// Start a new file once one holds this much, e.g. `100MB`, like
+    /// `--rotate-every` and together with it.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_nonzero_size,
+        conflicts_with_all = ["config", "preset", "dbt_project", "cdc_events", "deltas"]
+    )]
+    pub rotate_size: Option<u64>,
+
+    /// Delete the oldest rotated files so only this many are kept.
+    #[arg(long, value_name = "FILES", value_parser = clap::value_parser!(u64).range(1..))]
+    pub retain: Option<u64>,
+}
+
+impl RotateArgs {
+    /// Whether the output is rotated.
+    pub fn rotates(&self) -> bool {
+        self.rotate_every.is_some() || self.rotate_size.is_some()
+    }
+}
+
+/// Settings for generating a table's history, a partition per day.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Backfill")]
+pub struct BackfillArgs {
+    /// Generate a partition for each day from START up to but not including
+    /// END, as `START..END` in `YYYY-MM-DD` dates, each with its dates and
+    /// timestamps on that day. `--output` names each with `{day}`, its date.
+    #[arg(
+        long,
+        value_name = "START..END",
+        value_parser = Days::parse,
+        requires = "per_day",
+        conflicts_with_all = ["config", "preset", "size", "rows", "dbt_project", "cdc_events", "deltas", "rotate_every", "rotate_size"]
+    )]
+    pub backfill: Option<Days>,
+
+    /// How much each day of `--backfill` holds at its start, e.g. `5GB`.
+    #[arg(long, value_name = "SIZE", value_parser = parse_nonzero_size, requires = "backfill")]
+    pub per_day: Option<u64>,
+
+    /// How much more a day holds a year later, e.g. `0.5` for half as much
+    /// again, growing a little every day. Negative rates shrink.
+    #[arg(long, value_name = "RATE", default_value_t = 0.0, allow_negative_numbers = true, value_parser = parse_growth)]
+    pub growth: f64,
+
+    /// Change the volume of some periods, each day of `--backfill` or each
+    /// file of a `--rate`d `--rotate-every` stream, on top of `--growth`:
+    /// `linear:RATE` adds RATE times the first day's volume a year,
+    /// `step:YYYY-MM-DD=FACTOR` multiplies it from a day on, and
+    /// `spike:MM-DD=FACTOR`, `weekday:sat=FACTOR` and `month:dec=FACTOR` on
+    /// that day, weekday or month of every year. Repeat for more; they
+    /// multiply.
+    #[arg(long, value_name = "SPEC", value_parser = crate::trend::parse)]
+    pub trend: Vec<Change>,
+}
+
+/// Settings for writing table DDL next to the data.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Table DDL")]
+pub struct DdlArgs {
+    /// Write a `CREATE TABLE` for the schema in each of these comma-separated
+    /// dialects, as `<table>.<dialect>.sql` in the output's directory.
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "DIALECTS")]
+    pub ddl: Vec<DdlDialect>,
+
+    /// Table name used in the DDL. Defaults to the schema's name.
+    #[arg(long, value_name = "TABLE", requires = "ddl")]
+    pub ddl_table: Option<String>,
+}
+
+/// Settings for writing dbt seeds.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "dbt seeds")]
+pub struct DbtArgs {
+    /// Write the data as a seed of the dbt project in `DIR`: the rows go to
+    /// `DIR/seeds/<schema>.csv` next to a `<schema>.yml` declaring column types
+    /// and tests. `--rows` defaults to 1,000.
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["output", "shards", "compression", "csv_dialect", "format"])]
+    pub dbt_project: Option<PathBuf>,
+}
+
+/// Settings for generating a change data capture stream.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Change data capture")]
+pub struct CdcArgs {
+    /// Write `--rows` rows to `--output` as a snapshot, then this many
+    /// insert, update and delete events against them to `--cdc-output`.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        value_parser = parse_count,
+        requires_all = ["cdc_output", "rows"],
+        conflicts_with_all = ["config", "preset", "size", "shards", "dbt_project", "deltas"]
+    )]
+    pub cdc_events: Option<u64>,
+
+    /// Where the events go, one Debezium-style JSON envelope per line.
+    #[arg(long, value_name = "PATH", requires = "cdc_events")]
+    pub cdc_output: Option<String>,
+
+    /// Relative weights of inserts, updates and deletes, in events and deltas.
+    #[arg(long, value_name = "I,U,D", value_delimiter = ',', default_value = "20,70,10")]
+    pub cdc_mix: Vec<u32>,
+
+    /// Write `--rows` rows to `--output` as a snapshot, then a file of
+    /// changes to them for each of this many days to `--delta-output`.
+    #[arg(
+        long,
+        value_name = "DAYS",
+        requires_all = ["delta_output", "rows"],
+        conflicts_with_all = ["config", "preset", "size", "shards", "dbt_project"]
+    )]
+    pub deltas: Option<u64>,
+
+    /// Where each day's delta goes, with `{day}` replaced by its date.
+    #[arg(long, value_name = "PATH", requires = "deltas")]
+    pub delta_output: Option<String>,
+
+    /// Changes in each delta.
+    #[arg(long, value_name = "COUNT", value_parser = parse_count, default_value = "1000", requires = "deltas")]
+    pub delta_rows: u64,
+
+    /// The date of the first delta, `YYYY-MM-DD`; defaults to today.
+    #[arg(long, value_name = "DATE", requires = "deltas")]
+    pub delta_start: Option<String>,
+}
+
+/// Settings for loading `gs://` outputs into BigQuery once they are written.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "BigQuery load")]
+pub struct BigQueryArgs {
+    /// Load the generated files into this table (`project:dataset.table`)
+    /// once generation finishes. Requires a `gs://` output.
+    #[arg(long, value_name = "TABLE")]
+    pub bq_load: Option<TableRef>,
+
+    /// Location of the dataset, e.g. `US` or `europe-west1`.
+    #[arg(long, requires = "bq_load")]
+    pub bq_location: Option<String>,
+
+    /// What to do if the table already has data.
+    #[arg(long, value_enum, default_value_t = WriteDisposition::Append, requires = "bq_load")]
+    pub bq_write_disposition: WriteDisposition,
+
+    /// Fail instead of creating the table when it does not exist.
+    #[arg(long, requires = "bq_load")]
+    pub bq_no_create: bool,
+
+    /// Partition a newly created table by ingestion time with this granularity.
+    #[arg(long, value_enum, requires = "bq_load")]
+    pub bq_partition: Option<Partitioning>,
+
+    /// Comma-separated columns to cluster a newly created table by (at most 4).
+    #[arg(long, value_delimiter = ',', num_args = 1..=4, requires = "bq_load")]
+    pub bq_cluster_by: Vec<String>,
+}
+
+/// Settings for copying cloud storage outputs into Snowflake through an
+/// external stage once they are written.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Snowflake load")]
+pub struct SnowflakeArgs {
+    /// Run `COPY INTO` this table (`database.schema.table`) once generation
+    /// finishes. The output must be under the location of `--sf-stage`.
+    #[arg(long, value_name = "TABLE", requires = "sf_stage")]
+    pub sf_copy_into: Option<String>,
+
+    /// External stage over the output's bucket or container, e.g. `@db.schema.stage`.
+    #[arg(long, value_name = "STAGE", requires = "sf_copy_into")]
+    pub sf_stage: Option<String>,
+
+    /// Create the table from the column definitions if it does not exist.
+    #[arg(long, requires = "sf_copy_into")]
+    pub sf_create_table: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WriteDisposition {
+    Append,
+    Truncate,
+    Empty,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Partitioning {
+    Hour,
+    Day,
+    Month,
+    Year,
+}
+
+/// Settings for `s3://` outputs.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "S3 output")]
+pub struct S3Args {
+    /// Size of each multipart upload part. S3 requires at least 5MB.
+    #[arg(long, default_value = "16MB", value_parser = parse_part_size)]
+    pub s3_part_size: u64,
+
+    /// Parts uploaded concurrently per object.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    pub s3_concurrency: u16,
+
+    /// Storage class for created objects, e.g. `STANDARD_IA` or `GLACIER_IR`.
+    #[arg(long)]
+    pub s3_storage_class: Option<String>,
+
+    /// Server-side encryption: `AES256` or `aws:kms`.
+    #[arg(long)]
+    pub s3_sse: Option<String>,
+
+    /// KMS key for `--s3-sse aws:kms`; the bucket default is used if omitted.
+    #[arg(long, requires = "s3_sse")]
+    pub s3_sse_kms_key_id: Option<String>,
+}
+
+/// Settings for `az://` outputs.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Azure Blob Storage output")]
+pub struct AzureArgs {
+    /// Size of each staged block. Azure allows up to 4000MB.
+    #[arg(long, default_value = "16MB", value_parser = parse_block_size)]
+    pub az_block_size: u64,
+
+    /// Blocks staged concurrently per blob.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    pub az_concurrency: u16,
+}
+
+/// Settings for `pubsub://` outputs.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Pub/Sub output")]
+pub struct PubSubArgs {
+    /// Column whose value becomes each message's ordering key.
+    #[arg(long, value_name = "COLUMN")]
+    pub pubsub_ordering_key: Option<String>,
+
+    /// Comma-separated columns to also send as message attributes.
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    pub pubsub_attributes: Vec<String>,
+
+    /// Messages per publish request. Pub/Sub allows up to 1000.
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u16).range(1..=1000))]
+    pub pubsub_batch_messages: u16,
+
+    /// Message bytes per publish request. Pub/Sub allows 10MB per request
+    /// after base64, so at most 7MB.
+    #[arg(long, default_value = "1MB", value_parser = parse_pubsub_batch_bytes)]
+    pub pubsub_batch_bytes: u64,
+}
+
+/// Settings for `kafka://` outputs.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Kafka output")]
+pub struct KafkaArgs {
+    /// How each row is serialized into a message value.
+    #[arg(long, value_enum, default_value_t = RecordFormat::Csv)]
+    pub kafka_format: RecordFormat,
+
+    /// Column whose value becomes each message's key.
+    #[arg(long, value_name = "COLUMN")]
+    pub kafka_key: Option<String>,
+
+    /// How keyed messages are assigned to partitions; unkeyed messages are
+    /// always spread round-robin.
+    #[arg(long, value_enum, default_value_t = KafkaPartitioner::Murmur2)]
+    pub kafka_partitioner: KafkaPartitioner,
+
+    #[arg(long, value_enum, default_value_t = KafkaCompression::None)]
+    pub kafka_compression: KafkaCompression,
+
+    /// Acknowledgements to wait for: `none`, `one` (the leader) or `all` in-sync replicas.
+    #[arg(long, value_enum, default_value_t = KafkaAcks::All)]
+    pub kafka_acks: KafkaAcks,
+
+    /// Messages per produce request.
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u32).range(1..))]
+    pub kafka_batch_messages: u32,
+
+    /// Schema Registry URL. With `--kafka-format avro`, the schema is
+    /// registered for the `{topic}-value` subject and every message carries its id.
+    #[arg(long, value_name = "URL")]
+    pub kafka_schema_registry: Option<String>,
+}
+
+/// Settings for `kinesis://` outputs.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Kinesis output")]
+pub struct KinesisArgs {
+    /// How each row is serialized into a record.
+    #[arg(long, value_enum, default_value_t = RecordFormat::Csv)]
+    pub kinesis_format: RecordFormat,
+
+    /// Column whose value becomes each record's partition key. Without it,
+    /// records get random keys and spread evenly over the stream's shards.
+    #[arg(long, value_name = "COLUMN")]
+    pub kinesis_partition_key: Option<String>,
+
+    /// Number of shards in the stream. Puts are then paced to the shard
+    /// limits of 1000 records and 1MB per second per shard.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub kinesis_shard_count: Option<u32>,
+}
+
+/// Settings for `postgres://` outputs.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "PostgreSQL output")]
+pub struct PostgresArgs {
+    /// Table to copy rows into, optionally schema-qualified.
+    #[arg(long, value_name = "TABLE")]
+    pub pg_table: Option<String>,
+
+    /// Create the table from the column definitions if it does not exist.
+    #[arg(long, requires = "pg_table")]
+    pub pg_create_table: bool,
+}
+
+/// Settings for `clickhouse://` outputs.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "ClickHouse output")]
+pub struct ClickHouseArgs {
+    /// Table to insert rows into, optionally `database.table`.
+    #[arg(long, value_name = "TABLE")]
+    pub ch_table: Option<String>,
+
+    /// Create the table as a MergeTree from the column definitions if it does not exist.
+    #[arg(long, requires = "ch_table")]
+    pub ch_create_table: bool,
+
+    /// CSV bytes per INSERT; each one becomes a part on the server.
+    #[arg(long, default_value = "64MB", value_parser = parse_nonzero_size)]
+    pub ch_block_size: u64,
+
+    /// Talk HTTPS, on port 8443 unless the URL gives one.
+    #[arg(long)]
+    pub ch_tls: bool,
+}
+
+/// Settings for `mysql://` outputs.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "MySQL output")]
+pub struct MySqlArgs {
+    /// Table to insert rows into, optionally `database.table`.
+    #[arg(long, value_name = "TABLE")]
+    pub mysql_table: Option<String>,
+
+    /// Create the table from the column definitions if it does not exist.
+    #[arg(long, requires = "mysql_table")]
+    pub mysql_create_table: bool,
+
+    /// Rows per multi-row INSERT, capped at what fits in 65,535 placeholders.
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub mysql_batch_rows: u64,
+}
+
+/// Settings for `tcp://` and `tls://` outputs.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "TCP output")]
+pub struct TcpArgs {
+    /// How each row is written as a line.
+    #[arg(long, value_enum, default_value_t = RecordFormat::Csv)]
+    pub tcp_format: RecordFormat,
+
+    /// PEM file of CA certificates to trust for `tls://` outputs instead of
+    /// the bundled public roots, e.g. a collector's private CA.
+    #[arg(long, value_name = "PATH")]
+    pub tcp_ca_file: Option<PathBuf>,
+}
+
+/// How CSV files begin.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "CSV headers")]
+pub struct HeaderArgs {
+    /// Which CSV files start with a header line.
+    #[arg(long, value_enum, default_value_t = HeaderMode::Every)]
+    pub header: HeaderMode,
+
+    /// Names the header gives columns instead of their own, as
+    /// `COLUMN=NAME` pairs separated by commas, e.g. `id=user_id`.
+    #[arg(long, value_name = "COLUMN=NAME", value_delimiter = ',', value_parser = parse_header_name)]
+    pub header_names: Vec<(String, String)>,
+
+    /// Start each CSV file with a `#` comment giving the generator's version,
+    /// a hash of the columns, and the seed and shard the rows came from.
+    #[arg(long)]
+    pub metadata_comment: bool,
+}
+
+impl HeaderArgs {
+    /// The lines before the first row of every CSV file, for loaders to
+    /// skip, or `None` if `--header first` gives shards different ones.
+    pub fn leading_lines(&self) -> Option<u64> {
+        let header = match self.header {
+            HeaderMode::Every => 1,
+            HeaderMode::First => return None,
+            HeaderMode::None => 0,
+        };
+        Some(header + u64::from(self.metadata_comment))
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeaderMode {
+    /// Every file, shard and table.
+    Every,
+    /// Only shard 0 of each table, so the shards concatenate into one file.
+    First,
+    /// No file; loaders then take the columns in schema order.
+    None,
+}
+
+/// How calls to services are retried, and what happens to rows they
+/// couldn't deliver.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "Retries")]
+pub struct RetryArgs {
+    /// Attempts at each call to a service before it counts as failed.
+    #[arg(long, value_name = "N", default_value_t = 6, value_parser = clap::value_parser!(u32).range(1..))]
+    pub retry_attempts: u32,
+
+    /// The wait before the second attempt, doubled before each one after it.
+    /// Each wait is jittered to between half and all of it, so shards that
+    /// failed together don't retry together.
+    #[arg(long, value_name = "DURATION", default_value = "0.5s", value_parser = parse_duration)]
+    pub retry_delay: Duration,
+
+    /// The longest wait between attempts.
+    #[arg(long, value_name = "DURATION", default_value = "30s", value_parser = parse_duration)]
+    pub retry_max_delay: Duration,
+
+    /// Append the records Pub/Sub, Kafka, Kinesis and TCP outputs couldn't
+    /// deliver after every attempt to this file, one JSON object a line, and
+    /// go on with the run instead of failing it.
+    #[arg(long, value_name = "PATH")]
+    pub dead_letter: Option<PathBuf>,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Serve generated data over HTTP instead of writing files, e.g.
+    /// `GET /generate?rows=1M&schema=orders&format=jsonl`.
+    Serve(ServeArgs),
+    /// Serve the `GenerateRows` gRPC service described by `proto/generator.proto`.
+    Grpc(GrpcArgs),
+    /// Read generated CSV back and check it against its schema or config:
+    /// types, ranges, row counts, unique keys and foreign keys. Exits with
+    /// an error if anything doesn't match.
+    Validate(ValidateArgs),
+    /// Compare two runs' CSV: which files each has, their headers, their
+    /// row counts and their rows, in order or matched by `--key`. Exits
+    /// with an error if they differ.
+    Diff(DiffArgs),
+    /// Rewrite a CSV file in another format, or compressed, through the
+    /// same writers as generated data, with column types inferred from it.
+    Convert(ConvertArgs),
+    /// Sort a CSV file by one or more columns, in bounded memory, spilling
+    /// sorted runs to disk and merging them.
+    Sort(SortArgs),
+    /// Predict what the run the other options describe would write: its
+    /// rows, how long it would take and, for cloud outputs, roughly what it
+    /// would cost, from a sample of rows generated in memory.
+    Estimate(EstimateArgs),
+    /// Print the first rows of what the run the other options describe
+    /// would generate, without writing its output, to try out a schema or
+    /// config quickly.
+    Preview(PreviewArgs),
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize)]
+pub struct ServeArgs {
+    /// Port to listen on.
+    #[arg(long, default_value_t = 8080)]
+    pub port: u16,
+
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1")]
+    pub bind: String,
+
+    /// Largest `rows` a single request may ask for.
+    #[arg(long, default_value = "1G", value_parser = parse_count)]
+    pub max_rows: u64,
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize)]
+pub struct GrpcArgs {
+    /// Port to listen on.
+    #[arg(long, default_value_t = 50051)]
+    pub port: u16,
+
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1")]
+    pub bind: String,
+
+    /// Largest `rows` a single call may ask for.
+    #[arg(long, default_value = "1G", value_parser = parse_count)]
+    pub max_rows: u64,
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize)]
+pub struct ValidateArgs {
+    /// A CSV file, or a directory of the files of a run, which may be
+    /// gzipped.
+    pub path: PathBuf,
+
+    /// Check the related tables of this TOML file, finding each in the
+    /// directory by its name: `orders.csv`, or shards like `orders-0.csv`.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["schema", "rows"])]
+    pub config: Option<PathBuf>,
+
+    /// The built-in schema the files were generated with.
+    #[arg(long, default_value = "users", value_parser = parse_schema)]
+    pub schema: String,
+
+    /// The locale or mix of locales the files were generated in, which the
+    /// values of names, cities and the like are checked against.
+    #[arg(long, value_name = "LOCALE", default_value = "en", value_parser = LocaleMix::parse)]
+    pub locale: LocaleMix,
+
+    /// The rows there should be over all the files. Config tables with
+    /// `rows` are checked against that.
+    #[arg(long, value_parser = parse_count)]
+    pub rows: Option<u64>,
+
+    /// Print this many problems, and only count the rest.
+    #[arg(long, default_value_t = 20)]
+    pub max_problems: u64,
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize)]
+pub struct DiffArgs {
+    /// A CSV file, or a directory of them, which may be gzipped.
+    pub a: PathBuf,
+
+    /// The file or directory to compare with it; files of a directory are
+    /// compared with the files of the same name.
+    pub b: PathBuf,
+
+    /// Match rows by this column rather than by position, for runs whose
+    /// rows come in a different order.
+    #[arg(long, value_name = "COLUMN")]
+    pub key: Option<String>,
+
+    /// Print this many differences, and only count the rest.
+    #[arg(long, default_value_t = 20)]
+    pub max_differences: u64,
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize)]
+pub struct ConvertArgs {
+    /// The CSV file to convert, which may be gzipped.
+    pub input: PathBuf,
+
+    /// What to write.
+    #[arg(long, value_enum, default_value_t = FileFormat::Parquet)]
+    pub to: FileFormat,
+
+    /// Where to write it, as for `--output` of a run, including `gs://`,
+    /// `s3://`, `bq://` and the other services.
+    #[arg(short, long)]
+    pub output: String,
+
+    /// Compress CSV output.
+    #[arg(long, value_enum, default_value_t = FileCompression::None)]
+    pub compression: FileCompression,
+
+    /// How `--to parquet` compresses its pages.
+    #[arg(long, value_enum, default_value_t = ParquetCompression::Snappy)]
+    pub parquet_compression: ParquetCompression,
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize)]
+pub struct SortArgs {
+    /// The CSV file to sort, which may be gzipped.
+    pub input: PathBuf,
+
+    /// The columns to sort by, the first deciding first.
+    #[arg(long, value_name = "COLUMN", required = true, value_delimiter = ',')]
+    pub by: Vec<String>,
+
+    /// Sort from the greatest key down.
+    #[arg(long)]
+    pub descending: bool,
+
+    /// Where to write the sorted file, as for `--output` of a run.
+    #[arg(short, long)]
+    pub output: String,
+
+    /// Compress the sorted file.
+    #[arg(long, value_enum, default_value_t = FileCompression::None)]
+    pub compression: FileCompression,
+
+    /// How much of the file to sort in memory at a time, e.g. `512MB`.
+    #[arg(long, default_value = "256MB", value_parser = parse_size)]
+    pub memory: u64,
+
+    /// Where to spill sorted runs, which take as much space as the file.
+    /// Defaults to the system's temporary directory.
+    #[arg(long, value_name = "DIR")]
+    pub temp_dir: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize)]
+pub struct EstimateArgs {
+    /// Rows to generate to measure the size of a row and how fast rows are
+    /// made.
+    #[arg(long, default_value_t = 5000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub sample_rows: u64,
+
+    /// US dollars a GB-month of storage costs, instead of the service's
+    /// list price.
+    #[arg(long, value_name = "USD")]
+    pub storage_price: Option<f64>,
+
+    /// US dollars a GB downloaded costs, instead of the service's list
+    /// price.
+    #[arg(long, value_name = "USD")]
+    pub egress_price: Option<f64>,
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize)]
+pub struct PreviewArgs {
+    /// Rows of each table to print.
+    #[arg(long, value_name = "N", default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub head: u64,
+
+    /// Print CSV rather than aligned columns.
+    #[arg(long)]
+    pub raw: bool,
+}
+
+/// Settings for `.sqlite` and `.duckdb` outputs.
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[command(next_help_heading = "SQLite and DuckDB output")]
+pub struct DatabaseArgs {
+    /// Table the rows are inserted into; the file is recreated on every run.
+    #[arg(long, value_name = "TABLE", default_value = "data")]
+    pub db_table: String,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CsvDialect {
+    /// Fields quoted as RFC 4180 describes, with LF line endings.
+    Rfc4180,
+    /// RFC 4180 to the letter: quoted as it describes, with CRLF line endings.
+    Rfc4180Strict,
+    /// What Excel opens without mangling text: UTF-8 with a byte order mark
+    /// so accents and CJK aren't read as the system code page, CRLF line
+    /// endings, and every field that isn't a number quoted.
+    Excel,
+    /// Backslash escapes, `\N` for NULL and LF line endings, as `LOAD DATA` expects.
+    Mysql,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileFormat {
+    Csv,
+    Parquet,
+    /// One JSON object per line, keyed by column name.
+    Jsonl,
+    /// Apache combined log lines.
+    Combined,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileCompression {
+    None,
+    Gzip,
+    /// Zstandard; needs the `zstd` feature.
+    Zstd,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParquetCompression {
+    None,
+    Snappy,
+    Gzip,
+    /// Zstandard; needs the `zstd` feature.
+    Zstd,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KafkaPartitioner {
+    /// murmur2 of the key, matching the Java client's default partitioner.
+    Murmur2,
+    /// Spread messages over the available partitions, ignoring keys.
+    RoundRobin,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KafkaCompression {
+    None,
+    Gzip,
+    Snappy,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KafkaAcks {
+    None,
+    One,
+    All,
+}
+
+fn parse_nonzero_size(s: &str) -> Result<u64, String> {
+    match parse_size(s)? {
+        0 => Err(format!("{s} must be more than 0 bytes")),
+        size => Ok(size),
+    }
+}
+
+fn parse_bandwidth(s: &str) -> Result<u64, String> {
+    parse_nonzero_size(s.trim().strip_suffix("/s").unwrap_or(s))
+}
+
+fn parse_pubsub_batch_bytes(s: &str) -> Result<u64, String> {
+    let size = parse_size(s)?;
+    if size == 0 || size > 7 << 20 {
+        return Err(format!("{s} must be between 1 byte and 7MB"));
+    }
+    Ok(size)
+}
+
+fn parse_block_size(s: &str) -> Result<u64, String> {
+    let size = parse_size(s)?;
+    if size == 0 || size > 4000 << 20 {
+        return Err("Azure blocks must be between 1 byte and 4000MB".to_string());
+    }
+    Ok(size)
+}
+
+fn parse_part_size(s: &str) -> Result<u64, String> {
+    let size = parse_size(s)?;
+    if size < 5 << 20 {
+        return Err("S3 parts must be at least 5MB".to_string());
+    }
+    Ok(size)
+}
+
+fn parse_schema(s: &str) -> Result<String, String> {
+    match crate::schema::builtin(s, Locale::En) {
+        Some(_) => Ok(s.to_string()),
+        None => Err(format!("no schema named {s}; try users, orders, contacts, unicode or access_log")),
+    }
+}
+
+fn parse_scale(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(scale) if scale > 0.0 && scale.is_finite() => Ok(scale),
+        _ => Err(format!("{s} is not a positive scale factor")),
+    }
+}
+
+fn parse_exponent(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(exponent) if exponent > 1.0 && exponent.is_finite() => Ok(exponent),
+        _ => Err(format!("{s} is not a power-law exponent greater than 1")),
+    }
+}
+
+fn parse_probability(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+        _ => Err(format!("{s} is not a fraction between 0 and 1")),
+    }
+}
+
+fn parse_growth(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate > -1.0 && rate.is_finite() => Ok(rate),
+        _ => Err(format!("{s} is not a growth rate greater than -1")),
+    }
+}
+
+fn parse_compression_ratio(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(ratio) if ratio >= 1.0 && ratio.is_finite() => Ok(ratio),
+        _ => Err(format!("{s} is not a compression ratio of 1 or more")),
+    }
+}
+
+fn parse_column_rate(s: &str) -> Result<(String, f64), String> {
+    let (column, rate) = s.split_once('=').ok_or_else(|| format!("{s} is not COLUMN=RATE"))?;
+    Ok((column.to_string(), parse_probability(rate)?))
+}
+
+fn parse_header_name(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((column, name)) if !name.is_empty() => Ok((column.to_string(), name.to_string())),
+        _ => Err(format!("{s} is not COLUMN=NAME")),
+    }
+}
+
+fn parse_archive(s: &str) -> Result<PathBuf, String> {
+    match [".tar.gz", ".tgz", ".zip"].iter().any(|extension| s.ends_with(extension)) {
+        true => Ok(PathBuf::from(s)),
+        false => Err(format!("{s} should end in .tar.gz, .tgz or .zip")),
+    }
+}
+
+fn parse_width(s: &str) -> Result<(String, Width), String> {
+    let error = || format!("{s} is not COLUMN=WIDTH or COLUMN=MIN..MAX");
+    let (column, width) = s.split_once('=').ok_or_else(error)?;
+    let (min, max) = width.split_once("..").unwrap_or((width, width));
+    match (min.parse(), max.parse()) {
+        (Ok(min), Ok(max)) if min <= max && max > 0 => Ok((column.to_string(), Width { min, max })),
+        _ => Err(error()),
+    }
+}
+
+fn parse_noise(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(noise) if noise >= 0.0 && noise.is_finite() => Ok(noise),
+        _ => Err(format!("{s} is not a non-negative standard deviation")),
+    }
+}
+
+fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        _ => Err(format!("{s} is not a positive number of rows per second")),
+    }
+}
+
+/// Parses a size such as `512KB`, `10GB` or `1.5TB`. Units are powers of 1024;
+/// a bare number is a count of bytes.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid size: {s}"))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        other => return Err(format!("unknown size unit: {other}")),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parses a count such as `5000`, `250k` or `1.5M`. Unlike sizes, these
+/// units are powers of 1000.
+pub fn parse_count(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid count: {s}"))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" => 1,
+        "K" => 1_000,
+        "M" => 1_000_000,
+        "G" | "B" => 1_000_000_000,
+        other => return Err(format!("unknown count unit: {other}")),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Parses a duration such as `90s`, `5m`, `1.5h` or `1d`; a bare number is
+/// seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid duration: {s}"))?;
+    let seconds = match unit.trim() {
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        "d" => 86_400.0,
+        other => return Err(format!("unknown duration unit: {other}")),
+    };
+    match number * seconds {
+        seconds if seconds > 0.0 => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!("{s} must be longer than 0 seconds")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bandwidths_are_sizes_per_second() {
+        assert_eq!(parse_bandwidth("200MB/s"), Ok(200 << 20));
+        assert_eq!(parse_bandwidth("1GB"), Ok(1 << 30));
+        assert!(parse_bandwidth("0MB/s").is_err());
+    }
+
+    #[test]
+    fn widths_are_exact_or_bounded() {
+        assert_eq!(parse_width("name=20"), Ok(("name".to_string(), Width { min: 20, max: 20 })));
+        assert_eq!(parse_width("*=4..8"), Ok(("*".to_string(), Width { min: 4, max: 8 })));
+        assert!(parse_width("name=8..4").is_err());
+        assert!(parse_width("name=0").is_err());
+        assert!(parse_width("name").is_err());
+    }
+
+    #[test]
+    fn durations_take_units() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1.5h"), Ok(Duration::from_secs(5400)));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("5 weeks").is_err());
+    }
+
+    #[test]
+    fn counts_use_decimal_units() {
+        assert_eq!(parse_count("5000"), Ok(5000));
+        assert_eq!(parse_count("250k"), Ok(250_000));
+        assert_eq!(parse_count("1.5M"), Ok(1_500_000));
+        assert!(parse_count("1MB").is_err());
+        assert!(parse_count("many").is_err());
+    }
+
+    #[test]
+    fn sizes_use_binary_units() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("1KB"), Ok(1024));
+        assert_eq!(parse_size("500MB"), Ok(500 << 20));
+        assert_eq!(parse_size("10GB"), Ok(10 << 30));
+        assert_eq!(parse_size("1.5TB"), Ok(3 << 39));
+        assert_eq!(parse_size(" 2 gib "), Ok(2 << 30));
+    }
+
+    #[test]
+    fn rates_must_be_positive() {
+        assert_eq!(parse_rate("2500"), Ok(2500.0));
+        assert_eq!(parse_rate("0.5"), Ok(0.5));
+        for rate in ["0", "-1", "inf", "NaN", "fast"] {
+            assert!(parse_rate(rate).is_err(), "{rate}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("GB").is_err());
+        assert!(parse_size("10XB").is_err());
+    }
+
+    #[test]
+    fn default_arguments_match_the_original_behaviour() {
+        let args = Args::parse_from(["csv_data_generator"]);
+        assert_eq!(args.output, "large_data_rust.csv");
+        assert_eq!(args.size, None);
+        assert_eq!(args.shards, 1);
+        assert_eq!(args.csv_dialect, CsvDialect::Rfc4180);
+    }
+}
@@ -0,0 +1,333 @@
+//! The clickstream preset: users, their sessions and the events of each
+//! session, generated together so that the three tables agree.
+//!
+//! A user's first session starts when they sign up and each later one at
+//! least [`SESSION_TIMEOUT`] after the previous one ended, while the events
+//! of a session are always less than that apart, so sessionizing the events
+//! by the timeout gives back the sessions. Every session lands on a page and
+//! then goes through the steps of a shopping funnel, going on from each step
+//! at its `--clickstream-funnel` rate, with pages browsed in between. The
+//! referrer and UTM parameters follow the channel the session came from.
+
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::generator::{Row, push_timestamp};
+use crate::presets::{self, push_int};
+use crate::schema::{Column, ColumnKind};
+use crate::signal;
+
+/// Sessions end after this long without an event, as in most web analytics.
+pub const SESSION_TIMEOUT: i64 = 30 * 60;
+
+/// Users sign up and sessions start during 2024, from 2024-01-01 UTC...
+const WINDOW_START: i64 = 1_704_067_200;
+/// ...to 2025-01-01 UTC.
+const WINDOW_END: i64 = 1_735_689_600;
+
+/// Users at scale factor 1.
+const USERS: f64 = 10_000.0;
+/// The chance of a user coming back for another session, for 4 sessions
+/// per user on average.
+const RETURN_RATE: f64 = 0.75;
+/// The mean time between a session's end and the timeout, in seconds.
+const MEAN_SESSION_GAP: f64 = 3.0 * 86_400.0;
+/// The mean time between two events of a session, in seconds.
+const MEAN_EVENT_GAP: f64 = 40.0;
+/// The chance of browsing one more page before the next funnel step.
+const BROWSE_RATE: f64 = 0.4;
+const PRODUCTS: u32 = 1_000;
+
+const TABLES: [&str; 3] = ["users", "sessions", "events"];
+
+const COUNTRIES: &[&str] = &["US", "GB", "DE", "FR", "IN", "BR", "JP", "CA", "AU", "MX"];
+const DEVICES: &[&str] = &["desktop", "mobile", "tablet"];
+const CATEGORIES: &[&str] = &["shoes", "jackets", "bags", "watches", "hats"];
+const CAMPAIGNS: &[&str] = &["spring_sale", "summer_sale", "back_to_school", "black_friday", "holiday"];
+
+/// Where a session came from.
+struct Channel {
+    name: &'static str,
+    weight: u32,
+    /// The referrers a session of the channel may have, "" for none.
+    referrers: &'static [&'static str],
+    /// The `utm_source` going with each referrer, or none when the channel
+    /// isn't tagged.
+    sources: &'static [&'static str],
+    medium: &'static str,
+    /// Whether the session's ad or link lands on a product page.
+    lands_on_product: bool,
+}
+
+const CHANNELS: &[Channel] = &[
+    Channel { name: "direct", weight: 30, referrers: &[""], sources: &[], medium: "", lands_on_product: false },
+    Channel {
+        name: "organic_search",
+        weight: 30,
+        referrers: &["https://www.google.com/", "https://www.bing.com/", "https://duckduckgo.com/"],
+        sources: &[],
+        medium: "",
+        lands_on_product: false,
+    },
+    Channel {
+        name: "paid_search",
+        weight: 15,
+        referrers: &["https://www.google.com/", "https://www.bing.com/"],
+        sources: &["google", "bing"],
+        medium: "cpc",
+        lands_on_product: true,
+    },
+    Channel {
+        name: "social",
+        weight: 15,
+        referrers: &["https://www.facebook.com/", "https://www.instagram.com/", "https://t.co/"],
+        sources: &["facebook", "instagram", "twitter"],
+        medium: "social",
+        lands_on_product: false,
+    },
+    Channel { name: "email", weight: 10, referrers: &[""], sources: &["newsletter"], medium: "email", lands_on_product: true },
+];
+
+/// The funnel steps after landing, with the event and page of each.
+const FUNNEL: [&str; 4] = ["view_item", "add_to_cart", "begin_checkout", "purchase"];
+
+/// The columns of each table, in [`TABLES`] order. Only the names and types
+/// of these matter; the values are written below.
+fn columns() -> [Vec<Column>; 3] {
+    let integer = |name| Column::new(name, ColumnKind::Sequence { start: 1 });
+    let string = |name| Column::new(name, ColumnKind::Choice(Vec::new()));
+    let timestamp = |name| Column::new(name, ColumnKind::TimestampRange { min: 0, max: 0 });
+    [
+        vec![integer("user_id"), timestamp("signup_at"), string("country"), string("device")],
+        vec![
+            integer("session_id"),
+            integer("user_id"),
+            timestamp("started_at"),
+            timestamp("ended_at"),
+            string("device"),
+            string("channel"),
+            string("referrer"),
+            string("utm_source"),
+            string("utm_medium"),
+            string("utm_campaign"),
+            string("landing_page"),
+            integer("events"),
+            integer("converted"),
+        ],
+        vec![
+            integer("event_id"),
+            integer("session_id"),
+            integer("user_id"),
+            timestamp("occurred_at"),
+            integer("sequence_number"),
+            string("event_type"),
+            string("page"),
+        ],
+    ]
+}
+
+fn pick<'a, R: Rng + ?Sized>(rng: &mut R, values: &[&'a str]) -> &'a str {
+    values[rng.gen_range(0..values.len())]
+}
+
+/// An exponentially distributed number of seconds with the given mean.
+pub fn exponential<R: Rng + ?Sized>(rng: &mut R, mean: f64) -> f64 {
+    -mean * (1.0 - rng.r#gen::<f64>()).ln()
+}
+
+fn product_page<R: Rng + ?Sized>(rng: &mut R) -> String {
+    format!("/product/{}", rng.gen_range(1..=PRODUCTS))
+}
+
+/// A page that isn't part of the funnel.
+fn browse_page<R: Rng + ?Sized>(rng: &mut R) -> String {
+    match rng.gen_range(0..4) {
+        0 => "/".to_string(),
+        1 => "/search".to_string(),
+        2 => format!("/category/{}", pick(rng, CATEGORIES)),
+        _ => product_page(rng),
+    }
+}
+
+/// The `(event_type, page)` of each event of a session, and whether it
+/// purchased.
+fn journey<R: Rng + ?Sized>(rng: &mut R, funnel: &[f64], channel: &Channel) -> (Vec<(&'static str, String)>, bool) {
+    let landing = if channel.lands_on_product { product_page(rng) } else { browse_page(rng) };
+    let product = if landing.starts_with("/product/") { landing.clone() } else { product_page(rng) };
+    let mut events = vec![("page_view", landing)];
+    for (step, (&event, &rate)) in FUNNEL.iter().zip(funnel).enumerate() {
+        while rng.gen_bool(BROWSE_RATE) {
+            let page = events.last().map(|(_, page)| page.clone()).unwrap_or_default();
+            events.push(if rng.gen_bool(0.5) { ("click", page) } else { ("page_view", browse_page(rng)) });
+        }
+        if !rng.gen_bool(rate) {
+            return (events, false);
+        }
+        let page = match step {
+            0 | 1 => product.clone(),
+            2 => "/checkout".to_string(),
+            _ => "/checkout/complete".to_string(),
+        };
+        events.push((event, page));
+    }
+    (events, true)
+}
+
+/// Writes each user followed by their sessions, the events of a session
+/// before the session row that sums them up.
+pub fn generate(args: &Args) -> Result<(), BoxError> {
+    let funnel = &args.preset.clickstream_funnel;
+    if funnel.len() != FUNNEL.len() {
+        return Err(format!("--clickstream-funnel takes {} rates, one for each step after landing", FUNNEL.len()).into());
+    }
+    let [mut users_sink, mut sessions_sink, mut events_sink] = presets::open_tables(args, "clickstream", TABLES, columns())?;
+
+    let mut rng = rand::thread_rng();
+    let channels = WeightedIndex::new(CHANNELS.iter().map(|c| c.weight))?;
+    let mut users = ((USERS * args.preset.scale_factor).round() as u64).max(1);
+    let (mut sessions, mut events, mut conversions) = (0u64, 0u64, 0u64);
+    let mut row = Row::new();
+    for user in 1..=users {
+        if signal::interrupted() {
+            users = user - 1;
+            break;
+        }
+        let signup = rng.gen_range(WINDOW_START..WINDOW_END);
+        let device = pick(&mut rng, DEVICES);
+        row.clear();
+        push_int(&mut row, user);
+        push_timestamp(&mut row, signup);
+        row.push(pick(&mut rng, COUNTRIES).as_bytes());
+        row.push(device.as_bytes());
+        users_sink.write_row(&row)?;
+
+        let mut start = signup;
+        loop {
+            sessions += 1;
+            let channel = &CHANNELS[channels.sample(&mut rng)];
+            let (steps, converted) = journey(&mut rng, funnel, channel);
+            let mut at = start;
+            for (n, (event, page)) in steps.iter().enumerate() {
+                if n > 0 {
+                    at += (1.0 + exponential(&mut rng, MEAN_EVENT_GAP)).min((SESSION_TIMEOUT - 1) as f64) as i64;
+                }
+                events += 1;
+                row.clear();
+                push_int(&mut row, events);
+                push_int(&mut row, sessions);
+                push_int(&mut row, user);
+                push_timestamp(&mut row, at);
+                push_int(&mut row, n as u64 + 1);
+                row.push(event.as_bytes());
+                row.push(page.as_bytes());
+                events_sink.write_row(&row)?;
+            }
+
+            let i = rng.gen_range(0..channel.referrers.len());
+            let source = channel.sources.get(i).copied().unwrap_or_default();
+            row.clear();
+            push_int(&mut row, sessions);
+            push_int(&mut row, user);
+            push_timestamp(&mut row, start);
+            push_timestamp(&mut row, at);
+            // Most sessions come from the device the user signed up on.
+            row.push(if rng.gen_bool(0.8) { device } else { pick(&mut rng, DEVICES) }.as_bytes());
+            row.push(channel.name.as_bytes());
+            row.push(channel.referrers[i].as_bytes());
+            row.push(source.as_bytes());
+            row.push(channel.medium.as_bytes());
+            row.push(if source.is_empty() { "" } else { pick(&mut rng, CAMPAIGNS) }.as_bytes());
+            row.push(steps[0].1.as_bytes());
+            push_int(&mut row, steps.len() as u64);
+            push_int(&mut row, converted.into());
+            sessions_sink.write_row(&row)?;
+            conversions += u64::from(converted);
+
+            start = at + SESSION_TIMEOUT + exponential(&mut rng, MEAN_SESSION_GAP) as i64;
+            if start >= WINDOW_END || !rng.gen_bool(RETURN_RATE) {
+                break;
+            }
+        }
+    }
+    presets::finish_tables(args, TABLES, [users_sink, sessions_sink, events_sink], [users, sessions, events])?;
+    println!("  {conversions} of {sessions} sessions purchased");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::testing::{read, temp_dir};
+    use crate::schema::parse_timestamp;
+    use clap::Parser;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn tables_agree() {
+        let dir = temp_dir("clickstream");
+        let template = dir.join("{table}.csv");
+        let args = Args::parse_from([
+            "gen",
+            "--preset",
+            "clickstream",
+            "--scale-factor",
+            "0.02",
+            "--output",
+            template.to_str().unwrap(),
+        ]);
+        generate(&args).unwrap();
+
+        let time = |s: &str| parse_timestamp(s).unwrap();
+        let users: HashMap<String, i64> = read(dir.join("users.csv")).into_iter().map(|u| (u[0].clone(), time(&u[1]))).collect();
+        assert_eq!(users.len(), 200);
+        let sessions = read(dir.join("sessions.csv"));
+        let mut last_end: HashMap<&str, i64> = HashMap::new();
+        for s in &sessions {
+            let (start, end) = (time(&s[2]), time(&s[3]));
+            assert!(start >= users[&s[1]] && end >= start);
+            if let Some(previous) = last_end.insert(&s[1], end) {
+                assert!(start >= previous + SESSION_TIMEOUT);
+            }
+            assert_eq!(s[7].is_empty(), s[8].is_empty());
+            assert_eq!(s[7].is_empty(), s[9].is_empty());
+        }
+
+        let events = read(dir.join("events.csv"));
+        let by_id: HashMap<&str, &Vec<String>> = sessions.iter().map(|s| (s[0].as_str(), s)).collect();
+        let mut reached = [0usize; 5];
+        for session in events.chunk_by(|a, b| a[1] == b[1]) {
+            let s = by_id[session[0][1].as_str()];
+            assert_eq!(session.len().to_string(), s[11]);
+            assert_eq!(session[0][6], s[10]);
+            assert_eq!(time(&session[0][3]), time(&s[2]));
+            assert_eq!(time(&session[session.len() - 1][3]), time(&s[3]));
+            for pair in session.windows(2) {
+                let gap = time(&pair[1][3]) - time(&pair[0][3]);
+                assert!(gap > 0 && gap < SESSION_TIMEOUT);
+            }
+            // The funnel steps come at most once each, in order.
+            let steps: Vec<_> = session.iter().filter_map(|e| FUNNEL.iter().position(|&f| f == e[5])).collect();
+            assert_eq!(steps, (0..steps.len()).collect::<Vec<_>>());
+            assert_eq!(s[12], if steps.len() == FUNNEL.len() { "1" } else { "0" });
+            for step in reached.iter_mut().take(steps.len() + 1) {
+                *step += 1;
+            }
+        }
+        assert_eq!(reached[0], sessions.len());
+        assert!(reached.windows(2).all(|pair| pair[0] >= pair[1]));
+        // 60% of sessions view a product.
+        let viewed = reached[1] as f64 / reached[0] as f64;
+        assert!((0.5..0.7).contains(&viewed), "{viewed}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn funnel_takes_a_rate_per_step() {
+        let args = Args::parse_from(["gen", "--preset", "clickstream", "--clickstream-funnel", "0.5,0.5", "--output", "{table}.csv"]);
+        assert!(generate(&args).is_err());
+        assert!(Args::try_parse_from(["gen", "--preset", "clickstream", "--clickstream-funnel", "1.5,1,1,1"]).is_err());
+    }
+}
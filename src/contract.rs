@@ -0,0 +1,226 @@
+//! `--json-schema`: a JSON Schema (draft 2020-12) of each file's rows,
+//! written next to it as `.schema.json`, for contract-testing tools to check
+//! the data against. It describes the rows as `--format jsonl` writes them,
+//! with the types, ranges, lists and patterns the columns generate from, so
+//! the dirty-data options' changes fall outside it.
+
+use std::io::Write;
+
+use serde_json::{Map, Value, json};
+
+use crate::BoxError;
+use crate::generator::{Row, push_timestamp};
+use crate::geo::Shape;
+use crate::output::{Destination, Outputs};
+use crate::schema::{Column, ColumnKind, DataType, format_date};
+
+const SUFFIX: &str = ".schema.json";
+
+const TIMESTAMP_PATTERN: &str = r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}$";
+
+/// Writes the schema of the rows of `data` next to it, if `--json-schema`
+/// is given.
+pub fn write(outputs: &Outputs, data: &Destination, columns: &[Column]) -> Result<(), BoxError> {
+    if !outputs.args().json_schema {
+        return Ok(());
+    }
+    let destination = data.beside(SUFFIX).ok_or("--json-schema needs a file or cloud storage output")?;
+    let title = match data {
+        Destination::File(path) => path.file_name().map(|n| n.to_string_lossy().into_owned()),
+        _ => None,
+    };
+    let mut output = outputs.open(&destination)?;
+    serde_json::to_writer_pretty(&mut output, &json_schema(title.as_deref(), columns))?;
+    output.write_all(b"\n")?;
+    output.finish()
+}
+
+/// The schema of a row of `columns`. Its properties come out in name order,
+/// so `required` is what lists the columns in order.
+pub fn json_schema(title: Option<&str>, columns: &[Column]) -> Value {
+    // The presets that generate rows themselves describe their columns only
+    // by type, with empty lists for text, and leave some fields empty.
+    let typed_only = columns.iter().any(|c| matches!(&c.kind, ColumnKind::Choice(values) if values.is_empty()));
+    let properties: Map<String, Value> = columns
+        .iter()
+        .map(|c| {
+            let property = if typed_only { nullable(c.kind.data_type()) } else { property(&c.kind) };
+            (c.name.clone(), property)
+        })
+        .collect();
+    let mut schema = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "properties": properties,
+        "required": columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+        "additionalProperties": false,
+    });
+    if let Some(title) = title {
+        schema["title"] = title.into();
+    }
+    schema
+}
+
+/// What a column's values are. Date and timestamp ranges are given as
+/// `formatMinimum` and `formatMaximum`, as ajv-formats reads them; they
+/// order as text as they do in time.
+fn property(kind: &ColumnKind) -> Value {
+    match kind {
+        ColumnKind::Sha256Hex => json!({ "type": "string", "pattern": "^[0-9a-f]{64}$" }),
+        ColumnKind::UuidV4 => json!({ "type": "string", "format": "uuid" }),
+        ColumnKind::Choice(values) | ColumnKind::Cycle(values) => json!({ "type": "string", "enum": values }),
+        ColumnKind::IntChoice(values) => json!({ "type": "integer", "enum": values }),
+        ColumnKind::IntRange { min, max } => json!({ "type": "integer", "minimum": min, "maximum": max }),
+        ColumnKind::Sequence { start } => json!({ "type": "integer", "minimum": start }),
+        ColumnKind::ParentKey => json!({ "type": "integer" }),
+        ColumnKind::ForeignKey { min, max, matching, .. } => {
+            // Keys that match no row are the ones right after `max`.
+            let max = if *matching < 1.0 { max + (max - min + 1) } else { *max };
+            json!({ "type": "integer", "minimum": min, "maximum": max })
+        }
+        ColumnKind::ChildNumber => json!({ "type": "integer", "minimum": 1 }),
+        ColumnKind::Label { prefix, width } => {
+            json!({ "type": "string", "pattern": format!(r"^{}\d{{{width},}}$", escape(prefix)) })
+        }
+        ColumnKind::DateRange { min, max } => json!({
+            "type": "string",
+            "format": "date",
+            "formatMinimum": format_date(*min),
+            "formatMaximum": format_date(*max),
+        }),
+        ColumnKind::TimestampRange { min, max } => json!({
+            "type": "string",
+            "pattern": TIMESTAMP_PATTERN,
+            "formatMinimum": timestamp(*min),
+            "formatMaximum": timestamp(*max),
+        }),
+        ColumnKind::Clock { .. } | ColumnKind::Lagged { .. } => json!({ "type": "string", "pattern": TIMESTAMP_PATTERN }),
+        ColumnKind::Decimal { min, max, scale } => {
+            let unit = 10f64.powi((*scale).into());
+            json!({ "type": "number", "minimum": *min as f64 / unit, "maximum": *max as f64 / unit })
+        }
+        ColumnKind::Format { formats, lists } => {
+            let formats: Vec<_> = formats.iter().map(|f| format_pattern(f, lists)).collect();
+            json!({ "type": "string", "pattern": format!("^(?:{})$", formats.join("|")) })
+        }
+        ColumnKind::Unicode { .. } | ColumnKind::NationalId { .. } => json!({ "type": "string" }),
+        ColumnKind::Script { data_type, .. } | ColumnKind::Plugin { data_type, .. } => match data_type {
+            DataType::String => json!({ "type": "string" }),
+            DataType::Integer => json!({ "type": "integer" }),
+            DataType::Decimal { .. } => json!({ "type": "number" }),
+            DataType::Date => json!({ "type": "string", "format": "date" }),
+            DataType::Timestamp => json!({ "type": "string", "pattern": TIMESTAMP_PATTERN }),
+        },
+        ColumnKind::Geo { shape, area } => match shape {
+            Shape::Latitude => {
+                let south = area.iter().map(|b| b.south).fold(f64::INFINITY, f64::min);
+                let north = area.iter().map(|b| b.north).fold(f64::NEG_INFINITY, f64::max);
+                json!({ "type": "number", "minimum": south, "maximum": north })
+            }
+            Shape::Longitude => {
+                let west = area.iter().map(|b| b.west).fold(f64::INFINITY, f64::min);
+                let east = area.iter().map(|b| b.east).fold(f64::NEG_INFINITY, f64::max);
+                json!({ "type": "number", "minimum": west, "maximum": east })
+            }
+            Shape::Geohash { precision } => json!({ "type": "string", "pattern": format!("^[0-9b-hjkmnp-z]{{{precision}}}$") }),
+            Shape::Point => json!({ "type": "string", "pattern": r"^POINT \(" }),
+            Shape::Polygon { .. } => json!({ "type": "string", "pattern": r"^POLYGON \(\(" }),
+        },
+    }
+}
+
+/// Values of `data_type`, or null.
+fn nullable(data_type: DataType) -> Value {
+    match data_type {
+        DataType::String => json!({ "type": ["string", "null"] }),
+        DataType::Integer => json!({ "type": ["integer", "null"] }),
+        DataType::Decimal { .. } => json!({ "type": ["number", "null"] }),
+        DataType::Date => json!({ "type": ["string", "null"], "format": "date" }),
+        DataType::Timestamp => json!({ "type": ["string", "null"], "pattern": TIMESTAMP_PATTERN }),
+    }
+}
+
+/// The pattern of a `Format` column's `format`, whose `#` is any digit, `%`
+/// any but 0 and `{i}` any entry of `lists[i]`.
+fn format_pattern(format: &str, lists: &[Vec<String>]) -> String {
+    let mut pattern = String::new();
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '#' => pattern.push_str(r"\d"),
+            '%' => pattern.push_str("[1-9]"),
+            '{' if let Some((index, after)) = rest.split_once('}')
+                && let Some(list) = index.parse::<usize>().ok().and_then(|i| lists.get(i)) =>
+            {
+                let entries: Vec<_> = list.iter().map(|e| escape(e)).collect();
+                pattern.push_str(&format!("(?:{})", entries.join("|")));
+                rest = after;
+            }
+            c => pattern.push_str(&escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    pattern
+}
+
+/// `text` with the characters that mean something in a pattern escaped.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if r"\.^$|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn timestamp(seconds: i64) -> String {
+    let mut row = Row::new();
+    push_timestamp(&mut row, seconds);
+    String::from_utf8_lossy(row.fields().next().unwrap_or_default()).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+    use crate::schema;
+
+    #[test]
+    fn describes_each_column() {
+        let mut columns = schema::users(Locale::En);
+        columns.extend([
+            Column::new("at", ColumnKind::TimestampRange { min: 0, max: 86_399 }),
+            Column::new("price", ColumnKind::Decimal { min: -150, max: 99_999, scale: 2 }),
+            Column::new("sku", ColumnKind::Label { prefix: "SKU-".into(), width: 6 }),
+        ]);
+        let schema = json_schema(Some("users.csv"), &columns);
+        assert_eq!(schema["required"], json!(["id", "name", "age", "at", "price", "sku"]));
+        assert_eq!(schema["title"], "users.csv");
+        let property = |name: &str| &schema["properties"][name];
+        assert_eq!(property("id")["pattern"], "^[0-9a-f]{64}$");
+        assert_eq!(property("name")["enum"][0], "Liam");
+        assert_eq!(property("age"), &json!({ "type": "integer", "minimum": 18, "maximum": 60 }));
+        assert_eq!((&property("at")["formatMinimum"], &property("at")["formatMaximum"]), (&"1970-01-01 00:00:00".into(), &"1970-01-01 23:59:59".into()));
+        assert_eq!((&property("price")["minimum"], &property("price")["maximum"]), (&(-1.5).into(), &999.99.into()));
+        assert_eq!(property("sku")["pattern"], r"^SKU-\d{6,}$");
+    }
+
+    #[test]
+    fn preset_columns_are_described_by_type() {
+        let columns = [
+            Column::new("manager_id", ColumnKind::Sequence { start: 1 }),
+            Column::new("name", ColumnKind::Choice(Vec::new())),
+        ];
+        let schema = json_schema(None, &columns);
+        assert_eq!(schema["properties"]["manager_id"], json!({ "type": ["integer", "null"] }));
+        assert_eq!(schema["properties"]["name"], json!({ "type": ["string", "null"] }));
+    }
+
+    #[test]
+    fn formats_become_patterns() {
+        let lists = vec![vec!["St".to_string(), "Ave.".to_string()]];
+        assert_eq!(format_pattern("(%##) {0}", &lists), r"\([1-9]\d\d\) (?:St|Ave\.)");
+    }
+}
@@ -0,0 +1,158 @@
+//! The `convert` subcommand: rewrites a CSV file, this tool's or any other,
+//! in another format through the same sinks generated rows go through, so
+//! any output and option a run takes applies. Column types are inferred from
+//! a first pass over the file.
+
+use std::path::Path;
+
+use crate::BoxError;
+use crate::cli::{Args, ConvertArgs};
+use crate::generator::Row;
+use crate::output::{Destination, Outputs};
+use crate::schema::{Column, ColumnKind, DataType, parse_date, parse_timestamp};
+use crate::sink;
+use crate::validate::{csv_reader, parse_decimal};
+
+/// Converts `convert.input` with the other settings of `args`.
+pub fn run(args: &Args, convert: &ConvertArgs) -> Result<(), BoxError> {
+    let columns = infer_columns(&convert.input)?;
+    let mut args = args.clone();
+    args.output = convert.output.clone();
+    args.format = convert.to;
+    args.compression = convert.compression;
+    args.parquet_compression = convert.parquet_compression;
+    let mut sink = sink::open(&Outputs::new(&args), &Destination::parse(&args.output)?, &columns)?;
+
+    let name = convert.input.display();
+    let mut reader = csv_reader(&convert.input)?;
+    let mut record = csv::ByteRecord::new();
+    let mut row = Row::new();
+    let mut rows = 0u64;
+    while reader.read_byte_record(&mut record).map_err(|e| format!("{name}: {e}"))? {
+        rows += 1;
+        if record.len() != columns.len() {
+            return Err(format!("{name} row {rows}: {} fields, not {}", record.len(), columns.len()).into());
+        }
+        row.clear();
+        for field in &record {
+            row.push(field);
+        }
+        sink.write_row(&row)?;
+    }
+    sink.finish()?;
+    let types: Vec<String> = columns.iter().map(|c| format!("{} {}", c.name, type_name(c.kind.data_type()))).collect();
+    println!("Converted {rows} rows of {} to {}", types.join(", "), args.output);
+    Ok(())
+}
+
+/// The columns of the CSV file at `path`, named by its header and each of
+/// the narrowest type all its values are written as, in a first pass over
+/// it. A column with an empty value, or integers with leading zeros like
+/// zip codes, is text.
+pub fn infer_columns(path: &Path) -> Result<Vec<Column>, BoxError> {
+    let name = path.display();
+    let mut reader = csv_reader(path)?;
+    let header = reader.byte_headers().map_err(|e| format!("{name}: {e}"))?.clone();
+    let mut types: Vec<Option<Inferred>> = vec![Some(Inferred::Unknown); header.len()];
+    let mut record = csv::ByteRecord::new();
+    while reader.read_byte_record(&mut record).map_err(|e| format!("{name}: {e}"))? {
+        for (inferred, field) in types.iter_mut().zip(&record) {
+            if let Some(current) = *inferred {
+                *inferred = std::str::from_utf8(field).ok().and_then(|text| current.widen(text));
+            }
+        }
+    }
+    let columns = header.iter().zip(types).map(|(name, inferred)| {
+        // Placeholder kinds, of the type the values are written in.
+        let kind = match inferred {
+            Some(Inferred::Integer) => ColumnKind::IntRange { min: 0, max: 0 },
+            Some(Inferred::Decimal(scale)) => ColumnKind::Decimal { min: 0, max: 0, scale },
+            Some(Inferred::Date) => ColumnKind::DateRange { min: 0, max: 0 },
+            Some(Inferred::Timestamp) => ColumnKind::TimestampRange { min: 0, max: 0 },
+            Some(Inferred::Unknown) | None => ColumnKind::Choice(Vec::new()),
+        };
+        Column::new(String::from_utf8_lossy(name), kind)
+    });
+    Ok(columns.collect())
+}
+
+fn type_name(data_type: DataType) -> String {
+    match data_type {
+        DataType::String => "string".to_string(),
+        DataType::Integer => "integer".to_string(),
+        DataType::Decimal { scale } => format!("decimal({scale})"),
+        DataType::Date => "date".to_string(),
+        DataType::Timestamp => "timestamp".to_string(),
+    }
+}
+
+/// The type of the values of a column seen so far; none once they're text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Inferred {
+    /// No values yet.
+    Unknown,
+    Integer,
+    Decimal(u8),
+    Date,
+    Timestamp,
+}
+
+impl Inferred {
+    /// The narrowest type holding the values so far and `text`, if any.
+    fn widen(self, text: &str) -> Option<Self> {
+        let digits = text.strip_prefix('-').unwrap_or(text);
+        if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+            return None;
+        }
+        let scale = text.split_once('.').map_or(0, |(_, fraction)| fraction.len());
+        let number = u8::try_from(scale).ok().filter(|&scale| parse_decimal(text, scale).is_some());
+        let date = text.len() == 10 && parse_date(text).is_some();
+        let timestamp = text.len() == 19 && parse_timestamp(text).is_some();
+        // Decimals are written with every decimal place, so a column mixing
+        // scales, or integers, stays text.
+        match (self, number) {
+            (Inferred::Unknown | Inferred::Integer, Some(0)) => Some(Inferred::Integer),
+            (Inferred::Unknown, Some(scale)) => Some(Inferred::Decimal(scale)),
+            (Inferred::Decimal(scale), Some(s)) if s == scale => Some(self),
+            (Inferred::Unknown | Inferred::Date, _) if date => Some(Inferred::Date),
+            (Inferred::Unknown | Inferred::Timestamp, _) if timestamp => Some(Inferred::Timestamp),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+
+    #[test]
+    fn infers_the_narrowest_types() {
+        let widen = |texts: &[&str]| texts.iter().try_fold(Inferred::Unknown, |inferred, text| inferred.widen(text));
+        assert_eq!(widen(&["1", "-20", "300"]), Some(Inferred::Integer));
+        assert_eq!(widen(&["1", "2.50", "-0.25"]), None);
+        assert_eq!(widen(&["2.50", "-0.25"]), Some(Inferred::Decimal(2)));
+        assert_eq!(widen(&["02134"]), None);
+        assert_eq!(widen(&["2024-02-29", "1999-12-31"]), Some(Inferred::Date));
+        assert_eq!(widen(&["2024-02-29 23:59:59"]), Some(Inferred::Timestamp));
+        assert_eq!(widen(&["2024-02-30"]), None);
+        assert_eq!(widen(&["1", ""]), None);
+    }
+
+    #[test]
+    fn converts_to_json_lines() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_convert_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("in.csv"), dir.join("out.jsonl"));
+        fs::write(&input, "id,price,name,zip\n1,2.50,\"Ann, B\",02134\n2,10.00,Bo,10001\n").unwrap();
+        let args = Args::parse_from(["gen", "convert", input.to_str().unwrap(), "--to", "jsonl", "--output", output.to_str().unwrap()]);
+        let Some(crate::cli::Command::Convert(convert)) = &args.command else { unreachable!() };
+        run(&args, convert).unwrap();
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            "{\"id\":1,\"price\":2.50,\"name\":\"Ann, B\",\"zip\":\"02134\"}\n{\"id\":2,\"price\":10.00,\"name\":\"Bo\",\"zip\":\"10001\"}\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
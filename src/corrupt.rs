@@ -0,0 +1,286 @@
+//! Malformed rows for testing loaders' error handling and dead-letter
+//! paths: `--corrupt-rate` of the CSV rows written are mangled, and listed
+//! in the file's manifest.
+
+use clap::ValueEnum;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::BoxError;
+use crate::cli::{Args, CsvDialect, FileFormat};
+use crate::generator::Row;
+use crate::manifest::Injector;
+use crate::sink::csv_record;
+use crate::torture::char_boundaries;
+
+/// A way of mangling a CSV row.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Corruption {
+    /// Fewer fields than the header, losing some from the end.
+    MissingFields,
+    /// More fields than the header, repeating some of the row's.
+    ExtraFields,
+    /// A quote opening the last field that never closes, so a strict parser
+    /// takes the lines after as part of it.
+    UnterminatedQuote,
+    /// A control character such as NUL or ESC, unquoted, inside a field.
+    ControlCharacters,
+    /// Bytes that aren't valid UTF-8 inside a field: a stray continuation
+    /// byte, a cut-off sequence, an overlong form or a surrogate.
+    InvalidUtf8,
+    /// A field encoded as Latin-1 rather than UTF-8.
+    Latin1,
+}
+
+impl Corruption {
+    fn name(self) -> &'static str {
+        match self {
+            Corruption::MissingFields => "missing_fields",
+            Corruption::ExtraFields => "extra_fields",
+            Corruption::UnterminatedQuote => "unterminated_quote",
+            Corruption::ControlCharacters => "control_characters",
+            Corruption::InvalidUtf8 => "invalid_utf8",
+            Corruption::Latin1 => "latin1",
+        }
+    }
+}
+
+const CONTROL_CHARACTERS: &[u8] = &[0x00, 0x01, 0x07, 0x08, 0x0b, 0x0c, 0x1b, 0x7f];
+const INVALID_UTF8: &[&[u8]] =
+    &[b"\x80", b"\xff", b"\xc3", b"\xe2\x82", b"\xc0\xaf", b"\xed\xa0\x80", b"\xf8\x88\x80\x80\x80"];
+/// Words that look different in Latin-1, one of which is added to a field
+/// with no characters of its own to re-encode.
+const LATIN1_WORDS: &[&str] = &["café", "naïve", "Zürich", "São Paulo", "façade", "Ångström", "niño"];
+
+/// Opens the corrupter for the CSV `injector` writes, or none without
+/// `--corrupt-rate`.
+pub fn open(args: &Args, injector: Injector) -> Result<Option<Corrupter>, BoxError> {
+    if args.corrupt.corrupt_rate == 0.0 {
+        return Ok(None);
+    }
+    if args.format != FileFormat::Csv || args.csv_dialect != CsvDialect::Rfc4180 {
+        return Err("--corrupt-rate only applies to CSV of --csv-dialect rfc4180".into());
+    }
+    if !injector.has_manifest() {
+        return Err("--corrupt-rate needs a file or cloud storage output".into());
+    }
+    Ok(Some(Corrupter { rate: args.corrupt.corrupt_rate, kinds: args.corrupt.corrupt_kinds.clone(), injector }))
+}
+
+/// Picks the rows to corrupt, mangles them and lists them in the manifest.
+pub struct Corrupter {
+    rate: f64,
+    kinds: Vec<Corruption>,
+    injector: Injector,
+}
+
+impl Corrupter {
+    /// The line to write instead of `row` if it's to be corrupted, without a
+    /// terminator.
+    pub fn corrupt(&mut self, row: &Row) -> Result<Option<Vec<u8>>, BoxError> {
+        self.injector.start_row();
+        let rng = &mut self.injector.rng;
+        if self.kinds.is_empty() || !rng.gen_bool(self.rate) {
+            return Ok(None);
+        }
+        let fields: Vec<&[u8]> = row.fields().collect();
+        let kind = match self.kinds[rng.gen_range(0..self.kinds.len())] {
+            // With one field, all there is to lose is the whole line.
+            Corruption::MissingFields if fields.len() < 2 => Corruption::ExtraFields,
+            kind => kind,
+        };
+        let (mangled, target) = mangle(rng, kind, &fields);
+        match target {
+            Some(target) => self.injector.record(Some(target), kind.name(), fields[target])?,
+            None => self.injector.record(None, kind.name(), &csv_record(row))?,
+        }
+        Ok(Some(mangled))
+    }
+}
+
+/// The mangled line, and the field mangled if it was only the one.
+fn mangle<R: Rng + ?Sized>(rng: &mut R, kind: Corruption, fields: &[&[u8]]) -> (Vec<u8>, Option<usize>) {
+    let mut row = Row::new();
+    match kind {
+        Corruption::MissingFields => {
+            for field in &fields[..rng.gen_range(1..fields.len())] {
+                row.push(field);
+            }
+            (csv_record(&row), None)
+        }
+        Corruption::ExtraFields => {
+            for field in fields {
+                row.push(field);
+            }
+            for _ in 0..rng.gen_range(1..=3) {
+                row.push(fields[rng.gen_range(0..fields.len())]);
+            }
+            (csv_record(&row), None)
+        }
+        Corruption::UnterminatedQuote => {
+            let (last, rest) = fields.split_last().expect("rows have fields");
+            for field in rest {
+                row.push(field);
+            }
+            let mut line = csv_record(&row);
+            if !rest.is_empty() {
+                line.push(b',');
+            }
+            line.push(b'"');
+            line.extend(last.iter().filter(|&&b| b != b'"'));
+            (line, None)
+        }
+        Corruption::ControlCharacters | Corruption::InvalidUtf8 => {
+            let inserted = match kind {
+                Corruption::ControlCharacters => {
+                    std::slice::from_ref(&CONTROL_CHARACTERS[rng.gen_range(0..CONTROL_CHARACTERS.len())])
+                }
+                _ => INVALID_UTF8[rng.gen_range(0..INVALID_UTF8.len())],
+            };
+            let target = rng.gen_range(0..fields.len());
+            for (i, field) in fields.iter().enumerate() {
+                if i != target {
+                    row.push(field);
+                    continue;
+                }
+                let boundaries = char_boundaries(field);
+                let at = boundaries[rng.gen_range(0..boundaries.len())];
+                row.push_with(|out| {
+                    out.extend_from_slice(&field[..at]);
+                    out.extend_from_slice(inserted);
+                    out.extend_from_slice(&field[at..]);
+                });
+            }
+            (csv_record(&row), Some(target))
+        }
+        Corruption::Latin1 => {
+            // A field with characters Latin-1 encodes differently if there's
+            // one, and otherwise any field with a word that has them added.
+            let latin = |field: &[u8]| {
+                std::str::from_utf8(field).is_ok_and(|s| s.chars().any(|c| ('\u{80}'..='\u{ff}').contains(&c)))
+            };
+            let candidates: Vec<usize> = (0..fields.len()).filter(|&i| latin(fields[i])).collect();
+            let target = if candidates.is_empty() {
+                rng.gen_range(0..fields.len())
+            } else {
+                candidates[rng.gen_range(0..candidates.len())]
+            };
+            let word = LATIN1_WORDS[rng.gen_range(0..LATIN1_WORDS.len())];
+            for (i, field) in fields.iter().enumerate() {
+                if i != target {
+                    row.push(field);
+                    continue;
+                }
+                let text = String::from_utf8_lossy(field);
+                let text = if candidates.is_empty() { format!("{text} {word}") } else { text.into_owned() };
+                // Characters Latin-1 has no byte for become `?`, as encoders
+                // replacing them do.
+                row.push_with(|out| out.extend(text.chars().map(|c| u8::try_from(c).unwrap_or(b'?'))));
+            }
+            (csv_record(&row), Some(target))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use crate::schema::{Column, ColumnKind};
+    use crate::sink;
+    use clap::Parser;
+    use crate::output::{Destination, Outputs};
+    use std::collections::HashMap;
+    use std::fs;
+
+    /// The kinds of corruption in a manifest, by the line they're on.
+    fn corrupted_lines(manifest: &str) -> HashMap<usize, String> {
+        let mut reader = csv::Reader::from_reader(manifest.as_bytes());
+        reader.records().map(|r| r.unwrap()).map(|r| (r[1].parse::<usize>().unwrap(), r[3].to_string())).collect()
+    }
+
+    #[test]
+    fn lists_the_rows_it_corrupts() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_corrupt_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.csv");
+        let args = Args::parse_from(["gen", "--output", path.to_str().unwrap(), "--corrupt-rate", "0.1"]);
+        let outputs = Outputs::new(&args);
+        let columns: Vec<Column> = ["id", "name", "city"].map(|n| Column::new(n, ColumnKind::Sha256Hex)).into();
+        let mut sink = sink::open(&outputs, &Destination::File(path.clone()), &columns).unwrap();
+        let mut row = Row::new();
+        for i in 0..2000 {
+            row.clear();
+            for field in [i.to_string(), format!("name {i}"), "Zürich".to_string()] {
+                row.push(field.as_bytes());
+            }
+            sink.write_row(&row).unwrap();
+        }
+        sink.finish().unwrap();
+
+        let data = fs::read(&path).unwrap();
+        let lines: Vec<&[u8]> = data.strip_suffix(b"\n").unwrap().split(|&b| b == b'\n').collect();
+        assert_eq!(lines.len(), 2001);
+        let report = fs::read_to_string(dir.join("data.csv.manifest.csv")).unwrap();
+        let corrupted = corrupted_lines(&report);
+        assert!((120..280).contains(&corrupted.len()), "{}", corrupted.len());
+        for (number, line) in (1..).zip(&lines) {
+            let commas = line.iter().filter(|&&b| b == b',').count();
+            match corrupted.get(&number).map(String::as_str) {
+                None if number == 1 => assert_eq!(*line, b"id,name,city"),
+                None => assert_eq!(*line, format!("{0},name {0},Zürich", number - 2).as_bytes()),
+                Some("missing_fields") => assert!(commas < 2),
+                Some("extra_fields") => assert!(commas > 2),
+                Some("unterminated_quote") => assert_eq!(line.iter().filter(|&&b| b == b'"').count(), 1),
+                Some("control_characters") => {
+                    assert!(line.iter().any(|b| CONTROL_CHARACTERS.contains(b)));
+                    assert!(std::str::from_utf8(line).is_ok());
+                }
+                Some(kind) => panic!("{kind}"),
+            }
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn breaks_encodings() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_encodings_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.csv");
+        let args = Args::parse_from([
+            "gen",
+            "--output",
+            path.to_str().unwrap(),
+            "--corrupt-rate",
+            "0.2",
+            "--corrupt-kinds",
+            "invalid-utf8,latin1",
+        ]);
+        let columns: Vec<Column> = ["id", "city"].map(|n| Column::new(n, ColumnKind::Sha256Hex)).into();
+        let mut sink = sink::open(&Outputs::new(&args), &Destination::File(path.clone()), &columns).unwrap();
+        let mut row = Row::new();
+        for i in 0..500 {
+            row.clear();
+            row.push(i.to_string().as_bytes());
+            row.push("Zürich 🦀".as_bytes());
+            sink.write_row(&row).unwrap();
+        }
+        sink.finish().unwrap();
+
+        let data = fs::read(&path).unwrap();
+        let report = fs::read_to_string(dir.join("data.csv.manifest.csv")).unwrap();
+        let corrupted = corrupted_lines(&report);
+        assert!(!corrupted.is_empty());
+        for (number, line) in (1..).zip(data.strip_suffix(b"\n").unwrap().split(|&b| b == b'\n')) {
+            let id = number as i64 - 2;
+            match corrupted.get(&number).map(String::as_str) {
+                None => assert!(std::str::from_utf8(line).is_ok()),
+                Some("latin1") => assert_eq!(line, format!("{id},Z\u{fc}rich ?").chars().map(|c| c as u8).collect::<Vec<_>>()),
+                Some("invalid_utf8") => assert!(std::str::from_utf8(line).is_err()),
+                Some(kind) => panic!("{kind}"),
+            }
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+//! Embedded database file outputs: SQLite with the `sqlite` feature, and
+//! DuckDB with the `duckdb` feature.
+
+use std::fs;
+use std::path::Path;
+
+#[cfg(feature = "sqlite")]
+use rusqlite::Connection;
+
+use crate::BoxError;
+use crate::generator::Row;
+use crate::pg::quote_ident;
+#[cfg(feature = "duckdb")]
+use crate::schema::DECIMAL_PRECISION;
+use crate::schema::{Column, DataType};
+use crate::sink::{Sink, csv_size};
+
+/// The `CREATE TABLE` for `columns`, with `sql_type` naming the dialect's types.
+fn create_table_sql(table: &str, columns: &[Column], sql_type: fn(DataType) -> String) -> String {
+    let columns: Vec<_> = columns.iter().map(|c| format!("{} {}", quote_ident(&c.name), sql_type(c.kind.data_type()))).collect();
+    format!("CREATE TABLE {} ({})", quote_ident(table), columns.join(", "))
+}
+
+/// SQLite's type names. It has no date types; ISO dates and times sort and compare as text.
+#[cfg(feature = "sqlite")]
+fn sqlite_type(data_type: DataType) -> String {
+    match data_type {
+        DataType::String | DataType::Date | DataType::Timestamp => "TEXT".to_string(),
+        DataType::Integer => "INTEGER".to_string(),
+        DataType::Decimal { .. } => "NUMERIC".to_string(),
+    }
+}
+
+#[cfg(feature = "duckdb")]
+fn duckdb_type(data_type: DataType) -> String {
+    match data_type {
+        DataType::String => "VARCHAR".to_string(),
+        DataType::Integer => "BIGINT".to_string(),
+        DataType::Decimal { scale } => format!("DECIMAL({DECIMAL_PRECISION}, {scale})"),
+        DataType::Date => "DATE".to_string(),
+        DataType::Timestamp => "TIMESTAMP".to_string(),
+    }
+}
+
+/// Removes a database left by an earlier run, the way a CSV output is
+/// truncated, so each run starts from an empty table.
+fn replace(path: &Path) -> Result<(), BoxError> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Inserts rows into a table of a new SQLite file, all in one transaction
+/// that [`Sink::finish`] commits.
+#[cfg(feature = "sqlite")]
+pub struct SqliteSink {
+    connection: Connection,
+    insert: String,
+    types: Vec<DataType>,
+    bytes: u64,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteSink {
+    pub fn create(path: &Path, table: &str, columns: &[Column]) -> Result<Self, BoxError> {
+        replace(path)?;
+        let connection = Connection::open(path)?;
+        // The file is rebuilt from scratch on failure anyway, so skip the
+        // journal and fsyncs that make bulk loads slow.
+        connection.execute_batch("PRAGMA journal_mode = OFF; PRAGMA synchronous = OFF;")?;
+        connection.execute_batch(&create_table_sql(table, columns, sqlite_type))?;
+        connection.execute_batch("BEGIN")?;
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        Ok(SqliteSink {
+            connection,
+            insert: format!("INSERT INTO {} VALUES ({placeholders})", quote_ident(table)),
+            types: columns.iter().map(|c| c.kind.data_type()).collect(),
+            bytes: 0,
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Sink for SqliteSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        let mut statement = self.connection.prepare_cached(&self.insert)?;
+        for (i, (field, data_type)) in row.fields().zip(&self.types).enumerate() {
+            let field = std::str::from_utf8(field)?;
+            match data_type {
+                DataType::String | DataType::Decimal { .. } | DataType::Date | DataType::Timestamp => {
+                    statement.raw_bind_parameter(i + 1, field)?
+                }
+                DataType::Integer => statement.raw_bind_parameter(i + 1, field.parse::<i64>()?)?,
+            }
+        }
+        statement.raw_execute()?;
+        self.bytes += csv_size(row);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.connection.execute_batch("COMMIT")?;
+        self.connection.close().map_err(|(_, e)| e)?;
+        Ok(())
+    }
+}
+
+/// Appends rows to a table of a new DuckDB file.
+///
+/// Rows are buffered and handed to DuckDB's appender a batch at a time, since
+/// the appender borrows the connection.
+#[cfg(feature = "duckdb")]
+pub struct DuckDbSink {
+    connection: duckdb::Connection,
+    table: String,
+    types: Vec<DataType>,
+    pending: Vec<Vec<duckdb::types::Value>>,
+    bytes: u64,
+}
+
+#[cfg(feature = "duckdb")]
+impl DuckDbSink {
+    /// Rows buffered before they are appended.
+    const BATCH_ROWS: usize = 100_000;
+
+    pub fn create(path: &Path, table: &str, columns: &[Column]) -> Result<Self, BoxError> {
+        replace(path)?;
+        let connection = duckdb::Connection::open(path)?;
+        connection.execute_batch(&create_table_sql(table, columns, duckdb_type))?;
+        Ok(DuckDbSink {
+            connection,
+            table: table.to_string(),
+            types: columns.iter().map(|c| c.kind.data_type()).collect(),
+            pending: Vec::new(),
+            bytes: 0,
+        })
+    }
+
+    fn append_pending(&mut self) -> Result<(), BoxError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut appender = self.connection.appender(&self.table)?;
+        for row in self.pending.drain(..) {
+            appender.append_row(duckdb::appender_params_from_iter(row))?;
+        }
+        appender.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "duckdb")]
+impl Sink for DuckDbSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        use duckdb::types::Value;
+
+        let values = row
+            .fields()
+            .zip(&self.types)
+            .map(|(field, data_type)| {
+                let field = std::str::from_utf8(field)?;
+                Ok(match data_type {
+                    // The appender casts text to the column's decimal, date and timestamp types.
+                    DataType::String | DataType::Decimal { .. } | DataType::Date | DataType::Timestamp => {
+                        Value::Text(field.to_string())
+                    }
+                    DataType::Integer => Value::BigInt(field.parse()?),
+                })
+            })
+            .collect::<Result<_, BoxError>>()?;
+        self.pending.push(values);
+        self.bytes += csv_size(row);
+        if self.pending.len() >= Self::BATCH_ROWS {
+            self.append_pending()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.append_pending()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.append_pending()?;
+        self.connection.close().map_err(|(_, e)| e)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnKind;
+
+    fn columns() -> Vec<Column> {
+        vec![
+            Column::new("name", ColumnKind::Choice(vec!["Mia".into()])),
+            Column::new("age", ColumnKind::IntRange { min: 0, max: 99 }),
+        ]
+    }
+
+    fn row(name: &str, age: &str) -> Row {
+        let mut row = Row::new();
+        row.push(name.as_bytes());
+        row.push(age.as_bytes());
+        row
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn creates_tables_in_each_dialect() {
+        assert_eq!(
+            create_table_sql("data", &columns(), sqlite_type),
+            "CREATE TABLE \"data\" (\"name\" TEXT, \"age\" INTEGER)"
+        );
+    }
+
+    #[test]
+    fn sizes_rows_as_csv() {
+        assert_eq!(csv_size(&row("Mia", "42")), "Mia,42\n".len() as u64);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn writes_sqlite_files() {
+        let path = std::env::temp_dir().join(format!("csv_data_generator_test_{}.sqlite", std::process::id()));
+        let mut sink = Box::new(SqliteSink::create(&path, "people", &columns()).unwrap());
+        sink.write_row(&row("Mia", "42")).unwrap();
+        sink.write_row(&row("Leo", "7")).unwrap();
+        sink.finish().unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let (count, total): (i64, i64) =
+            connection.query_row("SELECT count(*), sum(age) FROM people", [], |r| Ok((r.get(0)?, r.get(1)?))).unwrap();
+        assert_eq!((count, total), (2, 49));
+        drop(connection);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "duckdb")]
+    #[test]
+    fn writes_duckdb_files() {
+        let path = std::env::temp_dir().join(format!("csv_data_generator_test_{}.duckdb", std::process::id()));
+        let mut sink = Box::new(DuckDbSink::create(&path, "people", &columns()).unwrap());
+        sink.write_row(&row("Mia", "42")).unwrap();
+        sink.write_row(&row("Leo", "7")).unwrap();
+        sink.finish().unwrap();
+
+        let connection = duckdb::Connection::open(&path).unwrap();
+        let (count, total): (i64, i64) =
+            connection.query_row("SELECT count(*), sum(age) FROM people", [], |r| Ok((r.get(0)?, r.get(1)?))).unwrap();
+        assert_eq!((count, total), (2, 49));
+        drop(connection);
+        fs::remove_file(path).unwrap();
+    }
+}
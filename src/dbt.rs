@@ -0,0 +1,103 @@
+//! dbt seeds: a capped CSV under `seeds/` with a properties file, so the
+//! output drops straight into a dbt project.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::schema::{Column, ColumnKind, DECIMAL_PRECISION, DataType};
+
+/// Rows in a seed when `--rows` isn't given; dbt seeds are meant to be small.
+const DEFAULT_ROWS: u64 = 1000;
+
+/// Quotes a string for YAML, which single quotes escape by doubling.
+fn yaml_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// The seed's properties: `column_types` so `dbt seed` doesn't guess, and the
+/// generic tests each column's generator guarantees.
+pub fn properties_yaml(name: &str, columns: &[Column]) -> String {
+    let data_type = |column: &Column| match column.kind.data_type() {
+        DataType::String => "varchar".to_string(),
+        DataType::Integer => "bigint".to_string(),
+        DataType::Decimal { scale } => format!("numeric({DECIMAL_PRECISION}, {scale})"),
+        DataType::Date => "date".to_string(),
+        DataType::Timestamp => "timestamp".to_string(),
+    };
+    let mut yaml = format!("version: 2\n\nseeds:\n  - name: {name}\n    config:\n      column_types:\n");
+    for column in columns {
+        let _ = writeln!(yaml, "        {}: {}", column.name, data_type(column));
+    }
+    yaml.push_str("    columns:\n");
+    for column in columns {
+        let _ = write!(yaml, "      - name: {}\n        data_type: {}\n        data_tests:\n          - not_null\n", column.name, data_type(column));
+        match &column.kind {
+            // 256, or a UUID's 122, random bits don't collide in a seed-sized
+            // table.
+            ColumnKind::Sha256Hex | ColumnKind::UuidV4 => yaml.push_str("          - unique\n"),
+            ColumnKind::Choice(values) => {
+                let values: Vec<_> = values.iter().map(|v| yaml_string(v)).collect();
+                let _ = writeln!(yaml, "          - accepted_values:\n              values: [{}]", values.join(", "));
+            }
+            ColumnKind::Sequence { .. } | ColumnKind::Label { .. } => yaml.push_str("          - unique\n"),
+            ColumnKind::IntRange { .. }
+            | ColumnKind::IntChoice(_)
+            | ColumnKind::ParentKey
+            | ColumnKind::ForeignKey { .. }
+            | ColumnKind::ChildNumber
+            | ColumnKind::Cycle(_)
+            | ColumnKind::DateRange { .. }
+            | ColumnKind::TimestampRange { .. }
+            | ColumnKind::Clock { .. }
+            | ColumnKind::Decimal { .. }
+            | ColumnKind::Format { .. }
+            | ColumnKind::Lagged { .. }
+            | ColumnKind::Unicode { .. }
+            | ColumnKind::NationalId { .. }
+            | ColumnKind::Geo { .. }
+            | ColumnKind::Script { .. }
+            | ColumnKind::Plugin { .. } => {}
+        }
+    }
+    yaml
+}
+
+/// Writes the properties file into `project/seeds` and returns `args` with
+/// the output pointed at the seed's CSV and the row cap applied.
+pub fn seed_args(args: &Args, project: &Path, columns: &[Column]) -> Result<Args, BoxError> {
+    let seeds = project.join("seeds");
+    fs::create_dir_all(&seeds).map_err(|e| format!("creating {}: {e}", seeds.display()))?;
+    let properties = seeds.join(format!("{}.yml", args.schema));
+    fs::write(&properties, properties_yaml(&args.schema, columns))
+        .map_err(|e| format!("writing {}: {e}", properties.display()))?;
+    println!("Wrote {}", properties.display());
+
+    let mut args = args.clone();
+    args.output = seeds.join(format!("{}.csv", args.schema)).to_string_lossy().into_owned();
+    args.rows.get_or_insert(DEFAULT_ROWS);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+    use crate::schema;
+
+    #[test]
+    fn declares_types_and_tests() {
+        let yaml = properties_yaml("orders", &schema::orders(Locale::En));
+        assert!(yaml.starts_with("version: 2\n\nseeds:\n  - name: orders\n    config:\n      column_types:\n        order_id: varchar\n"));
+        assert!(yaml.contains("      - name: order_id\n        data_type: varchar\n        data_tests:\n          - not_null\n          - unique\n"));
+        assert!(yaml.contains("              values: ['pending', 'paid', 'shipped', 'delivered', 'cancelled']\n"));
+        assert!(yaml.ends_with("      - name: amount_cents\n        data_type: bigint\n        data_tests:\n          - not_null\n"));
+    }
+
+    #[test]
+    fn quotes_yaml_strings() {
+        assert_eq!(yaml_string("O'Brien"), "'O''Brien'");
+    }
+}
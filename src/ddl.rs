@@ -0,0 +1,183 @@
+//! `CREATE TABLE` statements for the generated data, written next to it with `--ddl`.
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::BoxError;
+use crate::gcp::bigquery::bq_type;
+use crate::output::{Destination, Outputs, shard_location};
+use crate::schema::{Column, DECIMAL_PRECISION, DataType};
+use crate::{clickhouse, pg};
+
+/// A SQL dialect to write table DDL in.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DdlDialect {
+    Bigquery,
+    Snowflake,
+    Postgres,
+    Hive,
+    Clickhouse,
+}
+
+impl DdlDialect {
+    fn name(self) -> &'static str {
+        match self {
+            DdlDialect::Bigquery => "bigquery",
+            DdlDialect::Snowflake => "snowflake",
+            DdlDialect::Postgres => "postgres",
+            DdlDialect::Hive => "hive",
+            DdlDialect::Clickhouse => "clickhouse",
+        }
+    }
+}
+
+/// Backtick-quotes each part of a dotted name the way Hive wants.
+fn hive_ident(name: &str) -> String {
+    name.split('.').map(|part| format!("`{}`", part.replace('`', "``"))).collect::<Vec<_>>().join(".")
+}
+
+/// A Hive table over the CSV files in `directory`. Only `gs://` and `s3://`
+/// locations are ones a Hive cluster can read as they are written; other
+/// outputs get a managed table to `LOAD DATA` into.
+fn hive_sql(table: &str, columns: &[Column], directory: &str) -> String {
+    let columns: Vec<_> = columns
+        .iter()
+        .map(|c| {
+            let hive_type = match c.kind.data_type() {
+                DataType::String => "STRING".to_string(),
+                DataType::Integer => "BIGINT".to_string(),
+                DataType::Decimal { scale } => format!("DECIMAL({DECIMAL_PRECISION}, {scale})"),
+                DataType::Date => "DATE".to_string(),
+                DataType::Timestamp => "TIMESTAMP".to_string(),
+            };
+            format!("{} {hive_type}", hive_ident(&c.name))
+        })
+        .collect();
+    let (external, location) = if directory.starts_with("gs://") || directory.starts_with("s3://") {
+        ("EXTERNAL ", format!("\nLOCATION '{directory}'"))
+    } else {
+        ("", String::new())
+    };
+    format!(
+        "CREATE {external}TABLE IF NOT EXISTS {} ({})\nROW FORMAT DELIMITED FIELDS TERMINATED BY ','\nSTORED AS TEXTFILE{location}\nTBLPROPERTIES ('skip.header.line.count' = '1')",
+        hive_ident(table),
+        columns.join(", ")
+    )
+}
+
+fn bigquery_sql(table: &str, columns: &[Column]) -> String {
+    let columns: Vec<_> = columns.iter().map(|c| format!("`{}` {}", c.name, bq_type(c.kind.data_type()))).collect();
+    format!("CREATE TABLE IF NOT EXISTS `{table}` ({})", columns.join(", "))
+}
+
+/// Quotes a column name unless Snowflake would take it as is.
+fn snowflake_ident(name: &str) -> String {
+    let mut chars = name.chars();
+    let plain = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if plain { name.to_string() } else { format!("\"{}\"", name.replace('"', "\"\"")) }
+}
+
+/// Snowflake's `CREATE TABLE`, which `--sf-create-table` runs too.
+pub fn snowflake_sql(table: &str, columns: &[Column]) -> String {
+    let columns: Vec<_> = columns
+        .iter()
+        .map(|c| {
+            let sf_type = match c.kind.data_type() {
+                DataType::String => "VARCHAR".to_string(),
+                DataType::Integer => "NUMBER(38, 0)".to_string(),
+                DataType::Decimal { scale } => format!("NUMBER({DECIMAL_PRECISION}, {scale})"),
+                DataType::Date => "DATE".to_string(),
+                DataType::Timestamp => "TIMESTAMP_NTZ".to_string(),
+            };
+            format!("{} {sf_type}", snowflake_ident(&c.name))
+        })
+        .collect();
+    format!("CREATE TABLE IF NOT EXISTS {table} ({})", columns.join(", "))
+}
+
+/// The `CREATE TABLE` for `columns` in `dialect`, ending in a semicolon.
+pub fn create_table_sql(dialect: DdlDialect, table: &str, columns: &[Column], directory: &str) -> String {
+    let sql = match dialect {
+        DdlDialect::Bigquery => bigquery_sql(table, columns),
+        DdlDialect::Snowflake => snowflake_sql(table, columns),
+        DdlDialect::Postgres => pg::create_table_sql(table, columns),
+        DdlDialect::Hive => hive_sql(table, columns, directory),
+        DdlDialect::Clickhouse => clickhouse::create_table_sql(table, columns),
+    };
+    format!("{sql};\n")
+}
+
+/// The directory part of `location`, with its trailing slash; empty for a
+/// bare file name.
+fn directory_of(location: &str) -> &str {
+    location.rfind('/').map_or("", |i| &location[..=i])
+}
+
+/// Writes `<table>.<dialect>.sql` for each of `dialects` into the directory
+/// the data goes to, returning where they went.
+pub fn write(
+    outputs: &Outputs,
+    dialects: &[DdlDialect],
+    table: &str,
+    columns: &[Column],
+) -> Result<Vec<String>, BoxError> {
+    let data = shard_location(&outputs.args().output, 0);
+    if !matches!(
+        Destination::parse(&data)?,
+        Destination::File(_) | Destination::Gcs { .. } | Destination::S3 { .. } | Destination::Azure { .. }
+    ) {
+        return Err("--ddl needs a CSV file or cloud storage output to write next to".into());
+    }
+    let directory = directory_of(&data);
+    let mut written = Vec::new();
+    for &dialect in dialects {
+        let location = format!("{directory}{table}.{}.sql", dialect.name());
+        let mut output = outputs.open(&Destination::parse(&location)?)?;
+        output.write_all(create_table_sql(dialect, table, columns, directory).as_bytes())?;
+        output.finish()?;
+        written.push(location);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+    use crate::schema::{self, ColumnKind};
+
+    #[test]
+    fn writes_each_dialect() {
+        let columns = schema::users(Locale::En);
+        let sql = |dialect| create_table_sql(dialect, "users", &columns, "out/");
+        assert_eq!(sql(DdlDialect::Bigquery), "CREATE TABLE IF NOT EXISTS `users` (`id` STRING, `name` STRING, `age` INT64);\n");
+        assert_eq!(
+            sql(DdlDialect::Snowflake),
+            "CREATE TABLE IF NOT EXISTS users (id VARCHAR, name VARCHAR, age NUMBER(38, 0));\n"
+        );
+        assert_eq!(sql(DdlDialect::Postgres), "CREATE TABLE IF NOT EXISTS \"users\" (\"id\" text, \"name\" text, \"age\" bigint);\n");
+        assert!(sql(DdlDialect::Clickhouse).ends_with("ENGINE = MergeTree ORDER BY tuple();\n"));
+        assert!(sql(DdlDialect::Hive).starts_with("CREATE TABLE IF NOT EXISTS `users` (`id` STRING, `name` STRING, `age` BIGINT)\n"));
+
+        let columns = [Column::new("id", ColumnKind::Sha256Hex), Column::new("order date", ColumnKind::IntRange { min: 0, max: 1 })];
+        assert_eq!(
+            snowflake_sql("bench.public.users", &columns),
+            "CREATE TABLE IF NOT EXISTS bench.public.users (id VARCHAR, \"order date\" NUMBER(38, 0))"
+        );
+    }
+
+    #[test]
+    fn hive_tables_over_buckets_are_external() {
+        let sql = create_table_sql(DdlDialect::Hive, "users", &schema::users(Locale::En), "gs://bucket/seed/");
+        assert!(sql.starts_with("CREATE EXTERNAL TABLE"));
+        assert!(sql.contains("\nLOCATION 'gs://bucket/seed/'\n"));
+    }
+
+    #[test]
+    fn finds_the_data_directory() {
+        assert_eq!(directory_of("gs://bucket/seed/part-0.csv"), "gs://bucket/seed/");
+        assert_eq!(directory_of("data.csv"), "");
+    }
+}
@@ -0,0 +1,255 @@
+//! The `diff` subcommand: compares two runs' CSV, to check that a seed
+//! generates the same data across versions and platforms. Files are paired
+//! by name and compared header first, then row by row, or by `--key` when
+//! the rows may come in another order. Keyed files are first split by key
+//! into buckets on disk, so only a bucket of rows is held at a time.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::BoxError;
+use crate::cli::DiffArgs;
+use crate::validate::{Report, csv_files, csv_reader};
+
+/// About how many bytes of CSV go into each bucket of a keyed comparison.
+const BUCKET_BYTES: u64 = 64 << 20;
+
+/// The most buckets a file is split into, each an open file while it's split.
+const MAX_BUCKETS: u64 = 512;
+
+/// Compares `args.a` with `args.b`, returning an error if they differ.
+pub fn run(args: &DiffArgs) -> Result<(), BoxError> {
+    let mut report = Report { max: args.max_differences, problems: 0 };
+    let pairs = match (args.a.is_dir(), args.b.is_dir()) {
+        (true, true) => {
+            let names = |dir: &Path| -> Result<Vec<String>, BoxError> {
+                let files = csv_files(dir, |_| true)?;
+                Ok(files.iter().filter_map(|f| f.file_name()?.to_str().map(str::to_owned)).collect())
+            };
+            let (a, b) = (names(&args.a)?, names(&args.b)?);
+            for name in a.iter().filter(|n| !b.contains(n)) {
+                report.problem(args.a.join(name).display(), format!("isn't in {}", args.b.display()));
+            }
+            for name in b.iter().filter(|n| !a.contains(n)) {
+                report.problem(args.b.join(name).display(), format!("isn't in {}", args.a.display()));
+            }
+            a.iter().filter(|n| b.contains(n)).map(|n| (args.a.join(n), args.b.join(n))).collect()
+        }
+        (false, false) => vec![(args.a.clone(), args.b.clone())],
+        _ => return Err("diff compares two files or two directories".into()),
+    };
+    let mut rows = 0;
+    for (a, b) in &pairs {
+        rows += match &args.key {
+            Some(key) => diff_keyed(a, b, key, &mut report)?,
+            None => diff_rows(a, b, &mut report)?,
+        };
+    }
+    match report.problems {
+        0 => {
+            println!("No differences in {rows} rows of {} files", pairs.len());
+            Ok(())
+        }
+        1 => Err("found 1 difference".into()),
+        differences => Err(format!("found {differences} differences").into()),
+    }
+}
+
+/// Reports how `b` differs from `a` in a field of a row, if it does.
+fn diff_record(
+    at: impl std::fmt::Display,
+    header: &csv::ByteRecord,
+    a: &csv::ByteRecord,
+    b: &csv::ByteRecord,
+    report: &mut Report,
+) {
+    if a == b {
+        return;
+    }
+    if a.len() != b.len() {
+        report.problem(at, format!("{} fields, not {}", b.len(), a.len()));
+        return;
+    }
+    let differing: Vec<usize> = (0..a.len()).filter(|&i| a[i] != b[i]).collect();
+    let i = differing[0];
+    let text = |field: &[u8]| String::from_utf8_lossy(field).into_owned();
+    let others = match differing.len() - 1 {
+        0 => String::new(),
+        n => format!(" and {n} more fields"),
+    };
+    let name = header.get(i).map_or_else(|| format!("field {}", i + 1), text);
+    report.problem(at, format!("{name} is {:?}, not {:?}{others}", text(&b[i]), text(&a[i])));
+}
+
+/// Whether the headers of `a` and `b` match, reporting it if they don't.
+fn same_header(a: &Path, header_a: &csv::ByteRecord, header_b: &csv::ByteRecord, report: &mut Report) -> bool {
+    if header_a == header_b {
+        return true;
+    }
+    let names = |header: &csv::ByteRecord| header.iter().map(String::from_utf8_lossy).collect::<Vec<_>>().join(",");
+    report.problem(a.display(), format!("the header is {}, not {}", names(header_b), names(header_a)));
+    false
+}
+
+/// Compares the rows of `a` and `b` in order, returning the rows of `a`.
+fn diff_rows(a: &Path, b: &Path, report: &mut Report) -> Result<u64, BoxError> {
+    let (mut reader_a, mut reader_b) = (csv_reader(a)?, csv_reader(b)?);
+    let header = reader_a.byte_headers().map_err(|e| format!("{}: {e}", a.display()))?.clone();
+    let header_b = reader_b.byte_headers().map_err(|e| format!("{}: {e}", b.display()))?.clone();
+    let compare = same_header(a, &header, &header_b, report);
+    let (mut record_a, mut record_b) = (csv::ByteRecord::new(), csv::ByteRecord::new());
+    let (mut rows_a, mut rows_b) = (0u64, 0u64);
+    loop {
+        let more_a = reader_a.read_byte_record(&mut record_a).map_err(|e| format!("{}: {e}", a.display()))?;
+        let more_b = reader_b.read_byte_record(&mut record_b).map_err(|e| format!("{}: {e}", b.display()))?;
+        rows_a += u64::from(more_a);
+        rows_b += u64::from(more_b);
+        match (more_a, more_b) {
+            (true, true) if compare => diff_record(format!("{} row {rows_a}", a.display()), &header, &record_a, &record_b, report),
+            (false, false) => break,
+            _ => {}
+        }
+    }
+    if rows_a != rows_b {
+        report.problem(a.display(), format!("{rows_b} rows in {}, not {rows_a}", b.display()));
+    }
+    Ok(rows_a)
+}
+
+/// Compares the rows of `a` and `b` with the same value of the column `key`,
+/// in whatever order they come, returning the rows of `a`.
+fn diff_keyed(a: &Path, b: &Path, key: &str, report: &mut Report) -> Result<u64, BoxError> {
+    let (mut reader_a, mut reader_b) = (csv_reader(a)?, csv_reader(b)?);
+    let header = reader_a.byte_headers().map_err(|e| format!("{}: {e}", a.display()))?.clone();
+    let header_b = reader_b.byte_headers().map_err(|e| format!("{}: {e}", b.display()))?.clone();
+    let column =
+        header.iter().position(|name| name == key.as_bytes()).ok_or_else(|| format!("{}: no column {key}", a.display()))?;
+    if !same_header(a, &header, &header_b, report) {
+        return Ok(0);
+    }
+    let size = fs::metadata(a)?.len();
+    // Gzipped CSV of generated data is about a quarter the size.
+    let size = if a.extension().is_some_and(|e| e == "gz") { size * 4 } else { size };
+    let buckets = size.div_ceil(BUCKET_BYTES).clamp(1, MAX_BUCKETS);
+    let dir = std::env::temp_dir().join(format!("csv_data_generator_diff_{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let result = (|| {
+        let rows_a = split(&mut reader_a, a, column, &dir.join("a"), buckets)?;
+        let rows_b = split(&mut reader_b, b, column, &dir.join("b"), buckets)?;
+        let key_text = |record: &csv::ByteRecord| String::from_utf8_lossy(&record[column]).into_owned();
+        for bucket in 0..buckets {
+            let mut rows: HashMap<Vec<u8>, csv::ByteRecord> = HashMap::new();
+            let mut reader = csv_reader(&bucket_path(&dir.join("a"), bucket))?;
+            for record in reader.byte_records() {
+                let record = record?;
+                if let Some(first) = rows.insert(record[column].to_vec(), record) {
+                    report.problem(a.display(), format!("{key} {} is repeated", key_text(&first)));
+                }
+            }
+            let mut reader = csv_reader(&bucket_path(&dir.join("b"), bucket))?;
+            for record in reader.byte_records() {
+                let record = record?;
+                match rows.remove(&record[column]) {
+                    Some(row) => diff_record(format!("{} {key} {}", a.display(), key_text(&row)), &header, &row, &record, report),
+                    None => report.problem(b.display(), format!("{key} {} isn't in {}", key_text(&record), a.display())),
+                }
+            }
+            let mut missing: Vec<_> = rows.values().map(key_text).collect();
+            missing.sort_unstable();
+            for key_value in missing {
+                report.problem(a.display(), format!("{key} {key_value} isn't in {}", b.display()));
+            }
+        }
+        if rows_a != rows_b {
+            report.problem(a.display(), format!("{rows_b} rows in {}, not {rows_a}", b.display()));
+        }
+        Ok(rows_a)
+    })();
+    fs::remove_dir_all(&dir)?;
+    result
+}
+
+fn bucket_path(prefix: &Path, bucket: u64) -> PathBuf {
+    let mut path = prefix.as_os_str().to_owned();
+    path.push(format!("-{bucket}.csv"));
+    path.into()
+}
+
+/// Writes the rows of `reader` into `buckets` files by the hash of the
+/// field at `column`, returning how many there were. Each bucket's first
+/// line is a header, as CSV readers expect.
+fn split(
+    reader: &mut csv::Reader<Box<dyn std::io::Read>>,
+    path: &Path,
+    column: usize,
+    prefix: &Path,
+    buckets: u64,
+) -> Result<u64, BoxError> {
+    let header = reader.byte_headers()?.clone();
+    let mut writers = (0..buckets)
+        .map(|bucket| {
+            let mut writer = csv::Writer::from_writer(BufWriter::new(File::create(bucket_path(prefix, bucket))?));
+            writer.write_byte_record(&header)?;
+            Ok(writer)
+        })
+        .collect::<Result<Vec<_>, BoxError>>()?;
+    let mut record = csv::ByteRecord::new();
+    let mut rows = 0;
+    while reader.read_byte_record(&mut record).map_err(|e| format!("{}: {e}", path.display()))? {
+        rows += 1;
+        let field = record.get(column).ok_or_else(|| format!("{} row {rows}: no {}th field", path.display(), column + 1))?;
+        let mut hasher = DefaultHasher::new();
+        field.hash(&mut hasher);
+        writers[(hasher.finish() % buckets) as usize].write_byte_record(&record)?;
+    }
+    for mut writer in writers {
+        writer.flush()?;
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Args, Command};
+    use clap::Parser;
+
+    fn diff(args: &[&str]) -> Result<(), BoxError> {
+        let Some(Command::Diff(args)) = Args::parse_from(args).command else { unreachable!() };
+        run(&args)
+    }
+
+    #[test]
+    fn finds_what_changed() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_diff_test_{}", std::process::id()));
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        let rows = |n: u32| (1..=n).map(|i| format!("{i},name {i}\n")).collect::<String>();
+        fs::write(a.join("users.csv"), format!("id,name\n{}", rows(100))).unwrap();
+        fs::write(b.join("users.csv"), format!("id,name\n{}", rows(100))).unwrap();
+        let (a_path, b_path) = (a.to_str().unwrap(), b.to_str().unwrap());
+        diff(&["gen", "diff", a_path, b_path]).unwrap();
+
+        // The same rows in another order only match by key.
+        let mut reordered: Vec<String> = rows(100).lines().map(str::to_owned).collect();
+        reordered.reverse();
+        fs::write(b.join("users.csv"), format!("id,name\n{}\n", reordered.join("\n"))).unwrap();
+        assert!(diff(&["gen", "diff", a_path, b_path]).is_err());
+        diff(&["gen", "diff", a_path, b_path, "--key", "id"]).unwrap();
+
+        reordered[0] = "100,someone else".to_string();
+        reordered[1] = "101,name 101".to_string();
+        fs::write(b.join("users.csv"), format!("id,name\n{}\n", reordered.join("\n"))).unwrap();
+        let error = diff(&["gen", "diff", a_path, b_path, "--key", "id"]).unwrap_err();
+        // 100 changed, 99 missing and 101 added.
+        assert_eq!(error.to_string(), "found 3 differences");
+        fs::write(b.join("extra.csv"), "id\n").unwrap();
+        assert_eq!(diff(&["gen", "diff", a_path, b_path, "--key", "id"]).unwrap_err().to_string(), "found 4 differences");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,165 @@
+//! Schema drift over a run's shards, for testing how ingestion copes with
+//! schema evolution: each `--drift` changes the columns from a shard on, so
+//! the shards are a series of files with the schema they had at the time.
+
+use serde::Serialize;
+
+use crate::BoxError;
+use crate::schema::{Column, ColumnKind, days_from_civil};
+
+/// How a column changes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    /// A new column at the end.
+    Add { name: String, kind: ColumnKind },
+    Rename { from: String, to: String },
+    Drop(String),
+    /// The column stays where it is, with values of another type.
+    Retype { name: String, kind: ColumnKind },
+}
+
+/// A change to the columns from `shard` on.
+#[derive(Debug, Clone, Serialize)]
+pub struct Drift {
+    pub shard: u64,
+    pub change: Change,
+}
+
+/// Parses `SHARD:add:COLUMN:TYPE`, `SHARD:rename:COLUMN:NEW`,
+/// `SHARD:drop:COLUMN` or `SHARD:type:COLUMN:TYPE`.
+pub fn parse(s: &str) -> Result<Drift, String> {
+    let parts: Vec<&str> = s.split(':').collect();
+    let shard = match parts[0].parse::<u64>() {
+        Ok(shard) if shard > 0 => shard,
+        _ => return Err(format!("{s} must start with the shard it applies from, 1 or later")),
+    };
+    let change = match parts[1..] {
+        ["add", name, kind] => Change::Add { name: name.to_string(), kind: new_kind(name, kind)? },
+        ["rename", from, to] => Change::Rename { from: from.to_string(), to: to.to_string() },
+        ["drop", name] => Change::Drop(name.to_string()),
+        ["type", name, kind] => Change::Retype { name: name.to_string(), kind: new_kind(name, kind)? },
+        _ => {
+            return Err(format!(
+                "{s} is not SHARD:add:COLUMN:TYPE, SHARD:rename:COLUMN:NEW, SHARD:drop:COLUMN or SHARD:type:COLUMN:TYPE"
+            ));
+        }
+    };
+    Ok(Drift { shard, change })
+}
+
+/// The values a column drifted to `kind` gets: integers below 1,000,
+/// amounts below 1,000.00, labels like `score-000042`, and dates and times
+/// from 2020 through 2024.
+fn new_kind(name: &str, kind: &str) -> Result<ColumnKind, String> {
+    let (start, end) = (days_from_civil(2020, 1, 1), days_from_civil(2025, 1, 1));
+    match kind {
+        "int" => Ok(ColumnKind::IntRange { min: 0, max: 999 }),
+        "decimal" => Ok(ColumnKind::Decimal { min: 0, max: 99_999, scale: 2 }),
+        "string" => Ok(ColumnKind::Label { prefix: format!("{name}-"), width: 6 }),
+        "date" => Ok(ColumnKind::DateRange { min: start, max: end - 1 }),
+        "timestamp" => Ok(ColumnKind::TimestampRange { min: start * 86_400, max: end * 86_400 - 1 }),
+        _ => Err(format!("{kind} is not a column type: try int, decimal, string, date or timestamp")),
+    }
+}
+
+/// The columns each shard from the first of a version on has, starting
+/// with `columns` at shard 0 and changing at every shard `drift` names.
+pub fn versions(columns: &[Column], drift: &[Drift], shards: u64) -> Result<Vec<(u64, Vec<Column>)>, BoxError> {
+    let mut drift = drift.to_vec();
+    drift.sort_by_key(|d| d.shard);
+    let mut versions = vec![(0, columns.to_vec())];
+    for Drift { shard, change } in drift {
+        if shard >= shards {
+            return Err(format!("--drift: shard {shard} is past the last of {shards} shards").into());
+        }
+        if versions.last().expect("the first version").0 != shard {
+            let latest = versions.last().expect("the first version").1.clone();
+            versions.push((shard, latest));
+        }
+        let columns = &mut versions.last_mut().expect("the version being changed").1;
+        apply(columns, change).map_err(|e| format!("--drift: shard {shard}: {e}"))?;
+    }
+    Ok(versions)
+}
+
+fn apply(columns: &mut Vec<Column>, change: Change) -> Result<(), String> {
+    let find = |columns: &[Column], name: &str| {
+        columns.iter().position(|c| c.name == name).ok_or_else(|| format!("no column named {name}"))
+    };
+    let unused = |columns: &[Column], name: &str| {
+        if columns.iter().any(|c| c.name == name) { Err(format!("there is already a column named {name}")) } else { Ok(()) }
+    };
+    match change {
+        Change::Add { name, kind } => {
+            unused(columns, &name)?;
+            columns.push(Column::new(&name, kind));
+        }
+        Change::Rename { from, to } => {
+            let index = find(columns, &from)?;
+            unused(columns, &to)?;
+            columns[index].name = to;
+        }
+        Change::Drop(name) => {
+            let index = find(columns, &name)?;
+            if columns.len() == 1 {
+                return Err(format!("{name} is the only column"));
+            }
+            // Lagged columns refer to the column they lag by position.
+            for column in columns.iter_mut() {
+                if let ColumnKind::Lagged { of, .. } = &mut column.kind {
+                    match (*of).cmp(&index) {
+                        std::cmp::Ordering::Equal => return Err(format!("{} lags {name}", column.name)),
+                        std::cmp::Ordering::Greater => *of -= 1,
+                        std::cmp::Ordering::Less => {}
+                    }
+                }
+            }
+            columns.remove(index);
+        }
+        Change::Retype { name, kind } => {
+            let index = find(columns, &name)?;
+            columns[index].kind = kind;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+    use crate::schema::{self, DataType};
+
+    fn names(columns: &[Column]) -> Vec<&str> {
+        columns.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    #[test]
+    fn changes_build_on_each_other() {
+        let drift: Vec<Drift> = ["3:drop:age", "1:add:score:decimal", "3:rename:name:full_name", "5:type:score:string"]
+            .map(|s| parse(s).unwrap())
+            .into();
+        let versions = versions(&schema::users(Locale::En), &drift, 6).unwrap();
+        let starts: Vec<u64> = versions.iter().map(|v| v.0).collect();
+        assert_eq!(starts, [0, 1, 3, 5]);
+        assert_eq!(names(&versions[0].1), ["id", "name", "age"]);
+        assert_eq!(names(&versions[1].1), ["id", "name", "age", "score"]);
+        assert_eq!(names(&versions[2].1), ["id", "full_name", "score"]);
+        assert_eq!(versions[2].1[2].kind.data_type(), DataType::Decimal { scale: 2 });
+        assert_eq!(versions[3].1[2].kind.data_type(), DataType::String);
+    }
+
+    #[test]
+    fn bad_drift_is_explained() {
+        let error = |specs: &[&str], shards| {
+            let drift: Vec<Drift> = specs.iter().map(|s| parse(s).unwrap()).collect();
+            versions(&schema::users(Locale::En), &drift, shards).unwrap_err().to_string()
+        };
+        assert_eq!(error(&["2:drop:email"], 4), "--drift: shard 2: no column named email");
+        assert_eq!(error(&["2:rename:id:age"], 4), "--drift: shard 2: there is already a column named age");
+        assert_eq!(error(&["4:drop:age"], 4), "--drift: shard 4 is past the last of 4 shards");
+        assert!(parse("0:drop:age").is_err());
+        assert!(parse("2:add:score:float").unwrap_err().contains("not a column type"));
+    }
+}
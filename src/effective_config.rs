@@ -0,0 +1,81 @@
+//! `--effective-config`: every setting of a run as JSON, written before it
+//! generates anything, to keep next to the data it describes and to compare
+//! runs by. `args` is the command line with its defaults filled in, then
+//! `config` holds the tables of `--config` or a preset of related tables,
+//! in the shape `--config` reads back from a `.json` file, or `columns` the
+//! columns of `--schema`. The presets that generate rows themselves are
+//! described by `args` alone.
+
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::output::{Destination, Outputs};
+use crate::presets;
+use crate::relational::Config;
+use crate::schema::Column;
+
+#[derive(Serialize)]
+struct EffectiveConfig<'a> {
+    version: &'static str,
+    args: &'a Args,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config: Option<Config>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    columns: Option<Vec<Column>>,
+}
+
+/// Writes the settings of `args` to `--effective-config`, if it's given.
+pub fn write(args: &Args) -> Result<(), BoxError> {
+    let Some(location) = &args.effective_config else { return Ok(()) };
+    let mut output = Outputs::new(args).open(&Destination::parse(location)?)?;
+    output.write_all(&json(args)?)?;
+    output.finish()
+}
+
+/// The settings of `args`, as `--effective-config` writes them.
+pub fn json(args: &Args) -> Result<Vec<u8>, BoxError> {
+    let config = match (&args.config, args.preset.preset) {
+        (Some(path), _) => Some(Config::load(path)?),
+        (None, Some(preset)) => presets::config(preset, &args.preset),
+        (None, None) => None,
+    };
+    let columns = match (&args.config, args.preset.preset) {
+        (None, None) => Some(crate::columns(args)?),
+        _ => None,
+    };
+    let effective = EffectiveConfig { version: env!("CARGO_PKG_VERSION"), args, config, columns };
+    let mut json = serde_json::to_vec_pretty(&effective)?;
+    json.push(b'\n');
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn configs_round_trip() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_effective_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let location = dir.join("effective.json");
+        let args = Args::parse_from(["gen", "--preset", "star", "--effective-config", location.to_str().unwrap()]);
+        write(&args).unwrap();
+        let written: serde_json::Value = serde_json::from_slice(&std::fs::read(&location).unwrap()).unwrap();
+        assert_eq!(written["args"]["preset"]["preset"], "star");
+        assert_eq!(written["args"]["format"], "csv");
+        assert!(written.get("columns").is_none());
+
+        // The config reads back as what it was written from.
+        let path = dir.join("star.json");
+        std::fs::write(&path, written["config"].to_string()).unwrap();
+        let config = Config::load(&path).unwrap();
+        let expected = presets::config(presets::Preset::Star, &args.preset).unwrap();
+        assert_eq!(serde_json::to_value(&config).unwrap(), serde_json::to_value(&expected).unwrap());
+        assert_eq!(toml::from_str::<Config>(&toml::to_string(&config).unwrap()).unwrap().tables.len(), expected.tables.len());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,448 @@
+//! The entity resolution preset: the same people recorded in two differently
+//! shaped tables, with the variations real systems pick up, and a ground
+//! truth of which records are whose.
+//!
+//! Each person appears in `customers`, `accounts` or both, sometimes more
+//! than once in the same table. Every record is drawn from the person's own
+//! details, and each of its fields varies with `--entity-noise` probability:
+//! a nickname or a typo in a name, an abbreviated or reordered address, a
+//! missing email, another spelling of the company. Phone numbers are written
+//! in whichever format the record's source happened to use. Records are
+//! shuffled within their table, so duplicates aren't next to each other, and
+//! `ground_truth` maps every record to its person's `entity_id`.
+
+use rand::Rng;
+use rand::rngs::ThreadRng;
+use rand::seq::SliceRandom;
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::generator::Row;
+use crate::output::{Destination, Outputs};
+use crate::presets;
+use crate::relational::table_location;
+use crate::schema::{Column, ColumnKind};
+use crate::signal;
+use crate::sink;
+
+const TABLES: [&str; 3] = ["customers", "accounts", "ground_truth"];
+
+/// People at scale factor 1.
+const ENTITIES: f64 = 10_000.0;
+/// The chances of a person having a record in `customers` and in `accounts`.
+const IN_CUSTOMERS: f64 = 0.8;
+const IN_ACCOUNTS: f64 = 0.6;
+/// The chance of a table having one more record of a person who's in it.
+const DUPLICATE_RATE: f64 = 0.15;
+
+/// First names, with the nicknames they go by, if any.
+const FIRST_NAMES: &[(&str, &[&str])] = &[
+    ("William", &["Bill", "Will", "Billy"]),
+    ("Robert", &["Bob", "Rob", "Bobby"]),
+    ("Elizabeth", &["Liz", "Beth", "Betty"]),
+    ("Katherine", &["Kate", "Kathy", "Katie"]),
+    ("Margaret", &["Peggy", "Maggie", "Meg"]),
+    ("Richard", &["Rick", "Dick", "Rich"]),
+    ("James", &["Jim", "Jimmy"]),
+    ("Michael", &["Mike", "Mikey"]),
+    ("Jennifer", &["Jen", "Jenny"]),
+    ("Thomas", &["Tom", "Tommy"]),
+    ("Christopher", &["Chris"]),
+    ("Daniel", &["Dan", "Danny"]),
+    ("Patricia", &["Pat", "Trish"]),
+    ("Joseph", &["Joe", "Joey"]),
+    ("Susan", &["Sue", "Suzy"]),
+    ("Anthony", &["Tony"]),
+    ("Alexander", &["Alex", "Xander"]),
+    ("Samuel", &["Sam", "Sammy"]),
+    ("Rebecca", &["Becky", "Becca"]),
+    ("Nicholas", &["Nick", "Nicky"]),
+    ("Benjamin", &["Ben", "Benny"]),
+    ("Jonathan", &["Jon", "Johnny"]),
+    ("Victoria", &["Vicky", "Tori"]),
+    ("Deborah", &["Debbie", "Deb"]),
+    ("Edward", &["Ed", "Eddie", "Ted"]),
+    ("Olivia", &[]),
+    ("Sophia", &[]),
+    ("Emma", &[]),
+    ("Noah", &[]),
+    ("Liam", &[]),
+    ("Priya", &[]),
+    ("Wei", &[]),
+    ("Mateo", &[]),
+    ("Aisha", &[]),
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Smith", "Johnson", "Williams", "Brown", "Jones", "Garcia", "Miller", "Davis", "Rodriguez", "Martinez",
+    "Hernandez", "Lopez", "Gonzalez", "Wilson", "Anderson", "Thomas", "Taylor", "Moore", "Jackson", "Martin",
+    "Lee", "Thompson", "White", "Harris", "Clark", "Lewis", "Robinson", "Walker", "Young", "Allen", "O'Brien",
+    "McDonald", "Nguyen", "Patel", "Kim", "Chen",
+];
+
+const STREETS: &[&str] = &["Oak", "Maple", "Cedar", "Pine", "Elm", "Washington", "Lake", "Hill", "Park", "Main", "Sunset"];
+
+/// Street suffixes, spelled out and abbreviated.
+const SUFFIXES: &[(&str, &str)] =
+    &[("Street", "St"), ("Avenue", "Ave"), ("Road", "Rd"), ("Boulevard", "Blvd"), ("Lane", "Ln"), ("Drive", "Dr")];
+
+/// Unit designators, spelled out and abbreviated.
+const UNITS: &[(&str, &str)] = &[("Apartment", "Apt"), ("Suite", "Ste"), ("Unit", "#")];
+
+const CITIES: &[(&str, &str)] = &[
+    ("Springfield", "IL"),
+    ("Portland", "OR"),
+    ("Austin", "TX"),
+    ("Columbus", "OH"),
+    ("Denver", "CO"),
+    ("Madison", "WI"),
+    ("Raleigh", "NC"),
+    ("Sacramento", "CA"),
+    ("Boise", "ID"),
+    ("Richmond", "VA"),
+];
+
+const COMPANIES: &[&str] = &["Acme", "Globex", "Initech", "Umbrella", "Stark", "Wayne", "Hooli", "Vandelay", "Soylent"];
+
+/// Legal suffixes of company names, each with its other spellings.
+const COMPANY_SUFFIXES: &[&[&str]] = &[
+    &["Inc.", "Inc", "Incorporated"],
+    &["Corp.", "Corp", "Corporation"],
+    &["LLC", "L.L.C."],
+    &["Co.", "Company"],
+];
+
+const EMAIL_DOMAINS: &[&str] = &["gmail.com", "yahoo.com", "outlook.com", "icloud.com"];
+
+fn pick<'a, T: ?Sized, R: Rng + ?Sized>(rng: &mut R, values: &'a [&'a T]) -> &'a T {
+    values[rng.gen_range(0..values.len())]
+}
+
+/// One person's details as they really are.
+struct Entity {
+    first: usize,
+    last: &'static str,
+    email: String,
+    /// The ten digits of a US phone number.
+    phone: String,
+    number: u32,
+    street: &'static str,
+    suffix: usize,
+    /// A designator and number, for people who live in a unit.
+    unit: Option<(usize, u32)>,
+    city: usize,
+    zip: String,
+    company: &'static str,
+    company_suffix: usize,
+}
+
+impl Entity {
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let first = rng.gen_range(0..FIRST_NAMES.len());
+        let last = pick(rng, LAST_NAMES);
+        let company = pick(rng, COMPANIES);
+        let local = match rng.gen_range(0..3) {
+            0 => format!("{}.{}", FIRST_NAMES[first].0, last),
+            1 => format!("{}{}{}", &FIRST_NAMES[first].0[..1], last, rng.gen_range(1..100)),
+            _ => format!("{}_{}", FIRST_NAMES[first].0, rng.gen_range(1000..10_000)),
+        };
+        let domain = if rng.gen_bool(0.3) { format!("{}.com", company.to_lowercase()) } else { pick(rng, EMAIL_DOMAINS).to_string() };
+        Entity {
+            first,
+            last,
+            email: format!("{}@{domain}", local.to_lowercase().replace('\'', "")),
+            phone: format!("{}{}{:04}", rng.gen_range(201..990), rng.gen_range(200..1000), rng.gen_range(0..10_000)),
+            number: rng.gen_range(1..10_000),
+            street: pick(rng, STREETS),
+            suffix: rng.gen_range(0..SUFFIXES.len()),
+            unit: rng.gen_bool(0.3).then(|| (rng.gen_range(0..UNITS.len()), rng.gen_range(1..500))),
+            city: rng.gen_range(0..CITIES.len()),
+            zip: format!("{:05}", rng.gen_range(10_000..100_000)),
+            company,
+            company_suffix: rng.gen_range(0..COMPANY_SUFFIXES.len()),
+        }
+    }
+}
+
+/// Changes one letter of `s` the way a typist would: swapping two, dropping
+/// one, doubling one or hitting another.
+fn typo<R: Rng + ?Sized>(rng: &mut R, s: &str) -> String {
+    let mut chars: Vec<char> = s.chars().collect();
+    if chars.len() < 2 {
+        return s.to_string();
+    }
+    let i = rng.gen_range(0..chars.len() - 1);
+    match rng.gen_range(0..4) {
+        0 => chars.swap(i, i + 1),
+        1 => {
+            chars.remove(i + 1);
+        }
+        2 => chars.insert(i, chars[i]),
+        _ => chars[i + 1] = rng.gen_range(b'a'..=b'z') as char,
+    }
+    chars.into_iter().collect()
+}
+
+/// How records write a person's details, drawn afresh for each field.
+struct Variant<'a> {
+    rng: &'a mut ThreadRng,
+    noise: f64,
+}
+
+impl Variant<'_> {
+    fn varies(&mut self) -> bool {
+        self.rng.gen_bool(self.noise)
+    }
+
+    fn first_name(&mut self, entity: &Entity) -> String {
+        let (name, nicknames) = FIRST_NAMES[entity.first];
+        if !self.varies() {
+            return name.to_string();
+        }
+        match nicknames {
+            [] => typo(self.rng, name),
+            nicknames if self.rng.gen_bool(0.7) => pick(self.rng, nicknames).to_string(),
+            _ => typo(self.rng, name),
+        }
+    }
+
+    fn last_name(&mut self, entity: &Entity) -> String {
+        match self.varies() {
+            false => entity.last.to_string(),
+            true if self.rng.gen_bool(0.3) => entity.last.to_uppercase(),
+            true => typo(self.rng, entity.last),
+        }
+    }
+
+    fn email(&mut self, entity: &Entity) -> String {
+        match self.varies() {
+            false => entity.email.clone(),
+            true => match self.rng.gen_range(0..3) {
+                0 => String::new(),
+                1 => entity.email.to_uppercase(),
+                _ => typo(self.rng, &entity.email),
+            },
+        }
+    }
+
+    fn phone(&mut self, entity: &Entity) -> String {
+        if self.rng.gen_bool(self.noise / 3.0) {
+            return String::new();
+        }
+        let (area, exchange, line) = (&entity.phone[..3], &entity.phone[3..6], &entity.phone[6..]);
+        match self.rng.gen_range(0..5) {
+            0 => format!("({area}) {exchange}-{line}"),
+            1 => format!("{area}-{exchange}-{line}"),
+            2 => format!("{area}.{exchange}.{line}"),
+            3 => format!("+1 {area} {exchange} {line}"),
+            _ => entity.phone.clone(),
+        }
+    }
+
+    /// The street line, with the unit after the street or, varied, before it.
+    fn street(&mut self, entity: &Entity) -> String {
+        let (suffix, short) = SUFFIXES[entity.suffix];
+        let suffix = if self.varies() { short } else { suffix };
+        let street = format!("{} {} {suffix}", entity.number, entity.street);
+        let street = if self.varies() { typo(self.rng, &street) } else { street };
+        let Some((unit, number)) = entity.unit else {
+            return street;
+        };
+        let (unit, short) = UNITS[unit];
+        let unit = if short == "#" { format!("#{number}") } else { format!("{} {number}", if self.varies() { short } else { unit }) };
+        if self.varies() { format!("{unit}, {street}") } else { format!("{street} {unit}") }
+    }
+
+    fn company(&mut self, entity: &Entity) -> String {
+        let spellings = COMPANY_SUFFIXES[entity.company_suffix];
+        let suffix = if self.varies() { pick(self.rng, spellings) } else { spellings[0] };
+        let name = if self.varies() { entity.company.to_uppercase() } else { entity.company.to_string() };
+        if self.varies() { name } else { format!("{name} {suffix}") }
+    }
+}
+
+/// The columns of each table, in [`TABLES`] order. Only the names and types
+/// of these matter; the values are written below.
+fn columns() -> [Vec<Column>; 3] {
+    let integer = |name| Column::new(name, ColumnKind::Sequence { start: 1 });
+    let string = |name| Column::new(name, ColumnKind::Choice(Vec::new()));
+    [
+        vec![
+            integer("customer_id"),
+            string("first_name"),
+            string("last_name"),
+            string("email"),
+            string("phone"),
+            string("street"),
+            string("city"),
+            string("state"),
+            string("zip"),
+            string("employer"),
+        ],
+        vec![
+            integer("account_id"),
+            string("full_name"),
+            string("email"),
+            string("phone"),
+            string("billing_address"),
+            string("company"),
+        ],
+        vec![integer("entity_id"), string("table"), integer("record_id")],
+    ]
+}
+
+/// A `customers` record: names, address and employer in fields of their own.
+fn customer(variant: &mut Variant<'_>, entity: &Entity, row: &mut Row) {
+    let (city, state) = CITIES[entity.city];
+    row.push(variant.first_name(entity).as_bytes());
+    row.push(variant.last_name(entity).as_bytes());
+    row.push(variant.email(entity).as_bytes());
+    row.push(variant.phone(entity).as_bytes());
+    row.push(variant.street(entity).as_bytes());
+    row.push(city.as_bytes());
+    row.push(state.as_bytes());
+    row.push(entity.zip.as_bytes());
+    row.push(variant.company(entity).as_bytes());
+}
+
+/// An `accounts` record: one name field, last name first or not, and the
+/// whole address on one line.
+fn account(variant: &mut Variant<'_>, entity: &Entity, row: &mut Row) {
+    let (first, last) = (variant.first_name(entity), variant.last_name(entity));
+    let name = if variant.rng.gen_bool(0.5) { format!("{last}, {first}") } else { format!("{first} {last}") };
+    let (city, state) = CITIES[entity.city];
+    row.push(name.as_bytes());
+    row.push(variant.email(entity).as_bytes());
+    row.push(variant.phone(entity).as_bytes());
+    row.push(format!("{}, {city}, {state} {}", variant.street(entity), entity.zip).as_bytes());
+    row.push(variant.company(entity).as_bytes());
+}
+
+/// A table's records, each with the id of the entity it is of.
+type Records = Vec<(u64, Row)>;
+
+/// Fills a record of one of the tables.
+type Fill = fn(&mut Variant<'_>, &Entity, &mut Row);
+
+/// Draws every entity and its source records up front, then writes each
+/// source shuffled with a ground truth row per record.
+pub fn generate(args: &Args) -> Result<(), BoxError> {
+    presets::check_tables(args, "entity resolution", &TABLES)?;
+    let outputs = Outputs::new(args);
+    let [customer_columns, account_columns, truth_columns] = columns();
+    let mut rng = rand::thread_rng();
+    let mut entities = ((ENTITIES * args.preset.scale_factor).round() as u64).max(1);
+
+    // Each table's records, with the person each is of, before shuffling.
+    let (mut customers, mut accounts): (Records, Records) = (Vec::new(), Vec::new());
+    for id in 1..=entities {
+        if signal::interrupted() {
+            entities = id - 1;
+            break;
+        }
+        let entity = Entity::random(&mut rng);
+        let mut variant = Variant { rng: &mut rng, noise: args.preset.entity_noise };
+        let sources: [(_, _, Fill); 2] = [(&mut customers, IN_CUSTOMERS, customer), (&mut accounts, IN_ACCOUNTS, account)];
+        for (records, chance, fill) in sources {
+            if !variant.rng.gen_bool(chance) {
+                continue;
+            }
+            loop {
+                let mut row = Row::new();
+                fill(&mut variant, &entity, &mut row);
+                records.push((id, row));
+                if !variant.rng.gen_bool(DUPLICATE_RATE) {
+                    break;
+                }
+            }
+        }
+    }
+
+    let truth_location = table_location(&args.output, TABLES[2]);
+    let mut truth = sink::open(&outputs, &Destination::parse(&truth_location)?, &truth_columns)?;
+    let mut truth_rows = 0;
+    let mut row = Row::new();
+    for (table, columns, mut records) in [(TABLES[0], customer_columns, customers), (TABLES[1], account_columns, accounts)] {
+        records.shuffle(&mut rng);
+        let location = table_location(&args.output, table);
+        let mut sink = sink::open(&outputs, &Destination::parse(&location)?, &columns)?;
+        for (record, (entity, fields)) in (1u64..).zip(&records) {
+            let record = itoa::Buffer::new().format(record).as_bytes().to_vec();
+            row.clear();
+            row.push(&record);
+            for field in fields.fields() {
+                row.push(field);
+            }
+            sink.write_row(&row)?;
+            row.clear();
+            row.push(itoa::Buffer::new().format(*entity).as_bytes());
+            row.push(table.as_bytes());
+            row.push(&record);
+            truth.write_row(&row)?;
+        }
+        sink.finish()?;
+        truth_rows += records.len();
+        println!("Successfully generated {location} ({} rows)", records.len());
+    }
+    truth.finish()?;
+    println!("Successfully generated {truth_location} ({truth_rows} rows of {entities} entities)");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::testing::{read, temp_dir};
+    use clap::Parser;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn typos_change_one_letter() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let changed = typo(&mut rng, "Johnson");
+            assert!(changed.len().abs_diff("Johnson".len()) <= 1, "{changed}");
+        }
+        assert_eq!(typo(&mut rng, "J"), "J");
+    }
+
+    #[test]
+    fn records_map_to_their_entities() {
+        let dir = temp_dir("entities");
+        let template = dir.join("{table}.csv");
+        let args = Args::parse_from([
+            "gen",
+            "--preset",
+            "entity-resolution",
+            "--scale-factor",
+            "0.05",
+            "--output",
+            template.to_str().unwrap(),
+        ]);
+        generate(&args).unwrap();
+
+        let table = |name: &str| read(dir.join(format!("{name}.csv")));
+        let (customers, accounts, truth) = (table("customers"), table("accounts"), table("ground_truth"));
+        assert_eq!(truth.len(), customers.len() + accounts.len());
+        let entity: HashMap<(&str, &str), &str> = truth.iter().map(|t| ((t[1].as_str(), t[2].as_str()), t[0].as_str())).collect();
+        assert_eq!(entity.len(), truth.len());
+        assert!(customers.iter().all(|c| entity.contains_key(&("customers", c[0].as_str()))));
+        assert!(accounts.iter().all(|a| entity.contains_key(&("accounts", a[0].as_str()))));
+        let ids: Vec<u64> = truth.iter().map(|t| t[0].parse().unwrap()).collect();
+        assert!(ids.iter().all(|&id| (1..=500).contains(&id)));
+
+        // Records of one entity share its city and zip code, which never vary.
+        let mut places: HashMap<&str, String> = HashMap::new();
+        for c in &customers {
+            let place = format!(", {}, {} {}", c[6], c[7], c[8]);
+            assert_eq!(*places.entry(entity[&("customers", c[0].as_str())]).or_insert_with(|| place.clone()), place);
+        }
+        for a in &accounts {
+            if let Some(place) = places.get(entity[&("accounts", a[0].as_str())]) {
+                assert!(a[4].ends_with(place.as_str()), "{} {place}", a[4]);
+            }
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
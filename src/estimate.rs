@@ -0,0 +1,132 @@
+//! The `estimate` subcommand: what a run would write, and how long it would
+//! take, before it's started. A few thousand rows are generated into a
+//! temporary file through the same writers as the run, in its format and
+//! compression, to measure the bytes of a row and how fast rows are made;
+//! the row count, the time and, for cloud outputs, a rough cost follow.
+
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+
+use crate::cli::{Args, EstimateArgs};
+use crate::generator::{Generator, Row};
+use crate::output::{Destination, Outputs};
+use crate::progress::clock;
+use crate::{BoxError, DEFAULT_SIZE, GB, shard_rng, sink};
+
+/// List prices in US dollars, in US regions at the time of writing, of a
+/// GB-month of standard storage and a GB downloaded over the internet.
+fn prices(destination: &Destination) -> Option<(&'static str, f64, f64)> {
+    match destination {
+        Destination::Gcs { .. } => Some(("GCS", 0.020, 0.12)),
+        Destination::S3 { .. } => Some(("S3", 0.023, 0.09)),
+        Destination::Azure { .. } => Some(("Azure Blob Storage", 0.018, 0.087)),
+        Destination::BigQuery(_) => Some(("BigQuery", 0.020, 0.12)),
+        _ => None,
+    }
+}
+
+/// `1234567` as `1,234,567`.
+fn grouped(n: u64) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// What generating `args` would write, and how long it would take.
+pub fn run(args: &Args, estimate: &EstimateArgs) -> Result<(), BoxError> {
+    if args.config.is_some() || args.preset.preset.is_some() {
+        return Err("estimate samples a single table of --schema; configs and presets aren't estimated".into());
+    }
+    let columns = crate::columns(args)?;
+    let destination = Destination::parse(&args.output)?;
+
+    // The sample goes through the run's format and compression, but none
+    // of the files that describe outputs.
+    let temporary = std::env::temp_dir().join(format!("csv_data_generator_estimate_{}", std::process::id()));
+    let mut sample = args.clone();
+    sample.output = temporary.display().to_string();
+    (sample.manifest, sample.archive, sample.column_stats, sample.json_schema) = (None, None, false, false);
+    let mut sink = sink::open(&Outputs::new(&sample), &Destination::parse(&sample.output)?, &columns)?;
+    let generator = Generator::compile(&columns)?;
+    let mut rows = generator.rows(shard_rng(args.seed, 0));
+    let mut row = Row::new();
+    let started = Instant::now();
+    for _ in 0..estimate.sample_rows {
+        rows.fill_next(&mut row);
+        sink.write_row(&row)?;
+    }
+    let bytes = sink.bytes_written();
+    sink.finish()?;
+    let elapsed = started.elapsed().as_secs_f64().max(f64::EPSILON);
+    let stored = fs::metadata(&temporary)?.len();
+    fs::remove_file(&temporary)?;
+
+    let (row_bytes, row_stored) = (bytes as f64 / estimate.sample_rows as f64, stored as f64 / estimate.sample_rows as f64);
+    println!(
+        "Sampled {} rows of {}: {row_bytes:.1} bytes a row, {row_stored:.1} as stored, {:.0} rows/s on one thread",
+        grouped(estimate.sample_rows),
+        args.format.to_possible_value().expect("no format is skipped").get_name(),
+        estimate.sample_rows as f64 / elapsed,
+    );
+    let total_rows = match (args.rows, args.size) {
+        (Some(rows), None) => rows,
+        (rows, size) => {
+            let size = size.unwrap_or(DEFAULT_SIZE);
+            let by_size = (size as f64 / row_bytes).ceil() as u64;
+            rows.map_or(by_size, |rows| rows.min(by_size))
+        }
+    };
+    let total_stored = total_rows as f64 * row_stored;
+    println!("Rows: {}, {:.2}GB as stored", grouped(total_rows), total_stored / GB);
+
+    let jobs = args.jobs.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+    let parallel = jobs.min(args.shards as usize).max(1);
+    let mut rate = estimate.sample_rows as f64 / elapsed * parallel as f64;
+    let mut limit = String::new();
+    if let Some(cap) = args.rate.filter(|&cap| cap < rate) {
+        (rate, limit) = (cap, " as --rate allows".to_string());
+    }
+    if let Some(cap) = args.upload_limit.map(|bytes| bytes as f64 / row_stored).filter(|&cap| cap < rate) {
+        (rate, limit) = (cap, " as --upload-limit allows".to_string());
+    }
+    let time = Duration::from_secs_f64(total_rows as f64 / rate);
+    println!("Time: about {} at {rate:.0} rows/s over {parallel} shard(s) at once{limit}", clock(time));
+    if !matches!(destination, Destination::File(_) | Destination::Stdout) {
+        println!("Time doesn't count sending rows over the network, which may take longer.");
+    }
+    match prices(&destination) {
+        Some((service, storage, egress)) => {
+            let storage = estimate.storage_price.unwrap_or(storage);
+            let egress = estimate.egress_price.unwrap_or(egress);
+            let gb = total_stored / GB;
+            println!(
+                "Cost: about ${:.2} a month to keep in {service} at ${storage}/GB, and ${:.2} to download once at ${egress}/GB",
+                gb * storage,
+                gb * egress,
+            );
+        }
+        None => println!("Cost: nothing for a cloud service, as {} isn't one", args.output),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_thousands() {
+        assert_eq!(grouped(7), "7");
+        assert_eq!(grouped(1000), "1,000");
+        assert_eq!(grouped(218_710_000), "218,710,000");
+    }
+}
@@ -0,0 +1,247 @@
+//! A C API for driving the generator in process from other languages, such
+//! as JVM and Go test frameworks, declared in `include/csv_data_generator.h`.
+//! A config is the tables of a `--config` JSON document and any other
+//! settings as command line flags, and generating writes the tables to
+//! files like a run does, or hands each table's bytes to a callback.
+//!
+//! Functions return 0 on success. On failure they return -1 and, if `error`
+//! isn't NULL, point it at a message to free with `csvgen_string_free`.
+
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use clap::Parser;
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::plugin;
+use crate::relational::{self, Config};
+
+/// `csvgen_config`: what to generate.
+pub struct CsvgenConfig {
+    args: Args,
+    config: Config,
+}
+
+/// Receives `len` bytes of the file of `table`, returning nonzero to stop.
+pub type Callback = unsafe extern "C" fn(*mut c_void, *const c_char, *const u8, usize) -> c_int;
+
+/// Parses `json`, a `--config` document, and the `argc` flags in `argv`,
+/// like `--seed 7` or `--format jsonl`. Returns NULL on failure.
+///
+/// # Safety
+///
+/// `json` and the `argc` entries of `argv` are NUL-terminated strings, and
+/// `error` is NULL or writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn csvgen_config_from_json(
+    json: *const c_char,
+    argv: *const *const c_char,
+    argc: usize,
+    error: *mut *mut c_char,
+) -> *mut CsvgenConfig {
+    let parsed = guard(|| {
+        let json = unsafe { string(json) }?;
+        let mut flags = vec!["csv_data_generator".to_string()];
+        for i in 0..argc {
+            flags.push(unsafe { string(*argv.add(i)) }?.to_string());
+        }
+        let args = Args::try_parse_from(flags)?;
+        let config: Config = serde_json::from_str(json)?;
+        config.generation_order()?;
+        Ok(CsvgenConfig { args, config })
+    });
+    match parsed {
+        Ok(config) => Box::into_raw(Box::new(config)),
+        Err(e) => {
+            unsafe { report(error, &e) };
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Writes the tables to `output`, with `{table}` replaced by each table's
+/// name, as `--output` does.
+///
+/// # Safety
+///
+/// `config` came from `csvgen_config_from_json` and isn't freed yet,
+/// `output` is a NUL-terminated string and `error` is NULL or writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn csvgen_generate_to_path(
+    config: *const CsvgenConfig,
+    output: *const c_char,
+    error: *mut *mut c_char,
+) -> c_int {
+    let config = unsafe { &*config };
+    let result = guard(|| {
+        let args = Args { output: unsafe { string(output) }?.to_string(), ..config.args.clone() };
+        relational::generate(&args, &config.config)
+    });
+    unsafe { status(result, error) }
+}
+
+/// Calls `callback` with `user`, a table's name (with `/` and the shard's
+/// number after it if there are several `--shards`) and the next bytes of
+/// the table's file, in `--format`, until every table is generated. Shards
+/// are generated on several threads at once, so the callback must be thread
+/// safe.
+///
+/// # Safety
+///
+/// `config` came from `csvgen_config_from_json` and isn't freed yet, and
+/// `error` is NULL or writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn csvgen_generate_to_callback(
+    config: *const CsvgenConfig,
+    callback: Callback,
+    user: *mut c_void,
+    error: *mut *mut c_char,
+) -> c_int {
+    static CALLS: AtomicU64 = AtomicU64::new(0);
+    let config = unsafe { &*config };
+    let result = guard(|| {
+        let name = format!("ffi-{}", CALLS.fetch_add(1, Ordering::Relaxed));
+        let target = Target { callback, user };
+        plugin::add_sink(&name, (&raw const target).cast_mut().cast(), open, write, close)?;
+        let tables = if config.args.shards > 1 { "{table}/{n}" } else { "{table}" };
+        let args = Args { output: format!("plugin://{name}/{tables}"), ..config.args.clone() };
+        let result = relational::generate(&args, &config.config);
+        plugin::remove_sink(&name);
+        result
+    });
+    unsafe { status(result, error) }
+}
+
+/// Frees a config.
+///
+/// # Safety
+///
+/// `config` is NULL or came from `csvgen_config_from_json`, and isn't used
+/// again.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn csvgen_config_free(config: *mut CsvgenConfig) {
+    if !config.is_null() {
+        drop(unsafe { Box::from_raw(config) });
+    }
+}
+
+/// Frees an error message.
+///
+/// # Safety
+///
+/// `string` is NULL or an error message of this API, and isn't used again.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn csvgen_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Where [`csvgen_generate_to_callback`] sends the bytes of its tables.
+struct Target {
+    callback: Callback,
+    user: *mut c_void,
+}
+
+/// The file of one table.
+struct Stream {
+    target: *const Target,
+    table: CString,
+}
+
+unsafe extern "C" fn open(target: *mut c_void, table: *const c_char) -> *mut c_void {
+    let table = unsafe { CStr::from_ptr(table) }.to_owned();
+    Box::into_raw(Box::new(Stream { target: target.cast(), table })).cast()
+}
+
+unsafe extern "C" fn write(stream: *mut c_void, data: *const u8, len: usize) -> c_int {
+    let stream = unsafe { &*stream.cast::<Stream>() };
+    let target = unsafe { &*stream.target };
+    unsafe { (target.callback)(target.user, stream.table.as_ptr(), data, len) }
+}
+
+unsafe extern "C" fn close(stream: *mut c_void) -> c_int {
+    drop(unsafe { Box::from_raw(stream.cast::<Stream>()) });
+    0
+}
+
+/// Runs `f`, turning a panic into an error so it doesn't unwind into C.
+fn guard<T>(f: impl FnOnce() -> Result<T, BoxError>) -> Result<T, BoxError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let message = panic.downcast_ref::<&str>().map(|s| s.to_string()).or_else(|| panic.downcast_ref::<String>().cloned());
+        Err(format!("panicked: {}", message.unwrap_or_default()).into())
+    })
+}
+
+unsafe fn string<'a>(s: *const c_char) -> Result<&'a str, BoxError> {
+    if s.is_null() {
+        return Err("a string argument is NULL".into());
+    }
+    Ok(unsafe { CStr::from_ptr(s) }.to_str()?)
+}
+
+unsafe fn status(result: Result<(), BoxError>, error: *mut *mut c_char) -> c_int {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            unsafe { report(error, &e) };
+            -1
+        }
+    }
+}
+
+unsafe fn report(error: *mut *mut c_char, e: &BoxError) {
+    if !error.is_null() {
+        let message = CString::new(e.to_string().replace('\0', " ")).unwrap_or_default();
+        unsafe { *error = message.into_raw() };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const CONFIG: &CStr = cr#"{"table": [
+        {"name": "users", "rows": 3, "column": [{"name": "id", "kind": "sequence"}]}
+    ]}"#;
+
+    unsafe extern "C" fn collect(user: *mut c_void, table: *const c_char, data: *const u8, len: usize) -> c_int {
+        let written = unsafe { &*user.cast::<Mutex<Vec<(String, Vec<u8>)>>>() };
+        let table = unsafe { CStr::from_ptr(table) }.to_string_lossy().into_owned();
+        written.lock().unwrap().push((table, unsafe { std::slice::from_raw_parts(data, len) }.to_vec()));
+        0
+    }
+
+    #[test]
+    fn generates_through_the_c_api() {
+        let argv = [c"--seed".as_ptr(), c"7".as_ptr()];
+        let mut error = ptr::null_mut();
+        unsafe {
+            let config = csvgen_config_from_json(CONFIG.as_ptr(), argv.as_ptr(), argv.len(), &mut error);
+            assert!(!config.is_null());
+            let written: Mutex<Vec<(String, Vec<u8>)>> = Mutex::new(Vec::new());
+            let user = (&raw const written).cast_mut().cast();
+            assert_eq!(csvgen_generate_to_callback(config, collect, user, &mut error), 0);
+            let written = written.into_inner().unwrap();
+            assert!(written.iter().all(|(table, _)| table == "users"));
+            assert_eq!(written.into_iter().flat_map(|(_, data)| data).collect::<Vec<u8>>(), b"id\n1\n2\n3\n");
+
+            let path = std::env::temp_dir().join(format!("csv_data_generator_ffi_{}_{{table}}.csv", std::process::id()));
+            let output = CString::new(path.to_str().unwrap()).unwrap();
+            assert_eq!(csvgen_generate_to_path(config, output.as_ptr(), &mut error), 0);
+            let path = path.to_str().unwrap().replace("{table}", "users");
+            assert_eq!(std::fs::read(&path).unwrap(), b"id\n1\n2\n3\n");
+            std::fs::remove_file(path).unwrap();
+            csvgen_config_free(config);
+
+            let config = csvgen_config_from_json(c"{}".as_ptr(), ptr::null(), 0, &mut error);
+            assert!(config.is_null());
+            assert!(CStr::from_ptr(error).to_str().unwrap().contains("table"));
+            csvgen_string_free(error);
+        }
+    }
+}
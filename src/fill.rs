@@ -0,0 +1,171 @@
+//! `--fill-rate`: columns that hold a value in only some of their cells,
+//! leaving the rest empty, for the sparse wide tables of event property
+//! exports. `--format parquet` and `jsonl` write the emptied cells of all
+//! but string columns as nulls, and those of string columns as empty
+//! strings.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use rand::Rng;
+use rand::rngs::StdRng;
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::generator::Row;
+use crate::schema::Column;
+use crate::shard_rng;
+use crate::sink::Sink;
+
+/// Mixed into `--seed`, so cells are emptied independently of how their
+/// values are generated.
+const SEED_SALT: u64 = 0x6669_6c6c;
+
+/// Columns [`summary`] gives one line each, rather than a range.
+const LISTED_COLUMNS: usize = 10;
+
+/// Of each column with a fill rate, the cells written and those holding a
+/// value.
+static FILLED: Mutex<BTreeMap<String, (u64, u64)>> = Mutex::new(BTreeMap::new());
+
+/// The rate `rates` gives column `name`: its own, or that of the longest
+/// pattern ending in `*` that it starts with.
+fn rate_of(rates: &[(String, f64)], name: &str) -> Option<f64> {
+    if let Some(&(_, rate)) = rates.iter().find(|(column, _)| column == name) {
+        return Some(rate);
+    }
+    rates
+        .iter()
+        .filter_map(|(column, rate)| Some((column.strip_suffix('*')?, *rate)))
+        .filter(|(prefix, _)| name.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, rate)| rate)
+}
+
+/// Empties cells of the rows passed on.
+pub struct FillSink {
+    inner: Box<dyn Sink>,
+    /// Of each column, its fill rate, if it has one.
+    rates: Vec<Option<f64>>,
+    rng: StdRng,
+    /// Of each column, the cells written and those holding a value.
+    filled: Vec<(u64, u64)>,
+    names: Vec<String>,
+    row: Row,
+}
+
+impl FillSink {
+    /// Wraps `inner`, writing rows of `columns` to shard `shard`, unless no
+    /// `--fill-rate` names one of `columns`.
+    pub fn wrap(inner: Box<dyn Sink>, args: &Args, columns: &[Column], shard: u64) -> Box<dyn Sink> {
+        let rates: Vec<_> = columns.iter().map(|c| rate_of(&args.fill_rate, &c.name)).collect();
+        if rates.iter().all(Option::is_none) {
+            return inner;
+        }
+        Box::new(FillSink {
+            inner,
+            filled: vec![(0, 0); columns.len()],
+            names: columns.iter().map(|c| c.name.clone()).collect(),
+            rates,
+            rng: shard_rng(args.seed.map(|seed| seed ^ SEED_SALT), shard),
+            row: Row::new(),
+        })
+    }
+}
+
+impl Sink for FillSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.row.clear();
+        for ((field, rate), (cells, filled)) in row.fields().zip(&self.rates).zip(&mut self.filled) {
+            let Some(rate) = *rate else {
+                self.row.push(field);
+                continue;
+            };
+            let field = if self.rng.gen_bool(rate) { field } else { b"" };
+            *cells += 1;
+            *filled += u64::from(!field.is_empty());
+            self.row.push(field);
+        }
+        self.inner.write_row(&self.row)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.inner.flush()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        let mut totals = FILLED.lock().unwrap();
+        for ((name, rate), (cells, filled)) in self.names.into_iter().zip(&self.rates).zip(self.filled) {
+            if rate.is_some() {
+                let total = totals.entry(name).or_default();
+                *total = (total.0 + cells, total.1 + filled);
+            }
+        }
+        drop(totals);
+        self.inner.finish()
+    }
+}
+
+/// The share of cells that held a value, column by column, or as a range
+/// for very many columns.
+pub(crate) fn summary() -> Vec<String> {
+    let totals = FILLED.lock().unwrap();
+    let share = |(cells, filled): (u64, u64)| filled as f64 / cells.max(1) as f64 * 100.0;
+    if totals.len() <= LISTED_COLUMNS {
+        return totals.iter().map(|(name, &counts)| format!("Filled {:.1}% of {} cells of {name}", share(counts), counts.0)).collect();
+    }
+    let shares: Vec<f64> = totals.values().map(|&counts| share(counts)).collect();
+    let (cells, filled) = totals.values().fold((0, 0), |(cells, filled), &(c, f)| (cells + c, filled + f));
+    vec![format!(
+        "Filled {:.1}% to {:.1}% of the cells of {} sparse columns, {:.1}% of them in all",
+        shares.iter().copied().fold(f64::INFINITY, f64::min),
+        shares.iter().copied().fold(0.0, f64::max),
+        totals.len(),
+        share((cells, filled)),
+    )]
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::generator::Generator;
+    use crate::output::{Destination, Outputs};
+    use crate::sink;
+
+    #[test]
+    fn columns_take_their_own_rate_or_the_longest_pattern() {
+        let rates = [("*".to_string(), 0.5), ("metric_*".to_string(), 0.1), ("metric_7".to_string(), 0.9)];
+        assert_eq!(rate_of(&rates, "metric_7"), Some(0.9));
+        assert_eq!(rate_of(&rates, "metric_70"), Some(0.1));
+        assert_eq!(rate_of(&rates, "id"), Some(0.5));
+        assert_eq!(rate_of(&rates[1..], "id"), None);
+    }
+
+    #[test]
+    fn typed_formats_write_emptied_cells_as_nulls() {
+        let path = std::env::temp_dir().join(format!("csv_data_generator_fill_{}.jsonl", std::process::id()));
+        let output = path.to_str().unwrap();
+        let args = Args::parse_from(["gen", "--format", "jsonl", "--fill-rate", "age=0.5", "--seed", "1", "--output", output]);
+        let columns = crate::columns(&args).unwrap();
+        let mut sink = sink::open(&Outputs::new(&args), &Destination::parse(output).unwrap(), &columns).unwrap();
+        for row in Generator::compile(&columns).unwrap().rows(shard_rng(args.seed, 0)).take(200) {
+            sink.write_row(&row).unwrap();
+        }
+        sink.finish().unwrap();
+        let ages: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["age"].clone())
+            .collect();
+        let nulls = ages.iter().filter(|age| age.is_null()).count();
+        assert!((60..140).contains(&nulls), "{nulls}");
+        assert!(ages.iter().all(|age| age.is_null() || age.is_i64()));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rsa::RsaPrivateKey;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::signature::{SignatureEncoding, Signer};
+use serde::Deserialize;
+use sha2::Sha256;
+use ureq::Agent;
+
+use crate::BoxError;
+use crate::http;
+use crate::retry::{Failure, retry};
+
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+/// Refresh tokens this long before they expire so in-flight requests don't race expiry.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(120);
+
+/// The credential files ADC understands: service account keys and the
+/// `authorized_user` files written by `gcloud auth application-default login`.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CredentialsFile {
+    ServiceAccount { client_email: String, private_key: String, token_uri: String },
+    AuthorizedUser { client_id: String, client_secret: String, refresh_token: String },
+}
+
+const USER_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+enum Credentials {
+    /// A token supplied through `GOOGLE_OAUTH_ACCESS_TOKEN`, used as is.
+    Static(String),
+    ServiceAccount { email: String, key: Box<SigningKey<Sha256>>, token_uri: String },
+    AuthorizedUser { client_id: String, client_secret: String, refresh_token: String },
+    /// The GCE/GKE metadata server.
+    Metadata,
+}
+
+/// Hands out OAuth access tokens, caching them until shortly before they expire.
+///
+/// Credentials are looked up in Application Default Credentials order — the
+/// file named by `GOOGLE_APPLICATION_CREDENTIALS`, then gcloud's
+/// `application_default_credentials.json`, then the metadata server — except
+/// that a token in `GOOGLE_OAUTH_ACCESS_TOKEN` takes precedence over all of them.
+pub struct TokenSource {
+    agent: Agent,
+    credentials: Credentials,
+    cached: Mutex<Option<(String, Instant)>>,
+}
+
+impl TokenSource {
+    pub fn from_env() -> Result<Self, BoxError> {
+        let credentials = if let Ok(token) = std::env::var("GOOGLE_OAUTH_ACCESS_TOKEN") {
+            Credentials::Static(token)
+        } else if let Ok(path) = std::env::var("GOOGLE_APPLICATION_CREDENTIALS") {
+            read_credentials_file(&PathBuf::from(path))?
+        } else if let Some(path) = well_known_file().filter(|p| p.exists()) {
+            read_credentials_file(&path)?
+        } else {
+            Credentials::Metadata
+        };
+        Ok(TokenSource { agent: http::agent(), credentials, cached: Mutex::new(None) })
+    }
+
+    /// Returns a valid access token, fetching a new one if needed.
+    pub fn token(&self) -> Result<String, BoxError> {
+        if let Credentials::Static(token) = &self.credentials {
+            return Ok(token.clone());
+        }
+        let mut cached = self.cached.lock().unwrap();
+        if let Some((token, expires)) = cached.as_ref()
+            && Instant::now() + EXPIRY_MARGIN < *expires
+        {
+            return Ok(token.clone());
+        }
+        let response = retry("fetching a Google access token", || self.fetch())?;
+        let expires = Instant::now() + Duration::from_secs(response.expires_in);
+        *cached = Some((response.access_token.clone(), expires));
+        Ok(response.access_token)
+    }
+
+    fn fetch(&self) -> Result<TokenResponse, Failure> {
+        let result = match &self.credentials {
+            Credentials::Static(_) => unreachable!("static tokens are never fetched"),
+            Credentials::ServiceAccount { email, key, token_uri } => {
+                let assertion = signed_jwt(email, key, token_uri).map_err(Failure::Fatal)?;
+                self.agent.post(token_uri).send_form([
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                    ("assertion", assertion.as_str()),
+                ])
+            }
+            Credentials::AuthorizedUser { client_id, client_secret, refresh_token } => {
+                self.agent.post(USER_TOKEN_URL).send_form([
+                    ("grant_type", "refresh_token"),
+                    ("client_id", client_id.as_str()),
+                    ("client_secret", client_secret.as_str()),
+                    ("refresh_token", refresh_token.as_str()),
+                ])
+            }
+            Credentials::Metadata => {
+                // Off GCP the metadata host doesn't resolve; retrying that
+                // only delays telling the user they have no credentials.
+                let result = self.agent.get(METADATA_TOKEN_URL).header("Metadata-Flavor", "Google").call();
+                if let Err(e) = &result {
+                    return Err(Failure::Fatal(
+                        format!(
+                            "no Google credentials found (the metadata server is unreachable: {e}); \
+                             set GOOGLE_APPLICATION_CREDENTIALS or run `gcloud auth application-default login`"
+                        )
+                        .into(),
+                    ));
+                }
+                result
+            }
+        };
+        http::check(result, &[])?
+            .body_mut()
+            .read_json()
+            .map_err(|e| Failure::Transient(e.into()))
+    }
+}
+
+fn read_credentials_file(path: &Path) -> Result<Credentials, BoxError> {
+    let shown = path.display();
+    let json = std::fs::read_to_string(path).map_err(|e| format!("reading credentials {shown}: {e}"))?;
+    let file: CredentialsFile = serde_json::from_str(&json).map_err(|e| {
+        format!("{shown} is neither a service account key nor an authorized_user credential: {e}")
+    })?;
+    Ok(match file {
+        CredentialsFile::ServiceAccount { client_email, private_key, token_uri } => {
+            let private_key = RsaPrivateKey::from_pkcs8_pem(&private_key)
+                .map_err(|e| format!("invalid private key in {shown}: {e}"))?;
+            Credentials::ServiceAccount { email: client_email, key: Box::new(SigningKey::new(private_key)), token_uri }
+        }
+        CredentialsFile::AuthorizedUser { client_id, client_secret, refresh_token } => {
+            Credentials::AuthorizedUser { client_id, client_secret, refresh_token }
+        }
+    })
+}
+
+/// Where `gcloud auth application-default login` stores its credentials.
+fn well_known_file() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("CLOUDSDK_CONFIG") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(std::env::var_os("APPDATA")?).join("gcloud"),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config").join("gcloud"),
+    };
+    Some(config_dir.join("application_default_credentials.json"))
+}
+
+/// Builds the RS256-signed assertion exchanged for a service account token.
+fn signed_jwt(email: &str, key: &SigningKey<Sha256>, token_uri: &str) -> Result<String, BoxError> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"RS256","typ":"JWT"}"#);
+    let claims = serde_json::json!({
+        "iss": email,
+        "scope": SCOPE,
+        "aud": token_uri,
+        "iat": now,
+        "exp": now + 3600,
+    });
+    let claims = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims)?);
+    let unsigned = format!("{header}.{claims}");
+    let signature = key.try_sign(unsigned.as_bytes())?;
+    Ok(format!("{unsigned}.{}", URL_SAFE_NO_PAD.encode(signature.to_bytes())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_authorized_user_files() {
+        let json = r#"{"type": "authorized_user", "client_id": "id", "client_secret": "secret",
+                       "refresh_token": "refresh", "quota_project_id": "p"}"#;
+        let file: CredentialsFile = serde_json::from_str(json).unwrap();
+        assert!(matches!(file, CredentialsFile::AuthorizedUser { refresh_token, .. } if refresh_token == "refresh"));
+    }
+
+    #[test]
+    fn rejects_unknown_credential_types() {
+        let json = r#"{"type": "external_account", "audience": "x"}"#;
+        assert!(serde_json::from_str::<CredentialsFile>(json).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use std::str::FromStr;
+#[cfg(feature = "cloud")]
+use std::thread;
+#[cfg(feature = "cloud")]
+use std::time::Duration;
+
+#[cfg(feature = "cloud")]
+use rand::Rng;
+use serde::Serialize;
+#[cfg(feature = "cloud")]
+use serde_json::{Value, json};
+#[cfg(feature = "cloud")]
+use ureq::Agent;
+
+use crate::BoxError;
+use crate::cli::{BigQueryArgs, FileFormat};
+#[cfg(feature = "cloud")]
+use crate::cli::{Partitioning, WriteDisposition};
+#[cfg(feature = "cloud")]
+use crate::gcp::auth::TokenSource;
+#[cfg(feature = "cloud")]
+use crate::http;
+#[cfg(feature = "cloud")]
+use crate::retry::{Failure, retry};
+use crate::schema::{Column, DataType};
+
+// Table references, types and `--bq-load`'s checks are needed to parse the
+// command line even without the `cloud` feature; the load jobs are not.
+#[cfg(feature = "cloud")]
+const API: &str = "https://bigquery.googleapis.com/bigquery/v2";
+#[cfg(feature = "cloud")]
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// A `project:dataset.table` (or `project.dataset.table`) reference.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TableRef {
+    pub project: String,
+    pub dataset: String,
+    pub table: String,
+}
+
+impl FromStr for TableRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (project, rest) = s.split_once(':').or_else(|| s.split_once('.')).ok_or_else(|| err(s))?;
+        let (dataset, table) = rest.split_once('.').ok_or_else(|| err(s))?;
+        if [project, dataset, table].iter().any(|p| p.is_empty()) {
+            return Err(err(s));
+        }
+        Ok(TableRef { project: project.into(), dataset: dataset.into(), table: table.into() })
+    }
+}
+
+fn err(s: &str) -> String {
+    format!("{s} is not a table reference like project:dataset.table")
+}
+
+/// The BigQuery type for values of `data_type`.
+pub fn bq_type(data_type: DataType) -> &'static str {
+    match data_type {
+        DataType::String => "STRING",
+        DataType::Integer => "INT64",
+        DataType::Decimal { .. } => "NUMERIC",
+        DataType::Date => "DATE",
+        DataType::Timestamp => "TIMESTAMP",
+    }
+}
+
+/// Checks that the clustering columns exist, before spending hours
+/// generating data.
+pub fn validate(args: &BigQueryArgs, format: FileFormat, columns: &[Column]) -> Result<(), BoxError> {
+    if format == FileFormat::Combined {
+        return Err("--bq-load can't load combined log lines; use --format csv, parquet or jsonl".into());
+    }
+    for field in &args.bq_cluster_by {
+        if !columns.iter().any(|c| &c.name == field) {
+            return Err(format!("--bq-cluster-by: no column named {field}").into());
+        }
+    }
+    Ok(())
+}
+
+/// Loads the files at `uris` into the table named by `--bq-load` and waits
+/// for the job to finish. CSV files start with `leading_lines` lines that
+/// aren't rows.
+#[cfg(feature = "cloud")]
+pub fn load(
+    auth: &TokenSource,
+    args: &BigQueryArgs,
+    format: FileFormat,
+    leading_lines: u64,
+    columns: &[Column],
+    uris: &[String],
+) -> Result<(), BoxError> {
+    let table = args.bq_load.as_ref().expect("only called with --bq-load");
+    let agent = http::agent();
+    let job_id = format!("csv_data_generator_{:016x}", rand::thread_rng().r#gen::<u64>());
+    let fields: Vec<Value> = columns
+        .iter()
+        .map(|c| json!({ "name": c.name, "type": bq_type(c.kind.data_type()), "mode": "NULLABLE" }))
+        .collect();
+
+    let mut load = json!({
+        "sourceUris": uris,
+        "schema": { "fields": fields },
+        "destinationTable": {
+            "projectId": table.project,
+            "datasetId": table.dataset,
+            "tableId": table.table,
+        },
+        "writeDisposition": match args.bq_write_disposition {
+            WriteDisposition::Append => "WRITE_APPEND",
+            WriteDisposition::Truncate => "WRITE_TRUNCATE",
+            WriteDisposition::Empty => "WRITE_EMPTY",
+        },
+        "createDisposition": if args.bq_no_create { "CREATE_NEVER" } else { "CREATE_IF_NEEDED" },
+    });
+    match format {
+        FileFormat::Csv => {
+            load["sourceFormat"] = json!("CSV");
+            load["skipLeadingRows"] = json!(leading_lines);
+        }
+        FileFormat::Parquet => load["sourceFormat"] = json!("PARQUET"),
+        FileFormat::Jsonl => load["sourceFormat"] = json!("NEWLINE_DELIMITED_JSON"),
+        FileFormat::Combined => unreachable!("validate rejects combined log lines"),
+    }
+    if let Some(partitioning) = args.bq_partition {
+        let granularity = match partitioning {
+            Partitioning::Hour => "HOUR",
+            Partitioning::Day => "DAY",
+            Partitioning::Month => "MONTH",
+            Partitioning::Year => "YEAR",
+        };
+        load["timePartitioning"] = json!({ "type": granularity });
+    }
+    if !args.bq_cluster_by.is_empty() {
+        load["clustering"] = json!({ "fields": args.bq_cluster_by });
+    }
+    let mut job_reference = json!({ "projectId": table.project, "jobId": job_id });
+    if let Some(location) = &args.bq_location {
+        job_reference["location"] = json!(location);
+    }
+    let job = json!({ "jobReference": job_reference, "configuration": { "load": load } });
+
+    println!(
+        "Loading {} file(s) into {}:{}.{} (job {job_id})...",
+        uris.len(),
+        table.project,
+        table.dataset,
+        table.table
+    );
+    // The job id is chosen here, so a retried insert that already went through
+    // shows up as a 409 instead of starting a second load.
+    let inserted: Option<Value> = retry("submitting the BigQuery load job", || {
+        let token = auth.token().map_err(Failure::Fatal)?;
+        let mut response = http::check(
+            agent
+                .post(format!("{API}/projects/{}/jobs", table.project))
+                .header("Authorization", format!("Bearer {token}"))
+                .send_json(&job),
+            &[409],
+        )?;
+        if response.status() == 409 {
+            return Ok(None);
+        }
+        response.body_mut().read_json().map(Some).map_err(|e| Failure::Transient(e.into()))
+    })?;
+    // Jobs outside the US multi-region can only be looked up with their
+    // location, which BigQuery picks from the dataset when none was given.
+    let location = inserted
+        .as_ref()
+        .and_then(job_location)
+        .or_else(|| args.bq_location.clone());
+
+    let status = wait_for_job(&agent, auth, &table.project, &job_id, location.as_deref())?;
+    if let Some(error) = status["status"].get("errorResult") {
+        let details = status["status"]["errors"].as_array().map(|errors| {
+            errors.iter().filter_map(|e| e["message"].as_str()).collect::<Vec<_>>().join("; ")
+        });
+        return Err(format!(
+            "BigQuery load job {job_id} failed: {}{}",
+            error["message"].as_str().unwrap_or("unknown error"),
+            details.map(|d| format!(" ({d})")).unwrap_or_default()
+        )
+        .into());
+    }
+    let rows = status["statistics"]["load"]["outputRows"].as_str().unwrap_or("?");
+    println!("BigQuery load job {job_id} finished: {rows} rows loaded.");
+    Ok(())
+}
+
+/// The location BigQuery assigned to a job, from its `jobReference`.
+#[cfg(feature = "cloud")]
+fn job_location(job: &Value) -> Option<String> {
+    job["jobReference"]["location"].as_str().map(str::to_owned)
+}
+
+#[cfg(feature = "cloud")]
+fn wait_for_job(
+    agent: &Agent,
+    auth: &TokenSource,
+    project: &str,
+    job_id: &str,
+    location: Option<&str>,
+) -> Result<Value, BoxError> {
+    let mut last_state = String::new();
+    loop {
+        let job: Value = retry("checking the BigQuery load job", || {
+            let token = auth.token().map_err(Failure::Fatal)?;
+            let mut request = agent
+                .get(format!("{API}/projects/{project}/jobs/{job_id}"))
+                .header("Authorization", format!("Bearer {token}"));
+            if let Some(location) = location {
+                request = request.query("location", location);
+            }
+            http::check(request.call(), &[])?
+                .body_mut()
+                .read_json()
+                .map_err(|e| Failure::Transient(e.into()))
+        })?;
+        let state = job["status"]["state"].as_str().unwrap_or_default().to_string();
+        if state == "DONE" {
+            return Ok(job);
+        }
+        if state != last_state {
+            println!("BigQuery load job {job_id} is {state}");
+            last_state = state;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::cli::Args;
+    use crate::schema::ColumnKind;
+
+    #[test]
+    fn parses_table_references() {
+        for s in ["proj:data.events", "proj.data.events"] {
+            let table: TableRef = s.parse().unwrap();
+            assert_eq!((table.project.as_str(), table.dataset.as_str(), table.table.as_str()), ("proj", "data", "events"));
+        }
+        let table: TableRef = "my-proj:data.t.x".parse().unwrap();
+        assert_eq!(table.table, "t.x");
+    }
+
+    #[test]
+    fn rejects_incomplete_table_references() {
+        for s in ["", "proj", "proj:data", "proj:.t", ":data.t", "proj:data."] {
+            assert!(s.parse::<TableRef>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn clustering_columns_must_exist() {
+        let columns = [Column::new("id", ColumnKind::Sha256Hex), Column::new("age", ColumnKind::IntRange { min: 0, max: 9 })];
+        let args = |cluster: &str| Args::parse_from(["gen", "--bq-load", "p:d.t", "--bq-cluster-by", cluster]).bigquery;
+        assert!(validate(&args("id,age"), FileFormat::Csv, &columns).is_ok());
+        assert!(validate(&args("id,name"), FileFormat::Csv, &columns).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "cloud")]
+    fn reads_the_job_location() {
+        let job = json!({ "jobReference": { "projectId": "p", "jobId": "j", "location": "EU" } });
+        assert_eq!(job_location(&job).as_deref(), Some("EU"));
+        assert_eq!(job_location(&json!({})), None);
+    }
+}
@@ -0,0 +1,340 @@
+use std::sync::Arc;
+
+use prost::encoding::{WireType, encode_key, encode_varint};
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{DescriptorProto, FieldDescriptorProto};
+use tokio::runtime::Runtime;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::{Channel, ClientTlsConfig};
+use tonic_prost::ProstCodec;
+
+use crate::BoxError;
+use crate::gcp::auth::TokenSource;
+use crate::gcp::bigquery::TableRef;
+use crate::generator::Row;
+use crate::http::percent_encode;
+use crate::schema::{Column, DataType, parse_date, parse_timestamp};
+use crate::sink::Sink;
+
+const ENDPOINT: &str = "https://bigquerystorage.googleapis.com";
+const APPEND_ROWS: &str = "/google.cloud.bigquery.storage.v1.BigQueryWrite/AppendRows";
+
+/// AppendRows requests are limited to 10MB; stay well under it.
+const MAX_BATCH_BYTES: usize = 5 << 20;
+
+/// The subset of `google.cloud.bigquery.storage.v1` messages this sink uses,
+/// written out by hand so the build doesn't need protoc.
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AppendRowsRequest {
+        #[prost(string, tag = "1")]
+        pub write_stream: String,
+        /// Part of the `rows` oneof, which encodes like a plain optional field.
+        #[prost(message, optional, tag = "4")]
+        pub proto_rows: Option<ProtoData>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ProtoData {
+        #[prost(message, optional, tag = "1")]
+        pub writer_schema: Option<ProtoSchema>,
+        #[prost(message, optional, tag = "2")]
+        pub rows: Option<ProtoRows>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ProtoSchema {
+        #[prost(message, optional, tag = "1")]
+        pub proto_descriptor: Option<prost_types::DescriptorProto>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ProtoRows {
+        #[prost(bytes = "vec", repeated, tag = "1")]
+        pub serialized_rows: Vec<Vec<u8>>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AppendRowsResponse {
+        #[prost(message, optional, tag = "2")]
+        pub error: Option<Status>,
+        #[prost(message, repeated, tag = "4")]
+        pub row_errors: Vec<RowError>,
+    }
+
+    /// `google.rpc.Status`.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Status {
+        #[prost(int32, tag = "1")]
+        pub code: i32,
+        #[prost(string, tag = "2")]
+        pub message: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RowError {
+        #[prost(int64, tag = "1")]
+        pub index: i64,
+        #[prost(string, tag = "3")]
+        pub message: String,
+    }
+}
+
+/// Appends rows to a table's default stream through the BigQuery Storage
+/// Write API, so they are committed as soon as each request is acknowledged.
+///
+/// Rows are encoded as protobuf messages whose fields are the columns in
+/// order, described to the API by a descriptor sent with the first request
+/// on each connection.
+pub struct BigQueryWriteSink {
+    runtime: Runtime,
+    channel: Channel,
+    auth: Arc<TokenSource>,
+    /// The access token the open AppendRows stream was authorized with.
+    token: String,
+    stream: Option<AppendStream>,
+    write_stream: String,
+    schema: proto::ProtoSchema,
+    types: Vec<DataType>,
+    batch: Vec<Vec<u8>>,
+    batch_bytes: usize,
+    bytes: u64,
+}
+
+/// One AppendRows call: requests go out through `requests` while a task
+/// checks and counts the responses.
+struct AppendStream {
+    requests: mpsc::Sender<proto::AppendRowsRequest>,
+    responses: JoinHandle<Result<u64, BoxError>>,
+    requests_sent: u64,
+}
+
+impl BigQueryWriteSink {
+    pub fn open(auth: Arc<TokenSource>, table: &TableRef, columns: &[Column]) -> Result<Self, BoxError> {
+        let write_stream = format!(
+            "projects/{}/datasets/{}/tables/{}/streams/_default",
+            table.project, table.dataset, table.table
+        );
+        let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(1).enable_all().build()?;
+        let channel = runtime.block_on(async {
+            Channel::from_static(ENDPOINT)
+                .tls_config(ClientTlsConfig::new().with_webpki_roots())?
+                .connect()
+                .await
+        })?;
+
+        let descriptor = DescriptorProto {
+            name: Some("Row".to_string()),
+            field: columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| FieldDescriptorProto {
+                    name: Some(column.name.clone()),
+                    number: Some(i as i32 + 1),
+                    label: Some(Label::Optional as i32),
+                    r#type: Some(proto_type(column.kind.data_type()) as i32),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        Ok(BigQueryWriteSink {
+            runtime,
+            channel,
+            auth,
+            token: String::new(),
+            stream: None,
+            write_stream,
+            schema: proto::ProtoSchema { proto_descriptor: Some(descriptor) },
+            types: columns.iter().map(|c| c.kind.data_type()).collect(),
+            batch: Vec::new(),
+            batch_bytes: 0,
+            bytes: 0,
+        })
+    }
+
+    /// Starts an AppendRows call authorized with the current access token.
+    fn open_stream(&mut self) -> Result<(), BoxError> {
+        let token = self.auth.token()?;
+        let (sender, receiver) = mpsc::channel(4);
+        let mut request = tonic::Request::new(ReceiverStream::new(receiver));
+        let metadata = request.metadata_mut();
+        metadata.insert("authorization", format!("Bearer {token}").parse()?);
+        metadata.insert(
+            "x-goog-request-params",
+            format!("write_stream={}", percent_encode(&self.write_stream, true)).parse()?,
+        );
+        let channel = self.channel.clone();
+        let responses = self.runtime.spawn(async move {
+            let mut grpc = tonic::client::Grpc::new(channel);
+            grpc.ready().await?;
+            let codec = ProstCodec::<proto::AppendRowsRequest, proto::AppendRowsResponse>::default();
+            let mut responses = grpc
+                .streaming(request, PathAndQuery::from_static(APPEND_ROWS), codec)
+                .await?
+                .into_inner();
+            let mut acknowledged = 0;
+            while let Some(response) = responses.message().await? {
+                if let Some(error) = response.error {
+                    return Err(format!("AppendRows failed ({}): {}", error.code, error.message).into());
+                }
+                if let Some(row) = response.row_errors.first() {
+                    return Err(format!("row {} was rejected: {}", row.index, row.message).into());
+                }
+                acknowledged += 1;
+            }
+            Ok(acknowledged)
+        });
+        self.token = token;
+        self.stream = Some(AppendStream { requests: sender, responses, requests_sent: 0 });
+        Ok(())
+    }
+
+    /// Ends the open AppendRows call, if any, once every request on it has
+    /// been acknowledged.
+    fn close_stream(&mut self) -> Result<(), BoxError> {
+        let Some(stream) = self.stream.take() else {
+            return Ok(());
+        };
+        let AppendStream { requests, responses, requests_sent } = stream;
+        drop(requests);
+        let acknowledged = self.runtime.block_on(responses)??;
+        if acknowledged != requests_sent {
+            return Err(format!("only {acknowledged} of {requests_sent} AppendRows requests were acknowledged").into());
+        }
+        Ok(())
+    }
+
+    fn send_batch(&mut self) -> Result<(), BoxError> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        // A stream stays authorized by the token it was opened with, so it is
+        // replaced whenever the token source has moved on to a fresh token.
+        if self.stream.is_some() && self.auth.token()? != self.token {
+            self.close_stream()?;
+        }
+        if self.stream.is_none() {
+            self.open_stream()?;
+        }
+        let stream = self.stream.as_mut().expect("stream was just opened");
+        let request = proto::AppendRowsRequest {
+            write_stream: self.write_stream.clone(),
+            proto_rows: Some(proto::ProtoData {
+                // Only the first request on a connection carries the schema.
+                writer_schema: (stream.requests_sent == 0).then(|| self.schema.clone()),
+                rows: Some(proto::ProtoRows { serialized_rows: std::mem::take(&mut self.batch) }),
+            }),
+        };
+        self.batch_bytes = 0;
+        if self.runtime.block_on(stream.requests.send(request)).is_err() {
+            // The response task ended early, which means the stream failed.
+            return Err(self.close_stream().err().unwrap_or_else(|| "AppendRows stream closed".into()));
+        }
+        stream.requests_sent += 1;
+        Ok(())
+    }
+}
+
+fn proto_type(data_type: DataType) -> Type {
+    match data_type {
+        // NUMERIC columns take their values as decimal strings, DATE columns
+        // as days since the epoch and TIMESTAMP columns as microseconds.
+        DataType::String | DataType::Decimal { .. } => Type::String,
+        DataType::Integer | DataType::Timestamp => Type::Int64,
+        DataType::Date => Type::Int32,
+    }
+}
+
+/// Serializes `row` as a `Row` message, with field `i + 1` holding column `i`.
+fn encode_row(row: &Row, types: &[DataType]) -> Result<Vec<u8>, BoxError> {
+    let mut message = Vec::new();
+    for (i, (field, data_type)) in row.fields().zip(types).enumerate() {
+        let tag = i as u32 + 1;
+        match data_type {
+            DataType::String | DataType::Decimal { .. } => {
+                encode_key(tag, WireType::LengthDelimited, &mut message);
+                encode_varint(field.len() as u64, &mut message);
+                message.extend_from_slice(field);
+            }
+            DataType::Integer => {
+                let value: i64 = std::str::from_utf8(field)?.parse()?;
+                encode_key(tag, WireType::Varint, &mut message);
+                encode_varint(value as u64, &mut message);
+            }
+            DataType::Date => {
+                let field = std::str::from_utf8(field)?;
+                let days = parse_date(field).ok_or_else(|| format!("{field} isn't a date"))?;
+                encode_key(tag, WireType::Varint, &mut message);
+                // int32 is sign-extended to 64 bits on the wire.
+                encode_varint(days as u64, &mut message);
+            }
+            DataType::Timestamp => {
+                let field = std::str::from_utf8(field)?;
+                let seconds = parse_timestamp(field).ok_or_else(|| format!("{field} isn't a timestamp"))?;
+                encode_key(tag, WireType::Varint, &mut message);
+                encode_varint((seconds * 1_000_000) as u64, &mut message);
+            }
+        }
+    }
+    Ok(message)
+}
+
+impl Sink for BigQueryWriteSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        let message = encode_row(row, &self.types)?;
+        self.bytes += message.len() as u64;
+        self.batch_bytes += message.len();
+        self.batch.push(message);
+        if self.batch_bytes >= MAX_BATCH_BYTES {
+            self.send_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.send_batch()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.send_batch()?;
+        self.close_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_rows_as_protobuf() {
+        let mut row = Row::new();
+        row.push(b"abc");
+        row.push(b"42");
+        let message = encode_row(&row, &[DataType::String, DataType::Integer]).unwrap();
+        assert_eq!(message, [0x0a, 3, b'a', b'b', b'c', 0x10, 42]);
+    }
+
+    #[test]
+    fn negative_integers_use_ten_byte_varints() {
+        let mut row = Row::new();
+        row.push(b"-1");
+        let message = encode_row(&row, &[DataType::Integer]).unwrap();
+        assert_eq!(message, [0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+    }
+
+    #[test]
+    fn rejects_non_numeric_integers() {
+        let mut row = Row::new();
+        row.push(b"4x");
+        assert!(encode_row(&row, &[DataType::Integer]).is_err());
+    }
+}
@@ -0,0 +1,11 @@
+//! Google Cloud clients, talking to the public REST APIs directly.
+
+#[cfg(feature = "cloud")]
+pub mod auth;
+pub mod bigquery;
+#[cfg(feature = "cloud")]
+pub mod bigquery_write;
+#[cfg(feature = "cloud")]
+pub mod pubsub;
+#[cfg(feature = "cloud")]
+pub mod storage;
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde_json::{Map, Value, json};
+use ureq::Agent;
+
+use crate::BoxError;
+use crate::cli::PubSubArgs;
+use crate::gcp::auth::TokenSource;
+use crate::generator::Row;
+use crate::http;
+use crate::retry::{Failure, dead_letter, retry};
+use crate::schema::Column;
+use crate::sink::{Sink, column_indices, csv_record};
+
+const API: &str = "https://pubsub.googleapis.com/v1";
+
+/// Publishes one message per row to a Pub/Sub topic, with the row as a CSV
+/// line in the message data.
+///
+/// Messages are batched into publish requests of up to
+/// `--pubsub-batch-messages` messages or `--pubsub-batch-bytes` bytes. A
+/// publish that is retried after a timeout may deliver its messages twice,
+/// as Pub/Sub itself does.
+pub struct PubSubSink {
+    agent: Agent,
+    auth: Arc<TokenSource>,
+    url: String,
+    topic: String,
+    ordering_key: Option<usize>,
+    /// `(name, index)` of each column sent as an attribute.
+    attributes: Vec<(String, usize)>,
+    batch_messages: usize,
+    batch_bytes: usize,
+    batch: Vec<Value>,
+    pending_bytes: usize,
+    bytes: u64,
+}
+
+impl PubSubSink {
+    pub fn open(
+        auth: Arc<TokenSource>,
+        args: &PubSubArgs,
+        project: &str,
+        topic: &str,
+        columns: &[Column],
+    ) -> Result<Self, BoxError> {
+        let ordering_key = match &args.pubsub_ordering_key {
+            Some(name) => Some(column_indices(columns, std::slice::from_ref(name), "--pubsub-ordering-key")?[0]),
+            None => None,
+        };
+        let attributes = column_indices(columns, &args.pubsub_attributes, "--pubsub-attributes")?
+            .into_iter()
+            .map(|i| (columns[i].name.clone(), i))
+            .collect();
+        Ok(PubSubSink {
+            agent: http::agent(),
+            auth,
+            url: format!("{API}/projects/{project}/topics/{topic}:publish"),
+            topic: format!("projects/{project}/topics/{topic}"),
+            ordering_key,
+            attributes,
+            batch_messages: args.pubsub_batch_messages as usize,
+            batch_bytes: args.pubsub_batch_bytes as usize,
+            batch: Vec::new(),
+            pending_bytes: 0,
+            bytes: 0,
+        })
+    }
+
+    fn publish(&mut self) -> Result<(), BoxError> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let body = json!({ "messages": std::mem::take(&mut self.batch) });
+        self.pending_bytes = 0;
+        let what = format!("publishing to {}", self.topic);
+        let published = retry(&what, || {
+            let token = self.auth.token().map_err(Failure::Fatal)?;
+            http::check(
+                self.agent
+                    .post(&self.url)
+                    .header("Authorization", format!("Bearer {token}"))
+                    .send_json(&body),
+                &[],
+            )
+            .map(drop)
+        });
+        match published {
+            Ok(()) => Ok(()),
+            Err(e) => dead_letter(&self.topic, e, body["messages"].as_array().into_iter().flatten().cloned()),
+        }
+    }
+}
+
+/// Builds the JSON for one message and returns it with the size of its data.
+fn message(row: &Row, attributes: &[(String, usize)], ordering_key: Option<usize>) -> (Value, usize) {
+    let data = csv_record(row);
+    let fields: Vec<&[u8]> = row.fields().collect();
+    let text = |i: usize| String::from_utf8_lossy(fields[i]).into_owned();
+    let mut message = json!({ "data": STANDARD.encode(&data) });
+    if !attributes.is_empty() {
+        let attributes: Map<String, Value> =
+            attributes.iter().map(|(name, i)| (name.clone(), Value::String(text(*i)))).collect();
+        message["attributes"] = Value::Object(attributes);
+    }
+    if let Some(i) = ordering_key {
+        message["orderingKey"] = Value::String(text(i));
+    }
+    (message, data.len())
+}
+
+impl Sink for PubSubSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        let (message, len) = message(row, &self.attributes, self.ordering_key);
+        if !self.batch.is_empty() && self.pending_bytes + len > self.batch_bytes {
+            self.publish()?;
+        }
+        self.batch.push(message);
+        self.pending_bytes += len;
+        self.bytes += len as u64;
+        if self.batch.len() >= self.batch_messages {
+            self.publish()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.publish()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.publish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(fields: &[&str]) -> Row {
+        let mut row = Row::new();
+        for field in fields {
+            row.push(field.as_bytes());
+        }
+        row
+    }
+
+    #[test]
+    fn messages_carry_the_csv_line() {
+        let (message, len) = message(&row(&["abc", "Mia", "42"]), &[], None);
+        assert_eq!(message, json!({ "data": STANDARD.encode("abc,Mia,42") }));
+        assert_eq!(len, 10);
+    }
+
+    #[test]
+    fn attributes_and_ordering_keys_come_from_columns() {
+        let attributes = [("name".to_string(), 1), ("age".to_string(), 2)];
+        let (message, _) = message(&row(&["abc", "Mia", "42"]), &attributes, Some(0));
+        assert_eq!(message["attributes"], json!({ "name": "Mia", "age": "42" }));
+        assert_eq!(message["orderingKey"], "abc");
+    }
+}
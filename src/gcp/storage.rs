@@ -0,0 +1,174 @@
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use ureq::Agent;
+
+use crate::BoxError;
+use crate::gcp::auth::TokenSource;
+use crate::http;
+use crate::output::Output;
+use crate::retry::{Failure, retry};
+
+const UPLOAD_URL: &str = "https://storage.googleapis.com/upload/storage/v1/b";
+
+/// Bytes sent per request. Resumable uploads require multiples of 256 KiB.
+const CHUNK_SIZE: usize = 32 * 256 * 1024;
+
+/// A GCS object written through a resumable upload session.
+///
+/// Data is buffered and sent in [`CHUNK_SIZE`] pieces as it arrives, so only
+/// one chunk per upload is ever held in memory. A failed chunk is retried from
+/// the last byte GCS reports as persisted.
+pub struct GcsUpload {
+    agent: Agent,
+    auth: Arc<TokenSource>,
+    object: String,
+    session_uri: String,
+    buf: Vec<u8>,
+    /// Bytes already persisted by GCS.
+    offset: u64,
+}
+
+impl GcsUpload {
+    /// Starts a resumable upload session for `gs://{bucket}/{object}`.
+    pub fn create(auth: Arc<TokenSource>, bucket: &str, object: &str) -> Result<Self, BoxError> {
+        let agent = http::agent();
+        let what = format!("starting upload of gs://{bucket}/{object}");
+        let session_uri = retry(&what, || {
+            let token = auth.token().map_err(Failure::Fatal)?;
+            let response = http::check(
+                agent
+                    .post(format!("{UPLOAD_URL}/{bucket}/o"))
+                    .query("uploadType", "resumable")
+                    .query("name", object)
+                    .header("Authorization", format!("Bearer {token}"))
+                    .header("X-Upload-Content-Type", "text/csv")
+                    .send_empty(),
+                &[],
+            )?;
+            response
+                .headers()
+                .get("location")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned)
+                .ok_or_else(|| Failure::Fatal("upload session response had no Location".into()))
+        })?;
+        Ok(GcsUpload {
+            agent,
+            auth,
+            object: format!("gs://{bucket}/{object}"),
+            session_uri,
+            buf: Vec::with_capacity(CHUNK_SIZE),
+            offset: 0,
+        })
+    }
+
+    /// Uploads the first `len` buffered bytes, finalizing the object if `last`.
+    fn send_chunk(&mut self, len: usize, last: bool) -> Result<(), BoxError> {
+        let chunk_start = self.offset;
+        let chunk_end = chunk_start + len as u64;
+        let total = if last { chunk_end.to_string() } else { "*".to_string() };
+        let what = format!("uploading {} at offset {chunk_start}", self.object);
+        let mut persisted = chunk_start;
+        let mut interrupted = false;
+        retry(&what, || {
+            let token = self.auth.token().map_err(Failure::Fatal)?;
+            let request = || {
+                self.agent
+                    .put(&self.session_uri)
+                    .header("Authorization", format!("Bearer {token}"))
+            };
+            // After a failure, ask GCS how much it kept rather than assuming.
+            if interrupted {
+                let response = http::check(
+                    request().header("Content-Range", format!("bytes */{total}")).send_empty(),
+                    &[308],
+                )?;
+                if response.status().as_u16() != 308 {
+                    persisted = chunk_end;
+                    return Ok(());
+                }
+                persisted = persisted_end(&response).unwrap_or(chunk_start).max(chunk_start);
+            }
+            interrupted = true;
+            let data = &self.buf[(persisted - chunk_start) as usize..len];
+            let range = if data.is_empty() {
+                format!("bytes */{total}")
+            } else {
+                format!("bytes {persisted}-{}/{total}", chunk_end - 1)
+            };
+            let response =
+                http::check(request().header("Content-Range", range).send(data), &[308])?;
+            if response.status().as_u16() == 308 {
+                persisted = persisted_end(&response).unwrap_or(chunk_start);
+                if persisted < chunk_end {
+                    return Err(Failure::Transient(
+                        format!("GCS persisted only up to byte {persisted}").into(),
+                    ));
+                }
+            } else if !last {
+                return Err(Failure::Fatal("GCS finalized the object early".into()));
+            }
+            Ok(())
+        })?;
+        self.buf.drain(..len);
+        self.offset = chunk_end;
+        Ok(())
+    }
+}
+
+/// Parses the `Range: bytes=0-N` header of a 308 into the next offset to send.
+fn persisted_end(response: &ureq::http::Response<ureq::Body>) -> Option<u64> {
+    parse_persisted_range(response.headers().get("range")?.to_str().ok()?)
+}
+
+fn parse_persisted_range(range: &str) -> Option<u64> {
+    let last: u64 = range.strip_prefix("bytes=0-")?.parse().ok()?;
+    Some(last + 1)
+}
+
+impl Write for GcsUpload {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        while self.buf.len() >= CHUNK_SIZE {
+            self.send_chunk(CHUNK_SIZE, false).map_err(io::Error::other)?;
+        }
+        Ok(data.len())
+    }
+
+    /// Chunks are sent as soon as they fill, and a partial chunk can only be
+    /// sent as the final one, so there is nothing to do here.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output for GcsUpload {
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        let len = self.buf.len();
+        self.send_chunk(len, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persisted_range_is_the_next_offset() {
+        assert_eq!(parse_persisted_range("bytes=0-0"), Some(1));
+        assert_eq!(parse_persisted_range("bytes=0-8388607"), Some(8_388_608));
+    }
+
+    #[test]
+    fn malformed_ranges_are_ignored() {
+        assert_eq!(parse_persisted_range("bytes=5-9"), None);
+        assert_eq!(parse_persisted_range("bytes=0-"), None);
+        assert_eq!(parse_persisted_range(""), None);
+    }
+
+    #[test]
+    fn chunks_are_multiples_of_256_kib() {
+        assert_eq!(CHUNK_SIZE % (256 * 1024), 0);
+    }
+}
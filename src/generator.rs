@@ -0,0 +1,900 @@
+use std::sync::Arc;
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+#[cfg(feature = "async")]
+use tokio_stream::Stream;
+
+use crate::BoxError;
+use crate::geo::{self, Bounds, Shape};
+use crate::national_id::{self, Country};
+use crate::plugin;
+use crate::pool::StringPool;
+use crate::record::RecordEncoder;
+#[cfg(feature = "script")]
+use crate::script::Script;
+use crate::schema::{
+    Column, ColumnKind, DECIMAL_PRECISION, DataType, Lag, civil_from_days, days_from_civil, is_matching_row, parse_date, parse_timestamp,
+};
+use crate::unicode;
+use crate::validate::parse_decimal;
+
+/// A column definition lowered into the form the hot loop works with.
+///
+/// Anything that can be computed once (string pools, parsed ranges) is
+/// computed here so that generating a value never allocates.
+enum CompiledColumn {
+    Sha256Hex,
+    UuidV4,
+    Choice(StringPool),
+    IntRange { min: i64, max: i64 },
+    Sequence { start: i64 },
+    ParentKey,
+    ForeignKey { min: i64, max: i64, matching: f64, skew: f64 },
+    ChildNumber,
+    Label { prefix: Vec<u8>, width: usize },
+    Cycle(Vec<Vec<u8>>),
+    DateRange { min: i64, max: i64 },
+    TimestampRange { min: i64, max: i64 },
+    Clock { start: i64, per_second: f64, skew: f64 },
+    Decimal { min: i64, max: i64, scale: u8 },
+    Format(Vec<Vec<Piece>>),
+    Lagged { of: usize, lag: Lag },
+    Unicode { min: usize, max: usize, thresholds: Vec<(f64, unicode::Class)> },
+    NationalId { country: Country, synthetic: bool },
+    /// `slot` is where the row's point in `area` is kept for the other
+    /// columns with the same area.
+    Geo { shape: Shape, area: Vec<Bounds>, slot: usize },
+    #[cfg(feature = "script")]
+    Script { script: Script, data_type: DataType },
+    Plugin(Arc<plugin::Generator>),
+}
+
+/// A part of a compiled [`ColumnKind::Format`].
+enum Piece {
+    Text(Vec<u8>),
+    Digit,
+    NonZeroDigit,
+    List(StringPool),
+}
+
+/// Splits a format into its pieces, checking that every `{i}` names one of `lists`.
+fn compile_format(format: &str, lists: &[Vec<String>]) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        let piece = match c {
+            '#' => Piece::Digit,
+            '%' => Piece::NonZeroDigit,
+            '{' => {
+                let index: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                match index.parse::<usize>().ok().and_then(|i| lists.get(i)) {
+                    Some(list) if !list.is_empty() => Piece::List(StringPool::new(list)),
+                    _ => return Err(format!("{{{index}}} in {format} isn't a non-empty list")),
+                }
+            }
+            c => {
+                text.push(c);
+                continue;
+            }
+        };
+        if !text.is_empty() {
+            pieces.push(Piece::Text(std::mem::take(&mut text).into_bytes()));
+        }
+        pieces.push(piece);
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text.into_bytes()));
+    }
+    Ok(pieces)
+}
+
+/// How many areas the geo columns of one schema can draw points in.
+const POINT_SLOTS: usize = 8;
+
+/// The last second of 9999-12-31, which is as late as a lagged time goes.
+const LATEST_TIME: i64 = 2_932_897 * 86_400 - 1;
+
+/// The per-row inputs of key columns, which unlike the other columns aren't random.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RowKeys {
+    /// The row's index within its table.
+    pub index: u64,
+    /// The key of the parent row, for child tables.
+    pub parent: i64,
+    /// The row's index among its parent's children.
+    pub child: u64,
+}
+
+/// A compiled set of columns that fills [`Row`]s with random values.
+pub struct Generator {
+    columns: Vec<CompiledColumn>,
+    types: Vec<DataType>,
+}
+
+impl Generator {
+    /// Compiles column definitions into a generator, rejecting definitions
+    /// that could not produce a value.
+    pub fn compile(columns: &[Column]) -> Result<Self, BoxError> {
+        let types = columns.iter().map(|c| c.kind.data_type()).collect();
+        let mut areas: Vec<&[Bounds]> = Vec::new();
+        let columns = columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                Ok(match &c.kind {
+                    ColumnKind::Sha256Hex => CompiledColumn::Sha256Hex,
+                    ColumnKind::UuidV4 => CompiledColumn::UuidV4,
+                    ColumnKind::Choice(items) => CompiledColumn::Choice(StringPool::new(items)),
+                    ColumnKind::IntChoice(items) => {
+                        CompiledColumn::Choice(StringPool::new(items.iter().map(i64::to_string).collect::<Vec<_>>()))
+                    }
+                    ColumnKind::IntRange { min, max } if min > max => {
+                        return Err(format!("column {}: range minimum {min} is greater than maximum {max}", c.name));
+                    }
+                    ColumnKind::IntRange { min, max } => CompiledColumn::IntRange { min: *min, max: *max },
+                    ColumnKind::Sequence { start } => CompiledColumn::Sequence { start: *start },
+                    ColumnKind::ParentKey => CompiledColumn::ParentKey,
+                    ColumnKind::ForeignKey { min, max, .. } if min > max => {
+                        return Err(format!("column {}: range minimum {min} is greater than maximum {max}", c.name));
+                    }
+                    ColumnKind::ForeignKey { matching, .. } if !(0.0..=1.0).contains(matching) => {
+                        return Err(format!("column {}: the matching fraction must be between 0 and 1", c.name));
+                    }
+                    ColumnKind::ForeignKey { skew, .. } if !(*skew >= 0.0 && skew.is_finite()) => {
+                        return Err(format!("column {}: the skew must be a non-negative number", c.name));
+                    }
+                    ColumnKind::ForeignKey { min, max, matching, .. }
+                        if max.checked_sub(*min).is_none() || (*matching < 1.0 && orphan_keys(*min, *max).is_none()) =>
+                    {
+                        return Err(format!(
+                            "column {}: keys {min} to {max} leave no room above them for the keys of missing rows",
+                            c.name
+                        ));
+                    }
+                    ColumnKind::ForeignKey { min, max, matching, skew } => {
+                        CompiledColumn::ForeignKey { min: *min, max: *max, matching: *matching, skew: *skew }
+                    }
+                    ColumnKind::ChildNumber => CompiledColumn::ChildNumber,
+                    ColumnKind::Label { prefix, width } => {
+                        CompiledColumn::Label { prefix: prefix.as_bytes().to_vec(), width: *width }
+                    }
+                    ColumnKind::Cycle(items) if items.is_empty() => {
+                        return Err(format!("column {}: nothing to cycle through", c.name));
+                    }
+                    ColumnKind::Cycle(items) => CompiledColumn::Cycle(items.iter().map(|s| s.as_bytes().to_vec()).collect()),
+                    ColumnKind::DateRange { min, max }
+                    | ColumnKind::TimestampRange { min, max }
+                    | ColumnKind::Decimal { min, max, .. }
+                        if min > max =>
+                    {
+                        return Err(format!("column {}: range minimum is greater than maximum", c.name));
+                    }
+                    // Four-digit years keep every date the same width.
+                    ColumnKind::DateRange { min, max } if !(-719_528..=2_932_896).contains(min) || !(-719_528..=2_932_896).contains(max) => {
+                        return Err(format!("column {}: dates must be within the years 0000 to 9999", c.name));
+                    }
+                    ColumnKind::DateRange { min, max } => CompiledColumn::DateRange { min: *min, max: *max },
+                    ColumnKind::TimestampRange { min, max }
+                        if !(-719_528 * 86_400..2_932_897 * 86_400).contains(min)
+                            || !(-719_528 * 86_400..2_932_897 * 86_400).contains(max) =>
+                    {
+                        return Err(format!("column {}: times must be within the years 0000 to 9999", c.name));
+                    }
+                    ColumnKind::TimestampRange { min, max } => CompiledColumn::TimestampRange { min: *min, max: *max },
+                    ColumnKind::Clock { start, .. } if !(-719_528 * 86_400..2_932_897 * 86_400).contains(start) => {
+                        return Err(format!("column {}: times must be within the years 0000 to 9999", c.name));
+                    }
+                    ColumnKind::Clock { per_second, skew, .. }
+                        if !(*per_second > 0.0 && per_second.is_finite() && *skew >= 0.0 && skew.is_finite()) =>
+                    {
+                        return Err(format!("column {}: a clock needs a positive rate and a non-negative skew", c.name));
+                    }
+                    ColumnKind::Clock { start, per_second, skew } => {
+                        CompiledColumn::Clock { start: *start, per_second: *per_second, skew: *skew }
+                    }
+                    ColumnKind::Decimal { scale, .. } if *scale >= DECIMAL_PRECISION => {
+                        return Err(format!("column {}: scale must be below {DECIMAL_PRECISION}", c.name));
+                    }
+                    ColumnKind::Decimal { min, max, scale } => CompiledColumn::Decimal { min: *min, max: *max, scale: *scale },
+                    ColumnKind::Format { formats, .. } if formats.is_empty() => {
+                        return Err(format!("column {}: no formats to choose from", c.name));
+                    }
+                    ColumnKind::Format { formats, lists } => CompiledColumn::Format(
+                        formats
+                            .iter()
+                            .map(|f| compile_format(f, lists))
+                            .collect::<Result<_, _>>()
+                            .map_err(|e| format!("column {}: {e}", c.name))?,
+                    ),
+                    // Only the built-in times are sure to be written as
+                    // `read_timestamp` reads them; scripts and plugins may
+                    // write any text as a timestamp.
+                    ColumnKind::Lagged { of, .. }
+                        if *of >= i
+                            || !matches!(
+                                columns[*of].kind,
+                                ColumnKind::TimestampRange { .. } | ColumnKind::Clock { .. } | ColumnKind::Lagged { .. }
+                            ) =>
+                    {
+                        return Err(format!(
+                            "column {}: a lag follows an earlier timestamp range, clock or lagged column",
+                            c.name
+                        ));
+                    }
+                    ColumnKind::Lagged { lag, .. } if let Err(e) = lag.check() => {
+                        return Err(format!("column {}: {e}", c.name));
+                    }
+                    ColumnKind::Lagged { of, lag } => CompiledColumn::Lagged { of: *of, lag: *lag },
+                    ColumnKind::Unicode { min, max, .. } if min > max => {
+                        return Err(format!("column {}: length minimum {min} is greater than maximum {max}", c.name));
+                    }
+                    ColumnKind::Unicode { mix, .. } if let Err(e) = mix.check() => {
+                        return Err(format!("column {}: {e}", c.name));
+                    }
+                    ColumnKind::Unicode { min, max, mix } => {
+                        CompiledColumn::Unicode { min: *min, max: *max, thresholds: unicode::thresholds(mix) }
+                    }
+                    ColumnKind::NationalId { country, synthetic } => {
+                        CompiledColumn::NationalId { country: *country, synthetic: *synthetic }
+                    }
+                    ColumnKind::Geo { shape: Shape::Geohash { precision }, .. } if !(1..=geo::MAX_PRECISION).contains(precision) => {
+                        return Err(format!("column {}: geohash precision must be 1 to {}", c.name, geo::MAX_PRECISION));
+                    }
+                    ColumnKind::Geo { shape: Shape::Polygon { vertices, radius }, .. }
+                        if *vertices < 3 || !(*radius > 0.0 && radius.is_finite()) =>
+                    {
+                        return Err(format!("column {}: a polygon needs 3 or more vertices and a positive radius", c.name));
+                    }
+                    ColumnKind::Geo { area, .. } if area.is_empty() => return Err(format!("column {}: no area to draw points in", c.name)),
+                    ColumnKind::Geo { area, .. } if let Some(Err(e)) = area.iter().map(Bounds::check).find(Result::is_err) => {
+                        return Err(format!("column {}: {e}", c.name));
+                    }
+                    ColumnKind::Geo { shape, area } => {
+                        let slot = areas.iter().position(|a| *a == &area[..]).unwrap_or_else(|| {
+                            areas.push(area);
+                            areas.len() - 1
+                        });
+                        if slot >= POINT_SLOTS {
+                            return Err(format!("column {}: geo columns can draw in at most {POINT_SLOTS} areas", c.name));
+                        }
+                        CompiledColumn::Geo { shape: *shape, area: area.clone(), slot }
+                    }
+                    #[cfg(feature = "script")]
+                    ColumnKind::Script { source, data_type } => {
+                        let script = Script::compile(source, &columns[..i]).map_err(|e| format!("column {}: {e}", c.name))?;
+                        if !script.writes(*data_type) {
+                            return Err(format!("column {}: the script's values can't be written as {data_type:?}", c.name));
+                        }
+                        CompiledColumn::Script { script, data_type: *data_type }
+                    }
+                    #[cfg(not(feature = "script"))]
+                    ColumnKind::Script { .. } => {
+                        return Err(format!("column {}: {}", c.name, crate::without("script", "script column")));
+                    }
+                    ColumnKind::Plugin { name, .. } => {
+                        CompiledColumn::Plugin(plugin::generator(name).map_err(|e| format!("column {}: {e}", c.name))?)
+                    }
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Generator { columns, types })
+    }
+
+    /// The rows of a table, from its first, drawing values from `rng`. Take
+    /// as many as needed; the iterator doesn't end.
+    pub fn rows<R: Rng>(&self, rng: R) -> Rows<'_, R> {
+        Rows { generator: self, rng, index: 0, hook: None }
+    }
+
+    /// [`Generator::rows`] as an async [`Stream`], which yields to the
+    /// runtime every few rows; needs the `async` feature.
+    #[cfg(feature = "async")]
+    pub fn stream<R: Rng + Unpin>(&self, rng: R) -> impl Stream<Item = Row> {
+        tokio_stream::iter(self.rows(rng))
+    }
+
+    /// Clears `row` and fills it with one value per column.
+    pub fn fill_row<R: Rng + ?Sized>(&self, rng: &mut R, row: &mut Row) {
+        self.fill_keyed_row(rng, row, RowKeys::default());
+    }
+
+    /// Like [`Generator::fill_row`], with `keys` giving the values of
+    /// sequence and parent key columns.
+    pub fn fill_keyed_row<R: Rng + ?Sized>(&self, rng: &mut R, row: &mut Row, keys: RowKeys) {
+        row.clear();
+        let mut points = [None; POINT_SLOTS];
+        for column in &self.columns {
+            match column {
+                CompiledColumn::Sha256Hex => {
+                    let random_bytes: [u8; 32] = rng.r#gen();
+                    let hash = Sha256::digest(random_bytes);
+                    // SHA256 is 32 bytes, so its hex encoding is exactly 64; encode
+                    // straight into the row buffer instead of through a String.
+                    let field = row.push_uninit(64);
+                    hex::encode_to_slice(hash.as_slice(), field)
+                        .expect("buffer is sized for a SHA256 digest");
+                }
+                CompiledColumn::UuidV4 => uuid_v4(rng, row.push_uninit(36)),
+                CompiledColumn::Choice(pool) => row.push(pool.choose(rng)),
+                CompiledColumn::IntRange { min, max } => {
+                    let value = rng.gen_range(*min..=*max);
+                    row.push(itoa::Buffer::new().format(value).as_bytes());
+                }
+                CompiledColumn::Sequence { start } => {
+                    let value = start.wrapping_add(keys.index as i64);
+                    row.push(itoa::Buffer::new().format(value).as_bytes());
+                }
+                CompiledColumn::ParentKey => row.push(itoa::Buffer::new().format(keys.parent).as_bytes()),
+                CompiledColumn::ForeignKey { min, max, matching, skew } => {
+                    let value = if !is_matching_row(keys.index, *matching) {
+                        rng.gen_range(orphan_keys(*min, *max).expect("compile checks the orphan keys fit"))
+                    } else if *skew > 0.0 {
+                        min + sample_power_law(rng, 0, (max - min) as u64, *skew) as i64
+                    } else {
+                        rng.gen_range(*min..=*max)
+                    };
+                    row.push(itoa::Buffer::new().format(value).as_bytes());
+                }
+                CompiledColumn::ChildNumber => row.push(itoa::Buffer::new().format(keys.child + 1).as_bytes()),
+                CompiledColumn::Label { prefix, width } => {
+                    let mut digits = itoa::Buffer::new();
+                    let digits = digits.format(keys.index + 1).as_bytes();
+                    let padding = width.saturating_sub(digits.len());
+                    let field = row.push_uninit(prefix.len() + padding + digits.len());
+                    field[..prefix.len()].copy_from_slice(prefix);
+                    field[prefix.len()..prefix.len() + padding].fill(b'0');
+                    field[prefix.len() + padding..].copy_from_slice(digits);
+                }
+                CompiledColumn::Cycle(items) => row.push(&items[(keys.index % items.len() as u64) as usize]),
+                CompiledColumn::DateRange { min, max } => push_date(row, rng.gen_range(*min..=*max)),
+                CompiledColumn::TimestampRange { min, max } => push_timestamp(row, rng.gen_range(*min..=*max)),
+                CompiledColumn::Clock { start, per_second, skew } => {
+                    let elapsed = (keys.index as f64 / per_second) as i64;
+                    let lag = sample_lag(rng, &Lag::Exponential { mean: *skew });
+                    push_timestamp(row, start.saturating_add(elapsed).saturating_sub(lag).clamp(-719_528 * 86_400, LATEST_TIME));
+                }
+                CompiledColumn::Decimal { min, max, scale } => {
+                    push_decimal(row, rng.gen_range(*min..=*max), *scale);
+                }
+                CompiledColumn::Format(formats) => {
+                    let pieces = &formats[rng.gen_range(0..formats.len())];
+                    row.push_with(|field| {
+                        for piece in pieces {
+                            match piece {
+                                Piece::Text(text) => field.extend_from_slice(text),
+                                Piece::Digit => field.push(rng.gen_range(b'0'..=b'9')),
+                                Piece::NonZeroDigit => field.push(rng.gen_range(b'1'..=b'9')),
+                                Piece::List(pool) => field.extend_from_slice(pool.choose(rng)),
+                            }
+                        }
+                    });
+                }
+                CompiledColumn::Lagged { of, lag } => {
+                    let time = read_timestamp(row.field(*of));
+                    push_timestamp(row, time.saturating_add(sample_lag(rng, lag)).min(LATEST_TIME));
+                }
+                CompiledColumn::Unicode { min, max, thresholds } => {
+                    row.push_with(|field| unicode::push_text(rng, field, *min, *max, thresholds));
+                }
+                CompiledColumn::NationalId { country, synthetic } => {
+                    row.push_with(|field| national_id::push(rng, *country, *synthetic, field));
+                }
+                CompiledColumn::Geo { shape, area, slot } => {
+                    let point = *points[*slot].get_or_insert_with(|| geo::sample(rng, area));
+                    row.push_with(|field| geo::push(rng, *shape, point, field));
+                }
+                #[cfg(feature = "script")]
+                CompiledColumn::Script { script, data_type } => script.push(rng, row, keys.index, *data_type),
+                CompiledColumn::Plugin(generator) => {
+                    let random = rng.next_u64();
+                    row.push_with(|field| generator.push(field, keys.index, random));
+                }
+            }
+        }
+    }
+}
+
+/// A value in `min..=max` with probability roughly proportional to
+/// `(value + 1)^-exponent`, by inverting the continuous power law's
+/// distribution function and rounding down.
+pub fn sample_power_law<R: Rng + ?Sized>(rng: &mut R, min: u64, max: u64, exponent: f64) -> u64 {
+    let (low, high) = (min as f64 + 1.0, max as f64 + 2.0);
+    let u: f64 = rng.r#gen();
+    let x = if (exponent - 1.0).abs() < 1e-9 {
+        low * (high / low).powf(u)
+    } else {
+        let power = 1.0 - exponent;
+        (low.powf(power) + u * (high.powf(power) - low.powf(power))).powf(1.0 / power)
+    };
+    (x.floor() as u64).saturating_sub(1).clamp(min, max)
+}
+
+/// A lag in whole seconds.
+fn sample_lag<R: Rng + ?Sized>(rng: &mut R, lag: &Lag) -> i64 {
+    match *lag {
+        Lag::Fixed { seconds } => seconds,
+        Lag::Uniform { min, max } => rng.gen_range(min..=max),
+        Lag::Exponential { mean } => (-mean * (1.0 - rng.r#gen::<f64>()).ln()) as i64,
+        Lag::LogNormal { median, sigma } => {
+            let (u, v): (f64, f64) = (rng.r#gen::<f64>().max(f64::MIN_POSITIVE), rng.r#gen());
+            let normal = (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos();
+            (median * (sigma * normal).exp()) as i64
+        }
+    }
+}
+
+/// Writes a version 4 UUID of `rng`'s random bits into `out`, which is 36
+/// bytes long: the version nibble is 4 and the variant bits are `10`.
+pub(crate) fn uuid_v4<R: Rng + ?Sized>(rng: &mut R, out: &mut [u8]) {
+    let mut bytes: [u8; 16] = rng.r#gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let mut at = 0;
+    for (i, group) in [0..4, 4..6, 6..8, 8..10, 10..16].into_iter().enumerate() {
+        if i > 0 {
+            out[at] = b'-';
+            at += 1;
+        }
+        let len = 2 * group.len();
+        hex::encode_to_slice(&bytes[group], &mut out[at..at + len]).expect("groups fit in 36 bytes");
+        at += len;
+    }
+}
+
+/// The keys a foreign key to rows `min..=max` takes when it points at no
+/// row: as many again, right after `max`, if they fit in an `i64`.
+fn orphan_keys(min: i64, max: i64) -> Option<std::ops::RangeInclusive<i64>> {
+    let first = max.checked_add(1)?;
+    Some(first..=max.checked_add(max.checked_sub(min)?.checked_add(1)?)?)
+}
+
+/// Reads back a time [`push_timestamp`] wrote, as seconds since 1970-01-01 UTC.
+fn read_timestamp(field: &[u8]) -> i64 {
+    let number = |range: std::ops::Range<usize>| field[range].iter().fold(0, |n, b| n * 10 + i64::from(b - b'0'));
+    let days = days_from_civil(number(0..4), number(5..7) as u32, number(8..10) as u32);
+    days * 86_400 + number(11..13) * 3600 + number(14..16) * 60 + number(17..19)
+}
+
+/// Appends a day counted from 1970-01-01 as `YYYY-MM-DD`. Years past 9999
+/// don't fit, which [`Generator::compile`] checks for date columns.
+pub fn push_date(row: &mut Row, days: i64) {
+    let (year, month, day) = civil_from_days(days);
+    write_date(row.push_uninit(10), year as u32, month, day);
+}
+
+/// Appends seconds since 1970-01-01 UTC as `YYYY-MM-DD HH:MM:SS`.
+pub fn push_timestamp(row: &mut Row, seconds: i64) {
+    let (days, second) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400) as u32);
+    let (year, month, day) = civil_from_days(days);
+    let field = row.push_uninit(19);
+    write_date(&mut field[..10], year as u32, month, day);
+    field[10] = b' ';
+    for (i, value) in [second / 3600, second / 60 % 60, second % 60].into_iter().enumerate() {
+        field[11 + 3 * i] = b'0' + (value / 10) as u8;
+        field[12 + 3 * i] = b'0' + (value % 10) as u8;
+        if i < 2 {
+            field[13 + 3 * i] = b':';
+        }
+    }
+}
+
+/// Writes `YYYY-MM-DD` into a 10-byte field.
+fn write_date(field: &mut [u8], year: u32, month: u32, day: u32) {
+    let digits = |field: &mut [u8], mut value: u32| {
+        for b in field.iter_mut().rev() {
+            *b = b'0' + (value % 10) as u8;
+            value /= 10;
+        }
+    };
+    digits(&mut field[0..4], year);
+    field[4] = b'-';
+    digits(&mut field[5..7], month);
+    field[7] = b'-';
+    digits(&mut field[8..10], day);
+}
+
+/// Appends `unscaled / 10^scale` with exactly `scale` decimal places.
+pub fn push_decimal(row: &mut Row, unscaled: i64, scale: u8) {
+    let mut digits = itoa::Buffer::new();
+    let digits = digits.format(unscaled.unsigned_abs()).as_bytes();
+    let scale = scale as usize;
+    // At least one digit before the point, so 5 at scale 2 is 0.05.
+    let whole = digits.len().saturating_sub(scale).max(1);
+    let padded = whole + scale;
+    let negative = usize::from(unscaled < 0);
+    let field = row.push_uninit(negative + padded + usize::from(scale > 0));
+    if unscaled < 0 {
+        field[0] = b'-';
+    }
+    let number = &mut field[negative..];
+    let mut source = digits.iter().rev();
+    let mut next = || source.next().copied().unwrap_or(b'0');
+    for i in (0..number.len()).rev() {
+        if scale > 0 && i == whole {
+            number[i] = b'.';
+        } else {
+            number[i] = next();
+        }
+    }
+}
+
+/// Called with each row and its index once it's generated, before it's
+/// written, to check or change it in place.
+pub type RowHook = dyn Fn(&mut Row, u64) + Send + Sync;
+
+/// The rows of [`Generator::rows`], each with the next row index as its
+/// keys, as a table's rows are generated.
+pub struct Rows<'a, R> {
+    generator: &'a Generator,
+    rng: R,
+    index: u64,
+    hook: Option<&'a RowHook>,
+}
+
+impl<'a, R: Rng + 'a> Rows<'a, R> {
+    /// The rows with each field as a value of its column's type.
+    pub fn typed(self) -> impl Iterator<Item = Vec<Value>> + 'a {
+        let types = &self.generator.types;
+        self.map(move |row| row.fields().zip(types).map(|(field, &data_type)| Value::parse(field, data_type)).collect())
+    }
+
+    /// The rows serialized by `encoder`, as Kafka and the other record sinks
+    /// send them.
+    pub fn records(self, encoder: RecordEncoder) -> impl Iterator<Item = Result<Vec<u8>, BoxError>> + 'a {
+        self.map(move |row| encoder.encode(&row))
+    }
+}
+
+impl<'a, R: Rng> Rows<'a, R> {
+    /// The rows after `hook` has seen each, if there is one.
+    pub fn hooked(self, hook: Option<&'a RowHook>) -> Self {
+        Rows { hook, ..self }
+    }
+
+    /// Fills `row` with the next row, reusing its buffer.
+    pub fn fill_next(&mut self, row: &mut Row) {
+        let keys = RowKeys { index: self.index, ..RowKeys::default() };
+        self.generator.fill_keyed_row(&mut self.rng, row, keys);
+        if let Some(hook) = self.hook {
+            hook(row, self.index);
+        }
+        self.index += 1;
+    }
+}
+
+impl<R: Rng> Iterator for Rows<'_, R> {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Row> {
+        let mut row = Row::new();
+        self.fill_next(&mut row);
+        Some(row)
+    }
+}
+
+/// A field as a value of its column's [`DataType`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    /// The value times 10^`scale`.
+    Decimal { unscaled: i64, scale: u8 },
+    /// Days since 1970-01-01.
+    Date(i64),
+    /// Seconds since 1970-01-01 00:00:00.
+    Timestamp(i64),
+    /// An empty field of a column that isn't text, which presets leave for
+    /// missing values.
+    Null,
+}
+
+impl Value {
+    /// `field` as a `data_type`, or as text if it isn't one.
+    pub fn parse(field: &[u8], data_type: DataType) -> Value {
+        let text = String::from_utf8_lossy(field);
+        if text.is_empty() && data_type != DataType::String {
+            return Value::Null;
+        }
+        let value = match data_type {
+            DataType::String => None,
+            DataType::Integer => text.parse().ok().map(Value::Integer),
+            DataType::Decimal { scale } => parse_decimal(&text, scale).map(|unscaled| Value::Decimal { unscaled, scale }),
+            DataType::Date => parse_date(&text).map(Value::Date),
+            DataType::Timestamp => parse_timestamp(&text).map(Value::Timestamp),
+        };
+        value.unwrap_or_else(|| Value::String(text.into_owned()))
+    }
+}
+
+/// One generated record, stored as consecutive fields in a reusable buffer.
+#[derive(Debug, Default, Clone)]
+pub struct Row {
+    buf: Vec<u8>,
+    ends: Vec<usize>,
+}
+
+impl Row {
+    pub fn new() -> Self {
+        Row::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.ends.clear();
+    }
+
+    /// Appends a field.
+    pub fn push(&mut self, field: &[u8]) {
+        self.buf.extend_from_slice(field);
+        self.ends.push(self.buf.len());
+    }
+
+    /// Appends a field that `write` appends to the buffer it's given.
+    pub fn push_with(&mut self, write: impl FnOnce(&mut Vec<u8>)) {
+        write(&mut self.buf);
+        self.ends.push(self.buf.len());
+    }
+
+    /// Appends a zeroed field of `len` bytes and returns it for in-place writing.
+    pub fn push_uninit(&mut self, len: usize) -> &mut [u8] {
+        let start = self.buf.len();
+        self.buf.resize(start + len, 0);
+        self.ends.push(self.buf.len());
+        &mut self.buf[start..]
+    }
+
+    /// The field of column `index`.
+    pub fn field(&self, index: usize) -> &[u8] {
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        &self.buf[start..self.ends[index]]
+    }
+
+    /// Replaces the field of column `index`.
+    pub fn set(&mut self, index: usize, field: &[u8]) {
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        let end = self.ends[index];
+        self.buf.splice(start..end, field.iter().copied());
+        for later in &mut self.ends[index..] {
+            *later = *later - end + start + field.len();
+        }
+    }
+
+    /// Iterates the fields in column order.
+    pub fn fields(&self) -> impl Iterator<Item = &[u8]> {
+        let mut start = 0;
+        self.ends.iter().map(move |&end| {
+            let field = &self.buf[start..end];
+            start = end;
+            field
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn row_fields_round_trip() {
+        let mut row = Row::new();
+        row.push(b"a");
+        row.push(b"");
+        row.push_uninit(3).copy_from_slice(b"xyz");
+        assert_eq!(row.fields().collect::<Vec<_>>(), [&b"a"[..], b"", b"xyz"]);
+        row.clear();
+        assert_eq!(row.fields().count(), 0);
+    }
+
+    #[test]
+    fn fills_one_field_per_column() {
+        let generator = Generator::compile(&[
+            Column::new("id", ColumnKind::Sha256Hex),
+            Column::new("name", ColumnKind::Choice(vec!["Ann".into()])),
+            Column::new("age", ColumnKind::IntRange { min: 7, max: 7 }),
+        ])
+        .unwrap();
+        let mut row = Row::new();
+        generator.fill_row(&mut rand::thread_rng(), &mut row);
+        let fields: Vec<_> = row.fields().collect();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0].len(), 64);
+        assert!(fields[0].iter().all(u8::is_ascii_hexdigit));
+        assert_eq!(&fields[1..], [&b"Ann"[..], b"7"]);
+    }
+
+    #[test]
+    fn fills_uuids_from_the_row_rng() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let generator = Generator::compile(&[Column::new("id", ColumnKind::UuidV4)]).unwrap();
+        let uuids = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut row = Row::new();
+            (0..100)
+                .map(|_| {
+                    generator.fill_row(&mut rng, &mut row);
+                    String::from_utf8(row.field(0).to_vec()).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        let ids = uuids(42);
+        assert_eq!(ids, uuids(42));
+        assert_ne!(ids, uuids(43));
+        for id in &ids {
+            let groups: Vec<_> = id.split('-').map(str::len).collect();
+            assert_eq!(groups, [8, 4, 4, 4, 12], "{id}");
+            assert!(id.bytes().all(|b| b == b'-' || b.is_ascii_digit() || (b'a'..=b'f').contains(&b)), "{id}");
+            assert_eq!(&id[14..15], "4", "{id}");
+            assert!(matches!(&id[19..20], "8" | "9" | "a" | "b"), "{id}");
+        }
+    }
+
+    #[test]
+    fn formats_dates_and_decimals() {
+        let mut row = Row::new();
+        write_date(row.push_uninit(10), 1992, 3, 7);
+        for (unscaled, scale) in [(12345, 2), (5, 2), (-5, 2), (-120, 1), (42, 0), (0, 3)] {
+            push_decimal(&mut row, unscaled, scale);
+        }
+        push_timestamp(&mut row, 1_700_000_000);
+        push_timestamp(&mut row, -1);
+        let fields: Vec<_> = row.fields().map(|f| std::str::from_utf8(f).unwrap()).collect();
+        assert_eq!(
+            fields,
+            ["1992-03-07", "123.45", "0.05", "-0.05", "-12.0", "42", "0.000", "2023-11-14 22:13:20", "1969-12-31 23:59:59"]
+        );
+    }
+
+    #[test]
+    fn fills_keyed_columns() {
+        let generator = Generator::compile(&[
+            Column::new("name", ColumnKind::Label { prefix: "Customer#".into(), width: 4 }),
+            Column::new("region", ColumnKind::Cycle(vec!["AFRICA".into(), "ASIA".into()])),
+            Column::new("line", ColumnKind::ChildNumber),
+            Column::new("day", ColumnKind::DateRange { min: 0, max: 0 }),
+        ])
+        .unwrap();
+        let mut row = Row::new();
+        generator.fill_keyed_row(&mut rand::thread_rng(), &mut row, RowKeys { index: 2, parent: 0, child: 1 });
+        assert_eq!(row.fields().collect::<Vec<_>>(), [&b"Customer#0003"[..], b"AFRICA", b"2", b"1970-01-01"]);
+    }
+
+    #[test]
+    fn iterates_rows() {
+        let generator = Generator::compile(&[
+            Column::new("id", ColumnKind::Sequence { start: 1 }),
+            Column::new("price", ColumnKind::Decimal { min: 250, max: 250, scale: 2 }),
+            Column::new("day", ColumnKind::DateRange { min: 3, max: 3 }),
+        ])
+        .unwrap();
+        let ids: Vec<_> = generator.rows(rand::thread_rng()).take(3).map(|row| row.field(0).to_vec()).collect();
+        assert_eq!(ids, [b"1", b"2", b"3"]);
+        let typed: Vec<_> = generator.rows(rand::thread_rng()).typed().nth(1).unwrap();
+        assert_eq!(typed, [Value::Integer(2), Value::Decimal { unscaled: 250, scale: 2 }, Value::Date(3)]);
+        assert_eq!(Value::parse(b"", DataType::Integer), Value::Null);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn streams_rows() {
+        use tokio_stream::StreamExt;
+
+        let generator = Generator::compile(&[Column::new("id", ColumnKind::Sequence { start: 1 })]).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let rows: Vec<Row> = runtime.block_on(generator.stream(rand::thread_rng()).take(100).collect());
+        assert_eq!(rows.len(), 100);
+    }
+
+    #[test]
+    fn foreign_keys_match_the_requested_fraction() {
+        let generator = Generator::compile(&[Column::new("customer_id", ColumnKind::ForeignKey { min: 1, max: 10, matching: 0.9, skew: 0.0 })]).unwrap();
+        let mut row = Row::new();
+        let matched = (0..1000)
+            .filter(|&index| {
+                generator.fill_keyed_row(&mut rand::thread_rng(), &mut row, RowKeys { index, ..RowKeys::default() });
+                let key: i64 = std::str::from_utf8(row.fields().next().unwrap()).unwrap().parse().unwrap();
+                assert!((1..=20).contains(&key), "{key}");
+                key <= 10
+            })
+            .count();
+        assert_eq!(matched, 900);
+    }
+
+    #[test]
+    fn foreign_keys_need_room_for_their_orphans() {
+        let key = |min, max, matching| Generator::compile(&[Column::new("k", ColumnKind::ForeignKey { min, max, matching, skew: 0.0 })]);
+        let error = key(i64::MAX - 10, i64::MAX - 5, 0.5).err().unwrap();
+        assert!(error.to_string().ends_with("leave no room above them for the keys of missing rows"), "{error}");
+        assert!(key(i64::MIN, i64::MAX, 1.0).is_err());
+        // Every key matching needs no room, and the last orphan can be i64::MAX.
+        assert!(key(i64::MAX - 10, i64::MAX, 1.0).is_ok());
+        assert_eq!(orphan_keys(i64::MAX - 9, i64::MAX - 5), Some(i64::MAX - 4..=i64::MAX));
+        let generator = key(i64::MAX - 9, i64::MAX - 5, 0.0).unwrap();
+        let mut row = Row::new();
+        for index in 0..100 {
+            generator.fill_keyed_row(&mut rand::thread_rng(), &mut row, RowKeys { index, ..RowKeys::default() });
+            let key: i64 = std::str::from_utf8(row.field(0)).unwrap().parse().unwrap();
+            assert!(key > i64::MAX - 5, "{key}");
+        }
+    }
+
+    #[test]
+    fn lagged_times_follow_their_column() {
+        let lag = |lag| ColumnKind::Lagged { of: 0, lag };
+        let generator = Generator::compile(&[
+            Column::new("occurred_at", ColumnKind::TimestampRange { min: -86_400 * 365, max: 2_000_000_000 }),
+            Column::new("ingested_at", lag(Lag::LogNormal { median: 30.0, sigma: 2.0 })),
+            Column::new("exported_at", lag(Lag::Fixed { seconds: 3600 })),
+        ])
+        .unwrap();
+        let mut row = Row::new();
+        for _ in 0..1000 {
+            generator.fill_row(&mut rand::thread_rng(), &mut row);
+            let times: Vec<_> = row.fields().map(read_timestamp).collect();
+            let parsed = crate::schema::parse_timestamp(std::str::from_utf8(row.field(0)).unwrap()).unwrap();
+            assert_eq!(times[0], parsed);
+            assert!(times[1] >= times[0]);
+            assert_eq!(times[2], times[0] + 3600);
+        }
+        let forward = [Column::new("at", lag(Lag::Fixed { seconds: 1 })), Column::new("b", ColumnKind::TimestampRange { min: 0, max: 0 })];
+        assert!(Generator::compile(&forward).is_err());
+        let negative = [Column::new("a", ColumnKind::TimestampRange { min: 0, max: 0 }), Column::new("b", lag(Lag::Uniform { min: -5, max: 5 }))];
+        assert!(Generator::compile(&negative).is_err());
+        #[cfg(feature = "script")]
+        {
+            let script = ColumnKind::Script { source: r#""soon""#.into(), data_type: crate::schema::DataType::Timestamp };
+            let scripted = [Column::new("a", script), Column::new("b", lag(Lag::Fixed { seconds: 1 }))];
+            let error = Generator::compile(&scripted).err().unwrap();
+            assert_eq!(error.to_string(), "column b: a lag follows an earlier timestamp range, clock or lagged column");
+        }
+    }
+
+    #[test]
+    fn geo_columns_of_an_area_share_a_point() {
+        let paris = vec![Bounds { south: 48.8, west: 2.2, north: 48.9, east: 2.4 }];
+        let geo = |shape, area: &Vec<Bounds>| ColumnKind::Geo { shape, area: area.clone() };
+        let generator = Generator::compile(&[
+            Column::new("lat", geo(Shape::Latitude, &paris)),
+            Column::new("elsewhere", geo(Shape::Latitude, &vec![geo::WORLD])),
+            Column::new("lon", geo(Shape::Longitude, &paris)),
+            Column::new("at", geo(Shape::Point, &paris)),
+            Column::new("cell", geo(Shape::Geohash { precision: 3 }, &paris)),
+        ])
+        .unwrap();
+        let mut row = Row::new();
+        for _ in 0..100 {
+            generator.fill_row(&mut rand::thread_rng(), &mut row);
+            let fields: Vec<_> = row.fields().map(|f| std::str::from_utf8(f).unwrap()).collect();
+            assert_eq!(fields[3], format!("POINT ({} {})", fields[2], fields[0]));
+            assert!((48.8..=48.9).contains(&fields[0].parse::<f64>().unwrap()));
+            // Paris, to about 150 km.
+            assert_eq!(fields[4], "u09");
+        }
+        let polygon = [Column::new("zone", geo(Shape::Polygon { vertices: 2, radius: 10.0 }, &paris))];
+        assert!(Generator::compile(&polygon).is_err());
+    }
+
+    #[test]
+    fn power_laws_favor_small_values() {
+        let mut rng = rand::thread_rng();
+        let samples: Vec<_> = (0..100_000).map(|_| sample_power_law(&mut rng, 0, 1000, 2.0)).collect();
+        assert!(samples.iter().all(|&s| s <= 1000));
+        let count = |value| samples.iter().filter(|&&s| s == value).count() as f64;
+        // P(0) / P(1) is (1/1)^-2 / (1/2)^-2, about 4 for the discrete law.
+        let ratio = count(0) / count(1);
+        assert!((2.5..5.0).contains(&ratio), "{ratio}");
+        assert!(samples.iter().any(|&s| s > 50));
+        assert_eq!(sample_power_law(&mut rng, 3, 3, 1.0), 3);
+    }
+
+    #[test]
+    fn rejects_inverted_ranges() {
+        let error = Generator::compile(&[Column::new("age", ColumnKind::IntRange { min: 60, max: 18 })])
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("column age"), "{error}");
+    }
+}
@@ -0,0 +1,207 @@
+//! Places for [`ColumnKind::Geo`](crate::schema::ColumnKind::Geo) columns:
+//! points in an area, written as coordinates, geohashes or WKT.
+
+use std::io::Write;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// A box of latitudes and longitudes, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+/// How a [`ColumnKind::Geo`](crate::schema::ColumnKind::Geo) column writes its point.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+    Latitude,
+    Longitude,
+    /// The geohash of the point, `precision` characters long.
+    Geohash { precision: usize },
+    /// `POINT (lon lat)`.
+    Point,
+    /// A `POLYGON` of `vertices` corners around the point, within `radius` metres.
+    Polygon { vertices: usize, radius: f64 },
+}
+
+/// The whole globe.
+pub const WORLD: Bounds = Bounds { south: -90.0, west: -180.0, north: 90.0, east: 180.0 };
+
+const fn b(south: f64, north: f64, west: f64, east: f64) -> Bounds {
+    Bounds { south, west, north, east }
+}
+
+/// Boxes inside each country's borders, so that every point drawn from them
+/// is in the country.
+const COUNTRIES: &[(&str, &[Bounds])] = &[
+    ("us", &[b(35.0, 42.0, -119.0, -84.0), b(31.0, 35.0, -103.0, -85.0), b(37.0, 42.0, -84.0, -77.0)]),
+    ("ca", &[b(50.0, 58.0, -120.0, -95.0), b(45.5, 48.5, -79.0, -71.0)]),
+    ("uk", &[b(51.0, 53.0, -2.5, 0.5), b(53.0, 55.0, -3.0, -1.5)]),
+    ("fr", &[b(44.0, 48.5, -0.5, 5.0)]),
+    ("es", &[b(37.5, 42.0, -6.0, -1.0)]),
+    ("de", &[b(48.5, 53.0, 8.0, 12.0)]),
+    ("nl", &[b(51.9, 52.9, 4.8, 6.0)]),
+    ("br", &[b(-20.0, -5.0, -55.0, -40.0)]),
+    ("in", &[b(17.0, 25.0, 74.0, 84.0)]),
+    ("cn", &[b(26.0, 40.0, 100.0, 115.0)]),
+    ("jp", &[b(35.0, 36.5, 136.5, 139.5)]),
+    ("au", &[b(-33.0, -22.0, 118.0, 148.0)]),
+];
+
+/// The boxes of a country by its two-letter code.
+pub fn country(code: &str) -> Option<&'static [Bounds]> {
+    COUNTRIES.iter().find(|(c, _)| *c == code).map(|(_, area)| *area)
+}
+
+/// The codes [`country`] knows, for error messages.
+pub fn country_codes() -> Vec<&'static str> {
+    COUNTRIES.iter().map(|(c, _)| *c).collect()
+}
+
+impl Bounds {
+    /// Why the box isn't one, if it isn't.
+    pub fn check(&self) -> Result<(), String> {
+        let Bounds { south, west, north, east } = *self;
+        if !(-90.0 <= south && south <= north && north <= 90.0 && -180.0 <= west && west <= east && east <= 180.0) {
+            return Err(format!("bounds [{west}, {south}, {east}, {north}] aren't west, south, east and north in degrees"));
+        }
+        Ok(())
+    }
+
+    fn area(&self) -> f64 {
+        (self.north - self.south) * (self.east - self.west)
+    }
+}
+
+/// A uniformly chosen `(latitude, longitude)` in `area`, picking a box by its size.
+pub fn sample<R: Rng + ?Sized>(rng: &mut R, area: &[Bounds]) -> (f64, f64) {
+    let total: f64 = area.iter().map(Bounds::area).sum();
+    let mut u = rng.gen_range(0.0..=total);
+    let bounds = area
+        .iter()
+        .find(|b| {
+            u -= b.area();
+            u <= 0.0
+        })
+        .unwrap_or(&area[area.len() - 1]);
+    (rng.gen_range(bounds.south..=bounds.north), rng.gen_range(bounds.west..=bounds.east))
+}
+
+const BASE32: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// The longest geohash written, which is already finer than a centimetre.
+pub const MAX_PRECISION: usize = 12;
+
+/// Appends `(lat, lon)` in the form of `shape`.
+pub fn push<R: Rng + ?Sized>(rng: &mut R, shape: Shape, (lat, lon): (f64, f64), field: &mut Vec<u8>) {
+    match shape {
+        Shape::Latitude => write!(field, "{lat:.6}"),
+        Shape::Longitude => write!(field, "{lon:.6}"),
+        Shape::Geohash { precision } => {
+            push_geohash(field, lat, lon, precision);
+            Ok(())
+        }
+        Shape::Point => write!(field, "POINT ({lon:.6} {lat:.6})"),
+        Shape::Polygon { vertices, radius } => {
+            // Corners at increasing angles make a simple ring, counterclockwise
+            // as WKT wants its exterior rings.
+            let degrees_lat = radius / 111_320.0;
+            let degrees_lon = degrees_lat / lat.to_radians().cos().max(0.01);
+            let step = std::f64::consts::TAU / vertices as f64;
+            let mut first = (0.0, 0.0);
+            let _ = field.write_all(b"POLYGON ((");
+            for i in 0..vertices {
+                let angle = step * (i as f64 + rng.gen_range(-0.25..0.25));
+                let scale = rng.gen_range(0.5..=1.0);
+                let corner = (
+                    (lon + degrees_lon * scale * angle.cos()).clamp(-180.0, 180.0),
+                    (lat + degrees_lat * scale * angle.sin()).clamp(-90.0, 90.0),
+                );
+                if i == 0 {
+                    first = corner;
+                }
+                let _ = write!(field, "{:.6} {:.6}, ", corner.0, corner.1);
+            }
+            write!(field, "{:.6} {:.6}))", first.0, first.1)
+        }
+    }
+    .expect("writing to a Vec doesn't fail");
+}
+
+/// Appends the geohash of `(lat, lon)`, interleaving longitude and latitude
+/// bits from the longitude's.
+fn push_geohash(field: &mut Vec<u8>, lat: f64, lon: f64, precision: usize) {
+    let (mut lats, mut lons) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut even = true;
+    for _ in 0..precision {
+        let mut index = 0;
+        for _ in 0..5 {
+            let (range, value) = if even { (&mut lons, lon) } else { (&mut lats, lat) };
+            let middle = (range.0 + range.1) / 2.0;
+            index <<= 1;
+            if value >= middle {
+                index |= 1;
+                range.0 = middle;
+            } else {
+                range.1 = middle;
+            }
+            even = !even;
+        }
+        field.push(BASE32[index]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(shape: Shape, point: (f64, f64)) -> String {
+        let mut field = Vec::new();
+        push(&mut rand::thread_rng(), shape, point, &mut field);
+        String::from_utf8(field).unwrap()
+    }
+
+    #[test]
+    fn geohashes_match_known_cells() {
+        assert_eq!(text(Shape::Geohash { precision: 11 }, (57.64911, 10.40744)), "u4pruydqqvj");
+        assert_eq!(text(Shape::Geohash { precision: 5 }, (-25.382708, -49.265506)), "6gkzw");
+        assert_eq!(text(Shape::Point, (1.5, -2.25)), "POINT (-2.250000 1.500000)");
+    }
+
+    #[test]
+    fn points_stay_in_their_area() {
+        let mut rng = rand::thread_rng();
+        for (code, area) in COUNTRIES {
+            for _ in 0..200 {
+                let (lat, lon) = sample(&mut rng, area);
+                assert!(area.iter().any(|b| (b.south..=b.north).contains(&lat) && (b.west..=b.east).contains(&lon)), "{code}");
+            }
+        }
+        assert!(b(10.0, 0.0, 0.0, 1.0).check().is_err());
+        assert!(WORLD.check().is_ok());
+    }
+
+    #[test]
+    fn polygons_are_closed_rings_around_the_point() {
+        let polygon = text(Shape::Polygon { vertices: 5, radius: 1000.0 }, (48.85, 2.35));
+        let ring = polygon.strip_prefix("POLYGON ((").unwrap().strip_suffix("))").unwrap();
+        let corners: Vec<(f64, f64)> = ring
+            .split(", ")
+            .map(|c| {
+                let (x, y) = c.split_once(' ').unwrap();
+                (x.parse().unwrap(), y.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(corners.len(), 6);
+        assert_eq!(corners[0], corners[5]);
+        // A positive shoelace area means counterclockwise.
+        let area: f64 = corners.windows(2).map(|w| w[0].0 * w[1].1 - w[1].0 * w[0].1).sum();
+        assert!(area > 0.0);
+        assert!(corners.iter().all(|&(lon, lat)| (lat - 48.85).abs() < 0.01 && (lon - 2.35).abs() < 0.02));
+    }
+}
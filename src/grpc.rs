@@ -0,0 +1,259 @@
+//! `grpc`: a `GenerateRows` server-streaming service, described by
+//! `proto/generator.proto`.
+
+use std::convert::Infallible;
+use std::future::{Future, Ready, ready};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::body::Body;
+use tonic::codegen::{Service, http};
+use tonic::server::NamedService;
+use tonic::{Request, Response, Status};
+use tonic_prost::ProstCodec;
+
+use crate::BoxError;
+use crate::cli::GrpcArgs;
+use crate::generator::{Generator, Row};
+use crate::locale::Locale;
+use crate::schema::{self, Column, DataType};
+
+const SERVICE: &str = "csv_data_generator.v1.Generator";
+const GENERATE_ROWS: &str = "/csv_data_generator.v1.Generator/GenerateRows";
+
+/// Rows sent per response message.
+const BATCH_ROWS: usize = 1000;
+
+/// The messages of `proto/generator.proto`, written out by hand so the build
+/// doesn't need protoc.
+mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GenerateRowsRequest {
+        #[prost(string, tag = "1")]
+        pub schema: String,
+        #[prost(uint64, tag = "2")]
+        pub rows: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GenerateRowsResponse {
+        #[prost(string, repeated, tag = "1")]
+        pub columns: Vec<String>,
+        #[prost(message, repeated, tag = "2")]
+        pub rows: Vec<Row>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Row {
+        #[prost(message, repeated, tag = "1")]
+        pub values: Vec<Value>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Value {
+        #[prost(oneof = "Kind", tags = "1, 2")]
+        pub kind: Option<Kind>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        #[prost(string, tag = "1")]
+        StringValue(String),
+        #[prost(int64, tag = "2")]
+        IntValue(i64),
+    }
+}
+
+/// Converts a generated row into its message, typing each value by its column.
+fn to_message(row: &Row, types: &[DataType]) -> Result<proto::Row, BoxError> {
+    let values = row
+        .fields()
+        .zip(types)
+        .map(|(field, data_type)| {
+            let field = std::str::from_utf8(field)?;
+            let kind = match data_type {
+                DataType::String | DataType::Decimal { .. } | DataType::Date | DataType::Timestamp => {
+                    proto::Kind::StringValue(field.to_string())
+                }
+                DataType::Integer => proto::Kind::IntValue(field.parse()?),
+            };
+            Ok(proto::Value { kind: Some(kind) })
+        })
+        .collect::<Result<_, BoxError>>()?;
+    Ok(proto::Row { values })
+}
+
+/// Checks a request and looks up the columns of its schema.
+fn columns_for(request: &proto::GenerateRowsRequest, max_rows: u64) -> Result<Vec<Column>, Status> {
+    if request.rows > max_rows {
+        return Err(Status::invalid_argument(format!("at most {max_rows} rows can be requested at once")));
+    }
+    let name = if request.schema.is_empty() { "users" } else { &request.schema };
+    schema::builtin(name, Locale::En).ok_or_else(|| Status::invalid_argument(format!("no schema named {name}")))
+}
+
+/// Generates the rows of one call on its own thread, sending them in
+/// batches until done or until the client goes away.
+fn generate(columns: Vec<Column>, rows: u64, responses: mpsc::Sender<Result<proto::GenerateRowsResponse, Status>>) {
+    let run = || -> Result<(), BoxError> {
+        let generator = Generator::compile(&columns)?;
+        let types: Vec<_> = columns.iter().map(|c| c.kind.data_type()).collect();
+        let mut rng = StdRng::from_entropy();
+        let mut row = Row::new();
+        let mut message =
+            proto::GenerateRowsResponse { columns: columns.iter().map(|c| c.name.clone()).collect(), rows: Vec::new() };
+        let mut remaining = rows;
+        loop {
+            while remaining > 0 && message.rows.len() < BATCH_ROWS {
+                generator.fill_row(&mut rng, &mut row);
+                message.rows.push(to_message(&row, &types)?);
+                remaining -= 1;
+            }
+            // The first message always goes out, so clients learn the columns.
+            if responses.blocking_send(Ok(std::mem::take(&mut message))).is_err() || remaining == 0 {
+                return Ok(());
+            }
+        }
+    };
+    if let Err(e) = run() {
+        let _ = responses.blocking_send(Err(Status::internal(e.to_string())));
+    }
+}
+
+/// The `GenerateRows` handler itself.
+struct GenerateRows {
+    max_rows: u64,
+}
+
+type ResponseStream = ReceiverStream<Result<proto::GenerateRowsResponse, Status>>;
+
+impl Service<Request<proto::GenerateRowsRequest>> for GenerateRows {
+    type Response = Response<ResponseStream>;
+    type Error = Status;
+    type Future = Ready<Result<Self::Response, Status>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Status>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<proto::GenerateRowsRequest>) -> Self::Future {
+        let request = request.into_inner();
+        let columns = match columns_for(&request, self.max_rows) {
+            Ok(columns) => columns,
+            Err(status) => return ready(Err(status)),
+        };
+        let (sender, receiver) = mpsc::channel(4);
+        thread::spawn(move || generate(columns, request.rows, sender));
+        ready(Ok(Response::new(ReceiverStream::new(receiver))))
+    }
+}
+
+/// Routes HTTP/2 requests to [`GenerateRows`], in place of the server code
+/// tonic would otherwise generate.
+#[derive(Clone)]
+struct GeneratorServer {
+    max_rows: u64,
+}
+
+impl NamedService for GeneratorServer {
+    const NAME: &'static str = SERVICE;
+}
+
+impl Service<http::Request<Body>> for GeneratorServer {
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let max_rows = self.max_rows;
+        Box::pin(async move {
+            if request.uri().path() != GENERATE_ROWS {
+                return Ok(Status::unimplemented(format!("no method {}", request.uri().path())).into_http());
+            }
+            let codec = ProstCodec::<proto::GenerateRowsResponse, proto::GenerateRowsRequest>::default();
+            Ok(tonic::server::Grpc::new(codec).server_streaming(GenerateRows { max_rows }, request).await)
+        })
+    }
+}
+
+/// Serves `GenerateRows` until the process is stopped.
+pub fn run(args: &GrpcArgs) -> Result<(), BoxError> {
+    let addr: SocketAddr = format!("{}:{}", args.bind, args.port).parse()?;
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    println!("Serving {SERVICE} on {addr}");
+    runtime.block_on(
+        tonic::transport::Server::builder().add_service(GeneratorServer { max_rows: args.max_rows }).serve(addr),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+    use tonic::codegen::http::uri::PathAndQuery;
+
+    #[test]
+    fn rows_keep_their_types() {
+        let mut row = Row::new();
+        row.push(b"Mia");
+        row.push(b"42");
+        let message = to_message(&row, &[DataType::String, DataType::Integer]).unwrap();
+        let kinds: Vec<_> = message.values.into_iter().map(|v| v.kind.unwrap()).collect();
+        assert_eq!(kinds, [proto::Kind::StringValue("Mia".into()), proto::Kind::IntValue(42)]);
+    }
+
+    #[test]
+    fn requests_are_checked() {
+        let request = |schema: &str, rows| proto::GenerateRowsRequest { schema: schema.into(), rows };
+        assert_eq!(columns_for(&request("", 5), 10).unwrap()[0].name, "id");
+        assert_eq!(columns_for(&request("orders", 5), 10).unwrap()[0].name, "order_id");
+        assert_eq!(columns_for(&request("nope", 5), 10).unwrap_err().code(), tonic::Code::InvalidArgument);
+        assert_eq!(columns_for(&request("", 11), 10).unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn streams_rows_to_a_client() {
+        let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(1).enable_all().build().unwrap();
+        let rows = runtime.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(
+                tonic::transport::Server::builder()
+                    .add_service(GeneratorServer { max_rows: u64::MAX })
+                    .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+            );
+
+            let channel = tonic::transport::Channel::from_shared(format!("http://{addr}")).unwrap().connect().await.unwrap();
+            let mut client = tonic::client::Grpc::new(channel);
+            client.ready().await.unwrap();
+            let codec = ProstCodec::<proto::GenerateRowsRequest, proto::GenerateRowsResponse>::default();
+            let request = Request::new(proto::GenerateRowsRequest { schema: "orders".into(), rows: 2500 });
+            let mut stream = client
+                .server_streaming(request, PathAndQuery::from_static(GENERATE_ROWS), codec)
+                .await
+                .unwrap()
+                .into_inner();
+            let first = stream.next().await.unwrap().unwrap();
+            assert_eq!(first.columns[0], "order_id");
+            let mut rows = first.rows.len();
+            while let Some(message) = stream.next().await {
+                let message = message.unwrap();
+                assert!(message.columns.is_empty());
+                rows += message.rows.len();
+            }
+            rows
+        });
+        assert_eq!(rows, 2500);
+    }
+}
@@ -0,0 +1,289 @@
+//! The HR preset: `employees` in a management hierarchy and the
+//! `departments` they work in, for recursive queries and org analytics.
+//!
+//! The chief executive is at level 1 and has no manager. Levels are filled
+//! one after another, each manager taking on around `--hr-span` reports,
+//! until there are enough employees or the tree is `--hr-depth` levels deep,
+//! when the managers one level up take on more. Every report at level 2
+//! heads a department that everyone under them works in. Salaries fall with
+//! each level down, people are hired no earlier than their manager, and
+//! some of those without reports have since left, so every `manager_id` is
+//! of someone still there.
+
+use rand::Rng;
+
+use crate::BoxError;
+use crate::business;
+use crate::cli::Args;
+use crate::generator::{Generator, Row, push_date, push_decimal};
+use crate::locale::Field;
+use crate::presets::{self, push_int};
+use crate::schema::{Column, ColumnKind};
+use crate::transactions::normal;
+
+/// The company was founded on 2005-01-01, and it's now 2025-01-01, in days
+/// since 1970-01-01.
+const FOUNDED: i64 = 12_784;
+const TODAY: i64 = 20_089;
+
+/// Employees at scale factor 1.
+const EMPLOYEES: f64 = 1_000.0;
+/// The chief executive's salary, what each level down earns of the one
+/// above, and the log-scale spread of salaries within a level.
+const TOP_SALARY: f64 = 450_000.0;
+const LEVEL_RATIO: f64 = 0.72;
+const SALARY_SIGMA: f64 = 0.1;
+/// The chance of someone without reports having left.
+const TURNOVER: f64 = 0.12;
+
+const TABLES: [&str; 2] = ["departments", "employees"];
+
+/// Manager titles by level, the last for any level below.
+const MANAGER_TITLES: &[&str] =
+    &["Chief Executive Officer", "Vice President", "Director", "Senior Manager", "Manager", "Team Lead"];
+const ROLES: &[&str] = &["Engineer", "Analyst", "Specialist", "Designer", "Coordinator", "Consultant"];
+const SENIORITIES: &[&str] = &["Junior ", "", "", "Senior ", "Principal "];
+
+/// The columns of each table, in [`TABLES`] order. Only the names and types
+/// of these matter; the values are written below.
+fn columns() -> [Vec<Column>; 2] {
+    let integer = |name| Column::new(name, ColumnKind::Sequence { start: 1 });
+    let string = |name| Column::new(name, ColumnKind::Choice(Vec::new()));
+    let date = |name| Column::new(name, ColumnKind::DateRange { min: 0, max: 0 });
+    [
+        vec![integer("department_id"), string("name"), integer("head_employee_id")],
+        vec![
+            integer("employee_id"),
+            integer("manager_id"),
+            string("first_name"),
+            string("last_name"),
+            string("job_title"),
+            integer("level"),
+            integer("department_id"),
+            Column::new("salary", ColumnKind::Decimal { min: 0, max: 0, scale: 2 }),
+            date("hire_date"),
+            date("termination_date"),
+        ],
+    ]
+}
+
+struct Employee {
+    manager: Option<usize>,
+    level: u64,
+    department: usize,
+    reports: usize,
+}
+
+/// The hierarchy of `count` employees, each after their manager, at most
+/// `depth` levels deep with spans drawn around `span`.
+fn hierarchy<R: Rng + ?Sized>(rng: &mut R, count: usize, depth: u64, span: u64) -> Vec<Employee> {
+    let mut employees = vec![Employee { manager: None, level: 1, department: 0, reports: 0 }];
+    let mut managers = std::collections::VecDeque::from([0]);
+    let (mut departments, mut widening) = (1, false);
+    while employees.len() < count {
+        let manager = match managers.pop_front() {
+            Some(manager) => manager,
+            // The tree is as deep as it may go, so widen the level above the
+            // last one report at a time.
+            None => {
+                managers.extend((0..employees.len()).filter(|&e| employees[e].level == depth - 1));
+                widening = true;
+                continue;
+            }
+        };
+        let level = employees[manager].level + 1;
+        let reports = if widening { 1 } else { rng.gen_range(1..=2 * span - 1) as usize };
+        for _ in 0..reports.min(count - employees.len()) {
+            let department = if level == 2 {
+                departments += 1;
+                departments - 1
+            } else {
+                employees[manager].department
+            };
+            employees[manager].reports += 1;
+            employees.push(Employee { manager: Some(manager), level, department, reports: 0 });
+            if level < depth {
+                managers.push_back(employees.len() - 1);
+            }
+        }
+    }
+    employees
+}
+
+/// Builds the whole org chart first so departments can name their heads,
+/// then writes departments and employees.
+pub fn generate(args: &Args) -> Result<(), BoxError> {
+    let [mut departments_sink, mut employees_sink] = presets::open_tables(args, "HR", TABLES, columns())?;
+
+    let mut rng = rand::thread_rng();
+    let count = ((EMPLOYEES * args.preset.scale_factor).round() as usize).max(1);
+    let employees = hierarchy(&mut rng, count, args.preset.hr_depth, args.preset.hr_span);
+
+    let ColumnKind::Choice(names) = business::department(2, " / ") else { unreachable!("departments are a choice") };
+    let mut row = Row::new();
+    let mut departments = 0;
+    for (id, employee) in (1..).zip(&employees) {
+        if employee.level > 2 {
+            continue;
+        }
+        row.clear();
+        push_int(&mut row, employee.department as u64 + 1);
+        let name = match employee.department {
+            0 => "Executive".to_string(),
+            d if d <= names.len() => names[d - 1].clone(),
+            d => format!("{} {}", names[(d - 1) % names.len()], (d - 1) / names.len() + 1),
+        };
+        row.push(name.as_bytes());
+        push_int(&mut row, id);
+        departments_sink.write_row(&row)?;
+        departments += 1;
+    }
+
+    let names = Generator::compile(&[
+        Column::new("first_name", args.locale.kind(Field::FirstName)),
+        Column::new("last_name", args.locale.kind(Field::LastName)),
+    ])?;
+    let mut name = Row::new();
+    let mut hired = Vec::with_capacity(employees.len());
+    let mut left = 0;
+    for (id, employee) in (1..).zip(&employees) {
+        let after = employee.manager.map_or(FOUNDED, |m| hired[m]);
+        let hire = rng.gen_range(after..TODAY);
+        hired.push(hire);
+        let title = if employee.reports > 0 {
+            MANAGER_TITLES[(employee.level as usize - 1).min(MANAGER_TITLES.len() - 1)].to_string()
+        } else {
+            format!("{}{}", SENIORITIES[rng.gen_range(0..SENIORITIES.len())], ROLES[rng.gen_range(0..ROLES.len())])
+        };
+        let salary = TOP_SALARY * LEVEL_RATIO.powi(employee.level as i32 - 1) * (SALARY_SIGMA * normal(&mut rng)).exp();
+
+        names.fill_row(&mut rng, &mut name);
+        row.clear();
+        push_int(&mut row, id);
+        match employee.manager {
+            Some(manager) => push_int(&mut row, manager as u64 + 1),
+            None => row.push(b""),
+        }
+        row.push(name.field(0));
+        row.push(name.field(1));
+        row.push(title.as_bytes());
+        push_int(&mut row, employee.level);
+        push_int(&mut row, employee.department as u64 + 1);
+        // Salaries are in whole hundreds.
+        push_decimal(&mut row, (salary / 100.0).round() as i64 * 10_000, 2);
+        push_date(&mut row, hire);
+        if employee.reports == 0 && hire + 1 < TODAY && rng.gen_bool(TURNOVER) {
+            push_date(&mut row, rng.gen_range(hire + 1..TODAY));
+            left += 1;
+        } else {
+            row.push(b"");
+        }
+        employees_sink.write_row(&row)?;
+    }
+
+    presets::finish_tables(args, TABLES, [departments_sink, employees_sink], [departments, employees.len() as u64])?;
+    let depth = employees.iter().map(|e| e.level).max().unwrap_or(1);
+    println!("  {depth} levels deep, {left} of {} employees have left", employees.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::testing::{read, temp_dir};
+    use crate::cli::FileFormat;
+    use crate::schema::parse_date;
+    use clap::{Parser, ValueEnum};
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn hierarchies_keep_to_their_depth() {
+        let mut rng = rand::thread_rng();
+        for (count, depth, span) in [(1000, 5, 6), (1000, 3, 2), (50, 10, 1), (1, 4, 4)] {
+            let employees = hierarchy(&mut rng, count, depth, span);
+            assert_eq!(employees.len(), count);
+            assert!(employees.iter().all(|e| e.level <= depth));
+            for (i, e) in employees.iter().enumerate().skip(1) {
+                let manager = &employees[e.manager.unwrap()];
+                assert!(e.manager.unwrap() < i && manager.level + 1 == e.level);
+                assert!(e.level == 2 || e.department == manager.department);
+            }
+            assert_eq!(employees.iter().map(|e| e.reports).sum::<usize>(), count - 1);
+        }
+    }
+
+    #[test]
+    fn managers_are_still_there() {
+        let dir = temp_dir("hr");
+        let template = dir.join("{table}.csv");
+        let args = Args::parse_from(["gen", "--preset", "hr", "--hr-depth", "4", "--output", template.to_str().unwrap()]);
+        generate(&args).unwrap();
+
+        let departments = read(dir.join("departments.csv"));
+        let employees: HashMap<String, Vec<String>> =
+            read(dir.join("employees.csv")).into_iter().map(|e| (e[0].clone(), e)).collect();
+        assert_eq!(employees.len(), 1000);
+        for d in &departments {
+            assert_eq!(employees[&d[2]][6], d[0]);
+        }
+        let mut salaries: HashMap<&str, Vec<f64>> = HashMap::new();
+        for e in employees.values() {
+            salaries.entry(&e[5]).or_default().push(e[7].parse().unwrap());
+            assert!(e[9].is_empty() || parse_date(&e[9]) > parse_date(&e[8]));
+            if e[1].is_empty() {
+                assert_eq!(e[5], "1");
+                continue;
+            }
+            let manager = &employees[&e[1]];
+            assert!(manager[9].is_empty(), "{manager:?}");
+            assert!(parse_date(&manager[8]) <= parse_date(&e[8]));
+            assert_eq!(manager[5].parse::<u64>().unwrap() + 1, e[5].parse::<u64>().unwrap());
+        }
+        assert_eq!(salaries.len(), 4);
+        let mean = |level: &str| salaries[level].iter().sum::<f64>() / salaries[level].len() as f64;
+        assert!(mean("2") > mean("3") && mean("3") > mean("4"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writes_the_root_manager_as_a_null_in_every_format() {
+        let dir = temp_dir("hr_formats");
+        for format in FileFormat::value_variants() {
+            let name = format.to_possible_value().unwrap().get_name().to_string();
+            let template = dir.join(format!("{{table}}.{name}"));
+            let args = Args::parse_from(["gen", "--preset", "hr", "--format", &name, "--output", template.to_str().unwrap()]);
+            let employees = dir.join(format!("employees.{name}"));
+            match format {
+                FileFormat::Csv => {
+                    generate(&args).unwrap();
+                    assert!(read(employees).iter().any(|e| e[1].is_empty()));
+                }
+                FileFormat::Jsonl => {
+                    generate(&args).unwrap();
+                    let managers: Vec<serde_json::Value> = fs::read_to_string(employees)
+                        .unwrap()
+                        .lines()
+                        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["manager_id"].clone())
+                        .collect();
+                    assert_eq!(managers.iter().filter(|m| m.is_null()).count(), 1);
+                    assert!(managers.iter().all(|m| m.is_null() || m.is_u64()));
+                }
+                #[cfg(feature = "parquet")]
+                FileFormat::Parquet => {
+                    use parquet::file::reader::{FileReader, SerializedFileReader};
+                    generate(&args).unwrap();
+                    let reader = SerializedFileReader::new(fs::File::open(employees).unwrap()).unwrap();
+                    let rows: Vec<_> = reader.get_row_iter(None).unwrap().map(|row| row.unwrap().to_string()).collect();
+                    assert_eq!(rows.len(), 1000);
+                    assert!(rows[0].starts_with("{employee_id: 1, manager_id: null, "), "{}", rows[0]);
+                }
+                #[cfg(not(feature = "parquet"))]
+                FileFormat::Parquet => assert!(generate(&args).is_err()),
+                // Combined log lines are of `--schema access_log` alone.
+                FileFormat::Combined => assert!(generate(&args).is_err()),
+            }
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
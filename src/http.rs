@@ -0,0 +1,80 @@
+use ureq::http::Response;
+use ureq::{Agent, Body};
+
+use crate::retry::Failure;
+
+/// Builds an agent that hands every status code back to the caller, so that
+/// protocol-specific codes (like GCS's 308 "Resume Incomplete") can be handled
+/// and failures classified for retrying.
+pub fn agent() -> Agent {
+    Agent::config_builder()
+        .http_status_as_error(false)
+        .max_redirects(0)
+        .build()
+        .into()
+}
+
+/// Whether an HTTP status is worth retrying.
+pub fn is_transient_status(status: u16) -> bool {
+    status == 408 || status == 429 || status >= 500
+}
+
+/// Turns a request result into a [`Failure`] unless it succeeded with a 2xx
+/// status or one of `also_ok`.
+pub fn check(
+    result: Result<Response<Body>, ureq::Error>,
+    also_ok: &[u16],
+) -> Result<Response<Body>, Failure> {
+    let mut response = result.map_err(|e| Failure::Transient(e.into()))?;
+    let status = response.status().as_u16();
+    if response.status().is_success() || also_ok.contains(&status) {
+        return Ok(response);
+    }
+    let body = response.body_mut().read_to_string().unwrap_or_default();
+    let error = format!("HTTP {status}: {}", body.trim()).into();
+    if is_transient_status(status) {
+        Err(Failure::Transient(error))
+    } else {
+        Err(Failure::Fatal(error))
+    }
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters (and `/`,
+/// unless `encode_slash`), which is what SigV4 and Azure both sign.
+#[cfg(feature = "cloud")]
+pub fn percent_encode(s: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{b:02X}")),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "cloud")]
+    fn percent_encoding_keeps_unreserved_characters() {
+        assert_eq!(percent_encode("AZaz09-_.~", true), "AZaz09-_.~");
+        assert_eq!(percent_encode("a b+c=d", true), "a%20b%2Bc%3Dd");
+        assert_eq!(percent_encode("dir/file.csv", false), "dir/file.csv");
+        assert_eq!(percent_encode("dir/file.csv", true), "dir%2Ffile.csv");
+        assert_eq!(percent_encode("é", true), "%C3%A9");
+    }
+
+    #[test]
+    fn throttling_and_server_errors_are_transient() {
+        for status in [408, 429, 500, 503] {
+            assert!(is_transient_status(status), "{status}");
+        }
+        for status in [400, 403, 404, 409] {
+            assert!(!is_transient_status(status), "{status}");
+        }
+    }
+}
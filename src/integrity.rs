@@ -0,0 +1,167 @@
+//! Columns that let consumers check what they received: `--row-checksum`
+//! adds a checksum of each row's other fields, and `--hash-chain` a SHA-256
+//! of each row and the hash of the row before it, so that an edited row, or
+//! rows dropped, reordered or moved between files, show up.
+//!
+//! Both are computed over the row's own fields as bytes, each followed by an
+//! ASCII unit separator (0x1F), so they don't depend on how the output
+//! quotes or escapes them.
+
+use clap::ValueEnum;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::BoxError;
+use crate::cli::IntegrityArgs;
+use crate::generator::Row;
+use crate::schema::{Column, ColumnKind};
+use crate::sink::Sink;
+
+/// How `row_checksum` is computed.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Checksum {
+    /// CRC-32 as zlib and gzip compute it, as 8 hex digits.
+    Crc32,
+    /// SHA-256, as 64 hex digits.
+    Sha256,
+}
+
+pub const CHECKSUM_COLUMN: &str = "row_checksum";
+pub const CHAIN_COLUMN: &str = "chain_hash";
+
+/// The hash the first row of a chain is chained to.
+pub const CHAIN_START: [u8; 32] = [0; 32];
+
+const SEPARATOR: u8 = 0x1f;
+
+/// `columns` with the columns `args` adds after them.
+pub fn columns(args: &IntegrityArgs, columns: &[Column]) -> Vec<Column> {
+    let mut columns = columns.to_vec();
+    if args.row_checksum.is_some() {
+        columns.push(Column::new(CHECKSUM_COLUMN, ColumnKind::Sha256Hex));
+    }
+    if args.hash_chain {
+        columns.push(Column::new(CHAIN_COLUMN, ColumnKind::Sha256Hex));
+    }
+    columns
+}
+
+/// Adds the integrity columns to the rows passed on.
+pub struct IntegritySink {
+    inner: Box<dyn Sink>,
+    checksum: Option<Checksum>,
+    /// The hash of the last row written, with `--hash-chain`.
+    chain: Option<[u8; 32]>,
+    row: Row,
+}
+
+impl IntegritySink {
+    /// Wraps `inner`, unless `args` adds no columns.
+    pub fn wrap(inner: Box<dyn Sink>, args: &IntegrityArgs) -> Box<dyn Sink> {
+        if args.row_checksum.is_none() && !args.hash_chain {
+            return inner;
+        }
+        let chain = args.hash_chain.then_some(CHAIN_START);
+        Box::new(IntegritySink { inner, checksum: args.row_checksum, chain, row: Row::new() })
+    }
+}
+
+impl Sink for IntegritySink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.row.clear();
+        for field in row.fields() {
+            self.row.push(field);
+        }
+        match self.checksum {
+            Some(Checksum::Crc32) => {
+                let mut crc = flate2::Crc::new();
+                for field in row.fields() {
+                    crc.update(field);
+                    crc.update(&[SEPARATOR]);
+                }
+                self.row.push(format!("{:08x}", crc.sum()).as_bytes());
+            }
+            Some(Checksum::Sha256) => {
+                let mut hasher = Sha256::new();
+                for field in row.fields() {
+                    hasher.update(field);
+                    hasher.update([SEPARATOR]);
+                }
+                self.row.push(hex::encode(hasher.finalize()).as_bytes());
+            }
+            None => {}
+        }
+        if let Some(previous) = &mut self.chain {
+            *previous = chain(previous, row);
+            self.row.push(hex::encode(*previous).as_bytes());
+        }
+        self.inner.write_row(&self.row)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.inner.flush()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.inner.finish()
+    }
+}
+
+/// The chain hash of `row` after a row whose hash was `previous`: SHA-256
+/// of `previous` in hex and then the row's fields.
+pub fn chain(previous: &[u8; 32], row: &Row) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(hex::encode(previous));
+    for field in row.fields() {
+        hasher.update(field);
+        hasher.update([SEPARATOR]);
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use crate::output::{Destination, Outputs};
+    use crate::sink;
+    use clap::Parser;
+    use std::fs;
+
+    #[test]
+    fn rows_can_be_checked_against_their_hashes() {
+        let path = std::env::temp_dir().join(format!("csv_data_generator_integrity_{}.csv", std::process::id()));
+        let args = Args::parse_from(["gen", "--output", path.to_str().unwrap(), "--row-checksum", "crc32", "--hash-chain"]);
+        let columns = [Column::new("id", ColumnKind::Sequence { start: 1 }), Column::new("note", ColumnKind::Sha256Hex)];
+        let mut sink = sink::open(&Outputs::new(&args), &Destination::File(path.clone()), &columns).unwrap();
+        let mut row = Row::new();
+        for i in 0..100 {
+            row.clear();
+            row.push(i.to_string().as_bytes());
+            row.push(format!("a, \"quoted\"\nnote {i}").as_bytes());
+            sink.write_row(&row).unwrap();
+        }
+        sink.finish().unwrap();
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        assert_eq!(reader.headers().unwrap(), vec!["id", "note", CHECKSUM_COLUMN, CHAIN_COLUMN]);
+        let mut previous = CHAIN_START;
+        for record in reader.records() {
+            let record = record.unwrap();
+            let mut row = Row::new();
+            row.push(record[0].as_bytes());
+            row.push(record[1].as_bytes());
+            let mut crc = flate2::Crc::new();
+            crc.update(format!("{}\x1f{}\x1f", &record[0], &record[1]).as_bytes());
+            assert_eq!(&record[2], format!("{:08x}", crc.sum()));
+            previous = chain(&previous, &row);
+            assert_eq!(&record[3], hex::encode(previous));
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
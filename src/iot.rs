@@ -0,0 +1,298 @@
+//! The IoT preset: sensor `devices` and the `readings` they report over a
+//! day, in the shape time-series databases receive them.
+//!
+//! Each device reports every `--iot-interval` seconds, at its own offset
+//! into the interval. Its temperature, humidity and pressure are random
+//! walks, each drifting its own way, and its battery drains at its own rate
+//! until the device dies. Now and then a device goes silent for a while,
+//! losing the readings it would have taken, and a sensor gets stuck and
+//! repeats its last value. Readings reach the server a moment after they
+//! are taken, except that a device that loses its connection holds on to
+//! them until it's back, so `readings` is written in `received_at` order and
+//! its `recorded_at` times are sometimes out of order.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use rand::Rng;
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::clickstream::exponential;
+use crate::generator::{Row, push_decimal, push_timestamp};
+use crate::geo::{self, Shape};
+use crate::presets::{self, push_int};
+use crate::schema::{Column, ColumnKind};
+use crate::signal;
+use crate::sink::Sink;
+
+/// Readings are taken during 2024-01-01 UTC.
+const WINDOW_START: i64 = 1_704_067_200;
+const WINDOW: i64 = 86_400;
+
+/// Devices at scale factor 1.
+const DEVICES: f64 = 1_000.0;
+
+/// The chance of a device going silent at each reading, and the mean number
+/// of readings it loses when it does.
+const GAP_RATE: f64 = 0.002;
+const MEAN_GAP: f64 = 20.0;
+/// The chance of a sensor getting stuck at each reading, and the mean number
+/// of readings it repeats its value for.
+const STUCK_RATE: f64 = 0.0005;
+const MEAN_STUCK: f64 = 30.0;
+/// The chance of a device losing its connection at each reading, and the
+/// mean seconds until it's back.
+const OUTAGE_RATE: f64 = 0.002;
+const MEAN_OUTAGE: f64 = 900.0;
+/// The mean seconds between taking a reading and the server receiving it.
+const MEAN_DELAY: f64 = 2.0;
+
+const TABLES: [&str; 2] = ["devices", "readings"];
+
+const MODELS: &[&str] = &["th-100", "th-200", "env-pro", "env-lite"];
+const FIRMWARE: &[&str] = &["1.4.2", "1.5.0", "1.5.1", "2.0.0-rc1"];
+
+/// A metric's walk: where it starts, how far it drifts and steps per
+/// reading at most, and the range the sensor reads.
+struct Metric {
+    start: (f64, f64),
+    drift: f64,
+    step: f64,
+    range: (f64, f64),
+}
+
+/// Temperature in °C, relative humidity in percent and pressure in hPa.
+const METRICS: [Metric; 3] = [
+    Metric { start: (15.0, 30.0), drift: 0.002, step: 0.05, range: (-40.0, 85.0) },
+    Metric { start: (30.0, 70.0), drift: 0.005, step: 0.2, range: (0.0, 100.0) },
+    Metric { start: (990.0, 1030.0), drift: 0.001, step: 0.05, range: (950.0, 1050.0) },
+];
+
+/// Most batteries are fresh; some are nearly flat and die during the day.
+const BATTERY: (f64, f64) = (2.0, 100.0);
+/// Battery percent drained per hour.
+const DRAIN: (f64, f64) = (0.05, 0.5);
+
+/// The columns of each table, in [`TABLES`] order. Only the names and types
+/// of these matter; the values are written below.
+fn columns() -> [Vec<Column>; 2] {
+    let integer = |name| Column::new(name, ColumnKind::Sequence { start: 1 });
+    let string = |name| Column::new(name, ColumnKind::Choice(Vec::new()));
+    let timestamp = |name| Column::new(name, ColumnKind::TimestampRange { min: 0, max: 0 });
+    let decimal = |name, scale| Column::new(name, ColumnKind::Decimal { min: 0, max: 0, scale });
+    [
+        vec![
+            integer("device_id"),
+            string("model"),
+            string("firmware"),
+            decimal("latitude", 6),
+            decimal("longitude", 6),
+            timestamp("installed_at"),
+        ],
+        vec![
+            integer("device_id"),
+            timestamp("recorded_at"),
+            timestamp("received_at"),
+            decimal("temperature", 2),
+            decimal("humidity", 2),
+            decimal("pressure", 2),
+            decimal("battery", 2),
+        ],
+    ]
+}
+
+struct Device {
+    /// Seconds into each interval the device reports at.
+    offset: i64,
+    values: [f64; 3],
+    drifts: [f64; 3],
+    /// The value each stuck sensor repeats and the reading it comes unstuck at.
+    stuck: [Option<(f64, u64)>; 3],
+    battery: f64,
+    /// Battery percent drained per reading.
+    drain: f64,
+    /// The reading the device reports again at, after going silent.
+    silent_until: u64,
+    /// When the device's connection is back, after losing it.
+    offline_until: i64,
+}
+
+impl Device {
+    fn random<R: Rng + ?Sized>(rng: &mut R, interval: i64) -> Self {
+        let values = METRICS.map(|m| rng.gen_range(m.start.0..=m.start.1));
+        let drifts = METRICS.map(|m| rng.gen_range(-m.drift..=m.drift));
+        Device {
+            offset: rng.gen_range(0..interval),
+            values,
+            drifts,
+            stuck: [None; 3],
+            battery: rng.gen_range(BATTERY.0..=BATTERY.1),
+            drain: rng.gen_range(DRAIN.0..=DRAIN.1) * interval as f64 / 3600.0,
+            silent_until: 0,
+            offline_until: i64::MIN,
+        }
+    }
+
+    /// Moves the device on to its `n`th reading, and returns what it reads,
+    /// or none if it's silent or dead.
+    fn read<R: Rng + ?Sized>(&mut self, rng: &mut R, n: u64) -> Option<[f64; 4]> {
+        self.battery -= self.drain;
+        for (i, metric) in METRICS.iter().enumerate() {
+            self.values[i] = (self.values[i] + self.drifts[i] + rng.gen_range(-metric.step..=metric.step))
+                .clamp(metric.range.0, metric.range.1);
+        }
+        if self.battery <= 0.0 || n < self.silent_until {
+            return None;
+        }
+        if rng.gen_bool(GAP_RATE) {
+            self.silent_until = n + 1 + exponential(rng, MEAN_GAP) as u64;
+            return None;
+        }
+        let mut reading = [0.0; 4];
+        for ((stuck, value), read) in self.stuck.iter_mut().zip(self.values).zip(&mut reading) {
+            if stuck.is_some_and(|(_, until)| n >= until) {
+                *stuck = None;
+            }
+            if stuck.is_none() && rng.gen_bool(STUCK_RATE) {
+                *stuck = Some((value, n + 1 + exponential(rng, MEAN_STUCK) as u64));
+            }
+            *read = stuck.map_or(value, |(stuck, _)| stuck);
+        }
+        reading[3] = self.battery;
+        Some(reading)
+    }
+}
+
+/// Hundredths, so that readings order without floats.
+fn hundredths(value: f64) -> i64 {
+    (value * 100.0).round() as i64
+}
+
+/// A reading waiting to be received: `(received_at, device, recorded_at, values)`.
+type Pending = Reverse<(i64, u64, i64, [i64; 4])>;
+
+/// Writes every device, then walks the readings window one interval at a
+/// time, writing each reading once it has been received.
+pub fn generate(args: &Args) -> Result<(), BoxError> {
+    let [mut devices_sink, mut readings_sink] = presets::open_tables(args, "IoT", TABLES, columns())?;
+
+    let mut rng = rand::thread_rng();
+    let interval = args.preset.iot_interval as i64;
+    let count = ((DEVICES * args.preset.scale_factor).round() as u64).max(1);
+    let area = geo::country("us").expect("a known country");
+    let mut row = Row::new();
+    let mut devices = Vec::new();
+    for id in 1..=count {
+        row.clear();
+        push_int(&mut row, id);
+        row.push(MODELS[rng.gen_range(0..MODELS.len())].as_bytes());
+        row.push(FIRMWARE[rng.gen_range(0..FIRMWARE.len())].as_bytes());
+        let point = geo::sample(&mut rng, area);
+        for shape in [Shape::Latitude, Shape::Longitude] {
+            row.push_with(|field| geo::push(&mut rng, shape, point, field));
+        }
+        push_timestamp(&mut row, WINDOW_START - rng.gen_range(86_400..3 * 365 * 86_400));
+        devices_sink.write_row(&row)?;
+        devices.push(Device::random(&mut rng, interval));
+    }
+
+    // Readings are taken interval by interval and wait here until received,
+    // which is never before they are taken, so every reading received before
+    // the next interval starts can be written once this one is done.
+    let mut pending: BinaryHeap<Pending> = BinaryHeap::new();
+    let mut latest = vec![i64::MIN; devices.len()];
+    let (mut readings, mut out_of_order) = (0u64, 0u64);
+    let mut write = |sink: &mut Box<dyn Sink>, Reverse((received, id, recorded, values)): Pending| {
+        let latest = &mut latest[id as usize - 1];
+        out_of_order += u64::from(recorded < *latest);
+        *latest = recorded.max(*latest);
+        readings += 1;
+        row.clear();
+        push_int(&mut row, id);
+        push_timestamp(&mut row, recorded);
+        push_timestamp(&mut row, received);
+        for value in values {
+            push_decimal(&mut row, value, 2);
+        }
+        sink.write_row(&row)
+    };
+    for n in 0..(WINDOW / interval) as u64 {
+        if signal::interrupted() {
+            break;
+        }
+        let start = WINDOW_START + n as i64 * interval;
+        for (id, device) in (1..).zip(&mut devices) {
+            let Some(values) = device.read(&mut rng, n) else { continue };
+            let recorded = start + device.offset;
+            if recorded >= device.offline_until && rng.gen_bool(OUTAGE_RATE) {
+                device.offline_until = recorded + 1 + exponential(&mut rng, MEAN_OUTAGE) as i64;
+            }
+            let received = recorded.max(device.offline_until) + exponential(&mut rng, MEAN_DELAY).round() as i64;
+            pending.push(Reverse((received, id, recorded, values.map(hundredths))));
+        }
+        while pending.peek().is_some_and(|Reverse((received, ..))| *received < start + interval) {
+            write(&mut readings_sink, pending.pop().expect("peeked"))?;
+        }
+    }
+    while let Some(reading) = pending.pop() {
+        write(&mut readings_sink, reading)?;
+    }
+
+    presets::finish_tables(args, TABLES, [devices_sink, readings_sink], [count, readings])?;
+    println!("  {out_of_order} of {readings} readings arrived after a later one");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::testing::{read, temp_dir};
+    use crate::schema::parse_timestamp;
+    use clap::Parser;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn readings_arrive_in_received_order() {
+        let dir = temp_dir("iot");
+        let template = dir.join("{table}.csv");
+        let args = Args::parse_from(["gen", "--preset", "iot", "--scale-factor", "0.02", "--output", template.to_str().unwrap()]);
+        generate(&args).unwrap();
+
+        assert_eq!(read(dir.join("devices.csv")).len(), 20);
+        let readings = read(dir.join("readings.csv"));
+        let time = |s: &str| parse_timestamp(s).unwrap();
+        let mut by_device: HashMap<&str, Vec<&Vec<String>>> = HashMap::new();
+        for pair in readings.windows(2) {
+            assert!(time(&pair[0][2]) <= time(&pair[1][2]));
+        }
+        for r in &readings {
+            assert!(time(&r[1]) <= time(&r[2]));
+            by_device.entry(&r[0]).or_default().push(r);
+        }
+        let (mut late, mut jumps, mut stuck) = (0, 0, 0);
+        for device in by_device.values_mut() {
+            late += device.windows(2).filter(|w| time(&w[1][1]) < time(&w[0][1])).count();
+            device.sort_by_key(|r| time(&r[1]));
+            // Every reading is at the device's offset, and the battery only drains.
+            let offset = time(&device[0][1]) % 60;
+            assert!(device.iter().all(|r| time(&r[1]) % 60 == offset));
+            for w in device.windows(2) {
+                assert!(w[1][6].parse::<f64>().unwrap() <= w[0][6].parse::<f64>().unwrap());
+                // Temperatures step at most 0.05 a reading, but for catching up after being stuck.
+                let step = (w[1][3].parse::<f64>().unwrap() - w[0][3].parse::<f64>().unwrap()).abs();
+                let readings_apart = (time(&w[1][1]) - time(&w[0][1])) / 60;
+                jumps += usize::from(step > 0.06 * readings_apart as f64 + 0.01);
+            }
+            // The same humidity five times running is a stuck sensor, not chance.
+            stuck += device.windows(5).filter(|w| w.iter().all(|r| r[4] == w[0][4])).count();
+        }
+        assert!(jumps * 100 < readings.len(), "{jumps}");
+        assert!(stuck > 0);
+        assert!(late > 0);
+        // Fewer than 1,440 a day each, since there are gaps.
+        assert!(readings.len() < 20 * 1440);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
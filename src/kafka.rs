@@ -0,0 +1,165 @@
+//! Kafka producer sink.
+
+use std::time::Duration;
+
+use kafka::client::{Compression, ProduceMessage, RequiredAcks};
+use kafka::producer::{Partitioner, Producer, Record, Topics};
+use serde_json::{Value, json};
+
+use crate::BoxError;
+use crate::cli::{KafkaAcks, KafkaArgs, KafkaCompression, KafkaPartitioner};
+use crate::generator::Row;
+use crate::http;
+use crate::record::{RecordEncoder, RecordFormat, avro_schema};
+use crate::retry::{Failure, dead_letter, payload, retry};
+use crate::schema::Column;
+use crate::shard_by::java_partition;
+use crate::sink::{Sink, column_indices};
+
+/// Produces one message per row to a Kafka topic.
+///
+/// Messages are sent synchronously in batches of `--kafka-batch-messages`; a
+/// batch that is retried after a partial failure may be delivered twice.
+pub struct KafkaSink {
+    producer: Producer<RowPartitioner>,
+    topic: String,
+    key: Option<usize>,
+    encoder: RecordEncoder,
+    batch: Vec<(Vec<u8>, Vec<u8>)>,
+    batch_messages: usize,
+    bytes: u64,
+}
+
+impl KafkaSink {
+    pub fn open(args: &KafkaArgs, brokers: &[String], topic: &str, columns: &[Column]) -> Result<Self, BoxError> {
+        let key = match &args.kafka_key {
+            Some(name) => Some(column_indices(columns, std::slice::from_ref(name), "--kafka-key")?[0]),
+            None => None,
+        };
+        let schema_id = match (&args.kafka_schema_registry, args.kafka_format) {
+            (Some(url), RecordFormat::Avro) => Some(register_schema(url, &format!("{topic}-value"), &avro_schema(columns))?),
+            _ => None,
+        };
+        let producer = Producer::from_hosts(brokers.to_vec())
+            .with_ack_timeout(Duration::from_secs(30))
+            .with_required_acks(match args.kafka_acks {
+                KafkaAcks::None => RequiredAcks::None,
+                KafkaAcks::One => RequiredAcks::One,
+                KafkaAcks::All => RequiredAcks::All,
+            })
+            .with_compression(match args.kafka_compression {
+                KafkaCompression::None => Compression::NONE,
+                KafkaCompression::Gzip => Compression::GZIP,
+                KafkaCompression::Snappy => Compression::SNAPPY,
+            })
+            .with_client_id("csv_data_generator".into())
+            .with_partitioner(RowPartitioner { strategy: args.kafka_partitioner, next: 0 })
+            .create()
+            .map_err(|e| format!("connecting to Kafka at {}: {e}", brokers.join(",")))?;
+        Ok(KafkaSink {
+            producer,
+            topic: topic.to_string(),
+            key,
+            encoder: RecordEncoder::new(args.kafka_format, columns, schema_id),
+            batch: Vec::new(),
+            batch_messages: args.kafka_batch_messages as usize,
+            bytes: 0,
+        })
+    }
+
+    fn send_batch(&mut self) -> Result<(), BoxError> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let batch = std::mem::take(&mut self.batch);
+        let records: Vec<_> =
+            batch.iter().map(|(key, value)| Record::from_key_value(&self.topic, key.as_slice(), value.as_slice())).collect();
+        let what = format!("producing to Kafka topic {}", self.topic);
+        let produced = retry(&what, || {
+            let confirms = self.producer.send_all(&records).map_err(|e| Failure::Transient(e.into()))?;
+            for confirm in confirms {
+                for partition in confirm.partition_confirms {
+                    if let Err(code) = partition.offset {
+                        return Err(Failure::Transient(
+                            format!("partition {} rejected the batch: {code:?}", partition.partition).into(),
+                        ));
+                    }
+                }
+            }
+            Ok(())
+        });
+        let records = || batch.iter().map(|(key, value)| json!({"key": payload(key), "value": payload(value)}));
+        produced.or_else(|e| dead_letter(&format!("kafka://{}", self.topic), e, records()))
+    }
+}
+
+impl Sink for KafkaSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        let value = self.encoder.encode(row)?;
+        // An empty key is sent as no key at all.
+        let key = self.key.and_then(|i| row.fields().nth(i)).unwrap_or_default().to_vec();
+        self.bytes += value.len() as u64;
+        self.batch.push((key, value));
+        if self.batch.len() >= self.batch_messages {
+            self.send_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.send_batch()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.send_batch()
+    }
+}
+
+/// Registers `schema` under `subject` and returns its id; registering a
+/// schema the subject already has returns the existing id.
+fn register_schema(registry: &str, subject: &str, schema: &Value) -> Result<u32, BoxError> {
+    let agent = http::agent();
+    let url = format!("{}/subjects/{subject}/versions", registry.trim_end_matches('/'));
+    let body = json!({ "schema": schema.to_string() });
+    let response: Value = retry("registering the Avro schema", || {
+        http::check(
+            agent.post(&url).header("Content-Type", "application/vnd.schemaregistry.v1+json").send_json(&body),
+            &[],
+        )?
+        .body_mut()
+        .read_json()
+        .map_err(|e| Failure::Transient(e.into()))
+    })?;
+    let id = response["id"].as_u64().ok_or_else(|| format!("schema registry returned no id: {response}"))?;
+    Ok(id as u32)
+}
+
+/// Assigns partitions the way `--kafka-partitioner` asks.
+struct RowPartitioner {
+    strategy: KafkaPartitioner,
+    next: u32,
+}
+
+impl Partitioner for RowPartitioner {
+    fn partition(&mut self, topics: Topics<'_>, message: &mut ProduceMessage<'_, '_>) {
+        let Some(partitions) = topics.partitions(message.topic) else {
+            return;
+        };
+        match (self.strategy, message.key) {
+            (KafkaPartitioner::Murmur2, Some(key)) if partitions.num_all() > 0 => {
+                message.partition = java_partition(key, partitions.num_all()) as i32;
+            }
+            _ => {
+                let available = partitions.available_ids();
+                if !available.is_empty() {
+                    message.partition = available[self.next as usize % available.len()];
+                    self.next = self.next.wrapping_add(1);
+                }
+            }
+        }
+    }
+}
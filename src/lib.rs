@@ -0,0 +1,597 @@
+//! Generates large CSV, Parquet and JSON datasets of synthetic rows, to
+//! files, cloud storage, databases and streams. The binary is a thin wrapper
+//! around [`run`]; other programs can generate rows themselves, for example
+//! in integration tests, from the same pieces:
+//!
+//! ```
+//! use csv_data_generator::{Generator, Locale, Row, schema};
+//!
+//! let columns = schema::builtin("orders", Locale::En).unwrap();
+//! let generator = Generator::compile(&columns).unwrap();
+//! let mut row = Row::new();
+//! generator.fill_row(&mut rand::thread_rng(), &mut row);
+//! assert_eq!(row.fields().count(), columns.len());
+//! ```
+//!
+//! [`sink::open`] writes rows to any destination the binary can, with the
+//! settings of an [`Args`](cli::Args), and [`Sink`] is what it returns.
+
+mod access_log;
+mod archive;
+mod ads;
+#[cfg(feature = "cloud")]
+mod aws;
+mod backfill;
+#[cfg(feature = "cloud")]
+mod azure;
+pub mod builder;
+mod business;
+mod cdc;
+mod cdr;
+mod claims;
+mod clickhouse;
+mod clickstream;
+mod contract;
+mod corrupt;
+pub mod cli;
+mod convert;
+#[cfg(any(feature = "sqlite", feature = "duckdb"))]
+mod db;
+mod dbt;
+mod ddl;
+mod diff;
+mod drift;
+mod effective_config;
+mod entities;
+mod estimate;
+mod ffi;
+mod fill;
+mod gcp;
+mod geo;
+pub mod generator;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod hr;
+#[cfg(any(feature = "cloud", feature = "clickhouse", feature = "kafka", feature = "otlp"))]
+mod http;
+mod integrity;
+mod iot;
+#[cfg(feature = "kafka")]
+mod kafka;
+pub mod locale;
+mod manifest;
+mod metrics;
+mod mismatch;
+mod ml;
+mod mysql;
+mod national_id;
+mod noise;
+pub mod output;
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "cloud")]
+mod parts;
+mod pg;
+mod plugin;
+mod pool;
+mod presets;
+mod preview;
+mod progress;
+pub mod record;
+pub mod relational;
+mod repeat;
+mod retry;
+mod rotate;
+mod run_manifest;
+pub mod schema;
+#[cfg(feature = "script")]
+mod script;
+#[cfg(feature = "serve")]
+mod serve;
+mod shard_by;
+mod shard_sizes;
+mod signal;
+pub mod sink;
+#[cfg(feature = "cloud")]
+mod snowflake;
+mod sort;
+mod stats;
+mod taxi;
+mod telemetry;
+mod tcp;
+mod throttle;
+mod torture;
+mod transactions;
+mod trend;
+mod unicode;
+mod validate;
+mod width;
+
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use cli::{Args, Command, FileFormat};
+use output::{Destination, Outputs};
+use shard_by::ShardBySink;
+use throttle::RateLimiter;
+
+pub use builder::{Dataset, GeneratorBuilder, IdKind};
+pub use generator::{Generator, Row, RowHook, RowKeys, Rows, Value};
+pub use record::{Encode, RecordEncoder, RecordFormat};
+pub use locale::Locale;
+pub use schema::{Column, ColumnKind, DataType};
+pub use sink::Sink;
+
+/// The error type used throughout; `Send + Sync` so shards can fail on any thread.
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+/// The error for using `what` in a build without the Cargo feature it's behind.
+fn without(feature: &str, what: &str) -> BoxError {
+    format!("this build has no {what} support; rebuild with `--features {feature}`").into()
+}
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Set when the data itself goes to stdout, so progress messages don't mix into it.
+static DATA_ON_STDOUT: AtomicBool = AtomicBool::new(false);
+
+/// `println!` for progress messages, which move to stderr while stdout carries data.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if DATA_ON_STDOUT.load(Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// `--size` for file destinations when none is given.
+const DEFAULT_SIZE: u64 = 10 << 30;
+
+/// What one shard ended up writing.
+struct ShardReport {
+    location: String,
+    rows: u64,
+    bytes: u64,
+}
+
+/// Returned by a shard that stopped because another shard failed.
+#[derive(Debug)]
+struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("cancelled because another shard failed")
+    }
+}
+
+impl Error for Cancelled {}
+
+/// Whether `e`, or an error it wraps, is a write to a pipe whose reader has
+/// gone away, which for piped output just means the consumer has had enough.
+fn is_broken_pipe(e: &BoxError) -> bool {
+    let mut current: Option<&(dyn Error + 'static)> = Some(e.as_ref());
+    while let Some(e) = current {
+        // csv::Error wraps I/O errors without exposing them as its source.
+        let io = match e.downcast_ref::<csv::Error>().map(csv::Error::kind) {
+            Some(csv::ErrorKind::Io(io)) => Some(io),
+            _ => e.downcast_ref::<std::io::Error>(),
+        };
+        if io.is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe) {
+            return true;
+        }
+        current = e.source();
+    }
+    false
+}
+
+/// The random numbers of shard `shard`, from `--seed` if it's given.
+pub(crate) fn shard_rng(seed: Option<u64>, shard: u64) -> StdRng {
+    seed.map_or_else(StdRng::from_entropy, |seed| StdRng::seed_from_u64(seed.wrapping_add(shard)))
+}
+
+/// Generates one shard of roughly `target_size_bytes` into `sink`.
+///
+/// # Arguments
+///
+/// * `location` - Where the shard is written, used for progress messages.
+/// * `sink` - The destination rows are written to.
+/// * `target_size_bytes` - Generation stops once this many bytes were written.
+/// * `row_limit` - Or once this many rows were, whichever comes first.
+/// * `rows` - The rows of the shard's columns.
+/// * `limiter` - Paces rows for `--rate`; shared by all shards.
+/// * `cancelled` - Set when another shard failed; checked between batches.
+fn generate_shard(
+    location: &str,
+    mut sink: Box<dyn Sink>,
+    target_size_bytes: u64,
+    row_limit: u64,
+    mut rows: Rows<StdRng>,
+    limiter: Option<&RateLimiter>,
+    cancelled: &AtomicBool,
+) -> Result<ShardReport, BoxError> {
+    let mut row = Row::new();
+    let mut row_count: u64 = 0;
+
+    // Write in large batches to minimize I/O overhead. When rate limited, a
+    // batch is ten seconds' worth of rows so progress is still reported.
+    const BATCH_SIZE: u64 = 500_000;
+    let batch_size = limiter.map_or(BATCH_SIZE, |l| l.burst() * 1000);
+
+    // Loop until the output size reaches the target.
+    while sink.bytes_written() < target_size_bytes && row_count < row_limit && !signal::interrupted() {
+        // Dropping the sink unfinished discards the partial shard.
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(Cancelled.into());
+        }
+
+        for _ in 0..batch_size {
+            if let Some(limiter) = limiter
+                && row_count.is_multiple_of(limiter.burst())
+            {
+                limiter.acquire(limiter.burst());
+            }
+            // The row buffer is reused, so filling it does not allocate.
+            rows.fill_next(&mut row);
+            sink.write_row(&row)?;
+            row_count += 1;
+            if sink.bytes_written() >= target_size_bytes || row_count >= row_limit || signal::interrupted() {
+                break;
+            }
+        }
+
+        // Flush the buffer to get an accurate size.
+        sink.flush()?;
+
+        // Provide periodic progress updates.
+        let current_size_gb = sink.bytes_written() as f64 / GB;
+        status!("{}: generated {} rows. Current size: {:.2}GB", location, row_count, current_size_gb);
+    }
+
+    let bytes = sink.bytes_written();
+    sink.finish()?;
+
+    Ok(ShardReport { location: location.to_string(), rows: row_count, bytes })
+}
+
+/// Generates every shard described by `args`, running up to `--jobs` at once,
+/// then runs any post-generation steps such as a BigQuery load. `hook` sees
+/// every row before it's written.
+fn generate(args: &Args, columns: &[Column], hook: Option<&RowHook>) -> Result<(), BoxError> {
+    let destination = Destination::parse(&args.output)?;
+    // Record destinations have no natural size, so ask for one explicitly.
+    let size = match args.size {
+        Some(size) => size,
+        None if args.rows.is_some() => u64::MAX,
+        None if destination.is_file() => DEFAULT_SIZE,
+        None => return Err(format!("--size or --rows is required when writing to {}", args.output).into()),
+    };
+    if destination == Destination::Stdout {
+        if args.shards > 1 {
+            return Err("--output - is a single stream, so it takes only one shard".into());
+        }
+        DATA_ON_STDOUT.store(true, Ordering::Relaxed);
+    }
+    // Shards of a table all append to it, so only files need `{n}`.
+    if args.shards > 1 && destination.is_file() && !output::has_shard(&args.output) {
+        return Err(format!("--output must contain {{n}} when generating {} shards", args.shards).into());
+    }
+    // What loaders and DDL describe: the columns as written, integrity ones included.
+    let written = integrity::columns(&args.integrity, columns);
+    let loaded = args.bigquery.bq_load.is_some() || args.snowflake.sf_copy_into.is_some();
+    if loaded && args.format == FileFormat::Csv && args.headers.leading_lines().is_none() {
+        return Err("--header first gives only shard 0 a header, so a load can't skip the same lines of every file; use --header every or none".into());
+    }
+    if args.bigquery.bq_load.is_some() {
+        if !matches!(destination, Destination::Gcs { .. }) {
+            return Err("--bq-load needs a gs:// output to load from".into());
+        }
+        gcp::bigquery::validate(&args.bigquery, args.format, &written)?;
+    }
+    #[cfg(not(feature = "cloud"))]
+    if args.bigquery.bq_load.is_some() || args.snowflake.sf_copy_into.is_some() {
+        return Err(without("cloud", "cloud"));
+    }
+    // Connect up front so missing credentials fail before generation, not after.
+    #[cfg(feature = "cloud")]
+    let snowflake = match args.snowflake.sf_copy_into {
+        Some(_) => {
+            snowflake::validate(&destination)?;
+            Some(snowflake::SnowflakeClient::from_env()?)
+        }
+        None => None,
+    };
+
+    // Compile the columns once so the hot loop only indexes precomputed
+    // tables, and once more for each shard `--drift` changes them from.
+    let versions = drift::versions(columns, &args.drift, args.shards)?
+        .into_iter()
+        .map(|(first, columns)| Generator::compile(&columns).map(|generator| (first, columns, generator)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if versions.len() > 1 {
+        for (first, columns, _) in &versions {
+            let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+            status!("Shards from {first} have columns {}", names.join(", "));
+        }
+    }
+    // With only `--rows` the size isn't a target, so no shard has one.
+    let shard_sizes = match size {
+        u64::MAX => vec![u64::MAX; args.shards as usize],
+        size => args.shard_sizes.split(size, args.shards),
+    };
+    // With only `--rows` the size isn't known up front, so uploads can't be
+    // checked. Otherwise the largest shard has to fit.
+    #[cfg(feature = "cloud")]
+    let largest = shard_sizes.iter().copied().max().unwrap_or(0);
+    match destination {
+        _ if size == u64::MAX => {}
+        #[cfg(feature = "cloud")]
+        Destination::S3 { .. } => aws::s3::check_shard_size(&args.s3, largest)?,
+        #[cfg(feature = "cloud")]
+        Destination::Azure { .. } => azure::check_shard_size(&args.azure, largest)?,
+        _ => {}
+    }
+    // `--shard-by` writes every shard from one stream of rows, so that with
+    // `--seed` each file gets the same rows in the same order.
+    let (streams, stream_sizes) = if args.shard_by.is_some() { (1, vec![size]) } else { (args.shards, shard_sizes) };
+    let stream_rows = args.rows.map(|rows| args.shard_sizes.split(rows, streams));
+    let routed = Arc::new(Mutex::new(Vec::new()));
+    let jobs = args
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+        .clamp(1, streams as usize);
+    let limiter = args.rate.map(RateLimiter::new);
+
+    let outputs = Outputs::new(args);
+    if !args.ddl.ddl.is_empty() {
+        let table = args.ddl.ddl_table.as_deref().unwrap_or(&args.schema);
+        for location in ddl::write(&outputs, &args.ddl.ddl, table, &written)? {
+            status!("Wrote {}", location);
+        }
+    }
+
+    match (args.rows, args.size) {
+        (Some(rows), None) => status!("Starting to generate {} rows in {} shard(s) at {}...", rows, args.shards, args.output),
+        _ => status!("Starting to generate {:.2}GB in {} shard(s) at {}...", size as f64 / GB, args.shards, args.output),
+    }
+    status!("This process will take a significant amount of time and disk space.");
+
+    let next_shard = AtomicU64::new(0);
+    let reports = Mutex::new(Vec::new());
+    // The first failure stops every other shard instead of letting the rest
+    // of the run finish before it is reported.
+    let cancelled = AtomicBool::new(false);
+    metrics::SHARDS_QUEUED.store(streams, Ordering::Relaxed);
+    progress::expect((size != u64::MAX).then_some(size), args.rows);
+
+    let results: Vec<Result<(), BoxError>> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| -> Result<(), BoxError> {
+                    loop {
+                        let shard = next_shard.fetch_add(1, Ordering::Relaxed);
+                        if shard >= streams || cancelled.load(Ordering::Relaxed) || signal::interrupted() {
+                            return Ok(());
+                        }
+                        metrics::SHARDS_QUEUED.fetch_sub(1, Ordering::Relaxed);
+                        metrics::SHARDS_RUNNING.fetch_add(1, Ordering::Relaxed);
+                        let location = match args.shard_by {
+                            Some(_) => args.output.clone(),
+                            None => output::shard_location(&args.output, shard as usize),
+                        };
+                        let mut span = telemetry::span("shard");
+                        span.set("shard", shard);
+                        span.set("location", location.as_str());
+                        let (_, columns, generator) =
+                            versions.iter().rev().find(|(first, ..)| *first <= shard).expect("shard 0 has a version");
+                        let sink = match &args.shard_by {
+                            Some(key) => ShardBySink::open(&outputs, columns, key, routed.clone())
+                                .map(|sink| Box::new(sink) as Box<dyn Sink>),
+                            None => Destination::parse(&location)
+                                .and_then(|destination| sink::open_shard(&outputs, &destination, columns, shard)),
+                        };
+                        let result = sink.and_then(|sink| {
+                            let row_limit = stream_rows.as_ref().map_or(u64::MAX, |rows| rows[shard as usize]);
+                            let rows = generator.rows(shard_rng(args.seed, shard)).hooked(hook);
+                            let size = stream_sizes[shard as usize];
+                            generate_shard(&location, sink, size, row_limit, rows, limiter.as_ref(), &cancelled)
+                        });
+                        metrics::SHARDS_RUNNING.fetch_sub(1, Ordering::Relaxed);
+                        match result {
+                            Ok(report) => {
+                                span.set("rows", report.rows);
+                                span.set("bytes", report.bytes);
+                                reports.lock().unwrap().push(report);
+                            }
+                            Err(e) => {
+                                span.fail(&e);
+                                if !e.is::<Cancelled>() {
+                                    metrics::ERRORS.fetch_add(1, Ordering::Relaxed);
+                                }
+                                cancelled.store(true, Ordering::Relaxed);
+                                return Err(e);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().expect("shard worker panicked")).collect()
+    });
+    // Shards a failure cancelled never leave the queue.
+    metrics::SHARDS_QUEUED.store(0, Ordering::Relaxed);
+    let mut errors: Vec<BoxError> = results.into_iter().filter_map(Result::err).collect();
+    if let Some(i) = errors.iter().position(|e| !e.is::<Cancelled>()) {
+        let error = errors.swap_remove(i);
+        if matches!(destination, Destination::File(_) | Destination::Stdout) && is_broken_pipe(&error) {
+            eprintln!("The reader closed the pipe; stopping.");
+            return Ok(());
+        }
+        return Err(error);
+    }
+
+    let mut reports = reports.into_inner().unwrap();
+    if args.shard_by.is_some() {
+        reports = std::mem::take(&mut *routed.lock().unwrap());
+    }
+    reports.sort_by(|a, b| a.location.cmp(&b.location));
+    let total_rows: u64 = reports.iter().map(|r| r.rows).sum();
+    let total_bytes: u64 = reports.iter().map(|r| r.bytes).sum();
+
+    status!("\n--------------------------------------------------");
+    for report in &reports {
+        status!("Successfully generated {} ({} rows)", report.location, report.rows);
+    }
+    status!("Total rows generated: {}", total_rows);
+    status!("Final size: {:.2}GB", total_bytes as f64 / GB);
+    status!("--------------------------------------------------");
+    // What was written is complete rows, but not the data asked for.
+    if signal::interrupted() {
+        return Ok(());
+    }
+
+    #[cfg(feature = "cloud")]
+    let leading_lines = args.headers.leading_lines().unwrap_or(0);
+    #[cfg(feature = "cloud")]
+    if args.bigquery.bq_load.is_some() {
+        let uris: Vec<_> = reports.iter().map(|r| r.location.clone()).collect();
+        let auth = outputs.gcp_auth()?;
+        gcp::bigquery::load(&auth, &args.bigquery, args.format, leading_lines, &written, &uris)?;
+    }
+    #[cfg(feature = "cloud")]
+    if let Some(client) = &snowflake {
+        let locations: Vec<_> = reports.iter().map(|r| r.location.clone()).collect();
+        let format = snowflake::file_format(args.csv_dialect, args.compression, leading_lines);
+        snowflake::copy_into(client, &args.snowflake, &format, &written, &locations)?;
+    }
+
+    Ok(())
+}
+
+/// The columns of `--schema`, as `--select` and `--order` arrange them.
+pub(crate) fn columns(args: &Args) -> Result<Vec<Column>, BoxError> {
+    let columns = schema::builtin(&args.schema, args.locale.clone()).expect("--schema is checked when parsed");
+    schema::arrange(columns, &args.select, &args.order)
+}
+
+/// Runs the command `args` describes, as the binary does: generates the data,
+/// or runs the subcommand.
+pub fn run(args: &Args) -> Result<(), BoxError> {
+    let args = &Args { output: output::run_location(&args.output, args.seed)?, ..args.clone() };
+    plugin::load(&args.plugin)?;
+    retry::configure(&args.retry);
+    if let Some(address) = &args.metrics {
+        metrics::serve(address)?;
+        status!("Serving metrics on http://{address}/metrics");
+    }
+    if let Some(endpoint) = &args.otlp_endpoint {
+        telemetry::start(endpoint)?;
+    }
+    if args.command.is_none() {
+        signal::install();
+        archive::check(args)?;
+        progress::start(args.control_socket.as_deref())?;
+        effective_config::write(args)?;
+    }
+    let result = match &args.command {
+        #[cfg(feature = "serve")]
+        Some(Command::Serve(serve)) => serve::run(serve),
+        #[cfg(not(feature = "serve"))]
+        Some(Command::Serve(_)) => Err(without("serve", "serve")),
+        #[cfg(feature = "grpc")]
+        Some(Command::Grpc(grpc)) => grpc::run(grpc),
+        #[cfg(not(feature = "grpc"))]
+        Some(Command::Grpc(_)) => Err(without("grpc", "gRPC")),
+        Some(Command::Validate(validate)) => validate::run(validate),
+        Some(Command::Diff(diff)) => diff::run(diff),
+        Some(Command::Convert(convert)) => convert::run(args, convert),
+        Some(Command::Sort(sort)) => sort::run(args, sort),
+        Some(Command::Estimate(estimate)) => estimate::run(args, estimate),
+        Some(Command::Preview(preview)) => preview::run(args, preview),
+        None if args.cdc.cdc_events.is_some() => columns(args).and_then(|columns| cdc::generate(args, &columns)),
+        None if args.cdc.deltas.is_some() => columns(args).and_then(|columns| cdc::generate_deltas(args, &columns)),
+        None if args.rotate.rotates() => columns(args).and_then(|columns| rotate::generate(args, &columns)),
+        None if args.backfill.backfill.is_some() => columns(args).and_then(|columns| backfill::generate(args, &columns)),
+        None => {
+            let columns = columns(args)?;
+            match (&args.config, args.preset.preset, &args.dbt.dbt_project) {
+                (Some(path), _, _) => {
+                    relational::Config::load(path).and_then(|config| relational::generate(args, &config))
+                }
+                (None, Some(presets::Preset::Clickstream), _) => clickstream::generate(args),
+                (None, Some(presets::Preset::EntityResolution), _) => entities::generate(args),
+                (None, Some(presets::Preset::Iot), _) => iot::generate(args),
+                (None, Some(presets::Preset::Transactions), _) => transactions::generate(args),
+                (None, Some(presets::Preset::Cdr), _) => cdr::generate(args),
+                (None, Some(presets::Preset::Ads), _) => ads::generate(args),
+                (None, Some(presets::Preset::Taxi), _) => taxi::generate(args),
+                (None, Some(presets::Preset::Hr), _) => hr::generate(args),
+                (None, Some(presets::Preset::Claims), _) => claims::generate(args),
+                (None, Some(presets::Preset::Ml), _) => ml::generate(args),
+                (None, Some(preset), _) => {
+                    let config = presets::config(preset, &args.preset).expect("the other presets are related tables");
+                    relational::generate(args, &config)
+                }
+                (None, None, Some(project)) => {
+                    dbt::seed_args(args, project, &columns).and_then(|args| generate(&args, &columns, None))
+                }
+                (None, None, None) => GeneratorBuilder::from_args(args.clone()).columns(columns).build().and_then(|d| d.write()),
+            }
+        }
+    };
+    telemetry::finish(&result);
+    if let Some(summary) = retry::summary() {
+        status!("{summary}");
+    }
+    result?;
+    run_manifest::write(args)?;
+    archive::write(args)?;
+    for (column, count) in mismatch::injected() {
+        status!("Put {count} mismatched values into {column}");
+    }
+    for filled in fill::summary() {
+        status!("{filled}");
+    }
+    // A signal is how a rotating run is meant to stop.
+    if signal::interrupted() && !args.rotate.rotates() {
+        return Err("interrupted; the outputs above hold the whole rows generated until then".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn broken_pipes_are_found_inside_csv_errors() {
+        let pipe = || io::Error::from(io::ErrorKind::BrokenPipe);
+        assert!(is_broken_pipe(&pipe().into()));
+        assert!(is_broken_pipe(&csv::Error::from(pipe()).into()));
+        assert!(!is_broken_pipe(&io::Error::from(io::ErrorKind::NotFound).into()));
+        assert!(!is_broken_pipe(&"broken pipe".into()));
+    }
+
+    #[test]
+    fn columns_can_be_selected_and_reordered() {
+        use clap::Parser;
+
+        let names = |flags: &[&str]| {
+            let args = Args::parse_from([&["gen", "--schema", "users"], flags].concat());
+            columns(&args).map(|columns| columns.into_iter().map(|c| c.name).collect::<Vec<_>>()).map_err(|e| e.to_string())
+        };
+        assert_eq!(names(&["--select", "age,id"]).unwrap(), ["id", "age"]);
+        assert_eq!(names(&["--select", "id,age", "--order", "age,id"]).unwrap(), ["age", "id"]);
+        assert_eq!(names(&["--order", "age"]).unwrap(), ["age", "id", "name"]);
+        assert_eq!(names(&["--select", "email"]).unwrap_err(), "--select: no column named email");
+        assert_eq!(names(&["--order", "age,age"]).unwrap_err(), "--order: age is named twice");
+    }
+}
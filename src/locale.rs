@@ -0,0 +1,466 @@
+//! Names, addresses and phone numbers as they look in each supported locale.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::schema::ColumnKind;
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Locale {
+    /// English, with US addresses and phone numbers.
+    #[default]
+    #[serde(rename = "en")]
+    En,
+    #[serde(rename = "de")]
+    De,
+    #[serde(rename = "fr")]
+    Fr,
+    #[serde(rename = "ja")]
+    Ja,
+    #[serde(rename = "zh")]
+    Zh,
+    /// Brazilian Portuguese.
+    #[value(name = "pt-BR")]
+    #[serde(rename = "pt-BR")]
+    PtBr,
+    #[serde(rename = "hi")]
+    Hi,
+    #[serde(rename = "ar")]
+    Ar,
+}
+
+/// The kinds of value a locale has data for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    FirstName,
+    LastName,
+    /// First and last name in the order and with the spacing the locale
+    /// writes them in.
+    FullName,
+    /// A street and house number.
+    StreetAddress,
+    City,
+    Postcode,
+    Phone,
+}
+
+/// One locale's lists. Formats are those of [`ColumnKind::Format`].
+struct Data {
+    first_names: &'static [&'static str],
+    last_names: &'static [&'static str],
+    /// Where `{0}` is a first name and `{1}` a last name.
+    full_name: &'static str,
+    cities: &'static [&'static str],
+    streets: &'static [&'static str],
+    /// Where `{0}` is a street.
+    addresses: &'static [&'static str],
+    postcodes: &'static [&'static str],
+    phones: &'static [&'static str],
+}
+
+const EN: Data = Data {
+    first_names: &[
+        "Liam", "Noah", "Jack", "Levi", "Owen", "John", "Leo", "Luke", "Ezra", "Luca",
+        "Alex", "Alan", "Ben", "Kyle", "Kurt", "Lou", "Matt", "Ryan", "Mia", "Elias",
+        "Mila", "Nova", "Axel", "Leon", "Amara", "Finn", "Molly", "Brian", "Dante",
+        "Rhys", "Thea", "Otis", "Rohan", "Anne", "Britt", "Brooks", "Cash", "Dane",
+        "Eve", "Gem", "Huck", "Ivy", "Lael", "Mack", "Maeve", "Nell", "Onyx", "Pace",
+        "Quinn", "Reed", "Scout", "Taft", "Ula", "Van", "Wade", "West"
+    ],
+    last_names: &[
+        "Smith", "Johnson", "Williams", "Brown", "Jones", "Garcia", "Miller", "Davis", "Wilson", "Taylor", "Clark",
+        "Lewis", "Walker", "Young", "Allen",
+    ],
+    full_name: "{0} {1}",
+    cities: &[
+        "New York", "Los Angeles", "Chicago", "Houston", "Phoenix", "Philadelphia", "San Antonio", "San Diego",
+        "Dallas", "Seattle",
+    ],
+    streets: &[
+        "Main Street", "Oak Avenue", "Maple Street", "Park Avenue", "Elm Street", "Washington Boulevard", "Lake Road",
+        "Cedar Lane",
+    ],
+    addresses: &["%## {0}", "%### {0}", "%## {0}, Apt %#"],
+    postcodes: &["#####"],
+    phones: &["(%##) %##-####", "%##-%##-####", "+1 %## %## ####"],
+};
+
+const DE: Data = Data {
+    first_names: &[
+        "Lukas", "Leon", "Finn", "Jonas", "Paul", "Felix", "Maximilian", "Emil", "Jürgen", "Uwe", "Anna", "Lena",
+        "Marie", "Sophie", "Emma", "Hannah", "Mia", "Lea", "Sabine", "Ursula",
+    ],
+    last_names: &[
+        "Müller", "Schmidt", "Schneider", "Fischer", "Weber", "Meyer", "Wagner", "Becker", "Schulz", "Hoffmann",
+        "Koch", "Richter", "Klein", "Wolf", "Schröder",
+    ],
+    full_name: "{0} {1}",
+    cities: &[
+        "Berlin", "Hamburg", "München", "Köln", "Frankfurt am Main", "Stuttgart", "Düsseldorf", "Leipzig",
+        "Dortmund", "Dresden",
+    ],
+    streets: &[
+        "Hauptstraße", "Schulstraße", "Gartenstraße", "Bahnhofstraße", "Dorfstraße", "Bergstraße", "Lindenstraße",
+        "Goethestraße", "Schillerstraße", "Waldweg",
+    ],
+    addresses: &["{0} %", "{0} %#", "{0} %#a"],
+    postcodes: &["%####"],
+    phones: &["+49 30 %#######", "+49 1%# %######", "0%## %######"],
+};
+
+const FR: Data = Data {
+    first_names: &[
+        "Gabriel", "Léo", "Raphaël", "Louis", "Arthur", "Jules", "Adam", "Lucas", "Hugo", "Jade", "Louise", "Emma",
+        "Alice", "Ambre", "Lina", "Chloé", "Léa", "Manon", "Camille",
+    ],
+    last_names: &[
+        "Martin", "Bernard", "Thomas", "Petit", "Robert", "Richard", "Durand", "Dubois", "Moreau", "Laurent", "Simon",
+        "Michel", "Lefebvre", "Leroy", "Roux",
+    ],
+    full_name: "{0} {1}",
+    cities: &[
+        "Paris", "Marseille", "Lyon", "Toulouse", "Nice", "Nantes", "Strasbourg", "Montpellier", "Bordeaux", "Lille",
+    ],
+    streets: &[
+        "rue de la Paix", "rue Victor Hugo", "avenue des Champs-Élysées", "boulevard Saint-Germain",
+        "rue de la République", "place de la Mairie", "rue du Moulin", "chemin des Vignes", "rue Pasteur",
+        "avenue Jean Jaurès",
+    ],
+    addresses: &["% {0}", "%# {0}", "%# bis {0}"],
+    postcodes: &["%####"],
+    phones: &["+33 6 ## ## ## ##", "+33 1 ## ## ## ##", "0% ## ## ## ##"],
+};
+
+const JA: Data = Data {
+    first_names: &[
+        "翔", "蓮", "大翔", "陽翔", "湊", "悠真", "健太", "直樹", "結衣", "陽菜", "美咲", "さくら", "葵", "凛", "裕子",
+        "恵子",
+    ],
+    last_names: &["佐藤", "鈴木", "高橋", "田中", "伊藤", "渡辺", "山本", "中村", "小林", "加藤", "吉田", "山田"],
+    full_name: "{1} {0}",
+    cities: &[
+        "東京都", "大阪市", "横浜市", "名古屋市", "札幌市", "福岡市", "神戸市", "京都市", "川崎市", "さいたま市",
+    ],
+    streets: &[
+        "千代田区丸の内", "港区六本木", "新宿区西新宿", "渋谷区神南", "中央区銀座", "北区梅田", "中区栄",
+        "博多区博多駅前",
+    ],
+    addresses: &["{0}%丁目%#-%", "{0}%-%#-%"],
+    postcodes: &["%##-####"],
+    phones: &["03-####-####", "06-####-####", "090-####-####", "080-####-####"],
+};
+
+const ZH: Data = Data {
+    first_names: &[
+        "伟", "芳", "娜", "秀英", "敏", "静", "丽", "强", "磊", "军", "洋", "勇", "艳", "杰", "娟", "涛", "明", "超",
+    ],
+    last_names: &["王", "李", "张", "刘", "陈", "杨", "黄", "赵", "吴", "周", "徐", "孙"],
+    full_name: "{1}{0}",
+    cities: &["北京市", "上海市", "广州市", "深圳市", "成都市", "杭州市", "武汉市", "西安市", "南京市", "重庆市"],
+    streets: &[
+        "朝阳区建国路", "海淀区中关村大街", "浦东新区世纪大道", "天河区天河路", "南山区科技园路", "武侯区人民南路",
+        "西湖区文三路",
+    ],
+    addresses: &["{0}%#号", "{0}%##号", "{0}%#号%#室"],
+    postcodes: &["%#####"],
+    phones: &["13# #### ####", "15# #### ####", "18# #### ####", "010-%#######"],
+};
+
+const PT_BR: Data = Data {
+    first_names: &[
+        "Miguel", "Arthur", "Gael", "Heitor", "Theo", "Davi", "Gabriel", "Bernardo", "João", "Helena", "Alice",
+        "Laura", "Maria Alice", "Valentina", "Heloísa", "Maria Clara", "Ana", "Beatriz",
+    ],
+    last_names: &[
+        "Silva", "Santos", "Oliveira", "Souza", "Rodrigues", "Ferreira", "Alves", "Pereira", "Lima", "Gomes", "Costa",
+        "Ribeiro", "Martins", "Carvalho",
+    ],
+    full_name: "{0} {1}",
+    cities: &[
+        "São Paulo", "Rio de Janeiro", "Brasília", "Salvador", "Fortaleza", "Belo Horizonte", "Manaus", "Curitiba",
+        "Recife", "Porto Alegre",
+    ],
+    streets: &[
+        "Rua das Flores", "Avenida Paulista", "Rua Sete de Setembro", "Rua XV de Novembro", "Avenida Brasil",
+        "Rua São João", "Rua da Consolação", "Avenida Atlântica",
+    ],
+    addresses: &["{0}, %#", "{0}, %##", "{0}, %## - apto %#"],
+    postcodes: &["%####-###"],
+    phones: &["(%#) 9####-####", "(%#) %###-####", "+55 %# 9####-####"],
+};
+
+const HI: Data = Data {
+    first_names: &[
+        "आरव", "विवान", "आदित्य", "अर्जुन", "साई", "राहुल", "रोहन", "अमित", "सुरेश", "राजेश", "अनन्या", "दिया",
+        "सान्वी", "ईशा", "प्रिया", "पूजा", "नेहा", "सुनीता",
+    ],
+    last_names: &[
+        "शर्मा", "वर्मा", "गुप्ता", "सिंह", "कुमार", "पटेल", "यादव", "जैन", "मिश्रा", "अग्रवाल", "चौहान", "रेड्डी",
+    ],
+    full_name: "{0} {1}",
+    cities: &["दिल्ली", "मुंबई", "बेंगलुरु", "कोलकाता", "चेन्नई", "हैदराबाद", "पुणे", "अहमदाबाद", "जयपुर", "लखनऊ"],
+    streets: &[
+        "महात्मा गांधी मार्ग", "नेहरू रोड", "स्टेशन रोड", "मुख्य बाज़ार", "सुभाष मार्ग", "शिवाजी नगर", "गांधी नगर",
+    ],
+    addresses: &["%#, {0}", "%##, {0}", "मकान नं. %#, {0}"],
+    postcodes: &["%#####"],
+    phones: &["+91 9#### #####", "+91 8#### #####", "+91 7#### #####"],
+};
+
+const AR: Data = Data {
+    first_names: &[
+        "محمد", "أحمد", "علي", "عمر", "يوسف", "خالد", "إبراهيم", "حسن", "فاطمة", "عائشة", "مريم", "زينب", "نور",
+        "سارة", "ليلى", "هدى",
+    ],
+    last_names: &[
+        "العلي", "الحسن", "الأحمد", "إبراهيم", "عبدالله", "السيد", "الخطيب", "حداد", "منصور", "الشامي", "النجار",
+        "العمري",
+    ],
+    full_name: "{0} {1}",
+    cities: &[
+        "القاهرة", "الرياض", "دبي", "عمّان", "بيروت", "الدار البيضاء", "الدوحة", "الكويت", "جدة", "الإسكندرية",
+    ],
+    streets: &[
+        "شارع الملك فهد", "شارع التحرير", "شارع الجامعة", "شارع الاستقلال", "شارع الملك عبدالعزيز", "شارع الحمراء",
+        "شارع النيل",
+    ],
+    addresses: &["%# {0}", "%## {0}"],
+    postcodes: &["%####"],
+    phones: &["+966 5# ### ####", "+20 1%# ### ####", "+971 5# ### ####", "+962 7# ### ####"],
+};
+
+impl Locale {
+    fn data(self) -> &'static Data {
+        match self {
+            Locale::En => &EN,
+            Locale::De => &DE,
+            Locale::Fr => &FR,
+            Locale::Ja => &JA,
+            Locale::Zh => &ZH,
+            Locale::PtBr => &PT_BR,
+            Locale::Hi => &HI,
+            Locale::Ar => &AR,
+        }
+    }
+
+    /// A column of `field` values as written in this locale.
+    pub fn kind(self, field: Field) -> ColumnKind {
+        let data = self.data();
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let format = |formats: &[&str], lists: Vec<Vec<String>>| ColumnKind::Format { formats: list(formats), lists };
+        match field {
+            Field::FirstName => ColumnKind::Choice(list(data.first_names)),
+            Field::LastName => ColumnKind::Choice(list(data.last_names)),
+            Field::FullName => format(&[data.full_name], vec![list(data.first_names), list(data.last_names)]),
+            Field::StreetAddress => format(data.addresses, vec![list(data.streets)]),
+            Field::City => ColumnKind::Choice(list(data.cities)),
+            Field::Postcode => format(data.postcodes, Vec::new()),
+            Field::Phone => format(data.phones, Vec::new()),
+        }
+    }
+}
+
+/// `{i}` in a [`ColumnKind::Format`] format made `{i + offset}`, for its
+/// lists appended after `offset` others.
+fn shift_lists(format: &str, offset: usize) -> String {
+    let mut shifted = String::with_capacity(format.len());
+    let mut rest = format;
+    while let Some((text, after)) = rest.split_once('{') {
+        let (index, after) = after.split_once('}').expect("locale formats close their lists");
+        let index: usize = index.parse().expect("locale formats index their lists");
+        shifted.push_str(&format!("{text}{{{}}}", index + offset));
+        rest = after;
+    }
+    shifted.push_str(rest);
+    shifted
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Locales values are drawn from, each for a share of them, like 60% `en`
+/// and 20% each `zh` and `ar`, for one file to test internationalization
+/// with. The shares are whole percentages, each at least 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocaleMix(Vec<(Locale, u32)>);
+
+impl From<Locale> for LocaleMix {
+    fn from(locale: Locale) -> Self {
+        LocaleMix(vec![(locale, 1)])
+    }
+}
+
+impl Default for LocaleMix {
+    fn default() -> Self {
+        Locale::default().into()
+    }
+}
+
+impl LocaleMix {
+    /// The mix of `locales`, weighted by any positive numbers, like
+    /// percentages.
+    pub fn new(locales: &BTreeMap<Locale, f64>) -> Result<Self, String> {
+        let total: f64 = locales.values().sum();
+        if locales.is_empty() || locales.values().any(|&w| !(w > 0.0 && w.is_finite())) {
+            return Err("a locale mix needs positive weights".to_string());
+        }
+        let shares: Vec<_> = locales.iter().map(|(&l, &w)| (l, ((w / total * 100.0).round() as u32).max(1))).collect();
+        let divisor = shares.iter().fold(0, |d, &(_, share)| gcd(d, share));
+        Ok(LocaleMix(shares.into_iter().map(|(l, share)| (l, share / divisor)).collect()))
+    }
+
+    /// `ja`, or `en=60,zh=20,ar=20` for a mix.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let locale = |name: &str| Locale::from_str(name, false).map_err(|_| format!("{name} is not a locale"));
+        if !s.contains('=') {
+            return locale(s).map(LocaleMix::from);
+        }
+        let mut locales = BTreeMap::new();
+        for pair in s.split(',') {
+            let (name, weight) = pair.split_once('=').ok_or_else(|| format!("{pair} is not LOCALE=WEIGHT"))?;
+            let weight = weight.parse().map_err(|_| format!("{weight} is not a weight"))?;
+            locales.insert(locale(name)?, weight);
+        }
+        LocaleMix::new(&locales)
+    }
+
+    /// A column of `field` values, each written in one of the locales.
+    pub fn kind(&self, field: Field) -> ColumnKind {
+        if let [(locale, _)] = self.0[..] {
+            return locale.kind(field);
+        }
+        // Each locale's values become formats over its own lists, repeated
+        // so that every locale's formats together come up as often as its
+        // share asks, however many it has.
+        let kinds: Vec<_> = self.0.iter().map(|&(locale, share)| (locale.kind(field), share)).collect();
+        let counts: Vec<u32> = kinds
+            .iter()
+            .map(|(kind, _)| match kind {
+                ColumnKind::Format { formats, .. } => formats.len() as u32,
+                _ => 1,
+            })
+            .collect();
+        let lcm = counts.iter().fold(1, |lcm, &n| lcm / gcd(lcm, n) * n);
+        let (mut formats, mut lists) = (Vec::new(), Vec::new());
+        for ((kind, share), count) in kinds.into_iter().zip(counts) {
+            let (own_formats, own_lists) = match kind {
+                ColumnKind::Format { formats, lists } => (formats, lists),
+                ColumnKind::Choice(items) => (vec!["{0}".to_string()], vec![items]),
+                _ => unreachable!("locale fields are choices or formats"),
+            };
+            for _ in 0..share * lcm / count {
+                formats.extend(own_formats.iter().map(|f| shift_lists(f, lists.len())));
+            }
+            lists.extend(own_lists);
+        }
+        ColumnKind::Format { formats, lists }
+    }
+}
+
+impl fmt::Display for LocaleMix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |locale: Locale| locale.to_possible_value().expect("no locale is skipped").get_name().to_string();
+        match &self.0[..] {
+            [(locale, _)] => f.write_str(&name(*locale)),
+            shares => {
+                let total: u32 = shares.iter().map(|&(_, share)| share).sum();
+                let pairs: Vec<_> = shares.iter().map(|&(l, share)| format!("{}={}", name(l), share * 100 / total)).collect();
+                f.write_str(&pairs.join(","))
+            }
+        }
+    }
+}
+
+impl Serialize for LocaleMix {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.0[..] {
+            [(locale, _)] => locale.serialize(serializer),
+            shares => serializer.collect_map(shares.iter().map(|(l, share)| (l, share))),
+        }
+    }
+}
+
+/// A locale, like `"ja"`, or a table of weights, like `{ en = 60, zh = 40 }`.
+impl<'de> Deserialize<'de> for LocaleMix {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Spec {
+            One(Locale),
+            Mix(BTreeMap<Locale, f64>),
+        }
+        match Spec::deserialize(deserializer)? {
+            Spec::One(locale) => Ok(locale.into()),
+            Spec::Mix(locales) => LocaleMix::new(&locales).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{Generator, Row};
+    use crate::schema::Column;
+
+    #[test]
+    fn every_locale_compiles() {
+        let fields = [
+            Field::FirstName,
+            Field::LastName,
+            Field::FullName,
+            Field::StreetAddress,
+            Field::City,
+            Field::Postcode,
+            Field::Phone,
+        ];
+        for locale in Locale::value_variants() {
+            let columns: Vec<_> = fields.iter().map(|&f| Column::new(format!("{f:?}"), locale.kind(f))).collect();
+            let generator = Generator::compile(&columns).unwrap();
+            let mut row = Row::new();
+            generator.fill_row(&mut rand::thread_rng(), &mut row);
+            for field in row.fields() {
+                let field = std::str::from_utf8(field).unwrap();
+                assert!(!field.is_empty() && !field.contains(['{', '}', '#', '%']), "{locale:?}: {field}");
+            }
+        }
+    }
+
+    #[test]
+    fn full_names_follow_the_locale() {
+        let mut row = Row::new();
+        let generator = Generator::compile(&[Column::new("name", Locale::Zh.kind(Field::FullName))]).unwrap();
+        generator.fill_row(&mut rand::thread_rng(), &mut row);
+        let name = std::str::from_utf8(row.field(0)).unwrap();
+        assert!(!name.contains(' ') && ZH.last_names.iter().any(|last| name.starts_with(last)), "{name}");
+        let generator = Generator::compile(&[Column::new("postcode", Locale::PtBr.kind(Field::Postcode))]).unwrap();
+        generator.fill_row(&mut rand::thread_rng(), &mut row);
+        let postcode = std::str::from_utf8(row.field(0)).unwrap();
+        assert!(postcode.len() == 9 && postcode.as_bytes()[5] == b'-' && !postcode.starts_with('0'), "{postcode}");
+    }
+
+    #[test]
+    fn mixes_draw_each_locale_for_its_share() {
+        let mix = LocaleMix::parse("en=60,zh=20,ar=20").unwrap();
+        assert_eq!(mix.to_string(), "en=60,zh=20,ar=20");
+        assert_eq!(LocaleMix::parse("ja").unwrap(), Locale::Ja.into());
+        assert!(LocaleMix::parse("en=1,xx=1").is_err());
+        assert!(LocaleMix::parse("en=0").is_err());
+        let columns = [Column::new("name", mix.kind(Field::FullName)), Column::new("address", mix.kind(Field::StreetAddress))];
+        let generator = Generator::compile(&columns).unwrap();
+        let mut row = Row::new();
+        let (mut zh, mut ar) = (0, 0);
+        for _ in 0..10_000 {
+            generator.fill_row(&mut rand::thread_rng(), &mut row);
+            let name = std::str::from_utf8(row.field(0)).unwrap();
+            zh += u32::from(ZH.last_names.iter().any(|last| name.starts_with(last)));
+            ar += u32::from(name.chars().any(|c| ('\u{600}'..='\u{6ff}').contains(&c)));
+            let address = std::str::from_utf8(row.field(1)).unwrap();
+            assert!(!address.contains(['{', '}', '#', '%']), "{address}");
+        }
+        assert!((1700..2300).contains(&zh) && (1700..2300).contains(&ar), "{zh} {ar}");
+    }
+}
@@ -0,0 +1,286 @@
+//! What the dirty-data options changed, for asserting a loader's error
+//! counts exactly: with any of them, every value or row changed in a file is
+//! listed in a `.manifest.csv` next to it, and `--dirty-seed` makes the same
+//! changes to the same rows on every run.
+
+use std::sync::{Arc, Mutex};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::BoxError;
+use crate::cli::{CorruptArgs, FileFormat};
+use crate::generator::Row;
+use crate::output::{Destination, Output, Outputs};
+use crate::schema::Column;
+use crate::sink::{Sink, csv_record};
+
+const SUFFIX: &str = ".manifest.csv";
+
+/// Whether any of the options that change what's written are given.
+fn wanted(args: &CorruptArgs) -> bool {
+    args.torture_rate > 0.0 || args.noise_rate > 0.0 || args.corrupt_rate > 0.0 || !args.mismatch.is_empty()
+}
+
+/// The changes made to one output, shared by the sinks making them.
+#[derive(Clone)]
+pub struct Manifest(Arc<Mutex<Listing>>);
+
+struct Listing {
+    output: Box<dyn Output>,
+    columns: Vec<String>,
+    /// The row being written, counting from 1 for the first after the header.
+    row: u64,
+    /// The line of the file the row being written starts on, counting from
+    /// 1, or none in Parquet files, which have no lines.
+    line: Option<u64>,
+    /// Whether the sink writing the file counts the lines each row takes,
+    /// as CSV's does; otherwise each takes one.
+    counted: bool,
+}
+
+impl Manifest {
+    /// Opens the manifest of `data`, or none without dirty-data options or
+    /// for outputs that aren't files.
+    pub fn open(outputs: &Outputs, data: &Destination, columns: &[Column]) -> Result<Option<Self>, BoxError> {
+        if !wanted(&outputs.args().corrupt) {
+            return Ok(None);
+        }
+        let Some(destination) = data.beside(SUFFIX) else { return Ok(None) };
+        let mut output = outputs.open(&destination)?;
+        output.write_all(b"row,line,column,corruption,original\n")?;
+        let columns = columns.iter().map(|c| c.name.clone()).collect();
+        let line = (outputs.args().format != FileFormat::Parquet).then_some(1);
+        Ok(Some(Manifest(Arc::new(Mutex::new(Listing { output, columns, row: 1, line, counted: false })))))
+    }
+
+    /// Counts `lines` written before the first row, like a header.
+    pub fn skip_lines(&self, lines: u64) {
+        if let Some(line) = &mut self.0.lock().unwrap().line {
+            *line += lines;
+        }
+    }
+
+    /// Counts the lines the row just written took, for a sink whose rows
+    /// can take more than one.
+    pub fn wrote_lines(&self, lines: u64) {
+        let mut listing = self.0.lock().unwrap();
+        listing.counted = true;
+        if let Some(line) = &mut listing.line {
+            *line += lines;
+        }
+    }
+
+    /// Lists a change to the row being written: to the column at `column`,
+    /// or to the whole row, which `original` is then the CSV line of.
+    fn record(&self, column: Option<usize>, corruption: &str, original: &[u8]) -> Result<(), BoxError> {
+        let mut listing = self.0.lock().unwrap();
+        let mut entry = Row::new();
+        entry.push(listing.row.to_string().as_bytes());
+        entry.push(listing.line.map_or(String::new(), |line| line.to_string()).as_bytes());
+        entry.push(column.map_or("", |i| &listing.columns[i]).as_bytes());
+        entry.push(corruption.as_bytes());
+        entry.push(original);
+        let mut line = csv_record(&entry);
+        line.push(b'\n');
+        listing.output.write_all(&line)?;
+        Ok(())
+    }
+}
+
+/// What a sink changing rows needs besides its settings: random numbers,
+/// seeded by `--dirty-seed` if it's given, and the manifest to list its
+/// changes in.
+pub struct Injector {
+    pub rng: StdRng,
+    /// What each row's random numbers are seeded from, given a seed.
+    seed: Option<u64>,
+    /// The rows started so far.
+    rows: u64,
+    manifest: Option<Manifest>,
+}
+
+impl Injector {
+    /// The injector named `name` for `data`. With a seed, each name and file
+    /// name gets its own random numbers, so that shards and tables differ
+    /// from each other but not from one run to the next, wherever they're
+    /// written.
+    pub fn new(args: &CorruptArgs, name: &str, data: &Destination, manifest: Option<&Manifest>) -> Self {
+        let seed = args.dirty_seed.map(|seed| {
+            let location = match data {
+                Destination::File(path) => path.file_name().map(|n| n.to_string_lossy().into_owned()),
+                Destination::Gcs { object: key, .. }
+                | Destination::S3 { key, .. }
+                | Destination::Azure { blob: key, .. } => key.rsplit('/').next().map(str::to_string),
+                _ => None,
+            };
+            let location = location.unwrap_or_else(|| format!("{data:?}"));
+            // FNV-1a, which unlike the standard library's hashers is the
+            // same in every release.
+            let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+            for byte in name.bytes().chain([0]).chain(location.bytes()) {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+            seed ^ hash
+        });
+        Injector { rng: StdRng::from_entropy(), seed, rows: 0, manifest: manifest.cloned() }
+    }
+
+    /// Starts the next row. With a seed, its random numbers are seeded from
+    /// the row's number, so that what's drawn for the values of one row
+    /// doesn't change which rows after it are picked. Within the row, sinks
+    /// pick the values to change before drawing anything for how, which can
+    /// depend on the values.
+    pub fn start_row(&mut self) {
+        if let Some(seed) = self.seed {
+            self.rng = StdRng::seed_from_u64(seed.wrapping_add(self.rows));
+        }
+        self.rows += 1;
+    }
+
+    /// Whether changes are listed anywhere.
+    pub fn has_manifest(&self) -> bool {
+        self.manifest.is_some()
+    }
+
+    /// Lists a change to `column` of the row being written, or to the whole
+    /// row without one.
+    pub fn record(&self, column: Option<usize>, corruption: &str, original: &[u8]) -> Result<(), BoxError> {
+        self.manifest.as_ref().map_or(Ok(()), |m| m.record(column, corruption, original))
+    }
+}
+
+/// Goes around the sinks changing rows to count the rows for the manifest,
+/// and the lines when the sink writing them doesn't, and closes it once
+/// they're done.
+pub struct ManifestSink {
+    inner: Box<dyn Sink>,
+    manifest: Manifest,
+}
+
+impl ManifestSink {
+    pub fn new(inner: Box<dyn Sink>, manifest: Manifest) -> Self {
+        ManifestSink { inner, manifest }
+    }
+}
+
+impl Sink for ManifestSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.inner.write_row(row)?;
+        let mut listing = self.manifest.0.lock().unwrap();
+        listing.row += 1;
+        if !listing.counted
+            && let Some(line) = &mut listing.line
+        {
+            *line += 1;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.inner.flush()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.inner.finish()?;
+        let listing = Arc::try_unwrap(self.manifest.0).ok().expect("the sinks sharing the manifest are finished");
+        listing.into_inner().unwrap().output.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use crate::schema::ColumnKind;
+    use crate::sink;
+    use clap::Parser;
+    use std::fs;
+
+    /// Writes the same rows to `dir` with every kind of dirty data, returning
+    /// the data and the manifest.
+    fn write(dir: &std::path::Path, seed: &str) -> (Vec<u8>, String) {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join("data.csv");
+        let mut args = vec!["gen", "--output", path.to_str().unwrap(), "--dirty-seed", seed];
+        args.extend(["--torture-rate", "0.05", "--noise-rate", "0.05", "--mismatch", "age=0.05", "--corrupt-rate", "0.05"]);
+        let args = Args::parse_from(args);
+        let columns = [
+            Column::new("id", ColumnKind::Sequence { start: 1 }),
+            Column::new("name", ColumnKind::Choice(Vec::new())),
+            Column::new("age", ColumnKind::IntRange { min: 0, max: 99 }),
+        ];
+        let mut sink = sink::open(&Outputs::new(&args), &Destination::File(path.clone()), &columns).unwrap();
+        let mut row = Row::new();
+        for i in 0..1000 {
+            row.clear();
+            for field in [i.to_string(), format!("Name {i}"), (i % 100).to_string()] {
+                row.push(field.as_bytes());
+            }
+            sink.write_row(&row).unwrap();
+        }
+        sink.finish().unwrap();
+        (fs::read(&path).unwrap(), fs::read_to_string(dir.join("data.csv.manifest.csv")).unwrap())
+    }
+
+    #[test]
+    fn a_seed_makes_the_same_changes() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_manifest_{}", std::process::id()));
+        let (data, manifest) = write(&dir.join("a"), "42");
+        assert_eq!(write(&dir.join("b"), "42"), (data.clone(), manifest.clone()));
+        assert_ne!(write(&dir.join("c"), "43").1, manifest);
+
+        let mut kinds = std::collections::BTreeSet::new();
+        for record in csv::Reader::from_reader(manifest.as_bytes()).records() {
+            let record = record.unwrap();
+            let row: u64 = record[0].parse().unwrap();
+            assert!((1..=1000).contains(&row));
+            // Mismatches are made first, to the values as generated.
+            if &record[3] == "mismatch" {
+                assert_eq!((&record[2], &record[4]), ("age", ((row - 1) % 100).to_string().as_str()));
+            }
+            kinds.insert(record[3].to_string());
+        }
+        for kind in ["torture", "mismatch", "whitespace", "missing_fields"] {
+            assert!(kinds.contains(kind), "{kind} in {kinds:?}");
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn lines_count_the_line_breaks_in_values() {
+        let path = std::env::temp_dir().join(format!("csv_data_generator_manifest_lines_{}.csv", std::process::id()));
+        let args = Args::parse_from(["gen", "--output", path.to_str().unwrap(), "--torture-rate", "0.2", "--metadata-comment"]);
+        let columns = [Column::new("id", ColumnKind::Sequence { start: 1 }), Column::new("note", ColumnKind::Choice(Vec::new()))];
+        let mut sink = sink::open(&Outputs::new(&args), &Destination::File(path.clone()), &columns).unwrap();
+        let mut row = Row::new();
+        for i in 0..500 {
+            row.clear();
+            row.push(i.to_string().as_bytes());
+            row.push(if i % 3 == 0 { "two\nlines" } else { "one line" }.as_bytes());
+            sink.write_row(&row).unwrap();
+        }
+        sink.finish().unwrap();
+
+        // The CSV reader counts lines as they're split on `\n`.
+        let data = fs::read(&path).unwrap();
+        let mut reader = csv::ReaderBuilder::new().comment(Some(b'#')).from_reader(&data[..]);
+        let starts: Vec<u64> = reader.records().map(|r| r.unwrap().position().unwrap().line()).collect();
+        assert_eq!(starts.len(), 500);
+        let manifest = fs::read_to_string(path.with_extension("csv.manifest.csv")).unwrap();
+        let mut differ = false;
+        for record in csv::Reader::from_reader(manifest.as_bytes()).records() {
+            let record = record.unwrap();
+            let (row, line): (usize, u64) = (record[0].parse().unwrap(), record[1].parse().unwrap());
+            assert_eq!(line, starts[row - 1], "row {row}");
+            differ |= line != row as u64 + 2;
+        }
+        assert!(differ);
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(path.with_extension("csv.manifest.csv")).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+//! `--metrics`: counters of the run in the Prometheus text format, served at
+//! `/metrics` while it goes on, so a dashboard can follow a long job. The
+//! server needs the `serve` feature; the counts are kept in every build.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "serve")]
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "serve")]
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "serve")]
+use tiny_http::{Header, Response, Server};
+
+use crate::BoxError;
+use crate::generator::Row;
+use crate::output::Destination;
+use crate::progress;
+use crate::sink::Sink;
+
+/// Rows generated so far.
+static ROWS: AtomicU64 = AtomicU64::new(0);
+
+/// Bytes written so far, as the outputs count them.
+static BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Shards and requests that failed.
+pub(crate) static ERRORS: AtomicU64 = AtomicU64::new(0);
+
+/// Shards waiting for a `--jobs` worker.
+pub(crate) static SHARDS_QUEUED: AtomicU64 = AtomicU64::new(0);
+
+/// Shards being generated.
+pub(crate) static SHARDS_RUNNING: AtomicU64 = AtomicU64::new(0);
+
+/// Upper bounds, in seconds, of the flush latency histogram's buckets.
+const BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0];
+
+/// How long one kind of output took to flush.
+#[derive(Default)]
+struct Latency {
+    /// Flushes that fell in each bucket and no lower one, then the rest.
+    counts: [u64; BUCKETS.len() + 1],
+    seconds: f64,
+}
+
+static LATENCIES: Mutex<BTreeMap<&str, Latency>> = Mutex::new(BTreeMap::new());
+
+/// Counts `rows` more rows generated, and `bytes` more bytes written.
+pub(crate) fn written(rows: u64, bytes: u64) {
+    ROWS.fetch_add(rows, Ordering::Relaxed);
+    BYTES.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// The rows and bytes written so far.
+pub(crate) fn totals() -> (u64, u64) {
+    (ROWS.load(Ordering::Relaxed), BYTES.load(Ordering::Relaxed))
+}
+
+/// Records a flush of a `sink` output, a [`Destination::kind`], that took
+/// `elapsed`.
+fn flushed(sink: &'static str, elapsed: Duration) {
+    let seconds = elapsed.as_secs_f64();
+    let bucket = BUCKETS.iter().position(|&le| seconds <= le).unwrap_or(BUCKETS.len());
+    let mut latencies = LATENCIES.lock().unwrap();
+    let latency = latencies.entry(sink).or_default();
+    latency.counts[bucket] += 1;
+    latency.seconds += seconds;
+}
+
+/// The metrics, in the Prometheus text exposition format.
+#[cfg(feature = "serve")]
+pub(crate) fn render() -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: &AtomicU64| {
+        let value = value.load(Ordering::Relaxed);
+        let _ = write!(out, "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n");
+    };
+    metric("csvgen_rows_total", "counter", "Rows generated.", &ROWS);
+    metric("csvgen_bytes_total", "counter", "Bytes written to outputs.", &BYTES);
+    metric("csvgen_errors_total", "counter", "Shards and requests that failed.", &ERRORS);
+    metric("csvgen_shards_queued", "gauge", "Shards waiting for a worker.", &SHARDS_QUEUED);
+    metric("csvgen_shards_running", "gauge", "Shards being generated.", &SHARDS_RUNNING);
+
+    let name = "csvgen_sink_flush_seconds";
+    let _ = write!(out, "# HELP {name} How long outputs took to flush a batch of rows.\n# TYPE {name} histogram\n");
+    for (sink, latency) in LATENCIES.lock().unwrap().iter() {
+        let mut count = 0;
+        for (le, n) in BUCKETS.iter().zip(latency.counts) {
+            count += n;
+            let _ = writeln!(out, "{name}_bucket{{sink=\"{sink}\",le=\"{le}\"}} {count}");
+        }
+        count += latency.counts[BUCKETS.len()];
+        let _ = writeln!(out, "{name}_bucket{{sink=\"{sink}\",le=\"+Inf\"}} {count}");
+        let _ = writeln!(out, "{name}_sum{{sink=\"{sink}\"}} {}", latency.seconds);
+        let _ = writeln!(out, "{name}_count{{sink=\"{sink}\"}} {count}");
+    }
+    out
+}
+
+/// The response to a request for `/metrics`.
+#[cfg(feature = "serve")]
+pub(crate) fn response() -> Response<std::io::Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes("Content-Type", "text/plain; version=0.0.4").expect("the header is ASCII");
+    Response::from_string(render()).with_header(content_type)
+}
+
+/// Serves `/metrics` at `address`, like `127.0.0.1:9090`, on a thread of its
+/// own for the rest of the process.
+#[cfg(feature = "serve")]
+pub(crate) fn serve(address: &str) -> Result<(), BoxError> {
+    let server = Server::http(address)?;
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            // A scraper that hangs up early just misses one sample.
+            let _ = match request.url() {
+                "/metrics" => request.respond(response()),
+                _ => request.respond(Response::from_string("only /metrics is served\n").with_status_code(404)),
+            };
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "serve"))]
+pub(crate) fn serve(_: &str) -> Result<(), BoxError> {
+    Err(crate::without("serve", "metrics endpoint"))
+}
+
+/// Rows a [`MetricsSink`] counts before it publishes them, if it isn't
+/// flushed first.
+const PUBLISH_ROWS: u64 = 10_000;
+
+/// Counts the rows and bytes a sink writes, for `--metrics` and progress
+/// reports, times its flushes, and holds it while the run is paused. The counts are published every few
+/// thousand rows, and when the sink is flushed.
+pub struct MetricsSink {
+    inner: Box<dyn Sink>,
+    kind: &'static str,
+    rows: u64,
+    /// Of the sink's bytes, those already counted.
+    counted: u64,
+}
+
+impl MetricsSink {
+    /// Wraps `inner`, which writes to `destination`.
+    pub fn wrap(inner: Box<dyn Sink>, destination: &Destination) -> Box<dyn Sink> {
+        Box::new(MetricsSink { inner, kind: destination.kind(), rows: 0, counted: 0 })
+    }
+
+    fn publish(&mut self) {
+        let bytes = self.inner.bytes_written();
+        written(self.rows, bytes - self.counted);
+        (self.rows, self.counted) = (0, bytes);
+    }
+}
+
+impl Sink for MetricsSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        if progress::paused() {
+            self.flush()?;
+            progress::wait_while_paused();
+        }
+        self.inner.write_row(row)?;
+        self.rows += 1;
+        if self.rows == PUBLISH_ROWS {
+            self.publish();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        let started = Instant::now();
+        self.inner.flush()?;
+        flushed(self.kind, started.elapsed());
+        self.publish();
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.publish();
+        let started = Instant::now();
+        self.inner.finish()?;
+        flushed(self.kind, started.elapsed());
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "serve"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counters_and_latency_histograms() {
+        flushed("metrics-test", Duration::from_millis(3));
+        flushed("metrics-test", Duration::from_secs(60));
+        let text = render();
+        assert!(text.contains("# TYPE csvgen_rows_total counter\ncsvgen_rows_total "));
+        assert!(text.contains("# TYPE csvgen_shards_queued gauge\n"));
+        assert!(text.contains("csvgen_sink_flush_seconds_bucket{sink=\"metrics-test\",le=\"0.001\"} 0\n"));
+        assert!(text.contains("csvgen_sink_flush_seconds_bucket{sink=\"metrics-test\",le=\"0.005\"} 1\n"));
+        assert!(text.contains("csvgen_sink_flush_seconds_bucket{sink=\"metrics-test\",le=\"30\"} 1\n"));
+        assert!(text.contains("csvgen_sink_flush_seconds_bucket{sink=\"metrics-test\",le=\"+Inf\"} 2\n"));
+        assert!(text.contains("csvgen_sink_flush_seconds_count{sink=\"metrics-test\"} 2\n"));
+    }
+}
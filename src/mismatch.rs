@@ -0,0 +1,246 @@
+//! Values of the wrong type in typed columns, for testing schema-on-read
+//! error paths: `--mismatch age=0.001` replaces that fraction of `age`
+//! values with ones like `twenty` or nothing at all, and the number put in
+//! is reported once everything is written, so bad-record counters can be
+//! checked against it.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use rand::Rng;
+
+use crate::BoxError;
+use crate::cli::{Args, FileFormat};
+use crate::generator::Row;
+use crate::manifest::Injector;
+use crate::schema::{Column, DataType};
+use crate::sink::Sink;
+
+/// Values put in, by column name, over every sink of the run.
+static INJECTED: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+/// The values put into each column named by `--mismatch`, for the run so far.
+pub fn injected() -> Vec<(String, u64)> {
+    INJECTED.lock().unwrap().iter().map(|(column, &count)| (column.clone(), count)).collect()
+}
+
+/// Checks that `--mismatch` writes CSV, whose cells hold any text: JSON
+/// lines with a word for a number no longer parse, and Parquet has no room
+/// for one in an integer column.
+pub fn check(args: &Args) -> Result<(), BoxError> {
+    if !args.corrupt.mismatch.is_empty() && args.format != FileFormat::Csv {
+        return Err("--mismatch only applies to --format csv, where a value of the wrong type can be written".into());
+    }
+    Ok(())
+}
+
+/// A column values are mismatched in.
+struct Target {
+    index: usize,
+    name: String,
+    data_type: DataType,
+    rate: f64,
+    /// Whether the row being written gets a mismatched value.
+    picked: bool,
+    injected: u64,
+}
+
+/// Puts mismatched values into some of the named columns of the rows passed on.
+pub struct MismatchSink {
+    inner: Box<dyn Sink>,
+    targets: Vec<Target>,
+    injector: Injector,
+    row: Row,
+}
+
+impl MismatchSink {
+    /// Wraps `inner`, unless none of the `rates` name one of `columns`.
+    /// Tables without a column of a given name are left alone, so each can
+    /// be named once for all of a preset's tables, but text columns fit any
+    /// value and can't be named.
+    pub fn wrap(
+        inner: Box<dyn Sink>,
+        columns: &[Column],
+        rates: &[(String, f64)],
+        injector: Injector,
+    ) -> Result<Box<dyn Sink>, BoxError> {
+        let mut targets = Vec::new();
+        for (name, rate) in rates {
+            let Some(index) = columns.iter().position(|c| &c.name == name) else { continue };
+            let data_type = columns[index].kind.data_type();
+            if data_type == DataType::String {
+                return Err(format!("--mismatch: {name} is a text column, which any value fits").into());
+            }
+            targets.push(Target { index, name: name.clone(), data_type, rate: *rate, picked: false, injected: 0 });
+        }
+        if targets.is_empty() {
+            return Ok(inner);
+        }
+        Ok(Box::new(MismatchSink { inner, targets, injector, row: Row::new() }))
+    }
+}
+
+impl Sink for MismatchSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.row.clear();
+        self.injector.start_row();
+        for target in &mut self.targets {
+            target.picked = self.injector.rng.gen_bool(target.rate);
+        }
+        for (i, field) in row.fields().enumerate() {
+            match self.targets.iter_mut().find(|t| t.index == i) {
+                Some(target) if target.picked => {
+                    target.injected += 1;
+                    let value = mismatch(&mut self.injector.rng, target.data_type, &String::from_utf8_lossy(field));
+                    self.injector.record(Some(i), "mismatch", field)?;
+                    self.row.push(value.as_bytes());
+                }
+                _ => self.row.push(field),
+            }
+        }
+        self.inner.write_row(&self.row)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.inner.flush()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        let mut injected = INJECTED.lock().unwrap();
+        for target in &self.targets {
+            *injected.entry(target.name.clone()).or_default() += target.injected;
+        }
+        drop(injected);
+        self.inner.finish()
+    }
+}
+
+const MISSING: &[&str] = &["", "N/A", "null", "-", "?"];
+
+/// A value that doesn't parse as `data_type`, standing in for `value`.
+fn mismatch<R: Rng + ?Sized>(rng: &mut R, data_type: DataType, value: &str) -> String {
+    if rng.gen_bool(0.3) {
+        return MISSING[rng.gen_range(0..MISSING.len())].to_string();
+    }
+    match data_type {
+        DataType::Integer => match value.parse::<i64>() {
+            Ok(n) if n.abs() < 100 && rng.gen_bool(0.6) => spelled(n),
+            _ => match rng.gen_range(0..4) {
+                0 => format!("{value}.5"),
+                1 => format!("#{value}"),
+                2 => format!("{value} units"),
+                _ => "many".to_string(),
+            },
+        },
+        DataType::Decimal { .. } => match rng.gen_range(0..4) {
+            0 => value.replace('.', ","),
+            1 => format!("${value}"),
+            2 => format!("{value}.0"),
+            _ => format!("~{value}"),
+        },
+        DataType::Date | DataType::Timestamp => {
+            // `YYYY-MM-DD` first, with any time after a space.
+            let (date, time) = value.split_once(' ').unwrap_or((value, ""));
+            let parts: Vec<&str> = date.split('-').collect();
+            let [year, month, day] = parts[..] else { return "not a date".to_string() };
+            let time = if time.is_empty() { String::new() } else { format!(" {time}") };
+            match rng.gen_range(0..4) {
+                0 => format!("{day}/{month}/{year}{time}"),
+                1 => format!("{year}-13-{day}{time}"),
+                2 if time.len() > 3 => format!("{date} 25{}", &time[3..]),
+                2 => format!("{year}-02-30"),
+                _ => "yesterday".to_string(),
+            }
+        }
+        DataType::String => value.to_string(),
+    }
+}
+
+/// `n` in English words, for magnitudes under 100.
+fn spelled(n: i64) -> String {
+    const ONES: [&str; 20] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+        "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+    ];
+    const TENS: [&str; 10] = ["", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+    let magnitude = n.unsigned_abs() as usize;
+    let words = match magnitude {
+        0..20 => ONES[magnitude].to_string(),
+        _ if magnitude.is_multiple_of(10) => TENS[magnitude / 10].to_string(),
+        _ => format!("{}-{}", TENS[magnitude / 10], ONES[magnitude % 10]),
+    };
+    if n < 0 { format!("minus {words}") } else { words }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use crate::output::Destination;
+    use crate::schema::{ColumnKind, parse_date, parse_timestamp};
+    use clap::Parser;
+
+    #[test]
+    fn spells_small_numbers() {
+        assert_eq!(spelled(20), "twenty");
+        assert_eq!(spelled(7), "seven");
+        assert_eq!(spelled(42), "forty-two");
+        assert_eq!(spelled(-13), "minus thirteen");
+    }
+
+    #[test]
+    fn mismatched_values_dont_parse() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            assert!(mismatch(&mut rng, DataType::Integer, "20").parse::<i64>().is_err());
+            assert!(mismatch(&mut rng, DataType::Decimal { scale: 2 }, "12.50").parse::<f64>().is_err());
+            assert!(parse_date(&mismatch(&mut rng, DataType::Date, "2024-03-09")).is_none());
+            assert!(parse_timestamp(&mismatch(&mut rng, DataType::Timestamp, "2024-03-09 10:20:30")).is_none());
+        }
+    }
+
+    #[test]
+    fn text_columns_cant_be_mismatched() {
+        struct Discard;
+        impl Sink for Discard {
+            fn write_row(&mut self, _: &Row) -> Result<(), BoxError> {
+                Ok(())
+            }
+            fn flush(&mut self) -> Result<(), BoxError> {
+                Ok(())
+            }
+            fn bytes_written(&self) -> u64 {
+                0
+            }
+            fn finish(self: Box<Self>) -> Result<(), BoxError> {
+                Ok(())
+            }
+        }
+        let columns = [Column::new("name", ColumnKind::Choice(Vec::new()))];
+        let wrap = |rates: &[(String, f64)]| {
+            let injector = Injector::new(&Args::parse_from(["gen"]).corrupt, "mismatch", &Destination::Stdout, None);
+            MismatchSink::wrap(Box::new(Discard), &columns, rates, injector)
+        };
+        let error = wrap(&[("name".into(), 0.1)]).err().unwrap();
+        assert_eq!(error.to_string(), "--mismatch: name is a text column, which any value fits");
+        assert!(wrap(&[("age".into(), 0.1)]).is_ok());
+    }
+
+    #[test]
+    fn only_csv_is_mismatched() {
+        let path = std::env::temp_dir().join(format!("csv_data_generator_mismatch_{}.jsonl", std::process::id()));
+        let output = path.to_str().unwrap();
+        let args = Args::parse_from(["gen", "--format", "jsonl", "--mismatch", "age=0.1", "--output", output]);
+        let columns = crate::columns(&args).unwrap();
+        let error = crate::sink::open(&crate::output::Outputs::new(&args), &Destination::parse(output).unwrap(), &columns);
+        assert!(error.err().unwrap().to_string().starts_with("--mismatch only applies to --format csv"));
+        // Nothing was written before the run was turned down.
+        assert!(!path.exists());
+        assert!(check(&Args::parse_from(["gen", "--mismatch", "age=0.1"])).is_ok());
+        assert!(check(&Args::parse_from(["gen", "--format", "parquet"])).is_ok());
+    }
+}
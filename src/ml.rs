@@ -0,0 +1,246 @@
+//! The ML preset: a matrix of numeric `samples` with a `label` that some of
+//! the features predict, and the `features` table saying which, for
+//! smoke-testing model training on data there's something to learn from.
+//!
+//! Every feature is standard normal. `--ml-informative` of the
+//! `--ml-features`, at random positions, each add a term to a score, in turn
+//! linear, quadratic and sinusoidal in the feature, scaled to the weights
+//! written in `features` and to a variance of 1 between them. The score has
+//! normal noise with a standard deviation of `--ml-noise` added, and the
+//! label is 1 where it's above the threshold that about `--ml-positive-rate`
+//! of the scores are above. The other features have no say in the label.
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::generator::{Row, push_decimal};
+use crate::presets::{self, push_int};
+use crate::schema::{Column, ColumnKind};
+use crate::signal;
+use crate::transactions::normal;
+
+/// Samples at scale factor 1.
+const SAMPLES: f64 = 100_000.0;
+/// The scores drawn to find the label threshold.
+const THRESHOLD_SAMPLES: usize = 100_000;
+/// Features and weights are written to this many places, and labels are
+/// worked out from the values written.
+const SCALE: u8 = 4;
+
+const TABLES: [&str; 2] = ["samples", "features"];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Relationship {
+    None,
+    Linear,
+    Quadratic,
+    Sine,
+}
+
+impl Relationship {
+    fn name(self) -> &'static str {
+        match self {
+            Relationship::None => "none",
+            Relationship::Linear => "linear",
+            Relationship::Quadratic => "quadratic",
+            Relationship::Sine => "sine",
+        }
+    }
+
+    /// The term of a standard normal `x`, with a mean of 0 and a variance
+    /// of 1.
+    fn term(self, x: f64) -> f64 {
+        match self {
+            Relationship::None => 0.0,
+            Relationship::Linear => x,
+            Relationship::Quadratic => (x * x - 1.0) / std::f64::consts::SQRT_2,
+            // The variance of sin(2x) is (1 - e^-8) / 2.
+            Relationship::Sine => (2.0 * x).sin() / ((1.0 - (-8.0f64).exp()) / 2.0).sqrt(),
+        }
+    }
+}
+
+struct Feature {
+    relationship: Relationship,
+    weight: f64,
+}
+
+fn round(value: f64) -> f64 {
+    let unit = 10f64.powi(SCALE as i32);
+    (value * unit).round() / unit
+}
+
+/// `count` features with `informative` of them at random positions, their
+/// weights of random sign and size with squares summing to about 1.
+fn features<R: Rng + ?Sized>(rng: &mut R, count: usize, informative: usize) -> Vec<Feature> {
+    const RELATIONSHIPS: [Relationship; 3] = [Relationship::Linear, Relationship::Quadratic, Relationship::Sine];
+    let mut positions: Vec<usize> = (0..count).collect();
+    positions.shuffle(rng);
+    let mut features: Vec<Feature> = (0..count).map(|_| Feature { relationship: Relationship::None, weight: 0.0 }).collect();
+    let weights: Vec<f64> =
+        (0..informative).map(|_| rng.gen_range(0.5..1.5) * if rng.r#gen() { 1.0 } else { -1.0 }).collect();
+    let norm = weights.iter().map(|w| w * w).sum::<f64>().sqrt();
+    for (i, (&position, weight)) in positions.iter().zip(weights).enumerate() {
+        features[position] = Feature { relationship: RELATIONSHIPS[i % RELATIONSHIPS.len()], weight: round(weight / norm) };
+    }
+    features
+}
+
+/// A row of features, rounded as they're written, and its score with noise.
+fn draw<R: Rng + ?Sized>(rng: &mut R, features: &[Feature], noise: f64, values: &mut Vec<f64>) -> f64 {
+    values.clear();
+    values.extend(features.iter().map(|_| round(normal(rng))));
+    let score: f64 = features.iter().zip(values.iter()).map(|(f, &x)| f.weight * f.relationship.term(x)).sum();
+    score + noise * normal(rng)
+}
+
+/// The score that `rate` of scores are above, from a sample of them.
+fn threshold<R: Rng + ?Sized>(rng: &mut R, features: &[Feature], noise: f64, rate: f64) -> f64 {
+    if rate <= 0.0 {
+        return f64::INFINITY;
+    }
+    let mut values = Vec::new();
+    let mut scores: Vec<f64> = (0..THRESHOLD_SAMPLES).map(|_| draw(rng, features, noise, &mut values)).collect();
+    scores.sort_unstable_by(f64::total_cmp);
+    let above = ((rate * THRESHOLD_SAMPLES as f64).round() as usize).min(THRESHOLD_SAMPLES);
+    if above == THRESHOLD_SAMPLES { f64::NEG_INFINITY } else { scores[THRESHOLD_SAMPLES - above - 1] }
+}
+
+/// The columns of each table, in [`TABLES`] order. Only the names and types
+/// of these matter; the values are written below.
+fn columns(count: usize) -> [Vec<Column>; 2] {
+    let integer = |name: &str| Column::new(name, ColumnKind::Sequence { start: 1 });
+    let decimal = |name: &str| Column::new(name, ColumnKind::Decimal { min: 0, max: 0, scale: SCALE });
+    let string = |name: &str| Column::new(name, ColumnKind::Choice(Vec::new()));
+    let mut samples = vec![integer("sample_id")];
+    samples.extend((1..=count).map(|i| decimal(&format!("feature_{i}"))));
+    samples.push(integer("label"));
+    [samples, vec![string("feature"), integer("informative"), string("relationship"), decimal("weight")]]
+}
+
+/// Writes one row per feature describing how it was drawn, then the
+/// samples.
+pub fn generate(args: &Args) -> Result<(), BoxError> {
+    let preset = &args.preset;
+    let (count, informative) = (preset.ml_features as usize, preset.ml_informative as usize);
+    if informative > count {
+        return Err(format!("--ml-informative {informative} is more than the {count} --ml-features").into());
+    }
+    let [mut samples_sink, mut features_sink] = presets::open_tables(args, "ML", TABLES, columns(count))?;
+
+    let mut rng = rand::thread_rng();
+    let features = features(&mut rng, count, informative);
+    // With nothing informative, the label is all noise.
+    let noise = if informative == 0 { 1.0 } else { preset.ml_noise };
+    let threshold = threshold(&mut rng, &features, noise, preset.ml_positive_rate);
+    let unit = 10f64.powi(SCALE as i32);
+    let mut row = Row::new();
+    for (i, feature) in (1..).zip(&features) {
+        row.clear();
+        row.push(format!("feature_{i}").as_bytes());
+        push_int(&mut row, u64::from(feature.relationship != Relationship::None));
+        row.push(feature.relationship.name().as_bytes());
+        push_decimal(&mut row, (feature.weight * unit).round() as i64, SCALE);
+        features_sink.write_row(&row)?;
+    }
+
+    let mut samples = ((SAMPLES * preset.scale_factor).round() as u64).max(1);
+    let mut values = Vec::with_capacity(count);
+    let mut positives = 0;
+    for sample in 1..=samples {
+        if signal::interrupted() {
+            samples = sample - 1;
+            break;
+        }
+        let label = draw(&mut rng, &features, noise, &mut values) > threshold;
+        positives += u64::from(label);
+        row.clear();
+        push_int(&mut row, sample);
+        for &x in &values {
+            push_decimal(&mut row, (x * unit).round() as i64, SCALE);
+        }
+        push_int(&mut row, label.into());
+        samples_sink.write_row(&row)?;
+    }
+
+    presets::finish_tables(args, TABLES, [samples_sink, features_sink], [samples, count as u64])?;
+    println!(
+        "  {informative} of {count} features informative, {:.2}% of samples labeled 1",
+        100.0 * positives as f64 / samples as f64
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::testing::{read, temp_dir};
+    use clap::Parser;
+    use std::fs;
+
+    fn relationship(name: &str) -> Relationship {
+        [Relationship::None, Relationship::Linear, Relationship::Quadratic, Relationship::Sine]
+            .into_iter()
+            .find(|r| r.name() == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn terms_are_standardized() {
+        let mut rng = rand::thread_rng();
+        let xs: Vec<f64> = (0..200_000).map(|_| normal(&mut rng)).collect();
+        for r in [Relationship::Linear, Relationship::Quadratic, Relationship::Sine] {
+            let terms: Vec<f64> = xs.iter().map(|&x| r.term(x)).collect();
+            let mean = terms.iter().sum::<f64>() / terms.len() as f64;
+            let variance = terms.iter().map(|t| (t - mean) * (t - mean)).sum::<f64>() / terms.len() as f64;
+            assert!(mean.abs() < 0.02 && (variance - 1.0).abs() < 0.03, "{r:?} {mean} {variance}");
+        }
+    }
+
+    #[test]
+    fn informative_features_decide_the_label() {
+        let dir = temp_dir("ml");
+        let template = dir.join("{table}.csv");
+        let args = Args::parse_from([
+            "gen",
+            "--preset",
+            "ml",
+            "--scale-factor",
+            "0.1",
+            "--ml-features",
+            "8",
+            "--ml-informative",
+            "3",
+            "--ml-positive-rate",
+            "0.1",
+            "--ml-noise",
+            "0",
+            "--output",
+            template.to_str().unwrap(),
+        ]);
+        generate(&args).unwrap();
+
+        let features = read(dir.join("features.csv"));
+        assert_eq!(features.len(), 8);
+        assert_eq!(features.iter().filter(|f| f[1] == "1").count(), 3);
+        let features: Vec<(Relationship, f64)> = features.iter().map(|f| (relationship(&f[2]), f[3].parse().unwrap())).collect();
+        let samples = read(dir.join("samples.csv"));
+        assert_eq!(samples.len(), 10_000);
+        // With no noise, the label is a cut of the score.
+        let mut scores: Vec<(f64, bool)> = samples
+            .iter()
+            .map(|s| {
+                let score = features.iter().zip(&s[1..9]).map(|((r, w), x)| w * r.term(x.parse().unwrap())).sum();
+                (score, s[9] == "1")
+            })
+            .collect();
+        scores.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+        let first = scores.iter().position(|s| s.1).unwrap();
+        assert!(scores[first..].iter().all(|s| s.1));
+        let rate = (scores.len() - first) as f64 / scores.len() as f64;
+        assert!((0.085..0.115).contains(&rate), "{rate}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+//! MySQL output: the `LOAD DATA` text format, and direct batched inserts
+//! behind the `mysql` feature.
+
+use std::io::Write;
+
+#[cfg(feature = "mysql")]
+use ::mysql::prelude::Queryable;
+#[cfg(feature = "mysql")]
+use ::mysql::{Conn, Value};
+
+use crate::BoxError;
+#[cfg(feature = "mysql")]
+use crate::cli::MySqlArgs;
+use crate::generator::Row;
+use crate::output::{CountingWriter, Output};
+#[cfg(feature = "mysql")]
+use crate::schema::{Column, DECIMAL_PRECISION, DataType};
+use crate::sink::{Delimiters, Sink};
+#[cfg(feature = "mysql")]
+use crate::sink::csv_record;
+
+/// Placeholders MySQL allows in one prepared statement.
+#[cfg(feature = "mysql")]
+const MAX_PLACEHOLDERS: usize = u16::MAX as usize;
+
+/// Appends one field in the format `LOAD DATA` reads with the default
+/// `ESCAPED BY '\\'` and `FIELDS TERMINATED BY ','`: special bytes are
+/// backslash-escaped instead of quoted, and NULL is `\N`.
+pub fn push_field(line: &mut Vec<u8>, field: Option<&[u8]>) {
+    let Some(field) = field else {
+        line.extend_from_slice(b"\\N");
+        return;
+    };
+    for &b in field {
+        match b {
+            b'\\' | b',' => line.extend_from_slice(&[b'\\', b]),
+            b'\n' => line.extend_from_slice(b"\\n"),
+            b'\r' => line.extend_from_slice(b"\\r"),
+            b'\t' => line.extend_from_slice(b"\\t"),
+            0 => line.extend_from_slice(b"\\0"),
+            _ => line.push(b),
+        }
+    }
+}
+
+/// Writes rows in the `LOAD DATA` text format, which is what
+/// `--csv-dialect mysql` selects for file outputs.
+///
+/// The file loads with `LOAD DATA INFILE ... FIELDS TERMINATED BY ','
+/// LINES TERMINATED BY '\n' IGNORE 1 LINES`; the header is the ignored line,
+/// which `--header none` leaves out.
+pub struct LoadDataSink {
+    writer: CountingWriter<Box<dyn Output>>,
+    line: Vec<u8>,
+    delimiters: Delimiters,
+}
+
+impl LoadDataSink {
+    pub fn new(output: Box<dyn Output>, header: Option<&[String]>, delimiters: Delimiters) -> Result<Self, BoxError> {
+        let mut sink = LoadDataSink { writer: CountingWriter::new(output), line: Vec::new(), delimiters };
+        if let Some(header) = header {
+            sink.write_line(header.iter().map(|name| name.as_bytes()))?;
+        }
+        Ok(sink)
+    }
+
+    fn write_line<'a>(&mut self, fields: impl Iterator<Item = &'a [u8]>) -> Result<(), BoxError> {
+        self.line.clear();
+        for (i, field) in self.delimiters.around(fields).enumerate() {
+            if i > 0 {
+                self.line.push(b',');
+            }
+            push_field(&mut self.line, Some(field));
+        }
+        self.line.push(b'\n');
+        self.writer.write_all(&self.line)?;
+        Ok(())
+    }
+}
+
+impl Sink for LoadDataSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.write_line(row.fields())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.writer.bytes()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.writer.into_inner().finish()
+    }
+}
+
+/// Backtick-quotes an identifier, and each part of a dotted `db.table` name.
+#[cfg(feature = "mysql")]
+fn quote_ident(name: &str) -> String {
+    name.split('.').map(|part| format!("`{}`", part.replace('`', "``"))).collect::<Vec<_>>().join(".")
+}
+
+/// The `CREATE TABLE IF NOT EXISTS` statement for `columns`.
+#[cfg(feature = "mysql")]
+pub fn create_table_sql(table: &str, columns: &[Column]) -> String {
+    let columns: Vec<_> = columns
+        .iter()
+        .map(|c| {
+            // TEXT rather than VARCHAR, since choice lists have no length bound.
+            let mysql_type = match c.kind.data_type() {
+                DataType::String => "TEXT".to_string(),
+                DataType::Integer => "BIGINT".to_string(),
+                DataType::Decimal { scale } => format!("DECIMAL({DECIMAL_PRECISION}, {scale})"),
+                DataType::Date => "DATE".to_string(),
+                DataType::Timestamp => "DATETIME".to_string(),
+            };
+            format!("{} {mysql_type}", quote_ident(&c.name))
+        })
+        .collect();
+    format!("CREATE TABLE IF NOT EXISTS {} ({})", quote_ident(table), columns.join(", "))
+}
+
+/// A multi-row `INSERT` with placeholders for `rows` rows of `columns`.
+#[cfg(feature = "mysql")]
+fn insert_sql(table: &str, columns: &[Column], rows: usize) -> String {
+    let names: Vec<_> = columns.iter().map(|c| quote_ident(&c.name)).collect();
+    let row = format!("({})", vec!["?"; columns.len()].join(", "));
+    format!("INSERT INTO {} ({}) VALUES {}", quote_ident(table), names.join(", "), vec![row; rows].join(", "))
+}
+
+#[cfg(feature = "mysql")]
+pub fn create_table(url: &str, table: &str, columns: &[Column]) -> Result<(), BoxError> {
+    Conn::new(url)?.query_drop(create_table_sql(table, columns))?;
+    Ok(())
+}
+
+/// Inserts rows into a MySQL table with prepared multi-row `INSERT`s of
+/// `--mysql-batch-rows` rows each, every one committed on its own.
+#[cfg(feature = "mysql")]
+pub struct MySqlSink {
+    conn: Conn,
+    table: String,
+    columns: Vec<Column>,
+    batch_rows: usize,
+    /// The statement for a full batch; the last, shorter one is prepared when needed.
+    insert: String,
+    params: Vec<Value>,
+    rows: usize,
+    bytes: u64,
+}
+
+#[cfg(feature = "mysql")]
+impl MySqlSink {
+    pub fn open(url: &str, args: &MySqlArgs, table: &str, columns: &[Column]) -> Result<Self, BoxError> {
+        let batch_rows = (args.mysql_batch_rows as usize).min(MAX_PLACEHOLDERS / columns.len().max(1));
+        Ok(MySqlSink {
+            conn: Conn::new(url)?,
+            table: table.to_string(),
+            columns: columns.to_vec(),
+            batch_rows,
+            insert: insert_sql(table, columns, batch_rows),
+            params: Vec::with_capacity(batch_rows * columns.len()),
+            rows: 0,
+            bytes: 0,
+        })
+    }
+
+    fn send_batch(&mut self) -> Result<(), BoxError> {
+        if self.rows == 0 {
+            return Ok(());
+        }
+        let params = std::mem::take(&mut self.params);
+        if self.rows == self.batch_rows {
+            self.conn.exec_drop(&self.insert, params)?;
+        } else {
+            self.conn.exec_drop(insert_sql(&self.table, &self.columns, self.rows), params)?;
+        }
+        self.params.reserve(self.batch_rows * self.columns.len());
+        self.rows = 0;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "mysql")]
+impl Sink for MySqlSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        for (field, column) in row.fields().zip(&self.columns) {
+            self.params.push(match column.kind.data_type() {
+                // The server converts text to DECIMAL, DATE and DATETIME columns exactly.
+                DataType::String | DataType::Decimal { .. } | DataType::Date | DataType::Timestamp => {
+                    Value::Bytes(field.to_vec())
+                }
+                DataType::Integer => Value::Int(std::str::from_utf8(field)?.parse()?),
+            });
+        }
+        self.rows += 1;
+        self.bytes += csv_record(row).len() as u64 + 1;
+        if self.rows >= self.batch_rows {
+            self.send_batch()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.send_batch()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.send_batch()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_fields_for_load_data() {
+        let mut line = Vec::new();
+        push_field(&mut line, Some(b"a,b\\c\nd\0"));
+        line.push(b',');
+        push_field(&mut line, None);
+        line.push(b',');
+        push_field(&mut line, Some(b"say \"hi\""));
+        assert_eq!(line, b"a\\,b\\\\c\\nd\\0,\\N,say \"hi\"");
+    }
+
+    #[cfg(feature = "mysql")]
+    #[test]
+    fn builds_statements() {
+        use crate::schema::ColumnKind;
+
+        let columns = || vec![Column::new("id", ColumnKind::Sha256Hex), Column::new("age", ColumnKind::IntRange { min: 0, max: 1 })];
+        assert_eq!(
+            create_table_sql("bench.users", &columns()),
+            "CREATE TABLE IF NOT EXISTS `bench`.`users` (`id` TEXT, `age` BIGINT)"
+        );
+        assert_eq!(insert_sql("users", &columns(), 2), "INSERT INTO `users` (`id`, `age`) VALUES (?, ?), (?, ?)");
+    }
+}
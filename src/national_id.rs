@@ -0,0 +1,297 @@
+//! National identifiers in each country's format and with its check digits,
+//! for [`ColumnKind::NationalId`](crate::schema::ColumnKind::NationalId) columns.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Country {
+    /// A Social Security number, `123-45-6789`.
+    Us,
+    /// A National Insurance number, `AB 12 34 56 C`.
+    Uk,
+    /// A Social Insurance Number, `046 454 286`, with a Luhn check digit.
+    Ca,
+    /// A NIR, `1 84 12 75 123 456 40`, with its key.
+    Fr,
+    /// A DNI, `12345678Z`, with its check letter.
+    Es,
+    /// A CPF, `123.456.789-09`, with both check digits.
+    Br,
+    /// An Aadhaar number, `2345 6789 0124`, with a Verhoeff check digit.
+    In,
+    /// A resident identity card number, with the birth date and check character.
+    Cn,
+    /// A My Number, `1234 5678 9018`, with its check digit.
+    Jp,
+    /// A BSN, `123456782`, passing the eleven test.
+    Nl,
+}
+
+/// Letters a NINO can start with, and be followed by.
+const NINO_FIRST: &[u8] = b"ABCEGHJKLMNOPRSTWXYZ";
+const NINO_SECOND: &[u8] = b"ABCEGHJKLMNPRSTWXYZ";
+const NINO_UNUSED: &[&[u8]] = &[b"BG", b"GB", b"KN", b"NK", b"NT", b"TN", b"ZZ"];
+
+const DNI_LETTERS: &[u8] = b"TRWAGMYFPDXBNJZSQVHLCKE";
+
+/// Districts of Beijing, Shanghai, Shenzhen, Chengdu, Hangzhou, Wuhan, Nanjing and Chongqing.
+const CN_REGIONS: &[&str] = &["110101", "310101", "440305", "510104", "330106", "420102", "320102", "500103"];
+
+const VERHOEFF_D: [[u8; 10]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+    [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+    [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+    [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+    [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+    [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+    [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+    [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+    [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+];
+const VERHOEFF_P: [[u8; 10]; 8] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+    [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+    [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+    [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+    [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+    [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+    [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+    [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+];
+const VERHOEFF_INV: [u8; 10] = [0, 4, 3, 2, 1, 5, 6, 7, 8, 9];
+
+/// Appends an identifier of `country`. With `synthetic`, it is from a range
+/// that is never issued where the country has one: US area numbers from 900,
+/// the `QQ` prefix of UK examples and Canadian numbers starting with 0.
+pub fn push<R: Rng + ?Sized>(rng: &mut R, country: Country, synthetic: bool, field: &mut Vec<u8>) {
+    match country {
+        Country::Us => {
+            let area = match synthetic {
+                true => rng.gen_range(900..1000),
+                false => loop {
+                    let area = rng.gen_range(1..900);
+                    if area != 666 {
+                        break area;
+                    }
+                },
+            };
+            let (group, serial) = (rng.gen_range(1..100), rng.gen_range(1..10_000));
+            field.extend_from_slice(format!("{area:03}-{group:02}-{serial:04}").as_bytes());
+        }
+        Country::Uk => {
+            let prefix = match synthetic {
+                true => *b"QQ",
+                false => loop {
+                    let prefix = [NINO_FIRST[rng.gen_range(0..NINO_FIRST.len())], NINO_SECOND[rng.gen_range(0..NINO_SECOND.len())]];
+                    if !NINO_UNUSED.contains(&&prefix[..]) {
+                        break prefix;
+                    }
+                },
+            };
+            field.extend_from_slice(&prefix);
+            field.push(b' ');
+            push_grouped(field, &digits(rng, 6), &[2, 2, 2], b' ');
+            field.extend_from_slice(&[b' ', rng.gen_range(b'A'..=b'D')]);
+        }
+        Country::Ca => {
+            let mut d = digits(rng, 8);
+            d[0] = if synthetic { 0 } else { rng.gen_range(1..8) };
+            d.push(luhn(&d));
+            push_grouped(field, &d, &[3, 3, 3], b' ');
+        }
+        Country::Fr => {
+            let (sex, year, month) = (rng.gen_range(1..=2u64), rng.gen_range(0..100u64), rng.gen_range(1..=12u64));
+            // Corsica's 2A and 2B aside.
+            let department = if rng.gen_bool(0.2) { rng.gen_range(1..20u64) } else { rng.gen_range(21..96) };
+            let (commune, order) = (rng.gen_range(1..991u64), rng.gen_range(1..1000u64));
+            let number = ((((sex * 100 + year) * 100 + month) * 100 + department) * 1000 + commune) * 1000 + order;
+            let key = 97 - number % 97;
+            let text = format!("{sex} {year:02} {month:02} {department:02} {commune:03} {order:03} {key:02}");
+            field.extend_from_slice(text.as_bytes());
+        }
+        Country::Es => {
+            let number = rng.gen_range(0..100_000_000u32);
+            field.extend_from_slice(format!("{number:08}").as_bytes());
+            field.push(DNI_LETTERS[(number % 23) as usize]);
+        }
+        Country::Br => {
+            // Numbers of one repeated digit pass the checks but are invalid.
+            let mut d = loop {
+                let d = digits(rng, 9);
+                if d.iter().any(|&x| x != d[0]) {
+                    break d;
+                }
+            };
+            for _ in 0..2 {
+                d.push(cpf_check(&d));
+            }
+            push_grouped(field, &d, &[3, 3, 3], b'.');
+            field.push(b'-');
+            field.extend(d[9..].iter().map(|x| b'0' + x));
+        }
+        Country::In => {
+            let mut d = digits(rng, 11);
+            d[0] = rng.gen_range(2..10);
+            d.push(verhoeff(&d));
+            push_grouped(field, &d, &[4, 4, 4], b' ');
+        }
+        Country::Cn => {
+            let region = CN_REGIONS[rng.gen_range(0..CN_REGIONS.len())];
+            // Born from 1950 to 2005.
+            let birth = crate::schema::format_date(rng.gen_range(-7305..13_149)).replace('-', "");
+            let mut text = format!("{region}{birth}{:03}", rng.gen_range(1..1000));
+            text.push(resident_id_check(&text));
+            field.extend_from_slice(text.as_bytes());
+        }
+        Country::Jp => {
+            let mut d = digits(rng, 11);
+            d.push(my_number_check(&d));
+            push_grouped(field, &d, &[4, 4, 4], b' ');
+        }
+        Country::Nl => {
+            let d = loop {
+                let mut d = digits(rng, 8);
+                d[0] = rng.gen_range(1..7);
+                let sum: u32 = d.iter().enumerate().map(|(i, &x)| u32::from(x) * (9 - i as u32)).sum();
+                if sum % 11 < 10 {
+                    d.push((sum % 11) as u8);
+                    break d;
+                }
+            };
+            field.extend(d.iter().map(|x| b'0' + x));
+        }
+    }
+}
+
+fn digits<R: Rng + ?Sized>(rng: &mut R, n: usize) -> Vec<u8> {
+    (0..n).map(|_| rng.gen_range(0..10)).collect()
+}
+
+/// Appends the first `digits` in groups of `groups` digits, separated by `separator`.
+fn push_grouped(field: &mut Vec<u8>, digits: &[u8], groups: &[usize], separator: u8) {
+    let mut rest = digits;
+    for (i, &len) in groups.iter().enumerate() {
+        if i > 0 {
+            field.push(separator);
+        }
+        let (group, tail) = rest.split_at(len);
+        field.extend(group.iter().map(|d| b'0' + d));
+        rest = tail;
+    }
+}
+
+/// The digit that makes `payload` followed by it pass the Luhn check.
+fn luhn(payload: &[u8]) -> u8 {
+    let sum: u32 = payload
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match u32::from(d) * if i % 2 == 0 { 2 } else { 1 } {
+            doubled if doubled > 9 => doubled - 9,
+            value => value,
+        })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+/// The next CPF check digit after `payload`, by weights counting down to 2.
+fn cpf_check(payload: &[u8]) -> u8 {
+    let sum: u32 = payload.iter().enumerate().map(|(i, &x)| u32::from(x) * (payload.len() + 1 - i) as u32).sum();
+    (sum * 10 % 11 % 10) as u8
+}
+
+/// The ISO 7064 MOD 11-2 check character of a Chinese resident ID's 17 digits.
+fn resident_id_check(payload: &str) -> char {
+    const WEIGHTS: [u32; 17] = [7, 9, 10, 5, 8, 4, 2, 1, 6, 3, 7, 9, 10, 5, 8, 4, 2];
+    let sum: u32 = payload.bytes().zip(WEIGHTS).map(|(b, w)| u32::from(b - b'0') * w).sum();
+    b"10X98765432"[(sum % 11) as usize] as char
+}
+
+/// The check digit of a My Number's 11 digits, weighted 2 to 7 and then 2
+/// to 6 from the right.
+fn my_number_check(payload: &[u8]) -> u8 {
+    let weight = |i: usize| if i < 6 { i + 2 } else { i - 4 } as u32;
+    let sum: u32 = payload.iter().rev().enumerate().map(|(i, &x)| u32::from(x) * weight(i)).sum();
+    match sum % 11 {
+        0 | 1 => 0,
+        r => (11 - r) as u8,
+    }
+}
+
+/// The Verhoeff check digit of `payload`.
+fn verhoeff(payload: &[u8]) -> u8 {
+    let check = payload.iter().rev().enumerate().fold(0, |c, (i, &d)| VERHOEFF_D[c as usize][VERHOEFF_P[(i + 1) % 8][d as usize] as usize]);
+    VERHOEFF_INV[check as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(country: Country, synthetic: bool) -> Vec<String> {
+        let mut rng = rand::thread_rng();
+        (0..500)
+            .map(|_| {
+                let mut field = Vec::new();
+                push(&mut rng, country, synthetic, &mut field);
+                String::from_utf8(field).unwrap()
+            })
+            .collect()
+    }
+
+    fn digits(id: &str) -> Vec<u32> {
+        id.chars().filter_map(|c| c.to_digit(10)).collect()
+    }
+
+    #[test]
+    fn check_digits_validate() {
+        for id in generate(Country::Ca, false) {
+            let sum: u32 = digits(&id).iter().rev().enumerate().map(|(i, &d)| if i % 2 == 1 { (2 * d) / 10 + (2 * d) % 10 } else { d }).sum();
+            assert_eq!(sum % 10, 0, "{id}");
+            assert!(!id.starts_with('0'), "{id}");
+        }
+        for id in generate(Country::In, false) {
+            let d = digits(&id);
+            let check = d.iter().rev().enumerate().fold(0, |c, (i, &x)| VERHOEFF_D[c as usize][VERHOEFF_P[i % 8][x as usize] as usize]);
+            assert_eq!((check, d.len()), (0, 12), "{id}");
+        }
+        for id in generate(Country::Fr, false) {
+            let d = digits(&id);
+            let number = d[..13].iter().fold(0u64, |n, &x| n * 10 + u64::from(x));
+            assert_eq!(97 - number % 97, u64::from(d[13] * 10 + d[14]), "{id}");
+        }
+        for id in generate(Country::Nl, false) {
+            let d = digits(&id);
+            let sum: i64 = d[..8].iter().enumerate().map(|(i, &x)| i64::from(x) * (9 - i as i64)).sum::<i64>() - i64::from(d[8]);
+            assert_eq!(sum % 11, 0, "{id}");
+        }
+        for id in generate(Country::Es, false) {
+            let number: u32 = id[..8].parse().unwrap();
+            assert_eq!(id.as_bytes()[8], DNI_LETTERS[(number % 23) as usize], "{id}");
+        }
+    }
+
+    #[test]
+    fn known_numbers_get_their_check_digits() {
+        assert_eq!(luhn(&[0, 4, 6, 4, 5, 4, 2, 8]), 6);
+        assert_eq!(verhoeff(&[2, 3, 6]), 3);
+        assert_eq!(verhoeff(&[2, 3, 4, 5, 6, 7, 8, 9, 0, 1, 2]), 4);
+        assert_eq!((cpf_check(&[1, 2, 3, 4, 5, 6, 7, 8, 9]), cpf_check(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 0])), (0, 9));
+        assert_eq!(resident_id_check("11010519491231002"), 'X');
+        assert_eq!(my_number_check(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1]), 8);
+    }
+
+    #[test]
+    fn synthetic_numbers_come_from_unissued_ranges() {
+        assert!(generate(Country::Us, true).iter().all(|id| id.starts_with('9') && id.len() == 11));
+        assert!(generate(Country::Us, false).iter().all(|id| !id.starts_with('9') && !id.starts_with("666") && !id.starts_with("000")));
+        assert!(generate(Country::Uk, true).iter().all(|id| id.starts_with("QQ ") && id.len() == 13));
+        assert!(generate(Country::Ca, true).iter().all(|id| id.starts_with('0')));
+        for id in generate(Country::Br, false).into_iter().chain(generate(Country::Cn, false)).chain(generate(Country::Jp, false)) {
+            assert!(id.len() == 14 || id.len() == 18, "{id}");
+        }
+    }
+}
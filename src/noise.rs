@@ -0,0 +1,238 @@
+//! The formatting of values typed in by people: `--noise-rate` of the values
+//! written get stray spaces, different casing, thousands separators or one
+//! of many date formats, while still meaning what they did.
+
+use clap::ValueEnum;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::BoxError;
+use crate::cli::{Args, FileFormat};
+use crate::generator::Row;
+use crate::manifest::Injector;
+use crate::schema::{Column, DataType};
+use crate::sink::Sink;
+
+/// A way of reformatting a value.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Noise {
+    /// Spaces or a tab before or after the value.
+    Whitespace,
+    /// Text in upper, lower or title case.
+    Casing,
+    /// Numbers of a thousand or more with commas between the thousands.
+    Thousands,
+    /// Dates and timestamps written day first, month first, with month
+    /// names and so on rather than as `YYYY-MM-DD`.
+    DateFormats,
+}
+
+impl Noise {
+    /// What the manifest calls it.
+    fn name(self) -> &'static str {
+        match self {
+            Noise::Whitespace => "whitespace",
+            Noise::Casing => "casing",
+            Noise::Thousands => "thousands",
+            Noise::DateFormats => "date_formats",
+        }
+    }
+
+    /// Whether values of `data_type` can be reformatted this way.
+    fn fits(self, data_type: DataType) -> bool {
+        match self {
+            Noise::Whitespace => true,
+            Noise::Casing => data_type == DataType::String,
+            Noise::Thousands => matches!(data_type, DataType::Integer | DataType::Decimal { .. }),
+            Noise::DateFormats => matches!(data_type, DataType::Date | DataType::Timestamp),
+        }
+    }
+}
+
+/// Checks that `--noise-rate` writes CSV, whose cells hold any text:
+/// thousands separators and spelled-out dates don't parse into the typed
+/// columns of JSON lines or Parquet.
+pub fn check(args: &Args) -> Result<(), BoxError> {
+    if args.corrupt.noise_rate > 0.0 && args.format != FileFormat::Csv {
+        return Err("--noise-rate only applies to --format csv, where values can be written as people type them".into());
+    }
+    Ok(())
+}
+
+const MONTHS: [&str; 12] =
+    ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+
+/// Reformats some of the values of the rows passed on.
+pub struct NoiseSink {
+    inner: Box<dyn Sink>,
+    /// The kinds of noise that fit each column.
+    fitting: Vec<Vec<Noise>>,
+    rate: f64,
+    injector: Injector,
+    /// The noise picked for each value of the row being written.
+    picked: Vec<Option<Noise>>,
+    row: Row,
+}
+
+impl NoiseSink {
+    pub fn new(inner: Box<dyn Sink>, columns: &[Column], rate: f64, kinds: &[Noise], injector: Injector) -> Self {
+        let fitting =
+            columns.iter().map(|c| kinds.iter().copied().filter(|k| k.fits(c.kind.data_type())).collect()).collect();
+        NoiseSink { inner, fitting, rate, injector, picked: Vec::new(), row: Row::new() }
+    }
+}
+
+impl Sink for NoiseSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.row.clear();
+        self.injector.start_row();
+        let rng = &mut self.injector.rng;
+        self.picked.clear();
+        self.picked.extend(self.fitting.iter().map(|kinds| {
+            (!kinds.is_empty() && rng.gen_bool(self.rate)).then(|| kinds[rng.gen_range(0..kinds.len())])
+        }));
+        for (i, (field, &kind)) in row.fields().zip(&self.picked).enumerate() {
+            let Some(kind) = kind else {
+                self.row.push(field);
+                continue;
+            };
+            let value = String::from_utf8_lossy(field);
+            match reformat(&mut self.injector.rng, kind, &value) {
+                Some(noisy) => {
+                    self.injector.record(Some(i), kind.name(), field)?;
+                    self.row.push(noisy.as_bytes());
+                }
+                None => self.row.push(field),
+            }
+        }
+        self.inner.write_row(&self.row)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.inner.flush()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.inner.finish()
+    }
+}
+
+/// `value` reformatted as `kind`, if there's a way to: empty values and
+/// numbers under a thousand are left as they are.
+fn reformat<R: Rng + ?Sized>(rng: &mut R, kind: Noise, value: &str) -> Option<String> {
+    if value.is_empty() {
+        return None;
+    }
+    match kind {
+        Noise::Whitespace => {
+            let pad = |rng: &mut R| [" ", "  ", "\t", "   "][rng.gen_range(0..4)];
+            Some(match rng.gen_range(0..3) {
+                0 => format!("{}{value}", pad(rng)),
+                1 => format!("{value}{}", pad(rng)),
+                _ => format!("{}{value}{}", pad(rng), pad(rng)),
+            })
+        }
+        Noise::Casing => Some(match rng.gen_range(0..3) {
+            0 => value.to_uppercase(),
+            1 => value.to_lowercase(),
+            _ => title_case(value),
+        }),
+        Noise::Thousands => {
+            let (sign, unsigned) = value.strip_prefix('-').map_or(("", value), |rest| ("-", rest));
+            let (whole, fraction) = unsigned.split_once('.').map_or((unsigned, None), |(w, f)| (w, Some(f)));
+            if whole.len() < 4 || !whole.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let mut grouped = String::with_capacity(whole.len() + whole.len() / 3);
+            for (i, digit) in whole.chars().enumerate() {
+                if i > 0 && (whole.len() - i).is_multiple_of(3) {
+                    grouped.push(',');
+                }
+                grouped.push(digit);
+            }
+            Some(match fraction {
+                Some(fraction) => format!("{sign}{grouped}.{fraction}"),
+                None => format!("{sign}{grouped}"),
+            })
+        }
+        Noise::DateFormats => {
+            let (date, time) = value.split_once(' ').map_or((value, None), |(d, t)| (d, Some(t)));
+            let parts: Vec<u32> = date.split('-').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+            let [year, month, day] = parts[..] else { return None };
+            let name = MONTHS.get((month as usize).checked_sub(1)?)?;
+            let date = match rng.gen_range(0..6) {
+                0 => format!("{month:02}/{day:02}/{year}"),
+                1 => format!("{day:02}/{month:02}/{year}"),
+                2 => format!("{year}/{month:02}/{day:02}"),
+                3 => format!("{day} {} {year}", &name[..3]),
+                4 => format!("{name} {day}, {year}"),
+                _ => format!("{year}{month:02}{day:02}"),
+            };
+            Some(match time {
+                Some(time) => format!("{date} {time}"),
+                None => date,
+            })
+        }
+    }
+}
+
+/// Each word's first letter upper case and the rest lower case.
+fn title_case(value: &str) -> String {
+    let mut titled = String::with_capacity(value.len());
+    let mut start = true;
+    for c in value.chars() {
+        if start {
+            titled.extend(c.to_uppercase());
+        } else {
+            titled.extend(c.to_lowercase());
+        }
+        start = c.is_whitespace();
+    }
+    titled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn noise_keeps_the_meaning() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..200 {
+            let spaced = reformat(&mut rng, Noise::Whitespace, "Ada Lovelace").unwrap();
+            assert!(spaced != "Ada Lovelace" && spaced.trim() == "Ada Lovelace");
+            let cased = reformat(&mut rng, Noise::Casing, "ada LOVELACE").unwrap();
+            assert!(["ADA LOVELACE", "ada lovelace", "Ada Lovelace"].contains(&cased.as_str()), "{cased}");
+            let date = reformat(&mut rng, Noise::DateFormats, "2024-03-09 10:20:30").unwrap();
+            assert!(date.contains("2024") && date.ends_with(" 10:20:30"), "{date}");
+            assert!(date.contains('9') && (date.contains('3') || date.contains("Mar")), "{date}");
+        }
+        assert_eq!(reformat(&mut rng, Noise::Thousands, "1234567").unwrap(), "1,234,567");
+        assert_eq!(reformat(&mut rng, Noise::Thousands, "-12345.50").unwrap(), "-12,345.50");
+        assert_eq!(reformat(&mut rng, Noise::Thousands, "999.99"), None);
+        assert_eq!(reformat(&mut rng, Noise::Whitespace, ""), None);
+    }
+
+    #[test]
+    fn kinds_fit_their_types() {
+        assert!(Noise::Casing.fits(DataType::String) && !Noise::Casing.fits(DataType::Integer));
+        assert!(Noise::Thousands.fits(DataType::Decimal { scale: 2 }) && !Noise::Thousands.fits(DataType::Date));
+        assert!(Noise::DateFormats.fits(DataType::Timestamp) && !Noise::DateFormats.fits(DataType::String));
+    }
+
+    #[test]
+    fn only_csv_gets_noise() {
+        assert!(check(&Args::parse_from(["gen", "--noise-rate", "0.1"])).is_ok());
+        for format in ["parquet", "jsonl"] {
+            let args = Args::parse_from(["gen", "--noise-rate", "0.1", "--format", format]);
+            assert!(check(&args).unwrap_err().to_string().starts_with("--noise-rate only applies to --format csv"));
+        }
+        assert!(check(&Args::parse_from(["gen", "--format", "parquet"])).is_ok());
+    }
+}
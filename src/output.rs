@@ -0,0 +1,735 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "cloud")]
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+#[cfg(feature = "async")]
+use tokio::io::AsyncWrite;
+#[cfg(feature = "async")]
+use tokio::runtime::Handle;
+
+use crate::BoxError;
+use crate::archive;
+#[cfg(feature = "cloud")]
+use crate::aws::kinesis::KinesisClient;
+#[cfg(feature = "cloud")]
+use crate::aws::s3::{S3Client, S3Upload};
+#[cfg(feature = "cloud")]
+use crate::azure::{BlobClient, BlobUpload};
+use crate::cli::Args;
+#[cfg(feature = "cloud")]
+use crate::gcp::auth::TokenSource;
+use crate::gcp::bigquery::TableRef;
+#[cfg(feature = "cloud")]
+use crate::gcp::storage::GcsUpload;
+use crate::plugin::PluginOutput;
+use crate::tcp::Endpoint;
+#[cfg(feature = "cloud")]
+use crate::throttle::RateLimiter;
+
+/// A byte destination for one output file.
+///
+/// Writes may be buffered or uploaded in pieces; only [`Output::finish`]
+/// guarantees the data is complete and durable at the destination.
+pub trait Output: Write + Send {
+    fn finish(self: Box<Self>) -> Result<(), BoxError>;
+}
+
+/// Where an output location string points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Destination {
+    File(PathBuf),
+    /// `-`: the process's standard output, for piping into another program.
+    Stdout,
+    Gcs { bucket: String, object: String },
+    S3 { bucket: String, key: String },
+    Azure { container: String, blob: String },
+    /// A BigQuery table fed through the Storage Write API; not a file.
+    BigQuery(TableRef),
+    /// A Pub/Sub topic that gets one message per row; not a file.
+    PubSub { project: String, topic: String },
+    /// A Kafka topic that gets one message per row; not a file.
+    Kafka { brokers: Vec<String>, topic: String },
+    /// A Kinesis data stream that gets one record per row; not a file.
+    Kinesis { stream: String },
+    /// A PostgreSQL connection URL; rows are copied into `--pg-table`.
+    Postgres { url: String },
+    /// A ClickHouse server URL; rows are inserted into `--ch-table`.
+    ClickHouse { url: String },
+    /// A MySQL connection URL; rows are inserted into `--mysql-table`.
+    MySql { url: String },
+    /// A local `.sqlite`/`.sqlite3` file; rows are inserted into `--db-table`.
+    Sqlite(PathBuf),
+    /// A local `.duckdb` file; rows are inserted into `--db-table`.
+    DuckDb(PathBuf),
+    /// A `tcp://` or `tls://` socket that gets one line per row; not a file.
+    Tcp(Endpoint),
+    /// A sink a `--plugin` registered as `name`, opened at `location`.
+    Plugin { name: String, location: String },
+}
+
+impl Destination {
+    /// Parses a local path or a `gs://bucket/object`, `s3://bucket/key`,
+    /// `az://container/blob`, `bq://project:dataset.table`,
+    /// `pubsub://project/topic`, `kafka://broker[,broker...]/topic`,
+    /// `kinesis://stream`, `postgres://`, `clickhouse://`, `mysql://`, `tcp://host:port`, `tls://host:port` or `plugin://name/location` URL. Local paths
+    /// ending in `.sqlite`, `.sqlite3` or `.duckdb` are database files, and
+    /// `-` is standard output.
+    pub fn parse(location: &str) -> Result<Self, BoxError> {
+        if location == "-" {
+            return Ok(Destination::Stdout);
+        }
+        if let Some(table) = location.strip_prefix("bq://") {
+            return Ok(Destination::BigQuery(table.parse()?));
+        }
+        if let Some(rest) = location.strip_prefix("pubsub://") {
+            let (project, topic) = split_bucket(rest)
+                .filter(|(_, topic)| !topic.contains('/'))
+                .ok_or_else(|| format!("{location} must look like pubsub://project/topic"))?;
+            return Ok(Destination::PubSub { project, topic });
+        }
+        if let Some(rest) = location.strip_prefix("kafka://") {
+            let (brokers, topic) = split_bucket(rest)
+                .filter(|(brokers, topic)| !topic.contains('/') && !brokers.split(',').any(str::is_empty))
+                .ok_or_else(|| format!("{location} must look like kafka://host:port[,host:port...]/topic"))?;
+            let brokers = brokers.split(',').map(str::to_owned).collect();
+            return Ok(Destination::Kafka { brokers, topic });
+        }
+        if let Some(stream) = location.strip_prefix("kinesis://") {
+            if stream.is_empty() || stream.contains('/') {
+                return Err(format!("{location} must look like kinesis://stream").into());
+            }
+            return Ok(Destination::Kinesis { stream: stream.to_string() });
+        }
+        if location.starts_with("postgres://") || location.starts_with("postgresql://") {
+            return Ok(Destination::Postgres { url: location.to_string() });
+        }
+        if location.starts_with("clickhouse://") {
+            return Ok(Destination::ClickHouse { url: location.to_string() });
+        }
+        if location.starts_with("mysql://") {
+            return Ok(Destination::MySql { url: location.to_string() });
+        }
+        if let Some(address) = location.strip_prefix("tcp://") {
+            return Ok(Destination::Tcp(Endpoint::parse(address, false)?));
+        }
+        if let Some(address) = location.strip_prefix("tls://") {
+            return Ok(Destination::Tcp(Endpoint::parse(address, true)?));
+        }
+        if let Some(rest) = location.strip_prefix("plugin://") {
+            let (name, path) = rest.split_once('/').unwrap_or((rest, ""));
+            if name.is_empty() {
+                return Err(format!("{location} must look like plugin://name/location").into());
+            }
+            return Ok(Destination::Plugin { name: name.to_string(), location: path.to_string() });
+        }
+        if let Some(rest) = location.strip_prefix("gs://") {
+            let (bucket, object) = split_bucket(rest)
+                .ok_or_else(|| format!("{location} must look like gs://bucket/object"))?;
+            return Ok(Destination::Gcs { bucket, object });
+        }
+        if let Some(rest) = location.strip_prefix("s3://") {
+            let (bucket, key) = split_bucket(rest)
+                .ok_or_else(|| format!("{location} must look like s3://bucket/key"))?;
+            return Ok(Destination::S3 { bucket, key });
+        }
+        if let Some(rest) = location.strip_prefix("az://") {
+            let (container, blob) = split_bucket(rest)
+                .ok_or_else(|| format!("{location} must look like az://container/blob"))?;
+            return Ok(Destination::Azure { container, blob });
+        }
+        let path = PathBuf::from(location);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("sqlite" | "sqlite3") => Ok(Destination::Sqlite(path)),
+            Some("duckdb") => Ok(Destination::DuckDb(path)),
+            _ => Ok(Destination::File(path)),
+        }
+    }
+
+    /// The file or object next to this one with `suffix` added to its name,
+    /// for files written alongside it. Only files have one.
+    pub fn beside(&self, suffix: &str) -> Option<Destination> {
+        match self {
+            Destination::File(path) => {
+                let mut path = path.clone().into_os_string();
+                path.push(suffix);
+                Some(Destination::File(path.into()))
+            }
+            Destination::Gcs { bucket, object } => {
+                Some(Destination::Gcs { bucket: bucket.clone(), object: format!("{object}{suffix}") })
+            }
+            Destination::S3 { bucket, key } => Some(Destination::S3 { bucket: bucket.clone(), key: format!("{key}{suffix}") }),
+            Destination::Azure { container, blob } => {
+                Some(Destination::Azure { container: container.clone(), blob: format!("{blob}{suffix}") })
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether this destination holds files, as opposed to feeding a service.
+    pub fn is_file(&self) -> bool {
+        !matches!(
+            self,
+            Destination::BigQuery(_)
+                | Destination::PubSub { .. }
+                | Destination::Kafka { .. }
+                | Destination::Kinesis { .. }
+                | Destination::Postgres { .. }
+                | Destination::ClickHouse { .. }
+                | Destination::MySql { .. }
+                | Destination::Tcp(_)
+        )
+    }
+
+    /// A short name for the kind of destination, like `gcs` or `kafka`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Destination::File(_) => "file",
+            Destination::Stdout => "stdout",
+            Destination::Gcs { .. } => "gcs",
+            Destination::S3 { .. } => "s3",
+            Destination::Azure { .. } => "azure",
+            Destination::BigQuery(_) => "bigquery",
+            Destination::PubSub { .. } => "pubsub",
+            Destination::Kafka { .. } => "kafka",
+            Destination::Kinesis { .. } => "kinesis",
+            Destination::Postgres { .. } => "postgres",
+            Destination::ClickHouse { .. } => "clickhouse",
+            Destination::MySql { .. } => "mysql",
+            Destination::Sqlite(_) => "sqlite",
+            Destination::DuckDb(_) => "duckdb",
+            Destination::Tcp(_) => "tcp",
+            Destination::Plugin { .. } => "plugin",
+        }
+    }
+}
+
+fn split_bucket(rest: &str) -> Option<(String, String)> {
+    let (bucket, object) = rest.split_once('/')?;
+    if bucket.is_empty() || object.is_empty() {
+        return None;
+    }
+    Some((bucket.to_string(), object.to_string()))
+}
+
+/// The zero-padded width and length of the shard token that `s` starts
+/// with: `{n}` or `{shard}`, or either with a width like `{shard:05}`.
+fn shard_token(s: &str) -> Option<(usize, usize)> {
+    let end = s.find('}')?;
+    let (name, width) = s[1..end].split_once(':').unwrap_or((&s[1..end], ""));
+    if name != "n" && name != "shard" {
+        return None;
+    }
+    let width = if width.is_empty() { 0 } else { width.strip_prefix('0')?.parse().ok()? };
+    Some((width, end + 1))
+}
+
+/// Whether an output template has a shard token for [`shard_location`] to
+/// replace.
+pub fn has_shard(template: &str) -> bool {
+    template.match_indices('{').any(|(i, _)| shard_token(&template[i..]).is_some())
+}
+
+/// Replaces the `{n}` or `{shard}` placeholders in an output template with a
+/// shard index, zero-padded as in `{shard:05}`.
+pub fn shard_location(template: &str, shard: usize) -> String {
+    let mut location = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        location.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match shard_token(rest) {
+            Some((width, len)) => {
+                location.push_str(&format!("{shard:0width$}"));
+                rest = &rest[len..];
+            }
+            None => {
+                location.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    location.push_str(rest);
+    location
+}
+
+/// Replaces the tokens of an output template that are the same for the
+/// whole run: `{date}`, the day it started in UTC, `{seed}`, the `--seed`,
+/// and `{random}`, eight random hex digits that keep runs from overwriting
+/// each other's files.
+pub fn run_location(template: &str, seed: Option<u64>) -> Result<String, BoxError> {
+    let mut location = template.replace("{date}", &chrono::Utc::now().format("%Y-%m-%d").to_string());
+    if location.contains("{seed}") {
+        let seed = seed.ok_or("{seed} in --output needs a --seed")?;
+        location = location.replace("{seed}", &seed.to_string());
+    }
+    if location.contains("{random}") {
+        location = location.replace("{random}", &format!("{:08x}", rand::random::<u32>()));
+    }
+    Ok(location)
+}
+
+/// Opens outputs, sharing cloud clients and credentials between all of them.
+pub struct Outputs {
+    args: Args,
+    #[cfg(feature = "cloud")]
+    gcp_auth: OnceLock<Result<Arc<TokenSource>, String>>,
+    #[cfg(feature = "cloud")]
+    s3: OnceLock<Result<Arc<S3Client>, String>>,
+    #[cfg(feature = "cloud")]
+    azure: OnceLock<Result<Arc<BlobClient>, String>>,
+    #[cfg(feature = "cloud")]
+    kinesis: OnceLock<Result<Arc<KinesisClient>, String>>,
+    /// `--upload-limit`, shared by every upload.
+    #[cfg(feature = "cloud")]
+    upload_limit: Option<Arc<RateLimiter>>,
+    table_created: OnceLock<Result<(), String>>,
+}
+
+impl Outputs {
+    pub fn new(args: &Args) -> Self {
+        Outputs {
+            args: args.clone(),
+            #[cfg(feature = "cloud")]
+            gcp_auth: OnceLock::new(),
+            #[cfg(feature = "cloud")]
+            s3: OnceLock::new(),
+            #[cfg(feature = "cloud")]
+            azure: OnceLock::new(),
+            #[cfg(feature = "cloud")]
+            kinesis: OnceLock::new(),
+            #[cfg(feature = "cloud")]
+            upload_limit: args.upload_limit.map(|limit| Arc::new(RateLimiter::new(limit as f64))),
+            table_created: OnceLock::new(),
+        }
+    }
+
+    /// The command line, for the per-destination settings in it.
+    pub fn args(&self) -> &Args {
+        &self.args
+    }
+
+    /// The Google credentials shared by GCS outputs and other Google APIs.
+    #[cfg(feature = "cloud")]
+    pub fn gcp_auth(&self) -> Result<Arc<TokenSource>, BoxError> {
+        let auth = self
+            .gcp_auth
+            .get_or_init(|| TokenSource::from_env().map(Arc::new).map_err(|e| e.to_string()))
+            .clone()?;
+        Ok(auth)
+    }
+
+    /// The Kinesis client shared by every shard, so `--kinesis-shard-count`
+    /// pacing covers the whole run.
+    #[cfg(feature = "cloud")]
+    pub fn kinesis(&self) -> Result<Arc<KinesisClient>, BoxError> {
+        let client = self
+            .kinesis
+            .get_or_init(|| KinesisClient::from_env(&self.args.kinesis).map(Arc::new).map_err(|e| e.to_string()))
+            .clone()?;
+        Ok(client)
+    }
+
+    /// Runs `create` once for the whole run, however many shards write into
+    /// the table it creates.
+    pub fn create_table_once(&self, create: impl FnOnce() -> Result<(), BoxError>) -> Result<(), BoxError> {
+        self.table_created.get_or_init(|| create().map_err(|e| e.to_string())).clone()?;
+        Ok(())
+    }
+
+    /// `upload`, paced to `--upload-limit` if there is one.
+    #[cfg(feature = "cloud")]
+    fn throttled(&self, upload: Box<dyn Output>) -> Box<dyn Output> {
+        match &self.upload_limit {
+            Some(limiter) => Box::new(ThrottledOutput { inner: upload, limiter: limiter.clone() }),
+            None => upload,
+        }
+    }
+
+    pub fn open(&self, destination: &Destination) -> Result<Box<dyn Output>, BoxError> {
+        match destination {
+            // Named pipes open like files; writes fail with a broken pipe
+            // once the reader goes away.
+            Destination::File(path) => {
+                // Templates like `out/{date}/{table}/` name directories that
+                // don't exist yet.
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent)?;
+                }
+                archive::track(path);
+                Ok(Box::new(BufWriter::new(File::create(path)?)))
+            }
+            Destination::Stdout => Ok(Box::new(BufWriter::new(io::stdout()))),
+            #[cfg(feature = "cloud")]
+            Destination::Gcs { bucket, object } => {
+                Ok(self.throttled(Box::new(GcsUpload::create(self.gcp_auth()?, bucket, object)?)))
+            }
+            #[cfg(feature = "cloud")]
+            Destination::S3 { bucket, key } => {
+                let client = self
+                    .s3
+                    .get_or_init(|| S3Client::from_env().map(Arc::new).map_err(|e| e.to_string()))
+                    .clone()?;
+                Ok(self.throttled(Box::new(S3Upload::create(client, &self.args.s3, bucket, key)?)))
+            }
+            #[cfg(feature = "cloud")]
+            Destination::Azure { container, blob } => {
+                let client = self
+                    .azure
+                    .get_or_init(|| BlobClient::from_env().map(Arc::new).map_err(|e| e.to_string()))
+                    .clone()?;
+                Ok(self.throttled(Box::new(BlobUpload::create(client, &self.args.azure, container, blob))))
+            }
+            #[cfg(not(feature = "cloud"))]
+            Destination::Gcs { .. } | Destination::S3 { .. } | Destination::Azure { .. } => {
+                Err(crate::without("cloud", "cloud storage"))
+            }
+            Destination::Plugin { name, location } => Ok(Box::new(PluginOutput::open(name, location)?)),
+            Destination::BigQuery(_)
+            | Destination::PubSub { .. }
+            | Destination::Kafka { .. }
+            | Destination::Kinesis { .. }
+            | Destination::Postgres { .. }
+            | Destination::ClickHouse { .. }
+            | Destination::MySql { .. }
+            | Destination::Sqlite(_)
+            | Destination::DuckDb(_)
+            | Destination::Tcp(_) => {
+                Err("only file destinations have an Output; records go through a Sink".into())
+            }
+        }
+    }
+}
+
+impl<W: Write + Send> Output for BufWriter<W> {
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.flush()?;
+        Ok(())
+    }
+}
+
+/// Waits after each write until the bytes written fit within `limiter`'s
+/// rate.
+#[cfg(feature = "cloud")]
+struct ThrottledOutput {
+    inner: Box<dyn Output>,
+    limiter: Arc<RateLimiter>,
+}
+
+#[cfg(feature = "cloud")]
+impl Write for ThrottledOutput {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(data)?;
+        self.limiter.acquire(written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "cloud")]
+impl Output for ThrottledOutput {
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.inner.finish()
+    }
+}
+
+/// Gzip-compresses everything written to it on the way to `inner`.
+pub struct GzipOutput {
+    encoder: GzEncoder<Box<dyn Output>>,
+}
+
+impl GzipOutput {
+    pub fn new(inner: Box<dyn Output>) -> Self {
+        GzipOutput { encoder: GzEncoder::new(inner, Compression::default()) }
+    }
+}
+
+impl Write for GzipOutput {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.encoder.write(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+impl Output for GzipOutput {
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.encoder.finish()?.finish()
+    }
+}
+
+/// Zstandard-compresses everything written to it on the way to `inner`.
+/// Needs the `zstd` feature.
+#[cfg(feature = "zstd")]
+pub struct ZstdOutput {
+    encoder: zstd::Encoder<'static, Box<dyn Output>>,
+}
+
+#[cfg(feature = "zstd")]
+impl ZstdOutput {
+    pub fn new(inner: Box<dyn Output>) -> io::Result<Self> {
+        Ok(ZstdOutput { encoder: zstd::Encoder::new(inner, zstd::DEFAULT_COMPRESSION_LEVEL)? })
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Write for ZstdOutput {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.encoder.write(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Output for ZstdOutput {
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.encoder.finish()?.finish()
+    }
+}
+
+/// An [`Output`] over a tokio [`AsyncWrite`], like a socket, that blocks on
+/// `handle`'s runtime for each write, so it's written from threads outside
+/// the runtime, as sinks are. Needs the `async` feature.
+#[cfg(feature = "async")]
+pub struct AsyncOutput<W> {
+    writer: W,
+    handle: Handle,
+}
+
+#[cfg(feature = "async")]
+impl<W: AsyncWrite + Unpin + Send> AsyncOutput<W> {
+    pub fn new(writer: W, handle: Handle) -> Self {
+        AsyncOutput { writer, handle }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<W: AsyncWrite + Unpin + Send> Write for AsyncOutput<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let writer = &mut self.writer;
+        self.handle.block_on(std::future::poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, data)))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let writer = &mut self.writer;
+        self.handle.block_on(std::future::poll_fn(|cx| Pin::new(&mut *writer).poll_flush(cx)))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<W: AsyncWrite + Unpin + Send> Output for AsyncOutput<W> {
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        let writer = &mut self.writer;
+        self.handle.block_on(std::future::poll_fn(|cx| Pin::new(&mut *writer).poll_shutdown(cx)))?;
+        Ok(())
+    }
+}
+
+/// Counts the bytes passed through to the wrapped writer.
+pub struct CountingWriter<W> {
+    inner: W,
+    bytes: u64,
+}
+
+impl<W: Write> CountingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        CountingWriter { inner, bytes: 0 }
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(data)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_templates_fill_their_tokens() {
+        assert_eq!(shard_location("out/part-{shard:05}.csv", 42), "out/part-00042.csv");
+        assert_eq!(shard_location("out/{n}-{n:3}-{x}.csv", 7), "out/7-{n:3}-{x}.csv");
+        assert!(has_shard("part-{shard:05}") && has_shard("part-{n}") && !has_shard("part-{table}"));
+        let location = run_location("{date}/{table}/{seed}-{random}", Some(9)).unwrap();
+        let (date, rest) = location.split_once("/{table}/9-").unwrap();
+        assert!(date.len() == 10 && rest.len() == 8 && rest.chars().all(|c| c.is_ascii_hexdigit()), "{location}");
+        assert!(run_location("{seed}", None).is_err());
+    }
+
+    #[test]
+    fn parses_local_paths() {
+        assert_eq!(Destination::parse("out/data.csv").unwrap(), Destination::File("out/data.csv".into()));
+    }
+
+    #[test]
+    fn parses_gcs_urls() {
+        assert_eq!(
+            Destination::parse("gs://bucket/path/part-1.csv").unwrap(),
+            Destination::Gcs { bucket: "bucket".into(), object: "path/part-1.csv".into() }
+        );
+        assert!(Destination::parse("gs://bucket").is_err());
+        assert!(Destination::parse("gs://bucket/").is_err());
+        assert!(Destination::parse("gs:///object").is_err());
+    }
+
+    #[test]
+    fn parses_bigquery_tables() {
+        let destination = Destination::parse("bq://proj:data.events").unwrap();
+        assert_eq!(destination, Destination::BigQuery("proj:data.events".parse().unwrap()));
+        assert!(!destination.is_file());
+        assert!(Destination::parse("bq://proj").is_err());
+        assert!(Destination::parse("gs://b/o").unwrap().is_file());
+    }
+
+    #[test]
+    fn parses_pubsub_topics() {
+        let destination = Destination::parse("pubsub://proj/events").unwrap();
+        assert_eq!(destination, Destination::PubSub { project: "proj".into(), topic: "events".into() });
+        assert!(!destination.is_file());
+        assert!(Destination::parse("pubsub://proj").is_err());
+        assert!(Destination::parse("pubsub://proj/topics/events").is_err());
+    }
+
+    #[test]
+    fn parses_kafka_topics() {
+        assert_eq!(
+            Destination::parse("kafka://a:9092,b:9092/events").unwrap(),
+            Destination::Kafka { brokers: vec!["a:9092".into(), "b:9092".into()], topic: "events".into() }
+        );
+        assert!(Destination::parse("kafka://a:9092").is_err());
+        assert!(Destination::parse("kafka://a:9092,/events").is_err());
+    }
+
+    #[test]
+    fn parses_tcp_sockets() {
+        let destination = Destination::parse("tls://logs.internal:6514").unwrap();
+        assert_eq!(destination, Destination::Tcp(Endpoint { host: "logs.internal".into(), port: 6514, tls: true }));
+        assert!(!destination.is_file());
+        assert!(Destination::parse("tcp://logs.internal").is_err());
+    }
+
+    #[test]
+    fn parses_kinesis_streams() {
+        assert_eq!(Destination::parse("kinesis://clicks").unwrap(), Destination::Kinesis { stream: "clicks".into() });
+        assert!(Destination::parse("kinesis://").is_err());
+    }
+
+    #[test]
+    fn keeps_postgres_urls_whole() {
+        let url = "postgres://app:secret@db:5432/bench?sslmode=disable";
+        assert_eq!(Destination::parse(url).unwrap(), Destination::Postgres { url: url.into() });
+        assert!(!Destination::parse(url).unwrap().is_file());
+    }
+
+    #[test]
+    fn keeps_mysql_urls_whole() {
+        let url = "mysql://app:secret@db:3306/bench";
+        assert_eq!(Destination::parse(url).unwrap(), Destination::MySql { url: url.into() });
+        assert!(!Destination::parse(url).unwrap().is_file());
+    }
+
+    #[test]
+    fn dash_is_stdout() {
+        assert_eq!(Destination::parse("-").unwrap(), Destination::Stdout);
+        assert!(Destination::Stdout.is_file());
+        assert_eq!(Destination::parse("./-").unwrap(), Destination::File("./-".into()));
+    }
+
+    #[test]
+    fn recognises_database_files() {
+        let destination = Destination::parse("out/part-{n}.sqlite").unwrap();
+        assert_eq!(destination, Destination::Sqlite("out/part-{n}.sqlite".into()));
+        // Each shard is its own file, so these still need `{n}` and get the default size.
+        assert!(destination.is_file());
+        assert_eq!(Destination::parse("bench.duckdb").unwrap(), Destination::DuckDb("bench.duckdb".into()));
+        assert_eq!(Destination::parse("bench.db").unwrap(), Destination::File("bench.db".into()));
+    }
+
+    #[test]
+    fn shard_placeholder_is_replaced() {
+        assert_eq!(shard_location("out/part-{n}.csv", 12), "out/part-12.csv");
+        assert_eq!(shard_location("single.csv", 3), "single.csv");
+    }
+
+    /// Keeps what was written where a test can see it after `finish`.
+    struct Shared(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(data)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output for Shared {
+        fn finish(self: Box<Self>) -> Result<(), BoxError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn gzip_output_compresses_until_finished() {
+        use std::sync::{Arc, Mutex};
+
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let mut output = Box::new(GzipOutput::new(Box::new(Shared(written.clone()))));
+        output.write_all(b"id,name\n1,Mia\n").unwrap();
+        output.finish().unwrap();
+        let mut decoded = String::new();
+        io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&written.lock().unwrap()[..]), &mut decoded).unwrap();
+        assert_eq!(decoded, "id,name\n1,Mia\n");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_output_compresses_until_finished() {
+        use std::sync::{Arc, Mutex};
+
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let mut output = Box::new(ZstdOutput::new(Box::new(Shared(written.clone()))).unwrap());
+        output.write_all(b"id,name\n1,Mia\n").unwrap();
+        output.finish().unwrap();
+        assert_eq!(zstd::decode_all(&written.lock().unwrap()[..]).unwrap(), b"id,name\n1,Mia\n");
+    }
+
+    #[test]
+    fn counting_writer_counts_bytes() {
+        let mut writer = CountingWriter::new(Vec::new());
+        writer.write_all(b"hello").unwrap();
+        writer.write_all(b", world").unwrap();
+        assert_eq!(writer.bytes(), 12);
+        assert_eq!(writer.into_inner(), b"hello, world");
+    }
+}
@@ -0,0 +1,263 @@
+//! Parquet file output, typed by the columns and written a row group at a time.
+
+use std::sync::Arc;
+
+use parquet::basic::{Compression, GzipLevel, LogicalType, Repetition, TimeUnit, Type as PhysicalType, ZstdLevel};
+use parquet::data_type::{ByteArray, ByteArrayType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+
+use crate::BoxError;
+use crate::cli::ParquetCompression;
+use crate::generator::Row;
+use crate::output::Output;
+use crate::schema::{Column, DECIMAL_PRECISION, DataType, parse_date, parse_timestamp};
+use crate::sink::{Sink, csv_size};
+
+/// One column's values for the row group being buffered.
+enum Values {
+    Strings(Vec<ByteArray>),
+    /// Integers, decimals as their unscaled values and timestamps in milliseconds.
+    Longs(Vec<i64>),
+    /// Dates as days since the epoch.
+    Days(Vec<i32>),
+}
+
+/// The Parquet schema of the files [`ParquetSink`] writes for `columns`,
+/// every field of which is optional so that empty cells can be nulls.
+fn schema(columns: &[Column]) -> Result<Type, BoxError> {
+    let fields = columns
+        .iter()
+        .map(|c| {
+            let (physical, logical) = match c.kind.data_type() {
+                DataType::String => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+                DataType::Integer => (PhysicalType::INT64, None),
+                DataType::Decimal { scale } => (
+                    PhysicalType::INT64,
+                    Some(LogicalType::Decimal { scale: scale as i32, precision: DECIMAL_PRECISION as i32 }),
+                ),
+                DataType::Date => (PhysicalType::INT32, Some(LogicalType::Date)),
+                DataType::Timestamp => (
+                    PhysicalType::INT64,
+                    Some(LogicalType::Timestamp { is_adjusted_to_u_t_c: true, unit: TimeUnit::MILLIS }),
+                ),
+            };
+            let mut field = Type::primitive_type_builder(&c.name, physical)
+                .with_repetition(Repetition::OPTIONAL)
+                .with_logical_type(logical);
+            if let DataType::Decimal { scale } = c.kind.data_type() {
+                field = field.with_precision(DECIMAL_PRECISION as i32).with_scale(scale as i32);
+            }
+            Ok(Arc::new(field.build()?))
+        })
+        .collect::<Result<_, BoxError>>()?;
+    Ok(Type::group_type_builder("schema").with_fields(fields).build()?)
+}
+
+/// Writes rows as a Parquet file to an [`Output`], Snappy-compressed unless
+/// opened [`with_compression`](ParquetSink::with_compression).
+///
+/// A file's footer only exists once it's finished, so `--size` is measured
+/// in the bytes the rows would take as CSV, like the database outputs.
+pub struct ParquetSink {
+    writer: SerializedFileWriter<Box<dyn Output>>,
+    types: Vec<DataType>,
+    values: Vec<Values>,
+    /// Of each column, the definition level of each buffered row: 1 for a
+    /// value, 0 for a null.
+    levels: Vec<Vec<i16>>,
+    buffered: usize,
+    bytes: u64,
+}
+
+impl ParquetSink {
+    /// Rows per row group, which bounds the sink's memory.
+    const ROW_GROUP_ROWS: usize = 256 * 1024;
+
+    pub fn new(output: Box<dyn Output>, columns: &[Column]) -> Result<Self, BoxError> {
+        Self::with_compression(output, columns, ParquetCompression::Snappy)
+    }
+
+    /// A sink compressing its pages with `compression`, where Zstandard
+    /// needs the `zstd` feature.
+    pub fn with_compression(
+        output: Box<dyn Output>,
+        columns: &[Column],
+        compression: ParquetCompression,
+    ) -> Result<Self, BoxError> {
+        let compression = match compression {
+            ParquetCompression::None => Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+            ParquetCompression::Zstd if cfg!(not(feature = "zstd")) => {
+                return Err(crate::without("zstd", "Zstandard compression"));
+            }
+            ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+        };
+        let properties = WriterProperties::builder().set_compression(compression).build();
+        let writer = SerializedFileWriter::new(output, Arc::new(schema(columns)?), Arc::new(properties))?;
+        let types: Vec<_> = columns.iter().map(|c| c.kind.data_type()).collect();
+        let values = types
+            .iter()
+            .map(|data_type| match data_type {
+                DataType::String => Values::Strings(Vec::new()),
+                DataType::Integer | DataType::Decimal { .. } | DataType::Timestamp => Values::Longs(Vec::new()),
+                DataType::Date => Values::Days(Vec::new()),
+            })
+            .collect();
+        let levels = vec![Vec::new(); types.len()];
+        Ok(ParquetSink { writer, types, values, levels, buffered: 0, bytes: 0 })
+    }
+
+    fn write_row_group(&mut self) -> Result<(), BoxError> {
+        if self.buffered == 0 {
+            return Ok(());
+        }
+        let mut row_group = self.writer.next_row_group()?;
+        for (values, levels) in self.values.iter_mut().zip(&mut self.levels) {
+            let mut column = row_group.next_column()?.ok_or("more buffered columns than the schema has")?;
+            match values {
+                Values::Strings(v) => column.typed::<ByteArrayType>().write_batch(v, Some(levels), None)?,
+                Values::Longs(v) => column.typed::<Int64Type>().write_batch(v, Some(levels), None)?,
+                Values::Days(v) => column.typed::<Int32Type>().write_batch(v, Some(levels), None)?,
+            };
+            column.close()?;
+            levels.clear();
+            match values {
+                Values::Strings(v) => v.clear(),
+                Values::Longs(v) => v.clear(),
+                Values::Days(v) => v.clear(),
+            }
+        }
+        row_group.close()?;
+        self.buffered = 0;
+        Ok(())
+    }
+}
+
+/// The unscaled value of a generated decimal, which always has exactly the
+/// column's number of decimal places.
+fn unscaled(field: &str) -> Result<i64, BoxError> {
+    Ok(match field.split_once('.') {
+        Some((whole, fraction)) => format!("{whole}{fraction}").parse()?,
+        None => field.parse()?,
+    })
+}
+
+impl Sink for ParquetSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        let columns = row.fields().zip(&self.types).zip(&mut self.values).zip(&mut self.levels);
+        for (((field, data_type), values), levels) in columns {
+            if data_type.is_null(field) {
+                levels.push(0);
+                continue;
+            }
+            levels.push(1);
+            match (values, data_type) {
+                (Values::Strings(v), _) => v.push(ByteArray::from(field.to_vec())),
+                (Values::Longs(v), DataType::Decimal { .. }) => v.push(unscaled(std::str::from_utf8(field)?)?),
+                (Values::Longs(v), DataType::Timestamp) => {
+                    let field = std::str::from_utf8(field)?;
+                    v.push(parse_timestamp(field).ok_or_else(|| format!("{field} isn't a timestamp"))? * 1000);
+                }
+                (Values::Longs(v), _) => v.push(std::str::from_utf8(field)?.parse()?),
+                (Values::Days(v), _) => {
+                    let field = std::str::from_utf8(field)?;
+                    v.push(parse_date(field).ok_or_else(|| format!("{field} isn't a date"))? as i32);
+                }
+            }
+        }
+        self.buffered += 1;
+        self.bytes += csv_size(row);
+        if self.buffered >= Self::ROW_GROUP_ROWS {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.write_row_group()?;
+        self.writer.into_inner()?.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnKind;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    #[test]
+    fn writes_typed_columns() {
+        let columns = [
+            Column::new("name", ColumnKind::Choice(vec!["Mia".into()])),
+            Column::new("age", ColumnKind::IntRange { min: 0, max: 99 }),
+            Column::new("price", ColumnKind::Decimal { min: 0, max: 1, scale: 2 }),
+            Column::new("day", ColumnKind::DateRange { min: 0, max: 1 }),
+            Column::new("at", ColumnKind::TimestampRange { min: 0, max: 1 }),
+        ];
+        let path = std::env::temp_dir().join(format!("csv_data_generator_test_{}.parquet", std::process::id()));
+        let output = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+        let mut sink = Box::new(ParquetSink::new(Box::new(output), &columns).unwrap());
+        let mut row = Row::new();
+        for field in ["Mia", "42", "123.45", "1992-03-07", "2023-11-14 22:13:20"] {
+            row.push(field.as_bytes());
+        }
+        sink.write_row(&row).unwrap();
+        sink.write_row(&row).unwrap();
+        assert_eq!(sink.bytes_written(), 2 * "Mia,42,123.45,1992-03-07,2023-11-14 22:13:20\n".len() as u64);
+        let mut empty = Row::new();
+        for _ in &columns {
+            empty.push(b"");
+        }
+        sink.write_row(&empty).unwrap();
+        sink.finish().unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 3);
+        let rows: Vec<_> = reader.get_row_iter(None).unwrap().map(|row| row.unwrap().to_string()).collect();
+        assert_eq!(rows[0], "{name: \"Mia\", age: 42, price: 123.45, day: 1992-03-07, at: 2023-11-14 22:13:20.000 +00:00}");
+        // Empty strings stay strings, and every other empty cell is a null.
+        assert_eq!(rows[2], "{name: \"\", age: null, price: null, day: null, at: null}");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn compresses_pages() {
+        let columns = [Column::new("age", ColumnKind::IntRange { min: 0, max: 99 })];
+        let mut codecs = vec![(ParquetCompression::None, "UNCOMPRESSED"), (ParquetCompression::Gzip, "GZIP")];
+        if cfg!(feature = "zstd") {
+            codecs.push((ParquetCompression::Zstd, "ZSTD"));
+        }
+        let path = std::env::temp_dir().join(format!("csv_data_generator_test_{}_codec.parquet", std::process::id()));
+        for (compression, codec) in codecs {
+            let output = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+            let mut sink = Box::new(ParquetSink::with_compression(Box::new(output), &columns, compression).unwrap());
+            let mut row = Row::new();
+            row.push(b"42");
+            sink.write_row(&row).unwrap();
+            sink.finish().unwrap();
+
+            let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+            let written = reader.metadata().row_group(0).column(0).compression().to_string();
+            assert!(written.starts_with(codec), "{written} for {compression:?}");
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unscales_decimals() {
+        assert_eq!(unscaled("-0.05").unwrap(), -5);
+        assert_eq!(unscaled("120.0").unwrap(), 1200);
+        assert_eq!(unscaled("42").unwrap(), 42);
+    }
+}
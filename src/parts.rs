@@ -0,0 +1,181 @@
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::BoxError;
+use crate::telemetry;
+
+/// `(part number, tag)` pairs, where the tag is whatever the store hands back
+/// to identify an uploaded part (an S3 ETag, an Azure block id).
+pub type PartTags = Vec<(u32, String)>;
+
+/// Uploads one numbered part and returns its tag.
+pub type UploadPart = dyn Fn(u32, &[u8]) -> Result<String, BoxError> + Send + Sync;
+
+/// Fails up front when an object of `object_size` bytes would need more than
+/// `max_parts` parts of `part_size`, instead of after most of it was uploaded.
+///
+/// A shard overshoots its target by up to one row, so one part is kept spare.
+pub fn check_part_count(
+    object_size: u64,
+    part_size: u64,
+    max_parts: u32,
+    noun: &str,
+    flag: &str,
+) -> Result<(), BoxError> {
+    let needed = object_size.div_ceil(part_size);
+    if needed >= u64::from(max_parts) {
+        return Err(format!(
+            "each shard needs {needed} {noun} of {part_size} bytes but at most {max_parts} are allowed; \
+             raise {flag} or --shards"
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Splits a byte stream into fixed-size parts and uploads them on a pool of
+/// threads, for object stores that assemble an object from separately
+/// uploaded pieces.
+///
+/// The queue holds one part per thread, so at most `2 × concurrency + 1`
+/// parts are in memory and a slow store applies backpressure to generation.
+pub struct PartUploads {
+    part_size: usize,
+    max_parts: u32,
+    buf: Vec<u8>,
+    next_part: u32,
+    sender: Option<SyncSender<(u32, Vec<u8>)>>,
+    workers: Vec<JoinHandle<Result<PartTags, BoxError>>>,
+    /// Set by a worker that gave up on a part, so generation stops early.
+    failed: Arc<AtomicBool>,
+}
+
+impl PartUploads {
+    pub fn new(part_size: usize, max_parts: u32, concurrency: usize, upload: Arc<UploadPart>) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<(u32, Vec<u8>)>(concurrency);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let failed = Arc::new(AtomicBool::new(false));
+        // Parts are requests of the shard that opened the upload.
+        let span = telemetry::current();
+        let workers = (0..concurrency)
+            .map(|_| {
+                let (receiver, failed, upload) = (receiver.clone(), failed.clone(), upload.clone());
+                thread::spawn(move || {
+                    telemetry::enter(span);
+                    let mut tags = Vec::new();
+                    while let Ok((number, data)) = receiver.lock().unwrap().recv() {
+                        match upload(number, &data) {
+                            Ok(tag) => tags.push((number, tag)),
+                            Err(e) => {
+                                failed.store(true, Ordering::Relaxed);
+                                return Err(e);
+                            }
+                        }
+                    }
+                    Ok(tags)
+                })
+            })
+            .collect();
+        PartUploads {
+            part_size,
+            max_parts,
+            buf: Vec::with_capacity(part_size),
+            next_part: 1,
+            sender: Some(sender),
+            workers,
+            failed,
+        }
+    }
+
+    /// Buffers `data`, queueing every part that fills up.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), BoxError> {
+        self.buf.extend_from_slice(data);
+        while self.buf.len() >= self.part_size {
+            let rest = self.buf.split_off(self.part_size);
+            let part = mem::replace(&mut self.buf, rest);
+            self.queue(part)?;
+        }
+        Ok(())
+    }
+
+    fn queue(&mut self, data: Vec<u8>) -> Result<(), BoxError> {
+        if self.next_part > self.max_parts {
+            return Err(format!("more than {} parts needed; use a larger part size", self.max_parts).into());
+        }
+        let sender = self.sender.as_ref().expect("uploads already finished");
+        if self.failed.load(Ordering::Relaxed) || sender.send((self.next_part, data)).is_err() {
+            // Workers only stop early when a part failed for good.
+            return Err(self.join().err().unwrap_or_else(|| "part uploaders stopped".into()));
+        }
+        self.next_part += 1;
+        Ok(())
+    }
+
+    /// Uploads the remaining bytes as the last, possibly short, part and waits
+    /// for every upload. Returns the tags sorted by part number; there is
+    /// always at least one part, even for an empty stream.
+    pub fn finish(&mut self) -> Result<PartTags, BoxError> {
+        if !self.buf.is_empty() || self.next_part == 1 {
+            let last = mem::take(&mut self.buf);
+            self.queue(last)?;
+        }
+        self.join()
+    }
+
+    /// Waits for the workers without queuing anything else.
+    pub fn join(&mut self) -> Result<PartTags, BoxError> {
+        self.sender = None;
+        let mut tags = Vec::new();
+        let mut first_error = None;
+        for worker in mem::take(&mut self.workers) {
+            match worker.join().expect("part uploader panicked") {
+                Ok(worker_tags) => tags.extend(worker_tags),
+                Err(e) => first_error = first_error.or(Some(e)),
+            }
+        }
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+        tags.sort();
+        Ok(tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part_count_keeps_one_part_spare() {
+        assert!(check_part_count(9_999 * 10, 10, 10_000, "parts", "--part-size").is_ok());
+        assert!(check_part_count(9_999 * 10 + 1, 10, 10_000, "parts", "--part-size").is_err());
+        assert!(check_part_count(0, 10, 10_000, "parts", "--part-size").is_ok());
+    }
+
+    #[test]
+    fn parts_are_numbered_in_order() {
+        let upload: Arc<UploadPart> = Arc::new(|number, data| Ok(format!("{number}:{}", data.len())));
+        let mut parts = PartUploads::new(4, 10, 2, upload);
+        parts.write(b"0123456789").unwrap();
+        let tags = parts.finish().unwrap();
+        assert_eq!(tags, vec![(1, "1:4".into()), (2, "2:4".into()), (3, "3:2".into())]);
+    }
+
+    #[test]
+    fn empty_streams_upload_one_part() {
+        let upload: Arc<UploadPart> = Arc::new(|number, data| Ok(format!("{number}:{}", data.len())));
+        let mut parts = PartUploads::new(4, 10, 1, upload);
+        assert_eq!(parts.finish().unwrap(), vec![(1, "1:0".into())]);
+    }
+
+    #[test]
+    fn failed_parts_stop_the_stream() {
+        let upload: Arc<UploadPart> = Arc::new(|_, _| Err("boom".into()));
+        let mut parts = PartUploads::new(1, 100, 1, upload);
+        let result = (0..50).try_for_each(|_| parts.write(b"x")).and_then(|_| parts.finish().map(drop));
+        assert_eq!(result.unwrap_err().to_string(), "boom");
+    }
+}
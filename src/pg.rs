@@ -0,0 +1,191 @@
+//! PostgreSQL output, streaming CSV straight into `COPY ... FROM STDIN`.
+//! The DDL is here in every build, and the client behind the `postgres`
+//! feature.
+
+#[cfg(feature = "postgres")]
+use std::io::{self, Write};
+#[cfg(feature = "postgres")]
+use std::mem;
+#[cfg(feature = "postgres")]
+use std::sync::mpsc::{self, SyncSender};
+#[cfg(feature = "postgres")]
+use std::thread::{self, JoinHandle};
+
+#[cfg(feature = "postgres")]
+use postgres::{Client, NoTls};
+
+#[cfg(feature = "postgres")]
+use crate::BoxError;
+#[cfg(feature = "postgres")]
+use crate::output::Output;
+use crate::schema::{Column, DECIMAL_PRECISION, DataType};
+
+/// CSV bytes handed to the connection thread at a time.
+#[cfg(feature = "postgres")]
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Quotes an identifier, and each part of a dotted `schema.table` name.
+pub fn quote_ident(name: &str) -> String {
+    name.split('.').map(|part| format!("\"{}\"", part.replace('"', "\"\""))).collect::<Vec<_>>().join(".")
+}
+
+/// The `CREATE TABLE IF NOT EXISTS` statement for `columns`.
+pub fn create_table_sql(table: &str, columns: &[Column]) -> String {
+    let columns: Vec<_> = columns
+        .iter()
+        .map(|c| {
+            let pg_type = match c.kind.data_type() {
+                DataType::String => "text".to_string(),
+                DataType::Integer => "bigint".to_string(),
+                DataType::Decimal { scale } => format!("numeric({DECIMAL_PRECISION}, {scale})"),
+                DataType::Date => "date".to_string(),
+                DataType::Timestamp => "timestamp".to_string(),
+            };
+            format!("{} {pg_type}", quote_ident(&c.name))
+        })
+        .collect();
+    format!("CREATE TABLE IF NOT EXISTS {} ({})", quote_ident(table), columns.join(", "))
+}
+
+/// The `COPY` statement the generated CSV, header included, is fed to.
+#[cfg(feature = "postgres")]
+fn copy_sql(table: &str, columns: &[Column]) -> String {
+    let names: Vec<_> = columns.iter().map(|c| quote_ident(&c.name)).collect();
+    format!("COPY {} ({}) FROM STDIN (FORMAT csv, HEADER true)", quote_ident(table), names.join(", "))
+}
+
+/// Spells out server errors, which `postgres::Error` displays as just "db error".
+#[cfg(feature = "postgres")]
+fn describe(e: postgres::Error) -> BoxError {
+    match e.as_db_error() {
+        Some(db) => format!("PostgreSQL {db}").into(),
+        None => match std::error::Error::source(&e) {
+            Some(source) => format!("PostgreSQL: {e}: {source}").into(),
+            None => format!("PostgreSQL: {e}").into(),
+        },
+    }
+}
+
+#[cfg(feature = "postgres")]
+pub fn connect(url: &str) -> Result<Client, BoxError> {
+    Client::connect(url, NoTls).map_err(describe)
+}
+
+/// Creates `table` from `columns` unless it already exists.
+#[cfg(feature = "postgres")]
+pub fn create_table(url: &str, table: &str, columns: &[Column]) -> Result<(), BoxError> {
+    connect(url)?.batch_execute(&create_table_sql(table, columns)).map_err(describe)
+}
+
+/// One `COPY` into a table, running on its own connection.
+///
+/// The connection lives on a thread that writes the chunks it is sent into
+/// the COPY; the rows are committed only by [`Output::finish`], and a copy
+/// that is dropped unfinished is aborted.
+#[cfg(feature = "postgres")]
+pub struct PgCopy {
+    buf: Vec<u8>,
+    /// `None` marks the end of the data; a closed channel without it aborts.
+    sender: Option<SyncSender<Option<Vec<u8>>>>,
+    worker: Option<JoinHandle<Result<u64, BoxError>>>,
+}
+
+#[cfg(feature = "postgres")]
+impl PgCopy {
+    pub fn start(url: &str, table: &str, columns: &[Column]) -> Result<Self, BoxError> {
+        let mut client = connect(url)?;
+        let statement = copy_sql(table, columns);
+        let (sender, receiver) = mpsc::sync_channel::<Option<Vec<u8>>>(2);
+        let worker = thread::spawn(move || -> Result<u64, BoxError> {
+            let mut writer = client.copy_in(&statement).map_err(describe)?;
+            while let Ok(chunk) = receiver.recv() {
+                match chunk {
+                    Some(chunk) => writer.write_all(&chunk)?,
+                    None => return writer.finish().map_err(describe),
+                }
+            }
+            Err("COPY was abandoned".into())
+        });
+        Ok(PgCopy { buf: Vec::with_capacity(CHUNK_SIZE), sender: Some(sender), worker: Some(worker) })
+    }
+
+    fn send(&mut self, chunk: Option<Vec<u8>>) -> Result<(), BoxError> {
+        let sender = self.sender.as_ref().expect("copy already finished");
+        if sender.send(chunk).is_err() {
+            // The connection thread only stops early when the COPY failed.
+            return Err(self.join().err().unwrap_or_else(|| "COPY stopped".into()));
+        }
+        Ok(())
+    }
+
+    fn join(&mut self) -> Result<u64, BoxError> {
+        self.sender = None;
+        match self.worker.take() {
+            Some(worker) => worker.join().expect("COPY thread panicked"),
+            None => Err("COPY already finished".into()),
+        }
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl Write for PgCopy {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_SIZE {
+            let chunk = mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+            self.send(Some(chunk)).map_err(io::Error::other)?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl Output for PgCopy {
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        let last = mem::take(&mut self.buf);
+        if !last.is_empty() {
+            self.send(Some(last))?;
+        }
+        self.send(None)?;
+        self.join()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnKind;
+
+    fn columns() -> Vec<Column> {
+        vec![Column::new("id", ColumnKind::Sha256Hex), Column::new("age", ColumnKind::IntRange { min: 0, max: 1 })]
+    }
+
+    #[test]
+    fn quotes_identifiers() {
+        assert_eq!(quote_ident("users"), "\"users\"");
+        assert_eq!(quote_ident("bench.users"), "\"bench\".\"users\"");
+        assert_eq!(quote_ident("we\"ird"), "\"we\"\"ird\"");
+    }
+
+    #[test]
+    fn creates_tables_from_columns() {
+        assert_eq!(
+            create_table_sql("bench.users", &columns()),
+            "CREATE TABLE IF NOT EXISTS \"bench\".\"users\" (\"id\" text, \"age\" bigint)"
+        );
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn copies_csv_with_a_header() {
+        assert_eq!(
+            copy_sql("users", &columns()),
+            "COPY \"users\" (\"id\", \"age\") FROM STDIN (FORMAT csv, HEADER true)"
+        );
+    }
+}
@@ -0,0 +1,388 @@
+//! `--plugin`: native column generators and sinks from shared libraries,
+//! for logic that has to run at native speed. A library exports
+//!
+//! ```c
+//! int csvgen_register(const struct csvgen_host *host);
+//! ```
+//!
+//! which the host calls once, right after loading it, with
+//!
+//! ```c
+//! typedef size_t (*csvgen_generate)(void *state, uint64_t row, uint64_t random,
+//!                                   uint8_t *out, size_t capacity);
+//! typedef void *(*csvgen_open)(void *state, const char *location);
+//! typedef int (*csvgen_write)(void *stream, const uint8_t *data, size_t len);
+//! typedef int (*csvgen_close)(void *stream);
+//!
+//! struct csvgen_host {
+//!     uint32_t abi_version; /* CSVGEN_ABI_VERSION, 1 */
+//!     void *context;
+//!     int (*register_column)(void *context, const char *name, void *state,
+//!                            csvgen_generate generate);
+//!     int (*register_sink)(void *context, const char *name, void *state,
+//!                          csvgen_open open, csvgen_write write, csvgen_close close);
+//! };
+//! ```
+//!
+//! and which returns 0 once it has registered its generators and sinks by
+//! name, passing `context` back. Names are UTF-8 and unique across the
+//! plugins of a run, and registering returns nonzero otherwise. Newer
+//! versions of the ABI only add fields at the end of `csvgen_host`.
+//!
+//! A generator writes the value of a column for the row with index `row`,
+//! from 0, to `out`, and returns its length in bytes. If that's more than
+//! `capacity`, nothing it wrote is kept and it is called again with the
+//! same arguments and at least that much room, so it must give the same
+//! value for the same `row` and `random`, 64 bits drawn from the run's
+//! random numbers, which also makes `--seed` apply to it. Shards generate
+//! on several threads at once, so a generator and its `state` must be
+//! thread safe.
+//!
+//! A sink is a destination, `plugin://name/location`, which is opened with
+//! `location` once per shard, gets the bytes of the shard's file in pieces,
+//! and is closed when that's complete. `open` returns NULL and `write` and
+//! `close` nonzero on failure. Each stream is used by one thread only, but
+//! several may be open at once.
+//!
+//! Libraries stay loaded until the process exits.
+
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString, c_char, c_int, c_void};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::BoxError;
+use crate::output::Output;
+
+/// The version of the ABI the host implements, passed as `abi_version`.
+pub const ABI_VERSION: u32 = 1;
+
+/// The symbol every plugin exports.
+const ENTRY_POINT: &CStr = c"csvgen_register";
+
+type Generate = unsafe extern "C" fn(*mut c_void, u64, u64, *mut u8, usize) -> usize;
+pub(crate) type Open = unsafe extern "C" fn(*mut c_void, *const c_char) -> *mut c_void;
+pub(crate) type WriteBytes = unsafe extern "C" fn(*mut c_void, *const u8, usize) -> c_int;
+pub(crate) type Close = unsafe extern "C" fn(*mut c_void) -> c_int;
+type Register = unsafe extern "C" fn(*const Host) -> c_int;
+
+/// `struct csvgen_host`.
+#[repr(C)]
+pub struct Host {
+    abi_version: u32,
+    context: *mut c_void,
+    register_column: unsafe extern "C" fn(*mut c_void, *const c_char, *mut c_void, Generate) -> c_int,
+    register_sink: unsafe extern "C" fn(*mut c_void, *const c_char, *mut c_void, Open, WriteBytes, Close) -> c_int,
+}
+
+/// A registered column generator.
+pub struct Generator {
+    state: *mut c_void,
+    generate: Generate,
+}
+
+// Plugins promise that generators are thread safe.
+unsafe impl Send for Generator {}
+unsafe impl Sync for Generator {}
+
+impl Generator {
+    /// Appends the value of the row with index `row` to `field`.
+    pub fn push(&self, field: &mut Vec<u8>, row: u64, random: u64) {
+        let start = field.len();
+        let mut room = 64;
+        loop {
+            field.reserve(room);
+            let capacity = field.capacity() - start;
+            // SAFETY: `out` has `capacity` bytes of room past the field's start.
+            let len = unsafe { (self.generate)(self.state, row, random, field.as_mut_ptr().add(start), capacity) };
+            if len <= capacity {
+                // SAFETY: the generator wrote `len` bytes.
+                unsafe { field.set_len(start + len) };
+                return;
+            }
+            room = len;
+        }
+    }
+}
+
+/// A registered sink.
+struct SinkPlugin {
+    state: *mut c_void,
+    open: Open,
+    write: WriteBytes,
+    close: Close,
+}
+
+unsafe impl Send for SinkPlugin {}
+unsafe impl Sync for SinkPlugin {}
+
+#[derive(Default)]
+struct Registrations {
+    columns: Vec<(String, Generator)>,
+    sinks: Vec<(String, SinkPlugin)>,
+}
+
+struct Registry {
+    columns: BTreeMap<String, Arc<Generator>>,
+    sinks: BTreeMap<String, Arc<SinkPlugin>>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry { columns: BTreeMap::new(), sinks: BTreeMap::new() });
+
+/// Loads the plugins at `paths`, in order.
+pub fn load(paths: &[impl AsRef<Path>]) -> Result<(), BoxError> {
+    for path in paths {
+        let path = path.as_ref();
+        let entry = open_library(path).map_err(|e| format!("plugin {}: {e}", path.display()))?;
+        register(entry).map_err(|e| format!("plugin {}: {e}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn open_library(path: &Path) -> Result<Register, BoxError> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: loading a library runs its initializers, which is what `--plugin` asks for.
+    unsafe {
+        let library = libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+        if library.is_null() {
+            return Err(dl_error().into());
+        }
+        let symbol = libc::dlsym(library, ENTRY_POINT.as_ptr());
+        if symbol.is_null() {
+            return Err(format!("no {} function: {}", ENTRY_POINT.to_string_lossy(), dl_error()).into());
+        }
+        Ok(std::mem::transmute::<*mut c_void, Register>(symbol))
+    }
+}
+
+#[cfg(unix)]
+unsafe fn dl_error() -> String {
+    let error = unsafe { libc::dlerror() };
+    if error.is_null() {
+        return "unknown error".to_string();
+    }
+    unsafe { CStr::from_ptr(error) }.to_string_lossy().into_owned()
+}
+
+#[cfg(not(unix))]
+fn open_library(_: &Path) -> Result<Register, BoxError> {
+    Err("plugins can only be loaded on Unix".into())
+}
+
+/// Calls a plugin's entry point and adds what it registers.
+fn register(entry: Register) -> Result<(), BoxError> {
+    let mut registrations = Registrations::default();
+    let host = Host {
+        abi_version: ABI_VERSION,
+        context: (&raw mut registrations).cast(),
+        register_column,
+        register_sink,
+    };
+    // SAFETY: `host` outlives the call, and plugins only use it during it.
+    let status = unsafe { entry(&host) };
+    if status != 0 {
+        return Err(format!("{} returned {status}", ENTRY_POINT.to_string_lossy()).into());
+    }
+    let mut registry = REGISTRY.lock().unwrap();
+    for (name, _) in &registrations.columns {
+        if registry.columns.contains_key(name) {
+            return Err(format!("another plugin has a column generator named {name}").into());
+        }
+    }
+    for (name, _) in &registrations.sinks {
+        if registry.sinks.contains_key(name) {
+            return Err(format!("another plugin has a sink named {name}").into());
+        }
+    }
+    registry.columns.extend(registrations.columns.into_iter().map(|(name, generator)| (name, Arc::new(generator))));
+    registry.sinks.extend(registrations.sinks.into_iter().map(|(name, sink)| (name, Arc::new(sink))));
+    Ok(())
+}
+
+/// A name the plugin passed, if it's new in `taken`.
+unsafe fn new_name<T>(name: *const c_char, taken: &[(String, T)]) -> Option<String> {
+    if name.is_null() {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(name) }.to_str().ok()?;
+    (!name.is_empty() && !taken.iter().any(|(n, _)| n == name)).then(|| name.to_string())
+}
+
+unsafe extern "C" fn register_column(context: *mut c_void, name: *const c_char, state: *mut c_void, generate: Generate) -> c_int {
+    let registrations = unsafe { &mut *context.cast::<Registrations>() };
+    let Some(name) = (unsafe { new_name(name, &registrations.columns) }) else { return 1 };
+    registrations.columns.push((name, Generator { state, generate }));
+    0
+}
+
+unsafe extern "C" fn register_sink(
+    context: *mut c_void,
+    name: *const c_char,
+    state: *mut c_void,
+    open: Open,
+    write: WriteBytes,
+    close: Close,
+) -> c_int {
+    let registrations = unsafe { &mut *context.cast::<Registrations>() };
+    let Some(name) = (unsafe { new_name(name, &registrations.sinks) }) else { return 1 };
+    registrations.sinks.push((name, SinkPlugin { state, open, write, close }));
+    0
+}
+
+/// Registers a sink from within the process, like the callbacks of the
+/// [C API](crate::ffi), until [`remove_sink`].
+pub(crate) fn add_sink(name: &str, state: *mut c_void, open: Open, write: WriteBytes, close: Close) -> Result<(), BoxError> {
+    let mut registry = REGISTRY.lock().unwrap();
+    if registry.sinks.contains_key(name) {
+        return Err(format!("there already is a sink named {name}").into());
+    }
+    registry.sinks.insert(name.to_string(), Arc::new(SinkPlugin { state, open, write, close }));
+    Ok(())
+}
+
+pub(crate) fn remove_sink(name: &str) {
+    REGISTRY.lock().unwrap().sinks.remove(name);
+}
+
+/// The column generator registered as `name`.
+pub fn generator(name: &str) -> Result<Arc<Generator>, String> {
+    let registry = REGISTRY.lock().unwrap();
+    registry.columns.get(name).cloned().ok_or_else(|| match registry.columns.len() {
+        0 => format!("no plugin column generator {name}; load the plugin with --plugin"),
+        _ => format!("no plugin column generator {name}; there are {}", names(registry.columns.keys())),
+    })
+}
+
+fn names<'a>(names: impl Iterator<Item = &'a String>) -> String {
+    names.map(String::as_str).collect::<Vec<_>>().join(", ")
+}
+
+/// An open stream of a plugin's sink.
+pub struct PluginOutput {
+    name: String,
+    sink: Arc<SinkPlugin>,
+    stream: *mut c_void,
+}
+
+// Streams are used by one thread at a time.
+unsafe impl Send for PluginOutput {}
+
+impl PluginOutput {
+    /// Opens the sink `name` at `location`.
+    pub fn open(name: &str, location: &str) -> Result<Self, BoxError> {
+        let sink = REGISTRY.lock().unwrap().sinks.get(name).cloned();
+        let sink = sink.ok_or_else(|| format!("no plugin sink {name}; load the plugin with --plugin"))?;
+        let location = CString::new(location)?;
+        // SAFETY: the sink's functions take the state it registered.
+        let stream = unsafe { (sink.open)(sink.state, location.as_ptr()) };
+        if stream.is_null() {
+            return Err(format!("plugin sink {name} couldn't open {}", location.to_string_lossy()).into());
+        }
+        Ok(PluginOutput { name: name.to_string(), sink, stream })
+    }
+}
+
+impl Write for PluginOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // SAFETY: `stream` is open until `finish`.
+        match unsafe { (self.sink.write)(self.stream, buf.as_ptr(), buf.len()) } {
+            0 => Ok(buf.len()),
+            status => Err(io::Error::other(format!("plugin sink {} failed to write: {status}", self.name))),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output for PluginOutput {
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        // SAFETY: the stream is closed once, here.
+        match unsafe { (self.sink.close)(self.stream) } {
+            0 => Ok(()),
+            status => Err(format!("plugin sink {} failed to close: {status}", self.name).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{self, Row};
+    use crate::output::{Destination, Outputs};
+    use crate::schema::{Column, ColumnKind, DataType};
+    use clap::Parser;
+
+    /// Writes the prefix in `state`, the row and the last digit of `random`.
+    unsafe extern "C" fn generate(state: *mut c_void, row: u64, random: u64, out: *mut u8, capacity: usize) -> usize {
+        let prefix = unsafe { CStr::from_ptr(state.cast()) }.to_bytes();
+        let value = format!("{}-{row}-{}", String::from_utf8_lossy(prefix), random % 10);
+        if value.len() <= capacity {
+            unsafe { std::ptr::copy_nonoverlapping(value.as_ptr(), out, value.len()) };
+        }
+        value.len()
+    }
+
+    static WRITTEN: Mutex<Vec<(String, Vec<u8>)>> = Mutex::new(Vec::new());
+
+    unsafe extern "C" fn open(_: *mut c_void, location: *const c_char) -> *mut c_void {
+        let location = unsafe { CStr::from_ptr(location) }.to_string_lossy().into_owned();
+        Box::into_raw(Box::new((location, Vec::<u8>::new()))).cast()
+    }
+
+    unsafe extern "C" fn write(stream: *mut c_void, data: *const u8, len: usize) -> c_int {
+        let (_, written) = unsafe { &mut *stream.cast::<(String, Vec<u8>)>() };
+        written.extend_from_slice(unsafe { std::slice::from_raw_parts(data, len) });
+        0
+    }
+
+    unsafe extern "C" fn close(stream: *mut c_void) -> c_int {
+        WRITTEN.lock().unwrap().push(*unsafe { Box::from_raw(stream.cast::<(String, Vec<u8>)>()) });
+        0
+    }
+
+    unsafe extern "C" fn entry(host: *const Host) -> c_int {
+        let host = unsafe { &*host };
+        assert_eq!(host.abi_version, ABI_VERSION);
+        // A prefix long enough that the first call runs out of room.
+        let prefix: &'static CStr = c"a-value-longer-than-the-first-buffer-the-host-offers-a-generator";
+        let state = prefix.as_ptr().cast_mut().cast();
+        unsafe {
+            assert_eq!((host.register_column)(host.context, c"test_label".as_ptr(), state, generate), 0);
+            assert_ne!((host.register_column)(host.context, c"test_label".as_ptr(), state, generate), 0);
+            assert_eq!((host.register_sink)(host.context, c"test_memory".as_ptr(), state, open, write, close), 0);
+        }
+        0
+    }
+
+    #[test]
+    fn registers_generators_and_sinks() {
+        register(entry).unwrap();
+        assert!(register(entry).unwrap_err().to_string().contains("test_label"));
+
+        let columns = vec![
+            Column::new("id", ColumnKind::Sequence { start: 1 }),
+            Column::new("label", ColumnKind::Plugin { name: "test_label".into(), data_type: DataType::String }),
+        ];
+        let generator = generator::Generator::compile(&columns).unwrap();
+        let rows: Vec<Row> = generator.rows(rand::thread_rng()).take(3).collect();
+        let label = String::from_utf8(rows[2].field(1).to_vec()).unwrap();
+        assert!(label.starts_with("a-value-longer-than-the-first-buffer-the-host-offers-a-generator-2-"), "{label}");
+        let unknown = vec![Column::new("x", ColumnKind::Plugin { name: "missing".into(), data_type: DataType::String })];
+        assert!(generator::Generator::compile(&unknown).is_err());
+
+        let args = crate::cli::Args::parse_from(["gen"]);
+        assert!(Destination::parse("plugin:///part-1").is_err());
+        let destination = Destination::parse("plugin://test_memory/part-1").unwrap();
+        let mut output = Outputs::new(&args).open(&destination).unwrap();
+        output.write_all(b"id,label\n").unwrap();
+        output.finish().unwrap();
+        assert_eq!(WRITTEN.lock().unwrap()[..], [("part-1".to_string(), b"id,label\n".to_vec())]);
+        assert!(PluginOutput::open("missing", "x").is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use rand::Rng;
+
+/// A list of byte strings packed into a single contiguous arena.
+///
+/// Entry `i` lives at `bytes[offsets[i]..offsets[i + 1]]`, so picking a random
+/// entry touches one small offset table and one arena instead of chasing a
+/// pointer per `&str`.
+#[derive(Debug, Clone, Default)]
+pub struct StringPool {
+    bytes: Vec<u8>,
+    offsets: Vec<u32>,
+}
+
+impl StringPool {
+    /// Packs `items` into a new pool, preserving their order.
+    pub fn new<I, S>(items: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        let mut bytes = Vec::new();
+        let mut offsets = vec![0u32];
+        for item in items {
+            bytes.extend_from_slice(item.as_ref());
+            offsets.push(u32::try_from(bytes.len()).expect("string pool exceeds 4GiB"));
+        }
+        bytes.shrink_to_fit();
+        StringPool { bytes, offsets }
+    }
+
+    /// Number of entries in the pool.
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns entry `index`. Panics if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> &[u8] {
+        let start = self.offsets[index] as usize;
+        let end = self.offsets[index + 1] as usize;
+        &self.bytes[start..end]
+    }
+
+    /// Returns a uniformly chosen entry, or an empty slice if the pool is empty.
+    pub fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> &[u8] {
+        if self.is_empty() {
+            return &[];
+        }
+        self.get(rng.gen_range(0..self.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_keep_their_order_and_bytes() {
+        let pool = StringPool::new(["Liam", "", "Noah"]);
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.get(0), b"Liam");
+        assert_eq!(pool.get(1), b"");
+        assert_eq!(pool.get(2), b"Noah");
+    }
+
+    #[test]
+    fn choose_picks_an_entry() {
+        let pool = StringPool::new(["a", "b"]);
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            assert!([&b"a"[..], b"b"].contains(&pool.choose(&mut rng)));
+        }
+    }
+
+    #[test]
+    fn empty_pool_chooses_empty_slice() {
+        let pool = StringPool::new(Vec::<&str>::new());
+        assert!(pool.is_empty());
+        assert_eq!(pool.choose(&mut rand::thread_rng()), b"");
+    }
+}
@@ -0,0 +1,518 @@
+//! Built-in sets of related tables, sized by a scale factor.
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::BoxError;
+use crate::cli::{Args, PresetArgs};
+use crate::generator::Row;
+use crate::output::{Destination, Outputs};
+use crate::relational::{ColumnConfig, Config, FanOut, KindConfig, TableConfig, check_locations, table_location};
+use crate::schema::Column;
+use crate::sink::{self, Sink};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    #[value(help = "A `sales` fact table referencing `--star-dimensions` dimension tables")]
+    Star,
+    #[value(help = "The eight TPC-H tables, `lineitem` to `region`")]
+    Tpch,
+    #[value(help = "A `nodes` and an `edges` table with power-law degrees")]
+    Graph,
+    // Generated by [`crate::clickstream`] rather than as a [`Config`], since
+    // every row depends on the one before it.
+    #[value(help = "`users`, their `sessions` and the `events` of each session, through a shopping funnel")]
+    Clickstream,
+    // Generated by [`crate::entities`].
+    #[value(help = "The same people in `customers` and `accounts`, with variations, and the `ground_truth` of which records are whose")]
+    EntityResolution,
+    // Generated by [`crate::iot`].
+    #[value(help = "Sensor `devices` and their `readings`, with drift, gaps, stuck values and late deliveries")]
+    Iot,
+    // Generated by [`crate::transactions`].
+    #[value(help = "Card `accounts` and their `transactions`, some labeled as fraud")]
+    Transactions,
+    // Generated by [`crate::cdr`].
+    #[value(help = "Cell `towers`, `subscribers` and the call detail records of their `calls`")]
+    Cdr,
+    // Generated by [`crate::ads`].
+    #[value(help = "Ad `campaigns` and their `impressions`, `clicks` and `conversions`")]
+    Ads,
+    // Generated by [`crate::taxi`].
+    #[value(help = "NYC yellow taxi `trips` between `taxi_zones`, with fares that add up")]
+    Taxi,
+    // Generated by [`crate::hr`].
+    #[value(help = "`employees` under their managers, and their `departments`")]
+    Hr,
+    // Generated by [`crate::claims`].
+    #[value(help = "Synthetic `patients` and `providers`, and the `claims` and `claim_lines` of their encounters")]
+    Claims,
+    // Generated by [`crate::ml`].
+    #[value(help = "A matrix of numeric `samples` with a `label` that some of its `features` predict")]
+    Ml,
+}
+
+/// Builds the tables of `preset`. The presets generated by modules of their
+/// own have none.
+pub fn config(preset: Preset, args: &PresetArgs) -> Option<Config> {
+    match preset {
+        Preset::Star => Some(star(args.scale_factor, args.star_dimensions as usize)),
+        Preset::Tpch => Some(tpch(args.scale_factor)),
+        Preset::Graph => Some(graph(args)),
+        Preset::Clickstream
+        | Preset::EntityResolution
+        | Preset::Iot
+        | Preset::Transactions
+        | Preset::Cdr
+        | Preset::Ads
+        | Preset::Taxi
+        | Preset::Hr
+        | Preset::Claims
+        | Preset::Ml => None,
+    }
+}
+
+/// Checks that the `tables` of the preset `name`, which a module of its own
+/// generates in one pass, can be written to `--output`.
+pub(crate) fn check_tables(args: &Args, name: &str, tables: &[&str]) -> Result<(), BoxError> {
+    if args.shards > 1 {
+        return Err(format!("the {name} preset is generated as one shard").into());
+    }
+    check_locations(&args.output, tables.iter().copied())
+}
+
+/// Opens a sink for each of `tables`, of its `columns`, at `--output` with
+/// `{table}` replaced by the table's name, after [`check_tables`].
+pub(crate) fn open_tables<const N: usize>(
+    args: &Args,
+    name: &str,
+    tables: [&str; N],
+    columns: [Vec<Column>; N],
+) -> Result<[Box<dyn Sink>; N], BoxError> {
+    check_tables(args, name, &tables)?;
+    let outputs = Outputs::new(args);
+    let mut sinks = Vec::with_capacity(N);
+    for (table, columns) in tables.iter().zip(columns) {
+        sinks.push(sink::open(&outputs, &Destination::parse(&table_location(&args.output, table))?, &columns)?);
+    }
+    Ok(sinks.try_into().unwrap_or_else(|_| unreachable!("a sink per table")))
+}
+
+/// Finishes the `sinks` [`open_tables`] gave, saying how many `rows` went
+/// into each.
+pub(crate) fn finish_tables<const N: usize>(
+    args: &Args,
+    tables: [&str; N],
+    sinks: [Box<dyn Sink>; N],
+    rows: [u64; N],
+) -> Result<(), BoxError> {
+    for ((table, sink), rows) in tables.iter().zip(sinks).zip(rows) {
+        sink.finish()?;
+        println!("Successfully generated {} ({rows} rows)", table_location(&args.output, table));
+    }
+    Ok(())
+}
+
+pub(crate) fn push_int(row: &mut Row, value: u64) {
+    row.push(itoa::Buffer::new().format(value).as_bytes());
+}
+
+fn column(name: &str, kind: KindConfig) -> ColumnConfig {
+    ColumnConfig { name: name.to_string(), kind }
+}
+
+fn int(min: i64, max: i64) -> KindConfig {
+    KindConfig::Int { min, max }
+}
+
+fn choice(values: &[&str]) -> KindConfig {
+    KindConfig::Choice { values: values.iter().map(|v| v.to_string()).collect() }
+}
+
+fn reference(table: &str) -> KindConfig {
+    KindConfig::Reference { table: table.to_string(), matching: 1.0, skew: 0.0 }
+}
+
+fn decimal(min: f64, max: f64) -> KindConfig {
+    KindConfig::Decimal { min, max, scale: 2 }
+}
+
+fn date(min: &str, max: &str) -> KindConfig {
+    KindConfig::Date { min: min.to_string(), max: max.to_string() }
+}
+
+fn label(prefix: &str) -> KindConfig {
+    KindConfig::Label { prefix: prefix.to_string(), width: 9 }
+}
+
+/// Every combination of one word from each list, space-separated, the way
+/// TPC-H builds part types and containers.
+fn combinations(lists: &[&[&str]]) -> KindConfig {
+    let values = lists.iter().fold(vec![String::new()], |prefixes, words| {
+        prefixes
+            .iter()
+            .flat_map(|prefix| words.iter().map(move |word| format!("{prefix}{}{word}", if prefix.is_empty() { "" } else { " " })))
+            .collect()
+    });
+    KindConfig::Choice { values }
+}
+
+/// How a dimension's row count grows with the scale factor.
+enum Growth {
+    /// With the facts, like customers.
+    Linear,
+    /// With the square root, like products and stores, which grow far
+    /// slower than the sales of them.
+    Sqrt,
+    /// Not at all, like sales channels.
+    Fixed,
+}
+
+struct Dimension {
+    name: &'static str,
+    /// Rows at scale factor 1.
+    rows: u64,
+    growth: Growth,
+    attributes: fn() -> Vec<ColumnConfig>,
+}
+
+/// The dimensions in the order `--star-dimensions` takes them.
+const DIMENSIONS: &[Dimension] = &[
+    Dimension {
+        name: "customer",
+        rows: 100_000,
+        growth: Growth::Linear,
+        attributes: || vec![column("first_name", KindConfig::FirstName { locale: None }), column("age", int(18, 90))],
+    },
+    Dimension {
+        name: "product",
+        rows: 10_000,
+        growth: Growth::Sqrt,
+        attributes: || {
+            vec![
+                column("category", choice(&["grocery", "electronics", "apparel", "home", "toys", "beauty", "sports"])),
+                column("list_price_cents", int(99, 250_000)),
+            ]
+        },
+    },
+    Dimension {
+        name: "store",
+        rows: 200,
+        growth: Growth::Sqrt,
+        attributes: || {
+            vec![
+                column("region", choice(&["north", "south", "east", "west", "central"])),
+                column("square_feet", int(2_000, 120_000)),
+            ]
+        },
+    },
+    Dimension {
+        name: "promotion",
+        rows: 500,
+        growth: Growth::Sqrt,
+        attributes: || {
+            vec![
+                column("promotion_type", choice(&["none", "coupon", "bundle", "clearance", "loyalty"])),
+                column("discount_percent", int(0, 60)),
+            ]
+        },
+    },
+    Dimension {
+        name: "supplier",
+        rows: 1_000,
+        growth: Growth::Sqrt,
+        attributes: || vec![column("country", choice(&["US", "CN", "DE", "MX", "VN", "IN", "JP", "BR"]))],
+    },
+    Dimension {
+        name: "employee",
+        rows: 5_000,
+        growth: Growth::Sqrt,
+        attributes: || {
+            vec![
+                column("first_name", KindConfig::FirstName { locale: None }),
+                column("role", choice(&["cashier", "stocker", "manager", "associate"])),
+            ]
+        },
+    },
+    Dimension {
+        name: "channel",
+        rows: 4,
+        growth: Growth::Fixed,
+        attributes: || vec![column("channel_name", choice(&["store", "web", "mobile", "phone"]))],
+    },
+];
+
+/// The most dimensions the star preset has.
+pub const MAX_STAR_DIMENSIONS: u64 = DIMENSIONS.len() as u64;
+
+/// A `sales` fact table of a million rows per unit of `scale` and the first
+/// `dimensions` tables of [`DIMENSIONS`], every fact row referencing one row
+/// of each through its surrogate key.
+fn star(scale: f64, dimensions: usize) -> Config {
+    let scaled = |rows: u64, growth: &Growth| {
+        let factor = match growth {
+            Growth::Linear => scale,
+            Growth::Sqrt => scale.sqrt(),
+            Growth::Fixed => 1.0,
+        };
+        ((rows as f64 * factor).round() as u64).max(1)
+    };
+    let dimensions = &DIMENSIONS[..dimensions];
+    let mut tables: Vec<_> = dimensions
+        .iter()
+        .map(|d| {
+            let mut columns = vec![column(&format!("{}_key", d.name), KindConfig::Sequence { start: 1 })];
+            columns.extend((d.attributes)());
+            TableConfig {
+                name: format!("dim_{}", d.name),
+                rows: Some(scaled(d.rows, &d.growth)),
+                parent: None,
+                fan_out: None,
+                scd2: None,
+                columns,
+            }
+        })
+        .collect();
+
+    let mut facts = vec![column("sale_id", KindConfig::Sequence { start: 1 })];
+    facts.extend(
+        dimensions
+            .iter()
+            .map(|d| column(&format!("{}_key", d.name), reference(&format!("dim_{}", d.name)))),
+    );
+    facts.push(column("quantity", int(1, 12)));
+    facts.push(column("amount_cents", int(99, 500_000)));
+    tables.push(TableConfig {
+        name: "fact_sales".to_string(),
+        rows: Some(scaled(1_000_000, &Growth::Linear)),
+        parent: None,
+        fan_out: None,
+        scd2: None,
+        columns: facts,
+    });
+    Config { tables }
+}
+
+const TPCH_NATIONS: &[&str] = &[
+    "ALGERIA", "ARGENTINA", "BRAZIL", "CANADA", "EGYPT", "ETHIOPIA", "FRANCE", "GERMANY", "INDIA", "INDONESIA", "IRAN",
+    "IRAQ", "JAPAN", "JORDAN", "KENYA", "MOROCCO", "MOZAMBIQUE", "PERU", "CHINA", "ROMANIA", "SAUDI ARABIA", "VIETNAM",
+    "RUSSIA", "UNITED KINGDOM", "UNITED STATES",
+];
+
+/// The TPC-H schema with its cardinalities at `scale`: `region` and `nation`
+/// are fixed, the other tables grow linearly. Orders per customer and lines
+/// per order follow `dbgen`'s averages, and keys, names and value ranges its
+/// specification; the free-text comment columns are left out, and dates are
+/// drawn independently rather than ship after order after commit.
+fn tpch(scale: f64) -> Config {
+    let scaled = |rows: u64| ((rows as f64 * scale).round() as u64).max(1);
+    let table = |name: &str, rows: Option<u64>, parent: Option<(&str, FanOut)>, columns: Vec<ColumnConfig>| TableConfig {
+        name: name.to_string(),
+        rows,
+        parent: parent.map(|(p, _)| p.to_string()),
+        fan_out: parent.map(|(_, f)| f),
+        scd2: None,
+        columns,
+    };
+    // TPC-H numbers regions and nations from 0 and everything else from 1.
+    let sequence = |start| KindConfig::Sequence { start };
+    let nations = TPCH_NATIONS.iter().map(|n| n.to_string()).collect();
+    let regions = ["AFRICA", "AMERICA", "ASIA", "EUROPE", "MIDDLE EAST"].iter().map(|r| r.to_string()).collect();
+    let segments = ["AUTOMOBILE", "BUILDING", "FURNITURE", "HOUSEHOLD", "MACHINERY"];
+    let priorities = ["1-URGENT", "2-HIGH", "3-MEDIUM", "4-NOT SPECIFIED", "5-LOW"];
+    let manufacturers = ["Manufacturer#1", "Manufacturer#2", "Manufacturer#3", "Manufacturer#4", "Manufacturer#5"];
+    let brands: Vec<String> = (1..=5).flat_map(|m| (1..=5).map(move |n| format!("Brand#{m}{n}"))).collect();
+    let types = combinations(&[
+        &["STANDARD", "SMALL", "MEDIUM", "LARGE", "ECONOMY", "PROMO"],
+        &["ANODIZED", "BURNISHED", "PLATED", "POLISHED", "BRUSHED"],
+        &["TIN", "NICKEL", "BRASS", "STEEL", "COPPER"],
+    ]);
+    let containers = combinations(&[
+        &["SM", "LG", "MED", "JUMBO", "WRAP"],
+        &["CASE", "BOX", "BAG", "JAR", "PKG", "PACK", "CAN", "DRUM"],
+    ]);
+
+    let tables = vec![
+        table("region", Some(5), None, vec![
+            column("r_regionkey", sequence(0)),
+            column("r_name", KindConfig::Cycle { values: regions }),
+        ]),
+        table("nation", Some(25), None, vec![
+            column("n_nationkey", sequence(0)),
+            column("n_name", KindConfig::Cycle { values: nations }),
+            column("n_regionkey", reference("region")),
+        ]),
+        table("supplier", Some(scaled(10_000)), None, vec![
+            column("s_suppkey", sequence(1)),
+            column("s_name", label("Supplier#")),
+            column("s_nationkey", reference("nation")),
+            column("s_acctbal", decimal(-999.99, 9_999.99)),
+        ]),
+        table("part", Some(scaled(200_000)), None, vec![
+            column("p_partkey", sequence(1)),
+            column("p_mfgr", choice(&manufacturers)),
+            column("p_brand", KindConfig::Choice { values: brands }),
+            column("p_type", types),
+            column("p_size", int(1, 50)),
+            column("p_container", containers),
+            column("p_retailprice", decimal(900.00, 2_098.99)),
+        ]),
+        table("partsupp", None, Some(("part", FanOut::Fixed { count: 4 })), vec![
+            column("ps_partkey", KindConfig::ParentKey),
+            column("ps_suppkey", reference("supplier")),
+            column("ps_availqty", int(1, 9_999)),
+            column("ps_supplycost", decimal(1.00, 1_000.00)),
+        ]),
+        table("customer", Some(scaled(150_000)), None, vec![
+            column("c_custkey", sequence(1)),
+            column("c_name", label("Customer#")),
+            column("c_nationkey", reference("nation")),
+            column("c_acctbal", decimal(-999.99, 9_999.99)),
+            column("c_mktsegment", choice(&segments)),
+        ]),
+        table("orders", None, Some(("customer", FanOut::Poisson { mean: 10.0 })), vec![
+            column("o_orderkey", sequence(1)),
+            column("o_custkey", KindConfig::ParentKey),
+            column("o_orderstatus", choice(&["F", "O", "P"])),
+            column("o_totalprice", decimal(857.71, 555_285.16)),
+            column("o_orderdate", date("1992-01-01", "1998-08-02")),
+            column("o_orderpriority", choice(&priorities)),
+            column("o_shippriority", int(0, 0)),
+        ]),
+        table("lineitem", None, Some(("orders", FanOut::Uniform { min: 1, max: 7 })), vec![
+            column("l_orderkey", KindConfig::ParentKey),
+            column("l_partkey", reference("part")),
+            column("l_suppkey", reference("supplier")),
+            column("l_linenumber", KindConfig::ChildNumber),
+            column("l_quantity", decimal(1.00, 50.00)),
+            column("l_extendedprice", decimal(901.00, 104_949.50)),
+            column("l_discount", decimal(0.00, 0.10)),
+            column("l_tax", decimal(0.00, 0.08)),
+            column("l_returnflag", choice(&["R", "A", "N"])),
+            column("l_linestatus", choice(&["O", "F"])),
+            column("l_shipdate", date("1992-01-02", "1998-12-01")),
+            column("l_commitdate", date("1992-01-31", "1998-10-31")),
+            column("l_receiptdate", date("1992-01-03", "1998-12-31")),
+            column("l_shipinstruct", choice(&["DELIVER IN PERSON", "COLLECT COD", "NONE", "TAKE BACK RETURN"])),
+            column("l_shipmode", choice(&["REG AIR", "AIR", "RAIL", "SHIP", "TRUCK", "MAIL", "FOB"])),
+        ]),
+    ];
+    Config { tables }
+}
+
+/// A hundred thousand `nodes` per unit of scale and their `edges`. Out-degrees
+/// follow a power law with `--graph-exponent`, and so do in-degrees: targets
+/// are drawn with a skew of `1 / (exponent - 1)` over the node keys, which
+/// makes the lowest keys the hubs. Every edge joins two existing nodes;
+/// self-loops and repeated edges are possible, as in a multigraph.
+fn graph(args: &PresetArgs) -> Config {
+    let nodes = ((100_000.0 * args.scale_factor).round() as u64).max(1);
+    let mut edges = vec![
+        column("edge_id", KindConfig::Sequence { start: 1 }),
+        column("source_id", KindConfig::ParentKey),
+        column(
+            "target_id",
+            KindConfig::Reference { table: "nodes".to_string(), matching: 1.0, skew: 1.0 / (args.graph_exponent - 1.0) },
+        ),
+        column("relation", choice(&["follows", "likes", "mentions", "replies"])),
+    ];
+    if args.graph_weights {
+        edges.push(column("weight", KindConfig::Decimal { min: 0.0, max: 1.0, scale: 4 }));
+    }
+    if args.graph_timestamps {
+        let time = |s: &str| s.to_string();
+        edges.push(column("created_at", KindConfig::Timestamp { min: time("2020-01-01"), max: time("2024-12-31 23:59:59") }));
+    }
+    let tables = vec![
+        TableConfig {
+            name: "nodes".to_string(),
+            rows: Some(nodes),
+            parent: None,
+            fan_out: None,
+            scd2: None,
+            columns: vec![
+                column("node_id", KindConfig::Sequence { start: 1 }),
+                column("label", choice(&["person", "organization", "place", "topic"])),
+                column("name", KindConfig::FirstName { locale: None }),
+            ],
+        },
+        TableConfig {
+            name: "edges".to_string(),
+            rows: None,
+            parent: Some("nodes".to_string()),
+            fan_out: Some(FanOut::PowerLaw { exponent: args.graph_exponent, min: 1, max: nodes.min(10_000) }),
+            scd2: None,
+            columns: edges,
+        },
+    ];
+    Config { tables }
+}
+
+/// What the tests of the presets with modules of their own share.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// An empty directory for the test `name` to write its tables to.
+    pub fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// The records of the CSV file at `path`, after its header.
+    pub fn read(path: impl AsRef<Path>) -> Vec<Vec<String>> {
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        reader.records().map(|r| r.unwrap().iter().map(String::from).collect()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn star_tables_scale() {
+        let config = star(4.0, 3);
+        let rows: Vec<_> = config.tables.iter().map(|t| (t.name.as_str(), t.rows.unwrap())).collect();
+        assert_eq!(
+            rows,
+            [("dim_customer", 400_000), ("dim_product", 20_000), ("dim_store", 400), ("fact_sales", 4_000_000)]
+        );
+        let facts = config.tables.last().unwrap();
+        let names: Vec<_> = facts.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["sale_id", "customer_key", "product_key", "store_key", "quantity", "amount_cents"]);
+        assert!(config.generation_order().is_ok());
+    }
+
+    #[test]
+    fn tpch_tables_scale() {
+        let config = tpch(10.0);
+        let rows: Vec<_> = config.tables.iter().map(|t| (t.name.as_str(), t.rows)).collect();
+        assert_eq!(rows[..4], [("region", Some(5)), ("nation", Some(25)), ("supplier", Some(100_000)), ("part", Some(2_000_000))]);
+        let order: Vec<_> = config.generation_order().unwrap().iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(order, ["region", "nation", "supplier", "part", "partsupp", "customer", "orders", "lineitem"]);
+        let KindConfig::Choice { values } = &config.tables[3].columns[3].kind else { panic!("p_type is a choice") };
+        assert_eq!((values.len(), values[0].as_str()), (150, "STANDARD ANODIZED TIN"));
+    }
+
+    #[test]
+    fn graph_columns_follow_the_flags() {
+        let args = crate::cli::Args::parse_from(["gen", "--preset", "graph", "--graph-weights", "--scale-factor", "0.5"]);
+        let config = graph(&args.preset);
+        assert_eq!(config.tables[0].rows, Some(50_000));
+        let edges: Vec<_> = config.tables[1].columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(edges, ["edge_id", "source_id", "target_id", "relation", "weight"]);
+        assert!(config.generation_order().is_ok());
+    }
+
+    #[test]
+    fn tiny_scales_keep_every_dimension() {
+        let config = star(0.000_001, DIMENSIONS.len());
+        assert!(config.tables.iter().all(|t| t.rows.unwrap() >= 1));
+    }
+}
@@ -0,0 +1,147 @@
+//! The `preview` subcommand: the first rows of what a run would generate,
+//! printed rather than written, to see what a schema or config makes before
+//! generating all of it. Config tables are shown parents first, and each
+//! child table holds the children of the parent rows shown.
+
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+
+use crate::cli::{Args, PreviewArgs};
+use crate::generator::{Generator, Row};
+use crate::schema::Column;
+use crate::sink::Sink;
+use crate::{BoxError, presets, relational, shard_rng};
+
+/// The widest a value is shown in an aligned table, in characters.
+const MAX_WIDTH: usize = 32;
+
+/// The first rows of a table.
+pub(crate) struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub rows: Vec<Row>,
+}
+
+/// Keeps the first rows written to it, and drops the rest.
+pub(crate) struct Head {
+    rows: Vec<Row>,
+    head: usize,
+}
+
+impl Head {
+    pub(crate) fn new(head: u64) -> Self {
+        Head { rows: Vec::new(), head: head as usize }
+    }
+
+    pub(crate) fn into_rows(self) -> Vec<Row> {
+        self.rows
+    }
+}
+
+impl Sink for Head {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        if self.rows.len() < self.head {
+            self.rows.push(row.clone());
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        0
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        Ok(())
+    }
+}
+
+/// `value`, cut to [`MAX_WIDTH`] characters with an ellipsis if longer.
+fn shown(value: &[u8]) -> String {
+    let value = String::from_utf8_lossy(value);
+    if value.chars().count() <= MAX_WIDTH {
+        return value.into_owned();
+    }
+    value.chars().take(MAX_WIDTH - 1).chain(['…']).collect()
+}
+
+/// `rows` of `columns` as a table of aligned columns under their names.
+fn aligned(columns: &[Column], rows: &[Row]) -> String {
+    let mut cells = vec![columns.iter().map(|c| shown(c.name.as_bytes())).collect::<Vec<_>>()];
+    cells.extend(rows.iter().map(|row| row.fields().map(shown).collect()));
+    let widths: Vec<usize> =
+        (0..columns.len()).map(|i| cells.iter().map(|line| line[i].chars().count()).max().unwrap_or(0)).collect();
+    let mut table = String::new();
+    for line in &cells {
+        let padded: Vec<_> = line.iter().zip(&widths).map(|(cell, &width)| format!("{cell:width$}")).collect();
+        table.push_str(padded.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Prints the first `--head` rows of each table `args` would generate.
+pub fn run(args: &Args, preview: &PreviewArgs) -> Result<(), BoxError> {
+    let config = match (&args.config, args.preset.preset) {
+        (Some(path), _) => Some(relational::Config::load(path)?),
+        (None, Some(preset)) => Some(presets::config(preset, &args.preset).ok_or_else(|| {
+            let name = preset.to_possible_value().expect("no preset is skipped").get_name().to_string();
+            format!("preview shows tables of --schema, --config and the related-table presets, which --preset {name} isn't")
+        })?),
+        (None, None) => None,
+    };
+    let tables = match config {
+        Some(config) => relational::preview(args, &config, preview.head)?,
+        None => {
+            let columns = crate::columns(args)?;
+            let generator = Generator::compile(&columns)?;
+            let rows = generator.rows(shard_rng(args.seed, 0)).take(preview.head as usize).collect();
+            vec![Table { name: args.schema.clone(), columns, rows }]
+        }
+    };
+    let mut out = io::stdout().lock();
+    for (i, Table { name, columns, rows }) in tables.iter().enumerate() {
+        if preview.raw {
+            if tables.len() > 1 {
+                writeln!(out, "{}# {name}", if i > 0 { "\n" } else { "" })?;
+            }
+            let mut csv = csv::Writer::from_writer(&mut out);
+            csv.write_record(columns.iter().map(|c| &c.name))?;
+            for row in rows {
+                csv.write_record(row.fields())?;
+            }
+            csv.flush()?;
+        } else {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{name}: the first {} rows", rows.len())?;
+            write!(out, "{}", aligned(columns, rows))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnKind;
+
+    #[test]
+    fn tables_line_values_up_under_their_names() {
+        let columns = [Column::new("id", ColumnKind::Sequence { start: 1 }), Column::new("n", ColumnKind::IntRange { min: 0, max: 9 })];
+        let mut rows = vec![Row::new(), Row::new()];
+        rows[0].push(b"1");
+        rows[0].push(b"123456");
+        rows[1].push(b"10");
+        rows[1].push(&[b'x'; 40]);
+        let lines: Vec<_> = aligned(&columns, &rows).lines().map(str::to_string).collect();
+        assert_eq!(lines[0], "id  n");
+        assert_eq!(lines[1], "1   123456");
+        assert_eq!(lines[2], format!("10  {}…", "x".repeat(31)));
+    }
+}
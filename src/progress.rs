@@ -0,0 +1,188 @@
+//! Progress reports on demand, for runs started without a terminal to watch:
+//! SIGUSR1 prints one to standard error, and connecting to
+//! `--control-socket` reads one. A report has the rows and bytes written,
+//! the rate, and how much longer the run should take once its size is known.
+//!
+//! A run can also be paused, with SIGUSR2 or by sending `pause` to the
+//! socket, to give its disk or network bandwidth back for a while. Each
+//! output flushes what it has and waits before its next row until SIGUSR2
+//! or `resume`.
+
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::metrics::{self, SHARDS_QUEUED, SHARDS_RUNNING};
+use crate::{BoxError, GB, signal};
+
+/// How often the reporter looks for signals, and paused outputs for a resume.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long the control socket waits for a command before it sends a report.
+#[cfg(unix)]
+const COMMAND_TIMEOUT: Duration = Duration::from_millis(200);
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// When the current pause began, and how long earlier ones lasted.
+static PAUSES: Mutex<(Option<Instant>, Duration)> = Mutex::new((None, Duration::ZERO));
+
+/// The `--size` and `--rows` the run stops at, or 0 if it has none.
+static TARGET_BYTES: AtomicU64 = AtomicU64::new(0);
+static TARGET_ROWS: AtomicU64 = AtomicU64::new(0);
+
+/// Starts answering SIGUSR1 and SIGUSR2, and connections to
+/// `control_socket` if it's given.
+pub(crate) fn start(control_socket: Option<&Path>) -> Result<(), BoxError> {
+    STARTED.get_or_init(Instant::now);
+    if let Some(path) = control_socket {
+        listen(path)?;
+    }
+    thread::spawn(|| {
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            if signal::report_requested() {
+                eprintln!("{}", report());
+            }
+            if signal::pause_toggled() {
+                eprintln!("{}", set_paused(!paused()));
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+fn listen(path: &Path) -> Result<(), BoxError> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    // A socket left behind by an earlier run that didn't get to remove it.
+    if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path).map_err(|e| format!("--control-socket {}: {e}", path.display()))?;
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            // A client that only reads gets a report.
+            let mut command = String::new();
+            let _ = stream.set_read_timeout(Some(COMMAND_TIMEOUT));
+            let _ = BufReader::new(&stream).read_line(&mut command);
+            let answer = match command.trim() {
+                "" | "status" => report(),
+                "pause" => set_paused(true),
+                "resume" => set_paused(false),
+                other => format!("unknown command {other:?}; expected status, pause or resume"),
+            };
+            // A client that leaves without reading misses nothing else.
+            let _ = writeln!(stream, "{answer}");
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn listen(_: &Path) -> Result<(), BoxError> {
+    Err("--control-socket needs Unix domain sockets".into())
+}
+
+/// Whether the run is paused.
+pub(crate) fn paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+/// Pauses or resumes the run, returning what happened.
+fn set_paused(pause: bool) -> String {
+    let mut pauses = PAUSES.lock().unwrap();
+    match (pause, pauses.0) {
+        (true, None) => pauses.0 = Some(Instant::now()),
+        (false, Some(since)) => *pauses = (None, pauses.1 + since.elapsed()),
+        (true, Some(_)) => return "Already paused".to_string(),
+        (false, None) => return "Not paused".to_string(),
+    }
+    PAUSED.store(pause, Ordering::Relaxed);
+    if pause { "Paused; outputs flush and wait before their next row" } else { "Resumed" }.to_string()
+}
+
+/// Blocks while the run is paused, unless a signal asks it to stop.
+pub(crate) fn wait_while_paused() {
+    while paused() && !signal::interrupted() {
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// How long the run has been generating, leaving out pauses.
+fn running_for() -> Duration {
+    let elapsed = STARTED.get().map_or(Duration::ZERO, Instant::elapsed);
+    let (since, earlier) = *PAUSES.lock().unwrap();
+    elapsed.saturating_sub(earlier + since.map_or(Duration::ZERO, |since| since.elapsed()))
+}
+
+/// Sets what the run is expected to write, for the share done and the time
+/// left.
+pub(crate) fn expect(bytes: Option<u64>, rows: Option<u64>) {
+    TARGET_BYTES.store(bytes.unwrap_or(0), Ordering::Relaxed);
+    TARGET_ROWS.store(rows.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// The share of the run done, whichever of its limits is nearest.
+fn done(rows: u64, bytes: u64) -> Option<f64> {
+    let share = |done: u64, target: &AtomicU64| match target.load(Ordering::Relaxed) {
+        0 => None,
+        target => Some((done as f64 / target as f64).min(1.0)),
+    };
+    match (share(bytes, &TARGET_BYTES), share(rows, &TARGET_ROWS)) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// `1:02:03`.
+pub(crate) fn clock(d: Duration) -> String {
+    let s = d.as_secs();
+    format!("{}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+}
+
+/// One line on how the run is going.
+pub(crate) fn report() -> String {
+    let (rows, bytes) = metrics::totals();
+    let elapsed = running_for();
+    let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+    let mut report = format!(
+        "Progress: {rows} rows, {:.2}GB in {}, {:.0} rows/s, {:.1}MB/s",
+        bytes as f64 / GB,
+        clock(elapsed),
+        rows as f64 / seconds,
+        bytes as f64 / seconds / (1 << 20) as f64,
+    );
+    if let Some(done) = done(rows, bytes).filter(|&done| done > 0.0) {
+        let left = elapsed.mul_f64((1.0 - done) / done);
+        let _ = write!(report, "; {:.1}% done, about {} left", done * 100.0, clock(left));
+    }
+    let (running, queued) = (SHARDS_RUNNING.load(Ordering::Relaxed), SHARDS_QUEUED.load(Ordering::Relaxed));
+    if running + queued > 0 {
+        let _ = write!(report, "; {running} shard(s) running, {queued} queued");
+    }
+    if paused() {
+        report.push_str("; paused");
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_clock_times() {
+        assert_eq!(clock(Duration::from_secs(59)), "0:00:59");
+        assert_eq!(clock(Duration::from_secs(3723)), "1:02:03");
+        assert_eq!(clock(Duration::from_secs(100 * 3600)), "100:00:00");
+    }
+}
@@ -0,0 +1,229 @@
+//! Encodings for sinks that send each row as its own record.
+
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::BoxError;
+use crate::access_log;
+use crate::generator::Row;
+use crate::schema::{Column, DataType, parse_date, parse_timestamp};
+use crate::sink::csv_record;
+
+/// How a row is serialized into a record.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordFormat {
+    /// One CSV line, without a terminator.
+    Csv,
+    /// A JSON object keyed by column name.
+    Json,
+    /// Avro binary, framed for Schema Registry when a schema id is known.
+    Avro,
+    /// An Apache combined log line, of the columns of `--schema access_log`.
+    Combined,
+}
+
+/// Serializes rows in one [`RecordFormat`].
+pub struct RecordEncoder {
+    format: RecordFormat,
+    names: Vec<String>,
+    types: Vec<DataType>,
+    /// Prefixes Avro records with the Confluent wire format header.
+    schema_id: Option<u32>,
+    /// Where the fields of combined log lines are, or why they can't be made.
+    combined: Result<Vec<usize>, String>,
+}
+
+impl RecordEncoder {
+    pub fn new(format: RecordFormat, columns: &[Column], schema_id: Option<u32>) -> Self {
+        let names: Vec<_> = columns.iter().map(|c| c.name.clone()).collect();
+        let combined = match format {
+            RecordFormat::Combined => access_log::field_indices(&names),
+            _ => Ok(Vec::new()),
+        };
+        RecordEncoder { format, names, types: columns.iter().map(|c| c.kind.data_type()).collect(), schema_id, combined }
+    }
+
+    pub fn encode(&self, row: &Row) -> Result<Vec<u8>, BoxError> {
+        match self.format {
+            RecordFormat::Csv => Ok(csv_record(row)),
+            RecordFormat::Json => self.json(row),
+            RecordFormat::Avro => self.avro(row),
+            RecordFormat::Combined => access_log::combined_line(row, self.combined.as_ref().map_err(|e| e.clone())?),
+        }
+    }
+
+    fn json(&self, row: &Row) -> Result<Vec<u8>, BoxError> {
+        let mut out = vec![b'{'];
+        for (i, ((field, name), data_type)) in row.fields().zip(&self.names).zip(&self.types).enumerate() {
+            if i > 0 {
+                out.push(b',');
+            }
+            serde_json::to_writer(&mut out, name)?;
+            out.push(b':');
+            if data_type.is_null(field) {
+                out.extend_from_slice(b"null");
+                continue;
+            }
+            match data_type {
+                DataType::String => serde_json::to_writer(&mut out, std::str::from_utf8(field)?)?,
+                // Integers and decimals are generated as plain decimal, which is valid JSON.
+                DataType::Integer | DataType::Decimal { .. } => out.extend_from_slice(field),
+                DataType::Date | DataType::Timestamp => serde_json::to_writer(&mut out, std::str::from_utf8(field)?)?,
+            }
+        }
+        out.push(b'}');
+        Ok(out)
+    }
+
+    fn avro(&self, row: &Row) -> Result<Vec<u8>, BoxError> {
+        let mut out = Vec::new();
+        if let Some(id) = self.schema_id {
+            out.push(0);
+            out.extend_from_slice(&id.to_be_bytes());
+        }
+        for (field, data_type) in row.fields().zip(&self.types) {
+            // Every field is a union of null and its type.
+            if data_type.is_null(field) {
+                avro_long(0, &mut out);
+                continue;
+            }
+            avro_long(1, &mut out);
+            match data_type {
+                DataType::String | DataType::Decimal { .. } => {
+                    avro_long(field.len() as i64, &mut out);
+                    out.extend_from_slice(field);
+                }
+                DataType::Integer => avro_long(std::str::from_utf8(field)?.parse()?, &mut out),
+                DataType::Date => {
+                    let field = std::str::from_utf8(field)?;
+                    avro_long(parse_date(field).ok_or_else(|| format!("{field} isn't a date"))?, &mut out);
+                }
+                DataType::Timestamp => {
+                    let field = std::str::from_utf8(field)?;
+                    let seconds = parse_timestamp(field).ok_or_else(|| format!("{field} isn't a timestamp"))?;
+                    avro_long(seconds * 1000, &mut out);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Turns a row into the bytes of one record. Closures taking a row are
+/// encoders too, for formats of the caller's own.
+pub trait Encode: Send {
+    fn encode(&self, row: &Row) -> Result<Vec<u8>, BoxError>;
+}
+
+impl Encode for RecordEncoder {
+    fn encode(&self, row: &Row) -> Result<Vec<u8>, BoxError> {
+        RecordEncoder::encode(self, row)
+    }
+}
+
+impl<F: Fn(&Row) -> Result<Vec<u8>, BoxError> + Send> Encode for F {
+    fn encode(&self, row: &Row) -> Result<Vec<u8>, BoxError> {
+        self(row)
+    }
+}
+
+/// Writes a zigzag varint, Avro's encoding for `int` and `long`.
+fn avro_long(value: i64, out: &mut Vec<u8>) {
+    let mut n = ((value << 1) ^ (value >> 63)) as u64;
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// The Avro schema of the records [`RecordEncoder`] writes for `columns`,
+/// every field of which may be null.
+pub fn avro_schema(columns: &[Column]) -> Value {
+    let fields: Vec<Value> = columns
+        .iter()
+        .map(|c| {
+            // Decimals stay strings: Avro's decimal logical type is two's
+            // complement bytes, which few consumers decode.
+            let avro_type = match c.kind.data_type() {
+                DataType::String | DataType::Decimal { .. } => json!("string"),
+                DataType::Integer => json!("long"),
+                DataType::Date => json!({ "type": "int", "logicalType": "date" }),
+                DataType::Timestamp => json!({ "type": "long", "logicalType": "timestamp-millis" }),
+            };
+            json!({ "name": c.name, "type": ["null", avro_type], "default": null })
+        })
+        .collect();
+    json!({ "type": "record", "name": "Row", "namespace": "csv_data_generator", "fields": fields })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnKind;
+
+    fn columns() -> Vec<Column> {
+        vec![
+            Column::new("name", ColumnKind::Choice(vec!["Mia".into()])),
+            Column::new("age", ColumnKind::IntRange { min: -100, max: 100 }),
+        ]
+    }
+
+    fn row(name: &str, age: &str) -> Row {
+        let mut row = Row::new();
+        row.push(name.as_bytes());
+        row.push(age.as_bytes());
+        row
+    }
+
+    #[test]
+    fn encodes_json_objects() {
+        let encoder = RecordEncoder::new(RecordFormat::Json, &columns(), None);
+        assert_eq!(encoder.encode(&row("M\"a", "-3")).unwrap(), br#"{"name":"M\"a","age":-3}"#);
+        assert_eq!(encoder.encode(&row("", "")).unwrap(), br#"{"name":"","age":null}"#);
+    }
+
+    #[test]
+    fn encodes_csv_lines() {
+        let encoder = RecordEncoder::new(RecordFormat::Csv, &columns(), None);
+        assert_eq!(encoder.encode(&row("Mia", "42")).unwrap(), b"Mia,42");
+    }
+
+    #[test]
+    fn encodes_avro_binary() {
+        let encoder = RecordEncoder::new(RecordFormat::Avro, &columns(), None);
+        assert_eq!(encoder.encode(&row("Mia", "42")).unwrap(), [2, 6, b'M', b'i', b'a', 2, 84]);
+        assert_eq!(encoder.encode(&row("", "-1")).unwrap(), [2, 0, 2, 1]);
+        assert_eq!(encoder.encode(&row("", "")).unwrap(), [2, 0, 0]);
+    }
+
+    #[test]
+    fn frames_avro_for_schema_registry() {
+        let encoder = RecordEncoder::new(RecordFormat::Avro, &columns(), Some(258));
+        assert_eq!(encoder.encode(&row("", "0")).unwrap(), [0, 0, 0, 1, 2, 2, 0, 2, 0]);
+    }
+
+    #[test]
+    fn zigzag_varints() {
+        for (value, expected) in [(0, vec![0]), (-1, vec![1]), (1, vec![2]), (-64, vec![127]), (64, vec![128, 1])] {
+            let mut out = Vec::new();
+            avro_long(value, &mut out);
+            assert_eq!(out, expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn describes_columns_as_an_avro_record() {
+        let schema = avro_schema(&columns());
+        assert_eq!(schema["type"], "record");
+        assert_eq!(
+            schema["fields"],
+            json!([
+                { "name": "name", "type": ["null", "string"], "default": null },
+                { "name": "age", "type": ["null", "long"], "default": null },
+            ])
+        );
+    }
+}
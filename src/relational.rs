@@ -0,0 +1,1414 @@
+//! Several related tables generated in one run from a TOML config, with
+//! foreign keys that always point at rows of the parent table.
+//!
+//! Keys are sequences, so a table of `n` rows has the keys `start..start + n`
+//! and other tables can reference it knowing only its row count. Child tables
+//! are generated parent row by parent row: each parent key gets a number of
+//! children drawn from the table's fan-out distribution.
+//!
+//! With `--shards`, each table is split into that many files. The children of
+//! a parent row go to the parent's shard, and each parent's fan-out is drawn
+//! from a generator seeded by the run, the table and the parent's index, so
+//! every table's row count and where each of its shards starts are worked out
+//! before any row is written. Shards are then generated independently, and
+//! their keys are still one sequence that every reference falls within.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+use rand::rngs::ThreadRng;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::BoxError;
+use crate::business;
+use crate::cli::Args;
+use crate::geo;
+use crate::generator::{Generator, Row, RowKeys, push_date, sample_power_law};
+use crate::locale::{Field, LocaleMix};
+use crate::national_id::Country;
+use crate::output::{self, Destination, Outputs};
+use crate::preview::{Head, Table};
+use crate::schema::{self, Column, ColumnKind, DataType, Lag, UnicodeMix};
+#[cfg(feature = "script")]
+use crate::script::Script;
+use crate::shard_sizes::ShardSizes;
+use crate::signal;
+use crate::sink::{self, Sink};
+use crate::telemetry;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(rename = "table")]
+    pub tables: Vec<TableConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableConfig {
+    pub name: String,
+    /// Row count of a table without a parent.
+    pub rows: Option<u64>,
+    /// The table whose rows this table's rows are children of.
+    pub parent: Option<String>,
+    /// How many children each parent row gets.
+    pub fan_out: Option<FanOut>,
+    /// Keep a type 2 history of each row, as several versions.
+    pub scd2: Option<Scd2>,
+    #[serde(rename = "column")]
+    pub columns: Vec<ColumnConfig>,
+}
+
+// No `deny_unknown_fields` here: serde doesn't support it next to `flatten`,
+// and the kind rejects unknown fields itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: KindConfig,
+}
+
+/// The generator of a column, picked by its `kind`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum KindConfig {
+    /// The table's key: `start`, `start + 1`, ...
+    Sequence {
+        #[serde(default = "one")]
+        start: i64,
+    },
+    /// The key of the parent row.
+    ParentKey,
+    /// The key of a uniformly chosen row of `table`, for a `matching`
+    /// fraction of the rows; the others get keys no row of `table` has.
+    /// With a `skew`, rows are chosen by a power law of their position
+    /// instead, so the first rows are referenced the most.
+    Reference {
+        table: String,
+        #[serde(default = "all")]
+        matching: f64,
+        #[serde(default)]
+        skew: f64,
+    },
+    Int { min: i64, max: i64 },
+    Choice { values: Vec<String> },
+    /// People's names, addresses and phone numbers in `locale`, which
+    /// defaults to `--locale`, or in a mix of locales given as a table of
+    /// weights, like `{ en = 60, zh = 40 }`.
+    FirstName { locale: Option<LocaleMix> },
+    LastName { locale: Option<LocaleMix> },
+    FullName { locale: Option<LocaleMix> },
+    StreetAddress { locale: Option<LocaleMix> },
+    City { locale: Option<LocaleMix> },
+    Postcode { locale: Option<LocaleMix> },
+    Phone { locale: Option<LocaleMix> },
+    /// A company name with a legal suffix, like `Apex Logistics GmbH`.
+    Company,
+    Industry,
+    JobTitle,
+    /// The path of a division, department or team `depth` levels down, from
+    /// 1 to 3, joined by `separator`.
+    Department {
+        #[serde(default = "whole_path")]
+        depth: usize,
+        #[serde(default = "slash")]
+        separator: String,
+    },
+    /// A national identifier of `country`, like a US Social Security number,
+    /// with valid check digits and, unless `synthetic` is false, from a
+    /// range that is never issued where the country has one.
+    NationalId {
+        country: Country,
+        #[serde(default = "yes")]
+        synthetic: bool,
+    },
+    /// A point's latitude or longitude, its geohash of `precision`
+    /// characters, or a WKT point, or polygon of `vertices` corners within
+    /// `radius` metres of it. Points are in `country`, one of the codes
+    /// [`geo::country`] knows, or within `bounds` given as west, south, east
+    /// and north in degrees, and anywhere without either; the columns of a
+    /// row in the same area share one point.
+    Latitude {
+        country: Option<String>,
+        bounds: Option<[f64; 4]>,
+    },
+    Longitude {
+        country: Option<String>,
+        bounds: Option<[f64; 4]>,
+    },
+    Geohash {
+        #[serde(default = "seven")]
+        precision: usize,
+        country: Option<String>,
+        bounds: Option<[f64; 4]>,
+    },
+    Point {
+        country: Option<String>,
+        bounds: Option<[f64; 4]>,
+    },
+    Polygon {
+        #[serde(default = "six")]
+        vertices: usize,
+        #[serde(default = "five_hundred")]
+        radius: f64,
+        country: Option<String>,
+        bounds: Option<[f64; 4]>,
+    },
+    Sha256Hex,
+    UuidV4,
+    /// The row's position among its parent's children, from 1.
+    ChildNumber,
+    /// `prefix` followed by the row number, zero-padded to `width` digits.
+    Label {
+        prefix: String,
+        #[serde(default = "nine")]
+        width: usize,
+    },
+    /// `values` in turn, row by row.
+    Cycle { values: Vec<String> },
+    /// A date between `min` and `max`, both `YYYY-MM-DD`.
+    Date { min: String, max: String },
+    /// A time between `min` and `max`, both `YYYY-MM-DD HH:MM:SS` in UTC or
+    /// `YYYY-MM-DD` for midnight.
+    Timestamp { min: String, max: String },
+    /// A number between `min` and `max` with `scale` decimal places.
+    Decimal { min: f64, max: f64, scale: u8 },
+    /// The time in the earlier timestamp column `of` plus a `lag` in seconds,
+    /// like the time an event was ingested after it occurred.
+    Lagged { of: String, lag: Lag },
+    /// `min` to `max` graphemes of emoji, CJK, right-to-left text, combining
+    /// marks, zero-width characters, long clusters and characters that need
+    /// quoting, each at its rate, and ASCII otherwise. Without any rates, a
+    /// mix of all of them.
+    Unicode {
+        #[serde(default)]
+        min: usize,
+        #[serde(default = "thirty_two")]
+        max: usize,
+        emoji: Option<f64>,
+        cjk: Option<f64>,
+        rtl: Option<f64>,
+        combining: Option<f64>,
+        zero_width: Option<f64>,
+        long_clusters: Option<f64>,
+        specials: Option<f64>,
+    },
+    /// The value of a [`script`](crate::script) over the row's earlier
+    /// columns, with `scale` decimal places if it's a float.
+    Script {
+        script: String,
+        #[serde(default = "two")]
+        scale: u8,
+    },
+    /// The value of the column generator a `--plugin` registered as
+    /// `generator`, written as `data_type` (text by default).
+    Plugin { generator: String, data_type: Option<DataType> },
+}
+
+fn one() -> i64 {
+    1
+}
+
+fn all() -> f64 {
+    1.0
+}
+
+fn two() -> u8 {
+    2
+}
+
+fn three() -> u64 {
+    3
+}
+
+fn nine() -> usize {
+    9
+}
+
+fn six() -> usize {
+    6
+}
+
+fn seven() -> usize {
+    7
+}
+
+fn five_hundred() -> f64 {
+    500.0
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn thirty_two() -> usize {
+    32
+}
+
+fn whole_path() -> usize {
+    business::DEPARTMENT_DEPTH
+}
+
+fn slash() -> String {
+    " / ".to_string()
+}
+
+/// A slowly changing dimension of type 2: a `changed` fraction of the rows,
+/// spread evenly, have 2 to `max_versions` versions, the others one. Each
+/// version repeats the row's key columns with freshly drawn other columns,
+/// and is valid from its `valid_from` until the next version's, with the
+/// current version valid until 9999-12-31.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scd2 {
+    pub changed: f64,
+    #[serde(default = "three")]
+    pub max_versions: u64,
+    /// When every first version becomes valid, `YYYY-MM-DD`.
+    pub start: String,
+    /// The last day a later version can become valid on.
+    pub end: String,
+}
+
+/// The `valid_to` of current versions.
+const OPEN_END: &str = "9999-12-31";
+
+/// The columns every version gets after the table's own.
+pub const SCD2_COLUMNS: [&str; 3] = ["valid_from", "valid_to", "is_current"];
+
+/// An [`Scd2`] with its dates parsed and its settings checked.
+struct History {
+    changed: f64,
+    max_versions: u64,
+    start: i64,
+    end: i64,
+    open_end: i64,
+}
+
+impl Scd2 {
+    fn check(&self, table: &TableConfig) -> Result<History, BoxError> {
+        let date = |s: &str| schema::parse_date(s).ok_or_else(|| format!("table {}: {s} isn't a YYYY-MM-DD date", table.name));
+        let (start, end) = (date(&self.start)?, date(&self.end)?);
+        if !(0.0..=1.0).contains(&self.changed) {
+            return Err(format!("table {}: `changed` must be between 0 and 1", table.name).into());
+        }
+        if self.max_versions < 2 {
+            return Err(format!("table {}: `max_versions` must be at least 2", table.name).into());
+        }
+        // Every later version needs a day of its own after `start`.
+        if end - start < self.max_versions as i64 - 1 {
+            return Err(format!("table {}: {} versions don't fit between {} and {}", table.name, self.max_versions, self.start, self.end).into());
+        }
+        if let Some(c) = table.columns.iter().find(|c| SCD2_COLUMNS.contains(&c.name.as_str())) {
+            return Err(format!("table {}: column {} is added by `scd2`", table.name, c.name).into());
+        }
+        let open_end = schema::parse_date(OPEN_END).expect("a valid date");
+        Ok(History { changed: self.changed, max_versions: self.max_versions, start, end, open_end })
+    }
+}
+
+impl History {
+    /// The `valid_from` of each version of row `index`, in order.
+    fn versions<R: Rng + ?Sized>(&self, rng: &mut R, index: u64) -> Vec<i64> {
+        let mut versions = vec![self.start];
+        if schema::is_matching_row(index, self.changed) {
+            let changes = rng.gen_range(1..self.max_versions) as usize;
+            let mut days: Vec<_> = rand::seq::index::sample(rng, (self.end - self.start) as usize, changes)
+                .into_iter()
+                .map(|day| self.start + 1 + day as i64)
+                .collect();
+            days.sort_unstable();
+            versions.extend(days);
+        }
+        versions
+    }
+
+    fn columns() -> impl Iterator<Item = Column> {
+        [
+            Column::new(SCD2_COLUMNS[0], ColumnKind::DateRange { min: 0, max: 0 }),
+            Column::new(SCD2_COLUMNS[1], ColumnKind::DateRange { min: 0, max: 0 }),
+            Column::new(SCD2_COLUMNS[2], ColumnKind::Choice(vec!["true".into(), "false".into()])),
+        ]
+        .into_iter()
+    }
+}
+
+/// The distribution of the number of children per parent row.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "distribution", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FanOut {
+    Fixed { count: u64 },
+    Uniform { min: u64, max: u64 },
+    Poisson { mean: f64 },
+    /// Many parents with few children and a long tail with many.
+    Geometric { mean: f64 },
+    /// A heavier tail than geometric: `min..=max` children, with `n`
+    /// children about `((n + 1) / (min + 1))^-exponent` times as likely as
+    /// `min`, like the degrees of many real graphs.
+    PowerLaw { exponent: f64, min: u64, max: u64 },
+}
+
+impl FanOut {
+    fn check(&self) -> Result<(), String> {
+        match *self {
+            FanOut::Uniform { min, max } | FanOut::PowerLaw { min, max, .. } if min > max => {
+                Err(format!("fan-out minimum {min} is greater than maximum {max}"))
+            }
+            FanOut::PowerLaw { exponent, .. } if !(exponent > 0.0 && exponent.is_finite()) => {
+                Err(format!("fan-out exponent {exponent} is not a positive number"))
+            }
+            FanOut::Poisson { mean } | FanOut::Geometric { mean } if !(mean >= 0.0 && mean.is_finite()) => {
+                Err(format!("fan-out mean {mean} is not a non-negative number"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        match *self {
+            FanOut::Fixed { count } => count,
+            FanOut::Uniform { min, max } => rng.gen_range(min..=max),
+            FanOut::Poisson { mean } if mean < 30.0 => {
+                // Knuth's method: count uniforms until their product drops below e^-mean.
+                let limit = (-mean).exp();
+                let mut count = 0;
+                let mut product: f64 = rng.r#gen();
+                while product > limit {
+                    count += 1;
+                    product *= rng.r#gen::<f64>();
+                }
+                count
+            }
+            FanOut::Poisson { mean } => {
+                // For large means the normal approximation is close and doesn't loop.
+                let (u, v): (f64, f64) = (rng.r#gen::<f64>().max(f64::MIN_POSITIVE), rng.r#gen());
+                let normal = (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos();
+                (mean + mean.sqrt() * normal).round().max(0.0) as u64
+            }
+            FanOut::Geometric { mean: 0.0 } => 0,
+            FanOut::Geometric { mean } => {
+                let p = 1.0 / (mean + 1.0);
+                let u: f64 = 1.0 - rng.r#gen::<f64>();
+                (u.ln() / (1.0 - p).ln()).floor() as u64
+            }
+            FanOut::PowerLaw { exponent, min, max } => sample_power_law(rng, min, max, exponent),
+        }
+    }
+}
+
+/// Columns one template may expand to.
+const MAX_TEMPLATE_COLUMNS: u64 = 100_000;
+
+/// Expands each column named like `metric_{1..2000}` into columns
+/// `metric_1` to `metric_2000` of the same kind, for tables thousands of
+/// columns wide. A range starting with a zero, like `{001..100}`,
+/// zero-pads the numbers to its width. Column names must then be unique.
+fn expand_templates(table: &str, columns: Vec<ColumnConfig>) -> Result<Vec<ColumnConfig>, BoxError> {
+    let mut expanded = Vec::with_capacity(columns.len());
+    for column in columns {
+        let Some((prefix, range, suffix)) = column.name.split_once('{').and_then(|(prefix, rest)| {
+            let (range, suffix) = rest.split_once('}')?;
+            Some((prefix, range, suffix))
+        }) else {
+            expanded.push(column);
+            continue;
+        };
+        let bad = || format!("column {table}.{}: expected a template like name_{{1..100}}", column.name);
+        let (first, last) = range.split_once("..").ok_or_else(bad)?;
+        let (start, end): (u64, u64) = (first.parse().map_err(|_| bad())?, last.parse().map_err(|_| bad())?);
+        if start > end {
+            return Err(format!("column {table}.{}: the range runs backwards", column.name).into());
+        }
+        if end - start >= MAX_TEMPLATE_COLUMNS {
+            return Err(format!("column {table}.{}: a template expands to at most {MAX_TEMPLATE_COLUMNS} columns", column.name).into());
+        }
+        let width = if first.len() > 1 && first.starts_with('0') { first.len() } else { 0 };
+        for n in start..=end {
+            expanded.push(ColumnConfig { name: format!("{prefix}{n:0width$}{suffix}"), kind: column.kind.clone() });
+        }
+    }
+    let mut names = HashSet::with_capacity(expanded.len());
+    if let Some(twice) = expanded.iter().find(|c| !names.insert(c.name.as_str())) {
+        return Err(format!("table {table} has two columns named {}", twice.name).into());
+    }
+    Ok(expanded)
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, BoxError> {
+        let mut span = telemetry::span("load config");
+        span.set("path", path.to_string_lossy().as_ref());
+        Self::parse(path).inspect_err(|e| span.fail(e))
+    }
+
+    fn parse(path: &Path) -> Result<Self, BoxError> {
+        let text = fs::read_to_string(path).map_err(|e| format!("reading {}: {e}", path.display()))?;
+        // The `config` of an `--effective-config` is JSON.
+        let mut config: Config = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?
+        } else {
+            toml::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?
+        };
+        for table in &mut config.tables {
+            table.columns = expand_templates(&table.name, std::mem::take(&mut table.columns))?;
+        }
+        Ok(config)
+    }
+
+    /// Checks how each table's rows are counted, and returns the tables in
+    /// [`generation_order`](Self::generation_order).
+    fn check(&self) -> Result<Vec<&TableConfig>, BoxError> {
+        for table in &self.tables {
+            match (&table.parent, table.rows, &table.fan_out) {
+                (None, Some(_), None) => {}
+                (None, _, _) => return Err(format!("table {} needs `rows`, or a `parent` and `fan_out`", table.name).into()),
+                (Some(_), None, Some(fan_out)) => fan_out.check().map_err(|e| format!("table {}: {e}", table.name))?,
+                (Some(_), _, _) => {
+                    return Err(format!("table {} has a parent, so it takes `fan_out` instead of `rows`", table.name).into());
+                }
+            }
+            if let Some(scd2) = &table.scd2 {
+                scd2.check(table)?;
+            }
+        }
+        self.generation_order()
+    }
+
+    /// The tables in an order where every table comes after the tables it
+    /// references, checking that references resolve.
+    pub fn generation_order(&self) -> Result<Vec<&TableConfig>, BoxError> {
+        let by_name: HashMap<_, _> = self.tables.iter().map(|t| (t.name.as_str(), t)).collect();
+        if by_name.len() != self.tables.len() {
+            return Err("table names must be unique".into());
+        }
+        let mut order = Vec::new();
+        // 1 while a table's dependencies are being visited, 2 once it is ordered.
+        let mut state: HashMap<&str, u8> = HashMap::new();
+        fn visit<'a>(
+            table: &'a TableConfig,
+            by_name: &HashMap<&str, &'a TableConfig>,
+            state: &mut HashMap<&'a str, u8>,
+            order: &mut Vec<&'a TableConfig>,
+        ) -> Result<(), BoxError> {
+            match state.get(table.name.as_str()) {
+                Some(2) => return Ok(()),
+                Some(_) => return Err(format!("table {} references itself through its dependencies", table.name).into()),
+                None => {}
+            }
+            state.insert(&table.name, 1);
+            for dependency in table.dependencies() {
+                let parent = by_name
+                    .get(dependency)
+                    .ok_or_else(|| format!("table {} references {dependency}, which isn't defined", table.name))?;
+                visit(parent, by_name, state, order)?;
+            }
+            state.insert(&table.name, 2);
+            order.push(table);
+            Ok(())
+        }
+        for table in &self.tables {
+            visit(table, &by_name, &mut state, &mut order)?;
+        }
+        Ok(order)
+    }
+}
+
+impl TableConfig {
+    fn dependencies(&self) -> impl Iterator<Item = &str> {
+        let references = self.columns.iter().filter_map(|c| match &c.kind {
+            KindConfig::Reference { table, .. } => Some(table.as_str()),
+            _ => None,
+        });
+        self.parent.as_deref().into_iter().chain(references)
+    }
+
+    /// The first sequence column, which is what other tables reference.
+    pub fn key(&self) -> Option<i64> {
+        self.columns.iter().find_map(|c| match c.kind {
+            KindConfig::Sequence { start } => Some(start),
+            _ => None,
+        })
+    }
+}
+
+/// The key range of a table that has been generated.
+#[derive(Debug, Clone, Copy)]
+pub struct Generated {
+    /// The start of the table's first sequence, if it has one.
+    pub key: Option<i64>,
+    pub rows: u64,
+}
+
+impl Generated {
+    /// The table's keys as an inclusive range, for columns referencing it.
+    fn keys(&self, name: &str, referrer: &str) -> Result<(i64, i64), BoxError> {
+        let start = self.key.ok_or_else(|| format!("{referrer} references {name}, which has no sequence column"))?;
+        if self.rows == 0 {
+            return Err(format!("{referrer} references {name}, which has no rows").into());
+        }
+        Ok((start, start + self.rows as i64 - 1))
+    }
+}
+
+/// Lowers a table's columns, resolving references to the key ranges of the
+/// tables already generated.
+fn resolve_columns(
+    table: &TableConfig,
+    generated: &HashMap<String, Generated>,
+    locale: &LocaleMix,
+) -> Result<Vec<Column>, BoxError> {
+    let mut columns: Vec<Column> = Vec::new();
+    for c in &table.columns {
+        let kind = match &c.kind {
+            KindConfig::Sequence { start } => ColumnKind::Sequence { start: *start },
+            KindConfig::ParentKey if table.parent.is_none() => {
+                return Err(format!("column {}.{} is a parent_key, but the table has no parent", table.name, c.name).into());
+            }
+            KindConfig::ParentKey => {
+                let parent = table.parent.as_deref().unwrap();
+                generated[parent].keys(parent, &table.name)?;
+                ColumnKind::ParentKey
+            }
+            KindConfig::Reference { matching, .. } if !(0.0..=1.0).contains(matching) => {
+                return Err(format!("column {}.{}: `matching` must be between 0 and 1", table.name, c.name).into());
+            }
+            KindConfig::Reference { table: target, matching, skew } => {
+                let (min, max) = generated[target].keys(target, &table.name)?;
+                ColumnKind::ForeignKey { min, max, matching: *matching, skew: *skew }
+            }
+            KindConfig::Int { min, max } => ColumnKind::IntRange { min: *min, max: *max },
+            KindConfig::Choice { values } if values.is_empty() => {
+                return Err(format!("column {}.{} has no values to choose from", table.name, c.name).into());
+            }
+            KindConfig::Choice { values } => ColumnKind::Choice(values.clone()),
+            KindConfig::FirstName { locale: l } => l.as_ref().unwrap_or(locale).kind(Field::FirstName),
+            KindConfig::LastName { locale: l } => l.as_ref().unwrap_or(locale).kind(Field::LastName),
+            KindConfig::FullName { locale: l } => l.as_ref().unwrap_or(locale).kind(Field::FullName),
+            KindConfig::StreetAddress { locale: l } => l.as_ref().unwrap_or(locale).kind(Field::StreetAddress),
+            KindConfig::City { locale: l } => l.as_ref().unwrap_or(locale).kind(Field::City),
+            KindConfig::Postcode { locale: l } => l.as_ref().unwrap_or(locale).kind(Field::Postcode),
+            KindConfig::Phone { locale: l } => l.as_ref().unwrap_or(locale).kind(Field::Phone),
+            KindConfig::Company => business::company(),
+            KindConfig::Industry => business::industry(),
+            KindConfig::JobTitle => business::job_title(),
+            KindConfig::Department { depth, .. } if !(1..=business::DEPARTMENT_DEPTH).contains(depth) => {
+                return Err(format!("column {}.{}: depth must be 1 to {}", table.name, c.name, business::DEPARTMENT_DEPTH).into());
+            }
+            KindConfig::Department { depth, separator } => business::department(*depth, separator),
+            KindConfig::NationalId { country, synthetic } => ColumnKind::NationalId { country: *country, synthetic: *synthetic },
+            KindConfig::Latitude { country, bounds } => geo_kind(table, c, geo::Shape::Latitude, country, bounds)?,
+            KindConfig::Longitude { country, bounds } => geo_kind(table, c, geo::Shape::Longitude, country, bounds)?,
+            KindConfig::Geohash { precision, country, bounds } => {
+                geo_kind(table, c, geo::Shape::Geohash { precision: *precision }, country, bounds)?
+            }
+            KindConfig::Point { country, bounds } => geo_kind(table, c, geo::Shape::Point, country, bounds)?,
+            KindConfig::Polygon { vertices, radius, country, bounds } => {
+                geo_kind(table, c, geo::Shape::Polygon { vertices: *vertices, radius: *radius }, country, bounds)?
+            }
+            KindConfig::Sha256Hex => ColumnKind::Sha256Hex,
+            KindConfig::UuidV4 => ColumnKind::UuidV4,
+            KindConfig::ChildNumber if table.parent.is_none() => {
+                return Err(format!("column {}.{} is a child_number, but the table has no parent", table.name, c.name).into());
+            }
+            KindConfig::ChildNumber => ColumnKind::ChildNumber,
+            KindConfig::Label { prefix, width } => ColumnKind::Label { prefix: prefix.clone(), width: *width },
+            KindConfig::Cycle { values } => ColumnKind::Cycle(values.clone()),
+            KindConfig::Date { min, max } => {
+                let date = |s: &str| {
+                    schema::parse_date(s).ok_or_else(|| format!("column {}.{}: {s} isn't a YYYY-MM-DD date", table.name, c.name))
+                };
+                ColumnKind::DateRange { min: date(min)?, max: date(max)? }
+            }
+            KindConfig::Timestamp { min, max } => {
+                let time = |s: &str| {
+                    schema::parse_timestamp(s)
+                        .ok_or_else(|| format!("column {}.{}: {s} isn't a YYYY-MM-DD HH:MM:SS time", table.name, c.name))
+                };
+                ColumnKind::TimestampRange { min: time(min)?, max: time(max)? }
+            }
+            KindConfig::Decimal { scale, .. } if *scale >= schema::DECIMAL_PRECISION => {
+                return Err(format!("column {}.{}: scale must be below {}", table.name, c.name, schema::DECIMAL_PRECISION).into());
+            }
+            KindConfig::Decimal { min, max, scale } => {
+                let unscaled = |value: f64| (value * 10f64.powi(*scale as i32)).round() as i64;
+                ColumnKind::Decimal { min: unscaled(*min), max: unscaled(*max), scale: *scale }
+            }
+            KindConfig::Lagged { of, lag } => {
+                let of = table.columns.iter().position(|c| &c.name == of).ok_or_else(|| {
+                    format!("column {}.{} lags column {of}, which the table doesn't have", table.name, c.name)
+                })?;
+                ColumnKind::Lagged { of, lag: *lag }
+            }
+            &KindConfig::Unicode { min, max, emoji, cjk, rtl, combining, zero_width, long_clusters, specials } => {
+                let rates = [emoji, cjk, rtl, combining, zero_width, long_clusters, specials];
+                let mix = if rates.iter().all(Option::is_none) {
+                    UnicodeMix::default()
+                } else {
+                    let rate = |rate: Option<f64>| rate.unwrap_or(0.0);
+                    UnicodeMix {
+                        emoji: rate(emoji),
+                        cjk: rate(cjk),
+                        rtl: rate(rtl),
+                        combining: rate(combining),
+                        zero_width: rate(zero_width),
+                        long_clusters: rate(long_clusters),
+                        specials: rate(specials),
+                    }
+                };
+                ColumnKind::Unicode { min, max, mix }
+            }
+            #[cfg(feature = "script")]
+            KindConfig::Script { script, scale } => {
+                let compiled = Script::compile(script, &columns).map_err(|e| format!("column {}.{}: {e}", table.name, c.name))?;
+                ColumnKind::Script { source: script.clone(), data_type: compiled.data_type(*scale) }
+            }
+            #[cfg(not(feature = "script"))]
+            KindConfig::Script { .. } => {
+                return Err(format!("column {}.{}: {}", table.name, c.name, crate::without("script", "script column")).into());
+            }
+            KindConfig::Plugin { generator, data_type } => {
+                ColumnKind::Plugin { name: generator.clone(), data_type: data_type.unwrap_or(DataType::String) }
+            }
+        };
+        columns.push(Column::new(&c.name, kind));
+    }
+    Ok(columns)
+}
+
+/// The columns of `table` as its files have them, with the history of an
+/// `scd2` table after its own.
+pub fn written_columns(
+    table: &TableConfig,
+    generated: &HashMap<String, Generated>,
+    locale: &LocaleMix,
+) -> Result<Vec<Column>, BoxError> {
+    let mut columns = resolve_columns(table, generated, locale)?;
+    if table.scd2.is_some() {
+        columns.extend(History::columns());
+    }
+    Ok(columns)
+}
+
+/// A geo column of `shape` with points in `country` or `bounds`, or anywhere.
+fn geo_kind(
+    table: &TableConfig,
+    c: &ColumnConfig,
+    shape: geo::Shape,
+    country: &Option<String>,
+    bounds: &Option<[f64; 4]>,
+) -> Result<ColumnKind, BoxError> {
+    let area = match (country, bounds) {
+        (Some(_), Some(_)) => return Err(format!("column {}.{}: give a country or bounds, not both", table.name, c.name).into()),
+        (Some(code), None) => geo::country(code).map(<[_]>::to_vec).ok_or_else(|| {
+            format!("column {}.{}: no country {code}; try {}", table.name, c.name, geo::country_codes().join(", "))
+        })?,
+        (None, Some([west, south, east, north])) => vec![geo::Bounds { south: *south, west: *west, north: *north, east: *east }],
+        (None, None) => vec![geo::WORLD],
+    };
+    Ok(ColumnKind::Geo { shape, area })
+}
+
+/// Where a table goes: `{table}` in `--output` replaced by its name.
+pub fn table_location(template: &str, table: &str) -> String {
+    template.replace("{table}", table)
+}
+
+/// Checks that `--output` puts each of `tables` in a file or object of its own.
+pub fn check_locations<'a>(template: &str, tables: impl IntoIterator<Item = &'a str>) -> Result<(), BoxError> {
+    if !template.contains("{table}") {
+        return Err("--output must contain {table} when generating several tables".into());
+    }
+    for table in tables {
+        let location = table_location(template, table);
+        if !matches!(
+            Destination::parse(&location)?,
+            Destination::File(_)
+                | Destination::Gcs { .. }
+                | Destination::S3 { .. }
+                | Destination::Azure { .. }
+                | Destination::Plugin { .. }
+        ) {
+            return Err(format!("{location}: related tables are each written to a file, cloud storage object or plugin sink").into());
+        }
+    }
+    Ok(())
+}
+
+/// Generates every table of `config`, parents first.
+pub fn generate(args: &Args, config: &Config) -> Result<(), BoxError> {
+    check_locations(&args.output, config.tables.iter().map(|t| t.name.as_str()))?;
+    let order = config.check()?;
+    if args.shards > 1 && !output::has_shard(&args.output) {
+        return Err(format!("--output must contain {{n}} when generating {} shards", args.shards).into());
+    }
+
+    let outputs = Outputs::new(args);
+    let seed: u64 = rand::thread_rng().r#gen();
+    let mut generated: HashMap<String, Generated> = HashMap::new();
+    let mut splits: HashMap<&str, Split> = HashMap::new();
+    for table in order {
+        if signal::interrupted() {
+            break;
+        }
+        let mut columns = resolve_columns(table, &generated, &args.locale)?;
+        let generator = Generator::compile(&columns)?;
+        let history = table.scd2.as_ref().map(|scd2| scd2.check(table)).transpose()?;
+        if history.is_some() {
+            columns.extend(History::columns());
+        }
+        let seed = table_seed(seed, &table.name);
+        let (split, parent) = match (&table.parent, &table.fan_out) {
+            (Some(parent), Some(fan_out)) => {
+                let split = Split::children(&splits[parent.as_str()], fan_out, seed);
+                // A parent without a sequence has no children to reference it.
+                (split, Some((generated[parent].key.unwrap_or(0), *fan_out)))
+            }
+            _ => (Split::rows(table.rows.unwrap_or(0), args.shards, args.shard_sizes), None),
+        };
+        let plan = TablePlan { generator, history, columns, parent, seed };
+
+        let jobs = args
+            .jobs
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+            .clamp(1, args.shards as usize);
+        let next_shard = AtomicU64::new(0);
+        let written = Mutex::new(Vec::new());
+        let results: Vec<Result<(), BoxError>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..jobs)
+                .map(|_| {
+                    scope.spawn(|| -> Result<(), BoxError> {
+                        loop {
+                            let shard = next_shard.fetch_add(1, Ordering::Relaxed) as usize;
+                            if shard >= args.shards as usize || signal::interrupted() {
+                                return Ok(());
+                            }
+                            let location = table_location(&output::shard_location(&args.output, shard), &table.name);
+                            let mut span = telemetry::span("shard");
+                            span.set("table", table.name.as_str());
+                            span.set("shard", shard as u64);
+                            span.set("location", location.as_str());
+                            let versions = plan.write_shard(&outputs, &location, &split, shard).inspect_err(|e| span.fail(e))?;
+                            span.set("rows", versions);
+                            written.lock().unwrap().push((shard, location, versions));
+                        }
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().expect("shard worker panicked")).collect()
+        });
+        results.into_iter().collect::<Result<(), _>>()?;
+        let mut written = written.into_inner().unwrap();
+        written.sort_unstable_by_key(|(shard, ..)| *shard);
+        for (shard, location, versions) in written {
+            let rows = split.rows[shard + 1] - split.rows[shard];
+            if plan.history.is_some() {
+                println!("Successfully generated {location} ({rows} rows in {versions} versions)");
+            } else {
+                println!("Successfully generated {location} ({versions} rows)");
+            }
+        }
+        let index = split.total();
+        for c in &table.columns {
+            if let KindConfig::Reference { table: target, matching, .. } = &c.kind
+                && *matching < 1.0
+            {
+                let matched = schema::matching_rows(index, *matching);
+                println!("  {}.{}: {matched} of {index} rows reference a row of {target}", table.name, c.name);
+            }
+        }
+        generated.insert(table.name.clone(), Generated { key: table.key(), rows: index });
+        splits.insert(&table.name, split);
+    }
+    Ok(())
+}
+
+/// SplitMix64's output function, which scrambles the bits of `x`.
+fn mix(x: u64) -> u64 {
+    let x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// The seed of a table's fan-outs within the run seeded by `seed`.
+fn table_seed(seed: u64, table: &str) -> u64 {
+    table.bytes().fold(mix(seed), |hash, b| mix(hash ^ u64::from(b)))
+}
+
+/// SplitMix64, seeded by a table and one of its parent rows, so that any
+/// shard can draw the same fan-out for that row as any other.
+struct FanOutRng(u64);
+
+impl FanOutRng {
+    const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+    fn new(table_seed: u64, parent_index: u64) -> Self {
+        FanOutRng(mix(table_seed ^ mix(parent_index.wrapping_mul(Self::GAMMA))))
+    }
+}
+
+impl RngCore for FanOutRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(Self::GAMMA);
+        mix(self.0)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Where each shard of a table starts, as indexes into the table's rows
+/// and, for child tables, its parent's rows. Shard `i` holds rows
+/// `rows[i]..rows[i + 1]`.
+struct Split {
+    rows: Vec<u64>,
+    parents: Vec<u64>,
+}
+
+impl Split {
+    /// `rows` rows spread over `shards` shards as `sizes` asks.
+    fn rows(rows: u64, shards: u64, sizes: ShardSizes) -> Self {
+        Split { rows: sizes.bounds(rows, shards), parents: Vec::new() }
+    }
+
+    /// The children of the rows of `parent`, with each shard holding the
+    /// children of the parent's rows in the parent's shard.
+    fn children(parent: &Split, fan_out: &FanOut, seed: u64) -> Self {
+        let mut rows = vec![0];
+        let mut total = 0;
+        for shard in parent.rows.windows(2) {
+            total += (shard[0]..shard[1]).map(|index| fan_out.sample(&mut FanOutRng::new(seed, index))).sum::<u64>();
+            rows.push(total);
+        }
+        Split { rows, parents: parent.rows.clone() }
+    }
+
+    /// The children of the first of `parents` rows, as far as the parent
+    /// whose children make up `head` rows.
+    fn first_children(parents: u64, fan_out: &FanOut, seed: u64, head: u64) -> Self {
+        let (mut parent, mut total) = (0, 0);
+        while parent < parents && total < head {
+            total += fan_out.sample(&mut FanOutRng::new(seed, parent));
+            parent += 1;
+        }
+        Split { rows: vec![0, total], parents: vec![0, parent] }
+    }
+
+    fn total(&self) -> u64 {
+        *self.rows.last().expect("a split has a bound per shard and one more")
+    }
+}
+
+/// What generating one table takes, shared by the workers writing its shards.
+struct TablePlan {
+    generator: Generator,
+    history: Option<History>,
+    /// The table's columns, with the history's after them.
+    columns: Vec<Column>,
+    /// The start of the parent's keys and the fan-out, for child tables.
+    parent: Option<(i64, FanOut)>,
+    seed: u64,
+}
+
+impl TablePlan {
+    /// Writes shard `shard` of the table to `location` and returns how many
+    /// versions of its rows it wrote.
+    fn write_shard(&self, outputs: &Outputs, location: &str, split: &Split, shard: usize) -> Result<u64, BoxError> {
+        let mut sink = sink::open_shard(outputs, &Destination::parse(location)?, &self.columns, shard as u64)?;
+        let versions = self.write_rows(&mut *sink, split, shard)?;
+        sink.finish()?;
+        Ok(versions)
+    }
+
+    /// Writes the rows of shard `shard` to `sink`, returning how many
+    /// versions of them it wrote.
+    fn write_rows(&self, sink: &mut dyn Sink, split: &Split, shard: usize) -> Result<u64, BoxError> {
+        let mut rng = rand::thread_rng();
+        let mut row = Row::new();
+        let (mut index, mut versions) = (split.rows[shard], 0);
+        let mut write = |rng: &mut ThreadRng, parent: i64, child: u64| -> Result<(), BoxError> {
+            let keys = RowKeys { index, parent, child };
+            index += 1;
+            let Some(history) = &self.history else {
+                self.generator.fill_keyed_row(rng, &mut row, keys);
+                versions += 1;
+                return sink.write_row(&row);
+            };
+            let starts = history.versions(rng, keys.index);
+            for (i, &valid_from) in starts.iter().enumerate() {
+                self.generator.fill_keyed_row(rng, &mut row, keys);
+                push_date(&mut row, valid_from);
+                push_date(&mut row, starts.get(i + 1).copied().unwrap_or(history.open_end));
+                row.push(if i + 1 == starts.len() { b"true" } else { b"false" });
+                sink.write_row(&row)?;
+            }
+            versions += starts.len() as u64;
+            Ok(())
+        };
+        match self.parent {
+            Some((start, fan_out)) => {
+                for parent_index in split.parents[shard]..split.parents[shard + 1] {
+                    if signal::interrupted() {
+                        break;
+                    }
+                    for child in 0..fan_out.sample(&mut FanOutRng::new(self.seed, parent_index)) {
+                        write(&mut rng, start + parent_index as i64, child)?;
+                    }
+                }
+            }
+            None => {
+                for _ in split.rows[shard]..split.rows[shard + 1] {
+                    if signal::interrupted() {
+                        break;
+                    }
+                    write(&mut rng, 0, 0)?;
+                }
+            }
+        }
+        Ok(versions)
+    }
+}
+
+/// The first `head` rows of each table of `config`, parents first, as
+/// `preview` shows them. A child table's are the children of its parent's
+/// first rows, and references point at the rows shown, as if each table
+/// had only those.
+pub(crate) fn preview(args: &Args, config: &Config, head: u64) -> Result<Vec<Table>, BoxError> {
+    let mut generated: HashMap<String, Generated> = HashMap::new();
+    let mut previews = Vec::new();
+    for table in config.check()? {
+        let mut columns = resolve_columns(table, &generated, &args.locale)?;
+        let generator = Generator::compile(&columns)?;
+        let history = table.scd2.as_ref().map(|scd2| scd2.check(table)).transpose()?;
+        if history.is_some() {
+            columns.extend(History::columns());
+        }
+        let seed = rand::thread_rng().r#gen();
+        let (split, parent) = match (&table.parent, &table.fan_out) {
+            (Some(parent), Some(fan_out)) => {
+                let split = Split::first_children(generated[parent].rows, fan_out, seed, head);
+                (split, Some((generated[parent].key.unwrap_or(0), *fan_out)))
+            }
+            _ => (Split::rows(table.rows.unwrap_or(0).min(head), 1, ShardSizes::Balanced), None),
+        };
+        let plan = TablePlan { generator, history, columns, parent, seed };
+        let mut rows = Head::new(head);
+        plan.write_rows(&mut rows, &split, 0)?;
+        generated.insert(table.name.clone(), Generated { key: table.key(), rows: split.total().min(head) });
+        previews.push(Table { name: table.name.clone(), columns: plan.columns, rows: rows.into_rows() });
+    }
+    Ok(previews)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::Locale;
+    use clap::Parser;
+
+    const SHOP: &str = r#"
+        [[table]]
+        name = "order_items"
+        parent = "orders"
+        fan_out = { distribution = "uniform", min = 1, max = 4 }
+        column = [
+            { name = "item_id", kind = "sequence" },
+            { name = "order_id", kind = "parent_key" },
+            { name = "quantity", kind = "int", min = 1, max = 9 },
+        ]
+
+        [[table]]
+        name = "customers"
+        rows = 200
+        column = [
+            { name = "customer_id", kind = "sequence", start = 1000 },
+            { name = "name", kind = "first_name" },
+        ]
+
+        [[table]]
+        name = "orders"
+        parent = "customers"
+        fan_out = { distribution = "geometric", mean = 2.5 }
+        column = [
+            { name = "order_id", kind = "sequence" },
+            { name = "customer_id", kind = "parent_key" },
+            { name = "status", kind = "choice", values = ["paid", "shipped"] },
+        ]
+    "#;
+
+    #[test]
+    fn orders_tables_after_their_parents() {
+        let config: Config = toml::from_str(SHOP).unwrap();
+        let order: Vec<_> = config.generation_order().unwrap().iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(order, ["customers", "orders", "order_items"]);
+    }
+
+    #[test]
+    fn rejects_cycles_and_unknown_tables() {
+        let cycle: Config = toml::from_str(
+            r#"
+            [[table]]
+            name = "a"
+            parent = "b"
+            fan_out = { distribution = "fixed", count = 1 }
+            column = [{ name = "b", kind = "parent_key" }]
+            [[table]]
+            name = "b"
+            rows = 1
+            column = [{ name = "a", kind = "reference", table = "a" }]
+            "#,
+        )
+        .unwrap();
+        assert!(cycle.generation_order().is_err());
+        let unknown: Config =
+            toml::from_str("[[table]]\nname = \"a\"\nrows = 1\ncolumn = [{ name = \"x\", kind = \"reference\", table = \"z\" }]").unwrap();
+        assert!(unknown.generation_order().unwrap_err().to_string().contains("references z"));
+    }
+
+    #[test]
+    fn fan_outs_stay_in_range() {
+        let mut rng = rand::thread_rng();
+        let uniform = FanOut::Uniform { min: 2, max: 5 };
+        assert!((0..1000).all(|_| (2..=5).contains(&uniform.sample(&mut rng))));
+        for fan_out in [FanOut::Poisson { mean: 3.0 }, FanOut::Poisson { mean: 100.0 }, FanOut::Geometric { mean: 3.0 }] {
+            let mean = (0..20_000).map(|_| fan_out.sample(&mut rng)).sum::<u64>() as f64 / 20_000.0;
+            let expected = match fan_out {
+                FanOut::Poisson { mean } | FanOut::Geometric { mean } => mean,
+                _ => unreachable!(),
+            };
+            assert!((mean - expected).abs() < expected * 0.1, "{fan_out:?}: {mean}");
+        }
+        assert!(FanOut::Uniform { min: 3, max: 1 }.check().is_err());
+    }
+
+    #[test]
+    fn every_foreign_key_has_a_parent() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_relational_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("shop.toml");
+        fs::write(&config, SHOP).unwrap();
+        let output = dir.join("{table}.csv");
+        let args = Args::parse_from(["gen", "--output", output.to_str().unwrap()]);
+        generate(&args, &Config::load(&config).unwrap()).unwrap();
+
+        let read = |table: &str| -> Vec<Vec<String>> {
+            let mut reader = csv::Reader::from_path(dir.join(format!("{table}.csv"))).unwrap();
+            reader.records().map(|r| r.unwrap().iter().map(String::from).collect()).collect()
+        };
+        let keys = |rows: &[Vec<String>]| -> HashSet<String> { rows.iter().map(|r| r[0].clone()).collect() };
+        let (customers, orders, items) = (read("customers"), read("orders"), read("order_items"));
+        assert_eq!(customers.len(), 200);
+        assert_eq!(customers[0][0], "1000");
+        let (customer_ids, order_ids) = (keys(&customers), keys(&orders));
+        assert_eq!(order_ids.len(), orders.len());
+        assert!(orders.iter().all(|o| customer_ids.contains(&o[1])));
+        assert!(items.iter().all(|i| order_ids.contains(&i[1])));
+        assert!(items.len() >= orders.len());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn previews_reference_the_rows_shown() {
+        let args = Args::parse_from(["gen"]);
+        let tables = preview(&args, &toml::from_str(SHOP).unwrap(), 10).unwrap();
+        let names: Vec<_> = tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["customers", "orders", "order_items"]);
+        let keys = |table: &Table, column: usize| -> Vec<String> {
+            table.rows.iter().map(|row| String::from_utf8(row.fields().nth(column).unwrap().to_vec()).unwrap()).collect()
+        };
+        assert_eq!(keys(&tables[0], 0)[..3], ["1000", "1001", "1002"]);
+        for (parent, child) in [(&tables[0], &tables[1]), (&tables[1], &tables[2])] {
+            assert!(child.rows.len() <= 10);
+            assert!(keys(child, 1).iter().all(|key| keys(parent, 0).contains(key)), "{:?}", keys(child, 1));
+        }
+    }
+
+    #[test]
+    fn sharded_keys_resolve_across_shards() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_sharded_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("shop.toml");
+        let reviews = r#"
+            [[table]]
+            name = "reviews"
+            rows = 300
+            column = [{ name = "order_id", kind = "reference", table = "orders" }]
+        "#;
+        fs::write(&config, format!("{SHOP}{reviews}")).unwrap();
+        let output = dir.join("{table}-{n}.csv");
+        let args = Args::parse_from(["gen", "--config", config.to_str().unwrap(), "--shards", "3", "--output", output.to_str().unwrap()]);
+        generate(&args, &Config::load(&config).unwrap()).unwrap();
+
+        let read = |table: &str| -> Vec<Vec<Vec<String>>> {
+            (0..3)
+                .map(|shard| {
+                    let mut reader = csv::Reader::from_path(dir.join(format!("{table}-{shard}.csv"))).unwrap();
+                    reader.records().map(|r| r.unwrap().iter().map(String::from).collect()).collect()
+                })
+                .collect()
+        };
+        let (customers, orders, items, reviews) = (read("customers"), read("orders"), read("order_items"), read("reviews"));
+        assert_eq!(customers.iter().map(Vec::len).collect::<Vec<_>>(), [67, 67, 66]);
+        assert_eq!(reviews.concat().len(), 300);
+        // The keys of every table are one sequence, in shard order.
+        for table in [&customers, &orders, &items] {
+            let keys: Vec<i64> = table.concat().iter().map(|r| r[0].parse().unwrap()).collect();
+            assert!(keys.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        }
+        // Children are in their parent's shard, and references find a row in some shard.
+        for (parents, children) in [(&customers, &orders), (&orders, &items)] {
+            for (shard, rows) in children.iter().enumerate() {
+                let keys: HashSet<_> = parents[shard].iter().map(|r| r[0].clone()).collect();
+                assert!(rows.iter().all(|r| keys.contains(&r[1])));
+            }
+        }
+        let order_ids: HashSet<_> = orders.concat().into_iter().map(|r| r[0].clone()).collect();
+        assert!(reviews.concat().iter().all(|r| order_ids.contains(&r[0])));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn splits_depend_on_the_seed_not_the_shards() {
+        let fan_out = FanOut::Poisson { mean: 3.0 };
+        let one = Split::children(&Split::rows(1000, 1, ShardSizes::Balanced), &fan_out, 7);
+        let four = Split::children(&Split::rows(1000, 4, ShardSizes::Balanced), &fan_out, 7);
+        assert_eq!(four.parents, [0, 250, 500, 750, 1000]);
+        assert_eq!(one.total(), four.total());
+        assert_ne!(one.total(), Split::children(&Split::rows(1000, 1, ShardSizes::Balanced), &fan_out, 8).total());
+    }
+
+    #[test]
+    fn scd2_versions_are_contiguous() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_scd2_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config: Config = toml::from_str(
+            r#"
+            [[table]]
+            name = "customers"
+            rows = 400
+            scd2 = { changed = 0.25, max_versions = 4, start = "2020-01-01", end = "2020-01-10" }
+            column = [{ name = "id", kind = "sequence" }, { name = "tier", kind = "int", min = 1, max = 3 }]
+            "#,
+        )
+        .unwrap();
+        let args = Args::parse_from(["gen", "--output", dir.join("{table}.csv").to_str().unwrap()]);
+        generate(&args, &config).unwrap();
+        let mut reader = csv::Reader::from_path(dir.join("customers.csv")).unwrap();
+        assert_eq!(reader.headers().unwrap(), vec!["id", "tier", "valid_from", "valid_to", "is_current"]);
+        let rows: Vec<Vec<String>> = reader.records().map(|r| r.unwrap().iter().map(String::from).collect()).collect();
+        let mut by_id: HashMap<&str, Vec<&Vec<String>>> = HashMap::new();
+        for row in &rows {
+            by_id.entry(&row[0]).or_default().push(row);
+        }
+        assert_eq!(by_id.len(), 400);
+        assert_eq!(by_id.values().filter(|versions| versions.len() > 1).count(), 100);
+        for versions in by_id.values() {
+            assert!(versions.len() <= 4);
+            assert_eq!(versions[0][2], "2020-01-01");
+            for pair in versions.windows(2) {
+                assert_eq!(pair[0][3], pair[1][2]);
+                assert!(pair[0][2] < pair[1][2]);
+                assert_eq!(pair[0][4], "false");
+            }
+            let current = versions.last().unwrap();
+            assert_eq!((current[3].as_str(), current[4].as_str()), ("9999-12-31", "true"));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn lagged_columns_name_an_earlier_column() {
+        let config: Config = toml::from_str(
+            r#"
+            [[table]]
+            name = "events"
+            rows = 10
+            column = [
+                { name = "occurred_at", kind = "timestamp", min = "2024-01-01", max = "2024-12-31" },
+                { name = "ingested_at", kind = "lagged", of = "occurred_at", lag = { distribution = "exponential", mean = 90 } },
+                { name = "processed_at", kind = "lagged", of = "processed", lag = { distribution = "fixed", seconds = 5 } },
+            ]
+            "#,
+        )
+        .unwrap();
+        let mut table = config.tables.into_iter().next().unwrap();
+        let error = resolve_columns(&table, &HashMap::new(), &Locale::En.into()).unwrap_err();
+        assert!(error.to_string().contains("lags column processed"), "{error}");
+        table.columns.pop();
+        let columns = resolve_columns(&table, &HashMap::new(), &Locale::En.into()).unwrap();
+        assert!(matches!(columns[1].kind, ColumnKind::Lagged { of: 0, lag: Lag::Exponential { mean: 90.0 } }));
+    }
+
+    #[test]
+    fn column_templates_expand_to_numbered_columns() {
+        let column = |name: &str| ColumnConfig { name: name.to_string(), kind: KindConfig::Company };
+        let names = |columns: Vec<ColumnConfig>| columns.into_iter().map(|c| c.name).collect::<Vec<_>>();
+        let expanded = expand_templates("t", vec![column("id"), column("metric_{1..3}"), column("m{08..10}_x")]).unwrap();
+        assert_eq!(names(expanded), ["id", "metric_1", "metric_2", "metric_3", "m08_x", "m09_x", "m10_x"]);
+        assert_eq!(expand_templates("t", vec![column("m_{1..2000}")]).unwrap().len(), 2000);
+        for bad in ["m_{3..1}", "m_{1-3}", "m_{a..b}", "m_{1..}"] {
+            assert!(expand_templates("t", vec![column(bad)]).is_err(), "{bad}");
+        }
+        let error = expand_templates("t", vec![column("m_{1..3}"), column("m_2")]).unwrap_err();
+        assert!(error.to_string().contains("two columns named m_2"), "{error}");
+    }
+
+    #[test]
+    fn unicode_columns_mix_only_the_rates_given() {
+        let config: Config = toml::from_str(
+            r#"
+            [[table]]
+            name = "notes"
+            rows = 1
+            column = [
+                { name = "body", kind = "unicode" },
+                { name = "title", kind = "unicode", min = 1, max = 5, emoji = 0.5, specials = 0.1 },
+                { name = "tag", kind = "unicode", cjk = 0.8, rtl = 0.8 },
+            ]
+            "#,
+        )
+        .unwrap();
+        let columns = resolve_columns(&config.tables[0], &HashMap::new(), &Locale::En.into()).unwrap();
+        assert!(matches!(&columns[0].kind, ColumnKind::Unicode { min: 0, max: 32, mix } if *mix == UnicodeMix::default()));
+        let ColumnKind::Unicode { min: 1, max: 5, mix } = &columns[1].kind else { panic!("{:?}", columns[1].kind) };
+        assert_eq!((mix.emoji, mix.specials, mix.cjk, mix.long_clusters), (0.5, 0.1, 0.0, 0.0));
+        let error = Generator::compile(&columns[2..]).err().unwrap();
+        assert!(error.to_string().contains("more than 1"), "{error}");
+    }
+
+    #[test]
+    fn departments_go_as_deep_as_asked() {
+        let config: Config = toml::from_str(
+            r#"
+            [[table]]
+            name = "employees"
+            rows = 1
+            column = [
+                { name = "team", kind = "department" },
+                { name = "division", kind = "department", depth = 1 },
+                { name = "unit", kind = "department", depth = 2, separator = "/" },
+                { name = "title", kind = "job_title" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let mut table = config.tables.into_iter().next().unwrap();
+        let columns = resolve_columns(&table, &HashMap::new(), &Locale::En.into()).unwrap();
+        let depth = |kind: &ColumnKind, separator| match kind {
+            ColumnKind::Choice(paths) => paths[0].split(separator).count(),
+            kind => panic!("{kind:?}"),
+        };
+        assert_eq!([depth(&columns[0].kind, " / "), depth(&columns[1].kind, " / "), depth(&columns[2].kind, "/")], [3, 1, 2]);
+        table.columns[1].kind = KindConfig::Department { depth: 4, separator: "/".into() };
+        let error = resolve_columns(&table, &HashMap::new(), &Locale::En.into()).unwrap_err();
+        assert!(error.to_string().contains("depth must be 1 to 3"), "{error}");
+    }
+
+    #[test]
+    fn national_ids_are_synthetic_unless_asked() {
+        let config: Config = toml::from_str(
+            r#"
+            [[table]]
+            name = "people"
+            rows = 1
+            column = [
+                { name = "ssn", kind = "national_id", country = "us" },
+                { name = "cpf", kind = "national_id", country = "br", synthetic = false },
+            ]
+            "#,
+        )
+        .unwrap();
+        let columns = resolve_columns(&config.tables[0], &HashMap::new(), &Locale::En.into()).unwrap();
+        assert!(matches!(columns[0].kind, ColumnKind::NationalId { country: Country::Us, synthetic: true }));
+        assert!(matches!(columns[1].kind, ColumnKind::NationalId { country: Country::Br, synthetic: false }));
+        assert!(toml::from_str::<Config>("[[table]]\nname = \"a\"\nrows = 1\ncolumn = [{ name = \"id\", kind = \"national_id\", country = \"xx\" }]").is_err());
+    }
+
+    #[test]
+    fn geo_columns_take_a_country_or_bounds() {
+        let config: Config = toml::from_str(
+            r#"
+            [[table]]
+            name = "stores"
+            rows = 1
+            column = [
+                { name = "cell", kind = "geohash", country = "jp" },
+                { name = "site", kind = "polygon", bounds = [-1, 50, 1, 52], vertices = 4 },
+                { name = "lat", kind = "latitude", country = "atlantis" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let mut table = config.tables.into_iter().next().unwrap();
+        let error = resolve_columns(&table, &HashMap::new(), &Locale::En.into()).unwrap_err();
+        assert!(error.to_string().contains("no country atlantis; try us, ca"), "{error}");
+        table.columns.pop();
+        let columns = resolve_columns(&table, &HashMap::new(), &Locale::En.into()).unwrap();
+        assert!(matches!(&columns[0].kind, ColumnKind::Geo { shape: geo::Shape::Geohash { precision: 7 }, area } if area[..] == *geo::country("jp").unwrap()));
+        let ColumnKind::Geo { shape: geo::Shape::Polygon { vertices: 4, radius }, area } = &columns[1].kind else { panic!() };
+        assert_eq!((*radius, area[0]), (500.0, geo::Bounds { south: 50.0, west: -1.0, north: 52.0, east: 1.0 }));
+    }
+
+    #[test]
+    fn columns_default_to_the_run_locale() {
+        let config: Config = toml::from_str(
+            r#"
+            [[table]]
+            name = "people"
+            rows = 1
+            column = [{ name = "city", kind = "city" }, { name = "phone", kind = "phone", locale = "pt-BR" }]
+            "#,
+        )
+        .unwrap();
+        let columns = resolve_columns(&config.tables[0], &HashMap::new(), &Locale::De.into()).unwrap();
+        let ColumnKind::Choice(cities) = &columns[0].kind else { panic!("{:?}", columns[0].kind) };
+        assert!(cities.iter().any(|c| c == "München"));
+        let ColumnKind::Format { formats, .. } = &columns[1].kind else { panic!("{:?}", columns[1].kind) };
+        assert!(formats.iter().all(|f| f.starts_with("(%#)") || f.starts_with("+55")));
+    }
+
+    #[test]
+    fn references_match_the_requested_fraction() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_overlap_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config: Config = toml::from_str(
+            r#"
+            [[table]]
+            name = "a"
+            rows = 50
+            column = [{ name = "id", kind = "sequence" }]
+            [[table]]
+            name = "b"
+            rows = 1000
+            column = [{ name = "a_id", kind = "reference", table = "a", matching = 0.75 }]
+            "#,
+        )
+        .unwrap();
+        let args = Args::parse_from(["gen", "--output", dir.join("{table}.csv").to_str().unwrap()]);
+        generate(&args, &config).unwrap();
+        let keys: Vec<i64> = csv::Reader::from_path(dir.join("b.csv"))
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap()[0].parse().unwrap())
+            .collect();
+        assert_eq!(keys.iter().filter(|&&k| (1..=50).contains(&k)).count(), 750);
+        assert!(keys.iter().all(|&k| (1..=100).contains(&k)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
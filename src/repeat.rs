@@ -0,0 +1,198 @@
+//! `--repeat-rate` and `--compression-ratio`: how compressible the data is.
+//! Random keys like SHA-256 hex barely compress, so a compression benchmark
+//! needs a way to move toward the other extreme: some values of each column
+//! repeat one of the column's recent values instead, like the repeated
+//! strings of logs and event tables.
+//!
+//! `--compression-ratio` finds the repeat rate at which a sample of the
+//! table's rows, as CSV, compresses that well with gzip.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Mutex;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::generator::{Generator, Row};
+use crate::schema::Column;
+use crate::shard_rng;
+use crate::sink::Sink;
+
+/// Recent values of a column that one may repeat.
+const WINDOW: usize = 16;
+
+/// Bytes of CSV rows that `--compression-ratio` compresses to measure a rate.
+const SAMPLE_BYTES: usize = 256 << 10;
+
+/// Halvings of the range of repeat rates `--compression-ratio` searches.
+const SEARCH_STEPS: usize = 16;
+
+/// Mixed into `--seed`, so values repeat independently of how they're
+/// generated.
+const SEED_SALT: u64 = 0x7265_7065_6174;
+
+/// The repeat rates found for `--compression-ratio`, by the JSON of the
+/// columns, so each table is sampled once rather than once per shard.
+static RATES: Mutex<Option<HashMap<String, f64>>> = Mutex::new(None);
+
+/// Repeats values of the rows it's given at a rate.
+struct Repeater {
+    rate: f64,
+    rng: StdRng,
+    /// Of each column, its recent fresh values, and the one to replace next.
+    windows: Vec<(Vec<Vec<u8>>, usize)>,
+}
+
+impl Repeater {
+    fn new(columns: usize, rate: f64, rng: StdRng) -> Self {
+        Repeater { rate, rng, windows: vec![(Vec::new(), 0); columns] }
+    }
+
+    /// `row`, with some values repeated, into `out`.
+    fn apply(&mut self, row: &Row, out: &mut Row) {
+        out.clear();
+        for (field, (window, next)) in row.fields().zip(&mut self.windows) {
+            if !window.is_empty() && self.rng.gen_bool(self.rate) {
+                out.push(&window[self.rng.gen_range(0..window.len())]);
+                continue;
+            }
+            out.push(field);
+            if window.len() < WINDOW {
+                window.push(field.to_vec());
+            } else {
+                window[*next].clear();
+                window[*next].extend_from_slice(field);
+                *next = (*next + 1) % WINDOW;
+            }
+        }
+    }
+}
+
+/// How well `sample` compresses with gzip, as CSV, with values repeated at
+/// `rate`.
+fn ratio(sample: &[Row], rate: f64) -> Result<f64, BoxError> {
+    let columns = sample.first().map_or(0, |row| row.fields().count());
+    let mut repeater = Repeater::new(columns, rate, StdRng::seed_from_u64(0));
+    let (mut raw, mut row) = (Vec::with_capacity(SAMPLE_BYTES), Row::new());
+    for sampled in sample {
+        repeater.apply(sampled, &mut row);
+        for (i, field) in row.fields().enumerate() {
+            if i > 0 {
+                raw.push(b',');
+            }
+            raw.extend_from_slice(field);
+        }
+        raw.push(b'\n');
+    }
+    let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+    gzip.write_all(&raw)?;
+    Ok(raw.len() as f64 / gzip.finish()?.len() as f64)
+}
+
+/// The repeat rate at which rows of `columns` compress `target` to 1.
+fn calibrate(columns: &[Column], target: f64) -> Result<f64, BoxError> {
+    let generator = Generator::compile(columns)?;
+    let (mut sample, mut bytes) = (Vec::new(), 0);
+    for row in generator.rows(StdRng::seed_from_u64(0)) {
+        bytes += row.fields().map(|f| f.len() + 1).sum::<usize>();
+        sample.push(row);
+        if bytes >= SAMPLE_BYTES {
+            break;
+        }
+    }
+    let (least, most) = (ratio(&sample, 0.0)?, ratio(&sample, 1.0)?);
+    if target < least {
+        return Err(format!("--compression-ratio {target}: these columns compress {least:.1}:1 without repeats").into());
+    }
+    if target > most {
+        return Err(format!("--compression-ratio {target}: these columns compress at most {most:.1}:1").into());
+    }
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..SEARCH_STEPS {
+        let middle = (low + high) / 2.0;
+        if ratio(&sample, middle)? < target {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(high)
+}
+
+/// Repeats some values of the rows passed on.
+pub struct RepeatSink {
+    inner: Box<dyn Sink>,
+    repeater: Repeater,
+    row: Row,
+}
+
+impl RepeatSink {
+    /// Wraps `inner`, writing rows of `columns` to shard `shard`, unless
+    /// `args` repeats no values.
+    pub fn wrap(inner: Box<dyn Sink>, args: &Args, columns: &[Column], shard: u64) -> Result<Box<dyn Sink>, BoxError> {
+        let rate = match (args.repeat_rate, args.compression_ratio) {
+            (_, Some(target)) => {
+                let key = serde_json::to_string(columns)?;
+                let mut rates = RATES.lock().unwrap();
+                let rates = rates.get_or_insert_with(HashMap::new);
+                match rates.get(&key) {
+                    Some(&rate) => rate,
+                    None => {
+                        let rate = calibrate(columns, target)?;
+                        println!("Repeating {:.1}% of values for a compression ratio of {target}", rate * 100.0);
+                        *rates.entry(key).or_insert(rate)
+                    }
+                }
+            }
+            (rate, None) => rate,
+        };
+        if rate == 0.0 {
+            return Ok(inner);
+        }
+        let rng = shard_rng(args.seed.map(|seed| seed ^ SEED_SALT), shard);
+        Ok(Box::new(RepeatSink { inner, repeater: Repeater::new(columns.len(), rate, rng), row: Row::new() }))
+    }
+}
+
+impl Sink for RepeatSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.repeater.apply(row, &mut self.row);
+        self.inner.write_row(&self.row)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.inner.flush()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnKind;
+
+    #[test]
+    fn repeats_reach_the_compression_ratio_asked_for() {
+        let columns = [Column::new("id", ColumnKind::Sha256Hex), Column::new("n", ColumnKind::IntRange { min: 0, max: 1 << 40 })];
+        let rate = calibrate(&columns, 4.0).unwrap();
+        assert!(rate > 0.0 && rate < 1.0, "{rate}");
+        let generator = Generator::compile(&columns).unwrap();
+        let sample: Vec<_> = generator.rows(StdRng::seed_from_u64(1)).take(3000).collect();
+        let ratio = ratio(&sample, rate).unwrap();
+        assert!((3.6..4.4).contains(&ratio), "{ratio}");
+        assert!(calibrate(&columns, 1.0).is_err());
+        assert!(calibrate(&columns, 1000.0).is_err());
+    }
+}
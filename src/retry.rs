@@ -0,0 +1,164 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand::Rng;
+use serde_json::{Value, json};
+
+use crate::BoxError;
+use crate::cli::RetryArgs;
+use crate::telemetry;
+
+/// `--retry-attempts`, `--retry-delay`, `--retry-max-delay` and
+/// `--dead-letter`, or their defaults for library callers.
+struct Policy {
+    attempts: u32,
+    delay: Duration,
+    max_delay: Duration,
+    dead_letter: Option<PathBuf>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy { attempts: 6, delay: Duration::from_millis(500), max_delay: Duration::from_secs(30), dead_letter: None }
+    }
+}
+
+static POLICY: OnceLock<Policy> = OnceLock::new();
+
+/// Calls made, those that needed more than one attempt, and those that
+/// failed after every attempt.
+static CALLS: AtomicU64 = AtomicU64::new(0);
+static RETRIED: AtomicU64 = AtomicU64::new(0);
+static FAILED: AtomicU64 = AtomicU64::new(0);
+
+/// Records appended to `--dead-letter`.
+static DEAD_LETTERED: AtomicU64 = AtomicU64::new(0);
+
+/// The `--dead-letter` file, opened at its first record.
+static DEAD_LETTER: Mutex<Option<BufWriter<File>>> = Mutex::new(None);
+
+fn policy() -> &'static Policy {
+    POLICY.get_or_init(Policy::default)
+}
+
+/// Retries the rest of the process's calls as `args` says.
+pub(crate) fn configure(args: &RetryArgs) {
+    let policy = Policy {
+        attempts: args.retry_attempts,
+        delay: args.retry_delay,
+        max_delay: args.retry_max_delay.max(args.retry_delay),
+        dead_letter: args.dead_letter.clone(),
+    };
+    let _ = POLICY.set(policy);
+}
+
+/// The outcome of a failed attempt.
+pub enum Failure {
+    /// Worth trying again, e.g. a dropped connection or a 503.
+    Transient(BoxError),
+    /// Retrying cannot help, e.g. a 403.
+    Fatal(BoxError),
+}
+
+/// Runs `op` until it succeeds, fails fatally, or runs out of attempts,
+/// doubling the delay between attempts.
+pub fn retry<T>(what: &str, mut op: impl FnMut() -> Result<T, Failure>) -> Result<T, BoxError> {
+    let Policy { attempts, delay: mut next, max_delay, .. } = *policy();
+    let mut span = telemetry::span("request").client();
+    span.set("request", what);
+    let mut attempt = 1;
+    let result = loop {
+        match op() {
+            Ok(value) => break Ok(value),
+            Err(Failure::Fatal(e)) => break Err(format!("{what} failed: {e}").into()),
+            Err(Failure::Transient(e)) if attempt >= attempts => {
+                break Err(format!("{what} failed after {attempt} attempts: {e}").into());
+            }
+            Err(Failure::Transient(e)) => {
+                let delay = next.mul_f64(rand::thread_rng().gen_range(0.5..=1.0));
+                eprintln!("{what} failed (attempt {attempt}/{attempts}): {e}; retrying in {delay:.1?}");
+                thread::sleep(delay);
+                next = (next * 2).min(max_delay);
+                attempt += 1;
+            }
+        }
+    };
+    CALLS.fetch_add(1, Ordering::Relaxed);
+    if attempt > 1 {
+        RETRIED.fetch_add(1, Ordering::Relaxed);
+    }
+    span.set("attempts", u64::from(attempt));
+    if let Err(e) = &result {
+        FAILED.fetch_add(1, Ordering::Relaxed);
+        span.fail(e);
+    }
+    result
+}
+
+/// Appends `records`, which `destination` couldn't be sent, to
+/// `--dead-letter` so the run can go on, or returns `error` without it.
+pub(crate) fn dead_letter(destination: &str, error: BoxError, records: impl IntoIterator<Item = Value>) -> Result<(), BoxError> {
+    let Some(path) = &policy().dead_letter else { return Err(error) };
+    let mut file = DEAD_LETTER.lock().unwrap();
+    let file = match &mut *file {
+        Some(file) => file,
+        None => {
+            let opened = OpenOptions::new().create(true).append(true).open(path);
+            file.insert(BufWriter::new(opened.map_err(|e| format!("--dead-letter {}: {e}", path.display()))?))
+        }
+    };
+    let error = error.to_string();
+    let mut count = 0;
+    for record in records {
+        serde_json::to_writer(&mut *file, &json!({"destination": destination, "error": error, "record": record}))?;
+        file.write_all(b"\n")?;
+        count += 1;
+    }
+    // Written as they come, so a run that fails later still keeps them.
+    file.flush()?;
+    DEAD_LETTERED.fetch_add(count, Ordering::Relaxed);
+    eprintln!("{error}; wrote {count} records to {}", path.display());
+    Ok(())
+}
+
+/// `bytes` in a dead-letter record: as text when it's UTF-8, otherwise as
+/// `{"base64": ...}`.
+pub(crate) fn payload(bytes: &[u8]) -> Value {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Value::from(text),
+        Err(_) => json!({"base64": STANDARD.encode(bytes)}),
+    }
+}
+
+/// How the run's calls to services went, if it made any.
+pub(crate) fn summary() -> Option<String> {
+    let calls = CALLS.load(Ordering::Relaxed);
+    if calls == 0 {
+        return None;
+    }
+    let (retried, failed) = (RETRIED.load(Ordering::Relaxed), FAILED.load(Ordering::Relaxed));
+    let mut summary = format!("Made {calls} calls to services: {retried} retried, {failed} failed");
+    if let Some(path) = &policy().dead_letter {
+        let count = DEAD_LETTERED.load(Ordering::Relaxed);
+        summary.push_str(&format!("; {count} records went to {}", path.display()));
+    }
+    Some(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dead_letter_payloads_keep_text_readable() {
+        assert_eq!(payload(b"a,1"), json!("a,1"));
+        assert_eq!(payload(&[0, 0xff]), json!({"base64": "AP8="}));
+    }
+}
@@ -0,0 +1,114 @@
+//! `--rotate-every` and `--rotate-size`: generation as a daemon, writing
+//! one file at a time like an application writing logs, for file-watcher
+//! ingestion pipelines. Each file is finished before the next is opened, so
+//! a watcher never sees a rotated file change again.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use crate::cli::Args;
+use crate::generator::{Generator, Row};
+use crate::output::{self, Destination, Outputs};
+use crate::schema::Column;
+use crate::throttle::RateLimiter;
+use crate::{BoxError, shard_rng, signal, sink, trend};
+
+/// How long rows may wait in a file's buffer before they are flushed to it,
+/// so a watcher sees a slow trickle of rows as it comes.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Rows written between checks of the clock, when not rate limited.
+const CHECK_ROWS: u64 = 1024;
+
+/// The location of file `n`, opened now: `{time}` is when, to the second.
+fn file_location(template: &str, n: usize) -> String {
+    output::shard_location(template, n).replace("{time}", &Utc::now().format("%Y%m%dT%H%M%SZ").to_string())
+}
+
+/// Writes `columns` to a new file every `--rotate-every` or `--rotate-size`
+/// until a signal stops the run or it reaches `--rows` or `--size`,
+/// deleting all but the newest `--retain` files.
+pub fn generate(args: &Args, columns: &[Column]) -> Result<(), BoxError> {
+    let rotate = &args.rotate;
+    if !args.output.contains("{time}") && !output::has_shard(&args.output) {
+        return Err("--output must contain {time} or {n} so each rotated file gets a name of its own".into());
+    }
+    if args.shards > 1 {
+        return Err("rotated output is one file at a time, so it takes only one shard".into());
+    }
+    let destination = Destination::parse(&file_location(&args.output, 0))?;
+    if !destination.is_file() || destination == Destination::Stdout {
+        return Err(format!("{} isn't a file, so it can't be rotated", args.output).into());
+    }
+    if rotate.retain.is_some() && !matches!(destination, Destination::File(_)) {
+        return Err("--retain only deletes local files".into());
+    }
+
+    if !args.backfill.trend.is_empty() && args.rate.is_none() {
+        return Err("--trend shapes a stream through its --rate, so it needs one".into());
+    }
+
+    let outputs = Outputs::new(args);
+    let generator = Generator::compile(columns)?;
+    let today = || Utc::now().timestamp().div_euclid(86_400);
+    let started = today();
+    let (row_limit, size_limit) = (args.rows.unwrap_or(u64::MAX), args.size.unwrap_or(u64::MAX));
+    let (every, file_size) = (rotate.rotate_every.unwrap_or(Duration::MAX), rotate.rotate_size.unwrap_or(u64::MAX));
+    let mut rows = generator.rows(shard_rng(args.seed, 0));
+    let mut row = Row::new();
+    let (mut total_rows, mut total_bytes) = (0, 0);
+    let mut kept = VecDeque::new();
+    println!("Generating {} until stopped, rotating files", args.output);
+    for n in 0.. {
+        let location = file_location(&args.output, n);
+        let mut sink = sink::open(&outputs, &Destination::parse(&location)?, columns)?;
+        // Each file is written at the rate its day's trend gives.
+        let limiter = args.rate.map(|rate| RateLimiter::new(rate * trend::factor(&args.backfill.trend, started, today())));
+        let check = limiter.as_ref().map_or(CHECK_ROWS, RateLimiter::burst);
+        let (opened, mut flushed) = (Instant::now(), Instant::now());
+        let mut file_rows = 0;
+        let done = loop {
+            if file_rows % check == 0 {
+                if signal::interrupted() {
+                    break true;
+                }
+                if opened.elapsed() >= every || sink.bytes_written() >= file_size {
+                    break false;
+                }
+                if let Some(limiter) = &limiter {
+                    limiter.acquire(check);
+                }
+                if flushed.elapsed() >= FLUSH_INTERVAL {
+                    sink.flush()?;
+                    flushed = Instant::now();
+                }
+            }
+            if total_rows + file_rows >= row_limit || total_bytes + sink.bytes_written() >= size_limit {
+                break true;
+            }
+            rows.fill_next(&mut row);
+            sink.write_row(&row)?;
+            file_rows += 1;
+        };
+        total_bytes += sink.bytes_written();
+        total_rows += file_rows;
+        sink.finish()?;
+        println!("Rotated {location} ({file_rows} rows)");
+        if let Some(retain) = rotate.retain {
+            kept.push_back(location);
+            while kept.len() as u64 > retain {
+                let old = kept.pop_front().expect("more than `retain` files are kept");
+                fs::remove_file(&old).map_err(|e| format!("removing {old}: {e}"))?;
+                println!("Removed {old}");
+            }
+        }
+        if done {
+            break;
+        }
+    }
+    println!("Wrote {total_rows} rows in total");
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! `--manifest`: a JSON listing of every file a run wrote, with its size, row
+//! count, checksums and first and last keys, for loaders to check what they
+//! received against. The MD5 and CRC32C are of the bytes as stored, gzipped
+//! or not, and base64-encoded big-endian as GCS reports them in an object's
+//! `md5Hash` and `crc32c`, so uploads can be compared without downloading.
+
+use std::fs;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+// md-5 and sha2 are on different versions of `digest`, so each has its own.
+use md5::{Digest as _, Md5};
+use serde::Serialize;
+use sha2::{Digest as _, Sha256};
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::generator::Row;
+use crate::output::{Destination, Output, Outputs};
+use crate::sink::Sink;
+
+static CRC32C: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISCSI);
+
+/// The files finished so far in the run.
+static FILES: Mutex<Vec<File>> = Mutex::new(Vec::new());
+
+/// One file of the manifest.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct File {
+    pub location: String,
+    pub bytes: u64,
+    pub rows: u64,
+    pub md5: String,
+    pub crc32c: String,
+    /// The first field of the first and last rows, which is the key of
+    /// every built-in and preset table and of configs that list it first.
+    pub first_key: Option<String>,
+    pub last_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    version: &'a str,
+    /// SHA-256 of the settings and config file the run was given, for
+    /// telling whether two runs were generated alike.
+    config_hash: String,
+    rows: u64,
+    bytes: u64,
+    files: &'a [File],
+}
+
+/// The listing of the file at `destination`, to be filled in as it's
+/// written, if `--manifest` or `--archive` is given and it's a file or
+/// cloud object.
+pub fn entry(args: &Args, destination: &Destination) -> Option<Arc<Mutex<File>>> {
+    if args.manifest.is_none() && args.archive.is_none() {
+        return None;
+    }
+    let location = match destination {
+        Destination::File(path) => path.display().to_string(),
+        Destination::Gcs { bucket, object } => format!("gs://{bucket}/{object}"),
+        Destination::S3 { bucket, key } => format!("s3://{bucket}/{key}"),
+        Destination::Azure { container, blob } => format!("az://{container}/{blob}"),
+        _ => return None,
+    };
+    Some(Arc::new(Mutex::new(File { location, ..File::default() })))
+}
+
+/// Writes the manifest of the files finished so far, if `--manifest` is
+/// given.
+pub fn write(args: &Args) -> Result<(), BoxError> {
+    let Some(location) = &args.manifest else { return Ok(()) };
+    let mut output = Outputs::new(args).open(&Destination::parse(location)?)?;
+    output.write_all(&json(args)?)?;
+    output.finish()
+}
+
+/// The manifest of the files finished so far, as `--manifest` writes it.
+pub fn json(args: &Args) -> Result<Vec<u8>, BoxError> {
+    let mut files = FILES.lock().unwrap().clone();
+    files.sort_by(|a, b| a.location.cmp(&b.location));
+    let manifest = Manifest {
+        version: env!("CARGO_PKG_VERSION"),
+        config_hash: config_hash(args)?,
+        rows: files.iter().map(|f| f.rows).sum(),
+        bytes: files.iter().map(|f| f.bytes).sum(),
+        files: &files,
+    };
+    let mut json = serde_json::to_vec_pretty(&manifest)?;
+    json.push(b'\n');
+    Ok(json)
+}
+
+/// SHA-256 of the parsed command line and the contents of `--config`.
+fn config_hash(args: &Args) -> Result<String, BoxError> {
+    let mut hasher = Sha256::new();
+    // Where the manifest and effective config go doesn't change what's generated.
+    hasher.update(format!("{:?}", Args { manifest: None, effective_config: None, archive: None, ..args.clone() }));
+    if let Some(path) = &args.config {
+        hasher.update(fs::read(path).map_err(|e| format!("reading {}: {e}", path.display()))?);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Sums the bytes written on the way to `inner` into `file`.
+pub struct SummedOutput {
+    inner: Box<dyn Output>,
+    file: Arc<Mutex<File>>,
+    md5: Md5,
+    crc32c: crc::Digest<'static, u32>,
+    bytes: u64,
+}
+
+impl SummedOutput {
+    /// Wraps `inner`, unless there's no `file` to sum into.
+    pub fn wrap(inner: Box<dyn Output>, file: Option<&Arc<Mutex<File>>>) -> Box<dyn Output> {
+        match file {
+            Some(file) => Box::new(SummedOutput {
+                inner,
+                file: file.clone(),
+                md5: Md5::new(),
+                crc32c: CRC32C.digest(),
+                bytes: 0,
+            }),
+            None => inner,
+        }
+    }
+}
+
+impl Write for SummedOutput {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(data)?;
+        self.md5.update(&data[..written]);
+        self.crc32c.update(&data[..written]);
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Output for SummedOutput {
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.inner.finish()?;
+        let mut file = self.file.lock().unwrap();
+        file.bytes = self.bytes;
+        file.md5 = STANDARD.encode(self.md5.finalize());
+        file.crc32c = STANDARD.encode(self.crc32c.finalize().to_be_bytes());
+        Ok(())
+    }
+}
+
+/// Counts the rows passed on and keeps the first and last keys, adding the
+/// file to the manifest once it's finished.
+pub struct ListedSink {
+    inner: Box<dyn Sink>,
+    file: Arc<Mutex<File>>,
+    rows: u64,
+    first_key: Option<String>,
+    last_key: Vec<u8>,
+}
+
+impl ListedSink {
+    /// Wraps `inner`, unless there's no `file` to list it in.
+    pub fn wrap(inner: Box<dyn Sink>, file: Option<Arc<Mutex<File>>>) -> Box<dyn Sink> {
+        match file {
+            Some(file) => Box::new(ListedSink { inner, file, rows: 0, first_key: None, last_key: Vec::new() }),
+            None => inner,
+        }
+    }
+}
+
+impl Sink for ListedSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        let key = row.fields().next().unwrap_or_default();
+        if self.rows == 0 {
+            self.first_key = Some(String::from_utf8_lossy(key).into_owned());
+        }
+        self.last_key.clear();
+        self.last_key.extend_from_slice(key);
+        self.rows += 1;
+        self.inner.write_row(row)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.inner.flush()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.inner.finish()?;
+        let mut file = self.file.lock().unwrap().clone();
+        file.rows = self.rows;
+        if self.rows > 0 {
+            file.first_key = self.first_key;
+            file.last_key = Some(String::from_utf8_lossy(&self.last_key).into_owned());
+        }
+        FILES.lock().unwrap().push(file);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Column, ColumnKind};
+    use crate::sink;
+    use clap::Parser;
+
+    #[test]
+    fn lists_what_each_file_holds() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_run_manifest_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.csv.gz");
+        let manifest = dir.join("manifest.json");
+        let args = Args::parse_from([
+            "gen",
+            "--output",
+            path.to_str().unwrap(),
+            "--compression",
+            "gzip",
+            "--manifest",
+            manifest.to_str().unwrap(),
+        ]);
+        let columns = [Column::new("id", ColumnKind::Sequence { start: 1 })];
+        let mut sink = sink::open(&Outputs::new(&args), &Destination::File(path.clone()), &columns).unwrap();
+        let mut row = Row::new();
+        for i in 1..=10 {
+            row.clear();
+            row.push(i.to_string().as_bytes());
+            sink.write_row(&row).unwrap();
+        }
+        sink.finish().unwrap();
+        write(&args).unwrap();
+
+        let manifest: serde_json::Value = serde_json::from_slice(&fs::read(&manifest).unwrap()).unwrap();
+        let files = manifest["files"].as_array().unwrap();
+        let file = files.iter().find(|f| f["location"] == path.to_str().unwrap()).unwrap();
+        let data = fs::read(&path).unwrap();
+        assert_eq!(file["bytes"], data.len());
+        assert_eq!(file["rows"], 10);
+        assert_eq!((&file["first_key"], &file["last_key"]), (&"1".into(), &"10".into()));
+        assert_eq!(file["md5"], STANDARD.encode(Md5::digest(&data)));
+        assert_eq!(file["crc32c"], STANDARD.encode(CRC32C.checksum(&data).to_be_bytes()));
+        // The check value of CRC-32C.
+        assert_eq!(CRC32C.checksum(b"123456789"), 0xe306_9283);
+        assert_eq!(manifest["config_hash"].as_str().unwrap().len(), 64);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
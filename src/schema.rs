@@ -0,0 +1,353 @@
+use serde::{Deserialize, Serialize};
+
+use crate::BoxError;
+
+use crate::access_log;
+use crate::business;
+use crate::geo::{Bounds, Shape};
+use crate::locale::{Field, LocaleMix};
+use crate::national_id::Country;
+use crate::sink::column_indices;
+
+/// A single output column: its header name and how its values are produced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Column {
+    pub name: String,
+    pub kind: ColumnKind,
+}
+
+/// The value generators a column can use.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnKind {
+    /// Hex-encoded SHA256 of 32 random bytes.
+    Sha256Hex,
+    /// A random version 4 UUID, in lower case with hyphens.
+    UuidV4,
+    /// A uniformly chosen entry from a fixed list.
+    Choice(Vec<String>),
+    /// A uniformly chosen entry from a fixed list of integers, which can
+    /// repeat an entry to weight it.
+    IntChoice(Vec<i64>),
+    /// A uniformly chosen integer in `min..=max`.
+    IntRange { min: i64, max: i64 },
+    /// `start` plus the row's index within its table, for primary keys.
+    Sequence { start: i64 },
+    /// The key of the parent row a child row was generated under.
+    ParentKey,
+    /// A key of another table, uniformly chosen from its keys `min..=max`
+    /// for a `matching` fraction of the rows, spread evenly by row index, and
+    /// from the equally many keys after `max` for the rest, which exist in
+    /// no row of it. A positive `skew` makes the matching keys follow a power
+    /// law with that exponent instead, so that low keys are referenced most.
+    ForeignKey { min: i64, max: i64, matching: f64, skew: f64 },
+    /// A child row's position under its parent, from 1, like a line number.
+    ChildNumber,
+    /// `prefix` and the row's index from 1, zero-padded to `width` digits,
+    /// e.g. `Customer#000000042`.
+    Label { prefix: String, width: usize },
+    /// The entries of a list in turn, by row index, so that the first rows
+    /// of a table of `n` rows each get a different one.
+    Cycle(Vec<String>),
+    /// A uniformly chosen `YYYY-MM-DD` date, in days since 1970-01-01.
+    DateRange { min: i64, max: i64 },
+    /// A uniformly chosen `YYYY-MM-DD HH:MM:SS` time in UTC, in seconds
+    /// since 1970-01-01.
+    TimestampRange { min: i64, max: i64 },
+    /// `start` plus the row's index over `per_second` rows a second, less a
+    /// lag with mean `skew` seconds, so times advance with the rows, a few
+    /// out of order, like lines of a log.
+    Clock { start: i64, per_second: f64, skew: f64 },
+    /// A uniformly chosen number with `scale` decimal places, given as the
+    /// integers `min` and `max` scaled by `10^scale`.
+    Decimal { min: i64, max: i64, scale: u8 },
+    /// One of `formats`, chosen uniformly, with each `#` replaced by a random
+    /// digit, each `%` by a random digit other than 0 and each `{i}` by a
+    /// uniformly chosen entry of `lists[i]`, like `(%##) %##-####`.
+    Format { formats: Vec<String>, lists: Vec<Vec<String>> },
+    /// The time in column `of`, an earlier timestamp range, clock or lagged
+    /// column of the row, plus a random `lag`, like the ingestion time of an
+    /// event.
+    Lagged { of: usize, lag: Lag },
+    /// `min..=max` graphemes, each of a class drawn from `mix` and plain
+    /// ASCII otherwise, for finding encoding and width bugs downstream.
+    Unicode { min: usize, max: usize, mix: UnicodeMix },
+    /// A national identifier of `country` with valid check digits, from a
+    /// range that is never issued where there is one if `synthetic`.
+    NationalId { country: Country, synthetic: bool },
+    /// A uniformly chosen point in `area`, written as `shape`. The columns
+    /// of a row with the same area share its point, so that a latitude, a
+    /// longitude and a geohash describe the same place.
+    Geo { shape: Shape, area: Vec<Bounds> },
+    /// The value of a [`script`](crate::script), which can read the row's
+    /// earlier columns, written as `data_type`.
+    Script { source: String, data_type: DataType },
+    /// The value of the column generator a [`plugin`](crate::plugin)
+    /// registered as `name`, written as `data_type`.
+    Plugin { name: String, data_type: DataType },
+}
+
+/// The distribution of a [`ColumnKind::Lagged`] column's lag, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "distribution", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Lag {
+    Fixed { seconds: i64 },
+    Uniform { min: i64, max: i64 },
+    Exponential { mean: f64 },
+    /// Most lags near `median` and a long tail of late ones; `sigma` is the
+    /// standard deviation of the lag's logarithm.
+    LogNormal { median: f64, sigma: f64 },
+}
+
+impl Lag {
+    /// Why the lag can't be sampled, if it can't.
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            Lag::Fixed { seconds } if seconds < 0 => Err(format!("lag {seconds} is negative")),
+            Lag::Uniform { min, max } if min < 0 || min > max => {
+                Err(format!("lag range {min} to {max} isn't a non-negative range"))
+            }
+            Lag::Exponential { mean } if !(mean >= 0.0 && mean.is_finite()) => {
+                Err(format!("lag mean {mean} is not a non-negative number"))
+            }
+            Lag::LogNormal { median, sigma } if !(median > 0.0 && median.is_finite() && sigma >= 0.0 && sigma.is_finite()) => {
+                Err(format!("log-normal lag needs a positive median and a non-negative sigma, not {median} and {sigma}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The rates at which a [`ColumnKind::Unicode`] column's graphemes are of
+/// each class rather than ASCII, which add up to at most 1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct UnicodeMix {
+    pub emoji: f64,
+    pub cjk: f64,
+    pub rtl: f64,
+    pub combining: f64,
+    pub zero_width: f64,
+    pub long_clusters: f64,
+    /// Commas, quotes, line breaks and other characters that need quoting.
+    pub specials: f64,
+}
+
+impl UnicodeMix {
+    /// Why the rates can't be drawn from, if they can't.
+    pub fn check(&self) -> Result<(), String> {
+        let rates = [self.emoji, self.cjk, self.rtl, self.combining, self.zero_width, self.long_clusters, self.specials];
+        if rates.iter().any(|rate| !(0.0..=1.0).contains(rate)) {
+            return Err("unicode rates must be between 0 and 1".to_string());
+        }
+        match rates.iter().sum::<f64>() {
+            total if total > 1.0 + 1e-9 => Err(format!("unicode rates add up to {total}, more than 1")),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for UnicodeMix {
+    fn default() -> Self {
+        UnicodeMix { emoji: 0.1, cjk: 0.1, rtl: 0.1, combining: 0.05, zero_width: 0.05, long_clusters: 0.01, specials: 0.02 }
+    }
+}
+
+/// How many of a table's first `rows` rows a [`ColumnKind::ForeignKey`]
+/// column with `matching` points at an existing row.
+pub fn matching_rows(rows: u64, matching: f64) -> u64 {
+    (rows as f64 * matching).floor() as u64
+}
+
+/// Whether row `index` is one of the matching rows of a
+/// [`ColumnKind::ForeignKey`] column: the row at which the count of
+/// [`matching_rows`] goes up.
+pub fn is_matching_row(index: u64, matching: f64) -> bool {
+    matching_rows(index + 1, matching) > matching_rows(index, matching)
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>, kind: ColumnKind) -> Self {
+        Column { name: name.into(), kind }
+    }
+}
+
+/// The logical type of a column's values, used wherever the output is
+/// described to another system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataType {
+    String,
+    Integer,
+    /// A fixed-point number with `scale` decimal places, written like `12.50`.
+    Decimal { scale: u8 },
+    /// A calendar date, written `YYYY-MM-DD`.
+    Date,
+    /// A time in UTC to the second, written `YYYY-MM-DD HH:MM:SS`.
+    Timestamp,
+}
+
+impl DataType {
+    /// Whether `field` has no value: an empty cell of any type but a
+    /// string, like a root employee's `manager_id`, which typed formats
+    /// write as a null.
+    pub fn is_null(self, field: &[u8]) -> bool {
+        field.is_empty() && self != DataType::String
+    }
+}
+
+/// Digits a decimal column holds in total, which fits the `i64` it is generated from.
+pub const DECIMAL_PRECISION: u8 = 18;
+
+impl ColumnKind {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            ColumnKind::Sha256Hex
+            | ColumnKind::UuidV4
+            | ColumnKind::Choice(_)
+            | ColumnKind::Label { .. }
+            | ColumnKind::Cycle(_)
+            | ColumnKind::Format { .. }
+            | ColumnKind::Unicode { .. }
+            | ColumnKind::NationalId { .. } => DataType::String,
+            ColumnKind::Geo { shape: Shape::Latitude | Shape::Longitude, .. } => DataType::Decimal { scale: 6 },
+            ColumnKind::Geo { .. } => DataType::String,
+            ColumnKind::IntRange { .. }
+            | ColumnKind::IntChoice(_)
+            | ColumnKind::Sequence { .. }
+            | ColumnKind::ParentKey
+            | ColumnKind::ForeignKey { .. }
+            | ColumnKind::ChildNumber => DataType::Integer,
+            ColumnKind::DateRange { .. } => DataType::Date,
+            ColumnKind::TimestampRange { .. } | ColumnKind::Clock { .. } | ColumnKind::Lagged { .. } => DataType::Timestamp,
+            ColumnKind::Decimal { scale, .. } => DataType::Decimal { scale: *scale },
+            ColumnKind::Script { data_type, .. } | ColumnKind::Plugin { data_type, .. } => *data_type,
+        }
+    }
+}
+
+/// The `(year, month, day)` of a day counted from 1970-01-01, by Howard
+/// Hinnant's `civil_from_days`.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// The day counted from 1970-01-01 of a `(year, month, day)`, by Howard
+/// Hinnant's `days_from_civil`.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Parses a `YYYY-MM-DD` date into days since 1970-01-01.
+pub fn parse_date(s: &str) -> Option<i64> {
+    let date = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+    let epoch = chrono::NaiveDate::from_ymd_opt(1970, 1, 1)?;
+    Some(date.signed_duration_since(epoch).num_days())
+}
+
+/// Parses a `YYYY-MM-DD HH:MM:SS` time, or a `YYYY-MM-DD` date for its
+/// midnight, into seconds since 1970-01-01 UTC.
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    match chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
+        Ok(time) => Some(time.and_utc().timestamp()),
+        Err(_) => parse_date(s).map(|days| days * 86_400),
+    }
+}
+
+/// Formats days since 1970-01-01 as `YYYY-MM-DD`.
+pub fn format_date(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn choice(items: &[&str]) -> ColumnKind {
+    ColumnKind::Choice(items.iter().map(|s| s.to_string()).collect())
+}
+
+/// The default schema: a random id, a first name and an age.
+pub fn users(locale: impl Into<LocaleMix>) -> Vec<Column> {
+    let locale = locale.into();
+    vec![
+        Column::new("id", ColumnKind::Sha256Hex),
+        Column::new("name", locale.kind(Field::FirstName)),
+        Column::new("age", ColumnKind::IntRange { min: 18, max: 60 }),
+    ]
+}
+
+/// Orders placed by the [`users`] names.
+pub fn orders(locale: impl Into<LocaleMix>) -> Vec<Column> {
+    let locale = locale.into();
+    vec![
+        Column::new("order_id", ColumnKind::Sha256Hex),
+        Column::new("customer", locale.kind(Field::FirstName)),
+        Column::new("status", choice(&["pending", "paid", "shipped", "delivered", "cancelled"])),
+        Column::new("quantity", ColumnKind::IntRange { min: 1, max: 20 }),
+        Column::new("amount_cents", ColumnKind::IntRange { min: 100, max: 500_000 }),
+    ]
+}
+
+/// People at companies, as a CRM holds them.
+pub fn contacts(locale: impl Into<LocaleMix>) -> Vec<Column> {
+    let locale = locale.into();
+    vec![
+        Column::new("id", ColumnKind::Sha256Hex),
+        Column::new("name", locale.kind(Field::FullName)),
+        Column::new("company", business::company()),
+        Column::new("industry", business::industry()),
+        Column::new("title", business::job_title()),
+        Column::new("department", business::department(2, " / ")),
+    ]
+}
+
+/// Stress text for downstream parsers: a key, a short and a long
+/// [`ColumnKind::Unicode`] column.
+pub fn unicode() -> Vec<Column> {
+    vec![
+        Column::new("id", ColumnKind::Sha256Hex),
+        Column::new("name", ColumnKind::Unicode { min: 1, max: 12, mix: UnicodeMix::default() }),
+        Column::new("comment", ColumnKind::Unicode { min: 1, max: 200, mix: UnicodeMix::default() }),
+    ]
+}
+
+/// Keeps the columns named in `select`, or all of them if it's empty, with
+/// those named in `order` moved to the front in that order.
+pub fn arrange(mut columns: Vec<Column>, select: &[String], order: &[String]) -> Result<Vec<Column>, BoxError> {
+    if !select.is_empty() {
+        let keep = column_indices(&columns, select, "--select")?;
+        columns = columns.into_iter().enumerate().filter(|(i, _)| keep.contains(i)).map(|(_, c)| c).collect();
+    }
+    if let Some(name) = order.iter().enumerate().find_map(|(i, name)| order[..i].contains(name).then_some(name)) {
+        return Err(format!("--order: {name} is named twice").into());
+    }
+    let first = column_indices(&columns, order, "--order")?;
+    let mut arranged: Vec<Column> = first.iter().map(|&i| columns[i].clone()).collect();
+    arranged.extend(columns.into_iter().enumerate().filter(|(i, _)| !first.contains(i)).map(|(_, c)| c));
+    Ok(arranged)
+}
+
+/// Looks up a built-in schema by name, with names from `locale`, a
+/// [`Locale`](crate::Locale) or a mix of them.
+pub fn builtin(name: &str, locale: impl Into<LocaleMix>) -> Option<Vec<Column>> {
+    match name {
+        "users" => Some(users(locale)),
+        "orders" => Some(orders(locale)),
+        "contacts" => Some(contacts(locale)),
+        "unicode" => Some(unicode()),
+        "access_log" => Some(access_log::columns(chrono::Utc::now().timestamp())),
+        _ => None,
+    }
+}
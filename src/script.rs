@@ -0,0 +1,777 @@
+//! A small expression language for [`ColumnKind::Script`] columns, for
+//! business rules the other kinds don't cover, without recompiling. A
+//! script is an expression, with `let` bindings before it, that can read
+//! the row's earlier columns by name and `row_number`, and draw random
+//! numbers:
+//!
+//! ```text
+//! let base = quantity * unit_price;
+//! if tier == "gold" { base * 0.9 } else { base + rand_int(0, 5) }
+//! ```
+//!
+//! Scripts are type-checked when compiled, so evaluating one can't fail:
+//! integer columns read as integers, decimals as floats and the rest as
+//! strings, `+` adds numbers or joins anything to a string, `/` divides as
+//! floats, and integer arithmetic wraps, with `% 0` giving 0. A float that
+//! isn't finite, from dividing by zero, is written as 0.
+//!
+//! [`ColumnKind::Script`]: crate::schema::ColumnKind::Script
+
+use std::fmt::Write as _;
+
+use rand::Rng;
+
+use crate::generator::Row;
+use crate::schema::{Column, DataType, civil_from_days, parse_date, parse_timestamp};
+
+/// The type of a value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Int,
+    Float,
+    Str,
+    Bool,
+}
+
+impl Type {
+    fn of(data_type: DataType) -> Type {
+        match data_type {
+            DataType::Integer => Type::Int,
+            DataType::Decimal { .. } => Type::Float,
+            DataType::String | DataType::Date | DataType::Timestamp => Type::Str,
+        }
+    }
+
+    fn is_number(self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Int => "an integer",
+            Type::Float => "a float",
+            Type::Str => "a string",
+            Type::Bool => "a bool",
+        }
+    }
+
+    /// The type both `self` and `other` convert to, if there's one.
+    fn common(self, other: Type) -> Option<Type> {
+        match (self, other) {
+            _ if self == other => Some(self),
+            (Type::Int, Type::Float) | (Type::Float, Type::Int) => Some(Type::Float),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl Value {
+    fn int(&self) -> i64 {
+        match self {
+            Value::Int(n) => *n,
+            Value::Float(x) => *x as i64,
+            _ => 0,
+        }
+    }
+
+    fn float(&self) -> f64 {
+        match self {
+            Value::Int(n) => *n as f64,
+            Value::Float(x) => *x,
+            _ => 0.0,
+        }
+    }
+
+    fn bool(&self) -> bool {
+        matches!(self, Value::Bool(true))
+    }
+
+    fn write(&self, out: &mut String) {
+        match self {
+            Value::Int(n) => out.push_str(itoa::Buffer::new().format(*n)),
+            Value::Float(x) => write!(out, "{x}").expect("writing to a String"),
+            Value::Str(s) => out.push_str(s),
+            Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        }
+    }
+
+    fn text(self) -> String {
+        match self {
+            Value::Str(s) => s,
+            value => {
+                let mut out = String::new();
+                value.write(&mut out);
+                out
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    RandInt,
+    RandFloat,
+    RandBool,
+    Choice,
+    Len,
+    Upper,
+    Lower,
+    Substr,
+    Str,
+    Int,
+    Float,
+    Round,
+    Abs,
+    Min,
+    Max,
+    Pad,
+    Days,
+    Date,
+    Seconds,
+    Timestamp,
+}
+
+/// A type-checked expression.
+#[derive(Debug, Clone)]
+enum Expr {
+    Const(Value),
+    Column(usize, Type),
+    Local(usize),
+    RowNumber,
+    ToFloat(Box<Expr>),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// Bindings to local slots, then the value.
+    Block(Vec<(usize, Expr)>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+/// A compiled script.
+#[derive(Debug, Clone)]
+pub struct Script {
+    body: Expr,
+    output: Type,
+    locals: usize,
+}
+
+impl Script {
+    /// Compiles `source`, which can read the columns in `earlier`.
+    pub fn compile(source: &str, earlier: &[Column]) -> Result<Script, String> {
+        let columns: Vec<(&str, Type)> = earlier.iter().map(|c| (c.name.as_str(), Type::of(c.kind.data_type()))).collect();
+        let mut parser = Parser { tokens: tokenize(source)?, pos: 0, columns, scopes: Vec::new(), locals: 0 };
+        let (body, output) = parser.block()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected {token:?} after the script's value"));
+        }
+        Ok(Script { body, output, locals: parser.locals })
+    }
+
+    /// The type the script's values are written as, with `scale` decimal
+    /// places if they're floats.
+    pub fn data_type(&self, scale: u8) -> DataType {
+        match self.output {
+            Type::Int => DataType::Integer,
+            Type::Float => DataType::Decimal { scale },
+            Type::Str | Type::Bool => DataType::String,
+        }
+    }
+
+    /// Whether the script's values can be written as `data_type`: integers
+    /// as decimals too, and strings as dates and timestamps, as which they
+    /// have to be written.
+    pub fn writes(&self, data_type: DataType) -> bool {
+        matches!(
+            (self.output, data_type),
+            (Type::Int, DataType::Integer | DataType::Decimal { .. })
+                | (Type::Float, DataType::Decimal { .. })
+                | (Type::Str, DataType::String | DataType::Date | DataType::Timestamp)
+                | (Type::Bool, DataType::String)
+        )
+    }
+
+    /// Evaluates the script for the row at `index`, whose earlier fields
+    /// are in `row`, and appends its value to `row` as `data_type`.
+    pub fn push<R: Rng + ?Sized>(&self, rng: &mut R, row: &mut Row, index: u64, data_type: DataType) {
+        let mut locals = vec![Value::Int(0); self.locals];
+        let value = eval(&self.body, &mut Context { rng, row, index, locals: &mut locals });
+        match (value, data_type) {
+            (value, DataType::Decimal { scale }) => {
+                let x = value.float();
+                let text = format!("{:.*}", usize::from(scale), if x.is_finite() { x } else { 0.0 });
+                row.push(text.as_bytes());
+            }
+            (value, _) => row.push(value.text().as_bytes()),
+        }
+    }
+}
+
+struct Context<'a, R: ?Sized> {
+    rng: &'a mut R,
+    row: &'a Row,
+    index: u64,
+    locals: &'a mut [Value],
+}
+
+fn eval<R: Rng + ?Sized>(expr: &Expr, cx: &mut Context<R>) -> Value {
+    match expr {
+        Expr::Const(value) => value.clone(),
+        Expr::Column(index, ty) => {
+            let text = String::from_utf8_lossy(cx.row.field(*index));
+            match ty {
+                Type::Int => Value::Int(text.parse().unwrap_or(0)),
+                Type::Float => Value::Float(text.parse().unwrap_or(0.0)),
+                _ => Value::Str(text.into_owned()),
+            }
+        }
+        Expr::Local(slot) => cx.locals[*slot].clone(),
+        Expr::RowNumber => Value::Int(cx.index as i64 + 1),
+        Expr::ToFloat(e) => Value::Float(eval(e, cx).float()),
+        Expr::Neg(e) => match eval(e, cx) {
+            Value::Int(n) => Value::Int(n.wrapping_neg()),
+            value => Value::Float(-value.float()),
+        },
+        Expr::Not(e) => Value::Bool(!eval(e, cx).bool()),
+        Expr::And(a, b) => Value::Bool(eval(a, cx).bool() && eval(b, cx).bool()),
+        Expr::Or(a, b) => Value::Bool(eval(a, cx).bool() || eval(b, cx).bool()),
+        Expr::Binary(op, a, b) => binary(*op, eval(a, cx), eval(b, cx)),
+        Expr::If(condition, then, otherwise) => {
+            if eval(condition, cx).bool() {
+                eval(then, cx)
+            } else {
+                eval(otherwise, cx)
+            }
+        }
+        Expr::Block(bindings, value) => {
+            for (slot, e) in bindings {
+                cx.locals[*slot] = eval(e, cx);
+            }
+            eval(value, cx)
+        }
+        Expr::Call(function, args) => call(*function, args, cx),
+    }
+}
+
+fn binary(op: Op, a: Value, b: Value) -> Value {
+    use std::cmp::Ordering;
+
+    let order = match (&a, &b) {
+        (Value::Int(x), Value::Int(y)) => Some(x.cmp(y)),
+        (Value::Str(x), Value::Str(y)) => Some(x.cmp(y)),
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        (Value::Str(_), _) | (_, Value::Str(_)) | (Value::Bool(_), _) | (_, Value::Bool(_)) => None,
+        _ => a.float().partial_cmp(&b.float()),
+    };
+    match (op, a, b) {
+        (Op::Add, Value::Int(x), Value::Int(y)) => Value::Int(x.wrapping_add(y)),
+        (Op::Sub, Value::Int(x), Value::Int(y)) => Value::Int(x.wrapping_sub(y)),
+        (Op::Mul, Value::Int(x), Value::Int(y)) => Value::Int(x.wrapping_mul(y)),
+        (Op::Rem, Value::Int(x), Value::Int(y)) => Value::Int(if y == 0 { 0 } else { x.wrapping_rem(y) }),
+        (Op::Add, a @ Value::Str(_), b) | (Op::Add, a, b @ Value::Str(_)) => {
+            let mut text = a.text();
+            b.write(&mut text);
+            Value::Str(text)
+        }
+        (Op::Add, a, b) => Value::Float(a.float() + b.float()),
+        (Op::Sub, a, b) => Value::Float(a.float() - b.float()),
+        (Op::Mul, a, b) => Value::Float(a.float() * b.float()),
+        (Op::Div, a, b) => Value::Float(a.float() / b.float()),
+        (Op::Rem, a, b) => Value::Float(a.float() % b.float()),
+        (Op::Eq, ..) => Value::Bool(order == Some(Ordering::Equal)),
+        (Op::Ne, ..) => Value::Bool(order != Some(Ordering::Equal)),
+        (Op::Lt, ..) => Value::Bool(order == Some(Ordering::Less)),
+        (Op::Le, ..) => Value::Bool(matches!(order, Some(Ordering::Less | Ordering::Equal))),
+        (Op::Gt, ..) => Value::Bool(order == Some(Ordering::Greater)),
+        (Op::Ge, ..) => Value::Bool(matches!(order, Some(Ordering::Greater | Ordering::Equal))),
+    }
+}
+
+fn call<R: Rng + ?Sized>(function: Function, args: &[Expr], cx: &mut Context<R>) -> Value {
+    if function == Function::Choice {
+        return eval(&args[cx.rng.gen_range(0..args.len())], cx);
+    }
+    let values: Vec<Value> = args.iter().map(|arg| eval(arg, cx)).collect();
+    let text = |i: usize| match &values[i] {
+        Value::Str(s) => s.as_str(),
+        _ => "",
+    };
+    match function {
+        Function::RandInt => {
+            let (min, max) = (values[0].int(), values[1].int());
+            Value::Int(if min < max { cx.rng.gen_range(min..=max) } else { min })
+        }
+        Function::RandFloat => Value::Float(cx.rng.r#gen()),
+        Function::RandBool => Value::Bool(cx.rng.r#gen::<f64>() < values[0].float()),
+        Function::Choice => unreachable!("handled above"),
+        Function::Len => Value::Int(text(0).chars().count() as i64),
+        Function::Upper => Value::Str(text(0).to_uppercase()),
+        Function::Lower => Value::Str(text(0).to_lowercase()),
+        Function::Substr => {
+            let (start, len) = (values[1].int().max(0) as usize, values[2].int().max(0) as usize);
+            Value::Str(text(0).chars().skip(start).take(len).collect())
+        }
+        Function::Str => Value::Str(values[0].clone().text()),
+        Function::Int => match &values[0] {
+            Value::Str(s) => Value::Int(s.trim().parse().unwrap_or(0)),
+            value => Value::Int(value.int()),
+        },
+        Function::Float => match &values[0] {
+            Value::Str(s) => Value::Float(s.trim().parse().unwrap_or(0.0)),
+            value => Value::Float(value.float()),
+        },
+        Function::Round => {
+            let unit = 10f64.powi(values[1].int().clamp(-18, 18) as i32);
+            Value::Float((values[0].float() * unit).round() / unit)
+        }
+        Function::Abs => match &values[0] {
+            Value::Int(n) => Value::Int(n.wrapping_abs()),
+            value => Value::Float(value.float().abs()),
+        },
+        Function::Min | Function::Max => {
+            let (a, b) = (&values[0], &values[1]);
+            let first = match (a, b) {
+                (Value::Int(x), Value::Int(y)) => x <= y,
+                _ => a.float() <= b.float(),
+            };
+            if first == (function == Function::Min) { a.clone() } else { b.clone() }
+        }
+        Function::Pad => {
+            let width = values[1].int().clamp(0, 64) as usize;
+            Value::Str(format!("{:0width$}", values[0].int()))
+        }
+        Function::Days => Value::Int(parse_date(text(0)).unwrap_or(0)),
+        Function::Date => {
+            let (year, month, day) = civil_from_days(values[0].int());
+            Value::Str(format!("{year:04}-{month:02}-{day:02}"))
+        }
+        Function::Seconds => Value::Int(parse_timestamp(text(0)).unwrap_or(0)),
+        Function::Timestamp => {
+            let mut row = Row::new();
+            crate::generator::push_timestamp(&mut row, values[0].int());
+            Value::Str(String::from_utf8_lossy(row.field(0)).into_owned())
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Ident(String),
+    Punct(&'static str),
+}
+
+const PUNCTUATION: [&str; 21] =
+    ["==", "!=", "<=", ">=", "&&", "||", "(", ")", "{", "}", ",", ";", "=", "<", ">", "!", "+", "-", "*", "/", "%"];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c == '#' {
+            // A comment, to the end of the line.
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if c.is_ascii_digit() {
+            let end = rest.find(|c: char| !c.is_ascii_digit() && c != '.' && c != '_').unwrap_or(rest.len());
+            let number = rest[..end].replace('_', "");
+            tokens.push(if number.contains('.') {
+                Token::Float(number.parse().map_err(|_| format!("{number} isn't a number"))?)
+            } else {
+                Token::Int(number.parse().map_err(|_| format!("{number} is too large"))?)
+            });
+            rest = &rest[end..];
+        } else if c == '_' || c.is_alphabetic() {
+            let end = rest.find(|c: char| c != '_' && !c.is_alphanumeric()).unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if c == '"' {
+            let mut text = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i + 2,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => text.push('\n'),
+                        Some((_, 't')) => text.push('\t'),
+                        Some((_, c @ ('"' | '\\'))) => text.push(c),
+                        _ => return Err("unknown escape in a string".to_string()),
+                    },
+                    Some((_, c)) => text.push(c),
+                    None => return Err("unterminated string".to_string()),
+                }
+            };
+            tokens.push(Token::Str(text));
+            rest = &rest[end..];
+        } else {
+            let punct = PUNCTUATION.iter().find(|p| rest.starts_with(**p)).ok_or_else(|| format!("unexpected {c:?}"))?;
+            tokens.push(Token::Punct(punct));
+            rest = &rest[punct.len()..];
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    columns: Vec<(&'a str, Type)>,
+    /// The `let` bindings in scope, innermost last.
+    scopes: Vec<(String, usize, Type)>,
+    locals: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Punct(p)) if *p == punct);
+        self.pos += usize::from(found);
+        found
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), String> {
+        if self.eat(punct) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(token) => Err(format!("expected {punct:?}, not {token:?}")),
+            None => Err(format!("expected {punct:?} at the end")),
+        }
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(w)) if w == word);
+        self.pos += usize::from(found);
+        found
+    }
+
+    /// `let` bindings, then an expression.
+    fn block(&mut self) -> Result<(Expr, Type), String> {
+        let scope = self.scopes.len();
+        let mut bindings = Vec::new();
+        while self.keyword("let") {
+            let Some(Token::Ident(name)) = self.peek().cloned() else {
+                return Err("expected a name after let".to_string());
+            };
+            self.pos += 1;
+            self.expect("=")?;
+            let (value, ty) = self.expr()?;
+            self.expect(";")?;
+            let slot = self.locals;
+            self.locals += 1;
+            self.scopes.push((name, slot, ty));
+            bindings.push((slot, value));
+        }
+        let (value, ty) = self.expr()?;
+        self.scopes.truncate(scope);
+        match bindings.is_empty() {
+            true => Ok((value, ty)),
+            false => Ok((Expr::Block(bindings, Box::new(value)), ty)),
+        }
+    }
+
+    fn expr(&mut self) -> Result<(Expr, Type), String> {
+        let (mut left, mut ty) = self.and()?;
+        while self.eat("||") {
+            let right = self.and()?;
+            check_bools("||", ty, right.1)?;
+            (left, ty) = (Expr::Or(Box::new(left), Box::new(right.0)), Type::Bool);
+        }
+        Ok((left, ty))
+    }
+
+    fn and(&mut self) -> Result<(Expr, Type), String> {
+        let (mut left, mut ty) = self.comparison()?;
+        while self.eat("&&") {
+            let right = self.comparison()?;
+            check_bools("&&", ty, right.1)?;
+            (left, ty) = (Expr::And(Box::new(left), Box::new(right.0)), Type::Bool);
+        }
+        Ok((left, ty))
+    }
+
+    fn comparison(&mut self) -> Result<(Expr, Type), String> {
+        let left = self.sum()?;
+        let ops = [("==", Op::Eq), ("!=", Op::Ne), ("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt)];
+        let Some(&(symbol, op)) = ops.iter().find(|(symbol, _)| self.eat(symbol)) else { return Ok(left) };
+        let right = self.sum()?;
+        if left.1.common(right.1).is_none() {
+            return Err(format!("can't compare {} with {}", left.1.name(), right.1.name()));
+        }
+        if left.1 == Type::Bool && !matches!(op, Op::Eq | Op::Ne) {
+            return Err(format!("{symbol} compares numbers or strings, not bools"));
+        }
+        Ok((Expr::Binary(op, Box::new(left.0), Box::new(right.0)), Type::Bool))
+    }
+
+    fn sum(&mut self) -> Result<(Expr, Type), String> {
+        let (mut left, mut ty) = self.product()?;
+        loop {
+            let op = if self.eat("+") {
+                Op::Add
+            } else if self.eat("-") {
+                Op::Sub
+            } else {
+                return Ok((left, ty));
+            };
+            let right = self.product()?;
+            ty = match (op, ty, right.1) {
+                (Op::Add, Type::Str, _) | (Op::Add, _, Type::Str) => Type::Str,
+                _ => arithmetic(if op == Op::Add { "+" } else { "-" }, ty, right.1)?,
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(right.0));
+        }
+    }
+
+    fn product(&mut self) -> Result<(Expr, Type), String> {
+        let (mut left, mut ty) = self.unary()?;
+        loop {
+            let (symbol, op) = if self.eat("*") {
+                ("*", Op::Mul)
+            } else if self.eat("/") {
+                ("/", Op::Div)
+            } else if self.eat("%") {
+                ("%", Op::Rem)
+            } else {
+                return Ok((left, ty));
+            };
+            let right = self.unary()?;
+            ty = arithmetic(symbol, ty, right.1)?;
+            if op == Op::Div {
+                ty = Type::Float;
+            }
+            left = Expr::Binary(op, Box::new(left), Box::new(right.0));
+        }
+    }
+
+    fn unary(&mut self) -> Result<(Expr, Type), String> {
+        if self.eat("-") {
+            let (e, ty) = self.unary()?;
+            if !ty.is_number() {
+                return Err(format!("can't negate {}", ty.name()));
+            }
+            return Ok((Expr::Neg(Box::new(e)), ty));
+        }
+        if self.eat("!") {
+            let (e, ty) = self.unary()?;
+            check_bools("!", ty, Type::Bool)?;
+            return Ok((Expr::Not(Box::new(e)), Type::Bool));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<(Expr, Type), String> {
+        let token = self.peek().cloned().ok_or("the script ends where a value should be")?;
+        self.pos += 1;
+        match token {
+            Token::Int(n) => Ok((Expr::Const(Value::Int(n)), Type::Int)),
+            Token::Float(x) => Ok((Expr::Const(Value::Float(x)), Type::Float)),
+            Token::Str(s) => Ok((Expr::Const(Value::Str(s)), Type::Str)),
+            Token::Punct("(") => {
+                let value = self.expr()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Token::Ident(word) if word == "true" || word == "false" => Ok((Expr::Const(Value::Bool(word == "true")), Type::Bool)),
+            Token::Ident(word) if word == "if" => self.if_else(),
+            Token::Ident(name) if self.eat("(") => self.call(&name),
+            Token::Ident(name) => {
+                if let Some((_, slot, ty)) = self.scopes.iter().rev().find(|(n, ..)| *n == name) {
+                    return Ok((Expr::Local(*slot), *ty));
+                }
+                if let Some(index) = self.columns.iter().position(|(n, _)| *n == name) {
+                    return Ok((Expr::Column(index, self.columns[index].1), self.columns[index].1));
+                }
+                if name == "row_number" {
+                    return Ok((Expr::RowNumber, Type::Int));
+                }
+                Err(format!("no earlier column or variable named {name}"))
+            }
+            token => Err(format!("unexpected {token:?}")),
+        }
+    }
+
+    /// After `if`: the condition, the branches in braces and any `else if`.
+    fn if_else(&mut self) -> Result<(Expr, Type), String> {
+        let (condition, ty) = self.expr()?;
+        check_bools("if", ty, Type::Bool)?;
+        self.expect("{")?;
+        let then = self.block()?;
+        self.expect("}")?;
+        if !self.keyword("else") {
+            return Err("an if needs an else, to have a value either way".to_string());
+        }
+        let otherwise = if self.keyword("if") {
+            self.if_else()?
+        } else {
+            self.expect("{")?;
+            let otherwise = self.block()?;
+            self.expect("}")?;
+            otherwise
+        };
+        let ty = then
+            .1
+            .common(otherwise.1)
+            .ok_or_else(|| format!("if's branches are {} and {}", then.1.name(), otherwise.1.name()))?;
+        Ok((Expr::If(Box::new(condition), Box::new(widen(then, ty)), Box::new(widen(otherwise, ty))), ty))
+    }
+
+    /// After a function's name and `(`: its arguments and `)`.
+    fn call(&mut self, name: &str) -> Result<(Expr, Type), String> {
+        let mut args = Vec::new();
+        if !self.eat(")") {
+            loop {
+                args.push(self.expr()?);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",")?;
+            }
+        }
+        let types: Vec<Type> = args.iter().map(|(_, ty)| *ty).collect();
+        let number = |ty: &Type| ty.is_number();
+        let any = |_: &Type| true;
+        let string = |ty: &Type| *ty == Type::Str;
+        let int = |ty: &Type| *ty == Type::Int;
+        let check = |params: &[&dyn Fn(&Type) -> bool]| {
+            params.len() == types.len() && params.iter().zip(&types).all(|(param, ty)| param(ty))
+        };
+        let (function, ty) = match name {
+            "rand_int" if check(&[&int, &int]) => (Function::RandInt, Type::Int),
+            "rand_float" if check(&[]) => (Function::RandFloat, Type::Float),
+            "rand_bool" if check(&[&number]) => (Function::RandBool, Type::Bool),
+            "choice" if !types.is_empty() => {
+                let ty = types
+                    .iter()
+                    .try_fold(types[0], |common, ty| common.common(*ty))
+                    .ok_or("choice's values must be of one type")?;
+                let args = args.into_iter().map(|arg| widen(arg, ty)).collect();
+                return Ok((Expr::Call(Function::Choice, args), ty));
+            }
+            "len" if check(&[&string]) => (Function::Len, Type::Int),
+            "upper" if check(&[&string]) => (Function::Upper, Type::Str),
+            "lower" if check(&[&string]) => (Function::Lower, Type::Str),
+            "substr" if check(&[&string, &int, &int]) => (Function::Substr, Type::Str),
+            "str" if check(&[&any]) => (Function::Str, Type::Str),
+            "int" if check(&[&|ty: &Type| *ty != Type::Bool]) => (Function::Int, Type::Int),
+            "float" if check(&[&|ty: &Type| *ty != Type::Bool]) => (Function::Float, Type::Float),
+            "round" if check(&[&number, &int]) => (Function::Round, Type::Float),
+            "abs" if check(&[&number]) => (Function::Abs, types[0]),
+            "min" | "max" if check(&[&number, &number]) => {
+                let ty = types[0].common(types[1]).expect("numbers have a common type");
+                let function = if name == "min" { Function::Min } else { Function::Max };
+                let args = args.into_iter().map(|arg| widen(arg, ty)).collect();
+                return Ok((Expr::Call(function, args), ty));
+            }
+            "pad" if check(&[&int, &int]) => (Function::Pad, Type::Str),
+            "days" if check(&[&string]) => (Function::Days, Type::Int),
+            "date" if check(&[&int]) => (Function::Date, Type::Str),
+            "seconds" if check(&[&string]) => (Function::Seconds, Type::Int),
+            "timestamp" if check(&[&int]) => (Function::Timestamp, Type::Str),
+            "rand_int" | "rand_float" | "rand_bool" | "choice" | "len" | "upper" | "lower" | "substr" | "str" | "int"
+            | "float" | "round" | "abs" | "min" | "max" | "pad" | "days" | "date" | "seconds" | "timestamp" => {
+                let given: Vec<_> = types.iter().map(|ty| ty.name()).collect();
+                return Err(format!("{name} can't take ({})", given.join(", ")));
+            }
+            _ => return Err(format!("no function named {name}")),
+        };
+        Ok((Expr::Call(function, args.into_iter().map(|(e, _)| e).collect()), ty))
+    }
+}
+
+/// `expr` of type `from` as a value of `to`, its common type with another.
+fn widen((expr, from): (Expr, Type), to: Type) -> Expr {
+    if from == Type::Int && to == Type::Float { Expr::ToFloat(Box::new(expr)) } else { expr }
+}
+
+fn arithmetic(symbol: &str, left: Type, right: Type) -> Result<Type, String> {
+    match left.common(right) {
+        Some(ty) if ty.is_number() => Ok(ty),
+        _ => Err(format!("can't {symbol} {} and {}", left.name(), right.name())),
+    }
+}
+
+fn check_bools(symbol: &str, left: Type, right: Type) -> Result<(), String> {
+    match (left, right) {
+        (Type::Bool, Type::Bool) => Ok(()),
+        _ => Err(format!("{symbol} takes bools, not {} and {}", left.name(), right.name())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnKind;
+
+    fn run(source: &str, fields: &[&str]) -> Result<String, String> {
+        let columns = [
+            Column::new("quantity", ColumnKind::IntRange { min: 0, max: 0 }),
+            Column::new("price", ColumnKind::Decimal { min: 0, max: 0, scale: 2 }),
+            Column::new("tier", ColumnKind::Choice(vec!["gold".into()])),
+            Column::new("ordered", ColumnKind::DateRange { min: 0, max: 0 }),
+        ];
+        let script = Script::compile(source, &columns)?;
+        let mut row = Row::new();
+        for field in fields {
+            row.push(field.as_bytes());
+        }
+        script.push(&mut rand::thread_rng(), &mut row, 4, script.data_type(2));
+        Ok(String::from_utf8(row.field(fields.len()).to_vec()).unwrap())
+    }
+
+    #[test]
+    fn evaluates_expressions_over_the_row() {
+        let row = ["3", "2.50", "gold", "2024-02-28"];
+        assert_eq!(run("quantity * price", &row).unwrap(), "7.50");
+        assert_eq!(run("let n = quantity + 1; n * n % 5", &row).unwrap(), "1");
+        assert_eq!(run(r#"if tier == "gold" { "VIP-" + row_number } else { "std" }"#, &row).unwrap(), "VIP-5");
+        assert_eq!(run("if quantity > 5 { 1 } else if quantity > 2 { 2.5 } else { 3 }", &row).unwrap(), "2.50");
+        assert_eq!(run("date(days(ordered) + 2)", &row).unwrap(), "2024-03-01");
+        assert_eq!(run("upper(substr(tier, 1, 2)) + pad(quantity, 4)", &row).unwrap(), "OL0003");
+        assert_eq!(run("quantity % 0 == 0 && !(price < 1)", &row).unwrap(), "true");
+        let drawn: i64 = run("rand_int(1, 6) + choice(0, 10)", &row).unwrap().parse().unwrap();
+        assert!((1..=6).contains(&drawn) || (11..=16).contains(&drawn));
+    }
+
+    #[test]
+    fn rejects_scripts_that_could_fail() {
+        let row = ["1", "1.00", "gold", "2024-01-01"];
+        assert_eq!(run("tier * 2", &row).unwrap_err(), "can't * a string and an integer");
+        assert_eq!(run("if quantity { 1 } else { 2 }", &row).unwrap_err(), "if takes bools, not an integer and a bool");
+        assert_eq!(run("if true { 1 }", &row).unwrap_err(), "an if needs an else, to have a value either way");
+        assert_eq!(run("total + 1", &row).unwrap_err(), "no earlier column or variable named total");
+        assert_eq!(run("len(quantity)", &row).unwrap_err(), "len can't take (an integer)");
+        assert_eq!(run("1 2", &row).unwrap_err(), "unexpected Int(2) after the script's value");
+    }
+}
@@ -0,0 +1,454 @@
+//! `serve`: generated data streamed over HTTP on demand.
+//!
+//! `/generate` returns a chunked body of a fixed number of rows, while
+//! `/events` (Server-Sent Events) and `/ws` (WebSocket) push one row per
+//! message at a steady rate for realtime consumers. `/metrics` counts what
+//! they have sent, for Prometheus.
+
+use std::io::{self, Read, Write};
+use std::sync::atomic::Ordering;
+use std::thread;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use clap::ValueEnum;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use sha1::{Digest, Sha1};
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+use crate::BoxError;
+use crate::cli::{ServeArgs, parse_count};
+use crate::generator::{Generator, Row, RowKeys};
+use crate::locale::Locale;
+use crate::metrics;
+use crate::record::{RecordEncoder, RecordFormat};
+use crate::schema::{self, Column};
+use crate::sink::csv_record;
+use crate::throttle::RateLimiter;
+
+/// Generated bytes buffered before they are handed to the connection.
+const CHUNK_SIZE: usize = 64 << 10;
+
+/// Rows a `/generate` request asks for when it does not say.
+const DEFAULT_ROWS: u64 = 1000;
+
+/// Rows per second `/events` and `/ws` send when the request does not say.
+const DEFAULT_STREAM_RATE: f64 = 10.0;
+
+/// Appended to a client's key to prove the server speaks WebSocket (RFC 6455).
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endpoint {
+    /// `/generate`: a chunked CSV or JSON lines body.
+    Generate,
+    /// `/events`: a `text/event-stream` with one event per row.
+    Events,
+    /// `/ws`: a WebSocket with one text message per row.
+    WebSocket,
+}
+
+/// How each row is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// CSV lines; `/generate` starts with a header line.
+    Csv,
+    /// One JSON object per row.
+    Json,
+    /// Apache combined log lines, for `schema=access_log`.
+    Combined,
+}
+
+impl Format {
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Json => "application/x-ndjson",
+            Format::Combined => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+/// What a request asked for.
+#[derive(Debug)]
+struct Plan {
+    endpoint: Endpoint,
+    /// `None` streams until the client goes away.
+    rows: Option<u64>,
+    rate: Option<f64>,
+    schema: String,
+    locale: Locale,
+    columns: Vec<Column>,
+    format: Format,
+}
+
+/// Decodes `%XX` escapes and `+` in a query string component.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Parses a request URL into a [`Plan`], or the status and message to
+/// reject it with.
+fn plan(url: &str, max_rows: u64) -> Result<Plan, (u16, String)> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let endpoint = match path {
+        "/generate" => Endpoint::Generate,
+        "/events" => Endpoint::Events,
+        "/ws" => Endpoint::WebSocket,
+        _ => return Err((404, format!("no such endpoint {path}; try /generate, /events or /ws"))),
+    };
+    let streaming = endpoint != Endpoint::Generate;
+    let mut plan = Plan {
+        endpoint,
+        rows: if streaming { None } else { Some(DEFAULT_ROWS) },
+        rate: streaming.then_some(DEFAULT_STREAM_RATE),
+        schema: "users".into(),
+        locale: Locale::En,
+        columns: Vec::new(),
+        format: if streaming { Format::Json } else { Format::Csv },
+    };
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value);
+        match percent_decode(name).as_str() {
+            "rows" => plan.rows = Some(parse_count(&value).map_err(|e| (400, format!("rows: {e}")))?),
+            "rate" => match value.parse::<f64>() {
+                Ok(rate) if rate > 0.0 && rate.is_finite() => plan.rate = Some(rate),
+                _ => return Err((400, format!("rate: {value} is not a positive number of rows per second"))),
+            },
+            "schema" => plan.schema = value,
+            "locale" => plan.locale = Locale::from_str(&value, false).map_err(|e| (400, format!("locale: {e}")))?,
+            "format" => {
+                plan.format = match value.as_str() {
+                    "csv" => Format::Csv,
+                    "json" | "jsonl" | "ndjson" => Format::Json,
+                    "combined" => Format::Combined,
+                    other => return Err((400, format!("format: {other} is not csv, json or combined"))),
+                }
+            }
+            other => return Err((400, format!("unknown parameter {other}"))),
+        }
+    }
+    if plan.rows.unwrap_or(0) > max_rows {
+        return Err((400, format!("rows: at most {max_rows} rows can be requested at once")));
+    }
+    plan.columns = schema::builtin(&plan.schema, plan.locale).ok_or_else(|| (400, format!("schema: no schema named {}", plan.schema)))?;
+    Ok(plan)
+}
+
+/// Generates rows and encodes each as one line, without a terminator.
+struct Lines {
+    generator: Generator,
+    /// Encodes JSON and log lines; CSV uses [`csv_record`] directly.
+    encoder: Option<RecordEncoder>,
+    /// The next row's index, for columns that count rows.
+    index: u64,
+    rng: StdRng,
+    row: Row,
+}
+
+impl Lines {
+    fn new(plan: &Plan) -> Result<Self, BoxError> {
+        Ok(Lines {
+            generator: Generator::compile(&plan.columns)?,
+            encoder: match plan.format {
+                Format::Csv => None,
+                Format::Json => Some(RecordEncoder::new(RecordFormat::Json, &plan.columns, None)),
+                Format::Combined => Some(RecordEncoder::new(RecordFormat::Combined, &plan.columns, None)),
+            },
+            index: 0,
+            rng: StdRng::from_entropy(),
+            row: Row::new(),
+        })
+    }
+
+    fn next(&mut self) -> Result<Vec<u8>, BoxError> {
+        self.generator.fill_keyed_row(&mut self.rng, &mut self.row, RowKeys { index: self.index, ..RowKeys::default() });
+        self.index += 1;
+        let line = match &self.encoder {
+            Some(encoder) => encoder.encode(&self.row)?,
+            None => csv_record(&self.row),
+        };
+        metrics::written(1, line.len() as u64 + 1);
+        Ok(line)
+    }
+}
+
+/// A `/generate` body that generates its rows as the client reads them.
+struct RowStream {
+    lines: Lines,
+    limiter: Option<RateLimiter>,
+    remaining: u64,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl RowStream {
+    fn new(plan: &Plan) -> Result<Self, BoxError> {
+        let mut buf = Vec::with_capacity(CHUNK_SIZE);
+        if plan.format == Format::Csv {
+            let mut header = Row::new();
+            plan.columns.iter().for_each(|c| header.push(c.name.as_bytes()));
+            buf.extend_from_slice(&csv_record(&header));
+            buf.push(b'\n');
+        }
+        Ok(RowStream {
+            lines: Lines::new(plan)?,
+            limiter: plan.rate.map(RateLimiter::new),
+            remaining: plan.rows.unwrap_or(DEFAULT_ROWS),
+            buf,
+            pos: 0,
+        })
+    }
+
+    /// Refills the buffer with the next chunk of rows.
+    fn fill(&mut self) -> Result<(), BoxError> {
+        self.buf.clear();
+        self.pos = 0;
+        while self.remaining > 0 && self.buf.len() < CHUNK_SIZE {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire(1);
+            }
+            self.buf.extend_from_slice(&self.lines.next()?);
+            self.buf.push(b'\n');
+            self.remaining -= 1;
+        }
+        Ok(())
+    }
+}
+
+impl Read for RowStream {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() {
+            self.fill().map_err(io::Error::other)?;
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Frames one row as a Server-Sent Event.
+fn sse_event(line: &[u8]) -> Vec<u8> {
+    let mut event = Vec::with_capacity(line.len() + 8);
+    event.extend_from_slice(b"data: ");
+    event.extend_from_slice(line);
+    event.extend_from_slice(b"\n\n");
+    event
+}
+
+/// Frames one row as an unmasked WebSocket text message, as servers send them.
+fn websocket_frame(line: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(line.len() + 10);
+    frame.push(0x81);
+    match line.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(line);
+    frame
+}
+
+/// The `Sec-WebSocket-Accept` answer to a client's `Sec-WebSocket-Key`.
+fn websocket_accept(key: &str) -> String {
+    STANDARD.encode(Sha1::digest(format!("{}{WEBSOCKET_GUID}", key.trim())))
+}
+
+/// Writes one framed message per row, paced by `--rate`, until the rows run
+/// out or the client disconnects.
+fn push_rows(writer: &mut dyn Write, plan: &Plan, frame: fn(&[u8]) -> Vec<u8>) -> Result<(), BoxError> {
+    let mut lines = Lines::new(plan)?;
+    let limiter = plan.rate.map(RateLimiter::new);
+    let mut sent = 0;
+    while plan.rows.is_none_or(|rows| sent < rows) {
+        if let Some(limiter) = &limiter {
+            limiter.acquire(1);
+        }
+        writer.write_all(&frame(&lines.next()?))?;
+        // Realtime consumers want each row as soon as it exists.
+        writer.flush()?;
+        sent += 1;
+    }
+    Ok(())
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("header names and values are ASCII")
+}
+
+fn reject(request: Request, status: u16, message: String) -> Result<(), BoxError> {
+    Ok(request.respond(Response::from_string(message + "\n").with_status_code(status))?)
+}
+
+fn handle(request: Request, max_rows: u64) -> Result<(), BoxError> {
+    if request.url() == "/metrics" {
+        return Ok(request.respond(metrics::response())?);
+    }
+    let plan = match request.method() {
+        Method::Get => plan(request.url(), max_rows),
+        _ => Err((405, "only GET is supported".to_string())),
+    };
+    let plan = match plan {
+        Ok(plan) => plan,
+        Err((status, message)) => return reject(request, status, message),
+    };
+    match plan.endpoint {
+        Endpoint::Generate => {
+            let body = RowStream::new(&plan)?;
+            // With no length the body is sent chunked, as it is generated.
+            let headers = vec![header("Content-Type", plan.format.content_type())];
+            request.respond(Response::new(200.into(), headers, body, None, None))?;
+        }
+        Endpoint::Events => {
+            // Events are written straight to the socket, since tiny_http
+            // holds chunked bodies back until 32KB have accumulated.
+            let mut writer = request.into_writer();
+            writer.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\
+                  Connection: close\r\n\r\n",
+            )?;
+            push_rows(&mut writer, &plan, sse_event)?;
+        }
+        Endpoint::WebSocket => {
+            let key = request.headers().iter().find(|h| h.field.equiv("Sec-WebSocket-Key")).map(|h| h.value.to_string());
+            let Some(key) = key else {
+                return reject(request, 426, "/ws needs a WebSocket upgrade request".to_string());
+            };
+            let response = Response::empty(StatusCode(101)).with_header(header("Sec-WebSocket-Accept", &websocket_accept(&key)));
+            // Messages from the client are never read; closing the
+            // connection is how it stops the stream.
+            let mut stream = request.upgrade("websocket", response);
+            push_rows(&mut stream, &plan, websocket_frame)?;
+            // A close frame with no status, once a `rows` limit is reached.
+            stream.write_all(&[0x88, 0])?;
+            stream.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether an error only means the client hung up, which is normal for a
+/// load generator and not worth reporting.
+fn client_left(e: &BoxError) -> bool {
+    e.downcast_ref::<io::Error>().is_some_and(|e| {
+        matches!(e.kind(), io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted)
+    })
+}
+
+/// Serves the endpoints until the process is stopped, one thread per request.
+pub fn run(args: &ServeArgs) -> Result<(), BoxError> {
+    let server = Server::http((args.bind.as_str(), args.port))?;
+    println!("Serving generated data on http://{}:{}/generate, /events and /ws, and metrics on /metrics", args.bind, args.port);
+    for request in server.incoming_requests() {
+        let max_rows = args.max_rows;
+        thread::spawn(move || {
+            let url = request.url().to_string();
+            if let Err(e) = handle(request, max_rows)
+                && !client_left(&e)
+            {
+                metrics::ERRORS.fetch_add(1, Ordering::Relaxed);
+                eprintln!("{url}: {e}");
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_query_components() {
+        assert_eq!(percent_decode("a%2Cb+c"), "a,b c");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn plans_generate_requests() {
+        let plan = plan("/generate?rows=1M&schema=orders&format=jsonl", u64::MAX).unwrap();
+        assert_eq!((plan.rows, plan.schema.as_str(), plan.format), (Some(1_000_000), "orders", Format::Json));
+        assert_eq!(plan.columns[0].name, "order_id");
+
+        let defaults = super::plan("/generate", u64::MAX).unwrap();
+        assert_eq!((defaults.rows, defaults.rate, defaults.format), (Some(DEFAULT_ROWS), None, Format::Csv));
+        assert_eq!(defaults.schema, "users");
+    }
+
+    #[test]
+    fn streams_default_to_paced_endless_json() {
+        let plan = plan("/events", 10).unwrap();
+        assert_eq!((plan.endpoint, plan.rows, plan.rate, plan.format), (Endpoint::Events, None, Some(10.0), Format::Json));
+        let plan = super::plan("/ws?rate=250&rows=5&format=csv", 10).unwrap();
+        assert_eq!((plan.endpoint, plan.rows, plan.rate, plan.format), (Endpoint::WebSocket, Some(5), Some(250.0), Format::Csv));
+    }
+
+    #[test]
+    fn rejects_bad_requests() {
+        assert_eq!(plan("/nope", 10).unwrap_err().0, 404);
+        assert_eq!(plan("/generate?rows=11", 10).unwrap_err().0, 400);
+        assert_eq!(plan("/generate?schema=nope", 10).unwrap_err().0, 400);
+        assert_eq!(plan("/generate?format=xml", 10).unwrap_err().0, 400);
+        assert_eq!(plan("/generate?colour=red", 10).unwrap_err().0, 400);
+        assert_eq!(plan("/events?rate=0", 10).unwrap_err().0, 400);
+    }
+
+    #[test]
+    fn streams_exactly_the_requested_rows() {
+        let plan = plan("/generate?rows=2500", u64::MAX).unwrap();
+        let mut body = String::new();
+        RowStream::new(&plan).unwrap().read_to_string(&mut body).unwrap();
+        let lines: Vec<_> = body.lines().collect();
+        assert_eq!(lines[0], "id,name,age");
+        assert_eq!(lines.len(), 2501);
+    }
+
+    #[test]
+    fn frames_events_and_messages() {
+        assert_eq!(sse_event(b"{\"a\":1}"), b"data: {\"a\":1}\n\n");
+        assert_eq!(websocket_frame(b"hi"), [0x81, 2, b'h', b'i']);
+        let long = websocket_frame(&[b'x'; 300]);
+        assert_eq!(long[..4], [0x81, 126, 1, 44]);
+        assert_eq!(long.len(), 304);
+        // The example handshake from RFC 6455.
+        assert_eq!(websocket_accept("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn pushes_paced_rows() {
+        let plan = plan("/events?rows=3&rate=1000", 10).unwrap();
+        let mut out = Vec::new();
+        push_rows(&mut out, &plan, sse_event).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("data: {\"id\":").count(), 3);
+    }
+}
@@ -0,0 +1,164 @@
+//! `--shard-by`: every row with the same value of a key column written to
+//! the same one of the `--shards` files, for distributed consumers that
+//! read one partition of the keys each. The file is picked as Kafka's
+//! default partitioner picks a partition, so a consumer can find a key's
+//! file with the same hash.
+
+use std::sync::{Arc, Mutex};
+
+use crate::generator::Row;
+use crate::output::{self, Destination, Outputs};
+use crate::schema::Column;
+use crate::sink::{self, Sink, column_indices};
+use crate::{BoxError, ShardReport};
+
+/// The partition the Java client's default partitioner picks for `key`, so
+/// keys land where other producers would put them.
+pub(crate) fn java_partition(key: &[u8], partitions: u32) -> u32 {
+    (murmur2(key) & 0x7fff_ffff) % partitions
+}
+
+/// Kafka's variant of MurmurHash2.
+fn murmur2(data: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    let mut h = 0x9747_b28c ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M) ^ k;
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &b) in tail.iter().enumerate().rev() {
+            h ^= u32::from(b) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^ (h >> 15)
+}
+
+/// Writes each row passed on to the shard its key picks.
+pub struct ShardBySink {
+    key: usize,
+    /// Of each shard, where it goes, its sink and the rows written to it.
+    shards: Vec<(String, Box<dyn Sink>, u64)>,
+    /// Where the shards' reports go once they're finished.
+    reports: Arc<Mutex<Vec<ShardReport>>>,
+}
+
+impl ShardBySink {
+    /// Opens every shard of `--output` for rows of `columns`, keyed by the
+    /// column `--shard-by` names.
+    pub(crate) fn open(
+        outputs: &Outputs,
+        columns: &[Column],
+        key: &str,
+        reports: Arc<Mutex<Vec<ShardReport>>>,
+    ) -> Result<Self, BoxError> {
+        let key = column_indices(columns, &[key.to_string()], "--shard-by")?[0];
+        let shards = u32::try_from(outputs.args().shards).map_err(|_| "--shard-by takes at most 2^32 - 1 shards")?;
+        let shards = (0..u64::from(shards))
+            .map(|shard| {
+                let location = output::shard_location(&outputs.args().output, shard as usize);
+                let sink = sink::open_shard(outputs, &Destination::parse(&location)?, columns, shard)?;
+                Ok((location, sink, 0))
+            })
+            .collect::<Result<_, BoxError>>()?;
+        Ok(ShardBySink { key, shards, reports })
+    }
+}
+
+impl Sink for ShardBySink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        let key = row.fields().nth(self.key).expect("rows have every column");
+        let shard = java_partition(key, self.shards.len() as u32) as usize;
+        let (_, sink, rows) = &mut self.shards[shard];
+        *rows += 1;
+        sink.write_row(row)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.shards.iter_mut().try_for_each(|(_, sink, _)| sink.flush())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.shards.iter().map(|(_, sink, _)| sink.bytes_written()).sum()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        for (location, sink, rows) in self.shards {
+            let bytes = sink.bytes_written();
+            sink.finish()?;
+            self.reports.lock().unwrap().push(ShardReport { location, rows, bytes });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use clap::Parser;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::cli::Args;
+    use crate::generator::Generator;
+
+    /// The cases from Kafka's own `UtilsTest.testMurmur2`.
+    #[test]
+    fn murmur2_matches_the_java_client() {
+        let cases: [(&[u8], i32); 6] = [
+            (b"21", -973932308),
+            (b"foobar", -790332482),
+            (b"a-little-bit-long-string", -985981536),
+            (b"a-little-bit-longer-string", -1486304829),
+            (b"lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8", -58897971),
+            (b"abc", 479470107),
+        ];
+        for (key, expected) in cases {
+            assert_eq!(murmur2(key) as i32, expected, "{}", String::from_utf8_lossy(key));
+        }
+    }
+
+    #[test]
+    fn partitions_are_in_range() {
+        for key in [&b"21"[..], b"foobar", b"abc"] {
+            let partition = java_partition(key, 7);
+            assert!((0..7).contains(&partition));
+        }
+    }
+
+    #[test]
+    fn rows_of_a_key_share_a_file() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_shard_by_{}", std::process::id()));
+        let output = dir.join("part-{n}.csv");
+        let args = Args::parse_from(["gen", "--schema", "orders", "--shards", "3", "--shard-by", "status", "--output", output.to_str().unwrap()]);
+        let columns = crate::columns(&args).unwrap();
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let mut sink: Box<dyn Sink> = Box::new(ShardBySink::open(&Outputs::new(&args), &columns, "status", reports.clone()).unwrap());
+        for row in Generator::compile(&columns).unwrap().rows(StdRng::seed_from_u64(0)).take(300) {
+            sink.write_row(&row).unwrap();
+        }
+        sink.finish().unwrap();
+        assert_eq!(reports.lock().unwrap().iter().map(|r| r.rows).sum::<u64>(), 300);
+        let mut files: HashMap<String, usize> = HashMap::new();
+        for shard in 0..3 {
+            let mut reader = csv::Reader::from_path(dir.join(format!("part-{shard}.csv"))).unwrap();
+            for record in reader.records() {
+                let status = record.unwrap()[2].to_string();
+                assert_eq!(*files.entry(status.clone()).or_insert(shard), shard, "{status}");
+                assert_eq!(java_partition(status.as_bytes(), 3) as usize, shard);
+            }
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
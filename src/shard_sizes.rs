@@ -0,0 +1,94 @@
+//! `--shard-sizes`: how a run's rows or bytes are split over its shards.
+//! Shards are even by default; the skewed splits make one shard far larger
+//! than the rest, or sizes falling off like Zipf's law, to test how
+//! downstream systems handle files of very different sizes.
+
+use serde::Serialize;
+
+/// The relative sizes of a run's shards.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShardSizes {
+    /// Every shard the same size, give or take a row.
+    Balanced,
+    /// The first shard this many times the size of each of the others.
+    OneLarge(f64),
+    /// Shard `n` in proportion to `1 / (n + 1)^exponent`.
+    Zipf(f64),
+}
+
+impl ShardSizes {
+    /// Parses `balanced`, `one-large`, `one-large:FACTOR`, `zipf` or
+    /// `zipf:EXPONENT`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (name, number) = match s.split_once(':') {
+            Some((name, number)) => {
+                let number: f64 = number.parse().map_err(|_| format!("{number} is not a number"))?;
+                (name, Some(number))
+            }
+            None => (s, None),
+        };
+        let sizes = match (name, number) {
+            ("balanced", None) => ShardSizes::Balanced,
+            ("one-large", factor) => ShardSizes::OneLarge(factor.unwrap_or(10.0)),
+            ("zipf", exponent) => ShardSizes::Zipf(exponent.unwrap_or(1.0)),
+            _ => return Err(format!("{s}: expected balanced, one-large[:FACTOR] or zipf[:EXPONENT]")),
+        };
+        match sizes {
+            ShardSizes::OneLarge(factor) if !(factor >= 1.0 && factor.is_finite()) => {
+                Err(format!("{s}: the factor must be at least 1"))
+            }
+            ShardSizes::Zipf(exponent) if !(exponent > 0.0 && exponent.is_finite()) => {
+                Err(format!("{s}: the exponent must be a positive number"))
+            }
+            sizes => Ok(sizes),
+        }
+    }
+
+    /// Where each of `shards` shards starts when `total` rows or bytes are
+    /// split over them. Shard `i` holds `bounds[i]..bounds[i + 1]`, so the
+    /// shards add up to exactly `total`.
+    pub fn bounds(self, total: u64, shards: u64) -> Vec<u64> {
+        let weight = |shard: u64| match self {
+            ShardSizes::Balanced => 1.0,
+            ShardSizes::OneLarge(factor) if shard == 0 => factor,
+            ShardSizes::OneLarge(_) => 1.0,
+            ShardSizes::Zipf(exponent) => 1.0 / ((shard + 1) as f64).powf(exponent),
+        };
+        if self == ShardSizes::Balanced {
+            return (0..=shards).map(|shard| shard * (total / shards) + shard.min(total % shards)).collect();
+        }
+        let sum: f64 = (0..shards).map(weight).sum();
+        let mut before = 0.0;
+        let mut bounds = vec![0];
+        for shard in 0..shards {
+            before += weight(shard);
+            bounds.push(if shard + 1 == shards { total } else { (total as f64 * before / sum).round() as u64 });
+        }
+        bounds
+    }
+
+    /// How much of `total` each of `shards` shards gets.
+    pub fn split(self, total: u64, shards: u64) -> Vec<u64> {
+        self.bounds(total, shards).windows(2).map(|bounds| bounds[1] - bounds[0]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shards_split_their_total_as_asked() {
+        assert_eq!(ShardSizes::Balanced.split(10, 4), [3, 3, 2, 2]);
+        assert_eq!(ShardSizes::parse("one-large:7").unwrap().split(100, 4), [70, 10, 10, 10]);
+        assert_eq!(ShardSizes::parse("one-large").unwrap().split(5, 1), [5]);
+        let zipf = ShardSizes::parse("zipf").unwrap().split(1_000_003, 10);
+        assert_eq!(zipf.iter().sum::<u64>(), 1_000_003);
+        assert!(zipf.windows(2).all(|pair| pair[0] > pair[1]), "{zipf:?}");
+        assert!((zipf[0] as f64 / zipf[1] as f64 - 2.0).abs() < 0.001);
+        assert!(ShardSizes::parse("zipf:0").is_err());
+        assert!(ShardSizes::parse("one-large:0.5").is_err());
+        assert!(ShardSizes::parse("balanced:2").is_err());
+    }
+}
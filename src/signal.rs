@@ -0,0 +1,64 @@
+//! Ctrl-C and `kill`: SIGINT and SIGTERM ask generation to stop between
+//! rows, so every output is flushed and finished (gzip trailers, Parquet
+//! footers, manifests) and holds only whole rows. A second signal exits at
+//! once. SIGUSR1 asks for a progress report, and SIGUSR2 pauses or resumes
+//! generation.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+static REPORT: AtomicBool = AtomicBool::new(false);
+
+static TOGGLE_PAUSE: AtomicBool = AtomicBool::new(false);
+
+/// Whether a signal asked generation to stop.
+pub(crate) fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Whether SIGUSR1 asked for a progress report since the last call.
+pub(crate) fn report_requested() -> bool {
+    REPORT.swap(false, Ordering::Relaxed)
+}
+
+/// Whether SIGUSR2 asked to pause or resume since the last call.
+pub(crate) fn pause_toggled() -> bool {
+    TOGGLE_PAUSE.swap(false, Ordering::Relaxed)
+}
+
+#[cfg(unix)]
+extern "C" fn request_report(_: libc::c_int) {
+    REPORT.store(true, Ordering::Relaxed);
+}
+
+#[cfg(unix)]
+extern "C" fn toggle_pause(_: libc::c_int) {
+    TOGGLE_PAUSE.fetch_xor(true, Ordering::Relaxed);
+}
+
+#[cfg(unix)]
+extern "C" fn handle(_: libc::c_int) {
+    if INTERRUPTED.swap(true, Ordering::Relaxed) {
+        // SAFETY: `_exit` is async-signal-safe.
+        unsafe { libc::_exit(130) };
+    }
+}
+
+/// Installs the handlers for the rest of the process.
+#[cfg(unix)]
+pub(crate) fn install() {
+    let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    let report = request_report as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    let pause = toggle_pause as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: the handlers only touch atomics and call `_exit`.
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGUSR1, report);
+        libc::signal(libc::SIGUSR2, pause);
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn install() {}
@@ -0,0 +1,597 @@
+use std::io::{BufWriter, Write};
+use std::sync::{Arc, Mutex};
+
+use sha2::{Digest, Sha256};
+
+use crate::BoxError;
+#[cfg(feature = "cloud")]
+use crate::aws::kinesis::KinesisSink;
+#[cfg(feature = "clickhouse")]
+use crate::clickhouse::{self, ClickHouseSink, Server};
+#[cfg(feature = "sqlite")]
+use crate::db::SqliteSink;
+#[cfg(feature = "cloud")]
+use crate::gcp::bigquery_write::BigQueryWriteSink;
+#[cfg(feature = "cloud")]
+use crate::gcp::pubsub::PubSubSink;
+use crate::generator::Row;
+use crate::integrity::{self, IntegritySink};
+use crate::cli::{CsvDialect, FileCompression, FileFormat, HeaderArgs, HeaderMode};
+use crate::contract;
+use crate::corrupt::{self, Corrupter};
+use crate::fill::FillSink;
+#[cfg(feature = "kafka")]
+use crate::kafka::KafkaSink;
+use crate::manifest::{Injector, Manifest, ManifestSink};
+use crate::metrics::MetricsSink;
+use crate::mismatch::{self, MismatchSink};
+use crate::noise::{self, NoiseSink};
+use crate::mysql::LoadDataSink;
+#[cfg(feature = "mysql")]
+use crate::mysql::{self, MySqlSink};
+use crate::output::{CountingWriter, Destination, GzipOutput, Output, Outputs};
+#[cfg(feature = "zstd")]
+use crate::output::ZstdOutput;
+#[cfg(feature = "parquet")]
+use crate::parquet::ParquetSink;
+#[cfg(feature = "postgres")]
+use crate::pg::{self, PgCopy};
+use crate::record::{Encode, RecordEncoder, RecordFormat};
+use crate::repeat::RepeatSink;
+use crate::run_manifest::{self, ListedSink, SummedOutput};
+use crate::schema::Column;
+use crate::stats::StatsSink;
+use crate::tcp::TcpSink;
+use crate::torture::TortureSink;
+use crate::width::WidthSink;
+
+/// Where generated rows go.
+///
+/// File-like destinations get [`CsvSink`] or another format's sink over an
+/// [`Output`]; services that take records rather than bytes implement this
+/// directly, as can callers with destinations of their own.
+pub trait Sink: Send {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError>;
+
+    /// Pushes buffered rows onward. Called between batches, so sinks that
+    /// batch internally may ignore it.
+    fn flush(&mut self) -> Result<(), BoxError>;
+
+    /// Bytes produced so far, which `--size` is measured against.
+    fn bytes_written(&self) -> u64;
+
+    /// Delivers everything still buffered and closes the sink.
+    fn finish(self: Box<Self>) -> Result<(), BoxError>;
+}
+
+/// Opens the sink for one shard: a service client for record destinations,
+/// otherwise CSV over the destination's [`Output`].
+pub fn open(outputs: &Outputs, destination: &Destination, columns: &[Column]) -> Result<Box<dyn Sink>, BoxError> {
+    open_shard(outputs, destination, columns, 0)
+}
+
+/// Like [`open`], for shard `shard` of a table split over several outputs,
+/// which `--header first` and `--metadata-comment` tell apart.
+pub fn open_shard(
+    outputs: &Outputs,
+    destination: &Destination,
+    columns: &[Column],
+    shard: u64,
+) -> Result<Box<dyn Sink>, BoxError> {
+    let args = &outputs.args().corrupt;
+    mismatch::check(outputs.args())?;
+    noise::check(outputs.args())?;
+    // The integrity columns are added to rows as the dirty data options
+    // left them, right before they're written.
+    let written = integrity::columns(&outputs.args().integrity, columns);
+    contract::write(outputs, destination, &written)?;
+    let manifest = Manifest::open(outputs, destination, &written)?;
+    let injector = |name| Injector::new(args, name, destination, manifest.as_ref());
+    let corrupter = corrupt::open(outputs.args(), injector("corrupt"))?;
+    let listing = run_manifest::entry(outputs.args(), destination);
+    let format = open_format(outputs, destination, &written, corrupter, manifest.as_ref(), listing.as_ref(), shard)?;
+    let format = MetricsSink::wrap(format, destination);
+    let format = StatsSink::wrap(format, outputs, destination, &written)?;
+    let mut sink = IntegritySink::wrap(format, &outputs.args().integrity);
+    // Dirty values are fitted too, and the checksums cover what's written.
+    sink = WidthSink::wrap(sink, columns, &outputs.args().width);
+    if args.torture_rate > 0.0 {
+        sink = Box::new(TortureSink::new(sink, columns, args.torture_rate, injector("torture")));
+    }
+    if args.noise_rate > 0.0 {
+        sink = Box::new(NoiseSink::new(sink, columns, args.noise_rate, &args.noise_kinds, injector("noise")));
+    }
+    sink = MismatchSink::wrap(sink, columns, &args.mismatch, injector("mismatch"))?;
+    sink = FillSink::wrap(sink, outputs.args(), columns, shard);
+    sink = RepeatSink::wrap(sink, outputs.args(), columns, shard)?;
+    if let Some(manifest) = manifest {
+        sink = Box::new(ManifestSink::new(sink, manifest));
+    }
+    Ok(ListedSink::wrap(sink, listing))
+}
+
+fn open_format(
+    outputs: &Outputs,
+    destination: &Destination,
+    columns: &[Column],
+    corrupter: Option<Corrupter>,
+    manifest: Option<&Manifest>,
+    listing: Option<&Arc<Mutex<run_manifest::File>>>,
+    shard: u64,
+) -> Result<Box<dyn Sink>, BoxError> {
+    let open = || Ok::<_, BoxError>(SummedOutput::wrap(outputs.open(destination)?, listing));
+    match destination {
+        #[cfg(feature = "cloud")]
+        Destination::BigQuery(table) => Ok(Box::new(BigQueryWriteSink::open(outputs.gcp_auth()?, table, columns)?)),
+        #[cfg(feature = "cloud")]
+        Destination::PubSub { project, topic } => Ok(Box::new(PubSubSink::open(
+            outputs.gcp_auth()?,
+            &outputs.args().pubsub,
+            project,
+            topic,
+            columns,
+        )?)),
+        #[cfg(feature = "kafka")]
+        Destination::Kafka { brokers, topic } => {
+            Ok(Box::new(KafkaSink::open(&outputs.args().kafka, brokers, topic, columns)?))
+        }
+        #[cfg(not(feature = "kafka"))]
+        Destination::Kafka { .. } => Err(crate::without("kafka", "Kafka")),
+        #[cfg(feature = "cloud")]
+        Destination::Kinesis { stream } => {
+            Ok(Box::new(KinesisSink::open(outputs.kinesis()?, &outputs.args().kinesis, stream, columns)?))
+        }
+        #[cfg(not(feature = "cloud"))]
+        Destination::BigQuery(_) | Destination::PubSub { .. } | Destination::Kinesis { .. } => {
+            Err(crate::without("cloud", "cloud"))
+        }
+        #[cfg(feature = "postgres")]
+        Destination::Postgres { url } => {
+            let table = outputs.args().postgres.pg_table.as_deref().ok_or("postgres:// outputs need --pg-table")?;
+            if outputs.args().postgres.pg_create_table {
+                outputs.create_table_once(|| pg::create_table(url, table, columns))?;
+            }
+            // COPY skips the header line, so the data is the same CSV as a file.
+            Ok(Box::new(CsvSink::new(Box::new(PgCopy::start(url, table, columns)?), &header(columns))?))
+        }
+        #[cfg(not(feature = "postgres"))]
+        Destination::Postgres { .. } => Err(crate::without("postgres", "PostgreSQL")),
+        #[cfg(feature = "clickhouse")]
+        Destination::ClickHouse { url } => {
+            let args = &outputs.args().clickhouse;
+            let table = args.ch_table.as_deref().ok_or("clickhouse:// outputs need --ch-table")?;
+            let server = Server::parse(url, args.ch_tls)?;
+            if args.ch_create_table {
+                outputs.create_table_once(|| clickhouse::create_table(&server, table, columns))?;
+            }
+            Ok(Box::new(ClickHouseSink::open(server, args, table, columns)))
+        }
+        #[cfg(not(feature = "clickhouse"))]
+        Destination::ClickHouse { .. } => Err(crate::without("clickhouse", "ClickHouse")),
+        #[cfg(feature = "mysql")]
+        Destination::MySql { url } => {
+            let args = &outputs.args().mysql;
+            let table = args.mysql_table.as_deref().ok_or("mysql:// outputs need --mysql-table")?;
+            if args.mysql_create_table {
+                outputs.create_table_once(|| mysql::create_table(url, table, columns))?;
+            }
+            Ok(Box::new(MySqlSink::open(url, args, table, columns)?))
+        }
+        #[cfg(not(feature = "mysql"))]
+        Destination::MySql { .. } => Err(crate::without("mysql", "MySQL")),
+        Destination::Tcp(endpoint) => Ok(Box::new(TcpSink::open(&outputs.args().tcp, endpoint, columns)?)),
+        #[cfg(feature = "sqlite")]
+        Destination::Sqlite(path) => Ok(Box::new(SqliteSink::create(path, &outputs.args().database.db_table, columns)?)),
+        #[cfg(not(feature = "sqlite"))]
+        Destination::Sqlite(_) => Err(crate::without("sqlite", "SQLite")),
+        #[cfg(feature = "duckdb")]
+        Destination::DuckDb(path) => {
+            Ok(Box::new(crate::db::DuckDbSink::create(path, &outputs.args().database.db_table, columns)?))
+        }
+        #[cfg(not(feature = "duckdb"))]
+        Destination::DuckDb(_) => Err("this build has no DuckDB support; rebuild with `--features duckdb`".into()),
+        #[cfg(feature = "parquet")]
+        _ if outputs.args().format == FileFormat::Parquet => {
+            Ok(Box::new(ParquetSink::with_compression(open()?, columns, outputs.args().parquet_compression)?))
+        }
+        _ if outputs.args().format != FileFormat::Csv => write_to(open()?, outputs.args().format, columns),
+        _ => {
+            let mut output = match outputs.args().compression {
+                FileCompression::None => open()?,
+                FileCompression::Gzip => Box::new(GzipOutput::new(open()?)),
+                #[cfg(feature = "zstd")]
+                FileCompression::Zstd => Box::new(ZstdOutput::new(open()?)?),
+                #[cfg(not(feature = "zstd"))]
+                FileCompression::Zstd => return Err(crate::without("zstd", "Zstandard compression")),
+            };
+            let args = outputs.args();
+            // The byte order mark, if any, goes before everything else.
+            if args.csv_dialect == CsvDialect::Excel {
+                output.write_all(UTF8_BOM)?;
+            }
+            if args.headers.metadata_comment {
+                output.write_all(metadata_comment(args.seed, columns, shard)?.as_bytes())?;
+            }
+            let header = file_header(&args.headers, columns, shard)?;
+            if let Some(manifest) = manifest {
+                manifest.skip_lines(u64::from(args.headers.metadata_comment) + u64::from(header.is_some()));
+            }
+            let delimiters = Delimiters { leading: args.leading_delimiter, trailing: args.trailing_delimiter };
+            match args.csv_dialect {
+                CsvDialect::Mysql => Ok(Box::new(LoadDataSink::new(output, header.as_deref(), delimiters)?)),
+                dialect => {
+                    let sink = CsvSink::with_dialect(output, header.as_deref(), dialect, delimiters)?.with_manifest(manifest);
+                    Ok(Box::new(match corrupter {
+                        Some(corrupter) => sink.with_corrupter(corrupter),
+                        None => sink,
+                    }))
+                }
+            }
+        }
+    }
+}
+
+/// A sink writing rows of `columns` to `output` in `format`, for callers
+/// with a writer of their own, like a socket or an in-memory buffer; any
+/// [`Write`] is an [`Output`] in a [`BufWriter`]. CSV is plain RFC 4180 with
+/// a header.
+pub fn write_to(output: Box<dyn Output>, format: FileFormat, columns: &[Column]) -> Result<Box<dyn Sink>, BoxError> {
+    match format {
+        FileFormat::Csv => Ok(Box::new(CsvSink::new(output, &header(columns))?)),
+        #[cfg(feature = "parquet")]
+        FileFormat::Parquet => Ok(Box::new(ParquetSink::new(output, columns)?)),
+        #[cfg(not(feature = "parquet"))]
+        FileFormat::Parquet => Err(crate::without("parquet", "Parquet")),
+        FileFormat::Jsonl => Ok(Box::new(LineSink::new(output, RecordEncoder::new(RecordFormat::Json, columns, None)))),
+        FileFormat::Combined => Ok(Box::new(LineSink::new(output, RecordEncoder::new(RecordFormat::Combined, columns, None)))),
+    }
+}
+
+fn header(columns: &[Column]) -> Vec<String> {
+    columns.iter().map(|c| c.name.clone()).collect()
+}
+
+/// The header line of a CSV file of shard `shard`, if `--header` gives it
+/// one, with the `--header-names` in it.
+fn file_header(args: &HeaderArgs, columns: &[Column], shard: u64) -> Result<Option<Vec<String>>, BoxError> {
+    if args.header == HeaderMode::None || (args.header == HeaderMode::First && shard > 0) {
+        return Ok(None);
+    }
+    let mut header = header(columns);
+    for (column, name) in &args.header_names {
+        let i = column_indices(columns, std::slice::from_ref(column), "--header-names")?[0];
+        header[i] = name.clone();
+    }
+    Ok(Some(header))
+}
+
+/// The `--metadata-comment` line that starts a CSV file, like
+/// `# csv_data_generator 0.1.0; columns sha256:…; seed 42; shard 3`.
+fn metadata_comment(seed: Option<u64>, columns: &[Column], shard: u64) -> Result<String, BoxError> {
+    let hash = hex::encode(&Sha256::digest(serde_json::to_vec(columns)?)[..8]);
+    let seed = seed.map_or("none".to_string(), |seed| seed.to_string());
+    Ok(format!("# csv_data_generator {}; columns sha256:{hash}; seed {seed}; shard {shard}\n", env!("CARGO_PKG_VERSION")))
+}
+
+/// What `--csv-dialect excel` files start with.
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// A CSV writer quoting fields and ending lines as `dialect` does.
+fn csv_writer<W: Write>(dialect: CsvDialect, writer: W) -> csv::Writer<W> {
+    let mut builder = csv::WriterBuilder::new();
+    match dialect {
+        CsvDialect::Rfc4180 | CsvDialect::Mysql => {}
+        CsvDialect::Rfc4180Strict => {
+            builder.terminator(csv::Terminator::CRLF);
+        }
+        CsvDialect::Excel => {
+            builder.terminator(csv::Terminator::CRLF).quote_style(csv::QuoteStyle::NonNumeric);
+        }
+    }
+    builder.from_writer(writer)
+}
+
+/// Extra delimiters around every line of a CSV file, for the quirks of
+/// `--leading-delimiter` and `--trailing-delimiter`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Delimiters {
+    pub leading: bool,
+    pub trailing: bool,
+}
+
+impl Delimiters {
+    /// `fields` with an empty field before or after them for each extra
+    /// delimiter.
+    pub fn around<'a>(self, fields: impl Iterator<Item = &'a [u8]>) -> impl Iterator<Item = &'a [u8]> {
+        let empty = |extra: bool| extra.then_some(&b""[..]);
+        empty(self.leading).into_iter().chain(fields).chain(empty(self.trailing))
+    }
+}
+
+/// The `\n`s in `bytes`, which quoting leaves as they are.
+fn line_breaks(bytes: &[u8]) -> u64 {
+    bytes.iter().filter(|&&b| b == b'\n').count() as u64
+}
+
+/// Writes rows as CSV to an [`Output`].
+pub struct CsvSink {
+    /// None only while a corrupted line is written around it.
+    writer: Option<csv::Writer<CountingWriter<Box<dyn Output>>>>,
+    dialect: CsvDialect,
+    delimiters: Delimiters,
+    corrupter: Option<Corrupter>,
+    /// Told how many lines each row took, which quoted line breaks make more
+    /// than one.
+    manifest: Option<Manifest>,
+}
+
+impl CsvSink {
+    /// Wraps `output` and writes the header record.
+    pub fn new(output: Box<dyn Output>, header: &[String]) -> Result<Self, BoxError> {
+        Self::with_dialect(output, Some(header), CsvDialect::Rfc4180, Delimiters::default())
+    }
+
+    /// Wraps `output`, writing `header` if there is one, and quotes fields
+    /// and ends lines as `dialect` does, with `delimiters` around every
+    /// line. [`CsvDialect::Mysql`] is RFC 4180.
+    pub fn with_dialect(
+        output: Box<dyn Output>,
+        header: Option<&[String]>,
+        dialect: CsvDialect,
+        delimiters: Delimiters,
+    ) -> Result<Self, BoxError> {
+        // Count bytes as they leave the buffers instead of stat-ing the file, so
+        // destinations that aren't local files can be sized too.
+        let mut writer = csv_writer(dialect, CountingWriter::new(output));
+        if let Some(header) = header {
+            writer.write_record(delimiters.around(header.iter().map(String::as_bytes)))?;
+        }
+        Ok(CsvSink { writer: Some(writer), dialect, delimiters, corrupter: None, manifest: None })
+    }
+
+    /// Has `corrupter` mangle some of the rows written.
+    pub fn with_corrupter(self, corrupter: Corrupter) -> Self {
+        CsvSink { corrupter: Some(corrupter), ..self }
+    }
+
+    /// Tells `manifest` the lines each row takes.
+    fn with_manifest(self, manifest: Option<&Manifest>) -> Self {
+        CsvSink { manifest: manifest.cloned(), ..self }
+    }
+}
+
+impl Sink for CsvSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        if let Some(corrupter) = &mut self.corrupter
+            && let Some(line) = corrupter.corrupt(row)?
+        {
+            // The CSV writer would quote the line right again, so it goes
+            // around it, after what it has buffered.
+            let writer = self.writer.take().expect("a CSV writer");
+            let mut counter = writer.into_inner().map_err(|e| e.into_error())?;
+            counter.write_all(&line)?;
+            counter.write_all(b"\n")?;
+            self.writer = Some(csv_writer(self.dialect, counter));
+            if let Some(manifest) = &self.manifest {
+                manifest.wrote_lines(1 + line_breaks(&line));
+            }
+            return Ok(());
+        }
+        self.writer.as_mut().expect("a CSV writer").write_record(self.delimiters.around(row.fields()))?;
+        if let Some(manifest) = &self.manifest {
+            manifest.wrote_lines(1 + row.fields().map(line_breaks).sum::<u64>());
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.writer.as_mut().expect("a CSV writer").flush()?;
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.writer.as_ref().expect("a CSV writer").get_ref().bytes()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        let counter = self.writer.expect("a CSV writer").into_inner().map_err(|e| e.into_error())?;
+        counter.into_inner().finish()
+    }
+}
+
+/// Writes each row as a line an [`Encode`], like a [`RecordEncoder`],
+/// encodes, with no header.
+pub struct LineSink {
+    writer: BufWriter<CountingWriter<Box<dyn Output>>>,
+    encoder: Box<dyn Encode>,
+}
+
+impl LineSink {
+    pub fn new(output: Box<dyn Output>, encoder: impl Encode + 'static) -> Self {
+        LineSink { writer: BufWriter::new(CountingWriter::new(output)), encoder: Box::new(encoder) }
+    }
+}
+
+impl Sink for LineSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.writer.write_all(&self.encoder.encode(row)?)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.writer.get_ref().bytes()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        let counter = self.writer.into_inner().map_err(|e| e.into_error())?;
+        counter.into_inner().finish()
+    }
+}
+
+/// Encodes `row` as one CSV line without a terminator, for sinks that send
+/// one record per row. Fields are quoted the way the CSV writer quotes them.
+pub fn csv_record(row: &Row) -> Vec<u8> {
+    let mut line = Vec::new();
+    for (i, field) in row.fields().enumerate() {
+        if i > 0 {
+            line.push(b',');
+        }
+        if field.iter().any(|b| matches!(b, b',' | b'"' | b'\r' | b'\n')) {
+            line.push(b'"');
+            for &b in field {
+                if b == b'"' {
+                    line.push(b'"');
+                }
+                line.push(b);
+            }
+            line.push(b'"');
+        } else {
+            line.extend_from_slice(field);
+        }
+    }
+    line
+}
+
+/// The bytes `row` would take as an unquoted CSV line, which `--size` is
+/// measured in so that database and Parquet outputs hold as much data as a CSV would.
+pub fn csv_size(row: &Row) -> u64 {
+    let (fields, bytes) = row.fields().fold((0, 0), |(n, bytes), field| (n + 1, bytes + field.len()));
+    // Separators between fields, then the line terminator.
+    (bytes + fields) as u64
+}
+
+/// Finds the position of each named column, failing on unknown names.
+pub fn column_indices(columns: &[Column], names: &[String], flag: &str) -> Result<Vec<usize>, BoxError> {
+    names
+        .iter()
+        .map(|name| {
+            columns
+                .iter()
+                .position(|c| &c.name == name)
+                .ok_or_else(|| format!("{flag}: no column named {name}").into())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::ColumnKind;
+
+    #[test]
+    fn csv_records_are_quoted_lines() {
+        let mut row = Row::new();
+        row.push(b"a,b");
+        row.push(b"42");
+        assert_eq!(csv_record(&row), b"\"a,b\",42");
+        row.clear();
+        row.push(b"say \"hi\"");
+        row.push(b"");
+        assert_eq!(csv_record(&row), b"\"say \"\"hi\"\"\",");
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn writes_custom_records_to_an_async_writer() {
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+
+        use crate::output::AsyncOutput;
+
+        /// Keeps what was written where the test can see it after `finish`.
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl tokio::io::AsyncWrite for Shared {
+            fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, data: &[u8]) -> Poll<std::io::Result<usize>> {
+                self.0.lock().unwrap().extend_from_slice(data);
+                Poll::Ready(Ok(data.len()))
+            }
+            fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+            fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let output = Box::new(AsyncOutput::new(Shared(written.clone()), runtime.handle().clone()));
+        let encoder = |row: &Row| Ok(row.fields().map(|f| String::from_utf8_lossy(f).into_owned()).collect::<Vec<_>>().join("|").into_bytes());
+        let mut sink: Box<dyn Sink> = Box::new(LineSink::new(output, encoder));
+        let mut row = Row::new();
+        row.push(b"1");
+        row.push(b"Mia");
+        sink.write_row(&row).unwrap();
+        sink.finish().unwrap();
+        assert_eq!(&written.lock().unwrap()[..], b"1|Mia\n");
+    }
+
+    #[test]
+    fn headers_follow_the_header_options() {
+        use clap::Parser;
+
+        use crate::cli::Args;
+
+        let path = std::env::temp_dir().join(format!("csv_data_generator_headers_{}.csv", std::process::id()));
+        let location = path.to_str().unwrap();
+        let columns = [Column::new("id", ColumnKind::Sha256Hex), Column::new("age", ColumnKind::IntRange { min: 0, max: 1 })];
+        let written = |flags: &[&str], shard| {
+            let args = Args::parse_from([&["gen", "--output", location], flags].concat());
+            let mut sink = open_shard(&Outputs::new(&args), &Destination::File(path.clone()), &columns, shard).unwrap();
+            let mut row = Row::new();
+            row.push(b"a");
+            row.push(b"1");
+            sink.write_row(&row).unwrap();
+            sink.finish().unwrap();
+            std::fs::read_to_string(&path).unwrap()
+        };
+        assert_eq!(written(&["--header-names", "id=user_id"], 1), "user_id,age\na,1\n");
+        assert_eq!(written(&["--header", "first"], 0), "id,age\na,1\n");
+        assert_eq!(written(&["--header", "first"], 1), "a,1\n");
+        assert_eq!(written(&["--header", "none"], 0), "a,1\n");
+        let commented = written(&["--metadata-comment", "--seed", "7"], 2);
+        let (comment, rest) = commented.split_once('\n').unwrap();
+        assert!(comment.starts_with("# csv_data_generator ") && comment.ends_with("; seed 7; shard 2"), "{comment}");
+        assert_eq!(rest, "id,age\na,1\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn dialects_and_quirks_shape_lines() {
+        use clap::Parser;
+
+        use crate::cli::Args;
+
+        let path = std::env::temp_dir().join(format!("csv_data_generator_dialects_{}.csv", std::process::id()));
+        let columns = [Column::new("name", ColumnKind::Sha256Hex), Column::new("age", ColumnKind::IntRange { min: 0, max: 1 })];
+        let written = |flags: &[&str]| {
+            let args = Args::parse_from([&["gen", "--output", path.to_str().unwrap()], flags].concat());
+            let mut sink = open(&Outputs::new(&args), &Destination::File(path.clone()), &columns).unwrap();
+            let mut row = Row::new();
+            row.push("Zoë, Jr.".as_bytes());
+            row.push(b"41");
+            sink.write_row(&row).unwrap();
+            sink.finish().unwrap();
+            std::fs::read(&path).unwrap()
+        };
+        assert_eq!(written(&[]), "name,age\n\"Zoë, Jr.\",41\n".as_bytes());
+        assert_eq!(written(&["--csv-dialect", "rfc4180-strict"]), "name,age\r\n\"Zoë, Jr.\",41\r\n".as_bytes());
+        assert_eq!(written(&["--csv-dialect", "excel"]), "\u{feff}\"name\",\"age\"\r\n\"Zoë, Jr.\",41\r\n".as_bytes());
+        assert_eq!(written(&["--trailing-delimiter"]), "name,age,\n\"Zoë, Jr.\",41,\n".as_bytes());
+        assert_eq!(written(&["--leading-delimiter", "--csv-dialect", "mysql"]), ",name,age\n,Zoë\\, Jr.,41\n".as_bytes());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn finds_columns_by_name() {
+        let columns = [Column::new("id", ColumnKind::Sha256Hex), Column::new("age", ColumnKind::IntRange { min: 0, max: 1 })];
+        assert_eq!(column_indices(&columns, &["age".into(), "id".into()], "--x").unwrap(), [1, 0]);
+        assert_eq!(column_indices(&columns, &["nope".into()], "--x").unwrap_err().to_string(), "--x: no column named nope");
+    }
+}
@@ -0,0 +1,369 @@
+//! Loading generated files into Snowflake with `COPY INTO`, through the SQL API.
+
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::{DecodePrivateKey, EncodePublicKey};
+use rsa::signature::{SignatureEncoding, Signer};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use ureq::Agent;
+
+use crate::BoxError;
+use crate::cli::{CsvDialect, FileCompression, SnowflakeArgs};
+use crate::http;
+use crate::output::Destination;
+use crate::retry::{Failure, retry};
+use crate::ddl;
+use crate::generator;
+use crate::schema::Column;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// `COPY INTO` takes at most this many names in `FILES = (...)`.
+const MAX_FILES_PER_COPY: usize = 1000;
+
+enum Credentials {
+    /// Key-pair authentication, signing a fresh JWT for every request.
+    KeyPair { key: Box<SigningKey<Sha256>>, issuer: String, subject: String },
+    /// An OAuth access token from `SNOWFLAKE_TOKEN`, used as is.
+    OAuth(String),
+}
+
+/// Runs statements through the Snowflake SQL API.
+pub struct SnowflakeClient {
+    agent: Agent,
+    /// `https://{account}.snowflakecomputing.com`, or `SNOWFLAKE_HOST`.
+    base_url: String,
+    credentials: Credentials,
+    warehouse: Option<String>,
+    role: Option<String>,
+}
+
+/// The account part of the identifiers in a key-pair JWT: upper case, and
+/// without the region of a `locator.region` account.
+fn jwt_account(account: &str) -> String {
+    account.split('.').next().unwrap_or(account).to_ascii_uppercase()
+}
+
+impl SnowflakeClient {
+    /// Reads `SNOWFLAKE_ACCOUNT` and `SNOWFLAKE_USER` with either
+    /// `SNOWFLAKE_PRIVATE_KEY_PATH` (an unencrypted PKCS#8 PEM key) or
+    /// `SNOWFLAKE_TOKEN`, plus the optional `SNOWFLAKE_WAREHOUSE` and `SNOWFLAKE_ROLE`.
+    pub fn from_env() -> Result<Self, BoxError> {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let account = var("SNOWFLAKE_ACCOUNT").ok_or("SNOWFLAKE_ACCOUNT is not set")?;
+        let credentials = match (var("SNOWFLAKE_PRIVATE_KEY_PATH"), var("SNOWFLAKE_TOKEN")) {
+            (Some(path), _) => {
+                let user = var("SNOWFLAKE_USER").ok_or("SNOWFLAKE_USER is not set")?.to_ascii_uppercase();
+                let pem = std::fs::read_to_string(&path).map_err(|e| format!("reading {path}: {e}"))?;
+                let key = RsaPrivateKey::from_pkcs8_pem(&pem).map_err(|e| format!("{path}: {e}"))?;
+                let public_key = RsaPublicKey::from(&key).to_public_key_der()?;
+                let fingerprint = STANDARD.encode(Sha256::digest(public_key.as_bytes()));
+                let subject = format!("{}.{user}", jwt_account(&account));
+                Credentials::KeyPair {
+                    key: Box::new(SigningKey::new(key)),
+                    issuer: format!("{subject}.SHA256:{fingerprint}"),
+                    subject,
+                }
+            }
+            (None, Some(token)) => Credentials::OAuth(token),
+            (None, None) => return Err("set SNOWFLAKE_PRIVATE_KEY_PATH or SNOWFLAKE_TOKEN to authenticate to Snowflake".into()),
+        };
+        let base_url = var("SNOWFLAKE_HOST")
+            .map(|host| if host.contains("://") { host } else { format!("https://{host}") })
+            .unwrap_or_else(|| format!("https://{account}.snowflakecomputing.com"));
+        Ok(SnowflakeClient {
+            agent: http::agent(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            credentials,
+            warehouse: var("SNOWFLAKE_WAREHOUSE"),
+            role: var("SNOWFLAKE_ROLE"),
+        })
+    }
+
+    /// The `Authorization` token and its `X-Snowflake-Authorization-Token-Type`.
+    fn authorization(&self) -> Result<(String, &'static str), BoxError> {
+        match &self.credentials {
+            Credentials::OAuth(token) => Ok((token.clone(), "OAUTH")),
+            Credentials::KeyPair { key, issuer, subject } => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"RS256","typ":"JWT"}"#);
+                let claims = json!({ "iss": issuer, "sub": subject, "iat": now, "exp": now + 3600 });
+                let unsigned = format!("{header}.{}", URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims)?));
+                let signature = key.try_sign(unsigned.as_bytes())?;
+                Ok((format!("{unsigned}.{}", URL_SAFE_NO_PAD.encode(signature.to_bytes())), "KEYPAIR_JWT"))
+            }
+        }
+    }
+
+    fn send(&self, request: ureq::RequestBuilder<ureq::typestate::WithBody>, body: &Value) -> Result<Value, Failure> {
+        let (token, token_type) = self.authorization().map_err(Failure::Fatal)?;
+        let request = request
+            .header("Authorization", format!("Bearer {token}"))
+            .header("X-Snowflake-Authorization-Token-Type", token_type)
+            .header("Accept", "application/json");
+        let mut response = http::check(request.send_json(body), &[422])?;
+        let status = response.status().as_u16();
+        let body: Value = response.body_mut().read_json().map_err(|e| Failure::Transient(e.into()))?;
+        if status == 422 {
+            return Err(Failure::Fatal(statement_error(&body).into()));
+        }
+        Ok(body)
+    }
+
+    /// Runs one statement and waits for it to finish, returning the result.
+    pub fn execute(&self, statement: &str) -> Result<Value, BoxError> {
+        let mut body = json!({ "statement": statement, "timeout": 0 });
+        if let Some(warehouse) = &self.warehouse {
+            body["warehouse"] = json!(warehouse);
+        }
+        if let Some(role) = &self.role {
+            body["role"] = json!(role);
+        }
+        let url = format!("{}/api/v2/statements", self.base_url);
+        // Statements are not idempotent, so a retried submission carries a
+        // request id that Snowflake deduplicates on.
+        let request_id = uuid_v4();
+        let mut result = retry("submitting a Snowflake statement", || {
+            self.send(self.agent.post(&url).query("requestId", &request_id).query("retry", "true"), &body)
+        })?;
+        // A statement still running comes back as 202 with a handle to poll.
+        while let Some(handle) = running_handle(&result) {
+            thread::sleep(POLL_INTERVAL);
+            let status_url = format!("{}/api/v2/statements/{handle}", self.base_url);
+            result = retry("checking a Snowflake statement", || {
+                let (token, token_type) = self.authorization().map_err(Failure::Fatal)?;
+                let request = self
+                    .agent
+                    .get(&status_url)
+                    .header("Authorization", format!("Bearer {token}"))
+                    .header("X-Snowflake-Authorization-Token-Type", token_type)
+                    .header("Accept", "application/json");
+                let mut response = http::check(request.call(), &[422])?;
+                let status = response.status().as_u16();
+                let body: Value = response.body_mut().read_json().map_err(|e| Failure::Transient(e.into()))?;
+                if status == 422 {
+                    return Err(Failure::Fatal(statement_error(&body).into()));
+                }
+                Ok(body)
+            })?;
+        }
+        Ok(result)
+    }
+}
+
+/// A random version 4 UUID, as the SQL API wants for `requestId`.
+fn uuid_v4() -> String {
+    let mut id = [0; 36];
+    generator::uuid_v4(&mut rand::thread_rng(), &mut id);
+    String::from_utf8(id.to_vec()).expect("UUIDs are ASCII")
+}
+
+/// The handle of a statement the response says is still running.
+fn running_handle(response: &Value) -> Option<&str> {
+    // Finished statements carry their result set metadata; running ones don't.
+    if response.get("resultSetMetaData").is_some() {
+        return None;
+    }
+    response["statementHandle"].as_str()
+}
+
+fn statement_error(body: &Value) -> String {
+    format!(
+        "Snowflake statement failed: {} (code {}, SQL state {})",
+        body["message"].as_str().unwrap_or("unknown error"),
+        body["code"].as_str().unwrap_or("?"),
+        body["sqlState"].as_str().unwrap_or("?")
+    )
+}
+
+fn literal(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// The `FILE_FORMAT` that reads files written with these settings, which
+/// start with `leading_lines` lines that aren't rows.
+pub fn file_format(dialect: CsvDialect, compression: FileCompression, leading_lines: u64) -> String {
+    let compression = match compression {
+        FileCompression::None => "NONE",
+        FileCompression::Gzip => "GZIP",
+        FileCompression::Zstd => "ZSTD",
+    };
+    let fields = match dialect {
+        CsvDialect::Rfc4180 | CsvDialect::Rfc4180Strict | CsvDialect::Excel => "FIELD_OPTIONALLY_ENCLOSED_BY = '\"'",
+        CsvDialect::Mysql => "FIELD_OPTIONALLY_ENCLOSED_BY = NONE ESCAPE_UNENCLOSED_FIELD = '\\\\' NULL_IF = ('\\\\N')",
+    };
+    format!("(TYPE = CSV SKIP_HEADER = {leading_lines} {fields} COMPRESSION = {compression})")
+}
+
+/// One `COPY INTO` per thousand files, naming the files so that only this
+/// run's output is loaded from the stage.
+fn copy_statements(table: &str, stage: &str, files: &[String], format: &str) -> Vec<String> {
+    files
+        .chunks(MAX_FILES_PER_COPY)
+        .map(|files| {
+            let names: Vec<_> = files.iter().map(|f| literal(f)).collect();
+            format!("COPY INTO {table} FROM {stage} FILES = ({}) FILE_FORMAT = {format}", names.join(", "))
+        })
+        .collect()
+}
+
+/// The `URL` of an external stage, from the rows `DESC STAGE` returns.
+fn stage_url(desc: &Value) -> Option<String> {
+    desc["data"].as_array()?.iter().find_map(|row| {
+        if row[1].as_str()? != "URL" {
+            return None;
+        }
+        // The value is itself a JSON list, e.g. `["s3://bucket/path/"]`.
+        let urls: Vec<String> = serde_json::from_str(row[3].as_str()?).ok()?;
+        urls.into_iter().next()
+    })
+}
+
+/// How Snowflake spells the location of a generated object.
+fn snowflake_url(destination: &Destination, azure_account: Option<&str>) -> Option<String> {
+    match destination {
+        Destination::S3 { bucket, key } => Some(format!("s3://{bucket}/{key}")),
+        Destination::Gcs { bucket, object } => Some(format!("gcs://{bucket}/{object}")),
+        Destination::Azure { container, blob } => {
+            Some(format!("azure://{}.blob.core.windows.net/{container}/{blob}", azure_account?))
+        }
+        _ => None,
+    }
+}
+
+/// The path of each output relative to the stage's URL.
+fn stage_paths(stage_url: &str, outputs: &[String], azure_account: Option<&str>) -> Result<Vec<String>, BoxError> {
+    let prefix = if stage_url.ends_with('/') { stage_url.to_string() } else { format!("{stage_url}/") };
+    outputs
+        .iter()
+        .map(|location| {
+            let url = snowflake_url(&Destination::parse(location)?, azure_account)
+                .ok_or_else(|| format!("{location} is not in cloud storage a Snowflake stage can read"))?;
+            match url.strip_prefix(&prefix) {
+                Some(path) => Ok(path.to_string()),
+                None => Err(format!("{location} is outside the stage's location {stage_url}").into()),
+            }
+        })
+        .collect()
+}
+
+/// Checks that the output can be staged, before spending hours generating data.
+pub fn validate(destination: &Destination) -> Result<(), BoxError> {
+    match destination {
+        Destination::S3 { .. } | Destination::Gcs { .. } | Destination::Azure { .. } => Ok(()),
+        _ => Err("--sf-copy-into needs an s3://, gs:// or az:// output under the stage's location".into()),
+    }
+}
+
+/// Copies the generated files from the external stage into the table,
+/// creating it first with `--sf-create-table`.
+pub fn copy_into(
+    client: &SnowflakeClient,
+    args: &SnowflakeArgs,
+    file_format: &str,
+    columns: &[Column],
+    outputs: &[String],
+) -> Result<(), BoxError> {
+    let (Some(table), Some(stage)) = (&args.sf_copy_into, &args.sf_stage) else {
+        unreachable!("--sf-copy-into and --sf-stage require each other");
+    };
+    let stage = if stage.starts_with('@') { stage.clone() } else { format!("@{stage}") };
+    let desc = client.execute(&format!("DESC STAGE {}", &stage[1..]))?;
+    let url = stage_url(&desc).ok_or_else(|| format!("{stage} is not an external stage with a URL"))?;
+    let azure_account = std::env::var("AZURE_STORAGE_ACCOUNT").ok();
+    let files = stage_paths(&url, outputs, azure_account.as_deref())?;
+
+    if args.sf_create_table {
+        client.execute(&ddl::snowflake_sql(table, columns))?;
+    }
+    println!("Copying {} file(s) from {stage} into {table}...", files.len());
+    let mut loaded = 0;
+    for statement in copy_statements(table, &stage, &files, file_format) {
+        loaded += rows_loaded(&client.execute(&statement)?);
+    }
+    println!("Snowflake COPY INTO {table} finished: {loaded} rows loaded.");
+    Ok(())
+}
+
+/// Sums the `rows_loaded` column of a `COPY INTO` result.
+fn rows_loaded(result: &Value) -> u64 {
+    let Some(column) = result["resultSetMetaData"]["rowType"]
+        .as_array()
+        .and_then(|columns| columns.iter().position(|c| c["name"].as_str().is_some_and(|n| n.eq_ignore_ascii_case("rows_loaded"))))
+    else {
+        return 0;
+    };
+    result["data"]
+        .as_array()
+        .map(|rows| rows.iter().filter_map(|row| row[column].as_str()?.parse::<u64>().ok()).sum())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jwt_accounts_drop_the_region() {
+        assert_eq!(jwt_account("xy12345.us-east-2.aws"), "XY12345");
+        assert_eq!(jwt_account("myorg-analytics"), "MYORG-ANALYTICS");
+    }
+
+    #[test]
+    fn formats_follow_the_output_settings() {
+        assert_eq!(
+            file_format(CsvDialect::Rfc4180, FileCompression::Gzip, 1),
+            "(TYPE = CSV SKIP_HEADER = 1 FIELD_OPTIONALLY_ENCLOSED_BY = '\"' COMPRESSION = GZIP)"
+        );
+        assert!(file_format(CsvDialect::Mysql, FileCompression::None, 0).contains("NULL_IF = ('\\\\N')"));
+    }
+
+    #[test]
+    fn builds_statements() {
+        let files: Vec<_> = (0..1500).map(|i| format!("seed/part-{i}.csv")).collect();
+        let statements = copy_statements("t", "@s", &files, "(TYPE = CSV)");
+        assert_eq!(statements.len(), 2);
+        assert!(statements[1].starts_with("COPY INTO t FROM @s FILES = ('seed/part-1000.csv', "));
+    }
+
+    #[test]
+    fn outputs_are_found_under_the_stage() {
+        let desc = json!({ "data": [
+            ["STAGE_FILE_FORMAT", "TYPE", "String", "CSV", "CSV"],
+            ["STAGE_LOCATION", "URL", "String", "[\"gcs://bucket/seed/\"]", ""],
+        ]});
+        let url = stage_url(&desc).unwrap();
+        assert_eq!(url, "gcs://bucket/seed/");
+        assert_eq!(stage_paths(&url, &["gs://bucket/seed/a/part-0.csv".into()], None).unwrap(), ["a/part-0.csv"]);
+        assert!(stage_paths(&url, &["gs://bucket/other/part-0.csv".into()], None).is_err());
+        assert_eq!(
+            stage_paths("azure://acct.blob.core.windows.net/c", &["az://c/part-0.csv".into()], Some("acct")).unwrap(),
+            ["part-0.csv"]
+        );
+    }
+
+    #[test]
+    fn reads_copy_results() {
+        let result = json!({
+            "resultSetMetaData": { "rowType": [{ "name": "file" }, { "name": "status" }, { "name": "rows_parsed" }, { "name": "rows_loaded" }] },
+            "data": [["a", "LOADED", "10", "10"], ["b", "LOADED", "5", "5"]],
+        });
+        assert_eq!(rows_loaded(&result), 15);
+        assert_eq!(running_handle(&result), None);
+        assert_eq!(running_handle(&json!({ "statementHandle": "01b2", "message": "Asynchronous execution in progress." })), Some("01b2"));
+    }
+
+    #[test]
+    fn request_ids_are_uuids() {
+        let id = uuid_v4();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert_ne!(id, uuid_v4());
+    }
+}
@@ -0,0 +1,255 @@
+//! The `sort` subcommand: an external merge sort of a CSV file by one or
+//! more columns. Rows are read into memory up to `--memory`, sorted and
+//! spilled to temporary files, which are then merged, so files far larger
+//! than memory sort in two passes over the disk. Columns inferred to be
+//! integers or decimals sort by value, the rest by their bytes, which puts
+//! dates and timestamps in order too. The sort is stable.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::BoxError;
+use crate::cli::{Args, FileCompression, SortArgs};
+use crate::convert::infer_columns;
+use crate::output::{Destination, GzipOutput, Output, Outputs};
+#[cfg(feature = "zstd")]
+use crate::output::ZstdOutput;
+use crate::schema::DataType;
+use crate::validate::{csv_reader, parse_decimal};
+
+/// The most runs merged at once, each an open file.
+const MAX_MERGE: usize = 256;
+
+/// Bytes a row is taken to use besides its fields.
+const ROW_OVERHEAD: usize = 64;
+
+/// How a column of the sort key compares.
+#[derive(Clone, Copy)]
+struct KeyColumn {
+    index: usize,
+    /// The scale of a numeric column, which compares by its unscaled value.
+    scale: Option<u8>,
+}
+
+/// Compares rows by their key columns, then by `descending`.
+struct Order {
+    columns: Vec<KeyColumn>,
+    descending: bool,
+}
+
+impl Order {
+    fn compare(&self, a: &csv::ByteRecord, b: &csv::ByteRecord) -> Ordering {
+        let ordering = self.columns.iter().fold(Ordering::Equal, |ordering, column| {
+            ordering.then_with(|| {
+                let (x, y) = (&a[column.index], &b[column.index]);
+                match column.scale {
+                    Some(scale) => number(x, scale).cmp(&number(y, scale)),
+                    None => x.cmp(y),
+                }
+            })
+        });
+        if self.descending { ordering.reverse() } else { ordering }
+    }
+}
+
+fn number(field: &[u8], scale: u8) -> Option<i64> {
+    std::str::from_utf8(field).ok().and_then(|text| parse_decimal(text, scale))
+}
+
+/// Sorts `sort.input` into `sort.output`.
+pub fn run(args: &Args, sort: &SortArgs) -> Result<(), BoxError> {
+    let columns = infer_columns(&sort.input)?;
+    let key = sort
+        .by
+        .iter()
+        .map(|name| {
+            let index = columns.iter().position(|c| &c.name == name).ok_or_else(|| format!("no column {name} to sort by"))?;
+            let scale = match columns[index].kind.data_type() {
+                DataType::Integer => Some(0),
+                DataType::Decimal { scale } => Some(scale),
+                _ => None,
+            };
+            Ok(KeyColumn { index, scale })
+        })
+        .collect::<Result<Vec<_>, BoxError>>()?;
+    let order = Order { columns: key, descending: sort.descending };
+
+    let dir = sort.temp_dir.clone().unwrap_or_else(std::env::temp_dir).join(format!("csv_data_generator_sort_{}", std::process::id()));
+    fs::create_dir_all(&dir).map_err(|e| format!("creating {}: {e}", dir.display()))?;
+    let result = sort_into(args, sort, &order, &dir);
+    fs::remove_dir_all(&dir)?;
+    let rows = result?;
+    println!("Sorted {rows} rows into {}", sort.output);
+    Ok(())
+}
+
+fn sort_into(args: &Args, sort: &SortArgs, order: &Order, dir: &Path) -> Result<u64, BoxError> {
+    let name = sort.input.display();
+    let mut reader = csv_reader(&sort.input)?;
+    let header = reader.byte_headers().map_err(|e| format!("{name}: {e}"))?.clone();
+    let limit = usize::try_from(sort.memory).unwrap_or(usize::MAX);
+    let mut runs: Vec<PathBuf> = Vec::new();
+    let mut rows: Vec<csv::ByteRecord> = Vec::new();
+    let (mut used, mut total) = (0, 0u64);
+    let mut record = csv::ByteRecord::new();
+    while reader.read_byte_record(&mut record).map_err(|e| format!("{name}: {e}"))? {
+        if record.len() != header.len() {
+            return Err(format!("{name} row {}: {} fields, not {}", total + 1, record.len(), header.len()).into());
+        }
+        total += 1;
+        used += record.as_slice().len() + record.len() * 8 + ROW_OVERHEAD;
+        rows.push(record.clone());
+        if used >= limit {
+            rows.sort_by(|a, b| order.compare(a, b));
+            runs.push(write_run(dir, runs.len(), &rows)?);
+            rows.clear();
+            used = 0;
+        }
+    }
+    rows.sort_by(|a, b| order.compare(a, b));
+
+    let outputs = Outputs::new(args);
+    let output = outputs.open(&Destination::parse(&sort.output)?)?;
+    let output: Box<dyn Output> = match sort.compression {
+        FileCompression::None => output,
+        FileCompression::Gzip => Box::new(GzipOutput::new(output)),
+        #[cfg(feature = "zstd")]
+        FileCompression::Zstd => Box::new(ZstdOutput::new(output)?),
+        #[cfg(not(feature = "zstd"))]
+        FileCompression::Zstd => return Err(crate::without("zstd", "Zstandard compression")),
+    };
+    let mut writer = csv::Writer::from_writer(output);
+    writer.write_byte_record(&header)?;
+    if runs.is_empty() {
+        for row in &rows {
+            writer.write_byte_record(row)?;
+        }
+    } else {
+        if !rows.is_empty() {
+            runs.push(write_run(dir, runs.len(), &rows)?);
+        }
+        drop(rows);
+        // Merge the runs in groups until few enough are left to merge into the output.
+        let mut next = runs.len();
+        while runs.len() > MAX_MERGE {
+            let mut merged = Vec::new();
+            for group in runs.chunks(MAX_MERGE) {
+                let path = dir.join(format!("run-{next}.csv"));
+                next += 1;
+                let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(BufWriter::new(File::create(&path)?));
+                merge(group, order, &mut writer)?;
+                writer.flush()?;
+                for run in group {
+                    fs::remove_file(run)?;
+                }
+                merged.push(path);
+            }
+            runs = merged;
+        }
+        merge(&runs, order, &mut writer)?;
+    }
+    let output = writer.into_inner().map_err(|e| e.into_error())?;
+    output.finish()?;
+    Ok(total)
+}
+
+/// Writes sorted `rows` to the `index`th run file in `dir`.
+fn write_run(dir: &Path, index: usize, rows: &[csv::ByteRecord]) -> Result<PathBuf, BoxError> {
+    let path = dir.join(format!("run-{index}.csv"));
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(BufWriter::new(File::create(&path)?));
+    for row in rows {
+        writer.write_byte_record(row)?;
+    }
+    writer.flush()?;
+    Ok(path)
+}
+
+/// The next row of a run being merged.
+struct Head<'a> {
+    record: csv::ByteRecord,
+    run: usize,
+    order: &'a Order,
+}
+
+impl Ord for Head<'_> {
+    /// Ties go to the earlier run, which holds earlier rows, keeping the sort stable.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order.compare(&self.record, &other.record).then(self.run.cmp(&other.run))
+    }
+}
+
+impl PartialOrd for Head<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head<'_> {}
+
+/// Merges the sorted `runs` into `writer`.
+fn merge<W: Write>(runs: &[PathBuf], order: &Order, writer: &mut csv::Writer<W>) -> Result<(), BoxError> {
+    let mut readers = runs
+        .iter()
+        .map(|run| Ok(csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(File::open(run)?)))
+        .collect::<Result<Vec<_>, BoxError>>()?;
+    let mut heap = BinaryHeap::new();
+    for (run, reader) in readers.iter_mut().enumerate() {
+        let mut record = csv::ByteRecord::new();
+        if reader.read_byte_record(&mut record)? {
+            heap.push(Reverse(Head { record, run, order }));
+        }
+    }
+    while let Some(Reverse(mut head)) = heap.pop() {
+        writer.write_byte_record(&head.record)?;
+        if readers[head.run].read_byte_record(&mut head.record)? {
+            heap.push(Reverse(head));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Command;
+    use clap::Parser;
+
+    #[test]
+    fn sorts_beyond_memory() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_sort_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("in.csv"), dir.join("out.csv"));
+        // Amounts repeat, so rows with the same one stay in input order.
+        let rows: Vec<(u32, String)> = (0..2000).map(|i| (i, format!("{}.{:02}", (i * 7919) % 50, i % 3))).collect();
+        let lines: String = rows.iter().map(|(i, amount)| format!("{i},{amount}\n")).collect();
+        fs::write(&input, format!("id,amount\n{lines}")).unwrap();
+        let args = Args::parse_from([
+            "gen",
+            "sort",
+            input.to_str().unwrap(),
+            "--by",
+            "amount",
+            "--memory",
+            "4KB",
+            "--output",
+            output.to_str().unwrap(),
+        ]);
+        let Some(Command::Sort(sort)) = &args.command else { unreachable!() };
+        run(&args, sort).unwrap();
+
+        let mut expected = rows;
+        expected.sort_by_key(|(_, amount)| parse_decimal(amount, 2));
+        let expected: String = expected.iter().map(|(i, amount)| format!("{i},{amount}\n")).collect();
+        assert_eq!(fs::read_to_string(&output).unwrap(), format!("id,amount\n{expected}"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,371 @@
+//! `--column-stats`: a `.stats.json` next to each file with the count, nulls,
+//! distinct values, minimum and maximum of every column, and the mean,
+//! standard deviation and a histogram of numeric ones, gathered as the rows
+//! are written, so tests can check aggregates without reading the data back.
+//!
+//! They describe the values as written, empty fields counting as nulls, so
+//! they include whatever the dirty-data options changed. Distinct counts are
+//! HyperLogLog estimates, within about 2% of the true count.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::BoxError;
+use crate::generator::Row;
+use crate::output::{Destination, Output, Outputs};
+use crate::schema::{Column, DataType};
+use crate::sink::Sink;
+use crate::validate::parse_decimal;
+
+const SUFFIX: &str = ".stats.json";
+
+/// A numeric column's histogram is kept to at most this many buckets.
+const MAX_BUCKETS: usize = 64;
+
+/// Bits of the hash that pick a HyperLogLog register.
+const HLL_BITS: u32 = 12;
+
+/// Gathers the statistics of the rows passed on.
+pub struct StatsSink {
+    inner: Box<dyn Sink>,
+    output: Box<dyn Output>,
+    rows: u64,
+    columns: Vec<ColumnStats>,
+}
+
+impl StatsSink {
+    /// Wraps `inner`, unless `--column-stats` isn't given.
+    pub fn wrap(
+        inner: Box<dyn Sink>,
+        outputs: &Outputs,
+        data: &Destination,
+        columns: &[Column],
+    ) -> Result<Box<dyn Sink>, BoxError> {
+        if !outputs.args().column_stats {
+            return Ok(inner);
+        }
+        let destination = data.beside(SUFFIX).ok_or("--column-stats needs a file or cloud storage output")?;
+        let output = outputs.open(&destination)?;
+        let columns = columns.iter().map(|c| ColumnStats::new(&c.name, c.kind.data_type())).collect();
+        Ok(Box::new(StatsSink { inner, output, rows: 0, columns }))
+    }
+}
+
+impl Sink for StatsSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.rows += 1;
+        for (column, field) in self.columns.iter_mut().zip(row.fields()) {
+            column.add(field);
+        }
+        self.inner.write_row(row)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.inner.flush()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.inner.finish()?;
+        let report = Report { rows: self.rows, columns: self.columns.iter().map(ColumnStats::report).collect() };
+        let mut output = self.output;
+        serde_json::to_writer_pretty(&mut output, &report)?;
+        output.write_all(b"\n")?;
+        output.finish()
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    rows: u64,
+    columns: Vec<ColumnReport>,
+}
+
+#[derive(Serialize)]
+struct ColumnReport {
+    name: String,
+    #[serde(rename = "type")]
+    data_type: &'static str,
+    /// Fields that aren't empty.
+    values: u64,
+    nulls: u64,
+    /// Values that aren't of the column's type, which aren't in the minimum,
+    /// maximum, mean or histogram.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    invalid: Option<u64>,
+    distinct: u64,
+    min: Option<String>,
+    max: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mean: Option<f64>,
+    /// The population standard deviation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stddev: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    histogram: Option<Vec<Bucket>>,
+}
+
+/// Values from `from` up to but not including `to`.
+#[derive(Serialize)]
+struct Bucket {
+    from: f64,
+    to: f64,
+    count: u64,
+}
+
+struct ColumnStats {
+    name: String,
+    data_type: DataType,
+    values: u64,
+    nulls: u64,
+    invalid: u64,
+    distinct: HyperLogLog,
+    /// The least and greatest valid values, and for numbers their unscaled
+    /// values, which they're ordered by first.
+    min: Option<(i64, Vec<u8>)>,
+    max: Option<(i64, Vec<u8>)>,
+    /// Welford's running mean and sum of squared differences from it, of
+    /// the unscaled values.
+    mean: f64,
+    m2: f64,
+    histogram: Histogram,
+}
+
+impl ColumnStats {
+    fn new(name: &str, data_type: DataType) -> Self {
+        ColumnStats {
+            name: name.to_string(),
+            data_type,
+            values: 0,
+            nulls: 0,
+            invalid: 0,
+            distinct: HyperLogLog::new(),
+            min: None,
+            max: None,
+            mean: 0.0,
+            m2: 0.0,
+            histogram: Histogram::new(),
+        }
+    }
+
+    fn add(&mut self, field: &[u8]) {
+        if field.is_empty() {
+            self.nulls += 1;
+            return;
+        }
+        self.values += 1;
+        self.distinct.add(field);
+        let unscaled = match self.data_type {
+            DataType::String => 0,
+            DataType::Integer | DataType::Decimal { .. } => {
+                let Some(unscaled) = std::str::from_utf8(field).ok().and_then(|text| parse_decimal(text, self.scale()))
+                else {
+                    self.invalid += 1;
+                    return;
+                };
+                let valid = (self.values - self.invalid) as f64;
+                let delta = unscaled as f64 - self.mean;
+                self.mean += delta / valid;
+                self.m2 += delta * (unscaled as f64 - self.mean);
+                self.histogram.add(unscaled);
+                unscaled
+            }
+            DataType::Date | DataType::Timestamp => {
+                // Written alike, these order as text as they do in time.
+                let shape: &[u8] = if self.data_type == DataType::Date { b"0000-00-00" } else { b"0000-00-00 00:00:00" };
+                let fits = field.len() == shape.len()
+                    && field.iter().zip(shape).all(|(&b, &s)| if s == b'0' { b.is_ascii_digit() } else { b == s });
+                if !fits {
+                    self.invalid += 1;
+                    return;
+                }
+                0
+            }
+        };
+        if self.min.as_ref().is_none_or(|(u, min)| (unscaled, field) < (*u, min.as_slice())) {
+            self.min = Some((unscaled, field.to_vec()));
+        }
+        if self.max.as_ref().is_none_or(|(u, max)| (unscaled, field) > (*u, max.as_slice())) {
+            self.max = Some((unscaled, field.to_vec()));
+        }
+    }
+
+    fn scale(&self) -> u8 {
+        match self.data_type {
+            DataType::Decimal { scale } => scale,
+            _ => 0,
+        }
+    }
+
+    fn report(&self) -> ColumnReport {
+        let text = |value: &Option<(i64, Vec<u8>)>| value.as_ref().map(|(_, v)| String::from_utf8_lossy(v).into_owned());
+        let numeric = matches!(self.data_type, DataType::Integer | DataType::Decimal { .. });
+        let valid = self.values - self.invalid;
+        let unit = 10f64.powi(self.scale().into());
+        ColumnReport {
+            name: self.name.clone(),
+            data_type: match self.data_type {
+                DataType::String => "string",
+                DataType::Integer => "integer",
+                DataType::Decimal { .. } => "decimal",
+                DataType::Date => "date",
+                DataType::Timestamp => "timestamp",
+            },
+            values: self.values,
+            nulls: self.nulls,
+            invalid: (self.data_type != DataType::String).then_some(self.invalid),
+            distinct: self.distinct.estimate(),
+            min: text(&self.min),
+            max: text(&self.max),
+            mean: (numeric && valid > 0).then(|| self.mean / unit),
+            stddev: (numeric && valid > 0).then(|| (self.m2 / valid as f64).sqrt() / unit),
+            histogram: numeric.then(|| self.histogram.buckets(unit)),
+        }
+    }
+}
+
+/// Counts of values in equal buckets `width` wide, bucket `k` holding those
+/// from `k * width`. Whenever the values span more than [`MAX_BUCKETS`], the
+/// width doubles and neighbouring buckets merge, so any range fits.
+struct Histogram {
+    width: i128,
+    counts: BTreeMap<i128, u64>,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Histogram { width: 1, counts: BTreeMap::new() }
+    }
+
+    fn add(&mut self, value: i64) {
+        *self.counts.entry(i128::from(value).div_euclid(self.width)).or_default() += 1;
+        while self.span() > MAX_BUCKETS as i128 {
+            self.width *= 2;
+            let mut merged = BTreeMap::new();
+            for (bucket, count) in &self.counts {
+                *merged.entry(bucket.div_euclid(2)).or_default() += count;
+            }
+            self.counts = merged;
+        }
+    }
+
+    fn span(&self) -> i128 {
+        match (self.counts.first_key_value(), self.counts.last_key_value()) {
+            (Some((first, _)), Some((last, _))) => last - first + 1,
+            _ => 0,
+        }
+    }
+
+    /// Every bucket from the lowest to the highest value, in units of `unit`.
+    fn buckets(&self, unit: f64) -> Vec<Bucket> {
+        let (Some((&first, _)), Some((&last, _))) = (self.counts.first_key_value(), self.counts.last_key_value()) else {
+            return Vec::new();
+        };
+        (first..=last)
+            .map(|k| Bucket {
+                from: (k * self.width) as f64 / unit,
+                to: ((k + 1) * self.width) as f64 / unit,
+                count: self.counts.get(&k).copied().unwrap_or(0),
+            })
+            .collect()
+    }
+}
+
+/// Flajolet et al.'s distinct count estimate, with linear counting for
+/// small counts.
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new() -> Self {
+        HyperLogLog { registers: vec![0; 1 << HLL_BITS] }
+    }
+
+    fn add(&mut self, value: &[u8]) {
+        let hash = hash(value);
+        let register = (hash >> (64 - HLL_BITS)) as usize;
+        // The guard bit caps the rank at the bits left after the register's.
+        let rank = ((hash << HLL_BITS) | (1 << (HLL_BITS - 1))).leading_zeros() as u8 + 1;
+        self.registers[register] = self.registers[register].max(rank);
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-i32::from(r))).sum();
+        let raw = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 { m * (m / zeros as f64).ln() } else { raw };
+        estimate.round() as u64
+    }
+}
+
+/// FNV-1a, mixed by MurmurHash3's finalizer so every bit of the result
+/// depends on every byte.
+fn hash(value: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &b in value {
+        hash = (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use crate::schema::ColumnKind;
+    use crate::sink;
+    use clap::Parser;
+    use std::fs;
+
+    #[test]
+    fn stats_describe_the_values_written() {
+        let path = std::env::temp_dir().join(format!("csv_data_generator_stats_{}.csv", std::process::id()));
+        let args = Args::parse_from(["gen", "--output", path.to_str().unwrap(), "--column-stats"]);
+        let columns = [
+            Column::new("id", ColumnKind::Sequence { start: 1 }),
+            Column::new("price", ColumnKind::Decimal { min: 0, max: 100_000, scale: 2 }),
+            Column::new("name", ColumnKind::Sha256Hex),
+        ];
+        let mut sink = sink::open(&Outputs::new(&args), &Destination::File(path.clone()), &columns).unwrap();
+        let mut row = Row::new();
+        for i in 1..=10_000i64 {
+            row.clear();
+            row.push(i.to_string().as_bytes());
+            row.push(if i % 10 == 0 { "".into() } else { format!("{}.{:02}", i / 100, i % 100) }.as_bytes());
+            row.push(format!("name {}", i % 1000).as_bytes());
+            sink.write_row(&row).unwrap();
+        }
+        sink.finish().unwrap();
+
+        let stats_path = format!("{}{SUFFIX}", path.display());
+        let stats: serde_json::Value = serde_json::from_slice(&fs::read(&stats_path).unwrap()).unwrap();
+        assert_eq!(stats["rows"], 10_000);
+        let [id, price, name] = [0, 1, 2].map(|i| &stats["columns"][i]);
+        assert_eq!((&id["min"], &id["max"], &id["invalid"]), (&"1".into(), &"10000".into(), &0.into()));
+        assert_eq!(id["mean"], 5000.5);
+        assert!((id["stddev"].as_f64().unwrap() - 2886.75).abs() < 0.01);
+        let histogram = id["histogram"].as_array().unwrap();
+        assert!(histogram.len() <= MAX_BUCKETS);
+        assert_eq!(histogram.iter().map(|b| b["count"].as_u64().unwrap()).sum::<u64>(), 10_000);
+        assert_eq!((&price["values"], &price["nulls"]), (&9000.into(), &1000.into()));
+        assert_eq!((&price["min"], &price["max"]), (&"0.01".into(), &"99.99".into()));
+        assert_eq!(histogram[0]["from"], 0.0);
+        let distinct = id["distinct"].as_f64().unwrap();
+        assert!((distinct - 10_000.0).abs() < 300.0, "{distinct}");
+        assert!((name["distinct"].as_f64().unwrap() - 1000.0).abs() < 30.0);
+        assert!(name.get("mean").is_none() && name.get("invalid").is_none());
+        fs::remove_file(path).unwrap();
+        fs::remove_file(stats_path).unwrap();
+    }
+}
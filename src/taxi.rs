@@ -0,0 +1,372 @@
+//! The taxi preset: `trips` in the shape of the NYC TLC yellow taxi trip
+//! records, and the `taxi_zones` their location ids refer to.
+//!
+//! Trips during January 2024 go between a subset of the real taxi zones,
+//! mostly in Manhattan and to and from the airports, for the road distance
+//! between the zones' centres and at the speed traffic allows at that hour.
+//! Fares follow the meter, $3 plus 70 cents a fifth of a mile and 70 cents a
+//! minute in slow traffic, except for the flat fare between JFK and
+//! Manhattan and the surcharge to Newark, and the surcharges, tolls and tips
+//! add up to `total_amount` to the cent.
+
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::generator::{Row, push_decimal, push_timestamp};
+use crate::presets::{self, push_int};
+use crate::schema::{Column, ColumnKind};
+use crate::signal;
+
+/// Trips start during the 31 days of January 2024, in the city's time.
+const WINDOW_START: i64 = 1_704_067_200;
+const DAYS: i64 = 31;
+
+/// Trips at scale factor 1, about a thirtieth of a real month's.
+const TRIPS: f64 = 100_000.0;
+
+const TABLES: [&str; 2] = ["taxi_zones", "trips"];
+
+/// A taxi zone: its TLC `LocationID`, borough, name, centre and how many
+/// trips start there, relatively.
+struct Zone {
+    id: u16,
+    borough: &'static str,
+    name: &'static str,
+    centre: (f64, f64),
+    weight: u32,
+}
+
+const fn z(id: u16, borough: &'static str, name: &'static str, centre: (f64, f64), weight: u32) -> Zone {
+    Zone { id, borough, name, centre, weight }
+}
+
+const ZONES: &[Zone] = &[
+    z(1, "EWR", "Newark Airport", (40.6895, -74.1745), 2),
+    z(7, "Queens", "Astoria", (40.7640, -73.9230), 8),
+    z(43, "Manhattan", "Central Park", (40.7812, -73.9665), 20),
+    z(48, "Manhattan", "Clinton East", (40.7620, -73.9900), 30),
+    z(65, "Brooklyn", "Downtown Brooklyn/MetroTech", (40.6930, -73.9870), 8),
+    z(68, "Manhattan", "East Chelsea", (40.7465, -74.0014), 30),
+    z(79, "Manhattan", "East Village", (40.7265, -73.9815), 30),
+    z(87, "Manhattan", "Financial District North", (40.7090, -74.0070), 20),
+    z(88, "Manhattan", "Financial District South", (40.7040, -74.0120), 15),
+    z(97, "Brooklyn", "Fort Greene", (40.6890, -73.9740), 5),
+    z(100, "Manhattan", "Garment District", (40.7540, -73.9900), 25),
+    z(107, "Manhattan", "Gramercy", (40.7370, -73.9840), 30),
+    z(113, "Manhattan", "Greenwich Village North", (40.7330, -73.9980), 25),
+    z(114, "Manhattan", "Greenwich Village South", (40.7290, -73.9990), 25),
+    z(129, "Queens", "Jackson Heights", (40.7560, -73.8830), 5),
+    z(132, "Queens", "JFK Airport", (40.6413, -73.7781), 60),
+    z(138, "Queens", "LaGuardia Airport", (40.7769, -73.8740), 40),
+    z(140, "Manhattan", "Lenox Hill East", (40.7660, -73.9560), 25),
+    z(141, "Manhattan", "Lenox Hill West", (40.7680, -73.9620), 30),
+    z(142, "Manhattan", "Lincoln Square East", (40.7740, -73.9820), 30),
+    z(145, "Queens", "Long Island City/Hunters Point", (40.7440, -73.9500), 6),
+    z(161, "Manhattan", "Midtown Center", (40.7580, -73.9780), 40),
+    z(162, "Manhattan", "Midtown East", (40.7560, -73.9700), 35),
+    z(163, "Manhattan", "Midtown North", (40.7640, -73.9780), 30),
+    z(164, "Manhattan", "Midtown South", (40.7480, -73.9850), 30),
+    z(166, "Manhattan", "Morningside Heights", (40.8090, -73.9620), 10),
+    z(168, "Bronx", "Mott Haven/Port Morris", (40.8060, -73.9200), 3),
+    z(170, "Manhattan", "Murray Hill", (40.7480, -73.9780), 30),
+    z(181, "Brooklyn", "Park Slope", (40.6720, -73.9780), 5),
+    z(186, "Manhattan", "Penn Station/Madison Sq West", (40.7500, -73.9930), 35),
+    z(226, "Queens", "Sunnyside", (40.7430, -73.9210), 3),
+    z(230, "Manhattan", "Times Sq/Theatre District", (40.7590, -73.9860), 35),
+    z(231, "Manhattan", "TriBeCa/Civic Center", (40.7160, -74.0080), 20),
+    z(234, "Manhattan", "Union Sq", (40.7360, -73.9900), 35),
+    z(236, "Manhattan", "Upper East Side North", (40.7770, -73.9530), 35),
+    z(237, "Manhattan", "Upper East Side South", (40.7700, -73.9600), 40),
+    z(238, "Manhattan", "Upper West Side North", (40.7920, -73.9720), 20),
+    z(239, "Manhattan", "Upper West Side South", (40.7830, -73.9780), 25),
+    z(249, "Manhattan", "West Village", (40.7350, -74.0050), 25),
+    z(255, "Brooklyn", "Williamsburg (North Side)", (40.7180, -73.9570), 6),
+    z(256, "Brooklyn", "Williamsburg (South Side)", (40.7090, -73.9580), 4),
+    z(261, "Manhattan", "World Trade Center", (40.7110, -74.0130), 15),
+    z(263, "Manhattan", "Yorkville West", (40.7790, -73.9500), 20),
+];
+
+const JFK: u16 = 132;
+const LAGUARDIA: u16 = 138;
+const NEWARK: u16 = 1;
+
+/// Road miles per mile as the crow flies.
+const DETOUR: f64 = 1.3;
+/// Fares in cents: the initial charge, a mile, a minute in slow traffic,
+/// the flat fare between JFK and Manhattan and the surcharge to Newark.
+const BASE_FARE: i64 = 300;
+const PER_MILE: i64 = 350;
+const PER_SLOW_MINUTE: i64 = 70;
+const JFK_FLAT_FARE: i64 = 7000;
+const NEWARK_SURCHARGE: i64 = 2000;
+/// Slower than this counts as slow traffic, in miles an hour.
+const SLOW_SPEED: f64 = 12.0;
+/// Surcharges in cents.
+const OVERNIGHT: i64 = 100;
+const RUSH_HOUR: i64 = 250;
+const MTA_TAX: i64 = 50;
+const IMPROVEMENT: i64 = 100;
+const CONGESTION: i64 = 250;
+const AIRPORT_FEE: i64 = 175;
+const TOLL: i64 = 694;
+
+/// How often trips start in each hour of the day.
+const HOURS: [u32; 24] = [6, 4, 3, 2, 2, 2, 4, 7, 9, 9, 9, 9, 10, 10, 10, 11, 11, 12, 13, 12, 11, 10, 9, 8];
+const PASSENGERS: &[(u64, u32)] = &[(1, 72), (2, 14), (3, 4), (4, 2), (5, 5), (6, 3)];
+/// Credit card, cash, no charge and dispute.
+const PAYMENTS: &[(u64, u32)] = &[(1, 76), (2, 21), (3, 2), (4, 1)];
+/// The tip percentages card payers pick.
+const TIPS: &[(i64, u32)] = &[(0, 10), (15, 10), (20, 45), (25, 25), (30, 10)];
+
+/// The columns of each table, in [`TABLES`] order, named as the TLC names
+/// them. Only the names and types of these matter; the values are written
+/// below.
+fn columns() -> [Vec<Column>; 2] {
+    let integer = |name| Column::new(name, ColumnKind::Sequence { start: 1 });
+    let string = |name| Column::new(name, ColumnKind::Choice(Vec::new()));
+    let timestamp = |name| Column::new(name, ColumnKind::TimestampRange { min: 0, max: 0 });
+    let money = |name| Column::new(name, ColumnKind::Decimal { min: 0, max: 0, scale: 2 });
+    [
+        vec![integer("LocationID"), string("Borough"), string("Zone"), string("service_zone")],
+        vec![
+            integer("VendorID"),
+            timestamp("tpep_pickup_datetime"),
+            timestamp("tpep_dropoff_datetime"),
+            integer("passenger_count"),
+            money("trip_distance"),
+            integer("RatecodeID"),
+            string("store_and_fwd_flag"),
+            integer("PULocationID"),
+            integer("DOLocationID"),
+            integer("payment_type"),
+            money("fare_amount"),
+            money("extra"),
+            money("mta_tax"),
+            money("tip_amount"),
+            money("tolls_amount"),
+            money("improvement_surcharge"),
+            money("total_amount"),
+            money("congestion_surcharge"),
+            money("Airport_fee"),
+        ],
+    ]
+}
+
+fn service_zone(zone: &Zone) -> &'static str {
+    match (zone.id, zone.borough) {
+        (NEWARK, _) => "EWR",
+        (JFK | LAGUARDIA, _) => "Airports",
+        (_, "Manhattan") => "Yellow Zone",
+        _ => "Boro Zone",
+    }
+}
+
+/// Miles as the crow flies between two points.
+fn haversine((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (dlat, dlon) = ((lat2 - lat1).to_radians(), (lon2 - lon1).to_radians());
+    let a = (dlat / 2.0).sin().powi(2) + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * 3958.8 * a.sqrt().asin()
+}
+
+/// Miles an hour in traffic at `hour`.
+fn speed(hour: i64, weekday: bool) -> f64 {
+    match hour {
+        7..=9 | 16..=19 if weekday => 9.0,
+        6..=21 => 12.0,
+        _ => 18.0,
+    }
+}
+
+fn pick<T: Copy, R: Rng + ?Sized>(rng: &mut R, items: &[(T, u32)], index: &WeightedIndex<u32>) -> T {
+    items[index.sample(rng)].0
+}
+
+/// The amounts of a trip in cents, in the order of the `trips` columns from
+/// `fare_amount` on, but for `total_amount`, which is their sum.
+struct Charges {
+    fare: i64,
+    extra: i64,
+    mta_tax: i64,
+    tip: i64,
+    tolls: i64,
+    improvement: i64,
+    congestion: i64,
+    airport: i64,
+}
+
+impl Charges {
+    fn total(&self) -> i64 {
+        self.fare + self.extra + self.mta_tax + self.tip + self.tolls + self.improvement + self.congestion + self.airport
+    }
+}
+
+/// Writes the zones, then `--scale-factor` times the usual trips between
+/// them.
+pub fn generate(args: &Args) -> Result<(), BoxError> {
+    let [mut zones_sink, mut trips_sink] = presets::open_tables(args, "taxi", TABLES, columns())?;
+
+    let mut row = Row::new();
+    for zone in ZONES {
+        row.clear();
+        push_int(&mut row, zone.id.into());
+        row.push(zone.borough.as_bytes());
+        row.push(zone.name.as_bytes());
+        row.push(service_zone(zone).as_bytes());
+        zones_sink.write_row(&row)?;
+    }
+
+    let mut rng = rand::thread_rng();
+    let zones = WeightedIndex::new(ZONES.iter().map(|z| z.weight))?;
+    let hours = WeightedIndex::new(HOURS)?;
+    let passengers = WeightedIndex::new(PASSENGERS.iter().map(|p| p.1))?;
+    let payments = WeightedIndex::new(PAYMENTS.iter().map(|p| p.1))?;
+    let tips = WeightedIndex::new(TIPS.iter().map(|t| t.1))?;
+    let mut trips = ((TRIPS * args.preset.scale_factor).round() as u64).max(1);
+    let mut revenue = 0;
+    for trip in 0..trips {
+        if signal::interrupted() {
+            trips = trip;
+            break;
+        }
+        let pickup_zone = &ZONES[zones.sample(&mut rng)];
+        // Airport trips mostly go to Manhattan, and others mostly stay in their borough.
+        let dropoff_zone = loop {
+            let zone = &ZONES[zones.sample(&mut rng)];
+            let airport = matches!(pickup_zone.id, JFK | LAGUARDIA | NEWARK);
+            let wanted = if airport { zone.borough == "Manhattan" } else { zone.borough == pickup_zone.borough };
+            if wanted || rng.gen_bool(0.15) {
+                break zone;
+            }
+        };
+        let day = rng.gen_range(0..DAYS);
+        let hour = hours.sample(&mut rng) as i64;
+        let pickup = WINDOW_START + day * 86_400 + hour * 3600 + rng.gen_range(0..3600);
+        // 2024-01-01 was a Monday.
+        let weekday = day % 7 < 5;
+        let miles = if pickup_zone.id == dropoff_zone.id {
+            rng.gen_range(0.3..1.5)
+        } else {
+            haversine(pickup_zone.centre, dropoff_zone.centre) * DETOUR * rng.gen_range(0.9..1.2)
+        };
+        let hundredths = (miles * 100.0).round() as i64;
+        let minutes = miles / speed(hour, weekday) * 60.0 * rng.gen_range(0.8..1.25);
+        let seconds = (minutes * 60.0).round().max(60.0) as i64;
+        let slow_minutes = (minutes - miles / SLOW_SPEED * 60.0).max(0.0) as i64;
+
+        let manhattan = |zone: &Zone| zone.borough == "Manhattan";
+        let (rate_code, fare) = match (pickup_zone.id, dropoff_zone.id) {
+            (JFK, _) if manhattan(dropoff_zone) => (2, JFK_FLAT_FARE),
+            (_, JFK) if manhattan(pickup_zone) => (2, JFK_FLAT_FARE),
+            (_, NEWARK) => (3, BASE_FARE + PER_MILE * hundredths / 100 + PER_SLOW_MINUTE * slow_minutes + NEWARK_SURCHARGE),
+            _ => (1, BASE_FARE + PER_MILE * hundredths / 100 + PER_SLOW_MINUTE * slow_minutes),
+        };
+        let payment = pick(&mut rng, PAYMENTS, &payments);
+        let extra = match hour {
+            20.. | ..6 => OVERNIGHT,
+            16..20 if weekday => RUSH_HOUR,
+            _ => 0,
+        };
+        let mut charges = Charges {
+            fare,
+            extra,
+            mta_tax: MTA_TAX,
+            tip: 0,
+            tolls: if pickup_zone.borough != dropoff_zone.borough && rng.gen_bool(0.6) { TOLL } else { 0 },
+            improvement: IMPROVEMENT,
+            congestion: if manhattan(pickup_zone) || manhattan(dropoff_zone) { CONGESTION } else { 0 },
+            airport: if matches!(pickup_zone.id, JFK | LAGUARDIA) { AIRPORT_FEE } else { 0 },
+        };
+        // Only card tips are recorded; cash tips go unseen.
+        if payment == 1 {
+            charges.tip = (charges.fare + charges.extra) * pick(&mut rng, TIPS, &tips) / 100;
+        }
+        revenue += charges.total();
+
+        row.clear();
+        push_int(&mut row, if rng.gen_bool(0.7) { 2 } else { 1 });
+        push_timestamp(&mut row, pickup);
+        push_timestamp(&mut row, pickup + seconds);
+        push_int(&mut row, pick(&mut rng, PASSENGERS, &passengers));
+        push_decimal(&mut row, hundredths, 2);
+        push_int(&mut row, rate_code);
+        row.push(if rng.gen_bool(0.005) { b"Y" } else { b"N" });
+        push_int(&mut row, pickup_zone.id.into());
+        push_int(&mut row, dropoff_zone.id.into());
+        push_int(&mut row, payment);
+        let total = charges.total();
+        let Charges { fare, extra, mta_tax, tip, tolls, improvement, congestion, airport } = charges;
+        for cents in [fare, extra, mta_tax, tip, tolls, improvement, total, congestion, airport] {
+            push_decimal(&mut row, cents, 2);
+        }
+        trips_sink.write_row(&row)?;
+    }
+
+    presets::finish_tables(args, TABLES, [zones_sink, trips_sink], [ZONES.len() as u64, trips])?;
+    println!("  ${:.2} in fares, surcharges and tips", revenue as f64 / 100.0);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::testing::{read, temp_dir};
+    use crate::schema::parse_timestamp;
+    use clap::Parser;
+    use std::collections::HashMap;
+    use std::fs;
+
+    fn cents(s: &str) -> i64 {
+        let (dollars, cents) = s.split_once('.').unwrap();
+        dollars.parse::<i64>().unwrap() * 100 + cents.parse::<i64>().unwrap()
+    }
+
+    #[test]
+    fn fares_add_up() {
+        let dir = temp_dir("taxi");
+        let template = dir.join("{table}.csv");
+        let args = Args::parse_from(["gen", "--preset", "taxi", "--scale-factor", "0.05", "--output", template.to_str().unwrap()]);
+        generate(&args).unwrap();
+
+        let zones: HashMap<String, Vec<String>> = read(dir.join("taxi_zones.csv")).into_iter().map(|z| (z[0].clone(), z)).collect();
+        let trips = read(dir.join("trips.csv"));
+        assert_eq!(trips.len(), 5000);
+        let mut rate_codes = HashMap::new();
+        for t in &trips {
+            let time = |s: &str| parse_timestamp(s).unwrap();
+            assert!(time(&t[2]) > time(&t[1]));
+            let (pickup, dropoff) = (&zones[&t[7]], &zones[&t[8]]);
+            let amounts: Vec<i64> = t[10..].iter().map(|a| cents(a)).collect();
+            let total = amounts[0..6].iter().sum::<i64>() + amounts[7] + amounts[8];
+            assert_eq!(total, amounts[6], "{t:?}");
+            match t[5].as_str() {
+                "2" => assert_eq!(amounts[0], JFK_FLAT_FARE),
+                code => {
+                    // Whatever is left after the distance is paid for by the minute.
+                    let surcharge = if code == "3" { NEWARK_SURCHARGE } else { 0 };
+                    let slow = amounts[0] - surcharge - BASE_FARE - PER_MILE * cents(&t[4]) / 100;
+                    assert!(slow >= 0 && slow % PER_SLOW_MINUTE == 0, "{t:?}");
+                }
+            }
+            assert_eq!(amounts[8] > 0, pickup[2] == "JFK Airport" || pickup[2] == "LaGuardia Airport");
+            assert_eq!(amounts[7] > 0, pickup[1] == "Manhattan" || dropoff[1] == "Manhattan");
+            if t[9] != "1" {
+                assert_eq!(amounts[3], 0);
+            }
+            *rate_codes.entry(t[5].clone()).or_insert(0) += 1;
+        }
+        assert!(rate_codes["1"] > 4000 && rate_codes["2"] > 0, "{rate_codes:?}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn distances_are_about_right() {
+        // Times Square to JFK is about 12 miles as the crow flies.
+        let zone = |id| ZONES.iter().find(|z| z.id == id).unwrap();
+        let miles = haversine(zone(230).centre, zone(JFK).centre);
+        assert!((11.0..14.0).contains(&miles), "{miles}");
+        assert_eq!(service_zone(zone(JFK)), "Airports");
+        assert_eq!(service_zone(zone(230)), "Yellow Zone");
+    }
+}
@@ -0,0 +1,248 @@
+//! Raw line streaming over TCP, optionally inside TLS, for log collectors
+//! and ingestion daemons that read newline-delimited records from a socket.
+//! `tls://` needs the `tls` feature.
+
+use std::io::Write;
+use std::net::TcpStream;
+use std::path::Path;
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use rustls::pki_types::pem::PemObject;
+#[cfg(feature = "tls")]
+use rustls::pki_types::{CertificateDer, ServerName};
+#[cfg(feature = "tls")]
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use serde_json::json;
+
+use crate::BoxError;
+use crate::cli::TcpArgs;
+use crate::generator::Row;
+use crate::record::{RecordEncoder, RecordFormat};
+use crate::retry::{Failure, dead_letter, payload, retry};
+use crate::schema::Column;
+use crate::sink::Sink;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Lines buffered before they are written to the socket.
+const BUFFER_BYTES: usize = 64 * 1024;
+
+#[cfg(feature = "tls")]
+type Tls = Arc<ClientConfig>;
+
+/// Never made: `tls_config` fails in builds without TLS.
+#[cfg(not(feature = "tls"))]
+enum Tls {}
+
+/// Where a `tcp://` or `tls://` output connects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+}
+
+impl Endpoint {
+    /// Parses the `host:port` after the scheme; IPv6 hosts go in brackets.
+    pub fn parse(address: &str, tls: bool) -> Result<Self, BoxError> {
+        let malformed = || format!("{address} must look like host:port");
+        let (host, port) = address.rsplit_once(':').ok_or_else(malformed)?;
+        let host = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(host);
+        if host.is_empty() || address.contains('/') {
+            return Err(malformed().into());
+        }
+        Ok(Endpoint { host: host.to_string(), port: port.parse().map_err(|_| malformed())?, tls })
+    }
+
+    fn connect(&self, tls: Option<&Tls>) -> Result<Box<dyn Write + Send>, BoxError> {
+        let address = std::net::ToSocketAddrs::to_socket_addrs(&(self.host.as_str(), self.port))?
+            .next()
+            .ok_or_else(|| format!("{} has no address", self.host))?;
+        let stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        stream.set_nodelay(true)?;
+        match tls {
+            None => Ok(Box::new(stream)),
+            #[cfg(not(feature = "tls"))]
+            Some(never) => match *never {},
+            #[cfg(feature = "tls")]
+            Some(config) => {
+                let name = ServerName::try_from(self.host.clone())?;
+                let connection = ClientConnection::new(config.clone(), name)?;
+                Ok(Box::new(StreamOwned::new(connection, stream)))
+            }
+        }
+    }
+}
+
+/// Trusts the certificates in `ca_file`, or else the public web roots.
+#[cfg(feature = "tls")]
+fn tls_config(ca_file: Option<&Path>) -> Result<Tls, BoxError> {
+    let roots = match ca_file {
+        None => RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() },
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_file_iter(path).map_err(|e| format!("{}: {e}", path.display()))? {
+                roots.add(cert.map_err(|e| format!("{}: {e}", path.display()))?)?;
+            }
+            roots
+        }
+    };
+    let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(Arc::new(config))
+}
+
+#[cfg(not(feature = "tls"))]
+fn tls_config(_: Option<&Path>) -> Result<Tls, BoxError> {
+    Err(crate::without("tls", "TLS"))
+}
+
+/// Whether `e` is a rejected certificate, which won't be accepted on the
+/// next connection either.
+#[cfg(feature = "tls")]
+fn rejected(e: &std::io::Error) -> bool {
+    e.get_ref().is_some_and(|inner| inner.is::<rustls::Error>())
+}
+
+#[cfg(not(feature = "tls"))]
+fn rejected(_: &std::io::Error) -> bool {
+    false
+}
+
+/// Writes one line per row to a socket, reconnecting when the connection
+/// drops.
+///
+/// A buffer that fails to send is sent again in full on a new connection, so
+/// a few lines around a reconnect may arrive twice, and the line being
+/// written when the connection dropped may arrive cut short.
+pub struct TcpSink {
+    endpoint: Endpoint,
+    tls: Option<Tls>,
+    stream: Option<Box<dyn Write + Send>>,
+    encoder: RecordEncoder,
+    buffer: Vec<u8>,
+    bytes: u64,
+}
+
+impl TcpSink {
+    pub fn open(args: &TcpArgs, endpoint: &Endpoint, columns: &[Column]) -> Result<Self, BoxError> {
+        if args.tcp_format == RecordFormat::Avro {
+            return Err("--tcp-format avro is binary, so it can't be sent as lines".into());
+        }
+        let tls = if endpoint.tls { Some(tls_config(args.tcp_ca_file.as_deref())?) } else { None };
+        let mut sink = TcpSink {
+            endpoint: endpoint.clone(),
+            tls,
+            stream: None,
+            encoder: RecordEncoder::new(args.tcp_format, columns, None),
+            buffer: Vec::with_capacity(BUFFER_BYTES),
+            bytes: 0,
+        };
+        // Connect now so a wrong address fails before any rows are generated.
+        sink.stream = Some(sink.endpoint.connect(sink.tls.as_ref())?);
+        Ok(sink)
+    }
+
+    fn send(&mut self) -> Result<(), BoxError> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let address = format!("{}:{}", self.endpoint.host, self.endpoint.port);
+        let what = format!("writing to {address}");
+        let sent = retry(&what, || {
+            let stream = match &mut self.stream {
+                Some(stream) => stream,
+                None => self.stream.insert(self.endpoint.connect(self.tls.as_ref()).map_err(Failure::Transient)?),
+            };
+            if let Err(e) = stream.write_all(&self.buffer).and_then(|()| stream.flush()) {
+                self.stream = None;
+                if rejected(&e) {
+                    return Err(Failure::Fatal(e.into()));
+                }
+                return Err(Failure::Transient(e.into()));
+            }
+            Ok(())
+        });
+        if let Err(e) = sent {
+            let lines = self.buffer.split(|&b| b == b'\n').filter(|line| !line.is_empty());
+            dead_letter(&address, e, lines.map(|line| json!({"line": payload(line)})))?;
+        }
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl Sink for TcpSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        let line = self.encoder.encode(row)?;
+        self.buffer.extend_from_slice(&line);
+        self.buffer.push(b'\n');
+        self.bytes += line.len() as u64 + 1;
+        if self.buffer.len() >= BUFFER_BYTES {
+            self.send()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.send()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), BoxError> {
+        self.send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    use crate::cli::Args;
+    use crate::schema::ColumnKind;
+    use clap::Parser;
+
+    #[test]
+    fn parses_endpoints() {
+        assert_eq!(
+            Endpoint::parse("logs.internal:514", false).unwrap(),
+            Endpoint { host: "logs.internal".into(), port: 514, tls: false }
+        );
+        assert_eq!(Endpoint::parse("[::1]:2003", true).unwrap().host, "::1");
+        assert!(Endpoint::parse("logs.internal", false).is_err());
+        assert!(Endpoint::parse("host:514/path", false).is_err());
+        assert!(Endpoint::parse(":514", false).is_err());
+    }
+
+    #[test]
+    fn streams_lines_to_a_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = Endpoint::parse(&listener.local_addr().unwrap().to_string(), false).unwrap();
+        let reader = std::thread::spawn(move || {
+            let mut received = String::new();
+            listener.accept().unwrap().0.read_to_string(&mut received).unwrap();
+            received
+        });
+
+        let args = Args::parse_from(["csv_data_generator", "--tcp-format", "json"]);
+        let columns = [Column::new("name", ColumnKind::Choice(vec!["Mia".into()])), Column::new("age", ColumnKind::IntRange { min: 0, max: 1 })];
+        let mut sink = Box::new(TcpSink::open(&args.tcp, &endpoint, &columns).unwrap());
+        let mut row = Row::new();
+        row.push(b"Mia");
+        row.push(b"42");
+        sink.write_row(&row).unwrap();
+        sink.write_row(&row).unwrap();
+        sink.finish().unwrap();
+        assert_eq!(reader.join().unwrap(), "{\"name\":\"Mia\",\"age\":42}\n".repeat(2));
+    }
+}
@@ -0,0 +1,249 @@
+//! `--otlp-endpoint`: OpenTelemetry spans of a run's stages, exported to a
+//! collector over OTLP/HTTP as JSON. A run is one trace: a `run` span with
+//! `load config`, one `shard` span per shard and one `request` span per call
+//! to a service under it, each request including its retries. Exporting
+//! needs the `otlp` feature.
+
+use std::cell::Cell;
+use std::fmt::Display;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+
+use crate::BoxError;
+#[cfg(feature = "otlp")]
+use crate::http;
+
+/// How often finished spans are sent while a run goes on.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `SPAN_KIND_INTERNAL` and `SPAN_KIND_CLIENT`.
+const INTERNAL: u8 = 1;
+const CLIENT: u8 = 3;
+
+type SpanId = [u8; 8];
+
+struct Exporter {
+    url: String,
+    trace: [u8; 16],
+    root: SpanId,
+    started: u64,
+    /// Finished spans not sent yet.
+    spans: Mutex<Vec<Value>>,
+}
+
+static EXPORTER: OnceLock<Exporter> = OnceLock::new();
+
+thread_local! {
+    /// The span a new span on this thread is a child of, when not the root.
+    static CURRENT: Cell<Option<SpanId>> = const { Cell::new(None) };
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// Starts the run's trace, exporting to the collector at `endpoint`, like
+/// `http://localhost:4318`, every few seconds.
+pub(crate) fn start(endpoint: &str) -> Result<(), BoxError> {
+    if cfg!(not(feature = "otlp")) {
+        return Err(crate::without("otlp", "OTLP export"));
+    }
+    let exporter = Exporter {
+        url: format!("{}/v1/traces", endpoint.trim_end_matches('/')),
+        trace: rand::random(),
+        root: rand::random(),
+        started: now(),
+        spans: Mutex::new(Vec::new()),
+    };
+    EXPORTER.set(exporter).map_err(|_| "the trace was already started")?;
+    thread::spawn(|| {
+        loop {
+            thread::sleep(EXPORT_INTERVAL);
+            export();
+        }
+    });
+    Ok(())
+}
+
+/// Ends the run's span, failed if `result` is, and sends every span left.
+pub(crate) fn finish(result: &Result<(), BoxError>) {
+    let Some(exporter) = EXPORTER.get() else { return };
+    let error = result.as_ref().err().map(ToString::to_string);
+    let run = Open { name: "run", kind: INTERNAL, id: exporter.root, parent: None, start: exporter.started, attributes: Vec::new(), error };
+    exporter.spans.lock().unwrap().push(encode(exporter, &run));
+    export();
+}
+
+fn export() {
+    let Some(exporter) = EXPORTER.get() else { return };
+    let spans = std::mem::take(&mut *exporter.spans.lock().unwrap());
+    if spans.is_empty() {
+        return;
+    }
+    let body = json!({"resourceSpans": [{
+        "resource": {"attributes": [attribute("service.name", &Attribute::from("csv_data_generator"))]},
+        "scopeSpans": [{"scope": {"name": "csv_data_generator", "version": env!("CARGO_PKG_VERSION")}, "spans": spans}],
+    }]});
+    if let Err(e) = post(&exporter.url, &body) {
+        eprintln!("exporting spans to {} failed: {e}", exporter.url);
+    }
+}
+
+#[cfg(feature = "otlp")]
+fn post(url: &str, body: &Value) -> Result<(), BoxError> {
+    let response = http::agent().post(url).send_json(body)?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()).into());
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "otlp"))]
+fn post(_: &str, _: &Value) -> Result<(), BoxError> {
+    Err(crate::without("otlp", "OTLP export"))
+}
+
+/// The value of a span attribute.
+pub(crate) enum Attribute {
+    String(String),
+    Int(u64),
+}
+
+impl From<&str> for Attribute {
+    fn from(s: &str) -> Self {
+        Attribute::String(s.to_string())
+    }
+}
+
+impl From<u64> for Attribute {
+    fn from(n: u64) -> Self {
+        Attribute::Int(n)
+    }
+}
+
+fn attribute(key: &str, value: &Attribute) -> Value {
+    let value = match value {
+        Attribute::String(s) => json!({"stringValue": s}),
+        // OTLP JSON writes 64-bit integers as strings.
+        Attribute::Int(n) => json!({"intValue": n.to_string()}),
+    };
+    json!({"key": key, "value": value})
+}
+
+/// `span`, ending now, as OTLP JSON.
+fn encode(exporter: &Exporter, span: &Open) -> Value {
+    let mut json = json!({
+        "traceId": hex::encode(exporter.trace),
+        "spanId": hex::encode(span.id),
+        "name": span.name,
+        "kind": span.kind,
+        "startTimeUnixNano": span.start.to_string(),
+        "endTimeUnixNano": now().to_string(),
+        "attributes": span.attributes.iter().map(|(key, value)| attribute(key, value)).collect::<Vec<_>>(),
+    });
+    if let Some(parent) = span.parent {
+        json["parentSpanId"] = hex::encode(parent).into();
+    }
+    if let Some(message) = &span.error {
+        json["status"] = json!({"code": 2, "message": message});
+    }
+    json
+}
+
+/// A stage of the run, sent when dropped. While it's open, spans started on
+/// the same thread are its children.
+pub(crate) struct Span(Option<Open>);
+
+struct Open {
+    name: &'static str,
+    kind: u8,
+    id: SpanId,
+    /// `None` only for the run's own span.
+    parent: Option<SpanId>,
+    start: u64,
+    attributes: Vec<(&'static str, Attribute)>,
+    error: Option<String>,
+}
+
+/// Opens a span named `name`: a no-op without `--otlp-endpoint`.
+pub(crate) fn span(name: &'static str) -> Span {
+    let Some(exporter) = EXPORTER.get() else { return Span(None) };
+    let id = rand::random();
+    let parent = Some(CURRENT.replace(Some(id)).unwrap_or(exporter.root));
+    Span(Some(Open { name, kind: INTERNAL, id, parent, start: now(), attributes: Vec::new(), error: None }))
+}
+
+impl Span {
+    /// Marks the span as a call to another service.
+    pub(crate) fn client(mut self) -> Self {
+        if let Some(open) = &mut self.0 {
+            open.kind = CLIENT;
+        }
+        self
+    }
+
+    pub(crate) fn set(&mut self, key: &'static str, value: impl Into<Attribute>) {
+        if let Some(open) = &mut self.0 {
+            open.attributes.push((key, value.into()));
+        }
+    }
+
+    /// Records that the stage failed with `error`.
+    pub(crate) fn fail(&mut self, error: &dyn Display) {
+        if let Some(open) = &mut self.0 {
+            open.error = Some(error.to_string());
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let (Some(open), Some(exporter)) = (self.0.take(), EXPORTER.get()) else { return };
+        CURRENT.set(open.parent.filter(|&parent| parent != exporter.root));
+        exporter.spans.lock().unwrap().push(encode(exporter, &open));
+    }
+}
+
+/// The span open on this thread, for [`enter`]ing on another thread.
+#[cfg(feature = "cloud")]
+pub(crate) fn current() -> Option<SpanId> {
+    CURRENT.get()
+}
+
+/// Makes new spans on this thread children of `parent`, from [`current`] on
+/// the thread that started this one.
+#[cfg(feature = "cloud")]
+pub(crate) fn enter(parent: Option<SpanId>) {
+    CURRENT.set(parent);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_spans_as_otlp_json() {
+        let exporter = Exporter { url: String::new(), trace: [0xab; 16], root: [1; 8], started: 0, spans: Mutex::new(Vec::new()) };
+        let span = Open {
+            name: "shard",
+            kind: CLIENT,
+            id: [2; 8],
+            parent: Some([1; 8]),
+            start: 5,
+            attributes: vec![("location", Attribute::from("a.csv")), ("rows", Attribute::from(7))],
+            error: Some("disk full".to_string()),
+        };
+        let json = encode(&exporter, &span);
+        assert_eq!(json["traceId"], "abababababababababababababababab");
+        assert_eq!(json["spanId"], "0202020202020202");
+        assert_eq!(json["parentSpanId"], "0101010101010101");
+        assert_eq!(json["kind"], 3);
+        assert_eq!(json["startTimeUnixNano"], "5");
+        assert_eq!(json["attributes"][0], json!({"key": "location", "value": {"stringValue": "a.csv"}}));
+        assert_eq!(json["attributes"][1], json!({"key": "rows", "value": {"intValue": "7"}}));
+        assert_eq!(json["status"], json!({"code": 2, "message": "disk full"}));
+    }
+}
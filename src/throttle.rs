@@ -0,0 +1,67 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Paces work to a fixed rate of units (rows, bytes) per second, shared by
+/// every thread holding a reference.
+pub struct RateLimiter {
+    units_per_second: f64,
+    burst: u64,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(units_per_second: f64) -> Self {
+        RateLimiter {
+            units_per_second,
+            burst: (units_per_second / 100.0).ceil().max(1.0) as u64,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// A suggested number of units to [`acquire`](Self::acquire) at once:
+    /// about 10ms worth, so the lock isn't taken per unit at high rates.
+    pub fn burst(&self) -> u64 {
+        self.burst
+    }
+
+    /// Blocks until `units` more units fit within the rate.
+    pub fn acquire(&self, units: u64) {
+        let start = {
+            let mut next = self.next.lock().unwrap();
+            // Idle time doesn't accumulate into a burst.
+            let start = (*next).max(Instant::now());
+            // Divided per call, since a byte at a few GB/s is under a nanosecond.
+            *next = start + Duration::from_secs_f64(units as f64 / self.units_per_second);
+            start
+        };
+        let now = Instant::now();
+        if start > now {
+            thread::sleep(start - now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_are_about_ten_milliseconds() {
+        assert_eq!(RateLimiter::new(100_000.0).burst(), 1000);
+        assert_eq!(RateLimiter::new(250.0).burst(), 3);
+        assert_eq!(RateLimiter::new(0.5).burst(), 1);
+    }
+
+    #[test]
+    fn acquiring_waits_for_the_rate() {
+        let limiter = RateLimiter::new(1000.0);
+        let start = Instant::now();
+        // The first 50 units go out at once, the next 50 after 50ms.
+        limiter.acquire(50);
+        limiter.acquire(50);
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(45), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(1), "{elapsed:?}");
+    }
+}
@@ -0,0 +1,137 @@
+//! Values that catch out naive parsers: `--torture-rate` of the text values
+//! written have a comma, a double quote and a CRLF line break embedded in
+//! them. They're still valid, since each sink quotes or escapes them the way
+//! its format says, but splitting lines or fields by hand gets them wrong.
+
+use rand::Rng;
+
+use crate::BoxError;
+use crate::generator::Row;
+use crate::manifest::Injector;
+use crate::schema::{Column, DataType};
+use crate::sink::Sink;
+
+/// What goes into each tortured value.
+const PIECES: [&[u8]; 3] = [b",", b"\"", b"\r\n"];
+
+/// Embeds [`PIECES`] in some of the text values of the rows passed on.
+pub struct TortureSink {
+    inner: Box<dyn Sink>,
+    /// Which columns hold text; the rest keep the values their types need.
+    text: Vec<bool>,
+    rate: f64,
+    injector: Injector,
+    /// Which values of the row being written are tortured.
+    picked: Vec<bool>,
+    row: Row,
+}
+
+impl TortureSink {
+    pub fn new(inner: Box<dyn Sink>, columns: &[Column], rate: f64, injector: Injector) -> Self {
+        let text = columns.iter().map(|c| c.kind.data_type() == DataType::String).collect();
+        TortureSink { inner, text, rate, injector, picked: Vec::new(), row: Row::new() }
+    }
+}
+
+impl Sink for TortureSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.row.clear();
+        self.injector.start_row();
+        let rng = &mut self.injector.rng;
+        self.picked.clear();
+        self.picked.extend(self.text.iter().map(|&text| text && rng.gen_bool(self.rate)));
+        for (i, (field, &picked)) in row.fields().zip(&self.picked).enumerate() {
+            if picked {
+                let tortured = torture(&mut self.injector.rng, field);
+                self.injector.record(Some(i), "torture", field)?;
+                self.row.push(&tortured);
+            } else {
+                self.row.push(field);
+            }
+        }
+        self.inner.write_row(&self.row)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.inner.flush()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.inner.finish()
+    }
+}
+
+/// `value` with each of [`PIECES`] put in at a random place between its
+/// characters.
+fn torture<R: Rng + ?Sized>(rng: &mut R, value: &[u8]) -> Vec<u8> {
+    let boundaries = char_boundaries(value);
+    let mut places: Vec<(usize, &[u8])> =
+        PIECES.iter().map(|&piece| (boundaries[rng.gen_range(0..boundaries.len())], piece)).collect();
+    places.sort_by_key(|&(at, _)| at);
+    let mut tortured = Vec::with_capacity(value.len() + 4);
+    let mut from = 0;
+    for (at, piece) in places {
+        tortured.extend_from_slice(&value[from..at]);
+        tortured.extend_from_slice(piece);
+        from = at;
+    }
+    tortured.extend_from_slice(&value[from..]);
+    tortured
+}
+
+/// The places in `bytes` between characters, not inside a UTF-8 sequence,
+/// including both ends.
+pub fn char_boundaries(bytes: &[u8]) -> Vec<usize> {
+    (0..=bytes.len()).filter(|&at| bytes.get(at).is_none_or(|b| b & 0xc0 != 0x80)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use crate::output::{Destination, Outputs};
+    use crate::schema::ColumnKind;
+    use crate::sink;
+    use clap::Parser;
+    use std::fs;
+
+    #[test]
+    fn tortured_values_survive_a_csv_parser() {
+        let path = std::env::temp_dir().join(format!("csv_data_generator_torture_{}.csv", std::process::id()));
+        let args = Args::parse_from(["gen", "--output", path.to_str().unwrap(), "--torture-rate", "0.3"]);
+        let columns = [
+            Column::new("id", ColumnKind::Sequence { start: 1 }),
+            Column::new("name", ColumnKind::Choice(Vec::new())),
+            Column::new("note", ColumnKind::Choice(Vec::new())),
+        ];
+        let mut sink = sink::open(&Outputs::new(&args), &Destination::File(path.clone()), &columns).unwrap();
+        let mut row = Row::new();
+        for i in 0..1000 {
+            row.clear();
+            for field in [i.to_string(), format!("Zoë {i}"), String::new()] {
+                row.push(field.as_bytes());
+            }
+            sink.write_row(&row).unwrap();
+        }
+        sink.finish().unwrap();
+
+        let mut tortured = 0;
+        for (i, record) in (0..).zip(csv::Reader::from_path(&path).unwrap().records()) {
+            let record = record.unwrap();
+            assert_eq!(&record[0], i.to_string());
+            for (value, original) in [(&record[1], format!("Zoë {i}")), (&record[2], String::new())] {
+                if value != original {
+                    tortured += 1;
+                    assert!(value.contains(',') && value.contains('"') && value.contains("\r\n"), "{value:?}");
+                    assert_eq!(value.replace([',', '"', '\r', '\n'], ""), original);
+                }
+            }
+        }
+        assert!((480..720).contains(&tortured), "{tortured}");
+        fs::remove_file(path).unwrap();
+    }
+}
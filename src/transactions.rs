@@ -0,0 +1,324 @@
+//! The transactions preset: card `accounts` and their `transactions` during
+//! 2024, with a labeled fraction of fraud for testing fraud models.
+//!
+//! An account spends in its home country and currency, mostly by day, with
+//! amounts log-normally distributed around a median for each merchant
+//! category. About `--fraud-rate` of transactions are fraud of one of three
+//! kinds: a spike far above the category's usual amounts at a merchant that
+//! resells well, a card used abroad at night without being present, or card
+//! testing, a burst of tiny online charges minutes apart. `is_fraud` and
+//! `fraud_type` say which rows are which.
+
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+
+use crate::BoxError;
+use crate::cli::Args;
+use crate::generator::{Row, push_decimal, push_timestamp};
+use crate::presets::{self, push_int};
+use crate::schema::{Column, ColumnKind};
+use crate::signal;
+
+/// Accounts open before and transactions happen during 2024, from
+/// 2024-01-01 UTC...
+const WINDOW_START: i64 = 1_704_067_200;
+/// ...to 2025-01-01 UTC.
+const WINDOW_END: i64 = 1_735_689_600;
+
+/// Accounts at scale factor 1.
+const ACCOUNTS: f64 = 10_000.0;
+/// Transactions per account during the year, at most.
+const MAX_TRANSACTIONS: u32 = 100;
+
+const TABLES: [&str; 2] = ["accounts", "transactions"];
+
+/// A country and its currency, with how many of it make a US dollar.
+struct Country {
+    code: &'static str,
+    currency: &'static str,
+    per_dollar: f64,
+    weight: u32,
+}
+
+const COUNTRIES: &[Country] = &[
+    Country { code: "US", currency: "USD", per_dollar: 1.0, weight: 40 },
+    Country { code: "GB", currency: "GBP", per_dollar: 0.79, weight: 12 },
+    Country { code: "DE", currency: "EUR", per_dollar: 0.92, weight: 10 },
+    Country { code: "FR", currency: "EUR", per_dollar: 0.92, weight: 8 },
+    Country { code: "CA", currency: "CAD", per_dollar: 1.36, weight: 8 },
+    Country { code: "JP", currency: "JPY", per_dollar: 150.0, weight: 8 },
+    Country { code: "BR", currency: "BRL", per_dollar: 5.0, weight: 7 },
+    Country { code: "IN", currency: "INR", per_dollar: 83.0, weight: 7 },
+];
+
+/// A merchant category: its ISO 18245 code, how often accounts spend in
+/// it, and the median and log-scale spread of its amounts in dollars.
+struct Category {
+    code: u16,
+    name: &'static str,
+    weight: u32,
+    median: f64,
+    sigma: f64,
+    card_present: f64,
+}
+
+const CATEGORIES: &[Category] = &[
+    Category { code: 5411, name: "grocery", weight: 30, median: 45.0, sigma: 0.6, card_present: 0.95 },
+    Category { code: 5812, name: "restaurants", weight: 20, median: 30.0, sigma: 0.5, card_present: 0.9 },
+    Category { code: 5541, name: "fuel", weight: 10, median: 40.0, sigma: 0.3, card_present: 1.0 },
+    Category { code: 5912, name: "pharmacy", weight: 6, median: 20.0, sigma: 0.7, card_present: 0.9 },
+    Category { code: 5999, name: "online_retail", weight: 15, median: 35.0, sigma: 0.9, card_present: 0.0 },
+    Category { code: 4900, name: "utilities", weight: 5, median: 90.0, sigma: 0.4, card_present: 0.0 },
+    Category { code: 7832, name: "entertainment", weight: 6, median: 25.0, sigma: 0.6, card_present: 0.5 },
+    Category { code: 5732, name: "electronics", weight: 4, median: 200.0, sigma: 0.9, card_present: 0.6 },
+    Category { code: 5944, name: "jewelry", weight: 1, median: 300.0, sigma: 1.0, card_present: 0.8 },
+    Category { code: 4511, name: "airlines", weight: 3, median: 400.0, sigma: 0.7, card_present: 0.0 },
+];
+
+/// The categories fraudsters spend in, since their goods resell well.
+const SPIKE_CATEGORIES: &[&str] = &["electronics", "jewelry", "online_retail"];
+
+/// How many times a category's amount a spike is.
+const SPIKE: (f64, f64) = (8.0, 30.0);
+/// Charges in a card test, and the dollars and seconds between each.
+const CARD_TEST_CHARGES: (usize, usize) = (3, 8);
+const CARD_TEST_AMOUNT: (f64, f64) = (0.5, 2.0);
+const CARD_TEST_GAP: (i64, i64) = (20, 180);
+
+/// The chance of a legitimate transaction in each hour of the day, in the
+/// account's time, which is taken to be UTC.
+const HOURS: [u32; 24] = [1, 1, 1, 1, 1, 2, 4, 6, 8, 9, 10, 11, 12, 11, 10, 10, 10, 11, 12, 11, 9, 6, 4, 2];
+
+/// The columns of each table, in [`TABLES`] order. Only the names and types
+/// of these matter; the values are written below.
+fn columns() -> [Vec<Column>; 2] {
+    let integer = |name| Column::new(name, ColumnKind::Sequence { start: 1 });
+    let string = |name| Column::new(name, ColumnKind::Choice(Vec::new()));
+    let timestamp = |name| Column::new(name, ColumnKind::TimestampRange { min: 0, max: 0 });
+    [
+        vec![integer("account_id"), timestamp("opened_at"), string("country"), string("currency")],
+        vec![
+            integer("transaction_id"),
+            integer("account_id"),
+            timestamp("occurred_at"),
+            integer("merchant_category_code"),
+            string("merchant_category"),
+            Column::new("amount", ColumnKind::Decimal { min: 0, max: 0, scale: 2 }),
+            string("currency"),
+            string("country"),
+            integer("card_present"),
+            integer("is_fraud"),
+            string("fraud_type"),
+        ],
+    ]
+}
+
+/// A standard normal number, by the Box-Muller transform.
+pub fn normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    let (u, v): (f64, f64) = (1.0 - rng.r#gen::<f64>(), rng.r#gen());
+    (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+}
+
+/// A dollar amount in `category`.
+fn amount<R: Rng + ?Sized>(rng: &mut R, category: &Category) -> f64 {
+    (category.median * (category.sigma * normal(rng)).exp()).max(0.5)
+}
+
+struct Transaction {
+    at: i64,
+    category: &'static Category,
+    dollars: f64,
+    country: &'static Country,
+    card_present: bool,
+    fraud: &'static str,
+}
+
+fn category(name: &str) -> &'static Category {
+    CATEGORIES.iter().find(|c| c.name == name).expect("a known category")
+}
+
+/// Seconds into a day at a random time in the given hours.
+fn time_of_day<R: Rng + ?Sized>(rng: &mut R, hours: &WeightedIndex<u32>) -> i64 {
+    hours.sample(rng) as i64 * 3600 + rng.gen_range(0..3600)
+}
+
+/// Writes each account followed by its spending in time order, fraud
+/// included.
+pub fn generate(args: &Args) -> Result<(), BoxError> {
+    let [mut accounts_sink, mut transactions_sink] = presets::open_tables(args, "transactions", TABLES, columns())?;
+
+    let mut rng = rand::thread_rng();
+    let fraud_rate = args.preset.fraud_rate;
+    let countries = WeightedIndex::new(COUNTRIES.iter().map(|c| c.weight))?;
+    let categories = WeightedIndex::new(CATEGORIES.iter().map(|c| c.weight))?;
+    let hours = WeightedIndex::new(HOURS)?;
+    let night = WeightedIndex::new(HOURS.map(|weight| 13 - weight))?;
+    let mut accounts = ((ACCOUNTS * args.preset.scale_factor).round() as u64).max(1);
+    let (mut transactions, mut frauds) = (0u64, 0u64);
+    let mut row = Row::new();
+    let mut spending = Vec::new();
+    for account in 1..=accounts {
+        if signal::interrupted() {
+            accounts = account - 1;
+            break;
+        }
+        let home = &COUNTRIES[countries.sample(&mut rng)];
+        row.clear();
+        push_int(&mut row, account);
+        push_timestamp(&mut row, WINDOW_START - rng.gen_range(86_400..10 * 365 * 86_400));
+        row.push(home.code.as_bytes());
+        row.push(home.currency.as_bytes());
+        accounts_sink.write_row(&row)?;
+
+        spending.clear();
+        for _ in 0..rng.gen_range(1..=MAX_TRANSACTIONS) {
+            let day = rng.gen_range(0..(WINDOW_END - WINDOW_START) / 86_400);
+            let at = WINDOW_START + day * 86_400;
+            if !rng.gen_bool(fraud_rate) {
+                let category = &CATEGORIES[categories.sample(&mut rng)];
+                spending.push(Transaction {
+                    at: at + time_of_day(&mut rng, &hours),
+                    category,
+                    dollars: amount(&mut rng, category),
+                    country: home,
+                    card_present: rng.gen_bool(category.card_present),
+                    fraud: "",
+                });
+                continue;
+            }
+            match rng.gen_range(0..3) {
+                0 => {
+                    let category = category(SPIKE_CATEGORIES[rng.gen_range(0..SPIKE_CATEGORIES.len())]);
+                    spending.push(Transaction {
+                        at: at + time_of_day(&mut rng, &hours),
+                        category,
+                        dollars: amount(&mut rng, category) * rng.gen_range(SPIKE.0..=SPIKE.1),
+                        country: home,
+                        card_present: false,
+                        fraud: "amount_spike",
+                    });
+                }
+                1 => {
+                    let abroad = loop {
+                        let country = &COUNTRIES[countries.sample(&mut rng)];
+                        if country.code != home.code {
+                            break country;
+                        }
+                    };
+                    let category = &CATEGORIES[categories.sample(&mut rng)];
+                    spending.push(Transaction {
+                        at: at + time_of_day(&mut rng, &night),
+                        category,
+                        dollars: amount(&mut rng, category) * rng.gen_range(1.0..=4.0),
+                        country: abroad,
+                        card_present: false,
+                        fraud: "foreign",
+                    });
+                }
+                _ => {
+                    let mut at = at + time_of_day(&mut rng, &night);
+                    for _ in 0..rng.gen_range(CARD_TEST_CHARGES.0..=CARD_TEST_CHARGES.1) {
+                        spending.push(Transaction {
+                            at,
+                            category: category("online_retail"),
+                            dollars: rng.gen_range(CARD_TEST_AMOUNT.0..=CARD_TEST_AMOUNT.1),
+                            country: home,
+                            card_present: false,
+                            fraud: "card_testing",
+                        });
+                        at += rng.gen_range(CARD_TEST_GAP.0..=CARD_TEST_GAP.1);
+                    }
+                }
+            }
+        }
+        spending.sort_by_key(|t| t.at);
+        for t in &spending {
+            transactions += 1;
+            frauds += u64::from(!t.fraud.is_empty());
+            row.clear();
+            push_int(&mut row, transactions);
+            push_int(&mut row, account);
+            push_timestamp(&mut row, t.at.min(WINDOW_END - 1));
+            push_int(&mut row, t.category.code.into());
+            row.push(t.category.name.as_bytes());
+            push_decimal(&mut row, (t.dollars * t.country.per_dollar * 100.0).round() as i64, 2);
+            row.push(t.country.currency.as_bytes());
+            row.push(t.country.code.as_bytes());
+            push_int(&mut row, t.card_present.into());
+            push_int(&mut row, (!t.fraud.is_empty()).into());
+            row.push(t.fraud.as_bytes());
+            transactions_sink.write_row(&row)?;
+        }
+    }
+    presets::finish_tables(args, TABLES, [accounts_sink, transactions_sink], [accounts, transactions])?;
+    println!("  {frauds} of {transactions} transactions are fraud");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::testing::{read, temp_dir};
+    use crate::schema::parse_timestamp;
+    use clap::Parser;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn fraud_stands_out() {
+        let dir = temp_dir("transactions");
+        let template = dir.join("{table}.csv");
+        let args = Args::parse_from([
+            "gen",
+            "--preset",
+            "transactions",
+            "--scale-factor",
+            "0.05",
+            "--fraud-rate",
+            "0.05",
+            "--output",
+            template.to_str().unwrap(),
+        ]);
+        generate(&args).unwrap();
+
+        let accounts: HashMap<String, Vec<String>> =
+            read(dir.join("accounts.csv")).into_iter().map(|a| (a[0].clone(), a)).collect();
+        assert_eq!(accounts.len(), 500);
+        let transactions = read(dir.join("transactions.csv"));
+        let time = |s: &str| parse_timestamp(s).unwrap();
+        let mut frauds = HashMap::new();
+        for pair in transactions.windows(2) {
+            assert!(pair[0][1] != pair[1][1] || time(&pair[0][2]) <= time(&pair[1][2]));
+        }
+        for t in &transactions {
+            let account = &accounts[&t[1]];
+            assert_eq!(t[9] == "1", !t[10].is_empty());
+            *frauds.entry(t[10].as_str()).or_insert(0) += 1;
+            match t[10].as_str() {
+                "" => assert_eq!((&t[7], &t[6]), (&account[2], &account[3])),
+                "foreign" => assert_ne!(t[7], account[2]),
+                "card_testing" => assert_eq!((t[4].as_str(), t[8].as_str()), ("online_retail", "0")),
+                "amount_spike" => assert!(SPIKE_CATEGORIES.contains(&t[4].as_str())),
+                other => panic!("{other}"),
+            }
+            let category = CATEGORIES.iter().find(|c| c.name == t[4]).unwrap();
+            assert_eq!(t[3], category.code.to_string());
+        }
+        let fraud = transactions.iter().filter(|t| t[9] == "1").count() as f64 / transactions.len() as f64;
+        // Card tests take several rows each.
+        assert!((0.05..0.15).contains(&fraud), "{fraud}");
+        assert_eq!(frauds.len(), 4, "{frauds:?}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn amounts_follow_their_category() {
+        let mut rng = rand::thread_rng();
+        for category in CATEGORIES {
+            let mut amounts: Vec<f64> = (0..2001).map(|_| amount(&mut rng, category)).collect();
+            amounts.sort_by(f64::total_cmp);
+            let median = amounts[1000];
+            assert!((median / category.median - 1.0).abs() < 0.15, "{} {median}", category.name);
+        }
+    }
+}
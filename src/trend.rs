@@ -0,0 +1,115 @@
+//! `--trend`: how a table's volume changes from period to period, for the
+//! days of `--backfill` and the rotated files of a `--rate`d stream, so
+//! capacity planning and partition size analyses see growth, step changes
+//! and seasonal spikes rather than one size throughout. The changes given
+//! multiply: a day's volume is the base volume times each one's factor.
+
+use serde::Serialize;
+
+use crate::schema::{civil_from_days, parse_date};
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+
+/// One change to a period's volume.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Change {
+    /// `rate` more of the first day's volume every year, in a straight line.
+    Linear { rate: f64 },
+    /// `factor` times as much from day `from` on, in days since 1970-01-01.
+    Step { from: i64, factor: f64 },
+    /// `factor` times as much on one day of every year, like a sales event.
+    Spike { month: u32, day: u32, factor: f64 },
+    /// `factor` times as much on one day of every week, Monday being 0.
+    Weekday { weekday: u32, factor: f64 },
+    /// `factor` times as much all through one month of every year, from 1.
+    Month { month: u32, factor: f64 },
+}
+
+/// Parses `linear:RATE`, `step:YYYY-MM-DD=FACTOR`, `spike:MM-DD=FACTOR`,
+/// `weekday:sat=FACTOR` or `month:dec=FACTOR`.
+pub fn parse(s: &str) -> Result<Change, String> {
+    let (kind, spec) = s.split_once(':').ok_or_else(|| format!("{s} is not KIND:SPEC"))?;
+    if kind == "linear" {
+        return match spec.parse::<f64>() {
+            Ok(rate) if rate > -1.0 && rate.is_finite() => Ok(Change::Linear { rate }),
+            _ => Err(format!("{s}: {spec} is not a growth rate greater than -1")),
+        };
+    }
+    let (when, factor) = spec.split_once('=').ok_or_else(|| format!("{s}: expected {kind}:WHEN=FACTOR"))?;
+    let factor = match factor.parse::<f64>() {
+        Ok(factor) if factor > 0.0 && factor.is_finite() => factor,
+        _ => return Err(format!("{s}: {factor} is not a positive factor")),
+    };
+    let position = |names: &[&str]| names.iter().position(|name| when.eq_ignore_ascii_case(name)).map(|i| i as u32);
+    match kind {
+        "step" => {
+            let from = parse_date(when).ok_or_else(|| format!("{s}: {when} isn't a YYYY-MM-DD date"))?;
+            Ok(Change::Step { from, factor })
+        }
+        "spike" => {
+            // Any leap year checks that Feb 29 is a day.
+            let (month, day) = parse_date(&format!("2000-{when}"))
+                .map(civil_from_days)
+                .map(|(_, month, day)| (month, day))
+                .ok_or_else(|| format!("{s}: {when} isn't an MM-DD day"))?;
+            Ok(Change::Spike { month, day, factor })
+        }
+        "weekday" => {
+            let weekday = position(&WEEKDAYS).ok_or_else(|| format!("{s}: {when} isn't one of {}", WEEKDAYS.join(", ")))?;
+            Ok(Change::Weekday { weekday, factor })
+        }
+        "month" => {
+            let month = position(&MONTHS).ok_or_else(|| format!("{s}: {when} isn't one of {}", MONTHS.join(", ")))? + 1;
+            Ok(Change::Month { month, factor })
+        }
+        _ => Err(format!("{s}: expected linear, step, spike, weekday or month")),
+    }
+}
+
+/// How many times the base volume day `day` gets, of a series starting on
+/// day `start`, both in days since 1970-01-01.
+pub fn factor(changes: &[Change], start: i64, day: i64) -> f64 {
+    let (_, month, day_of_month) = civil_from_days(day);
+    // 1970-01-01 was a Thursday.
+    let weekday = (day + 3).rem_euclid(7) as u32;
+    changes
+        .iter()
+        .map(|change| match *change {
+            Change::Linear { rate } => (1.0 + rate * (day - start) as f64 / 365.25).max(f64::EPSILON),
+            Change::Step { from, factor } if day >= from => factor,
+            Change::Spike { month: m, day: d, factor } if (m, d) == (month, day_of_month) => factor,
+            Change::Weekday { weekday: w, factor } if w == weekday => factor,
+            Change::Month { month: m, factor } if m == month => factor,
+            _ => 1.0,
+        })
+        .product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_multiply_on_the_days_they_apply_to() {
+        let changes: Vec<_> =
+            ["linear:1", "step:2024-06-01=2", "spike:11-29=5", "weekday:sat=0.5", "month:dec=1.5"].map(|s| parse(s).unwrap()).into();
+        let day = |date: &str| parse_date(date).unwrap();
+        let start = day("2023-06-01");
+        assert_eq!(factor(&changes, start, start), 1.0);
+        // A Wednesday a year and a half on, after the step.
+        assert!((factor(&changes, start, day("2024-11-27")) - 2.0 * (1.0 + 545.0 / 365.25)).abs() < 1e-9);
+        // A Friday on the spike, against a Thursday the year before.
+        let growth = |date| 1.0 + (day(date) - start) as f64 / 365.25;
+        assert!((factor(&changes, start, day("2024-11-29")) - 10.0 * growth("2024-11-29")).abs() < 1e-9);
+        assert!((factor(&changes, start, day("2023-11-30")) - growth("2023-11-30")).abs() < 1e-9);
+        // A Saturday in December.
+        assert!((factor(&changes, start, day("2024-12-07")) - 2.0 * 0.5 * 1.5 * growth("2024-12-07")).abs() < 1e-9);
+        assert!(parse("spike:02-30=2").is_err());
+        assert!(parse("weekday:someday=2").is_err());
+        assert!(parse("step:2024-01-01=0").is_err());
+        assert!(parse("linear:-1").is_err());
+    }
+}
@@ -0,0 +1,186 @@
+//! Text that is hard to handle: emoji, CJK and right-to-left scripts,
+//! combining marks, invisible characters and very long grapheme clusters,
+//! mixed into plain ASCII for [`ColumnKind::Unicode`](crate::schema::ColumnKind::Unicode) columns.
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::schema::UnicodeMix;
+
+/// The kinds of grapheme a [`UnicodeMix`] mixes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Ascii,
+    /// Pictographs, skin tones, flags and variation selectors.
+    Emoji,
+    /// Han, kana, Hangul, full-width forms and Han outside the BMP.
+    Cjk,
+    /// Hebrew and Arabic letters and Arabic-Indic digits.
+    Rtl,
+    /// A letter with one to three combining marks.
+    Combining,
+    /// Zero-width spaces and joiners, the byte order mark and bidi controls.
+    ZeroWidth,
+    /// A letter under dozens of combining marks, or a joined emoji sequence.
+    LongCluster,
+    /// Delimiters, quotes, line breaks and backslashes.
+    Special,
+}
+
+const ASCII: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 ";
+
+const EMOJI: &[&str] = &[
+    "😀", "😂", "🥲", "😍", "🤔", "🙃", "🎉", "🚀", "🔥", "✨", "🦀", "🍕", "🌍", "☕", "⭐", "✅", "❤️", "☺️", "👍",
+    "👍🏽", "👋🏿", "🙏🏻", "🇯🇵", "🇧🇷", "🇩🇪", "🇺🇸", "#️⃣", "1️⃣",
+];
+
+const CJK: &[(u32, u32)] = &[
+    (0x4E00, 0x9FFF),
+    (0x3041, 0x3096),
+    (0x30A1, 0x30FA),
+    (0xAC00, 0xD7A3),
+    (0xFF01, 0xFF5E),
+    (0x20000, 0x2A6DF),
+];
+
+const RTL: &[(u32, u32)] = &[(0x05D0, 0x05EA), (0x0627, 0x064A), (0x0660, 0x0669)];
+
+const COMBINING: (u32, u32) = (0x0300, 0x036F);
+
+const ZERO_WIDTH: &[&str] = &[
+    "\u{200B}", "\u{200C}", "\u{200D}", "\u{2060}", "\u{FEFF}", "\u{200E}", "\u{200F}", "\u{202A}", "\u{202B}",
+    "\u{202C}", "\u{202E}", "\u{2066}", "\u{2067}", "\u{2069}",
+];
+
+const JOINED: &[&str] = &[
+    "👨‍👩‍👧‍👦", "👩‍👩‍👦‍👦", "🏳️‍🌈", "🏴‍☠️", "👩🏽‍💻", "🧑🏿‍🚀", "🧑‍🤝‍🧑", "👁️‍🗨️", "🤦🏼‍♀️", "👨🏻‍❤️‍💋‍👨🏾",
+];
+
+const SPECIAL: &[&str] = &[",", "\"", "\"\"", "'", "\n", "\r\n", "\r", "\t", "\\", ";", "|"];
+
+/// The classes of `mix` with their cumulative rates, for [`Class::pick`].
+pub fn thresholds(mix: &UnicodeMix) -> Vec<(f64, Class)> {
+    let rates = [
+        (mix.emoji, Class::Emoji),
+        (mix.cjk, Class::Cjk),
+        (mix.rtl, Class::Rtl),
+        (mix.combining, Class::Combining),
+        (mix.zero_width, Class::ZeroWidth),
+        (mix.long_clusters, Class::LongCluster),
+        (mix.specials, Class::Special),
+    ];
+    let mut total = 0.0;
+    rates
+        .into_iter()
+        .filter(|&(rate, _)| rate > 0.0)
+        .map(|(rate, class)| {
+            total += rate;
+            (total, class)
+        })
+        .collect()
+}
+
+impl Class {
+    /// The class a uniform draw falls into, ASCII past the last threshold.
+    pub fn pick<R: Rng + ?Sized>(rng: &mut R, thresholds: &[(f64, Class)]) -> Class {
+        let u: f64 = rng.r#gen();
+        thresholds.iter().find(|&&(threshold, _)| u < threshold).map_or(Class::Ascii, |&(_, class)| class)
+    }
+
+    /// Appends one grapheme of the class, as UTF-8.
+    pub fn push<R: Rng + ?Sized>(self, rng: &mut R, field: &mut Vec<u8>) {
+        match self {
+            Class::Ascii => field.push(*ASCII.choose(rng).expect("not empty")),
+            Class::Emoji => field.extend_from_slice(EMOJI.choose(rng).expect("not empty").as_bytes()),
+            Class::Cjk => push_char(field, from_ranges(rng, CJK)),
+            Class::Rtl => push_char(field, from_ranges(rng, RTL)),
+            Class::Combining => push_marked(rng, field, 1..=3),
+            Class::ZeroWidth => field.extend_from_slice(ZERO_WIDTH.choose(rng).expect("not empty").as_bytes()),
+            Class::LongCluster if rng.gen_bool(0.5) => push_marked(rng, field, 8..=64),
+            Class::LongCluster => field.extend_from_slice(JOINED.choose(rng).expect("not empty").as_bytes()),
+            Class::Special => field.extend_from_slice(SPECIAL.choose(rng).expect("not empty").as_bytes()),
+        }
+    }
+}
+
+/// Appends `min..=max` graphemes drawn from `thresholds`.
+pub fn push_text<R: Rng + ?Sized>(rng: &mut R, field: &mut Vec<u8>, min: usize, max: usize, thresholds: &[(f64, Class)]) {
+    for _ in 0..rng.gen_range(min..=max) {
+        Class::pick(rng, thresholds).push(rng, field);
+    }
+}
+
+/// A letter followed by `marks` combining marks.
+fn push_marked<R: Rng + ?Sized>(rng: &mut R, field: &mut Vec<u8>, marks: std::ops::RangeInclusive<usize>) {
+    field.push(rng.gen_range(b'a'..=b'z'));
+    for _ in 0..rng.gen_range(marks) {
+        push_char(field, rng.gen_range(COMBINING.0..=COMBINING.1));
+    }
+}
+
+/// A code point from a range chosen uniformly among `ranges`.
+fn from_ranges<R: Rng + ?Sized>(rng: &mut R, ranges: &[(u32, u32)]) -> u32 {
+    let &(low, high) = ranges.choose(rng).expect("not empty");
+    rng.gen_range(low..=high)
+}
+
+fn push_char(field: &mut Vec<u8>, code: u32) {
+    let c = char::from_u32(code).expect("ranges hold no surrogates");
+    field.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{Generator, Row};
+    use crate::schema::{Column, ColumnKind};
+
+    #[test]
+    fn classes_follow_their_rates() {
+        let mix = UnicodeMix { emoji: 0.5, cjk: 0.0, rtl: 0.0, combining: 0.0, zero_width: 0.0, long_clusters: 0.0, specials: 0.25 };
+        let thresholds = thresholds(&mix);
+        assert_eq!(thresholds, [(0.5, Class::Emoji), (0.75, Class::Special)]);
+        let mut rng = rand::thread_rng();
+        let picks: Vec<_> = (0..10_000).map(|_| Class::pick(&mut rng, &thresholds)).collect();
+        let share = |class| picks.iter().filter(|&&c| c == class).count() as f64 / picks.len() as f64;
+        assert!((0.47..0.53).contains(&share(Class::Emoji)));
+        assert!((0.22..0.28).contains(&share(Class::Special)));
+        assert!((0.22..0.28).contains(&share(Class::Ascii)));
+        assert_eq!(share(Class::Cjk), 0.0);
+    }
+
+    #[test]
+    fn long_clusters_are_long() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let mut field = Vec::new();
+            Class::LongCluster.push(&mut rng, &mut field);
+            let text = String::from_utf8(field).unwrap();
+            assert!(text.chars().count() >= 4, "{text}");
+        }
+    }
+
+    #[test]
+    fn fields_survive_csv_quoting() {
+        let mix = UnicodeMix { specials: 0.3, ..UnicodeMix::default() };
+        let specials = UnicodeMix { emoji: 0.0, cjk: 0.0, rtl: 0.0, combining: 0.0, zero_width: 0.0, long_clusters: 0.0, specials: 1.0 };
+        let generator = Generator::compile(&[
+            Column::new("a", ColumnKind::Unicode { min: 0, max: 40, mix }),
+            Column::new("b", ColumnKind::Unicode { min: 1, max: 1, mix: specials }),
+        ])
+        .unwrap();
+        // A header, so that a byte order mark starting the first field isn't taken for the file's.
+        let (mut row, mut text, mut rows) = (Row::new(), b"a,b\n".to_vec(), Vec::new());
+        for _ in 0..500 {
+            generator.fill_row(&mut rand::thread_rng(), &mut row);
+            text.extend_from_slice(&crate::sink::csv_record(&row));
+            text.push(b'\n');
+            rows.push(row.fields().map(|f| String::from_utf8(f.to_vec()).unwrap()).collect::<Vec<_>>());
+        }
+        let read: Vec<Vec<String>> = csv::Reader::from_reader(&text[..])
+            .records()
+            .map(|r| r.unwrap().iter().map(str::to_string).collect())
+            .collect();
+        assert_eq!(read, rows);
+    }
+}
@@ -0,0 +1,449 @@
+//! The `validate` subcommand: reads a run's CSV back and checks it against
+//! what it was generated from, so the generator's guarantees are checked end
+//! to end. Every value must parse as its column's type and fall within its
+//! range or list of values, sequences must be unique, foreign keys must be
+//! keys of the table they reference, and tables with a row count must have
+//! it. Keys are kept as bitsets from their sequence's start, so checking a
+//! table takes a bit per row of memory.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::BoxError;
+use crate::cli::ValidateArgs;
+use crate::relational::{self, Config, Generated, KindConfig, SCD2_COLUMNS};
+use crate::schema::{self, Column, ColumnKind, DataType};
+
+/// Validates the files at `args.path`, returning an error if any problem is
+/// found.
+pub fn run(args: &ValidateArgs) -> Result<(), BoxError> {
+    let mut report = Report { max: args.max_problems, problems: 0 };
+    let rows = match &args.config {
+        Some(path) => check_config(args, &Config::load(path)?, &mut report)?,
+        None => {
+            let columns = schema::builtin(&args.schema, args.locale.clone()).expect("--schema is checked when parsed");
+            let files = if args.path.is_dir() {
+                csv_files(&args.path, |name| !name.contains(".manifest.csv"))?
+            } else {
+                vec![args.path.clone()]
+            };
+            let table = check_table(&files, &columns, &[], false, &mut report)?;
+            if let Some(rows) = args.rows
+                && table.rows != rows
+            {
+                report.problem(args.path.display(), format!("{} rows, not {rows}", table.rows));
+            }
+            println!("{}: {} rows in {} files", args.schema, table.rows, files.len());
+            table.rows
+        }
+    };
+    match report.problems {
+        0 => {
+            println!("No problems found in {rows} rows");
+            Ok(())
+        }
+        1 => Err("found 1 problem".into()),
+        problems => Err(format!("found {problems} problems").into()),
+    }
+}
+
+/// Checks each table of `config`, parents first, returning the rows checked.
+fn check_config(args: &ValidateArgs, config: &Config, report: &mut Report) -> Result<u64, BoxError> {
+    let mut generated: HashMap<String, Generated> = HashMap::new();
+    let mut keys: HashMap<String, Keys> = HashMap::new();
+    let mut total = 0;
+    for table in config.generation_order()? {
+        let files = csv_files(&args.path, |name| is_table_file(name, &table.name))?;
+        if files.is_empty() {
+            return Err(format!("no files for table {} in {}", table.name, args.path.display()).into());
+        }
+        let columns = relational::written_columns(table, &generated, &args.locale)?;
+        // Config columns line up with the first of the columns written.
+        let targets: Vec<Option<Target>> = table
+            .columns
+            .iter()
+            .map(|c| match &c.kind {
+                KindConfig::ParentKey => table.parent.as_deref().map(|parent| (parent, 1.0)),
+                KindConfig::Reference { table, matching, .. } => Some((table.as_str(), *matching)),
+                _ => None,
+            })
+            .map(|target| target.map(|(name, matching)| Target { table: name, keys: &keys[name], matching }))
+            .collect();
+        let checked = check_table(&files, &columns, &targets, table.scd2.is_some(), report)?;
+        let rows = if table.scd2.is_some() { checked.current } else { checked.rows };
+        if let Some(expected) = table.rows
+            && rows != expected
+        {
+            report.problem(&table.name, format!("{rows} rows, not {expected}"));
+        }
+        match table.scd2 {
+            Some(_) => println!("{}: {} versions of {rows} rows in {} files", table.name, checked.rows, files.len()),
+            None => println!("{}: {rows} rows in {} files", table.name, files.len()),
+        }
+        total += checked.rows;
+        generated.insert(table.name.clone(), Generated { key: table.key(), rows });
+        if let Some(table_keys) = checked.keys {
+            keys.insert(table.name.clone(), table_keys);
+        }
+    }
+    Ok(total)
+}
+
+/// Whether a file named `name` holds `table`: `table.csv`, or a shard of it
+/// with a number after a `-`, `_` or `.`, maybe gzipped.
+fn is_table_file(name: &str, table: &str) -> bool {
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    let Some(rest) = name.strip_prefix(table).and_then(|rest| rest.strip_suffix(".csv")) else { return false };
+    let shard = rest.strip_prefix(['-', '_', '.']).unwrap_or(rest);
+    rest.is_empty() || (shard.len() < rest.len() && !shard.is_empty() && shard.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// The CSV files in `dir` whose names `wanted` takes, in order of name.
+pub fn csv_files(dir: &Path, wanted: impl Fn(&str) -> bool) -> Result<Vec<PathBuf>, BoxError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| format!("reading {}: {e}", dir.display()))? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        if (name.ends_with(".csv") || name.ends_with(".csv.gz")) && wanted(name) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Reads the CSV file at `path`, gunzipping it if its name ends in `.gz`.
+/// Records may have any number of fields.
+pub fn csv_reader(path: &Path) -> Result<csv::Reader<Box<dyn Read>>, BoxError> {
+    let file = File::open(path).map_err(|e| format!("reading {}: {e}", path.display()))?;
+    let input: Box<dyn Read> = if path.extension().is_some_and(|e| e == "gz") {
+        Box::new(MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    Ok(csv::ReaderBuilder::new().flexible(true).from_reader(input))
+}
+
+/// Counts problems, printing the first `max` of them.
+pub struct Report {
+    pub max: u64,
+    pub problems: u64,
+}
+
+impl Report {
+    pub fn problem(&mut self, at: impl Display, problem: impl Display) {
+        self.problems += 1;
+        if self.problems <= self.max {
+            println!("{at}: {problem}");
+        } else if self.problems == self.max + 1 {
+            println!("... counting the rest of the problems without printing them");
+        }
+    }
+}
+
+/// The most keys past its start a sequence is checked for, taking a GB.
+const MAX_KEYS: u64 = 1 << 33;
+
+/// The keys of a table, as a bitset from the start of its sequence.
+struct Keys {
+    start: i64,
+    bits: Vec<u64>,
+}
+
+impl Keys {
+    /// Adds `key`, which is at least the start, returning whether it's new.
+    fn insert(&mut self, key: i64) -> bool {
+        let at = key.abs_diff(self.start) as usize;
+        if at / 64 >= self.bits.len() {
+            self.bits.resize(at / 64 + 1, 0);
+        }
+        let new = self.bits[at / 64] & 1 << (at % 64) == 0;
+        self.bits[at / 64] |= 1 << (at % 64);
+        new
+    }
+
+    fn contains(&self, key: i64) -> bool {
+        let Some(at) = key.checked_sub(self.start).and_then(|at| usize::try_from(at).ok()) else { return false };
+        self.bits.get(at / 64).is_some_and(|bits| bits & 1 << (at % 64) != 0)
+    }
+}
+
+/// The table a column's values are keys of.
+struct Target<'a> {
+    table: &'a str,
+    keys: &'a Keys,
+    /// The fraction of rows with a key the table has; the others have keys
+    /// it doesn't.
+    matching: f64,
+}
+
+/// What one column's values are checked for.
+struct ColumnCheck<'a> {
+    name: &'a str,
+    data_type: DataType,
+    /// The values' inclusive range, as integers, days, seconds or unscaled
+    /// decimals.
+    range: Option<(i64, i64)>,
+    /// The only values it can have, given a list of them.
+    values: Option<HashSet<Vec<u8>>>,
+    /// A sequence's keys so far.
+    keys: Option<Keys>,
+    /// Whether the sequence's keys are repeated, by the versions of an
+    /// `scd2` table.
+    repeated: bool,
+    target: Option<&'a Target<'a>>,
+    /// The values that are keys of the target.
+    matched: u64,
+}
+
+impl<'a> ColumnCheck<'a> {
+    fn new(column: &'a Column, target: Option<&'a Target<'a>>, history: bool) -> Self {
+        let range = match column.kind {
+            // The history's dates come from the table's settings instead.
+            _ if history && SCD2_COLUMNS.contains(&column.name.as_str()) => None,
+            ColumnKind::IntRange { min, max }
+            | ColumnKind::DateRange { min, max }
+            | ColumnKind::TimestampRange { min, max }
+            | ColumnKind::Decimal { min, max, .. } => Some((min, max)),
+            ColumnKind::Sequence { start } => Some((start, i64::MAX)),
+            _ => None,
+        };
+        let values = match &column.kind {
+            ColumnKind::Choice(values) | ColumnKind::Cycle(values) => {
+                Some(values.iter().map(|v| v.as_bytes().to_vec()).collect())
+            }
+            ColumnKind::IntChoice(values) => Some(values.iter().map(|v| v.to_string().into_bytes()).collect()),
+            _ if history && column.name == SCD2_COLUMNS[2] => Some([b"true".to_vec(), b"false".to_vec()].into()),
+            _ => None,
+        };
+        let keys = match column.kind {
+            ColumnKind::Sequence { start } => Some(Keys { start, bits: Vec::new() }),
+            _ => None,
+        };
+        ColumnCheck {
+            name: &column.name,
+            data_type: column.kind.data_type(),
+            range,
+            values,
+            keys,
+            repeated: history,
+            target,
+            matched: 0,
+        }
+    }
+
+    /// Checks one value, describing what's wrong with it if anything is.
+    fn check(&mut self, value: &[u8]) -> Result<(), String> {
+        let Ok(text) = std::str::from_utf8(value) else { return Err("isn't valid UTF-8".to_string()) };
+        if let Some(values) = &self.values
+            && !values.contains(value)
+        {
+            return Err(format!("{text:?} isn't one of its values"));
+        }
+        let number = match self.data_type {
+            DataType::String => return Ok(()),
+            DataType::Integer => text.parse::<i64>().ok(),
+            DataType::Decimal { scale } => parse_decimal(text, scale),
+            DataType::Date => schema::parse_date(text).filter(|_| text.len() == 10),
+            DataType::Timestamp => schema::parse_timestamp(text).filter(|_| text.len() == 19),
+        };
+        let Some(number) = number else {
+            let expected = match self.data_type {
+                DataType::Integer => "an integer".to_string(),
+                DataType::Decimal { scale } => format!("a number with {scale} decimal places"),
+                DataType::Date => "a YYYY-MM-DD date".to_string(),
+                _ => "a YYYY-MM-DD HH:MM:SS time".to_string(),
+            };
+            return Err(format!("{text:?} isn't {expected}"));
+        };
+        if let Some((min, max)) = self.range
+            && !(min..=max).contains(&number)
+        {
+            return Err(format!("{text} is out of its range"));
+        }
+        if self.keys.as_ref().is_some_and(|keys| number.abs_diff(keys.start) >= MAX_KEYS) {
+            return Err(format!("{text} is too far past the start of the sequence to check"));
+        }
+        if let Some(keys) = &mut self.keys
+            && !keys.insert(number)
+            && !self.repeated
+        {
+            return Err(format!("{text} is a key more than one row has"));
+        }
+        if let Some(target) = self.target {
+            if target.keys.contains(number) {
+                self.matched += 1;
+            } else if target.matching == 1.0 {
+                return Err(format!("{text} isn't a key of {}", target.table));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `text` as a decimal's unscaled value, if it has `scale` decimal places.
+pub fn parse_decimal(text: &str, scale: u8) -> Option<i64> {
+    let (negative, unsigned) = text.strip_prefix('-').map_or((false, text), |rest| (true, rest));
+    let (whole, fraction) = match unsigned.split_once('.') {
+        Some(_) if scale == 0 => return None,
+        Some((whole, fraction)) => (whole, fraction),
+        None => (unsigned, ""),
+    };
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !digits(whole) || fraction.len() != scale as usize || (scale > 0 && !digits(fraction)) {
+        return None;
+    }
+    let unscaled: i64 = format!("{whole}{fraction}").parse().ok()?;
+    Some(if negative { -unscaled } else { unscaled })
+}
+
+/// What's known of a table once it's checked.
+struct Checked {
+    rows: u64,
+    /// The rows whose `is_current` is true, for an `scd2` table.
+    current: u64,
+    /// The keys of its first sequence, which other tables reference.
+    keys: Option<Keys>,
+}
+
+/// Checks the rows of `files` against `columns`, the first of which may
+/// have targets their values are keys of.
+fn check_table(
+    files: &[PathBuf],
+    columns: &[Column],
+    targets: &[Option<Target>],
+    history: bool,
+    report: &mut Report,
+) -> Result<Checked, BoxError> {
+    let mut checks: Vec<ColumnCheck> =
+        columns.iter().enumerate().map(|(i, c)| ColumnCheck::new(c, targets.get(i).and_then(Option::as_ref), history)).collect();
+    let current = columns.iter().position(|c| history && c.name == SCD2_COLUMNS[2]);
+    let (mut rows, mut current_rows) = (0, 0);
+    for path in files {
+        let name = path.display();
+        let mut reader = csv_reader(path)?;
+        let header = reader.byte_headers().map_err(|e| format!("{name}: {e}"))?;
+        if !header.iter().eq(columns.iter().map(|c| c.name.as_bytes())) {
+            let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+            report.problem(format!("{name}:1"), format!("the header isn't {}", names.join(",")));
+            continue;
+        }
+        let mut record = csv::ByteRecord::new();
+        while reader.read_byte_record(&mut record).map_err(|e| format!("{name}: {e}"))? {
+            rows += 1;
+            let line = record.position().map_or(0, |p| p.line());
+            if record.len() != columns.len() {
+                report.problem(format!("{name}:{line}"), format!("{} fields, not {}", record.len(), columns.len()));
+                continue;
+            }
+            for (check, value) in checks.iter_mut().zip(&record) {
+                if let Err(problem) = check.check(value) {
+                    report.problem(format!("{name}:{line}"), format!("{}: {problem}", check.name));
+                }
+            }
+            if current.is_some_and(|i| &record[i] == b"true") {
+                current_rows += 1;
+            }
+        }
+    }
+    for check in &checks {
+        if let Some(target) = check.target
+            && target.matching < 1.0
+        {
+            let expected = schema::matching_rows(rows, target.matching);
+            if check.matched != expected {
+                let problem = format!("{} rows reference a row of {}, not {expected}", check.matched, target.table);
+                report.problem(check.name, problem);
+            }
+        }
+    }
+    let keys = checks.into_iter().find_map(|c| c.keys);
+    Ok(Checked { rows, current: current_rows, keys })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use clap::Parser;
+
+    fn validate(args: &[&str]) -> Result<(), BoxError> {
+        let Some(crate::cli::Command::Validate(args)) = Args::parse_from(args).command else { unreachable!() };
+        run(&args)
+    }
+
+    #[test]
+    fn generated_tables_are_valid() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_validate_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("tables.toml");
+        fs::write(
+            &config,
+            r#"
+            [[table]]
+            name = "customers"
+            rows = 300
+            column = [
+                { name = "id", kind = "sequence" },
+                { name = "name", kind = "full_name" },
+                { name = "balance", kind = "decimal", min = -100.0, max = 100.0, scale = 2 },
+            ]
+
+            [[table]]
+            name = "orders"
+            parent = "customers"
+            fan_out = { distribution = "poisson", mean = 3.0 }
+            column = [
+                { name = "id", kind = "sequence", start = 1000 },
+                { name = "customer_id", kind = "parent_key" },
+                { name = "referrer_id", kind = "reference", table = "customers", matching = 0.5 },
+                { name = "placed", kind = "date", min = "2024-01-01", max = "2024-12-31" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let output = dir.join("{table}-{n}.csv");
+        let args = Args::parse_from(["gen", "--config", config.to_str().unwrap(), "--output", output.to_str().unwrap(), "--shards", "3"]);
+        relational::generate(&args, &Config::load(&config).unwrap()).unwrap();
+        let (dir_arg, config_arg) = (dir.to_str().unwrap(), config.to_str().unwrap());
+        validate(&["gen", "validate", dir_arg, "--config", config_arg]).unwrap();
+
+        // A missing customer leaves orders pointing at nothing, and 300 rows short by one.
+        let first = dir.join("customers-0.csv");
+        let text = fs::read_to_string(&first).unwrap();
+        let (header, rest) = text.split_once('\n').unwrap();
+        fs::write(&first, format!("{header}\n{}", rest.split_once('\n').unwrap().1)).unwrap();
+        let error = validate(&["gen", "validate", dir_arg, "--config", config_arg, "--max-problems", "0"]).unwrap_err();
+        assert!(error.to_string().starts_with("found "), "{error}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn values_are_checked_against_their_columns() {
+        let dir = std::env::temp_dir().join(format!("csv_data_generator_validate_users_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("users.csv");
+        let columns = schema::builtin("users", crate::locale::Locale::En).unwrap();
+        let ColumnKind::Choice(names) = &columns[1].kind else { panic!("users have first names") };
+        let name = &names[0];
+        fs::write(&path, format!("id,name,age\na,{name},30\nb,{name},thirty\nc,Nobody,30\nd,{name},99\ne,{name}\n")).unwrap();
+        let mut report = Report { max: 0, problems: 0 };
+        let checked = check_table(std::slice::from_ref(&path), &columns, &[], false, &mut report).unwrap();
+        assert_eq!((checked.rows, report.problems), (5, 4));
+        fs::write(&path, format!("id,name,age\na,{name},30\n")).unwrap();
+        assert!(validate(&["gen", "validate", path.to_str().unwrap(), "--rows", "1"]).is_ok());
+        assert!(validate(&["gen", "validate", path.to_str().unwrap(), "--rows", "2"]).is_err());
+
+        assert_eq!(parse_decimal("-0.05", 2), Some(-5));
+        assert_eq!(parse_decimal("12", 0), Some(12));
+        assert_eq!(parse_decimal("12.5", 2), None);
+        assert!(is_table_file("orders-12.csv.gz", "orders") && is_table_file("orders.csv", "orders"));
+        assert!(!is_table_file("orders_items.csv", "orders") && !is_table_file("orders-.csv", "orders"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
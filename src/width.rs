@@ -0,0 +1,122 @@
+//! `--width`: values padded or cut to a byte width per column, so that rows
+//! of fixed-width columns all have one exact length, for precisely sized
+//! files and block-aligned records.
+//!
+//! Numbers are padded with zeros after their sign, so they still parse as
+//! the same number, and everything else with spaces after it. A value is
+//! cut at a character boundary, and padded with spaces if that leaves it
+//! short.
+
+use serde::Serialize;
+
+use crate::BoxError;
+use crate::generator::Row;
+use crate::schema::{Column, DataType};
+use crate::sink::Sink;
+
+/// The bytes a column's values take: at least `min` and at most `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Width {
+    pub min: usize,
+    pub max: usize,
+}
+
+/// The column that `*` names in `--width`: every column not named itself.
+pub const EVERY_COLUMN: &str = "*";
+
+/// `field` in `width`, appended to `out`, for a column of `data_type`.
+fn fit(field: &[u8], width: Width, data_type: DataType, out: &mut Vec<u8>) {
+    if field.len() > width.max {
+        // Back up over UTF-8 continuation bytes to the start of a character.
+        let mut end = width.max;
+        while end > 0 && field[end] & 0xc0 == 0x80 {
+            end -= 1;
+        }
+        out.extend_from_slice(&field[..end]);
+        out.resize(out.len() + width.min.saturating_sub(end), b' ');
+        return;
+    }
+    let padding = width.min.saturating_sub(field.len());
+    match data_type {
+        DataType::Integer | DataType::Decimal { .. } if padding > 0 && !field.is_empty() => {
+            let sign = usize::from(field[0] == b'-');
+            out.extend_from_slice(&field[..sign]);
+            out.resize(out.len() + padding, b'0');
+            out.extend_from_slice(&field[sign..]);
+        }
+        _ => {
+            out.extend_from_slice(field);
+            out.resize(out.len() + padding, b' ');
+        }
+    }
+}
+
+/// Fits the fields of some columns of the rows passed on to their widths.
+pub struct WidthSink {
+    inner: Box<dyn Sink>,
+    /// Of each column, its width, if it has one, and type.
+    widths: Vec<(Option<Width>, DataType)>,
+    row: Row,
+}
+
+impl WidthSink {
+    /// Wraps `inner`, unless none of `widths` names one of `columns`. Tables
+    /// without a column of a given name are left alone, as with `--mismatch`.
+    pub fn wrap(inner: Box<dyn Sink>, columns: &[Column], widths: &[(String, Width)]) -> Box<dyn Sink> {
+        let width = |name: &str| widths.iter().find(|(column, _)| column == name).map(|&(_, width)| width);
+        let every = width(EVERY_COLUMN);
+        let widths: Vec<_> = columns.iter().map(|c| (width(&c.name).or(every), c.kind.data_type())).collect();
+        if widths.iter().all(|(width, _)| width.is_none()) {
+            return inner;
+        }
+        Box::new(WidthSink { inner, widths, row: Row::new() })
+    }
+}
+
+impl Sink for WidthSink {
+    fn write_row(&mut self, row: &Row) -> Result<(), BoxError> {
+        self.row.clear();
+        for (field, &(width, data_type)) in row.fields().zip(&self.widths) {
+            match width {
+                Some(width) => self.row.push_with(|out| fit(field, width, data_type, out)),
+                None => self.row.push(field),
+            }
+        }
+        self.inner.write_row(&self.row)
+    }
+
+    fn flush(&mut self) -> Result<(), BoxError> {
+        self.inner.flush()
+    }
+
+    fn bytes_written(&self) -> u64 {
+        self.inner.bytes_written()
+    }
+
+    fn finish(self: Box<Self>) -> Result<(), BoxError> {
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_padded_or_cut_to_their_width() {
+        let fitted = |field: &str, min, max, data_type| {
+            let mut out = Vec::new();
+            fit(field.as_bytes(), Width { min, max }, data_type, &mut out);
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(fitted("abc", 5, 5, DataType::String), "abc  ");
+        assert_eq!(fitted("abcdef", 4, 4, DataType::String), "abcd");
+        assert_eq!(fitted("42", 5, 5, DataType::Integer), "00042");
+        assert_eq!(fitted("-1.50", 7, 7, DataType::Decimal { scale: 2 }), "-001.50");
+        assert_eq!(fitted("abc", 0, 8, DataType::String), "abc");
+        assert_eq!(fitted("abcdef", 2, 4, DataType::String), "abcd");
+        // `é` is two bytes, so cutting it in half cuts it out instead.
+        assert_eq!(fitted("aé", 2, 2, DataType::String), "a ");
+        assert_eq!(fitted("", 3, 3, DataType::Integer), "   ");
+    }
+}